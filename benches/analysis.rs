@@ -0,0 +1,120 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Analysis throughput benchmarks
+//!
+//! Run with `cargo bench --bench analysis` (add `--features simd` to
+//! compare the vectorized histogram).
+
+use std::sync::Arc;
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::{Rng, SeedableRng};
+
+use glowbarn::analysis::{
+    histogram, histogram_scalar, local_outlier_factors, AnalysisConfig, AnalysisEngine, EntropyAnalyzer, ENTROPY_BINS,
+};
+use glowbarn::core::EventBus;
+use glowbarn::sensors::{SensorReading, SensorType};
+use glowbarn::Config;
+
+/// One 1024-sample window from each of 14 sensors
+fn simultaneous_windows() -> Vec<SensorReading> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(14);
+    (0..14)
+        .map(|i| {
+            let data = (0..1024)
+                .map(|t| (t as f64 * 0.01 * (i + 1) as f64).sin() + rng.gen_range(-0.5..0.5))
+                .collect();
+            SensorReading::new(&format!("sensor-{}", i), SensorType::EMFProbe, data)
+        })
+        .collect()
+}
+
+fn batch_analysis(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let engine = runtime
+        .block_on(AnalysisEngine::new(Arc::new(Config::default()), Arc::new(EventBus::new(64))))
+        .unwrap();
+    let readings = simultaneous_windows();
+
+    let mut group = c.benchmark_group("14 sensors x 1024 samples");
+    group.sample_size(10);
+    group.bench_function("sequential", |b| {
+        b.iter(|| readings.iter().filter_map(|r| engine.analyze_reading(r)).count())
+    });
+    group.bench_function("parallel", |b| b.iter(|| engine.analyze_batch(&readings).len()));
+    group.finish();
+}
+
+/// Per-call cost with a warm plan cache vs planning every call
+fn fft_plan_reuse(c: &mut Criterion) {
+    let window = &simultaneous_windows()[0].data;
+    let cached = EntropyAnalyzer::new(AnalysisConfig::default());
+
+    let mut group = c.benchmark_group("spectral_entropy 1024");
+    group.bench_function("cached plan", |b| b.iter(|| cached.spectral_entropy(window)));
+    group.bench_function("fresh plan", |b| {
+        b.iter(|| EntropyAnalyzer::new(AnalysisConfig::default()).spectral_entropy(window))
+    });
+    group.finish();
+}
+
+/// Entropy binning of one 4096-sample window; the two only differ when
+/// built with `--features simd`
+fn entropy_histogram(c: &mut Criterion) {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(4096);
+    let window: Vec<f64> = (0..4096).map(|_| rng.gen_range(-1.0..1.0)).collect();
+
+    let mut group = c.benchmark_group("histogram 4096");
+    group.bench_function("scalar", |b| b.iter(|| histogram_scalar(&window, ENTROPY_BINS)));
+    group.bench_function("dispatch", |b| b.iter(|| histogram(&window, ENTROPY_BINS)));
+    group.finish();
+}
+
+/// All-pairs LOF as `detect_lof` used to compute it: every point's and
+/// every neighbour's distances are recomputed and sorted
+fn lof_pairwise(data: &[f64], k: usize) -> Vec<Option<f64>> {
+    let k_distance = |i: usize| {
+        let mut distances: Vec<f64> = data.iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .map(|(_, &y)| (data[i] - y).abs())
+            .collect();
+        distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        distances
+    };
+    (0..data.len())
+        .map(|i| {
+            let mut near: Vec<(usize, f64)> = data.iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(j, &y)| (j, (data[i] - y).abs()))
+                .collect();
+            near.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            let own = near[k - 1].1;
+            let densities: Vec<f64> = near[..k].iter()
+                .map(|&(j, _)| k_distance(j)[k - 1])
+                .filter(|&d| d > 1e-10)
+                .map(|d| 1.0 / d)
+                .collect();
+            (own > 1e-10 && !densities.is_empty())
+                .then(|| densities.iter().sum::<f64>() / densities.len() as f64 * own)
+        })
+        .collect()
+}
+
+/// Local outlier factor of a 500-point window, k = 5
+fn lof_window(c: &mut Criterion) {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(500);
+    let window: Vec<f64> = (0..500).map(|_| rng.gen_range(-1.0..1.0)).collect();
+
+    let mut group = c.benchmark_group("lof 500");
+    group.sample_size(10);
+    group.bench_function("pairwise", |b| b.iter(|| lof_pairwise(&window, 5)));
+    group.bench_function("sorted neighbours", |b| b.iter(|| local_outlier_factors(&window, 5)));
+    group.finish();
+}
+
+criterion_group!(benches, batch_analysis, fft_plan_reuse, entropy_histogram, lof_window);
+criterion_main!(benches);
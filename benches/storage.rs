@@ -0,0 +1,66 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Reading insert throughput benchmarks
+//!
+//! Run with `cargo bench --bench storage`.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rand::{Rng, SeedableRng};
+
+use glowbarn::config::DatabaseConfig;
+use glowbarn::db::Database;
+use glowbarn::sensors::{SensorReading, SensorType};
+
+/// One second of 14 sensors at 100 Hz, 64 samples per reading
+fn one_second() -> Vec<SensorReading> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1400);
+    (0..1400)
+        .map(|i| {
+            let data = (0..64).map(|_| rng.gen_range(-1.0..1.0)).collect();
+            SensorReading::new(&format!("sensor-{}", i % 14), SensorType::EMFProbe, data)
+        })
+        .collect()
+}
+
+/// Payload encoding alone: bincode into a fresh Vec vs raw f64s into a reused one
+fn payload_encoding(c: &mut Criterion) {
+    let readings = one_second();
+
+    let mut group = c.benchmark_group("encode 1400 payloads");
+    group.bench_function("bincode", |b| {
+        b.iter(|| readings.iter().map(|r| bincode::serialize(&r.data).unwrap().len()).sum::<usize>())
+    });
+    group.bench_function("raw scratch", |b| {
+        let mut scratch = Vec::new();
+        b.iter(|| {
+            readings.iter().map(|r| {
+                scratch.clear();
+                for value in &r.data {
+                    scratch.extend_from_slice(&value.to_le_bytes());
+                }
+                scratch.len()
+            }).sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+fn batch_insert(c: &mut Criterion) {
+    let readings = one_second();
+    let path = std::env::temp_dir().join(format!("glowbarn-bench-{}.db", uuid::Uuid::new_v4()));
+    let db = Database::open(&DatabaseConfig { path: path.clone(), ..Default::default() }, None).unwrap();
+
+    let mut group = c.benchmark_group("store_readings_batch");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(readings.len() as u64));
+    group.bench_function("1400 readings", |b| b.iter(|| db.store_readings_batch(&readings).unwrap()));
+    group.finish();
+
+    drop(db);
+    let _ = std::fs::remove_file(&path);
+}
+
+criterion_group!(benches, payload_encoding, batch_insert);
+criterion_main!(benches);
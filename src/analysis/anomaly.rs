@@ -4,11 +4,12 @@
 
 //! Anomaly detection - statistical, ML, and ensemble methods
 
-use std::collections::VecDeque;
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
 use nalgebra::{DMatrix, DVector};
 use serde::{Deserialize, Serialize};
+use statrs::function::gamma::ln_gamma;
 
-use super::AnalysisConfig;
+use super::{erf, AnalysisConfig, RunningStats};
 
 /// Detected anomaly
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +19,16 @@ pub struct Anomaly {
     pub score: f64,
     pub anomaly_type: AnomalyType,
     pub confidence: f64,
+    /// Methods that flagged this index
+    #[serde(default)]
+    pub methods: Vec<AnomalyMethod>,
+}
+
+impl Anomaly {
+    /// Number of methods that agree this index is anomalous
+    pub fn votes(&self) -> usize {
+        self.methods.len()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -32,6 +43,110 @@ pub enum AnomalyType {
     Oscillation,        // Abnormal oscillation
 }
 
+/// Detection method that flagged an anomaly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AnomalyMethod {
+    ZScore,
+    Mad,
+    IsolationForest,
+    Cusum,
+    Lof,
+    /// Outside a percentile band estimated over the window
+    Percentile,
+    /// Bayesian online change-point detection
+    Bocpd,
+}
+
+/// Methods `AnomalyDetector` runs unless configured otherwise
+pub const DEFAULT_ANOMALY_METHODS: [AnomalyMethod; 5] = [
+    AnomalyMethod::ZScore,
+    AnomalyMethod::Mad,
+    AnomalyMethod::IsolationForest,
+    AnomalyMethod::Cusum,
+    AnomalyMethod::Lof,
+];
+
+/// Percentile band settings
+///
+/// Unlike the Z-score and MAD thresholds the band makes no assumption of
+/// symmetry, so it suits skewed sensors such as Geiger counters whose long
+/// tail is legitimate.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PercentileBandConfig {
+    /// Percentile (0-100) below which a point is flagged
+    pub lower: f64,
+    /// Percentile (0-100) above which a point is flagged
+    pub upper: f64,
+    /// Fewest points a window needs before its band is estimated
+    pub min_samples: usize,
+}
+
+impl Default for PercentileBandConfig {
+    fn default() -> Self {
+        Self {
+            lower: 1.0,
+            upper: 99.0,
+            min_samples: 100,
+        }
+    }
+}
+
+/// Bayesian online change-point detection settings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BocpdConfig {
+    /// Expected number of points between changes
+    pub hazard_lambda: f64,
+    /// Probability (0-1) of a recent change above which a change point is flagged
+    pub threshold: f64,
+    /// Run lengths shorter than this count as a recent change
+    pub recent: usize,
+    /// Longest run length tracked; longer runs are folded into it
+    pub max_run_length: usize,
+    /// Points scanned before changes are flagged, and the fewest a
+    /// sensor's first window needs to be standardized by
+    pub min_samples: usize,
+}
+
+impl Default for BocpdConfig {
+    fn default() -> Self {
+        Self {
+            hazard_lambda: 250.0,
+            threshold: 0.5,
+            recent: 10,
+            max_run_length: 500,
+            min_samples: 30,
+        }
+    }
+}
+
+/// Isolation forest settings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IsolationForestConfig {
+    pub n_trees: usize,
+    /// Points each tree is grown from, capped at the number fitted to
+    pub sample_size: usize,
+    /// Anomaly score (0-1) above which a point is flagged; a typical point
+    /// scores about 0.5
+    pub threshold: f64,
+    /// Fewest points `AnomalyDetector::detect` fits a forest to when none
+    /// has been fitted in advance
+    pub min_samples: usize,
+}
+
+impl Default for IsolationForestConfig {
+    fn default() -> Self {
+        Self {
+            n_trees: 100,
+            sample_size: 256,
+            threshold: 0.6,
+            min_samples: 100,
+        }
+    }
+}
+
 /// Anomaly detector with multiple methods
 pub struct AnomalyDetector {
     config: AnalysisConfig,
@@ -42,12 +157,35 @@ pub struct AnomalyDetector {
     running_mean: f64,
     running_var: f64,
     
-    // Isolation Forest state
-    isolation_trees: Vec<IsolationTree>,
+    // Forest fitted by `fit_isolation_forest`, reused by every `detect`
+    isolation_forest: Option<IsolationForest>,
     
     // CUSUM parameters
     cusum_pos: f64,
     cusum_neg: f64,
+    
+    // Percentile band of each sensor, learned across its windows
+    percentile_bands: parking_lot::Mutex<HashMap<String, PercentileBand>>,
+    
+    // Noise σ of each characterized sensor; a window's spread is never
+    // taken as smaller than it
+    noise_floors: parking_lot::Mutex<HashMap<String, f64>>,
+    
+    // Change-point detector of each sensor, run across its windows
+    change_tracks: parking_lot::Mutex<HashMap<String, ChangeTrack>>,
+}
+
+/// BOCPD state of one sensor, carried from window to window
+#[derive(Debug, Clone)]
+struct ChangeTrack {
+    bocpd: Bocpd,
+    // Median and MAD scale of the first window; later points are
+    // standardized by them too, so a shift stays a shift
+    median: f64,
+    scale: f64,
+    // The change probability ended the last window above the threshold
+    // and that change was reported
+    reported: bool,
 }
 
 impl AnomalyDetector {
@@ -58,56 +196,123 @@ impl AnomalyDetector {
             history_size: 10000,
             running_mean: 0.0,
             running_var: 1.0,
-            isolation_trees: Vec::new(),
+            isolation_forest: None,
             cusum_pos: 0.0,
             cusum_neg: 0.0,
+            percentile_bands: parking_lot::Mutex::new(HashMap::new()),
+            noise_floors: parking_lot::Mutex::new(HashMap::new()),
+            change_tracks: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Whether `method` is one of the configured `anomaly_methods`
+    pub fn is_enabled(&self, method: AnomalyMethod) -> bool {
+        self.config.anomaly_methods.contains(&method)
+    }
+    
+    /// Take over the per-sensor state `previous` learned, e.g. when the
+    /// detector is rebuilt for a reloaded config. Bands estimated for other
+    /// percentiles and change-point runs tracked with other BOCPD settings
+    /// are dropped; noise floors always carry over.
+    pub fn inherit_bands(&self, previous: &AnomalyDetector) {
+        if self.config.percentile_band == previous.config.percentile_band {
+            *self.percentile_bands.lock() = std::mem::take(&mut *previous.percentile_bands.lock());
+        }
+        if self.config.bocpd == previous.config.bocpd {
+            *self.change_tracks.lock() = std::mem::take(&mut *previous.change_tracks.lock());
+        }
+        *self.noise_floors.lock() = std::mem::take(&mut *previous.noise_floors.lock());
+    }
+    
+    /// Use `sigma`, the noise of `sensor_id` measured while it saw nothing
+    /// (see `NoiseProfile`), as the least spread its windows are scored
+    /// with. A quiet window then can't turn the sensor's own noise into
+    /// Z-score or MAD outliers.
+    pub fn set_noise_floor(&self, sensor_id: &str, sigma: f64) {
+        if sigma.is_finite() && sigma > 0.0 {
+            self.noise_floors.lock().insert(sensor_id.to_string(), sigma);
         }
     }
     
+    /// Detect anomalies in one window on its own
     pub fn detect(&self, data: &[f64]) -> Vec<Anomaly> {
+        self.detect_window(None, data)
+    }
+    
+    /// Detect anomalies in the next window from `sensor_id`
+    ///
+    /// Stateful methods carry over from the sensor's earlier windows, so
+    /// e.g. a window that is shifted as a whole is still scored against the
+    /// percentile band learned before it.
+    pub fn detect_sensor(&self, sensor_id: &str, data: &[f64]) -> Vec<Anomaly> {
+        self.detect_window(Some(sensor_id), data)
+    }
+    
+    fn detect_window(&self, sensor_id: Option<&str>, data: &[f64]) -> Vec<Anomaly> {
         let mut anomalies = Vec::new();
         
         // Statistical detection
-        anomalies.extend(self.detect_statistical(data));
+        anomalies.extend(self.detect_statistical(sensor_id, data));
         
         // Isolation Forest
-        anomalies.extend(self.detect_isolation_forest(data));
+        if self.is_enabled(AnomalyMethod::IsolationForest) {
+            anomalies.extend(self.detect_isolation_forest(data));
+        }
         
         // CUSUM for change detection
-        anomalies.extend(self.detect_cusum(data));
+        if self.is_enabled(AnomalyMethod::Cusum) {
+            anomalies.extend(self.detect_cusum(data));
+        }
         
         // Local Outlier Factor
-        anomalies.extend(self.detect_lof(data));
+        if self.is_enabled(AnomalyMethod::Lof) {
+            anomalies.extend(self.detect_lof(data));
+        }
+        
+        // Percentile band, robust to skewed distributions
+        if self.is_enabled(AnomalyMethod::Percentile) {
+            anomalies.extend(self.detect_percentile(sensor_id, data));
+        }
+        
+        // BOCPD for shifts in mean or variance
+        if self.is_enabled(AnomalyMethod::Bocpd) {
+            anomalies.extend(self.detect_bocpd(sensor_id, data));
+        }
         
-        // Remove duplicates and sort by score
-        self.deduplicate_anomalies(&mut anomalies);
-        anomalies.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        // One anomaly per index, voted on by the methods that flagged it
+        let mut anomalies = self.combine_votes(anomalies);
+        anomalies.retain(|a| a.confidence >= self.config.anomaly_probability_threshold);
+        anomalies.sort_by(|a, b| b.score.total_cmp(&a.score));
         
         anomalies
     }
     
     /// Statistical anomaly detection (Z-score, MAD, Grubbs)
-    fn detect_statistical(&self, data: &[f64]) -> Vec<Anomaly> {
+    fn detect_statistical(&self, sensor_id: Option<&str>, data: &[f64]) -> Vec<Anomaly> {
         let mut anomalies = Vec::new();
         
         if data.len() < 10 {
             return anomalies;
         }
+        let noise_floor = sensor_id
+            .and_then(|id| self.noise_floors.lock().get(id).copied())
+            .unwrap_or(0.0);
         
         // Z-score detection
-        let mean = data.iter().sum::<f64>() / data.len() as f64;
-        let std = self.std_dev(data);
+        let stats = RunningStats::from_slice(data);
+        let (mean, std) = (stats.mean(), stats.std_dev().max(noise_floor));
         
-        if std > 1e-10 {
+        if std > 1e-10 && self.is_enabled(AnomalyMethod::ZScore) {
             for (i, &x) in data.iter().enumerate() {
                 let z_score = (x - mean).abs() / std;
-                if z_score > self.config.anomaly_threshold {
+                if z_score > self.config.zscore_sigma_threshold {
                     anomalies.push(Anomaly {
                         index: i,
                         value: x,
                         score: z_score,
                         anomaly_type: if x > mean { AnomalyType::Spike } else { AnomalyType::Drop },
                         confidence: self.z_score_to_confidence(z_score),
+                        methods: vec![AnomalyMethod::ZScore],
                     });
                 }
             }
@@ -115,23 +320,22 @@ impl AnomalyDetector {
         
         // MAD (Median Absolute Deviation) detection - more robust
         let median = self.median(data);
-        let mad = self.mad(data, median);
+        // The MAD of normal noise is 0.6745σ
+        let mad = self.mad(data, median).max(0.6745 * noise_floor);
         
-        if mad > 1e-10 {
+        if mad > 1e-10 && self.is_enabled(AnomalyMethod::Mad) {
             let threshold = 3.5;  // Modified Z-score threshold
             for (i, &x) in data.iter().enumerate() {
                 let modified_z = 0.6745 * (x - median) / mad;
                 if modified_z.abs() > threshold {
-                    // Only add if not already detected
-                    if !anomalies.iter().any(|a| a.index == i) {
-                        anomalies.push(Anomaly {
-                            index: i,
-                            value: x,
-                            score: modified_z.abs(),
-                            anomaly_type: AnomalyType::PointAnomaly,
-                            confidence: self.z_score_to_confidence(modified_z.abs()),
-                        });
-                    }
+                    anomalies.push(Anomaly {
+                        index: i,
+                        value: x,
+                        score: modified_z.abs(),
+                        anomaly_type: AnomalyType::PointAnomaly,
+                        confidence: self.z_score_to_confidence(modified_z.abs()),
+                        methods: vec![AnomalyMethod::Mad],
+                    });
                 }
             }
         }
@@ -139,45 +343,31 @@ impl AnomalyDetector {
         anomalies
     }
     
-    /// Isolation Forest anomaly detection
+    /// Fit an isolation forest to reference data and use it to score every
+    /// later window, so scores are comparable from one window to the next
+    pub fn fit_isolation_forest(&mut self, data: &[f64]) {
+        self.isolation_forest = Some(IsolationForest::fit(data, &self.config.isolation_forest));
+    }
+    
+    /// Forget the fitted forest; `detect` goes back to fitting each window
+    pub fn clear_isolation_forest(&mut self) {
+        self.isolation_forest = None;
+    }
+    
+    pub fn isolation_forest(&self) -> Option<&IsolationForest> {
+        self.isolation_forest.as_ref()
+    }
+    
+    /// Isolation Forest anomaly detection, with the fitted forest or else one
+    /// fitted to `data` itself
     fn detect_isolation_forest(&self, data: &[f64]) -> Vec<Anomaly> {
-        let mut anomalies = Vec::new();
-        
-        if data.len() < 100 {
-            return anomalies;
-        }
-        
-        let n_trees = 100;
-        let sample_size = (data.len() / 4).min(256);
-        
-        // Build forest
-        let trees: Vec<_> = (0..n_trees)
-            .map(|_| IsolationTree::build(data, sample_size))
-            .collect();
-        
-        // Score each point
-        let avg_path_length = self.expected_path_length(sample_size);
-        
-        for (i, &x) in data.iter().enumerate() {
-            let avg_depth: f64 = trees.iter()
-                .map(|tree| tree.path_length(x) as f64)
-                .sum::<f64>() / n_trees as f64;
-            
-            // Anomaly score: 2^(-avg_depth / avg_path_length)
-            let score = 2.0_f64.powf(-avg_depth / avg_path_length);
-            
-            if score > 0.6 {  // Threshold for anomaly
-                anomalies.push(Anomaly {
-                    index: i,
-                    value: x,
-                    score: score * 10.0,  // Scale to be comparable
-                    anomaly_type: AnomalyType::PointAnomaly,
-                    confidence: score,
-                });
+        match self.isolation_forest {
+            Some(ref forest) => forest.detect(data),
+            None if data.len() >= self.config.isolation_forest.min_samples => {
+                IsolationForest::fit(data, &self.config.isolation_forest).detect(data)
             }
+            None => Vec::new(),
         }
-        
-        anomalies
     }
     
     /// CUSUM (Cumulative Sum) change point detection
@@ -188,8 +378,8 @@ impl AnomalyDetector {
             return anomalies;
         }
         
-        let mean = data.iter().sum::<f64>() / data.len() as f64;
-        let std = self.std_dev(data);
+        let stats = RunningStats::from_slice(data);
+        let (mean, std) = (stats.mean(), stats.std_dev());
         
         if std < 1e-10 {
             return anomalies;
@@ -212,6 +402,7 @@ impl AnomalyDetector {
                     score: cusum_pos / h,
                     anomaly_type: AnomalyType::ChangePoint,
                     confidence: (cusum_pos / h).min(1.0),
+                    methods: vec![AnomalyMethod::Cusum],
                 });
                 cusum_pos = 0.0;
             }
@@ -223,6 +414,7 @@ impl AnomalyDetector {
                     score: cusum_neg / h,
                     anomaly_type: AnomalyType::ChangePoint,
                     confidence: (cusum_neg / h).min(1.0),
+                    methods: vec![AnomalyMethod::Cusum],
                 });
                 cusum_neg = 0.0;
             }
@@ -233,120 +425,166 @@ impl AnomalyDetector {
     
     /// Local Outlier Factor (simplified 1D version)
     fn detect_lof(&self, data: &[f64]) -> Vec<Anomaly> {
-        let mut anomalies = Vec::new();
-        
         if data.len() < 20 {
-            return anomalies;
+            return Vec::new();
         }
         
-        let k = 5;  // Number of neighbors
-        
-        // For each point, calculate LOF
-        for i in 0..data.len() {
-            let x = data[i];
-            
-            // Find k nearest neighbors
-            let mut distances: Vec<(usize, f64)> = data.iter()
-                .enumerate()
-                .filter(|&(j, _)| i != j)
-                .map(|(j, &y)| (j, (x - y).abs()))
-                .collect();
-            distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-            
-            let k_neighbors: Vec<_> = distances.iter().take(k).collect();
-            
-            if k_neighbors.is_empty() {
-                continue;
-            }
-            
-            // k-distance (distance to k-th neighbor)
-            let k_dist = k_neighbors.last().map(|(_, d)| *d).unwrap_or(0.0);
-            
-            // Local reachability density
-            let lrd = if k_dist > 1e-10 {
-                k as f64 / k_neighbors.iter().map(|(_, d)| d.max(k_dist)).sum::<f64>()
-            } else {
-                f64::MAX
-            };
-            
-            // Calculate LOF (simplified)
-            let mut neighbor_lrds = Vec::new();
-            for &(j, _) in &k_neighbors {
-                let neighbor_x = data[*j];
-                let mut n_dists: Vec<f64> = data.iter()
-                    .enumerate()
-                    .filter(|&(idx, _)| idx != *j)
-                    .map(|(_, &y)| (neighbor_x - y).abs())
-                    .collect();
-                n_dists.sort_by(|a, b| a.partial_cmp(b).unwrap());
-                
-                let n_k_dist = n_dists.get(k-1).copied().unwrap_or(0.0);
-                if n_k_dist > 1e-10 {
-                    let n_lrd = k as f64 / n_dists.iter().take(k).map(|d| d.max(n_k_dist)).sum::<f64>();
-                    neighbor_lrds.push(n_lrd);
-                }
-            }
-            
-            if !neighbor_lrds.is_empty() && lrd < f64::MAX {
-                let avg_neighbor_lrd = neighbor_lrds.iter().sum::<f64>() / neighbor_lrds.len() as f64;
-                let lof = avg_neighbor_lrd / lrd;
-                
-                if lof > 1.5 {  // LOF threshold
-                    anomalies.push(Anomaly {
-                        index: i,
-                        value: x,
-                        score: lof,
-                        anomaly_type: AnomalyType::ContextualAnomaly,
-                        confidence: ((lof - 1.0) / 2.0).min(1.0),
-                    });
-                }
+        local_outlier_factors(data, self.config.lof_neighbors)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, lof)| {
+                let lof = lof?;
+                (lof > 1.5).then(|| Anomaly {  // LOF threshold
+                    index: i,
+                    value: data[i],
+                    score: lof,
+                    anomaly_type: AnomalyType::ContextualAnomaly,
+                    confidence: ((lof - 1.0) / 2.0).min(1.0),
+                    methods: vec![AnomalyMethod::Lof],
+                })
+            })
+            .collect()
+    }
+    
+    /// Flag points outside the `[lower, upper]` percentile band, using the
+    /// sensor's band from earlier windows when `sensor_id` is given and a
+    /// fresh one otherwise
+    fn detect_percentile(&self, sensor_id: Option<&str>, data: &[f64]) -> Vec<Anomaly> {
+        let config = &self.config.percentile_band;
+        match sensor_id {
+            Some(id) => self.percentile_bands.lock()
+                .entry(id.to_string())
+                .or_insert_with(|| PercentileBand::new(config))
+                .score(data, config),
+            None => PercentileBand::new(config).score(data, config),
+        }
+    }
+    
+    /// Flag changes in the mean or variance of `data` with BOCPD.
+    ///
+    /// Points are standardized by the median and MAD of the sensor's first
+    /// window so the model's unit prior fits any sensor. Each excursion of
+    /// the recent-change probability above the threshold yields one change
+    /// point, at the start of the most likely run when the probability
+    /// peaked. A sensor's run-length distribution carries over from one of
+    /// its windows to the next, so a change that straddles two windows is
+    /// reported once, in the window it started in.
+    fn detect_bocpd(&self, sensor_id: Option<&str>, data: &[f64]) -> Vec<Anomaly> {
+        match sensor_id {
+            Some(id) => {
+                let mut tracks = self.change_tracks.lock();
+                let track = match tracks.entry(id.to_string()) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => match self.start_change_track(data) {
+                        Some(track) => entry.insert(track),
+                        None => return Vec::new(),
+                    },
+                };
+                self.scan_changes(track, data)
             }
+            None => match self.start_change_track(data) {
+                Some(mut track) => self.scan_changes(&mut track, data),
+                None => Vec::new(),
+            },
         }
-        
-        anomalies
     }
     
-    fn deduplicate_anomalies(&self, anomalies: &mut Vec<Anomaly>) {
-        // Keep highest scoring anomaly for each index
-        anomalies.sort_by_key(|a| a.index);
-        let mut seen = std::collections::HashSet::new();
-        anomalies.retain(|a| seen.insert(a.index));
+    /// Fresh change-point state standardized by `data`, if it is long and
+    /// spread out enough to estimate a scale from
+    fn start_change_track(&self, data: &[f64]) -> Option<ChangeTrack> {
+        if data.len() < self.config.bocpd.min_samples.max(2) {
+            return None;
+        }
+        
+        let median = self.median(data);
+        let mut scale = 1.4826 * self.mad(data, median);
+        if scale < 1e-10 {
+            scale = RunningStats::from_slice(data).std_dev();
+        }
+        if scale < 1e-10 {
+            return None;
+        }
+        
+        Some(ChangeTrack {
+            bocpd: Bocpd::new(self.config.bocpd),
+            median,
+            scale,
+            reported: false,
+        })
     }
     
-    fn expected_path_length(&self, n: usize) -> f64 {
-        if n <= 1 {
-            return 0.0;
+    fn scan_changes(&self, track: &mut ChangeTrack, data: &[f64]) -> Vec<Anomaly> {
+        let config = self.config.bocpd;
+        let mut anomalies = Vec::new();
+        let mut peak: Option<Anomaly> = None;
+        for (t, &x) in data.iter().enumerate() {
+            let probability = track.bocpd.update((x - track.median) / track.scale);
+            if track.bocpd.count() <= config.min_samples || probability <= config.threshold {
+                anomalies.extend(peak.take());
+                track.reported = false;
+                continue;
+            }
+            if track.reported || matches!(&peak, Some(a) if a.confidence >= probability) {
+                continue;
+            }
+            let index = (t + 1).saturating_sub(track.bocpd.most_likely_run_length()).min(t);
+            peak = Some(Anomaly {
+                index,
+                value: data[index],
+                score: probability / config.threshold,
+                anomaly_type: AnomalyType::ChangePoint,
+                confidence: probability,
+                methods: vec![AnomalyMethod::Bocpd],
+            });
+        }
+        // A change still in progress is reported now and not again
+        if peak.is_some() {
+            track.reported = true;
         }
-        let n = n as f64;
-        2.0 * (n.ln() + 0.5772156649) - 2.0 * (n - 1.0) / n
+        anomalies.extend(peak);
+        anomalies
     }
     
-    fn z_score_to_confidence(&self, z: f64) -> f64 {
-        // Approximate conversion using error function
-        let x = z / 2.0_f64.sqrt();
-        let t = 1.0 / (1.0 + 0.3275911 * x.abs());
-        let a1 = 0.254829592;
-        let a2 = -0.284496736;
-        let a3 = 1.421413741;
-        let a4 = -1.453152027;
-        let a5 = 1.061405429;
-        let erf = 1.0 - (((((a5 * t + a4) * t) + a3) * t + a2) * t + a1) * t * (-x * x).exp();
-        let erf = if x >= 0.0 { erf } else { -erf };
+    /// Merge the flags raised for each index into one anomaly.
+    ///
+    /// The highest-scoring flag supplies the value, score and type. Each
+    /// method's confidence is treated as independent evidence, so the
+    /// combined confidence is the chance that not all of them are wrong:
+    /// `1 - prod(1 - c)`. A point several methods agree on therefore ends
+    /// up more confident than any single method's flag.
+    fn combine_votes(&self, mut anomalies: Vec<Anomaly>) -> Vec<Anomaly> {
+        anomalies.sort_by(|a, b| a.index.cmp(&b.index).then(b.score.total_cmp(&a.score)));
         
-        ((1.0 + erf) / 2.0 - 0.5).abs() * 2.0  // Two-tailed
+        let mut combined: Vec<Anomaly> = Vec::new();
+        let mut doubt = 1.0;
+        for anomaly in anomalies {
+            match combined.last_mut() {
+                Some(last) if last.index == anomaly.index => {
+                    for method in anomaly.methods {
+                        if !last.methods.contains(&method) {
+                            last.methods.push(method);
+                            doubt *= 1.0 - anomaly.confidence.clamp(0.0, 1.0);
+                        }
+                    }
+                    last.confidence = 1.0 - doubt;
+                }
+                _ => {
+                    doubt = 1.0 - anomaly.confidence.clamp(0.0, 1.0);
+                    combined.push(anomaly);
+                }
+            }
+        }
+        combined
     }
     
-    fn std_dev(&self, data: &[f64]) -> f64 {
-        if data.len() < 2 { return 0.0; }
-        let n = data.len() as f64;
-        let mean = data.iter().sum::<f64>() / n;
-        let variance = data.iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
-        variance.sqrt()
+    fn z_score_to_confidence(&self, z: f64) -> f64 {
+        // Two-tailed probability mass within |z|
+        erf(z.abs() / std::f64::consts::SQRT_2)
     }
     
     fn median(&self, data: &[f64]) -> f64 {
         let mut sorted = data.to_vec();
-        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        sorted.sort_by(|a, b| a.total_cmp(b));
         let mid = sorted.len() / 2;
         if sorted.len() % 2 == 0 {
             (sorted[mid - 1] + sorted[mid]) / 2.0
@@ -361,6 +599,420 @@ impl AnomalyDetector {
     }
 }
 
+/// Percentile band learned online from every sample seen so far
+#[derive(Debug, Clone)]
+struct PercentileBand {
+    lower: P2Quantile,
+    upper: P2Quantile,
+}
+
+impl PercentileBand {
+    fn new(config: &PercentileBandConfig) -> Self {
+        Self {
+            lower: P2Quantile::new(config.lower / 100.0),
+            upper: P2Quantile::new(config.upper / 100.0),
+        }
+    }
+    
+    /// Score each point against the band estimated from the points before
+    /// it, then learn from it.
+    ///
+    /// The band is estimated with P² estimators rather than by sorting, and
+    /// nothing is flagged until `min_samples` points have been seen. A
+    /// flagged point's confidence starts at the band's coverage and
+    /// approaches 1 the further it lies outside, measured in band widths.
+    fn score(&mut self, data: &[f64], config: &PercentileBandConfig) -> Vec<Anomaly> {
+        let coverage = ((config.upper - config.lower) / 100.0).clamp(0.0, 1.0);
+        let mut anomalies = Vec::new();
+        
+        for (i, &x) in data.iter().enumerate() {
+            let band = match (self.lower.value(), self.upper.value()) {
+                (Some(lo), Some(hi)) if self.lower.count() >= config.min_samples.max(5) && hi - lo > 1e-10 => Some((lo, hi)),
+                _ => None,
+            };
+            if let Some((lo, hi)) = band.filter(|&(lo, hi)| x < lo || x > hi) {
+                let excess = if x > hi { (x - hi) / (hi - lo) } else { (lo - x) / (hi - lo) };
+                anomalies.push(Anomaly {
+                    index: i,
+                    value: x,
+                    score: 1.0 + excess,
+                    anomaly_type: if x > hi { AnomalyType::Spike } else { AnomalyType::Drop },
+                    confidence: 1.0 - (1.0 - coverage) * (-excess).exp(),
+                    methods: vec![AnomalyMethod::Percentile],
+                });
+            }
+            self.lower.push(x);
+            self.upper.push(x);
+        }
+        anomalies
+    }
+}
+
+/// Online estimate of one quantile with the P² algorithm (Jain & Chlamtac,
+/// 1985), which tracks five markers instead of storing the observations
+#[derive(Debug, Clone)]
+pub struct P2Quantile {
+    p: f64,
+    count: usize,
+    /// Marker heights; the middle one estimates the quantile
+    heights: [f64; 5],
+    /// Actual marker positions, 0-based
+    positions: [f64; 5],
+    /// Desired marker positions
+    desired: [f64; 5],
+    /// Increment of each desired position per observation
+    increments: [f64; 5],
+}
+
+impl P2Quantile {
+    /// Estimator of quantile `p` (0-1)
+    pub fn new(p: f64) -> Self {
+        let p = p.clamp(0.0, 1.0);
+        Self {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [0.0, 1.0, 2.0, 3.0, 4.0],
+            desired: [0.0, 2.0 * p, 4.0 * p, 2.0 + 2.0 * p, 4.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+    
+    pub fn count(&self) -> usize {
+        self.count
+    }
+    
+    pub fn push(&mut self, x: f64) {
+        if self.count < 5 {
+            self.heights[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(|a, b| a.total_cmp(b));
+            }
+            return;
+        }
+        self.count += 1;
+        
+        // Cell the observation falls in, widening the extremes if needed
+        let q = &mut self.heights;
+        let k = if x < q[0] {
+            q[0] = x;
+            0
+        } else if x >= q[4] {
+            q[4] = x;
+            3
+        } else {
+            (0..4).find(|&i| x < q[i + 1]).unwrap_or(3)
+        };
+        
+        for position in &mut self.positions[k + 1..] {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+        
+        // Move the middle markers towards their desired positions
+        for i in 1..4 {
+            let n = &self.positions;
+            let d = self.desired[i] - n[i];
+            if (d >= 1.0 && n[i + 1] - n[i] > 1.0) || (d <= -1.0 && n[i - 1] - n[i] < -1.0) {
+                let d = d.signum();
+                let q = &self.heights;
+                let parabolic = q[i]
+                    + d / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]));
+                let height = if q[i - 1] < parabolic && parabolic < q[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0.0 { i + 1 } else { i - 1 };
+                    q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+                };
+                self.heights[i] = height;
+                self.positions[i] += d;
+            }
+        }
+    }
+    
+    /// Current estimate, exact until five values have been seen
+    pub fn value(&self) -> Option<f64> {
+        match self.count {
+            0 => None,
+            n if n < 5 => {
+                let mut seen = self.heights[..n].to_vec();
+                seen.sort_by(|a, b| a.total_cmp(b));
+                Some(seen[(self.p * (n - 1) as f64).round() as usize])
+            }
+            _ => Some(self.heights[2]),
+        }
+    }
+}
+
+/// Bayesian online change-point detection (Adams & MacKay, 2007)
+///
+/// Keeps a distribution over the run length, the number of points since
+/// the last change. Every run models its points as normal with unknown mean
+/// and variance under a Normal-Gamma prior, so a shift in either moves the
+/// mass onto short runs. The prior expects data of about unit scale, such
+/// as Z-scores.
+#[derive(Debug, Clone)]
+pub struct Bocpd {
+    config: BocpdConfig,
+    count: usize,
+    /// Probability of each run length, indexed by run length
+    run_lengths: Vec<f64>,
+    /// Posterior of each run length's model
+    posteriors: Vec<NormalGamma>,
+}
+
+impl Bocpd {
+    pub fn new(config: BocpdConfig) -> Self {
+        Self {
+            config,
+            count: 0,
+            run_lengths: vec![1.0],
+            posteriors: vec![NormalGamma::PRIOR],
+        }
+    }
+    
+    /// Points seen so far
+    pub fn count(&self) -> usize {
+        self.count
+    }
+    
+    /// Add `x` to the run-length distribution and return the probability
+    /// that a change happened within the last `recent` points
+    pub fn update(&mut self, x: f64) -> f64 {
+        let hazard = 1.0 / self.config.hazard_lambda.max(1.0);
+        
+        let mut run_lengths = Vec::with_capacity(self.run_lengths.len() + 1);
+        run_lengths.push(0.0);
+        let mut change = 0.0;
+        for (p, posterior) in self.run_lengths.iter().zip(&self.posteriors) {
+            let joint = p * posterior.predictive(x);
+            change += joint * hazard;
+            run_lengths.push(joint * (1.0 - hazard));
+        }
+        run_lengths[0] = change;
+        
+        let total: f64 = run_lengths.iter().sum();
+        if total > 0.0 && total.is_finite() {
+            run_lengths.iter_mut().for_each(|p| *p /= total);
+        } else {
+            // No run explains `x` at all, which can only mean a change
+            run_lengths.iter_mut().for_each(|p| *p = 0.0);
+            run_lengths[0] = 1.0;
+        }
+        
+        let mut posteriors = Vec::with_capacity(run_lengths.len());
+        posteriors.push(NormalGamma::PRIOR);
+        posteriors.extend(self.posteriors.iter().map(|posterior| posterior.update(x)));
+        
+        // Fold runs past the cap into the longest one tracked
+        let cap = self.config.max_run_length.max(2);
+        if run_lengths.len() > cap {
+            let tail: f64 = run_lengths.drain(cap..).sum();
+            run_lengths[cap - 1] += tail;
+            posteriors.truncate(cap);
+        }
+        
+        self.run_lengths = run_lengths;
+        self.posteriors = posteriors;
+        self.count += 1;
+        self.change_probability()
+    }
+    
+    /// Probability that a change happened within the last `recent` points
+    pub fn change_probability(&self) -> f64 {
+        self.run_lengths.iter().take(self.config.recent).sum()
+    }
+    
+    /// Run length with the highest probability
+    pub fn most_likely_run_length(&self) -> usize {
+        self.run_lengths
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(r, _)| r)
+            .unwrap_or(0)
+    }
+    
+    /// Probability of each run length, indexed by run length
+    pub fn run_length_distribution(&self) -> &[f64] {
+        &self.run_lengths
+    }
+}
+
+/// Normal-Gamma posterior over the mean and precision of one run
+#[derive(Debug, Clone, Copy)]
+struct NormalGamma {
+    mean: f64,
+    kappa: f64,
+    alpha: f64,
+    beta: f64,
+}
+
+impl NormalGamma {
+    const PRIOR: Self = Self {
+        mean: 0.0,
+        kappa: 1.0,
+        alpha: 1.0,
+        beta: 1.0,
+    };
+    
+    /// Posterior predictive density of `x`, a Student's t
+    fn predictive(&self, x: f64) -> f64 {
+        let nu = 2.0 * self.alpha;
+        let scale = (self.beta * (self.kappa + 1.0) / (self.alpha * self.kappa)).sqrt();
+        let t = (x - self.mean) / scale;
+        let ln_density = ln_gamma((nu + 1.0) / 2.0) - ln_gamma(nu / 2.0)
+            - 0.5 * (nu * std::f64::consts::PI).ln()
+            - scale.ln()
+            - (nu + 1.0) / 2.0 * (t * t / nu).ln_1p();
+        ln_density.exp()
+    }
+    
+    fn update(&self, x: f64) -> Self {
+        Self {
+            mean: (self.kappa * self.mean + x) / (self.kappa + 1.0),
+            kappa: self.kappa + 1.0,
+            alpha: self.alpha + 0.5,
+            beta: self.beta + self.kappa * (x - self.mean).powi(2) / (2.0 * (self.kappa + 1.0)),
+        }
+    }
+}
+
+/// Local outlier factor of each point of `data` against its `k` nearest
+/// neighbours, `None` where it's undefined (a point with `k` exact
+/// duplicates, or whose neighbours all have them).
+///
+/// A point's reachability density is `1 / k-distance`, and its LOF is the
+/// mean density of its neighbours over its own. Neighbours at equal
+/// distance are taken in index order.
+pub fn local_outlier_factors(data: &[f64], k: usize) -> Vec<Option<f64>> {
+    let n = data.len();
+    let k = k.min(n.saturating_sub(1));
+    if k == 0 {
+        return vec![None; n];
+    }
+    
+    // One sort serves every neighbour search: the k nearest neighbours of a
+    // point are a run of the sorted order around it
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| data[a].total_cmp(&data[b]));
+    
+    let mut neighbors = vec![Vec::new(); n];
+    let mut k_distance = vec![0.0; n];
+    let mut candidates = Vec::new();
+    for p in 0..n {
+        let (i, x) = (order[p], data[order[p]]);
+        let (mut lo, mut hi) = (p, p + 1);
+        let mut radius = 0.0;
+        for _ in 0..k {
+            let below = if lo > 0 { x - data[order[lo - 1]] } else { f64::INFINITY };
+            let above = if hi < n { data[order[hi]] - x } else { f64::INFINITY };
+            if below <= above {
+                lo -= 1;
+                radius = below;
+            } else {
+                hi += 1;
+                radius = above;
+            }
+        }
+        // Widen to every point tied with the k-th, then pick by index
+        while lo > 0 && x - data[order[lo - 1]] <= radius {
+            lo -= 1;
+        }
+        while hi < n && data[order[hi]] - x <= radius {
+            hi += 1;
+        }
+        
+        candidates.clear();
+        candidates.extend(order[lo..hi].iter().filter(|&&j| j != i).map(|&j| ((x - data[j]).abs(), j)));
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        neighbors[i] = candidates.iter().take(k).map(|&(_, j)| j).collect();
+        k_distance[i] = radius;
+    }
+    
+    let density = |i: usize| (k_distance[i] > 1e-10).then(|| 1.0 / k_distance[i]);
+    neighbors.iter()
+        .enumerate()
+        .map(|(i, near)| {
+            let own = density(i)?;
+            let densities: Vec<f64> = near.iter().filter_map(|&j| density(j)).collect();
+            (!densities.is_empty()).then(|| densities.iter().sum::<f64>() / densities.len() as f64 / own)
+        })
+        .collect()
+}
+
+/// Isolation forest fitted to reference data
+pub struct IsolationForest {
+    trees: Vec<IsolationTree>,
+    // Average path length of a tree grown from `sample_size` points
+    normalizer: f64,
+    threshold: f64,
+}
+
+impl IsolationForest {
+    pub fn fit(data: &[f64], config: &IsolationForestConfig) -> Self {
+        let sample_size = config.sample_size.min(data.len());
+        let trees = if data.is_empty() {
+            Vec::new()
+        } else {
+            (0..config.n_trees).map(|_| IsolationTree::build(data, sample_size)).collect()
+        };
+        
+        Self {
+            trees,
+            normalizer: average_path_length(sample_size),
+            threshold: config.threshold,
+        }
+    }
+    
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+    
+    /// Anomaly score `2^(-E[h(x)] / c(n))`: near 1 for points isolated
+    /// quickly, about 0.5 or less for typical ones
+    pub fn score(&self, x: f64) -> f64 {
+        if self.trees.is_empty() || self.normalizer <= 0.0 {
+            return 0.5;
+        }
+        let avg_depth = self.trees.iter().map(|tree| tree.path_length(x)).sum::<f64>() / self.trees.len() as f64;
+        2.0_f64.powf(-avg_depth / self.normalizer)
+    }
+    
+    /// Points of `data` scoring above the threshold
+    pub fn detect(&self, data: &[f64]) -> Vec<Anomaly> {
+        data.iter()
+            .enumerate()
+            .filter_map(|(i, &x)| {
+                let score = self.score(x);
+                (score > self.threshold).then(|| Anomaly {
+                    index: i,
+                    value: x,
+                    score: score * 10.0,  // Scale to be comparable
+                    anomaly_type: AnomalyType::PointAnomaly,
+                    confidence: score,
+                    methods: vec![AnomalyMethod::IsolationForest],
+                })
+            })
+            .collect()
+    }
+}
+
+/// Average path length of an unsuccessful search in a binary tree of `n`
+/// points, the depth a leaf of `n` points stands in for
+fn average_path_length(n: usize) -> f64 {
+    if n <= 1 {
+        return 0.0;
+    }
+    let n = n as f64;
+    2.0 * (n.ln() + 0.5772156649) - 2.0 * (n - 1.0) / n
+}
+
 /// Isolation Tree for Isolation Forest
 struct IsolationTree {
     root: Option<Box<IsolationNode>>,
@@ -391,16 +1043,17 @@ impl IsolationTree {
     }
     
     fn build_node(data: &[f64], depth: usize, max_depth: usize, rng: &mut ThreadRng) -> Option<Box<IsolationNode>> {
-        if data.is_empty() || depth >= max_depth {
+        if data.is_empty() {
             return None;
         }
         
-        if data.len() == 1 {
+        // Points left at the depth limit share a leaf, scored by their count
+        if data.len() == 1 || depth >= max_depth {
             return Some(Box::new(IsolationNode {
                 split_value: data[0],
                 left: None,
                 right: None,
-                size: 1,
+                size: data.len(),
             }));
         }
         
@@ -429,16 +1082,16 @@ impl IsolationTree {
         }))
     }
     
-    fn path_length(&self, value: f64) -> usize {
+    fn path_length(&self, value: f64) -> f64 {
         self.path_length_recursive(&self.root, value, 0)
     }
     
-    fn path_length_recursive(&self, node: &Option<Box<IsolationNode>>, value: f64, depth: usize) -> usize {
+    fn path_length_recursive(&self, node: &Option<Box<IsolationNode>>, value: f64, depth: usize) -> f64 {
         match node {
-            None => depth,
+            None => depth as f64,
             Some(n) => {
                 if n.left.is_none() && n.right.is_none() {
-                    return depth + self.c(n.size);
+                    return depth as f64 + average_path_length(n.size);
                 }
                 
                 if value < n.split_value {
@@ -449,12 +1102,279 @@ impl IsolationTree {
             }
         }
     }
-    
-    fn c(&self, n: usize) -> usize {
-        if n <= 1 { return 0; }
-        let n = n as f64;
-        (2.0 * (n.ln() + 0.5772156649) - 2.0 * (n - 1.0) / n) as usize
-    }
 }
 
 use rand::prelude::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_agreeing_methods_raise_confidence() {
+        // Deterministic noise in [0, 1) with one clear outlier
+        let mut data: Vec<f64> = (0..200).map(|i| ((i * 7919) % 100) as f64 / 100.0).collect();
+        data[150] = 10.0;
+        
+        let detector = AnomalyDetector::new(AnalysisConfig::default());
+        let anomalies = detector.detect(&data);
+        assert_eq!(anomalies.iter().filter(|a| a.index == 150).count(), 1);
+        
+        let outlier = anomalies.iter().find(|a| a.index == 150).unwrap();
+        assert!(outlier.votes() >= 3, "only {:?} flagged the outlier", outlier.methods);
+        
+        for marginal in anomalies.iter().filter(|a| a.votes() == 1) {
+            assert!(marginal.confidence <= outlier.confidence);
+        }
+        
+        // Three agreeing methods beat one equally confident method
+        let flag = |index, method, confidence| Anomaly {
+            index,
+            value: 0.0,
+            score: confidence,
+            anomaly_type: AnomalyType::PointAnomaly,
+            confidence,
+            methods: vec![method],
+        };
+        let combined = detector.combine_votes(vec![
+            flag(5, AnomalyMethod::ZScore, 0.7),
+            flag(9, AnomalyMethod::ZScore, 0.7),
+            flag(5, AnomalyMethod::Mad, 0.7),
+            flag(5, AnomalyMethod::Lof, 0.6),
+        ]);
+        assert_eq!(combined.len(), 2);
+        assert_eq!(combined[0].votes(), 3);
+        assert!((combined[0].confidence - (1.0 - 0.3 * 0.3 * 0.4)).abs() < 1e-12);
+        assert!(combined[0].confidence > combined[1].confidence);
+    }
+    
+    #[test]
+    fn test_probability_threshold_is_not_read_as_sigmas() {
+        let file_config = crate::config::AnalysisConfig {
+            anomaly_threshold: 0.7,
+            ..Default::default()
+        };
+        let config = AnalysisConfig::from(&file_config);
+        assert_eq!(config.anomaly_probability_threshold, 0.7);
+        assert_eq!(config.zscore_sigma_threshold, 3.0);
+        
+        // Uniform noise: read as 0.7 sigma, most of it would be flagged
+        let data: Vec<f64> = (0..200).map(|i| ((i * 7919) % 100) as f64 / 100.0).collect();
+        let anomalies = AnomalyDetector::new(config).detect(&data);
+        assert!(anomalies.len() < data.len() / 20, "{} of {} flagged", anomalies.len(), data.len());
+    }
+    
+    #[test]
+    fn test_fitted_forest_scores_later_windows() {
+        use rand::SeedableRng;
+        use rand_distr::{Distribution, Normal};
+        
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1624);
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let reference: Vec<f64> = (0..1000).map(|_| normal.sample(&mut rng)).collect();
+        
+        let mut detector = AnomalyDetector::new(AnalysisConfig::default());
+        detector.fit_isolation_forest(&reference);
+        let forest = detector.isolation_forest().unwrap();
+        
+        let window = [0.0, 0.3, -0.5, 1.0, 8.0];
+        for &x in &window[..4] {
+            assert!(forest.score(x) < forest.threshold(), "inlier {} scored {}", x, forest.score(x));
+        }
+        assert!(forest.score(8.0) > forest.threshold(), "outlier scored {}", forest.score(8.0));
+        // The same forest gives the same scores every time
+        assert_eq!(forest.score(1.0), forest.score(1.0));
+        
+        // Windows far too short to fit a forest of their own are still scored
+        let flagged = detector.detect_isolation_forest(&window);
+        assert_eq!(flagged.iter().map(|a| a.index).collect::<Vec<_>>(), vec![4]);
+        detector.clear_isolation_forest();
+        assert!(detector.detect_isolation_forest(&window).is_empty());
+    }
+    
+    #[test]
+    fn test_percentile_band_does_not_overflag_skewed_tail() {
+        use rand::SeedableRng;
+        use rand_distr::{Distribution, Exp};
+        
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1635);
+        let exp = Exp::new(1.0).unwrap();
+        let data: Vec<f64> = (0..20_000).map(|_| exp.sample(&mut rng)).collect();
+        
+        // P² tracks the exact quantiles of an exponential distribution
+        let mut p99 = P2Quantile::new(0.99);
+        data.iter().for_each(|&x| p99.push(x));
+        assert!((p99.value().unwrap() - 100f64.ln()).abs() < 0.25, "p99 estimated as {:?}", p99.value());
+        
+        let flagged = |methods: Vec<AnomalyMethod>, lower, upper| {
+            let config = AnalysisConfig {
+                anomaly_methods: methods,
+                percentile_band: PercentileBandConfig { lower, upper, min_samples: 100 },
+                ..AnalysisConfig::default()
+            };
+            AnomalyDetector::new(config).detect(&data).len()
+        };
+        
+        // Both at the nominal false-positive rate of 3 sigma, 0.27%
+        let zscore = flagged(vec![AnomalyMethod::ZScore], 0.135, 99.865);
+        let percentile = flagged(vec![AnomalyMethod::Percentile], 0.135, 99.865);
+        assert!(zscore > 250, "Z-score flagged {}", zscore);
+        assert!(percentile * 3 < zscore, "percentile flagged {} vs Z-score {}", percentile, zscore);
+    }
+    
+    #[test]
+    fn test_percentile_band_carries_across_windows() {
+        use rand::SeedableRng;
+        use rand_distr::{Distribution, Exp};
+        
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1635);
+        let exp = Exp::new(1.0).unwrap();
+        let mut window = |shift: f64| -> Vec<f64> { (0..200).map(|_| exp.sample(&mut rng) + shift).collect() };
+        
+        let config = AnalysisConfig {
+            anomaly_methods: vec![AnomalyMethod::Percentile],
+            percentile_band: PercentileBandConfig { lower: 0.135, upper: 99.865, min_samples: 100 },
+            ..AnalysisConfig::default()
+        };
+        let detector = AnomalyDetector::new(config);
+        
+        // Clean windows stay near the band's nominal 0.27%
+        let clean: usize = (0..50).map(|_| detector.detect_sensor("geiger-1", &window(0.0)).len()).sum();
+        assert!(clean < 60, "{} of 10000 clean points flagged", clean);
+        
+        // A window lifted as a whole is judged by the band learned before
+        // it, not by its own percentiles
+        let shifted = window(20.0);
+        let flagged = detector.detect_sensor("geiger-1", &shifted);
+        assert!((0..10).all(|i| flagged.iter().any(|a| a.index == i)), "{} flagged", flagged.len());
+        assert!(detector.detect(&shifted).len() < 5);
+        
+        // Other sensors start from scratch
+        assert!(detector.detect_sensor("geiger-2", &shifted).len() < 5);
+    }
+    
+    #[test]
+    fn test_noise_floor_keeps_quiet_windows_quiet() {
+        let config = AnalysisConfig {
+            anomaly_methods: vec![AnomalyMethod::ZScore, AnomalyMethod::Mad],
+            ..AnalysisConfig::default()
+        };
+        let detector = AnomalyDetector::new(config);
+        
+        // A near-flat window whose one wiggle is well inside the sensor's noise
+        let mut quiet: Vec<f64> = (0..100).map(|i| if i % 2 == 0 { 1e-3 } else { -1e-3 }).collect();
+        quiet[50] = 0.02;
+        assert!(!detector.detect_sensor("emf-1", &quiet).is_empty());
+        
+        detector.set_noise_floor("emf-1", 0.05);
+        assert!(detector.detect_sensor("emf-1", &quiet).is_empty());
+        
+        // Real excursions still stand out against the floor
+        quiet[50] = 1.0;
+        assert!(detector.detect_sensor("emf-1", &quiet).iter().any(|a| a.index == 50));
+        
+        // The floor survives a rebuilt detector
+        let rebuilt = AnomalyDetector::new(AnalysisConfig {
+            anomaly_methods: vec![AnomalyMethod::ZScore, AnomalyMethod::Mad],
+            ..AnalysisConfig::default()
+        });
+        rebuilt.inherit_bands(&detector);
+        quiet[50] = 0.02;
+        assert!(rebuilt.detect_sensor("emf-1", &quiet).is_empty());
+    }
+    
+    #[test]
+    fn test_bocpd_catches_variance_change_cusum_misses() {
+        // A 64-bit LCG through Box-Muller, so the series doesn't depend on
+        // the rand version
+        let mut state: u64 = 6;
+        let mut uniform = move || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let mut normal = move || {
+            let u1 = uniform().max(1e-300);
+            let u2 = uniform();
+            (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+        };
+        // Same mean throughout, standard deviation tripling at 400
+        let mut data: Vec<f64> = (0..400).map(|_| normal()).collect();
+        data.extend((0..400).map(|_| 3.0 * normal()));
+        
+        let config = AnalysisConfig {
+            anomaly_methods: vec![AnomalyMethod::Bocpd],
+            ..AnalysisConfig::default()
+        };
+        let detector = AnomalyDetector::new(config);
+        
+        let cusum = detector.detect_cusum(&data);
+        assert!(!cusum.iter().any(|a| (390..450).contains(&a.index)), "CUSUM flagged {:?}", cusum);
+        
+        let changes = detector.detect(&data);
+        assert_eq!(changes.len(), 1, "{:?}", changes);
+        assert_eq!(changes[0].anomaly_type, AnomalyType::ChangePoint);
+        assert!(changes[0].index.abs_diff(400) <= 5, "{:?}", changes[0]);
+        assert!(changes[0].confidence > 0.9, "{:?}", changes[0]);
+        
+        // Streaming the same points, the most likely run starts at the change
+        let median = detector.median(&data);
+        let scale = 1.4826 * detector.mad(&data, median);
+        let mut bocpd = Bocpd::new(BocpdConfig::default());
+        for &x in &data {
+            bocpd.update((x - median) / scale);
+        }
+        assert_eq!(bocpd.count(), 800);
+        assert!(bocpd.most_likely_run_length().abs_diff(400) <= 5, "run length {}", bocpd.most_likely_run_length());
+        assert!((bocpd.run_length_distribution().iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        
+        // Fed in windows, one sensor's change is found once, in the window
+        // it happened in
+        let changes: Vec<Vec<Anomaly>> = data.chunks(150).map(|w| detector.detect_sensor("emf-1", w)).collect();
+        assert_eq!(changes.iter().map(Vec::len).sum::<usize>(), 1, "{:?}", changes);
+        assert_eq!(changes[2].len(), 1, "{:?}", changes);
+        assert!(changes[2][0].index.abs_diff(100) <= 5, "{:?}", changes[2]);
+    }
+    
+    #[test]
+    fn test_lof_matches_pairwise_computation() {
+        // The original all-pairs LOF, recomputing distances for every neighbour
+        fn pairwise(data: &[f64], k: usize) -> Vec<Option<f64>> {
+            let k_nearest = |i: usize| {
+                let mut distances: Vec<(usize, f64)> = data.iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(j, &y)| (j, (data[i] - y).abs()))
+                    .collect();
+                distances.sort_by(|a, b| a.1.total_cmp(&b.1));
+                distances.truncate(k);
+                distances
+            };
+            let lrd = |near: &[(usize, f64)]| {
+                let k_dist = near.last().unwrap().1;
+                (k_dist > 1e-10).then(|| k as f64 / near.iter().map(|(_, d)| d.max(k_dist)).sum::<f64>())
+            };
+            (0..data.len())
+                .map(|i| {
+                    let near = k_nearest(i);
+                    let own = lrd(&near)?;
+                    let densities: Vec<f64> = near.iter().filter_map(|&(j, _)| lrd(&k_nearest(j))).collect();
+                    (!densities.is_empty()).then(|| densities.iter().sum::<f64>() / densities.len() as f64 / own)
+                })
+                .collect()
+        }
+        
+        // Coarse values, so there are plenty of ties and exact duplicates
+        let mut data: Vec<f64> = (0..120).map(|i| ((i * 7919) % 37) as f64 / 10.0).collect();
+        data.extend([9.0, 9.0, -4.0]);
+        for k in [1, 5, 12] {
+            let fast = local_outlier_factors(&data, k);
+            for (i, (a, b)) in fast.iter().zip(pairwise(&data, k)).enumerate() {
+                match (a, b) {
+                    (Some(a), Some(b)) => assert!((a - b).abs() < 1e-9, "k={} point {}: {} vs {}", k, i, a, b),
+                    (a, b) => assert_eq!(*a, b, "k={} point {}", k, i),
+                }
+            }
+        }
+        assert!(local_outlier_factors(&data, 5)[122].unwrap() > 1.5);
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Per-sensor rolling entropy baselines
+//!
+//! Sensors differ widely in how ordered their signals are, so a global
+//! entropy threshold is too tight for some and too loose for others. Each
+//! sensor instead keeps an exponentially weighted mean and variance of its
+//! spectral and Shannon entropy, and new readings are scored in standard
+//! deviations from that sensor's own recent history.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+use super::EntropyResult;
+
+/// Effective number of recent readings a baseline follows
+pub const BASELINE_WINDOW: usize = 200;
+
+/// Readings a sensor needs before its baseline is used for scoring
+pub const BASELINE_MIN_SAMPLES: u64 = 20;
+
+/// Smallest spread a baseline is divided by, so a perfectly steady sensor
+/// doesn't turn rounding noise into huge scores
+const MIN_SPREAD: f64 = 1e-3;
+
+/// Exponentially weighted mean and variance of one measure
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RollingBaseline {
+    pub count: u64,
+    pub mean: f64,
+    pub variance: f64,
+}
+
+impl RollingBaseline {
+    /// Fold in `x`. The first `BASELINE_WINDOW` values are weighted equally
+    /// so the baseline settles quickly, later ones exponentially.
+    pub fn push(&mut self, x: f64) {
+        if !x.is_finite() {
+            return;
+        }
+        self.count += 1;
+        let alpha = 1.0 / self.count.min(BASELINE_WINDOW as u64) as f64;
+        let delta = x - self.mean;
+        self.mean += alpha * delta;
+        self.variance = (1.0 - alpha) * (self.variance + alpha * delta * delta);
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    /// Standard deviations `x` lies from the mean
+    pub fn z_score(&self, x: f64) -> f64 {
+        (x - self.mean).abs() / self.std_dev().max(MIN_SPREAD)
+    }
+}
+
+/// Entropy baselines of one sensor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SensorBaseline {
+    pub spectral: RollingBaseline,
+    pub shannon: RollingBaseline,
+}
+
+/// How far a reading's entropy strayed from its sensor's baseline
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BaselineDeviation {
+    pub spectral_z: f64,
+    pub shannon_z: f64,
+    /// Larger of the two
+    pub score: f64,
+    pub is_anomalous: bool,
+}
+
+/// Rolling entropy baselines keyed by sensor id
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EntropyBaselines {
+    sensors: HashMap<String, SensorBaseline>,
+}
+
+impl EntropyBaselines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, sensor_id: &str) -> Option<&SensorBaseline> {
+        self.sensors.get(sensor_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sensors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sensors.is_empty()
+    }
+
+    /// Score `entropy` against `sensor_id`'s baseline, then fold it in.
+    /// `None` while the baseline is still settling.
+    pub fn observe(&mut self, sensor_id: &str, entropy: &EntropyResult, sigma_threshold: f64) -> Option<BaselineDeviation> {
+        let baseline = self.sensors.entry(sensor_id.to_string()).or_default();
+
+        let deviation = (baseline.spectral.count.min(baseline.shannon.count) >= BASELINE_MIN_SAMPLES).then(|| {
+            let spectral_z = baseline.spectral.z_score(entropy.spectral);
+            let shannon_z = baseline.shannon.z_score(entropy.shannon);
+            let score = spectral_z.max(shannon_z);
+            BaselineDeviation { spectral_z, shannon_z, score, is_anomalous: score > sigma_threshold }
+        });
+
+        baseline.spectral.push(entropy.spectral);
+        baseline.shannon.push(entropy.shannon);
+        deviation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_scores_scale_with_each_sensors_own_spread() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut baselines = EntropyBaselines::new();
+        let entropy = |spectral: f64, shannon: f64| EntropyResult { spectral, shannon, ..Default::default() };
+
+        // A tonal sensor near 0.2 spectral entropy and a noisy one near 0.9,
+        // with ten times its spread
+        for _ in 0..300 {
+            baselines.observe("tonal", &entropy(0.2 + rng.gen_range(-0.002..0.002), 3.0), 3.0);
+            baselines.observe("noisy", &entropy(0.9 + rng.gen_range(-0.02..0.02), 7.0), 3.0);
+        }
+        let tonal = baselines.get("tonal").unwrap().spectral;
+        let noisy = baselines.get("noisy").unwrap().spectral;
+        assert!((tonal.mean - 0.2).abs() < 0.001 && (noisy.mean - 0.9).abs() < 0.01);
+
+        // The same absolute shift is a large excursion for one, noise for the other
+        let shift = 0.02;
+        let tonal_dev = baselines.observe("tonal", &entropy(0.2 + shift, 3.0), 3.0).unwrap();
+        let noisy_dev = baselines.observe("noisy", &entropy(0.9 + shift, 7.0), 3.0).unwrap();
+        assert!(tonal_dev.is_anomalous && tonal_dev.spectral_z > 10.0, "{:?}", tonal_dev);
+        assert!(!noisy_dev.is_anomalous && noisy_dev.spectral_z < 3.0, "{:?}", noisy_dev);
+        // Shifts of equal size in each sensor's own spreads score alike
+        let ratio = tonal_dev.spectral_z / (noisy_dev.spectral_z * 10.0);
+        assert!((ratio - 1.0).abs() < 0.25, "ratio {}", ratio);
+
+        assert!(baselines.observe("new", &entropy(0.5, 5.0), 3.0).is_none());
+    }
+}
@@ -6,10 +6,16 @@
 
 use serde::{Deserialize, Serialize};
 
+/// Largest interval used for the Higuchi dimension in `analyze`
+const HIGUCHI_K_MAX: usize = 10;
+
 /// Complexity analysis results
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ComplexityResult {
     pub fractal_dimension: f64,
+    /// Higuchi fractal dimension, 1 for smooth curves up to 2 for noise
+    #[serde(default)]
+    pub higuchi_dimension: f64,
     pub correlation_dimension: f64,
     pub lyapunov_exponent: f64,
     pub recurrence_rate: f64,
@@ -39,6 +45,7 @@ impl ComplexityAnalyzer {
         
         ComplexityResult {
             fractal_dimension,
+            higuchi_dimension: self.higuchi_fractal_dimension(data, HIGUCHI_K_MAX),
             correlation_dimension,
             lyapunov_exponent,
             recurrence_rate: rqa.0,
@@ -106,6 +113,56 @@ impl ComplexityAnalyzer {
         (-slope).clamp(1.0, 2.0)
     }
     
+    /// Higuchi fractal dimension
+    ///
+    /// Measures the mean curve length `L(k)` of the series subsampled at
+    /// every k-th point for k = 1..=k_max; `L(k) ~ k^-D`, so D is the slope
+    /// of ln L(k) against ln(1/k). Works directly on the samples, so unlike
+    /// box counting it needs no normalization and holds up on short windows.
+    pub fn higuchi_fractal_dimension(&self, data: &[f64], k_max: usize) -> f64 {
+        let n = data.len();
+        if k_max < 2 || n < 2 * k_max {
+            return 1.0;
+        }
+        
+        let mut log_k = Vec::with_capacity(k_max);
+        let mut log_l = Vec::with_capacity(k_max);
+        
+        for k in 1..=k_max {
+            let mut total = 0.0;
+            let mut curves = 0;
+            
+            for m in 0..k {
+                let steps = (n - 1 - m) / k;
+                if steps == 0 {
+                    continue;
+                }
+                let length: f64 = (1..=steps)
+                    .map(|i| (data[m + i * k] - data[m + (i - 1) * k]).abs())
+                    .sum();
+                // Normalize for the number of steps actually taken
+                total += length * (n - 1) as f64 / (steps * k * k) as f64;
+                curves += 1;
+            }
+            
+            let mean_length = total / curves as f64;
+            if mean_length <= 0.0 {
+                // Constant series
+                return 1.0;
+            }
+            log_k.push((1.0 / k as f64).ln());
+            log_l.push(mean_length.ln());
+        }
+        
+        let n_points = log_k.len() as f64;
+        let sum_x: f64 = log_k.iter().sum();
+        let sum_y: f64 = log_l.iter().sum();
+        let sum_xy: f64 = log_k.iter().zip(log_l.iter()).map(|(x, y)| x * y).sum();
+        let sum_xx: f64 = log_k.iter().map(|x| x * x).sum();
+        
+        (n_points * sum_xy - sum_x * sum_y) / (n_points * sum_xx - sum_x * sum_x)
+    }
+    
     /// Correlation dimension (Grassberger-Procaccia algorithm)
     fn correlation_dimension(&self, data: &[f64]) -> f64 {
         let n = data.len();
@@ -145,7 +202,7 @@ impl ComplexityAnalyzer {
                 all_dists.push(dist);
             }
         }
-        all_dists.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        all_dists.sort_by(|a, b| a.total_cmp(b));
         
         let r_min = all_dists.get(all_dists.len() / 10).copied().unwrap_or(0.01);
         let r_max = all_dists.get(all_dists.len() * 9 / 10).copied().unwrap_or(1.0);
@@ -403,3 +460,29 @@ impl ComplexityAnalyzer {
         (n * sum_xy - sum_x * sum_y) / (n * sum_xx - sum_x * sum_x)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+    
+    #[test]
+    fn test_higuchi_dimension_of_known_curves() {
+        let analyzer = ComplexityAnalyzer::new();
+        let n = 2000;
+        
+        // Weierstrass function with a = 0.5, b = 4: D = 2 + ln(a) / ln(b) = 1.5
+        let weierstrass: Vec<f64> = (0..n)
+            .map(|i| (0..12).map(|k| 0.5f64.powi(k) * (4f64.powi(k) * PI * i as f64 / n as f64).cos()).sum())
+            .collect();
+        let d = analyzer.higuchi_fractal_dimension(&weierstrass, 10);
+        assert!((d - 1.5).abs() < 0.1, "Weierstrass dimension {}", d);
+        
+        // A smooth curve is one-dimensional
+        let sine: Vec<f64> = (0..n).map(|i| (2.0 * PI * 5.0 * i as f64 / n as f64).sin()).collect();
+        let d = analyzer.higuchi_fractal_dimension(&sine, 10);
+        assert!((d - 1.0).abs() < 0.05, "sine dimension {}", d);
+        
+        assert_eq!(analyzer.higuchi_fractal_dimension(&[3.0; 100], 10), 1.0);
+    }
+}
@@ -6,10 +6,11 @@
 
 use std::collections::HashMap;
 use std::f64::consts::{E, PI};
-use rustfft::{FftPlanner, num_complex::Complex};
+use rustfft::num_complex::Complex;
 use serde::{Deserialize, Serialize};
 
-use super::AnalysisConfig;
+use super::{histogram, AnalysisConfig, FftPlanCache, RunningStats, WindowFunction, ENTROPY_BINS};
+use crate::config::{MultiscaleMethod, SpectralEntropyMethod};
 
 /// Result of entropy analysis
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,10 +44,50 @@ pub struct EntropyResult {
     pub anomaly_score: f64,
 }
 
+/// Weights of the terms combined into `EntropyResult::anomaly_score`.
+///
+/// The entropy terms catch changes in how ordered a signal is, but an
+/// impulsive event (a static discharge, a burst of Geiger counts) barely
+/// moves entropy: a handful of huge samples in an otherwise normal window
+/// shows up as heavy tails instead. Excess kurtosis measures exactly that
+/// (0 for Gaussian noise, large when rare outliers dominate the fourth
+/// moment), and skewness flags impulses that are one-sided. Both only
+/// contribute above a threshold, on a log scale so a single enormous
+/// spike can't swamp every other term.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AnomalyScoreWeights {
+    /// Relative deviation of Shannon entropy from the baseline
+    pub shannon: f64,
+    /// Added when sample entropy is near zero (suspiciously regular)
+    pub regularity: f64,
+    /// Added when spectral entropy is extreme (pure tone or flat noise)
+    pub spectral: f64,
+    /// Multiplies `ln(1 + excess kurtosis above kurtosis_threshold)`
+    pub kurtosis: f64,
+    pub kurtosis_threshold: f64,
+    /// Multiplies `ln(1 + |skewness| above skewness_threshold)`
+    pub skewness: f64,
+    pub skewness_threshold: f64,
+}
+
+impl Default for AnomalyScoreWeights {
+    fn default() -> Self {
+        Self {
+            shannon: 1.0,
+            regularity: 2.0,
+            spectral: 1.5,
+            kurtosis: 1.0,
+            kurtosis_threshold: 3.0,
+            skewness: 0.5,
+            skewness_threshold: 2.0,
+        }
+    }
+}
+
 /// Entropy analyzer
 pub struct EntropyAnalyzer {
     config: AnalysisConfig,
-    fft_planner: FftPlanner<f64>,
+    fft_planner: FftPlanCache,
     baseline_entropy: Option<f64>,
 }
 
@@ -54,7 +95,7 @@ impl EntropyAnalyzer {
     pub fn new(config: AnalysisConfig) -> Self {
         Self {
             config,
-            fft_planner: FftPlanner::new(),
+            fft_planner: FftPlanCache::new(),
             baseline_entropy: None,
         }
     }
@@ -72,9 +113,21 @@ impl EntropyAnalyzer {
         let sample = self.sample_entropy(data, 2, 0.2);
         let approximate = self.approximate_entropy(data, 2, 0.2);
         let permutation = self.permutation_entropy(data, 3, 1);
-        let multiscale = self.multiscale_entropy(data, 2, 0.2, 10);
+        let multiscale = match (self.config.multiscale_entropy, self.config.multiscale_method) {
+            (false, _) => Vec::new(),
+            (true, MultiscaleMethod::Standard) => {
+                self.multiscale_entropy(data, 2, 0.2, self.config.entropy_scales)
+            }
+            (true, MultiscaleMethod::RefinedComposite) => {
+                self.refined_composite_multiscale_entropy(data, 2, 0.2, self.config.entropy_scales)
+            }
+        };
         
-        let spectral = self.spectral_entropy(data);
+        let spectral = match self.config.spectral_entropy_method {
+            SpectralEntropyMethod::Periodogram => self.spectral_entropy(data),
+            SpectralEntropyMethod::Windowed => self.spectral_entropy_windowed(data, self.config.spectral_window),
+            SpectralEntropyMethod::Welch => self.spectral_entropy_welch(data, self.config.spectral_window),
+        };
         let wavelet = self.wavelet_entropy(data);
         
         let lz_complexity = self.lempel_ziv_complexity(data);
@@ -84,8 +137,8 @@ impl EntropyAnalyzer {
         let (skewness, kurtosis) = self.compute_moments(data);
         
         // Anomaly detection based on entropy deviation
-        let anomaly_score = self.compute_anomaly_score(shannon, sample, spectral);
-        let is_anomalous = anomaly_score > self.config.anomaly_threshold;
+        let anomaly_score = self.compute_anomaly_score(shannon, sample, spectral, kurtosis, skewness);
+        let is_anomalous = anomaly_score > self.config.entropy_score_threshold;
         
         EntropyResult {
             shannon, renyi, tsallis,
@@ -99,25 +152,13 @@ impl EntropyAnalyzer {
     
     /// Shannon entropy: H = -Σ p(x) log2(p(x))
     pub fn shannon_entropy(&self, data: &[f64]) -> f64 {
-        let mut histogram = HashMap::new();
-        let bins = 256;
-        
-        let (min, max) = data.iter().fold((f64::MAX, f64::MIN), |(min, max), &x| {
-            (min.min(x), max.max(x))
-        });
-        
-        let range = (max - min).max(1e-10);
-        
-        for &x in data {
-            let bin = (((x - min) / range) * (bins - 1) as f64) as usize;
-            *histogram.entry(bin).or_insert(0usize) += 1;
-        }
-        
+        let counts = histogram(data, ENTROPY_BINS);
         let n = data.len() as f64;
-        histogram.values()
+        counts.iter()
+            .filter(|&&count| count > 0)
             .map(|&count| {
                 let p = count as f64 / n;
-                if p > 0.0 { -p * p.log2() } else { 0.0 }
+                -p * p.log2()
             })
             .sum()
     }
@@ -128,21 +169,10 @@ impl EntropyAnalyzer {
             return self.shannon_entropy(data);
         }
         
-        let mut histogram = HashMap::new();
-        let bins = 256;
-        
-        let (min, max) = data.iter().fold((f64::MAX, f64::MIN), |(min, max), &x| {
-            (min.min(x), max.max(x))
-        });
-        let range = (max - min).max(1e-10);
-        
-        for &x in data {
-            let bin = (((x - min) / range) * (bins - 1) as f64) as usize;
-            *histogram.entry(bin).or_insert(0usize) += 1;
-        }
-        
+        let counts = histogram(data, ENTROPY_BINS);
         let n = data.len() as f64;
-        let sum_p_alpha: f64 = histogram.values()
+        let sum_p_alpha: f64 = counts.iter()
+            .filter(|&&count| count > 0)
             .map(|&count| (count as f64 / n).powf(alpha))
             .sum();
         
@@ -155,21 +185,10 @@ impl EntropyAnalyzer {
             return self.shannon_entropy(data);
         }
         
-        let mut histogram = HashMap::new();
-        let bins = 256;
-        
-        let (min, max) = data.iter().fold((f64::MAX, f64::MIN), |(min, max), &x| {
-            (min.min(x), max.max(x))
-        });
-        let range = (max - min).max(1e-10);
-        
-        for &x in data {
-            let bin = (((x - min) / range) * (bins - 1) as f64) as usize;
-            *histogram.entry(bin).or_insert(0usize) += 1;
-        }
-        
+        let counts = histogram(data, ENTROPY_BINS);
         let n = data.len() as f64;
-        let sum_p_q: f64 = histogram.values()
+        let sum_p_q: f64 = counts.iter()
+            .filter(|&&count| count > 0)
             .map(|&count| (count as f64 / n).powf(q))
             .sum();
         
@@ -184,10 +203,23 @@ impl EntropyAnalyzer {
         }
         
         let std_dev = self.std_dev(data);
-        let r = r_mult * std_dev;
+        let (count_m, count_m1) = self.template_matches(data, m, r_mult * std_dev);
+        
+        if count_m == 0 || count_m1 == 0 {
+            return 0.0;
+        }
         
+        -((count_m1 as f64) / (count_m as f64)).ln()
+    }
+    
+    /// Pairs of templates matching within `r` at lengths m and m + 1
+    fn template_matches(&self, data: &[f64], m: usize, r: f64) -> (usize, usize) {
+        let n = data.len();
         let mut count_m = 0usize;
         let mut count_m1 = 0usize;
+        if n < m + 2 {
+            return (0, 0);
+        }
         
         // Count template matches for embedding dimension m
         for i in 0..(n - m) {
@@ -217,11 +249,7 @@ impl EntropyAnalyzer {
             }
         }
         
-        if count_m == 0 || count_m1 == 0 {
-            return 0.0;
-        }
-        
-        -((count_m1 as f64) / (count_m as f64)).ln()
+        (count_m, count_m1)
     }
     
     /// Approximate entropy - similar to sample entropy but includes self-matches
@@ -276,7 +304,7 @@ impl EntropyAnalyzer {
             indices.sort_by(|&a, &b| {
                 let va = data[i + a * delay];
                 let vb = data[i + b * delay];
-                va.partial_cmp(&vb).unwrap()
+                va.total_cmp(&vb)
             });
             
             *patterns.entry(indices).or_insert(0) += 1;
@@ -303,6 +331,36 @@ impl EntropyAnalyzer {
         }).collect()
     }
     
+    /// Refined composite multiscale entropy (Wu et al., 2014)
+    ///
+    /// At scale τ the series is coarse-grained from each of the τ starting
+    /// offsets and the template matches of all of them are summed before
+    /// taking the log, instead of using the single offset-0 series. The
+    /// tolerance is `r` times the standard deviation of the original data
+    /// at every scale. Much less variable than `multiscale_entropy` on
+    /// short windows, and undefined (0) less often at high scales.
+    pub fn refined_composite_multiscale_entropy(&self, data: &[f64], m: usize, r: f64, scales: usize) -> Vec<f64> {
+        let tolerance = r * self.std_dev(data);
+        
+        (1..=scales).map(|scale| {
+            let (mut count_m, mut count_m1) = (0, 0);
+            for offset in 0..scale.min(data.len()) {
+                let coarse: Vec<f64> = data[offset..].chunks_exact(scale)
+                    .map(|chunk| chunk.iter().sum::<f64>() / scale as f64)
+                    .collect();
+                let (a, b) = self.template_matches(&coarse, m, tolerance);
+                count_m += a;
+                count_m1 += b;
+            }
+            
+            if count_m == 0 || count_m1 == 0 {
+                0.0
+            } else {
+                -((count_m1 as f64) / (count_m as f64)).ln()
+            }
+        }).collect()
+    }
+    
     fn coarse_grain(&self, data: &[f64], scale: usize) -> Vec<f64> {
         data.chunks(scale)
             .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
@@ -311,41 +369,52 @@ impl EntropyAnalyzer {
     
     /// Spectral entropy
     pub fn spectral_entropy(&self, data: &[f64]) -> f64 {
+        self.spectral_entropy_windowed(data, WindowFunction::Rectangular)
+    }
+    
+    /// Spectral entropy of the periodogram of `data` tapered with `window`,
+    /// which keeps a tone between bins from leaking into the rest of the
+    /// spectrum and inflating the entropy
+    pub fn spectral_entropy_windowed(&self, data: &[f64], window: WindowFunction) -> f64 {
         if data.len() < 4 {
             return 0.0;
         }
         
-        let n = data.len().next_power_of_two();
+        let power = self.power_spectrum(&window.apply(data), data.len().next_power_of_two());
+        normalized_spectral_entropy(&power)
+    }
+    
+    /// Spectral entropy of the Welch power spectrum: the average periodogram
+    /// of segments half the length of `data` (rounded down to a power of
+    /// two), overlapping by half and each tapered with `window`
+    pub fn spectral_entropy_welch(&self, data: &[f64], window: WindowFunction) -> f64 {
+        if data.len() < 8 {
+            return self.spectral_entropy_windowed(data, window);
+        }
+        
+        let segment = 1usize << (data.len() / 2).ilog2();
+        let mut power = vec![0.0; segment / 2];
+        for start in (0..=data.len() - segment).step_by(segment / 2) {
+            let segment_power = self.power_spectrum(&window.apply(&data[start..start + segment]), segment);
+            for (total, p) in power.iter_mut().zip(segment_power) {
+                *total += p;
+            }
+        }
+        normalized_spectral_entropy(&power)
+    }
+    
+    /// Power of the positive frequencies of `data` zero-padded to `n`
+    fn power_spectrum(&self, data: &[f64], n: usize) -> Vec<f64> {
         let mut buffer: Vec<Complex<f64>> = data.iter()
             .map(|&x| Complex::new(x, 0.0))
             .collect();
         buffer.resize(n, Complex::new(0.0, 0.0));
         
-        // Create a new planner for this call
-        let mut planner = FftPlanner::new();
-        let fft = planner.plan_fft_forward(n);
-        fft.process(&mut buffer);
+        self.fft_planner.forward(n).process(&mut buffer);
         
-        // Power spectrum (only positive frequencies)
-        let power: Vec<f64> = buffer[0..n/2].iter()
+        buffer[0..n/2].iter()
             .map(|c| c.norm_sqr())
-            .collect();
-        
-        let total: f64 = power.iter().sum();
-        if total < 1e-10 {
-            return 0.0;
-        }
-        
-        // Normalized power spectral density
-        let psd: Vec<f64> = power.iter().map(|&p| p / total).collect();
-        
-        // Shannon entropy of PSD
-        let max_entropy = (n as f64 / 2.0).log2();
-        let entropy: f64 = psd.iter()
-            .map(|&p| if p > 0.0 { -p * p.log2() } else { 0.0 })
-            .sum();
-        
-        entropy / max_entropy  // Normalized
+            .collect()
     }
     
     /// Wavelet entropy (simplified Haar wavelet)
@@ -507,8 +576,8 @@ impl EntropyAnalyzer {
         }
         
         let n = data.len() as f64;
-        let mean = data.iter().sum::<f64>() / n;
-        let std = self.std_dev(data);
+        let stats = RunningStats::from_slice(data);
+        let (mean, std) = (stats.mean(), stats.std_dev());
         
         if std < 1e-10 {
             return (0.0, 0.0);
@@ -525,30 +594,29 @@ impl EntropyAnalyzer {
         (skewness, kurtosis)
     }
     
-    fn compute_anomaly_score(&self, shannon: f64, sample: f64, spectral: f64) -> f64 {
+    fn compute_anomaly_score(&self, shannon: f64, sample: f64, spectral: f64, kurtosis: f64, skewness: f64) -> f64 {
         // Combine entropy measures for anomaly detection
         // High entropy + low sample entropy = potentially anomalous
+        let weights = &self.config.score_weights;
         
         let baseline = self.baseline_entropy.unwrap_or(shannon);
         let shannon_dev = (shannon - baseline).abs() / baseline.max(1e-10);
         
         // Sample entropy close to 0 indicates regularity (potentially artificial)
-        let regularity_score = if sample < 0.1 { 2.0 } else { 0.0 };
+        let regularity_score = if sample < 0.1 { weights.regularity } else { 0.0 };
         
         // Very high or very low spectral entropy
-        let spectral_score = if spectral < 0.2 || spectral > 0.95 { 1.5 } else { 0.0 };
+        let spectral_score = if spectral < 0.2 || spectral > 0.95 { weights.spectral } else { 0.0 };
         
-        shannon_dev + regularity_score + spectral_score
+        // Heavy tails / one-sided outliers: impulsive events
+        let kurtosis_score = weights.kurtosis * (kurtosis - weights.kurtosis_threshold).max(0.0).ln_1p();
+        let skewness_score = weights.skewness * (skewness.abs() - weights.skewness_threshold).max(0.0).ln_1p();
+        
+        weights.shannon * shannon_dev + regularity_score + spectral_score + kurtosis_score + skewness_score
     }
     
     fn std_dev(&self, data: &[f64]) -> f64 {
-        if data.len() < 2 {
-            return 0.0;
-        }
-        let n = data.len() as f64;
-        let mean = data.iter().sum::<f64>() / n;
-        let variance = data.iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
-        variance.sqrt()
+        RunningStats::from_slice(data).std_dev()
     }
     
     fn median(&self, data: &[f64]) -> f64 {
@@ -556,7 +624,7 @@ impl EntropyAnalyzer {
             return 0.0;
         }
         let mut sorted = data.to_vec();
-        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        sorted.sort_by(|a, b| a.total_cmp(b));
         let mid = sorted.len() / 2;
         if sorted.len() % 2 == 0 {
             (sorted[mid - 1] + sorted[mid]) / 2.0
@@ -588,3 +656,108 @@ impl Default for EntropyResult {
         }
     }
 }
+
+/// Shannon entropy of a power spectrum treated as a distribution over its
+/// bins, normalized to 0-1
+fn normalized_spectral_entropy(power: &[f64]) -> f64 {
+    let total: f64 = power.iter().sum();
+    if total < 1e-10 || power.len() < 2 {
+        return 0.0;
+    }
+    
+    let max_entropy = (power.len() as f64).log2();
+    let entropy: f64 = power.iter()
+        .map(|&p| p / total)
+        .map(|p| if p > 0.0 { -p * p.log2() } else { 0.0 })
+        .sum();
+    
+    entropy / max_entropy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_distr::{Distribution, Normal};
+    
+    #[test]
+    fn test_impulses_are_anomalous_through_kurtosis() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let noise = Normal::new(0.0, 1.0).unwrap();
+        let mut data: Vec<f64> = (0..1000).map(|_| noise.sample(&mut rng)).collect();
+        for i in [120, 430, 777] {
+            data[i] = 40.0;
+        }
+        
+        let config = AnalysisConfig::default();
+        let result = EntropyAnalyzer::new(config.clone()).analyze(&data);
+        assert!(result.kurtosis > 50.0, "kurtosis {}", result.kurtosis);
+        assert!(result.is_anomalous, "score {}", result.anomaly_score);
+        
+        // The kurtosis term outweighs everything else in the score
+        let mut without = config.clone();
+        without.score_weights.kurtosis = 0.0;
+        let rest = EntropyAnalyzer::new(without).analyze(&data).anomaly_score;
+        assert!(result.anomaly_score - rest > rest, "kurtosis {} vs rest {}", result.anomaly_score - rest, rest);
+        
+        // Plain noise doesn't trip it
+        let clean: Vec<f64> = (0..1000).map(|_| noise.sample(&mut rng)).collect();
+        assert!(!EntropyAnalyzer::new(config).analyze(&clean).is_anomalous);
+    }
+    
+    #[test]
+    fn test_multiscale_follows_configured_scales() {
+        let data: Vec<f64> = (0..600).map(|i| (i as f64 * 0.37).sin() + ((i * 7919) % 13) as f64 * 0.05).collect();
+        
+        let mut config = AnalysisConfig::from(&crate::config::AnalysisConfig {
+            entropy_scales: 4,
+            ..Default::default()
+        });
+        assert_eq!(EntropyAnalyzer::new(config.clone()).analyze(&data).multiscale.len(), 4);
+        
+        config.multiscale_entropy = false;
+        assert!(EntropyAnalyzer::new(config).analyze(&data).multiscale.is_empty());
+    }
+    
+    #[test]
+    fn test_refined_composite_mse_is_steadier() {
+        let analyzer = EntropyAnalyzer::new(AnalysisConfig::default());
+        let mut rng = rand::rngs::StdRng::seed_from_u64(21);
+        let noise = Normal::new(0.0, 1.0).unwrap();
+        
+        let (mut mse, mut rcmse) = (RunningStats::new(), RunningStats::new());
+        for _ in 0..20 {
+            let data: Vec<f64> = (0..300).map(|_| noise.sample(&mut rng)).collect();
+            mse.push(analyzer.multiscale_entropy(&data, 2, 0.2, 5)[4]);
+            rcmse.push(analyzer.refined_composite_multiscale_entropy(&data, 2, 0.2, 5)[4]);
+        }
+        
+        assert!(rcmse.variance() < mse.variance(), "RCMSE {} vs MSE {}", rcmse.variance(), mse.variance());
+        assert!(rcmse.mean() > 0.0);
+    }
+    
+    #[test]
+    fn test_windowing_cuts_tone_leakage() {
+        // 100.5 cycles in 1024 samples falls midway between two bins, the
+        // worst case for leakage
+        let tone: Vec<f64> = (0..1024)
+            .map(|i| (2.0 * PI * 100.5 * i as f64 / 1024.0).sin())
+            .collect();
+        let analyzer = EntropyAnalyzer::new(AnalysisConfig::default());
+        
+        let raw = analyzer.spectral_entropy(&tone);
+        let windowed = analyzer.spectral_entropy_windowed(&tone, WindowFunction::Hann);
+        let welch = analyzer.spectral_entropy_welch(&tone, WindowFunction::Hann);
+        assert!(windowed < 0.6 * raw, "windowed {} vs raw {}", windowed, raw);
+        assert!(welch < 0.7 * raw, "welch {} vs raw {}", welch, raw);
+        assert_eq!(analyzer.spectral_entropy_windowed(&tone, WindowFunction::Rectangular), raw);
+        
+        // The configured method is what `analyze` reports
+        let config = AnalysisConfig {
+            spectral_entropy_method: SpectralEntropyMethod::Windowed,
+            multiscale_entropy: false,
+            ..Default::default()
+        };
+        assert_eq!(EntropyAnalyzer::new(config).analyze(&tone).spectral, windowed);
+    }
+}
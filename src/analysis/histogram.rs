@@ -0,0 +1,103 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Equal-width value histograms for the distribution entropies
+//!
+//! With the `simd` feature the range scan and binning run four samples per
+//! iteration. Every lane does the same IEEE operations in the same order as
+//! the scalar loop, so counts - and therefore entropies - are bit-identical
+//! to `histogram_scalar`.
+
+/// Bin count used by Shannon, Rényi and Tsallis entropy
+pub const ENTROPY_BINS: usize = 256;
+
+/// Counts of `data` in `bins` equal-width bins spanning its min..max
+pub fn histogram(data: &[f64], bins: usize) -> Vec<usize> {
+    #[cfg(feature = "simd")]
+    {
+        simd::histogram(data, bins)
+    }
+    #[cfg(not(feature = "simd"))]
+    {
+        histogram_scalar(data, bins)
+    }
+}
+
+/// Reference implementation of `histogram`, one sample at a time
+pub fn histogram_scalar(data: &[f64], bins: usize) -> Vec<usize> {
+    let mut counts = vec![0; bins.max(1)];
+    let (min, max) = data.iter().fold((f64::MAX, f64::MIN), |(min, max), &x| {
+        (if x < min { x } else { min }, if x > max { x } else { max })
+    });
+    let range = (max - min).max(1e-10);
+    let scale = (counts.len() - 1) as f64;
+    let last = counts.len() - 1;
+
+    for &x in data {
+        let bin = (((x - min) / range) * scale) as usize;
+        counts[bin.min(last)] += 1;
+    }
+    counts
+}
+
+#[cfg(feature = "simd")]
+mod simd {
+    use wide::f64x4;
+
+    pub fn histogram(data: &[f64], bins: usize) -> Vec<usize> {
+        let mut counts = vec![0; bins.max(1)];
+        let (chunks, tail) = data.split_at(data.len() - data.len() % 4);
+        let lanes = || chunks.chunks_exact(4).map(|c| f64x4::from([c[0], c[1], c[2], c[3]]));
+
+        // Same comparisons as the scalar fold, so NaNs are skipped alike
+        let (mut lo, mut hi) = (f64x4::splat(f64::MAX), f64x4::splat(f64::MIN));
+        for v in lanes() {
+            lo = v.cmp_lt(lo).blend(v, lo);
+            hi = v.cmp_gt(hi).blend(v, hi);
+        }
+        let (min, max) = lo.to_array().iter().chain(tail)
+            .zip(hi.to_array().iter().chain(tail))
+            .fold((f64::MAX, f64::MIN), |(min, max), (&l, &h)| {
+                (if l < min { l } else { min }, if h > max { h } else { max })
+            });
+
+        let range = (max - min).max(1e-10);
+        let scale = (counts.len() - 1) as f64;
+        let last = counts.len() - 1;
+        let (min_v, range_v, scale_v) = (f64x4::splat(min), f64x4::splat(range), f64x4::splat(scale));
+
+        for v in lanes() {
+            for position in (((v - min_v) / range_v) * scale_v).to_array() {
+                counts[(position as usize).min(last)] += 1;
+            }
+        }
+        for &x in tail {
+            let bin = (((x - min) / range) * scale) as usize;
+            counts[bin.min(last)] += 1;
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_histogram_matches_scalar() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4096);
+        let mut data: Vec<f64> = (0..4099).map(|_| rng.gen_range(-3.0..7.0)).collect();
+        data[17] = f64::NAN;
+        data[4097] = -4.5;
+
+        for len in [0, 1, 3, 4, 5, 64, 4099] {
+            let window = &data[..len];
+            let counts = histogram(window, ENTROPY_BINS);
+            assert_eq!(counts, histogram_scalar(window, ENTROPY_BINS));
+            assert_eq!(counts.iter().sum::<usize>(), len);
+        }
+        assert_eq!(histogram(&[2.0; 9], 4), vec![9, 0, 0, 0]);
+    }
+}
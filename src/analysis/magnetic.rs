@@ -0,0 +1,171 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Field gradient and source direction from an array of 3-axis magnetometers
+//!
+//! Outside its sources a static magnetic field is curl- and divergence-free,
+//! so its gradient tensor is symmetric and traceless: five numbers, which a
+//! least-squares fit recovers even from a flat array. A compact source then
+//! follows from Euler's homogeneity equation for a dipole,
+//! `G (p - x0) = -3 B`, so `x0 = p + 3 G⁻¹ B`.
+//!
+//! Positions are in metres with x east, y north and z up.
+
+use nalgebra::{DMatrix, DVector, Matrix3, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// Sensor position in metres (x east, y north, z up)
+pub type Position = [f64; 3];
+
+/// Euler structural index of a point dipole
+const DIPOLE_INDEX: f64 = 3.0;
+
+/// Estimated field gradient across a sensor array
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FieldGradient {
+    /// Mean sensor position, where the estimates apply
+    pub centroid: Position,
+    /// Mean field over the array
+    pub field: [f64; 3],
+    /// ∂B_row/∂col, in field units per metre
+    pub tensor: [[f64; 3]; 3],
+    /// Gradient of the field strength |B|, pointing up the slope
+    pub magnitude_gradient: [f64; 3],
+    /// Dipole location, when the tensor is well enough determined
+    pub source: Option<Position>,
+    /// Compass bearing from the centroid toward the source in degrees
+    /// clockwise from north; follows the magnitude gradient when there's no
+    /// source estimate
+    pub bearing_deg: Option<f64>,
+}
+
+/// Fit the field gradient across `readings` of (position, field vector).
+///
+/// The tensor isn't affected by a uniform ambient field, but the source
+/// estimate assumes the fields contain only the anomaly, so subtract each
+/// sensor's baseline (e.g. the Earth's field) first. At least three sensors
+/// not in a line are needed for a bearing.
+pub fn estimate_field_gradient(readings: &[(Position, [f64; 3])]) -> FieldGradient {
+    let n = readings.len();
+    if n == 0 {
+        return FieldGradient::default();
+    }
+
+    let mean = |pick: &dyn Fn(&(Position, [f64; 3])) -> [f64; 3]| {
+        let mut sum = [0.0; 3];
+        for r in readings {
+            for (s, v) in sum.iter_mut().zip(pick(r)) {
+                *s += v / n as f64;
+            }
+        }
+        sum
+    };
+    let centroid = mean(&|r| r.0);
+    let field = mean(&|r| r.1);
+
+    // Unknowns gxx, gyy, gxy, gxz, gyz; gzz = -(gxx + gyy)
+    let mut a = DMatrix::zeros(3 * n, 5);
+    let mut b = DVector::zeros(3 * n);
+    for (i, (position, value)) in readings.iter().enumerate() {
+        let [dx, dy, dz] = [0, 1, 2].map(|k| position[k] - centroid[k]);
+        let rows = [
+            [dx, 0.0, dy, dz, 0.0],
+            [0.0, dy, dx, 0.0, dz],
+            [-dz, -dz, 0.0, dx, dy],
+        ];
+        for (j, row) in rows.iter().enumerate() {
+            for (k, &coefficient) in row.iter().enumerate() {
+                a[(3 * i + j, k)] = coefficient;
+            }
+            b[3 * i + j] = value[j] - field[j];
+        }
+    }
+
+    let svd = a.svd(true, true);
+    let determined = svd.rank(1e-9) == 5;
+    let Ok(g) = svd.solve(&b, 1e-12) else {
+        return FieldGradient { centroid, field, ..Default::default() };
+    };
+    let tensor = Matrix3::new(
+        g[0], g[2], g[3],
+        g[2], g[1], g[4],
+        g[3], g[4], -(g[0] + g[1]),
+    );
+
+    let b_mean = Vector3::from(field);
+    let magnitude_gradient = if b_mean.norm() > 1e-12 {
+        tensor.transpose() * b_mean / b_mean.norm()
+    } else {
+        Vector3::zeros()
+    };
+
+    let source = if determined {
+        tensor.try_inverse().map(|inverse| {
+            let offset = DIPOLE_INDEX * inverse * b_mean;
+            [0, 1, 2].map(|k| centroid[k] + offset[k])
+        })
+    } else {
+        None
+    };
+
+    let heading = match source {
+        Some(s) => [s[0] - centroid[0], s[1] - centroid[1]],
+        None => [magnitude_gradient[0], magnitude_gradient[1]],
+    };
+    let bearing_deg = (heading[0].hypot(heading[1]) > 1e-12)
+        .then(|| heading[0].atan2(heading[1]).to_degrees().rem_euclid(360.0));
+
+    FieldGradient {
+        centroid,
+        field,
+        tensor: [0, 1, 2].map(|r| [0, 1, 2].map(|c| tensor[(r, c)])),
+        magnitude_gradient: [magnitude_gradient[0], magnitude_gradient[1], magnitude_gradient[2]],
+        source,
+        bearing_deg,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dipole(source: Position, moment: [f64; 3], at: Position) -> [f64; 3] {
+        let r = Vector3::from([0, 1, 2].map(|k| at[k] - source[k]));
+        let m = Vector3::from(moment);
+        let d = r.norm();
+        let field = (3.0 * m.dot(&r) * r / (d * d) - m) / d.powi(3);
+        [field[0], field[1], field[2]]
+    }
+
+    #[test]
+    fn test_points_toward_dipole() {
+        let source = [-3.0, 2.0, 0.3];
+        let moment = [30.0, 10.0, 5.0];
+        // Flat cross of fluxgates, half a metre apart
+        let positions = [[0.0, 0.0, 0.0], [0.5, 0.0, 0.0], [-0.5, 0.0, 0.0], [0.0, 0.5, 0.0], [0.0, -0.5, 0.0]];
+        let readings: Vec<(Position, [f64; 3])> = positions.iter()
+            .map(|&p| (p, dipole(source, moment, p)))
+            .collect();
+
+        let gradient = estimate_field_gradient(&readings);
+        let expected = (-3.0f64).atan2(2.0).to_degrees().rem_euclid(360.0);
+        let bearing = gradient.bearing_deg.unwrap();
+        assert!((bearing - expected).abs() < 3.0, "bearing {} vs {}", bearing, expected);
+        let located = gradient.source.unwrap();
+        assert!((located[0] - source[0]).hypot(located[1] - source[1]) < 0.3, "{:?}", located);
+
+        // A uniform ambient field leaves the tensor alone
+        let ambient: Vec<(Position, [f64; 3])> = readings.iter()
+            .map(|&(p, b)| (p, [b[0] + 15.0, b[1] + 5.0, b[2] + 45.0]))
+            .collect();
+        let shifted = estimate_field_gradient(&ambient);
+        for (row, shifted_row) in gradient.tensor.iter().zip(&shifted.tensor) {
+            for (g, s) in row.iter().zip(shifted_row) {
+                assert!((g - s).abs() < 1e-9);
+            }
+        }
+
+        assert!(estimate_field_gradient(&readings[..2]).source.is_none());
+    }
+}
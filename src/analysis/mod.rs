@@ -10,6 +10,15 @@ mod signal;
 mod patterns;
 mod statistics;
 mod complexity;
+mod stats_util;
+mod histogram;
+mod randomness;
+mod schumann;
+mod thermal;
+mod magnetic;
+mod baseline;
+mod voice;
+mod window;
 
 pub use entropy::*;
 pub use anomaly::*;
@@ -17,73 +26,311 @@ pub use signal::*;
 pub use patterns::*;
 pub use statistics::*;
 pub use complexity::*;
+pub use stats_util::*;
+pub use histogram::*;
+pub use randomness::*;
+pub use schumann::*;
+pub use thermal::*;
+pub use magnetic::*;
+pub use baseline::*;
+pub use voice::*;
+pub use window::*;
 
+use std::borrow::Cow;
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, broadcast::error::{RecvError, TryRecvError}, mpsc};
 use anyhow::Result;
-use tracing::{info, debug};
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, debug, warn};
 
-use crate::sensors::SensorReading;
-use crate::config::Config;
+use crate::sensors::{SensorReading, SensorType};
+use crate::config::{Config, MultiscaleMethod, SpectralEntropyMethod};
 use crate::core::EventBus;
+use crate::db::Database;
+
+/// Settings key the entropy baselines are persisted under
+const BASELINES_SETTING: &str = "analysis.entropy_baselines";
+
+/// Settings key the partly filled sensor windows are persisted under
+const WINDOWS_SETTING: &str = "analysis.sensor_windows";
 
 /// Analysis engine configuration
+///
+/// Thresholds are in the units their detector works in; build it from the
+/// file configuration with `AnalysisConfig::from(&config.analysis)`.
 #[derive(Debug, Clone)]
 pub struct AnalysisConfig {
     pub entropy_window: usize,
-    pub anomaly_threshold: f64,
+    /// Standard deviations from the mean before the Z-score detector flags a point
+    pub zscore_sigma_threshold: f64,
+    /// Combined confidence (0-1) an anomaly needs before it is reported
+    pub anomaly_probability_threshold: f64,
+    /// `EntropyResult::anomaly_score` above which a window is anomalous
+    pub entropy_score_threshold: f64,
     pub pattern_min_length: usize,
+    /// Compute multiscale entropy (the most expensive entropy measure)
+    pub multiscale_entropy: bool,
+    /// Coarse-graining scales for multiscale entropy
+    pub entropy_scales: usize,
+    pub multiscale_method: MultiscaleMethod,
+    pub spectral_entropy_method: SpectralEntropyMethod,
+    /// Taper of the windowed and Welch spectral entropy methods
+    pub spectral_window: WindowFunction,
     pub fft_size: usize,
     pub enable_gpu: bool,
+    /// Weights of the terms in `EntropyResult::anomaly_score`
+    pub score_weights: AnomalyScoreWeights,
+    pub isolation_forest: IsolationForestConfig,
+    /// Neighbours each point is compared with by the local outlier factor
+    pub lof_neighbors: usize,
+    /// Detection methods `AnomalyDetector` runs
+    pub anomaly_methods: Vec<AnomalyMethod>,
+    pub percentile_band: PercentileBandConfig,
+    pub bocpd: BocpdConfig,
 }
 
 impl Default for AnalysisConfig {
     fn default() -> Self {
         Self {
             entropy_window: 1024,
-            anomaly_threshold: 3.0,  // Standard deviations
+            zscore_sigma_threshold: 3.0,
+            anomaly_probability_threshold: 0.7,
+            entropy_score_threshold: 3.0,
             pattern_min_length: 16,
+            multiscale_entropy: true,
+            entropy_scales: 10,
+            multiscale_method: MultiscaleMethod::Standard,
+            spectral_entropy_method: SpectralEntropyMethod::Periodogram,
+            spectral_window: WindowFunction::Hann,
             fft_size: 4096,
             enable_gpu: true,
+            score_weights: AnomalyScoreWeights::default(),
+            isolation_forest: IsolationForestConfig::default(),
+            lof_neighbors: 5,
+            anomaly_methods: DEFAULT_ANOMALY_METHODS.to_vec(),
+            percentile_band: PercentileBandConfig::default(),
+            bocpd: BocpdConfig::default(),
+        }
+    }
+}
+
+impl From<&crate::config::AnalysisConfig> for AnalysisConfig {
+    fn from(config: &crate::config::AnalysisConfig) -> Self {
+        Self {
+            entropy_window: config.entropy_window,
+            zscore_sigma_threshold: config.zscore_sigma_threshold,
+            anomaly_probability_threshold: config.anomaly_threshold,
+            multiscale_entropy: config.multiscale_entropy,
+            entropy_scales: config.entropy_scales,
+            multiscale_method: config.multiscale_method,
+            spectral_entropy_method: config.spectral_entropy_method,
+            spectral_window: config.spectral_window,
+            fft_size: config.fft_size,
+            enable_gpu: config.gpu_enabled,
+            isolation_forest: config.isolation_forest,
+            lof_neighbors: config.lof_neighbors,
+            anomaly_methods: config.anomaly_methods.clone(),
+            percentile_band: config.percentile_band,
+            bocpd: config.bocpd,
+            ..Self::default()
         }
     }
 }
 
+/// Most readings analyzed together in one parallel batch
+const MAX_BATCH: usize = 64;
+
+/// Largest fraction of NaN/Inf samples a window may hold and still be analyzed
+const MAX_NON_FINITE_FRACTION: f64 = 0.5;
+
+/// Replace NaN/Inf samples (ADC saturation, disconnects) by linear
+/// interpolation between their finite neighbours, holding the nearest finite
+/// value at either end.
+///
+/// Returns `None` when more than `MAX_NON_FINITE_FRACTION` of the window is
+/// non-finite; such a window describes the sensor fault, not the signal.
+pub fn sanitize_samples(data: &[f64]) -> Option<Cow<'_, [f64]>> {
+    let bad = data.iter().filter(|x| !x.is_finite()).count();
+    if bad == 0 {
+        return Some(Cow::Borrowed(data));
+    }
+    if bad as f64 > data.len() as f64 * MAX_NON_FINITE_FRACTION {
+        return None;
+    }
+    
+    let mut out = data.to_vec();
+    let mut prev = None;
+    let mut i = 0;
+    while i < out.len() {
+        if out[i].is_finite() {
+            prev = Some(i);
+            i += 1;
+            continue;
+        }
+        
+        let start = i;
+        while i < out.len() && !out[i].is_finite() {
+            i += 1;
+        }
+        let next = (i < out.len()).then_some(i);
+        
+        match (prev, next) {
+            (Some(p), Some(n)) => {
+                let (a, b) = (out[p], out[n]);
+                for (j, value) in out.iter_mut().enumerate().take(n).skip(start) {
+                    *value = a + (b - a) * (j - p) as f64 / (n - p) as f64;
+                }
+            }
+            (Some(p), None) => {
+                let a = out[p];
+                out[start..].fill(a);
+            }
+            (None, Some(n)) => {
+                let b = out[n];
+                out[..n].fill(b);
+            }
+            (None, None) => return None,
+        }
+    }
+    
+    Some(Cow::Owned(out))
+}
+
+/// Features computed for one reading
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisResult {
+    pub sensor_id: String,
+    pub sensor_type: SensorType,
+    /// Timestamp of the analyzed reading
+    pub timestamp: DateTime<Utc>,
+    pub entropy: EntropyResult,
+    pub anomalies: Vec<Anomaly>,
+    pub signal: SignalFeatures,
+    pub patterns: Vec<Pattern>,
+    /// Entropy relative to this sensor's own recent history, once known
+    #[serde(default)]
+    pub baseline: Option<BaselineDeviation>,
+}
+
+impl AnalysisResult {
+    pub fn is_anomalous(&self) -> bool {
+        !self.anomalies.is_empty()
+            || self.entropy.is_anomalous
+            || self.baseline.is_some_and(|b| b.is_anomalous)
+    }
+}
+
+/// The per-reading analyzers, shared with the worker pool
+struct Analyzers {
+    entropy: EntropyAnalyzer,
+    anomaly: AnomalyDetector,
+    signal: SignalProcessor,
+    pattern: PatternDetector,
+}
+
+impl Analyzers {
+    fn new(config: &AnalysisConfig) -> Self {
+        Self {
+            entropy: EntropyAnalyzer::new(config.clone()),
+            anomaly: AnomalyDetector::new(config.clone()),
+            signal: SignalProcessor::new(config.clone()),
+            pattern: PatternDetector::new(config.clone()),
+        }
+    }
+    
+    fn analyze(&self, reading: &SensorReading) -> Option<AnalysisResult> {
+        if reading.data.is_empty() {
+            return None;
+        }
+        
+        // One sensor's NaN must not take down the analysis workers
+        let Some(data) = sanitize_samples(&reading.data) else {
+            let bad = reading.data.iter().filter(|x| !x.is_finite()).count();
+            warn!("Skipping window from {}: {} of {} samples are NaN/Inf",
+                  reading.sensor_id, bad, reading.data.len());
+            return None;
+        };
+        if let Cow::Owned(_) = data {
+            debug!("Interpolated over NaN/Inf samples from {}", reading.sensor_id);
+        }
+        
+        Some(AnalysisResult {
+            sensor_id: reading.sensor_id.clone(),
+            sensor_type: reading.sensor_type,
+            timestamp: reading.timestamp,
+            entropy: self.entropy.analyze(&data),
+            anomalies: self.anomaly.detect_sensor(&reading.sensor_id, &data),
+            signal: self.signal.extract_features(&data, reading.sample_rate),
+            patterns: self.pattern.find_patterns(&data),
+            baseline: None,
+        })
+    }
+}
+
 /// Main analysis engine
+///
+/// Readings that queue up while a batch is being analyzed form the next
+/// batch, which is spread across a rayon pool of `analysis.worker_threads`
+/// threads. Results are published on the event bus as each completes.
+/// Readings too short to analyze alone are first gathered into per-sensor
+/// windows of `entropy_window` samples. `apply_config` swaps in new
+/// settings while running; batches already on the pool finish with the old.
 pub struct AnalysisEngine {
     config: Arc<Config>,
-    analysis_config: AnalysisConfig,
-    entropy_analyzer: EntropyAnalyzer,
-    anomaly_detector: AnomalyDetector,
-    signal_processor: SignalProcessor,
-    pattern_detector: PatternDetector,
+    analysis_config: parking_lot::RwLock<AnalysisConfig>,
+    analyzers: parking_lot::RwLock<Arc<Analyzers>>,
+    pool: Arc<rayon::ThreadPool>,
     event_bus: Arc<EventBus>,
+    baselines: parking_lot::Mutex<EntropyBaselines>,
+    windows: parking_lot::Mutex<SensorWindows>,
 }
 
 impl AnalysisEngine {
     pub async fn new(config: Arc<Config>, event_bus: Arc<EventBus>) -> Result<Self> {
-        let analysis_config = AnalysisConfig::default();
+        let analysis_config = AnalysisConfig::from(&config.analysis);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(config.analysis.worker_threads)
+            .thread_name(|i| format!("glowbarn-analysis-{}", i))
+            .build()?;
         
         Ok(Self {
+            analyzers: parking_lot::RwLock::new(Arc::new(Analyzers::new(&analysis_config))),
             config,
-            analysis_config: analysis_config.clone(),
-            entropy_analyzer: EntropyAnalyzer::new(analysis_config.clone()),
-            anomaly_detector: AnomalyDetector::new(analysis_config.clone()),
-            signal_processor: SignalProcessor::new(analysis_config.clone()),
-            pattern_detector: PatternDetector::new(analysis_config.clone()),
+            analysis_config: parking_lot::RwLock::new(analysis_config),
+            pool: Arc::new(pool),
             event_bus,
+            baselines: parking_lot::Mutex::new(EntropyBaselines::new()),
+            windows: parking_lot::Mutex::new(SensorWindows::new()),
         })
     }
     
     pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) -> Result<()> {
-        info!("Starting analysis engine...");
+        info!("Starting analysis engine with {} workers...", self.pool.current_num_threads());
         
         let mut reading_rx = self.event_bus.subscribe_readings();
         
         loop {
             tokio::select! {
-                Ok(reading) = reading_rx.recv() => {
-                    self.process_reading(&reading).await;
+                reading = reading_rx.recv() => {
+                    let reading = match reading {
+                        Ok(reading) => reading,
+                        Err(RecvError::Lagged(n)) => {
+                            self.fell_behind(n);
+                            continue;
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
+                    let mut batch = vec![reading];
+                    while batch.len() < MAX_BATCH {
+                        match reading_rx.try_recv() {
+                            Ok(reading) => batch.push(reading),
+                            Err(TryRecvError::Lagged(n)) => self.fell_behind(n),
+                            Err(_) => break,
+                        }
+                    }
+                    self.process_batch(batch).await;
                 }
                 _ = shutdown.recv() => {
                     info!("Analysis engine shutting down...");
@@ -95,27 +342,237 @@ impl AnalysisEngine {
         Ok(())
     }
     
-    async fn process_reading(&self, reading: &SensorReading) {
-        if reading.data.is_empty() {
+    /// Readings lost to a full broadcast channel, counted on the bus
+    fn fell_behind(&self, skipped: u64) {
+        warn!("Analysis fell behind, skipped {} readings", skipped);
+        self.event_bus.record_lagged(skipped);
+    }
+    
+    /// Apply an edited `[analysis]` section to the running engine
+    ///
+    /// Thresholds, windows and methods take effect from the next batch.
+    /// Noise floors and the learned percentile bands carry over, the bands
+    /// unless their percentiles changed; the worker count only changes on
+    /// restart.
+    pub fn apply_config(&self, config: &crate::config::AnalysisConfig) {
+        let analysis_config = AnalysisConfig::from(config);
+        let analyzers = Analyzers::new(&analysis_config);
+        
+        let mut current = self.analyzers.write();
+        analyzers.anomaly.inherit_bands(&current.anomaly);
+        *current = Arc::new(analyzers);
+        *self.analysis_config.write() = analysis_config;
+        
+        if config.worker_threads != self.pool.current_num_threads() {
+            warn!("Change to analysis.worker_threads requires a restart, ignoring it");
+        }
+        info!("Applied reloaded analysis config (anomaly threshold {:.2})", config.anomaly_threshold);
+    }
+    
+    /// Score `sensor_id`'s windows against at least its characterized
+    /// noise `sigma`
+    pub fn set_noise_floor(&self, sensor_id: &str, sigma: f64) {
+        self.analyzers.read().anomaly.set_noise_floor(sensor_id, sigma);
+    }
+    
+    /// Analyze one reading on the calling thread
+    pub fn analyze_reading(&self, reading: &SensorReading) -> Option<AnalysisResult> {
+        self.analyzers.read().analyze(reading)
+    }
+    
+    /// Analyze readings in parallel on the worker pool, in input order
+    pub fn analyze_batch(&self, readings: &[SensorReading]) -> Vec<AnalysisResult> {
+        let analyzers = self.analyzers.read().clone();
+        self.pool.install(|| {
+            readings.par_iter().filter_map(|reading| analyzers.analyze(reading)).collect()
+        })
+    }
+    
+    /// The reading to analyze for `reading`: itself if it is long enough,
+    /// otherwise its sensor's accumulated window once that is due
+    pub fn accumulate(&self, reading: SensorReading) -> Option<SensorReading> {
+        let window = self.analysis_config.read().entropy_window;
+        self.windows.lock().push(reading, window)
+    }
+    
+    /// Score `result` against its sensor's entropy baseline and update it
+    pub fn apply_baseline(&self, result: &mut AnalysisResult) {
+        let sigma_threshold = self.analysis_config.read().zscore_sigma_threshold;
+        result.baseline = self.baselines.lock().observe(
+            &result.sensor_id,
+            &result.entropy,
+            sigma_threshold,
+        );
+    }
+    
+    /// Restore baselines saved by `save_baselines`; returns how many sensors
+    /// had one
+    pub fn load_baselines(&self, db: &Database) -> Result<usize> {
+        let Some(json) = db.get_setting(BASELINES_SETTING)? else {
+            return Ok(0);
+        };
+        let baselines: EntropyBaselines = serde_json::from_str(&json)?;
+        let count = baselines.len();
+        *self.baselines.lock() = baselines;
+        Ok(count)
+    }
+    
+    /// Persist every sensor's baseline to the settings table
+    pub fn save_baselines(&self, db: &Database) -> Result<()> {
+        let json = serde_json::to_string(&*self.baselines.lock())?;
+        db.set_setting(BASELINES_SETTING, &json)
+    }
+    
+    /// Persist the baselines and the partly filled sensor windows
+    pub fn save_checkpoint(&self, db: &Database) -> Result<()> {
+        self.save_baselines(db)?;
+        let json = serde_json::to_string(&*self.windows.lock())?;
+        db.set_setting(WINDOWS_SETTING, &json)
+    }
+    
+    /// Restore what `save_checkpoint` persisted; returns whether there was
+    /// anything to restore
+    pub fn load_checkpoint(&self, db: &Database) -> Result<bool> {
+        let baselines = self.load_baselines(db)?;
+        let Some(json) = db.get_setting(WINDOWS_SETTING)? else {
+            return Ok(baselines > 0);
+        };
+        *self.windows.lock() = serde_json::from_str(&json)?;
+        Ok(true)
+    }
+    
+    /// Analyze `batch` on the worker pool without blocking the async task,
+    /// publishing each result as soon as it is ready
+    async fn process_batch(&self, batch: Vec<SensorReading>) {
+        let batch: Vec<SensorReading> = batch.into_iter().filter_map(|r| self.accumulate(r)).collect();
+        if batch.is_empty() {
             return;
         }
         
-        // Compute entropy metrics
-        let entropy_result = self.entropy_analyzer.analyze(&reading.data);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let analyzers = self.analyzers.read().clone();
+        self.pool.spawn(move || {
+            batch.into_par_iter().for_each_with(tx, |tx, reading| {
+                if let Some(result) = analyzers.analyze(&reading) {
+                    let _ = tx.send(result);
+                }
+            });
+        });
         
-        // Detect anomalies
-        let anomalies = self.anomaly_detector.detect(&reading.data);
+        // Ends once every worker has dropped its sender
+        while let Some(mut result) = rx.recv().await {
+            self.apply_baseline(&mut result);
+            if result.is_anomalous() {
+                debug!("Anomaly detected in {}: entropy={:.4}, anomalies={}",
+                    result.sensor_id, result.entropy.shannon, result.anomalies.len());
+            }
+            self.event_bus.publish_analysis(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::SensorType;
+    
+    #[tokio::test]
+    async fn test_batch_results_match_sequential() {
+        let event_bus = Arc::new(EventBus::new(64));
+        let engine = AnalysisEngine::new(Arc::new(Config::default()), event_bus.clone()).await.unwrap();
         
-        // Signal analysis
-        let signal_features = self.signal_processor.extract_features(&reading.data, reading.sample_rate);
+        let readings: Vec<_> = (0..14)
+            .map(|i| {
+                let data = (0..256).map(|t| ((t * (i + 1)) as f64 * 0.05).sin()).collect();
+                SensorReading::new(&format!("emf-{}", i), SensorType::EMFProbe, data)
+            })
+            .collect();
         
-        // Pattern detection
-        let patterns = self.pattern_detector.find_patterns(&reading.data);
+        let parallel = engine.analyze_batch(&readings);
+        assert_eq!(parallel.len(), readings.len());
+        for (reading, result) in readings.iter().zip(&parallel) {
+            let sequential = engine.analyze_reading(reading).unwrap();
+            assert_eq!(result.sensor_id, reading.sensor_id);
+            assert_eq!(result.entropy.shannon, sequential.entropy.shannon);
+            assert_eq!(result.entropy.spectral, sequential.entropy.spectral);
+        }
         
-        // Publish results
-        if !anomalies.is_empty() || entropy_result.is_anomalous {
-            debug!("Anomaly detected in {}: entropy={:.4}, anomalies={}",
-                reading.sensor_id, entropy_result.shannon, anomalies.len());
+        // The async path publishes one result per reading
+        let mut results = event_bus.subscribe_analysis();
+        engine.process_batch(readings.clone()).await;
+        let mut published = Vec::new();
+        while let Ok(result) = results.try_recv() {
+            published.push(result.sensor_id);
         }
+        published.sort();
+        let mut expected: Vec<_> = readings.iter().map(|r| r.sensor_id.clone()).collect();
+        expected.sort();
+        assert_eq!(published, expected);
+    }
+    
+    #[tokio::test]
+    async fn test_nan_samples_do_not_break_analysis() {
+        let engine = AnalysisEngine::new(Arc::new(Config::default()), Arc::new(EventBus::new(16))).await.unwrap();
+        
+        let mut data: Vec<f64> = (0..256).map(|t| (t as f64 * 0.1).sin()).collect();
+        data[0] = f64::NAN;
+        data[40] = f64::INFINITY;
+        data[41] = f64::NAN;
+        data[255] = f64::NEG_INFINITY;
+        let reading = SensorReading::new("adc", SensorType::EMFProbe, data);
+        
+        let result = engine.analyze_reading(&reading).unwrap();
+        assert!(result.entropy.shannon.is_finite());
+        assert!(result.entropy.spectral.is_finite());
+        assert!(result.signal.rms.is_finite() && result.signal.rms > 0.5 && result.signal.rms < 0.9);
+        assert!(result.anomalies.iter().all(|a| a.score.is_finite()));
+        
+        // Mostly-NaN windows are skipped rather than analyzed
+        let mut dead = vec![f64::NAN; 256];
+        dead[10] = 1.0;
+        assert!(engine.analyze_reading(&SensorReading::new("adc", SensorType::EMFProbe, dead)).is_none());
+        
+        let filled = sanitize_samples(&[f64::NAN, 1.0, f64::NAN, f64::NAN, 4.0, f64::INFINITY]).unwrap();
+        assert_eq!(&*filled, &[1.0, 1.0, 2.0, 3.0, 4.0, 4.0]);
+    }
+    
+    #[tokio::test]
+    async fn test_scalar_sensor_fills_a_window_before_analysis() {
+        let mut config = Config::default();
+        config.analysis.entropy_window = 200;
+        let event_bus = Arc::new(EventBus::new(64));
+        let engine = AnalysisEngine::new(Arc::new(config), event_bus.clone()).await.unwrap();
+        let mut results = event_bus.subscribe_analysis();
+        
+        // One EMF value per reading, a slow wobble plus a little jitter
+        let emf = |t: usize| {
+            let value = 50.0 + 5.0 * (t as f64 * 0.07).sin() + ((t * 7919) % 13) as f64 * 0.1;
+            SensorReading::new("emf", SensorType::EMFProbe, vec![value])
+        };
+        for t in 0..199 {
+            engine.process_batch(vec![emf(t)]).await;
+        }
+        assert!(results.try_recv().is_err(), "analyzed before the window filled");
+        assert_eq!(engine.windows.lock().len("emf"), 199);
+        
+        engine.process_batch(vec![emf(199)]).await;
+        let result = results.try_recv().unwrap();
+        assert_eq!(result.sensor_id, "emf");
+        assert!(result.entropy.shannon > 1.0, "shannon entropy {}", result.entropy.shannon);
+        assert!(result.entropy.spectral > 0.0 && result.entropy.spectral < 1.0);
+        
+        // The window slides, analyzed again once half of it is new
+        for t in 200..299 {
+            engine.process_batch(vec![emf(t)]).await;
+        }
+        assert!(results.try_recv().is_err());
+        engine.process_batch(vec![emf(299)]).await;
+        assert!(results.try_recv().is_ok());
+        assert_eq!(engine.windows.lock().len("emf"), 200);
+        
+        // Readings that are windows already are not buffered
+        let long = SensorReading::new("mic", SensorType::EMFProbe, (0..64).map(|t| (t as f64).sin()).collect());
+        assert_eq!(engine.accumulate(long).unwrap().data.len(), 64);
+        assert_eq!(engine.windows.lock().len("mic"), 0);
     }
 }
@@ -7,7 +7,10 @@
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
-use super::AnalysisConfig;
+use super::{normal_isf, AnalysisConfig};
+
+/// Largest SAX alphabet, one lowercase letter per symbol
+pub const SAX_MAX_ALPHABET: usize = 26;
 
 /// Detected pattern
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,6 +75,40 @@ impl PatternDetector {
         patterns
     }
     
+    /// Symbolic aggregate approximation of `data`: the z-normalized series
+    /// is averaged down to `word_length` segments (PAA), and each segment
+    /// becomes the letter of the equiprobable Gaussian band it falls in,
+    /// `'a'` lowest. Words of series with similar shapes are similar
+    /// whatever their offset and scale, so they are cheap to index and
+    /// compare with `sax_mindist`.
+    ///
+    /// Empty when `word_length` is 0 or longer than `data`, or
+    /// `alphabet_size` is outside `2..=SAX_MAX_ALPHABET`.
+    pub fn sax(&self, data: &[f64], word_length: usize, alphabet_size: usize) -> String {
+        if word_length == 0 || word_length > data.len() || !(2..=SAX_MAX_ALPHABET).contains(&alphabet_size) {
+            return String::new();
+        }
+        
+        let n = data.len() as f64;
+        let mean = data.iter().sum::<f64>() / n;
+        let std = (data.iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+        // A flat series sits in the middle band
+        let normalized: Vec<f64> = if std > 1e-10 {
+            data.iter().map(|&x| (x - mean) / std).collect()
+        } else {
+            vec![0.0; data.len()]
+        };
+        
+        let breakpoints = sax_breakpoints(alphabet_size);
+        piecewise_aggregate(&normalized, word_length)
+            .into_iter()
+            .map(|segment| {
+                let symbol = breakpoints.iter().filter(|&&b| segment >= b).count();
+                (b'a' + symbol as u8) as char
+            })
+            .collect()
+    }
+    
     /// Detect periodicity using autocorrelation
     fn detect_periodicity(&self, data: &[f64]) -> Option<Pattern> {
         let n = data.len();
@@ -314,7 +351,7 @@ impl PatternDetector {
             .map(|(i, &d)| (i, d))
             .collect();
         
-        motif_indices.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        motif_indices.sort_by(|a, b| a.1.total_cmp(&b.1));
         
         // Report top motifs
         let mut reported = std::collections::HashSet::new();
@@ -338,3 +375,105 @@ impl PatternDetector {
         patterns
     }
 }
+
+/// The `alphabet_size - 1` breakpoints cutting the standard normal into
+/// equiprobable bands, lowest first; empty outside `2..=SAX_MAX_ALPHABET`
+pub fn sax_breakpoints(alphabet_size: usize) -> Vec<f64> {
+    if !(2..=SAX_MAX_ALPHABET).contains(&alphabet_size) {
+        return Vec::new();
+    }
+    (1..alphabet_size)
+        .map(|i| normal_isf(1.0 - i as f64 / alphabet_size as f64))
+        .collect()
+}
+
+/// MINDIST between two SAX words of the same length and alphabet, made
+/// from series of `series_len` samples. It lower-bounds the Euclidean
+/// distance of the z-normalized series, so candidates whose words are far
+/// apart can be ruled out without touching the data. `None` if the words
+/// don't match in length or hold letters outside the alphabet.
+pub fn sax_mindist(a: &str, b: &str, series_len: usize, alphabet_size: usize) -> Option<f64> {
+    let breakpoints = sax_breakpoints(alphabet_size);
+    let symbol = |c: char| {
+        let i = (c as usize).checked_sub('a' as usize)?;
+        (i < alphabet_size).then_some(i)
+    };
+    
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    if a.len() != b.len() || a.is_empty() || breakpoints.is_empty() {
+        return None;
+    }
+    
+    let mut sum = 0.0;
+    for (&x, &y) in a.iter().zip(&b) {
+        let (x, y) = (symbol(x)?, symbol(y)?);
+        let (lo, hi) = (x.min(y), x.max(y));
+        // Adjacent bands share a breakpoint, so they're no distance apart
+        if hi - lo > 1 {
+            sum += (breakpoints[hi - 1] - breakpoints[lo]).powi(2);
+        }
+    }
+    Some((series_len as f64 / a.len() as f64).sqrt() * sum.sqrt())
+}
+
+/// Piecewise aggregate approximation: the mean of each of `segments` equal
+/// spans of `data`. A sample straddling two spans is shared between them,
+/// so the length needn't divide evenly.
+fn piecewise_aggregate(data: &[f64], segments: usize) -> Vec<f64> {
+    let (n, w) = (data.len(), segments);
+    // Positions are in units of 1/w of a sample, so span i is [i·n, (i+1)·n)
+    (0..w)
+        .map(|i| {
+            let (start, end) = (i * n, (i + 1) * n);
+            (start / w..end.div_ceil(w).min(n))
+                .map(|j| {
+                    let overlap = end.min((j + 1) * w) - start.max(j * w);
+                    data[j] * overlap as f64
+                })
+                .sum::<f64>() / n as f64
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_sax_words_follow_shape() {
+        let detector = PatternDetector::new(AnalysisConfig::default());
+        let breakpoints = sax_breakpoints(4);
+        assert_eq!(breakpoints.len(), 3);
+        assert!((breakpoints[0] + 0.6745).abs() < 1e-3 && breakpoints[1].abs() < 1e-9 && (breakpoints[2] - 0.6745).abs() < 1e-3);
+        
+        let wave = |offset: f64, scale: f64, wobble: f64| -> Vec<f64> {
+            (0..250)
+                .map(|i| {
+                    let t = i as f64 / 250.0;
+                    offset + scale * ((2.0 * std::f64::consts::PI * 2.0 * t).sin() + wobble * (37.0 * t).sin())
+                })
+                .collect()
+        };
+        let sine = detector.sax(&wave(0.0, 1.0, 0.0), 16, 6);
+        let similar = detector.sax(&wave(40.0, 7.5, 0.05), 16, 6);
+        let ramp: Vec<f64> = (0..250).map(|i| i as f64).collect();
+        let ramp = detector.sax(&ramp, 16, 6);
+        assert_eq!(sine.len(), 16);
+        
+        let differing = |a: &str, b: &str| a.chars().zip(b.chars()).filter(|(x, y)| x != y).count();
+        assert!(differing(&sine, &similar) <= 2, "{} vs {}", sine, similar);
+        assert_eq!(sax_mindist(&sine, &similar, 250, 6), Some(0.0));
+        assert!(differing(&sine, &ramp) >= 8, "{} vs {}", sine, ramp);
+        assert!(sax_mindist(&sine, &ramp, 250, 6).unwrap() > 5.0);
+        assert!(ramp.starts_with('a') && ramp.ends_with('f'), "{}", ramp);
+        
+        // Lengths that don't divide evenly and degenerate input
+        assert_eq!(detector.sax(&[1.0, 2.0, 3.0], 2, 3), "ac");
+        assert_eq!(detector.sax(&[5.0; 10], 5, 3), "bbbbb");
+        assert!(detector.sax(&[1.0; 8], 0, 4).is_empty());
+        assert!(detector.sax(&[1.0; 8], 4, 27).is_empty());
+        assert!(detector.sax(&[1.0, 2.0], 4, 4).is_empty());
+        assert_eq!(sax_mindist("ab", "abc", 10, 4), None);
+        assert_eq!(sax_mindist("az", "ab", 10, 4), None);
+    }
+}
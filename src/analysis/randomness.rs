@@ -0,0 +1,353 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Randomness tests from NIST SP 800-22 for random-source sensors
+//!
+//! Implements the frequency (monobit), runs, longest-run-of-ones,
+//! approximate entropy and cumulative sums tests as specified in SP 800-22
+//! rev. 1a, section 2. Each returns a p-value; a sequence fails a test when
+//! its p-value is below `SIGNIFICANCE`.
+
+use serde::{Deserialize, Serialize};
+use statrs::function::gamma::gamma_ur;
+use std::f64::consts::{LN_2, SQRT_2};
+
+use super::{erfc, normal_cdf};
+
+/// Significance level recommended by SP 800-22
+pub const SIGNIFICANCE: f64 = 0.01;
+
+/// Shortest sequence most tests are defined for
+pub const MIN_BITS: usize = 100;
+
+/// Block length for approximate entropy in `nist_battery`
+const APEN_BLOCK: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RandomnessTestKind {
+    Frequency,
+    Runs,
+    LongestRun,
+    ApproximateEntropy,
+    CumulativeSumsForward,
+    CumulativeSumsBackward,
+}
+
+/// One test's outcome
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RandomnessTest {
+    pub kind: RandomnessTestKind,
+    pub p_value: f64,
+}
+
+impl RandomnessTest {
+    pub fn passed(&self) -> bool {
+        self.p_value >= SIGNIFICANCE
+    }
+}
+
+/// Outcome of `nist_battery`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RandomnessReport {
+    pub bits: usize,
+    /// Tests the sequence was long enough for
+    pub tests: Vec<RandomnessTest>,
+}
+
+impl RandomnessReport {
+    /// Whether every test that ran passed
+    pub fn passed(&self) -> bool {
+        self.tests.iter().all(|t| t.passed())
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &RandomnessTest> {
+        self.tests.iter().filter(|t| !t.passed())
+    }
+
+    /// 0-1 anomaly score, 0.5 when the best-supported failure sits exactly
+    /// at `SIGNIFICANCE`.
+    ///
+    /// The smallest p-value is Šidák-corrected for the number of tests run
+    /// (so running more tests doesn't raise the score by chance), then
+    /// `e = -log10(p)` is mapped to `e / (e + 2)`.
+    pub fn anomaly_score(&self) -> f64 {
+        let Some(min_p) = self.tests.iter().map(|t| t.p_value).reduce(f64::min) else {
+            return 0.0;
+        };
+        let adjusted = 1.0 - (1.0 - min_p).powi(self.tests.len() as i32);
+        let evidence = -adjusted.max(1e-300).log10();
+        evidence / (evidence + 2.0)
+    }
+}
+
+/// Bitstream of samples in [0, 1]: the top `bits_per_sample` bits of each,
+/// most significant first
+pub fn bits_from_unit_samples(data: &[f64], bits_per_sample: u32) -> Vec<bool> {
+    let levels = 1u64 << bits_per_sample;
+    let mut bits = Vec::with_capacity(data.len() * bits_per_sample as usize);
+    for &x in data {
+        let value = ((x.clamp(0.0, 1.0) * levels as f64) as u64).min(levels - 1);
+        bits.extend((0..bits_per_sample).rev().map(|b| (value >> b) & 1 == 1));
+    }
+    bits
+}
+
+/// Every test `bits` is long enough for
+pub fn nist_battery(bits: &[bool]) -> RandomnessReport {
+    let mut tests = Vec::new();
+    let mut push = |kind, p_value| tests.push(RandomnessTest { kind, p_value });
+
+    if bits.len() >= MIN_BITS {
+        push(RandomnessTestKind::Frequency, frequency_test(bits));
+        push(RandomnessTestKind::Runs, runs_test(bits));
+        push(RandomnessTestKind::CumulativeSumsForward, cumulative_sums_test(bits, true));
+        push(RandomnessTestKind::CumulativeSumsBackward, cumulative_sums_test(bits, false));
+    }
+    if let Some(p) = longest_run_test(bits) {
+        push(RandomnessTestKind::LongestRun, p);
+    }
+    // SP 800-22 asks for m < log2(n) - 5
+    if (APEN_BLOCK as f64) < (bits.len() as f64).log2().floor() - 5.0 {
+        push(RandomnessTestKind::ApproximateEntropy, approximate_entropy_test(bits, APEN_BLOCK));
+    }
+
+    RandomnessReport { bits: bits.len(), tests }
+}
+
+/// Runs `nist_battery` over successive blocks of a live bit stream, such
+/// as a random number source's output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RandomnessMonitor {
+    block_bits: usize,
+    // Bits still short of a full block; a restart starts the block over
+    #[serde(skip)]
+    pending: Vec<bool>,
+    // Whether the last block tested failed
+    failing: bool,
+}
+
+impl RandomnessMonitor {
+    /// Monitor testing blocks of `block_bits` (at least `MIN_BITS`)
+    pub fn new(block_bits: usize) -> Self {
+        Self {
+            block_bits: block_bits.max(MIN_BITS),
+            pending: Vec::new(),
+            failing: false,
+        }
+    }
+
+    /// Whether the last block tested failed the battery
+    pub fn is_failing(&self) -> bool {
+        self.failing
+    }
+
+    /// Add `bits` to the stream and test every block it completes.
+    ///
+    /// A block fails when its `anomaly_score` is above 0.5, i.e. its
+    /// corrected smallest p-value is below `SIGNIFICANCE`. The report is
+    /// returned only for a failing block after a passing one, so a source
+    /// that stays biased is reported once.
+    pub fn push(&mut self, bits: &[bool]) -> Option<RandomnessReport> {
+        self.pending.extend_from_slice(bits);
+        let mut onset = None;
+        while self.pending.len() >= self.block_bits {
+            let report = nist_battery(&self.pending[..self.block_bits]);
+            self.pending.drain(..self.block_bits);
+            let failed = report.anomaly_score() > 0.5;
+            if failed && !self.failing && onset.is_none() {
+                onset = Some(report);
+            }
+            self.failing = failed;
+        }
+        onset
+    }
+}
+
+/// Frequency (monobit) test, 2.1: are ones and zeros equally common?
+pub fn frequency_test(bits: &[bool]) -> f64 {
+    if bits.is_empty() {
+        return 0.0;
+    }
+    let sum: i64 = bits.iter().map(|&b| if b { 1 } else { -1 }).sum();
+    let s_obs = sum.abs() as f64 / (bits.len() as f64).sqrt();
+    erfc(s_obs / SQRT_2)
+}
+
+/// Runs test, 2.3: do ones and zeros alternate as often as they should?
+pub fn runs_test(bits: &[bool]) -> f64 {
+    let n = bits.len() as f64;
+    if bits.is_empty() {
+        return 0.0;
+    }
+    let pi = bits.iter().filter(|&&b| b).count() as f64 / n;
+
+    // Prerequisite: the sequence must pass a loose frequency check first
+    if (pi - 0.5).abs() >= 2.0 / n.sqrt() {
+        return 0.0;
+    }
+
+    let runs = 1 + bits.windows(2).filter(|w| w[0] != w[1]).count();
+    let expected = 2.0 * n * pi * (1.0 - pi);
+    erfc((runs as f64 - expected).abs() / (2.0 * (2.0 * n).sqrt() * pi * (1.0 - pi)))
+}
+
+/// Longest run of ones in a block, 2.4; `None` below 128 bits
+pub fn longest_run_test(bits: &[bool]) -> Option<f64> {
+    // Block length, run-length class bounds and class probabilities
+    let (block, classes, probabilities): (usize, std::ops::RangeInclusive<usize>, &[f64]) = match bits.len() {
+        n if n < 128 => return None,
+        n if n < 6272 => (8, 1..=4, &[0.2148, 0.3672, 0.2305, 0.2266]),
+        n if n < 750_000 => (128, 4..=9, &[0.1174, 0.2430, 0.2493, 0.1752, 0.1027, 0.1124]),
+        _ => (10_000, 10..=16, &[0.0882, 0.2092, 0.2483, 0.1933, 0.1208, 0.0675, 0.0727]),
+    };
+
+    let mut counts = vec![0usize; probabilities.len()];
+    for chunk in bits.chunks_exact(block) {
+        let (mut longest, mut run) = (0, 0);
+        for &bit in chunk {
+            run = if bit { run + 1 } else { 0 };
+            longest = longest.max(run);
+        }
+        let class = longest.clamp(*classes.start(), *classes.end()) - classes.start();
+        counts[class] += 1;
+    }
+
+    let blocks = (bits.len() / block) as f64;
+    let chi_square: f64 = counts.iter()
+        .zip(probabilities)
+        .map(|(&count, &p)| (count as f64 - blocks * p).powi(2) / (blocks * p))
+        .sum();
+    let degrees = (probabilities.len() - 1) as f64;
+    Some(chi_square_p_value(degrees, chi_square))
+}
+
+/// Approximate entropy test, 2.12: are overlapping `m`- and `m+1`-bit
+/// patterns as evenly spread as they should be?
+pub fn approximate_entropy_test(bits: &[bool], m: usize) -> f64 {
+    let n = bits.len() as f64;
+    if bits.is_empty() || m == 0 {
+        return 0.0;
+    }
+    let ap_en = phi(bits, m) - phi(bits, m + 1);
+    let chi_square = 2.0 * n * (LN_2 - ap_en);
+    chi_square_p_value(2f64.powi(m as i32), chi_square)
+}
+
+/// Upper tail of the chi-square distribution; `gamma_ur` rejects x = 0
+fn chi_square_p_value(degrees: f64, chi_square: f64) -> f64 {
+    if chi_square <= 0.0 {
+        return 1.0;
+    }
+    gamma_ur(degrees / 2.0, chi_square / 2.0)
+}
+
+/// Σ π ln π over the frequencies of overlapping `m`-bit patterns, wrapping
+/// around the end of the sequence
+fn phi(bits: &[bool], m: usize) -> f64 {
+    let n = bits.len();
+    let mut counts = vec![0usize; 1 << m];
+    for start in 0..n {
+        let pattern = (0..m).fold(0, |acc, k| (acc << 1) | bits[(start + k) % n] as usize);
+        counts[pattern] += 1;
+    }
+    counts.iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / n as f64;
+            p * p.ln()
+        })
+        .sum()
+}
+
+/// Cumulative sums test, 2.13: does the ±1 random walk stray too far from
+/// zero, starting from the front (`forward`) or the back?
+pub fn cumulative_sums_test(bits: &[bool], forward: bool) -> f64 {
+    if bits.is_empty() {
+        return 0.0;
+    }
+    let step = |&b: &bool| if b { 1i64 } else { -1 };
+    let mut sum = 0i64;
+    let mut z = 0i64;
+    let mut walk = |s: i64| {
+        sum += s;
+        z = z.max(sum.abs());
+    };
+    if forward {
+        bits.iter().map(step).for_each(&mut walk);
+    } else {
+        bits.iter().rev().map(step).for_each(&mut walk);
+    }
+
+    let n = bits.len() as f64;
+    let z = z as f64;
+    let sqrt_n = n.sqrt();
+    // Truncating casts, as in the reference implementation
+    let terms = |from: f64, to: f64, hi: f64, lo: f64| -> f64 {
+        (from as i64..=to as i64)
+            .map(|k| {
+                let k = k as f64;
+                normal_cdf((4.0 * k + hi) * z / sqrt_n) - normal_cdf((4.0 * k + lo) * z / sqrt_n)
+            })
+            .sum()
+    };
+    let p = 1.0
+        - terms((-n / z + 1.0) / 4.0, (n / z - 1.0) / 4.0, 1.0, -1.0)
+        + terms((-n / z - 3.0) / 4.0, (n / z - 1.0) / 4.0, 3.0, 1.0);
+    p.clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+
+    fn parse(bits: &str) -> Vec<bool> {
+        bits.chars().map(|c| c == '1').collect()
+    }
+
+    #[test]
+    fn test_matches_sp800_22_examples() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-4;
+        assert!(close(frequency_test(&parse("1011010101")), 0.527089));
+        assert!(close(runs_test(&parse("1001101011")), 0.147232));
+        assert!(close(approximate_entropy_test(&parse("0100110101"), 3), 0.261961));
+        assert!(close(cumulative_sums_test(&parse("1011010111"), true), 0.4116588));
+    }
+
+    #[test]
+    fn test_biased_stream_fails_frequency() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(22);
+        let fair: Vec<f64> = (0..500).map(|_| rng.gen()).collect();
+        let fair = nist_battery(&bits_from_unit_samples(&fair, 8));
+        assert_eq!(fair.bits, 4000);
+        assert_eq!(fair.tests.len(), 6);
+
+        // Ones 55% of the time
+        let biased: Vec<bool> = (0..4000).map(|_| rng.gen_bool(0.55)).collect();
+        let biased = nist_battery(&biased);
+        assert!(biased.failures().any(|t| t.kind == RandomnessTestKind::Frequency));
+        assert!(biased.anomaly_score() > 0.5);
+        assert!(biased.anomaly_score() > fair.anomaly_score());
+    }
+
+    #[test]
+    fn test_monitor_reports_a_biased_source_once() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut monitor = RandomnessMonitor::new(2000);
+
+        let fair: Vec<bool> = (0..1000).map(|_| rng.gen()).collect();
+        // Half a block is not tested yet
+        assert!(monitor.push(&fair).is_none());
+        assert!(!monitor.is_failing());
+
+        // Ones 60% of the time, over three blocks
+        let biased: Vec<bool> = (0..6000).map(|_| rng.gen_bool(0.6)).collect();
+        let onsets: Vec<RandomnessReport> = biased.chunks(500)
+            .filter_map(|chunk| monitor.push(chunk))
+            .collect();
+        assert_eq!(onsets.len(), 1);
+        assert!(onsets[0].failures().any(|t| t.kind == RandomnessTestKind::Frequency));
+        assert!(monitor.is_failing());
+    }
+}
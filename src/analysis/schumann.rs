@@ -0,0 +1,261 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Schumann resonance tracking for infrasound
+//!
+//! Samples are block-averaged down to about `TRACKING_RATE_HZ` and the last
+//! `WINDOW_SECONDS` are kept. On each update every mode is searched within
+//! `SEARCH_HALF_WIDTH_HZ` of its nominal frequency with the Goertzel filter
+//! over the Hann-windowed buffer, and the strongest probe is refined by
+//! fitting a parabola to the log power of it and its neighbours. Amplitudes
+//! are corrected for the window and for the block average's roll-off.
+//!
+//! Each mode's baseline follows its amplitude, more slowly than the window
+//! turns over, except during an excursion. An excursion that outlasts
+//! `WINDOW_SECONDS` is taken as the mode's new level and becomes the
+//! baseline.
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use serde::{Deserialize, Serialize};
+
+use super::{AnalysisConfig, SignalProcessor, WindowFunction};
+
+/// Nominal frequencies of the first Schumann modes, Hz
+pub const SCHUMANN_MODES: [f64; 3] = [7.83, 14.3, 20.8];
+
+/// Amplitude change from a mode's baseline that counts as an excursion
+pub const SCHUMANN_EXCURSION_DB: f64 = 6.0;
+
+/// Rate the input is decimated to; comfortably above twice the top mode
+const TRACKING_RATE_HZ: f64 = 100.0;
+
+/// Analysis window; frequency resolution is roughly 2 / WINDOW_SECONDS
+const WINDOW_SECONDS: f64 = 20.0;
+
+/// Data needed before the first estimate
+const MIN_SECONDS: f64 = 4.0;
+
+const SEARCH_HALF_WIDTH_HZ: f64 = 1.0;
+const SEARCH_STEP_HZ: f64 = 0.05;
+
+/// Time constant of a mode's (log) baseline; a few windows long, so a
+/// change that takes a window to fill in still stands out from it
+const BASELINE_SECONDS: f64 = 60.0;
+
+/// Level relative to the window's RMS below which a mode counts as absent,
+/// so spectral leakage doesn't register as excursions
+const ABSENT_MODE_DB: f64 = -80.0;
+
+/// How long an excursion lasts before the baseline follows it
+const SUSTAINED_EXCURSION_SECONDS: f64 = WINDOW_SECONDS;
+
+/// Latest estimate of each Schumann mode, in `SCHUMANN_MODES` order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SchumannState {
+    pub mode_freqs: Vec<f64>,
+    pub mode_amplitudes: Vec<f64>,
+    /// Usual amplitude of each mode, excluding excursions
+    pub baseline_amplitudes: Vec<f64>,
+    /// Modes whose amplitude is more than `SCHUMANN_EXCURSION_DB` off baseline
+    pub excursions: Vec<bool>,
+}
+
+impl SchumannState {
+    pub fn has_excursion(&self) -> bool {
+        self.excursions.iter().any(|&e| e)
+    }
+}
+
+/// Continuously estimates the Schumann modes of one infrasound stream
+pub struct SchumannTracker {
+    signal: SignalProcessor,
+    sample_rate: f64,
+    decimation: usize,
+    // Running sum and count of the block being averaged
+    block: (f64, usize),
+    samples: VecDeque<f64>,
+    capacity: usize,
+    // Decimated samples taken so far, and by the last estimate
+    taken: u64,
+    estimated: u64,
+    baselines: Vec<Option<f64>>,
+    // Sample count at which each mode's current excursion began
+    excursion_since: Vec<Option<u64>>,
+    state: Option<SchumannState>,
+}
+
+impl SchumannTracker {
+    pub fn new(sample_rate: f64) -> Self {
+        let decimation = ((sample_rate / TRACKING_RATE_HZ).floor() as usize).max(1);
+        let capacity = (WINDOW_SECONDS * sample_rate / decimation as f64) as usize;
+        Self {
+            signal: SignalProcessor::new(AnalysisConfig::default()),
+            sample_rate,
+            decimation,
+            block: (0.0, 0),
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            taken: 0,
+            estimated: 0,
+            baselines: vec![None; SCHUMANN_MODES.len()],
+            excursion_since: vec![None; SCHUMANN_MODES.len()],
+            state: None,
+        }
+    }
+    
+    /// Rate of the raw samples the tracker was built for
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+    
+    /// Rate of the decimated samples the modes are estimated from
+    pub fn tracking_rate(&self) -> f64 {
+        self.sample_rate / self.decimation as f64
+    }
+    
+    /// Add consecutive samples and re-estimate. `None` until `MIN_SECONDS`
+    /// of data have arrived.
+    pub fn push(&mut self, data: &[f64]) -> Option<&SchumannState> {
+        for &x in data {
+            self.block.0 += x;
+            self.block.1 += 1;
+            if self.block.1 == self.decimation {
+                if self.samples.len() == self.capacity {
+                    self.samples.pop_front();
+                }
+                self.samples.push_back(self.block.0 / self.decimation as f64);
+                self.taken += 1;
+                self.block = (0.0, 0);
+            }
+        }
+        
+        if (self.samples.len() as f64) < MIN_SECONDS * self.tracking_rate() {
+            return None;
+        }
+        self.estimate();
+        self.state.as_ref()
+    }
+    
+    pub fn state(&self) -> Option<&SchumannState> {
+        self.state.as_ref()
+    }
+    
+    fn estimate(&mut self) {
+        let rate = self.tracking_rate();
+        let windowed = WindowFunction::Hann.apply(self.samples.make_contiguous());
+        let rms = (self.samples.iter().map(|x| x * x).sum::<f64>() / self.samples.len() as f64).sqrt();
+        let absent = (rms * 10f64.powf(ABSENT_MODE_DB / 20.0)).max(1e-300);
+        let elapsed = (self.taken - self.estimated) as f64 / rate;
+        let alpha = 1.0 - (-elapsed / BASELINE_SECONDS).exp();
+        self.estimated = self.taken;
+        
+        let mut state = SchumannState::default();
+        for (mode, &nominal) in SCHUMANN_MODES.iter().enumerate() {
+            let (freq, amplitude) = if nominal + SEARCH_HALF_WIDTH_HZ < rate / 2.0 {
+                self.locate(&windowed, rate, nominal)
+            } else {
+                (nominal, 0.0)
+            };
+            
+            let baseline = *self.baselines[mode].get_or_insert(amplitude);
+            let excursion_db = 20.0 * (amplitude.max(absent) / baseline.max(absent)).log10();
+            let excursion = excursion_db.abs() > SCHUMANN_EXCURSION_DB;
+            if !excursion {
+                self.excursion_since[mode] = None;
+                let log_baseline = baseline.max(1e-300).ln();
+                let updated = log_baseline + alpha * (amplitude.max(1e-300).ln() - log_baseline);
+                self.baselines[mode] = Some(updated.exp());
+            } else {
+                let since = *self.excursion_since[mode].get_or_insert(self.taken);
+                if (self.taken - since) as f64 >= SUSTAINED_EXCURSION_SECONDS * rate {
+                    self.excursion_since[mode] = None;
+                    self.baselines[mode] = Some(amplitude);
+                }
+            }
+            
+            state.mode_freqs.push(freq);
+            state.mode_amplitudes.push(amplitude);
+            state.baseline_amplitudes.push(baseline);
+            state.excursions.push(excursion);
+        }
+        self.state = Some(state);
+    }
+    
+    /// Frequency and amplitude of the strongest tone near `nominal`
+    fn locate(&self, windowed: &[f64], rate: f64, nominal: f64) -> (f64, f64) {
+        let probes = (2.0 * SEARCH_HALF_WIDTH_HZ / SEARCH_STEP_HZ).round() as usize + 1;
+        let start = nominal - SEARCH_HALF_WIDTH_HZ;
+        let power: Vec<f64> = (0..probes)
+            .map(|k| self.signal.goertzel(windowed, rate, start + k as f64 * SEARCH_STEP_HZ))
+            .collect();
+        
+        // Strongest interior probe, so it has a neighbour on each side
+        let peak = (1..probes - 1)
+            .max_by(|&a, &b| power[a].total_cmp(&power[b]))
+            .unwrap_or(probes / 2);
+        let [left, centre, right] = [power[peak - 1], power[peak], power[peak + 1]].map(|p| p.max(1e-300).ln());
+        let curvature = left - 2.0 * centre + right;
+        let offset = if curvature < 0.0 {
+            (0.5 * (left - right) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+        let freq = start + (peak as f64 + offset) * SEARCH_STEP_HZ;
+        
+        // Hann coherent gain is 1/2; the block mean rolls off as a Dirichlet kernel
+        let amplitude = 2.0 * self.signal.goertzel(windowed, rate, freq).sqrt();
+        let d = self.decimation as f64;
+        let x = PI * freq / self.sample_rate;
+        let roll_off = if self.decimation > 1 { ((d * x).sin() / (d * x.sin())).abs() } else { 1.0 };
+        (freq, amplitude / roll_off.max(1e-10))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_locks_onto_and_tracks_fundamental() {
+        let sample_rate = 1000.0;
+        let mut tracker = SchumannTracker::new(sample_rate);
+        let mut t = 0.0;
+        let mut feed = |tracker: &mut SchumannTracker, freq: f64, amplitude: f64, seconds: usize| {
+            let mut state = None;
+            for _ in 0..seconds * 10 {
+                let chunk: Vec<f64> = (0..100)
+                    .map(|i| {
+                        let s = t + i as f64 / sample_rate;
+                        amplitude * (2.0 * PI * freq * s).sin() + 0.0002 * (2.0 * PI * 14.3 * s).sin()
+                    })
+                    .collect();
+                t += 0.1;
+                state = tracker.push(&chunk).cloned();
+            }
+            state.unwrap()
+        };
+        
+        let state = feed(&mut tracker, 7.83, 0.0003, 25);
+        assert!((state.mode_freqs[0] - 7.83).abs() < 0.005, "{:?}", state);
+        assert!((state.mode_amplitudes[0] / 0.0003 - 1.0).abs() < 0.03, "{:?}", state);
+        assert!((state.mode_freqs[1] - 14.3).abs() < 0.005, "{:?}", state);
+        assert!(!state.has_excursion());
+        
+        // A 0.12 Hz shift, once the window has turned over
+        let state = feed(&mut tracker, 7.95, 0.0003, 21);
+        assert!((state.mode_freqs[0] - 7.95).abs() < 0.005, "{:?}", state);
+        assert!(!state.excursions[0]);
+        
+        // Quadrupled amplitude is a 12 dB excursion
+        let state = feed(&mut tracker, 7.95, 0.0012, 21);
+        assert!(state.excursions[0]);
+        assert!(!state.excursions[1]);
+        
+        // Kept up, it becomes the new baseline
+        let state = feed(&mut tracker, 7.95, 0.0012, 20);
+        assert!(!state.excursions[0], "{:?}", state);
+        assert!((state.baseline_amplitudes[0] / 0.0012 - 1.0).abs() < 0.05, "{:?}", state);
+    }
+}
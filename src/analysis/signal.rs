@@ -4,12 +4,20 @@
 
 //! Signal processing - FFT, filtering, feature extraction
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::f64::consts::PI;
-use rustfft::{FftPlanner, num_complex::Complex};
+use std::sync::Arc;
+use nalgebra::DMatrix;
+use parking_lot::RwLock;
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
 use serde::{Deserialize, Serialize};
 
 use super::AnalysisConfig;
 
+/// Upper edge of the band kept after heterodyning
+const AUDIBLE_MAX_HZ: f64 = 20_000.0;
+
 /// Signal features extracted from waveform
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SignalFeatures {
@@ -36,14 +44,107 @@ pub struct SignalFeatures {
     pub decay_time: f64,
 }
 
+/// Taper applied to a block of samples before its FFT to limit spectral
+/// leakage
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowFunction {
+    /// No taper; narrowest main lobe, most leakage
+    Rectangular,
+    #[default]
+    Hann,
+    Hamming,
+    /// Lowest sidelobes of the four, widest main lobe
+    Blackman,
+}
+
+impl WindowFunction {
+    /// Weight of sample `i` of an `n`-sample block
+    pub fn weight(self, i: usize, n: usize) -> f64 {
+        if n < 2 {
+            return 1.0;
+        }
+        let phase = 2.0 * PI * i as f64 / (n - 1) as f64;
+        match self {
+            WindowFunction::Rectangular => 1.0,
+            WindowFunction::Hann => 0.5 * (1.0 - phase.cos()),
+            WindowFunction::Hamming => 0.54 - 0.46 * phase.cos(),
+            WindowFunction::Blackman => 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos(),
+        }
+    }
+    
+    /// `data` multiplied by the window
+    pub fn apply(self, data: &[f64]) -> Vec<f64> {
+        data.iter().enumerate().map(|(i, &x)| x * self.weight(i, data.len())).collect()
+    }
+}
+
+thread_local! {
+    /// Planners can't be shared between threads, so each thread plans with its own
+    static FFT_PLANNER: RefCell<FftPlanner<f64>> = RefCell::new(FftPlanner::new());
+}
+
+/// FFT plans cached by size
+///
+/// Planning allocates twiddle factors, so re-planning for every window is
+/// a hotspot at high sample rates. Plans are shared between threads;
+/// missing ones are built with the calling thread's planner.
+#[derive(Default)]
+pub struct FftPlanCache {
+    forward: RwLock<HashMap<usize, Arc<dyn Fft<f64>>>>,
+    inverse: RwLock<HashMap<usize, Arc<dyn Fft<f64>>>>,
+}
+
+impl FftPlanCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Forward FFT of length `n`
+    pub fn forward(&self, n: usize) -> Arc<dyn Fft<f64>> {
+        Self::plan(&self.forward, n, |planner| planner.plan_fft_forward(n))
+    }
+    
+    /// Inverse FFT of length `n`
+    pub fn inverse(&self, n: usize) -> Arc<dyn Fft<f64>> {
+        Self::plan(&self.inverse, n, |planner| planner.plan_fft_inverse(n))
+    }
+    
+    /// Number of cached plans
+    pub fn len(&self) -> usize {
+        self.forward.read().len() + self.inverse.read().len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    fn plan(
+        cache: &RwLock<HashMap<usize, Arc<dyn Fft<f64>>>>,
+        n: usize,
+        make: impl FnOnce(&mut FftPlanner<f64>) -> Arc<dyn Fft<f64>>,
+    ) -> Arc<dyn Fft<f64>> {
+        if let Some(fft) = cache.read().get(&n) {
+            return fft.clone();
+        }
+        let fft = FFT_PLANNER.with(|planner| make(&mut planner.borrow_mut()));
+        cache.write().entry(n).or_insert(fft).clone()
+    }
+}
+
 /// Signal processor for waveform analysis
 pub struct SignalProcessor {
     config: AnalysisConfig,
+    fft_plans: FftPlanCache,
 }
 
 impl SignalProcessor {
     pub fn new(config: AnalysisConfig) -> Self {
-        Self { config }
+        Self { config, fft_plans: FftPlanCache::new() }
+    }
+    
+    /// Plans reused across calls, e.g. for spectra computed outside this type
+    pub fn fft_plans(&self) -> &FftPlanCache {
+        &self.fft_plans
     }
     
     pub fn extract_features(&self, data: &[f64], sample_rate: f64) -> SignalFeatures {
@@ -110,9 +211,7 @@ impl SignalProcessor {
         let n = data.len().next_power_of_two();
         
         // Window the data (Hann window)
-        let windowed: Vec<f64> = data.iter().enumerate()
-            .map(|(i, &x)| x * 0.5 * (1.0 - (2.0 * PI * i as f64 / (data.len() - 1) as f64).cos()))
-            .collect();
+        let windowed = WindowFunction::Hann.apply(data);
         
         // FFT
         let mut buffer: Vec<Complex<f64>> = windowed.iter()
@@ -120,9 +219,7 @@ impl SignalProcessor {
             .collect();
         buffer.resize(n, Complex::new(0.0, 0.0));
         
-        let mut planner = FftPlanner::new();
-        let fft = planner.plan_fft_forward(n);
-        fft.process(&mut buffer);
+        self.fft_plans.forward(n).process(&mut buffer);
         
         // Power spectrum (positive frequencies only)
         let power: Vec<f64> = buffer[0..n/2].iter()
@@ -139,7 +236,7 @@ impl SignalProcessor {
         
         // Dominant frequency
         let (max_idx, _) = power.iter().enumerate()
-            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
             .unwrap();
         let dominant_frequency = max_idx as f64 * freq_resolution;
         
@@ -228,7 +325,7 @@ impl SignalProcessor {
         
         // Decay time (time from peak to 10% of max)
         let (peak_idx, _) = envelope.iter().enumerate()
-            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
             .unwrap_or((0, &0.0));
         
         let decay_threshold = 0.1 * max_env;
@@ -292,13 +389,159 @@ impl SignalProcessor {
         output
     }
     
+    /// Shift `data` down by `shift_hz`, as a bat detector does: mix it with a
+    /// `shift_hz` local oscillator and low-pass away the sum band, so a tone
+    /// at f comes out at |f - shift_hz| with its amplitude kept.
+    ///
+    /// The cut-off is the lowest of `shift_hz`, the top of the audible band
+    /// and 0.45 of `sample_rate`, applied twice for 24 dB/octave. Brings
+    /// ultrasonic bursts into hearing range for WAV export, and into bins
+    /// of their own for spectral analysis.
+    pub fn heterodyne(&self, data: &[f64], sample_rate: f64, shift_hz: f64) -> Vec<f64> {
+        if shift_hz <= 0.0 || sample_rate <= 0.0 {
+            return data.to_vec();
+        }
+        
+        let step = 2.0 * PI * shift_hz / sample_rate;
+        let mixed: Vec<f64> = data.iter()
+            .enumerate()
+            .map(|(i, &x)| 2.0 * x * (step * i as f64).cos())
+            .collect();
+        
+        // The sum band starts at shift_hz, so cut below it
+        let cutoff = shift_hz.min(AUDIBLE_MAX_HZ).min(0.45 * sample_rate);
+        lowpass(&lowpass(&mixed, sample_rate, cutoff), sample_rate, cutoff)
+    }
+    
+    /// Savitzky-Golay smoothing: a least-squares polynomial fit over a
+    /// sliding window.
+    ///
+    /// Unlike a moving average this has no phase lag and keeps peaks and
+    /// trends of degree up to `poly_order` intact. The first and last
+    /// `window / 2` samples are read off the fit of the first and last full
+    /// window. `window` must be odd and larger than `poly_order`; otherwise,
+    /// or if `data` is shorter than `window`, the data is returned as is.
+    pub fn savitzky_golay(&self, data: &[f64], window: usize, poly_order: usize) -> Vec<f64> {
+        let n = data.len();
+        if window % 2 == 0 || window <= poly_order || n < window {
+            return data.to_vec();
+        }
+        let half = window / 2;
+        
+        // Vandermonde matrix over offsets scaled to [-1, 1] for conditioning;
+        // the fitted values don't depend on the scaling
+        let scale = half.max(1) as f64;
+        let design = DMatrix::from_fn(window, poly_order + 1, |i, j| {
+            ((i as f64 - half as f64) / scale).powi(j as i32)
+        });
+        let Ok(fit) = design.clone().pseudo_inverse(1e-12) else {
+            return data.to_vec();
+        };
+        // Row r maps a window of samples to the fitted value at position r
+        let hat = &design * fit;
+        
+        let smooth = |start: usize, row: usize| -> f64 {
+            (0..window).map(|k| hat[(row, k)] * data[start + k]).sum()
+        };
+        
+        (0..n)
+            .map(|i| {
+                if i < half {
+                    smooth(0, i)
+                } else if i + half >= n {
+                    smooth(n - window, i + window - n)
+                } else {
+                    smooth(i - half, half)
+                }
+            })
+            .collect()
+    }
+    
+    /// Power at a single frequency (Goertzel algorithm).
+    ///
+    /// O(n) with no FFT or allocation, so watching a handful of known
+    /// frequencies is cheaper than a full spectrum. `target_freq` need not
+    /// fall on an FFT bin. Scaled so a tone of amplitude A reports about A².
+    pub fn goertzel(&self, data: &[f64], sample_rate: f64, target_freq: f64) -> f64 {
+        if data.is_empty() || sample_rate <= 0.0 {
+            return 0.0;
+        }
+        
+        let coeff = 2.0 * (2.0 * PI * target_freq / sample_rate).cos();
+        let (mut s1, mut s2) = (0.0, 0.0);
+        for &x in data {
+            let s0 = x + coeff * s1 - s2;
+            s2 = s1;
+            s1 = s0;
+        }
+        
+        let power = s1 * s1 + s2 * s2 - coeff * s1 * s2;
+        let n = data.len() as f64;
+        4.0 * power / (n * n)
+    }
+    
+    /// Normalized cross-correlation of two equally sampled signals.
+    ///
+    /// Returns the correlation for lags `-max_lag..=max_lag` (index
+    /// `lag + max_lag`), the lag of the peak and the peak value. A positive
+    /// lag means `b` trails `a`: an event in `a` at sample `n` shows up in
+    /// `b` at `n + lag`. Values are normalized so a delayed copy peaks at 1.
+    pub fn cross_correlation(&self, a: &[f64], b: &[f64], max_lag: usize) -> (Vec<f64>, isize, f64) {
+        if a.is_empty() || b.is_empty() {
+            return (Vec::new(), 0, 0.0);
+        }
+        let max_lag = max_lag.min(a.len().max(b.len()) - 1);
+        
+        let centered = |x: &[f64]| {
+            let mean = x.iter().sum::<f64>() / x.len() as f64;
+            x.iter().map(|&v| v - mean).collect::<Vec<f64>>()
+        };
+        let a = centered(a);
+        let b = centered(b);
+        let norm = (a.iter().map(|x| x * x).sum::<f64>() * b.iter().map(|x| x * x).sum::<f64>()).sqrt();
+        if norm < 1e-12 {
+            return (vec![0.0; 2 * max_lag + 1], 0, 0.0);
+        }
+        
+        // Zero-padded so the circular correlation equals the linear one
+        let n_fft = (a.len() + b.len()).next_power_of_two();
+        let forward = self.fft_plans.forward(n_fft);
+        let inverse = self.fft_plans.inverse(n_fft);
+        
+        let spectrum = |x: &[f64]| {
+            let mut buffer: Vec<Complex<f64>> = x.iter().map(|&v| Complex::new(v, 0.0)).collect();
+            buffer.resize(n_fft, Complex::new(0.0, 0.0));
+            forward.process(&mut buffer);
+            buffer
+        };
+        let spec_a = spectrum(&a);
+        let mut product: Vec<Complex<f64>> = spectrum(&b).iter()
+            .zip(spec_a.iter())
+            .map(|(y, x)| x.conj() * y)
+            .collect();
+        inverse.process(&mut product);
+        
+        // Index k holds sum(a[n] * b[n + k]); negative lags wrap to the end
+        let scale = n_fft as f64 * norm;
+        let correlation: Vec<f64> = (-(max_lag as isize)..=max_lag as isize)
+            .map(|lag| product[lag.rem_euclid(n_fft as isize) as usize].re / scale)
+            .collect();
+        
+        let (peak_index, peak) = correlation.iter()
+            .copied()
+            .enumerate()
+            .max_by(|x, y| x.1.total_cmp(&y.1))
+            .expect("at least one lag");
+        
+        (correlation, peak_index as isize - max_lag as isize, peak)
+    }
+    
     /// Compute spectrogram
     pub fn spectrogram(&self, data: &[f64], sample_rate: f64, window_size: usize, hop_size: usize) -> Vec<Vec<f64>> {
         let mut spectrogram = Vec::new();
         let n_fft = window_size.next_power_of_two();
         
-        let mut planner = FftPlanner::new();
-        let fft = planner.plan_fft_forward(n_fft);
+        let fft = self.fft_plans.forward(n_fft);
         
         let hann: Vec<f64> = (0..window_size)
             .map(|i| 0.5 * (1.0 - (2.0 * PI * i as f64 / (window_size - 1) as f64).cos()))
@@ -325,3 +568,137 @@ impl SignalProcessor {
         spectrogram
     }
 }
+
+/// Second-order Butterworth low-pass
+fn lowpass(data: &[f64], sample_rate: f64, cutoff_hz: f64) -> Vec<f64> {
+    let w0 = 2.0 * PI * cutoff_hz / sample_rate;
+    let alpha = w0.sin() / std::f64::consts::SQRT_2;
+    let a0 = 1.0 + alpha;
+    let b0 = (1.0 - w0.cos()) / 2.0 / a0;
+    let b1 = (1.0 - w0.cos()) / a0;
+    let a1 = -2.0 * w0.cos() / a0;
+    let a2 = (1.0 - alpha) / a0;
+    
+    let (mut x1, mut x2, mut y1, mut y2) = (0.0, 0.0, 0.0, 0.0);
+    data.iter()
+        .map(|&x| {
+            let y = b0 * x + b1 * x1 + b0 * x2 - a1 * y1 - a2 * y2;
+            (x2, x1, y2, y1) = (x1, x, y1, y);
+            y
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_distr::{Distribution, Normal};
+    
+    #[test]
+    fn test_fft_plans_are_reused() {
+        let processor = SignalProcessor::new(AnalysisConfig::default());
+        let data: Vec<f64> = (0..1000).map(|i| (i as f64 * 0.1).sin()).collect();
+        
+        let first = processor.extract_features(&data, 100.0);
+        assert_eq!(processor.fft_plans().len(), 1);
+        let second = processor.extract_features(&data, 100.0);
+        assert_eq!(processor.fft_plans().len(), 1);
+        assert_eq!(first.dominant_frequency, second.dominant_frequency);
+        
+        let plans = processor.fft_plans();
+        assert!(Arc::ptr_eq(&plans.forward(1024), &plans.forward(1024)));
+        
+        // Plans built on other threads land in the same cache
+        std::thread::scope(|scope| {
+            scope.spawn(|| plans.forward(512));
+        });
+        assert_eq!(plans.len(), 2);
+    }
+    
+    #[test]
+    fn test_cross_correlation_finds_delay() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let a: Vec<f64> = (0..500).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        let delay = 7;
+        let mut b = vec![0.0; delay];
+        b.extend_from_slice(&a[..a.len() - delay]);
+        
+        let processor = SignalProcessor::new(AnalysisConfig::default());
+        let (correlation, lag, peak) = processor.cross_correlation(&a, &b, 20);
+        assert_eq!(correlation.len(), 41);
+        assert_eq!(lag, 7);
+        assert!(peak > 0.9, "peak {}", peak);
+        
+        // Swapping the signals flips which one leads
+        let (_, lag, _) = processor.cross_correlation(&b, &a, 20);
+        assert_eq!(lag, -7);
+    }
+    
+    #[test]
+    fn test_savitzky_golay_keeps_polynomials_and_smooths_noise() {
+        let processor = SignalProcessor::new(AnalysisConfig::default());
+        
+        // A cubic passes through a cubic fit untouched, edges included
+        let cubic: Vec<f64> = (0..60)
+            .map(|i| {
+                let t = i as f64 * 0.1;
+                0.5 * t * t * t - 2.0 * t * t + t - 3.0
+            })
+            .collect();
+        let smoothed = processor.savitzky_golay(&cubic, 11, 3);
+        for (x, y) in cubic.iter().zip(&smoothed) {
+            assert!((x - y).abs() < 1e-8, "{} became {}", x, y);
+        }
+        
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let noise = Normal::new(0.0, 1.0).unwrap();
+        let noisy: Vec<f64> = (0..2000).map(|_| noise.sample(&mut rng)).collect();
+        let smoothed = processor.savitzky_golay(&noisy, 21, 2);
+        let variance = |x: &[f64]| x.iter().map(|v| v * v).sum::<f64>() / x.len() as f64;
+        assert!(variance(&smoothed) < 0.3 * variance(&noisy));
+        
+        // Even windows are rejected
+        assert_eq!(processor.savitzky_golay(&cubic, 10, 3), cubic);
+    }
+    
+    #[test]
+    fn test_goertzel_picks_out_tone() {
+        let sample_rate = 250.0;
+        let tone: Vec<f64> = (0..1000)
+            .map(|i| 2.0 * (2.0 * PI * 7.83 * i as f64 / sample_rate).sin())
+            .collect();
+        
+        let processor = SignalProcessor::new(AnalysisConfig::default());
+        let present = processor.goertzel(&tone, sample_rate, 7.83);
+        assert!((present - 4.0).abs() < 0.2, "power at tone {}", present);
+        
+        let absent = processor.goertzel(&tone, sample_rate, 60.0);
+        assert!(absent < 1e-3, "power off tone {}", absent);
+    }
+    
+    #[test]
+    fn test_heterodyne_brings_ultrasound_down() {
+        let sample_rate = 192_000.0;
+        let tone = |freq: f64, amplitude: f64, i: usize| amplitude * (2.0 * PI * freq * i as f64 / sample_rate).sin();
+        let burst: Vec<f64> = (0..16384).map(|i| tone(40_000.0, 1.0, i) + tone(45_000.0, 0.5, i)).collect();
+        
+        let processor = SignalProcessor::new(AnalysisConfig::default());
+        let shifted = processor.heterodyne(&burst, sample_rate, 38_000.0);
+        assert_eq!(shifted.len(), burst.len());
+        
+        // 40 kHz lands at 2 kHz, in the output spectrum's strongest bin
+        let features = processor.extract_features(&shifted, sample_rate);
+        let resolution = sample_rate / 16384.0;
+        assert!((features.dominant_frequency - 2000.0).abs() <= resolution, "peak at {}", features.dominant_frequency);
+        
+        // The two tones keep their relative levels, and the sum band is gone.
+        // Measured over whole cycles of both, once the filter has settled.
+        let settled = &shifted[shifted.len() - 75 * 192..];
+        let at = |freq| processor.goertzel(settled, sample_rate, freq);
+        let ratio = at(2000.0) / at(7000.0);
+        assert!((ratio - 4.0).abs() < 0.4, "power ratio {}", ratio);
+        assert!(at(2000.0) > 1000.0 * at(78_000.0));
+        assert!((at(2000.0) - 1.0).abs() < 0.1, "power at 2 kHz {}", at(2000.0));
+    }
+}
@@ -6,6 +6,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use super::normal_cdf;
+
 /// Statistical summary
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StatisticalSummary {
@@ -43,7 +45,7 @@ impl StatisticalAnalyzer {
         let mean = data.iter().sum::<f64>() / count as f64;
         
         let mut sorted = data.to_vec();
-        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        sorted.sort_by(|a, b| a.total_cmp(b));
         
         let median = if count % 2 == 0 {
             (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0
@@ -109,7 +111,8 @@ impl StatisticalAnalyzer {
         }
     }
     
-    fn percentile(&self, sorted: &[f64], p: f64) -> f64 {
+    /// `p`th percentile (0-100) of already sorted data, interpolated linearly
+    pub fn percentile(&self, sorted: &[f64], p: f64) -> f64 {
         if sorted.is_empty() {
             return 0.0;
         }
@@ -206,7 +209,7 @@ impl StatisticalAnalyzer {
     fn t_distribution_p_value(&self, t: f64, df: f64) -> f64 {
         // Approximation using normal distribution for large df
         if df > 30.0 {
-            return 2.0 * self.normal_cdf(-t.abs());
+            return 2.0 * normal_cdf(-t.abs());
         }
         
         // Beta function approximation for small df
@@ -215,27 +218,6 @@ impl StatisticalAnalyzer {
         2.0 * p
     }
     
-    fn normal_cdf(&self, x: f64) -> f64 {
-        0.5 * (1.0 + self.erf(x / std::f64::consts::SQRT_2))
-    }
-    
-    fn erf(&self, x: f64) -> f64 {
-        // Approximation
-        let a1 = 0.254829592;
-        let a2 = -0.284496736;
-        let a3 = 1.421413741;
-        let a4 = -1.453152027;
-        let a5 = 1.061405429;
-        let p = 0.3275911;
-        
-        let sign = if x < 0.0 { -1.0 } else { 1.0 };
-        let x = x.abs();
-        let t = 1.0 / (1.0 + p * x);
-        let y = 1.0 - (((((a5 * t + a4) * t) + a3) * t + a2) * t + a1) * t * (-x * x).exp();
-        
-        sign * y
-    }
-    
     fn regularized_beta(&self, x: f64, a: f64, b: f64) -> f64 {
         // Simplified approximation
         if x <= 0.0 { return 0.0; }
@@ -304,7 +286,7 @@ impl StatisticalAnalyzer {
             .map(|&x| (x, 0usize))
             .chain(sample2.iter().map(|&x| (x, 1usize)))
             .collect();
-        combined.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        combined.sort_by(|a, b| a.0.total_cmp(&b.0));
         
         // Assign ranks
         let mut ranks = vec![0.0; combined.len()];
@@ -337,7 +319,7 @@ impl StatisticalAnalyzer {
         let std_u = ((n1 * n2 * (n1 + n2 + 1)) as f64 / 12.0).sqrt();
         
         let z = if std_u > 1e-10 { (u - mean_u) / std_u } else { 0.0 };
-        let p_value = 2.0 * self.normal_cdf(-z.abs());
+        let p_value = 2.0 * normal_cdf(-z.abs());
         
         UTestResult {
             u_statistic: u,
@@ -358,7 +340,7 @@ impl StatisticalAnalyzer {
         
         let n = sample.len();
         let mut sorted = sample.to_vec();
-        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        sorted.sort_by(|a, b| a.total_cmp(b));
         
         let mut d_max: f64 = 0.0;
         
@@ -405,6 +387,86 @@ impl StatisticalAnalyzer {
     }
 }
 
+/// Running mean and variance (Welford's online algorithm).
+///
+/// Updates in O(1) per sample without keeping the samples, and stays
+/// accurate over millions of samples with a large offset where the naive
+/// sum-of-squares formula cancels catastrophically. Stats built on separate
+/// chunks can be combined with `merge`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunningStats {
+    count: u64,
+    mean: f64,
+    /// Sum of squared deviations from the mean
+    m2: f64,
+}
+
+impl RunningStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn from_slice(data: &[f64]) -> Self {
+        let mut stats = Self::new();
+        for &x in data {
+            stats.push(x);
+        }
+        stats
+    }
+    
+    pub fn push(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+    
+    /// Fold in stats gathered over another chunk (Chan et al.)
+    pub fn merge(&mut self, other: &RunningStats) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = *other;
+            return;
+        }
+        
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.count as f64 / count as f64;
+        self.m2 += other.m2 + delta * delta * (self.count as f64 * other.count as f64 / count as f64);
+        self.count = count;
+    }
+    
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+    
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+    
+    /// Sample variance (n - 1 denominator), 0 for fewer than two samples
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        self.m2 / (self.count - 1) as f64
+    }
+    
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+}
+
+impl Extend<f64> for RunningStats {
+    fn extend<I: IntoIterator<Item = f64>>(&mut self, iter: I) {
+        for x in iter {
+            self.push(x);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TTestResult {
     pub t_statistic: f64,
@@ -426,3 +488,52 @@ pub struct KSTestResult {
     pub p_value: f64,
     pub significant: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_distr::{Distribution, Normal};
+    
+    fn batch_mean_variance(data: &[f64]) -> (f64, f64) {
+        let n = data.len() as f64;
+        let mean = data.iter().sum::<f64>() / n;
+        let variance = data.iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        (mean, variance)
+    }
+    
+    #[test]
+    fn test_running_stats_match_batch() {
+        // Large offset, small spread: where sum-of-squares loses everything
+        let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+        let noise = Normal::new(1e9, 0.5).unwrap();
+        let data: Vec<f64> = (0..1_000_000).map(|_| noise.sample(&mut rng)).collect();
+        
+        let stats = RunningStats::from_slice(&data);
+        let (mean, variance) = batch_mean_variance(&data);
+        assert_eq!(stats.count(), data.len() as u64);
+        assert!((stats.mean() - mean).abs() < 1e-3);
+        assert!((stats.variance() - variance).abs() / variance < 1e-6, "{} vs {}", stats.variance(), variance);
+        assert!((stats.std_dev() - 0.5).abs() < 0.01);
+        
+        assert_eq!(RunningStats::from_slice(&[4.0]).variance(), 0.0);
+    }
+    
+    #[test]
+    fn test_merged_halves_equal_whole() {
+        let data: Vec<f64> = (0..1001).map(|i| ((i * 37) % 101) as f64 * 0.25 - 3.0).collect();
+        let (left, right) = data.split_at(400);
+        
+        let mut merged = RunningStats::from_slice(left);
+        merged.merge(&RunningStats::from_slice(right));
+        let whole = RunningStats::from_slice(&data);
+        
+        assert_eq!(merged.count(), whole.count());
+        assert!((merged.mean() - whole.mean()).abs() < 1e-12);
+        assert!((merged.variance() - whole.variance()).abs() < 1e-9);
+        
+        let mut empty = RunningStats::new();
+        empty.merge(&whole);
+        assert_eq!(empty, whole);
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Hot and cold spot segmentation for thermal grids
+//!
+//! A cell is hot above mean + k·σ of the frame and cold below mean - k·σ.
+//! Touching cells of the same kind (sharing an edge) form one blob.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThermalBlobKind {
+    Hot,
+    Cold,
+}
+
+/// Connected region of unusually hot or cold cells
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ThermalBlob {
+    pub kind: ThermalBlobKind,
+    /// Mean column and row of the blob's cells
+    pub centroid: [f32; 2],
+    /// Cells in the blob
+    pub area: usize,
+    /// Hottest temperature of a hot blob, coldest of a cold one
+    pub peak: f32,
+    /// Distance of `peak` from the frame mean, in standard deviations
+    pub sigma: f32,
+}
+
+/// Blobs in a row-major `width` × `height` temperature grid, in order of
+/// their first cell. Cells that aren't finite are ignored; a grid of the
+/// wrong size or with no spread has no blobs.
+pub fn detect_thermal_blobs(data: &[f32], width: usize, height: usize, sigma_threshold: f32) -> Vec<ThermalBlob> {
+    if width * height != data.len() || data.is_empty() {
+        return Vec::new();
+    }
+
+    let finite = || data.iter().filter(|t| t.is_finite()).map(|&t| t as f64);
+    let count = finite().count();
+    if count < 2 {
+        return Vec::new();
+    }
+    let mean = finite().sum::<f64>() / count as f64;
+    let std_dev = (finite().map(|t| (t - mean).powi(2)).sum::<f64>() / count as f64).sqrt();
+    if std_dev < 1e-6 {
+        return Vec::new();
+    }
+
+    let limit = sigma_threshold as f64 * std_dev;
+    let kind_of = |t: f32| {
+        let deviation = t as f64 - mean;
+        if deviation > limit {
+            Some(ThermalBlobKind::Hot)
+        } else if deviation < -limit {
+            Some(ThermalBlobKind::Cold)
+        } else {
+            None
+        }
+    };
+
+    let mut visited = vec![false; data.len()];
+    let mut blobs = Vec::new();
+    let mut stack = Vec::new();
+    for start in 0..data.len() {
+        let Some(kind) = kind_of(data[start]).filter(|_| !visited[start]) else {
+            continue;
+        };
+
+        visited[start] = true;
+        stack.push(start);
+        let (mut area, mut sum_x, mut sum_y) = (0usize, 0.0f64, 0.0f64);
+        let mut peak = data[start];
+        while let Some(cell) = stack.pop() {
+            let (x, y) = (cell % width, cell / width);
+            area += 1;
+            sum_x += x as f64;
+            sum_y += y as f64;
+            peak = match kind {
+                ThermalBlobKind::Hot => peak.max(data[cell]),
+                ThermalBlobKind::Cold => peak.min(data[cell]),
+            };
+
+            let neighbours = [
+                (x > 0).then(|| cell - 1),
+                (x + 1 < width).then(|| cell + 1),
+                (y > 0).then(|| cell - width),
+                (y + 1 < height).then(|| cell + width),
+            ];
+            for next in neighbours.into_iter().flatten() {
+                if !visited[next] && kind_of(data[next]) == Some(kind) {
+                    visited[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+
+        blobs.push(ThermalBlob {
+            kind,
+            centroid: [(sum_x / area as f64) as f32, (sum_y / area as f64) as f32],
+            area,
+            peak,
+            sigma: ((peak as f64 - mean).abs() / std_dev) as f32,
+        });
+    }
+    blobs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_finds_planted_hot_blob() {
+        let (width, height) = (32, 24);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(90640);
+        let mut grid: Vec<f32> = (0..width * height).map(|_| 21.0 + rng.gen_range(-0.2..0.2)).collect();
+
+        // A 3x3 hot patch centred on column 20, row 6
+        for y in 5..=7 {
+            for x in 19..=21 {
+                grid[y * width + x] = 30.0;
+            }
+        }
+        grid[6 * width + 20] = 34.0;
+
+        let blobs = detect_thermal_blobs(&grid, width, height, 3.0);
+        assert_eq!(blobs.len(), 1, "{:?}", blobs);
+        let blob = blobs[0];
+        assert_eq!(blob.kind, ThermalBlobKind::Hot);
+        assert_eq!(blob.area, 9);
+        assert_eq!(blob.centroid, [20.0, 6.0]);
+        assert_eq!(blob.peak, 34.0);
+        assert!(blob.sigma > 3.0);
+
+        assert!(detect_thermal_blobs(&grid, width, height + 1, 3.0).is_empty());
+        assert!(detect_thermal_blobs(&[21.0; 64], 8, 8, 3.0).is_empty());
+    }
+}
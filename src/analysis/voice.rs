@@ -0,0 +1,262 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Voice activity detection for EVP candidates
+//!
+//! Audio is cut into short frames, and a frame is voiced when its energy
+//! stands clear of the sensor's rolling noise floor and its zero crossing
+//! rate is in the range of voiced speech. Runs of voiced frames become
+//! segments, which are kept only if their spectrum looks like formants:
+//! a centroid in the speech band and far from flat. A burst of white noise
+//! fails the flatness gate however loud it is.
+
+use serde::{Deserialize, Serialize};
+
+use super::{AnalysisConfig, RollingBaseline, SignalProcessor};
+
+/// Length of one voice activity frame
+pub const VAD_FRAME_SECS: f64 = 0.02;
+
+/// Decibels above the noise floor that make a frame voiced
+pub const VAD_MARGIN_DB: f64 = 6.0;
+
+/// Zero crossings per second of voiced speech
+pub const VOICE_ZCR_HZ: (f64, f64) = (100.0, 4000.0);
+
+/// Spectral centroid range of formant-bearing speech
+pub const VOICE_CENTROID_HZ: (f64, f64) = (200.0, 3500.0);
+
+/// Flattest spectrum a segment can have, 0 being a pure tone and about
+/// 0.56 white noise
+pub const VOICE_MAX_FLATNESS: f64 = 0.3;
+
+/// Shortest segment reported
+pub const EVP_MIN_SECS: f64 = 0.1;
+
+/// Mean level above the noise floor a segment needs, in decibels
+pub const EVP_MIN_SNR_DB: f64 = 10.0;
+
+/// Quiet frames the rolling noise floor needs before it is trusted over
+/// the window's own quiet frames
+const NOISE_MIN_FRAMES: u64 = 20;
+
+/// Unvoiced frames a segment may bridge, e.g. a stop between syllables
+const MAX_GAP_FRAMES: usize = 1;
+
+/// Speech-like stretch of an audio window
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VoiceSegment {
+    /// Start of the segment, in seconds from the start of the window
+    pub start_secs: f64,
+    /// End of the segment, in seconds from the start of the window
+    pub end_secs: f64,
+    /// Mean level above the noise floor, in decibels
+    pub snr_db: f64,
+    pub spectral_centroid: f64,
+    pub spectral_flatness: f64,
+    pub confidence: f64,
+}
+
+impl VoiceSegment {
+    pub fn duration_secs(&self) -> f64 {
+        self.end_secs - self.start_secs
+    }
+}
+
+/// EVP candidate detector for one audio sensor, keeping its noise floor
+/// from window to window
+pub struct EvpDetector {
+    noise_floor: RollingBaseline,
+    signal: SignalProcessor,
+}
+
+impl Default for EvpDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EvpDetector {
+    pub fn new() -> Self {
+        Self {
+            noise_floor: RollingBaseline::default(),
+            signal: SignalProcessor::new(AnalysisConfig::default()),
+        }
+    }
+
+    /// Detector resuming from a noise floor saved earlier
+    pub fn with_noise_floor(noise_floor: RollingBaseline) -> Self {
+        Self {
+            noise_floor,
+            ..Self::new()
+        }
+    }
+
+    /// Frame level, in decibels, of the sensor's quiet frames so far
+    pub fn noise_floor(&self) -> &RollingBaseline {
+        &self.noise_floor
+    }
+
+    /// Speech-like segments of `samples`, in time order. Frames quieter
+    /// than the voicing threshold are folded into the noise floor.
+    pub fn detect(&mut self, samples: &[f64], sample_rate: f64) -> Vec<VoiceSegment> {
+        let frame_len = (sample_rate * VAD_FRAME_SECS).round() as usize;
+        if frame_len < 8 || samples.len() < frame_len {
+            return Vec::new();
+        }
+
+        let frames: Vec<(f64, f64)> = samples
+            .chunks_exact(frame_len)
+            .map(|frame| (power_db(frame), zero_crossing_rate(frame, sample_rate)))
+            .collect();
+        let (floor, spread) = self.floor(&frames);
+        let threshold = floor + VAD_MARGIN_DB.max(3.0 * spread);
+
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for (i, &(db, zcr)) in frames.iter().enumerate() {
+            if db < threshold {
+                self.noise_floor.push(db);
+            }
+            let voiced = db >= threshold && (VOICE_ZCR_HZ.0..=VOICE_ZCR_HZ.1).contains(&zcr);
+            if !voiced {
+                continue;
+            }
+            match runs.last_mut() {
+                Some((_, end)) if i - *end <= MAX_GAP_FRAMES => *end = i + 1,
+                _ => runs.push((i, i + 1)),
+            }
+        }
+
+        runs.into_iter()
+            .filter_map(|(start, end)| {
+                self.segment(&samples[start * frame_len..end * frame_len], start * frame_len, floor, sample_rate)
+            })
+            .collect()
+    }
+
+    /// Mean and spread of the noise floor, from the rolling baseline once
+    /// it has settled and until then from the quieter half of `frames`
+    fn floor(&self, frames: &[(f64, f64)]) -> (f64, f64) {
+        if self.noise_floor.count >= NOISE_MIN_FRAMES {
+            return (self.noise_floor.mean, self.noise_floor.std_dev());
+        }
+
+        let mut levels: Vec<f64> = frames.iter().map(|&(db, _)| db).collect();
+        levels.sort_by(|a, b| a.total_cmp(b));
+        let mut quiet = RollingBaseline::default();
+        levels[..(levels.len() + 1) / 2].iter().for_each(|&db| quiet.push(db));
+        (quiet.mean, quiet.std_dev())
+    }
+
+    /// The run of voiced samples starting at sample `offset`, if it is long
+    /// and loud enough and its spectrum passes the formant gates
+    fn segment(&self, samples: &[f64], offset: usize, floor: f64, sample_rate: f64) -> Option<VoiceSegment> {
+        let duration = samples.len() as f64 / sample_rate;
+        let snr_db = power_db(samples) - floor;
+        if duration < EVP_MIN_SECS || snr_db < EVP_MIN_SNR_DB {
+            return None;
+        }
+
+        let features = self.signal.extract_features(samples, sample_rate);
+        let centroid = features.spectral_centroid;
+        let flatness = features.spectral_flatness;
+        if !(VOICE_CENTROID_HZ.0..=VOICE_CENTROID_HZ.1).contains(&centroid) || flatness > VOICE_MAX_FLATNESS {
+            return None;
+        }
+
+        // 0.25 right at both gates, approaching 1 for loud, tonal segments
+        let loudness = 1.0 - 0.5 * (-(snr_db - EVP_MIN_SNR_DB) / EVP_MIN_SNR_DB).exp();
+        let tonality = 1.0 - 0.5 * flatness / VOICE_MAX_FLATNESS;
+        let start_secs = offset as f64 / sample_rate;
+        Some(VoiceSegment {
+            start_secs,
+            end_secs: start_secs + duration,
+            snr_db,
+            spectral_centroid: centroid,
+            spectral_flatness: flatness,
+            confidence: loudness * tonality,
+        })
+    }
+}
+
+/// Mean power about the mean, in decibels
+fn power_db(samples: &[f64]) -> f64 {
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let power = samples.iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / n;
+    10.0 * (power + 1e-20).log10()
+}
+
+/// Zero crossings per second about the mean
+fn zero_crossing_rate(samples: &[f64], sample_rate: f64) -> f64 {
+    let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+    let crossings = samples.windows(2).filter(|w| (w[0] - mean) * (w[1] - mean) < 0.0).count();
+    crossings as f64 * sample_rate / samples.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_distr::{Distribution, Normal};
+
+    const SAMPLE_RATE: f64 = 16000.0;
+
+    /// Harmonics of a 120 Hz voice shaped by formants at 700, 1220 and
+    /// 2600 Hz, scaled to an RMS of `rms`
+    fn formant_burst(len: usize, rms: f64) -> Vec<f64> {
+        let burst: Vec<f64> = (0..len)
+            .map(|i| {
+                let t = i as f64 / SAMPLE_RATE;
+                (1..30)
+                    .map(|h| {
+                        let f = 120.0 * h as f64;
+                        let gain: f64 = [(700.0, 1.0), (1220.0, 0.6), (2600.0, 0.3)]
+                            .iter()
+                            .map(|&(formant, g)| g * (-((f - formant) / 150.0).powi(2)).exp())
+                            .sum();
+                        gain * (2.0 * std::f64::consts::PI * f * t + h as f64).sin()
+                    })
+                    .sum()
+            })
+            .collect();
+        let scale = rms / (burst.iter().map(|x| x * x).sum::<f64>() / len as f64).sqrt();
+        burst.into_iter().map(|x| x * scale).collect()
+    }
+
+    #[test]
+    fn test_formant_burst_is_found_and_white_noise_is_not() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1636);
+        let mut noise = |len: usize, sigma: f64| -> Vec<f64> {
+            let normal = Normal::new(0.0, sigma).unwrap();
+            (0..len).map(|_| normal.sample(&mut rng)).collect()
+        };
+        let window = (2.0 * SAMPLE_RATE) as usize;
+        let (start, len) = ((0.8 * SAMPLE_RATE) as usize, (0.4 * SAMPLE_RATE) as usize);
+
+        let mut detector = EvpDetector::new();
+        assert!(detector.detect(&noise(window, 0.01), SAMPLE_RATE).is_empty());
+
+        // A voice 20 dB over the noise floor
+        let mut voiced = noise(window, 0.01);
+        for (x, b) in voiced[start..start + len].iter_mut().zip(formant_burst(len, 0.1)) {
+            *x += b;
+        }
+        let segments = detector.detect(&voiced, SAMPLE_RATE);
+        assert_eq!(segments.len(), 1, "{:?}", segments);
+        let segment = segments[0];
+        assert!((segment.start_secs - 0.8).abs() <= 0.04, "{:?}", segment);
+        assert!((segment.end_secs - 1.2).abs() <= 0.04, "{:?}", segment);
+        assert!(segment.snr_db > 15.0 && segment.spectral_flatness < 0.1, "{:?}", segment);
+        assert!(segment.confidence > 0.6, "{:?}", segment);
+
+        // White noise just as loud stands out from the floor but isn't speech
+        let mut hiss = noise(window, 0.01);
+        for (x, b) in hiss[start..start + len].iter_mut().zip(noise(len, 0.1)) {
+            *x += b;
+        }
+        assert!(detector.detect(&hiss, SAMPLE_RATE).is_empty());
+        assert!(detector.noise_floor().count >= NOISE_MIN_FRAMES);
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Per-sensor sliding windows for sensors with short readings
+//!
+//! An EMF probe or barometer reports one value at a time and an
+//! accelerometer three, far too few for entropy, spectra or patterns. Such
+//! readings are appended to sliding windows of the sensor's recent
+//! samples instead, one per axis, and the windows are analyzed as a reading
+//! of their own once they hold `entropy_window` samples, and again every
+//! time half of them has been replaced.
+
+use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+
+use crate::sensors::SensorReading;
+
+/// Samples a reading needs to be analyzed on its own; the longest any
+/// windowed measure (the Hurst exponent) requires
+pub const MIN_WINDOW_SAMPLES: usize = 32;
+
+/// Fraction of a full window replaced between analyses of it
+const WINDOW_HOP: f64 = 0.5;
+
+/// Recent samples of one sensor, one window per axis
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SampleWindow {
+    #[serde(default)]
+    axes: Vec<VecDeque<f64>>,
+    /// Readings added since the window was last analyzed
+    fresh: usize,
+}
+
+/// Sliding windows keyed by sensor id
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SensorWindows {
+    windows: HashMap<String, SampleWindow>,
+}
+
+impl SensorWindows {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Samples per axis accumulated so far for `sensor_id`
+    pub fn len(&self, sensor_id: &str) -> usize {
+        self.windows.get(sensor_id)
+            .and_then(|w| w.axes.first())
+            .map_or(0, |axis| axis.len())
+    }
+    
+    /// The reading to analyze for `reading`, if any.
+    ///
+    /// A reading of at least `MIN_WINDOW_SAMPLES` is returned unchanged.
+    /// A shorter one is one sample per axis: each value is appended to its
+    /// axis' window of up to `window` samples, and `None` is returned until
+    /// the windows are due. Then a copy of `reading` comes back carrying the
+    /// windows axis after axis, with `dimensions` of `[axes, window]` for
+    /// more than one axis; each axis is sampled at the reading's
+    /// `sample_rate`. A change in the number of axes starts the windows over.
+    pub fn push(&mut self, reading: SensorReading, window: usize) -> Option<SensorReading> {
+        if reading.data.len() >= MIN_WINDOW_SAMPLES {
+            return Some(reading);
+        }
+        
+        let window = window.max(MIN_WINDOW_SAMPLES);
+        let buffer = self.windows.entry(reading.sensor_id.clone()).or_default();
+        if buffer.axes.len() != reading.data.len() {
+            buffer.axes = vec![VecDeque::with_capacity(window); reading.data.len()];
+            buffer.fresh = 0;
+        }
+        for (axis, &x) in buffer.axes.iter_mut().zip(&reading.data) {
+            if axis.len() == window {
+                axis.pop_front();
+            }
+            axis.push_back(x);
+        }
+        buffer.fresh += 1;
+        
+        let filled = buffer.axes.first().map_or(0, |axis| axis.len());
+        let hop = ((window as f64 * WINDOW_HOP) as usize).max(1);
+        if filled < window || buffer.fresh < hop {
+            return None;
+        }
+        buffer.fresh = 0;
+        
+        let dimensions = match buffer.axes.len() {
+            1 => reading.dimensions.clone(),
+            axes => vec![axes, window],
+        };
+        Some(SensorReading {
+            data: buffer.axes.iter().flatten().copied().collect(),
+            dimensions,
+            ..reading
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::SensorType;
+    
+    #[test]
+    fn test_each_axis_gets_its_own_window() {
+        let mut windows = SensorWindows::new();
+        let mut due = None;
+        for t in 0..MIN_WINDOW_SAMPLES {
+            let reading = SensorReading::new("accel", SensorType::Accelerometer, vec![t as f64, -(t as f64), 9.81]);
+            due = windows.push(reading, MIN_WINDOW_SAMPLES);
+        }
+        assert_eq!(windows.len("accel"), MIN_WINDOW_SAMPLES);
+        
+        let due = due.expect("window should be due");
+        assert_eq!(due.dimensions, vec![3, MIN_WINDOW_SAMPLES]);
+        let (x, rest) = due.data.split_at(MIN_WINDOW_SAMPLES);
+        assert_eq!(x[..3], [0.0, 1.0, 2.0]);
+        assert_eq!(rest[..3], [0.0, -1.0, -2.0]);
+        assert!(rest[MIN_WINDOW_SAMPLES..].iter().all(|&z| z == 9.81));
+    }
+}
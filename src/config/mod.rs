@@ -7,10 +7,65 @@
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::security::SecurityConfig;
-use crate::streaming::StreamingConfig;
+use crate::analysis::{AnomalyMethod, BocpdConfig, IsolationForestConfig, PercentileBandConfig, WindowFunction, DEFAULT_ANOMALY_METHODS};
+use crate::detection::DetectionType;
+use crate::sensors::{default_known_bands, KnownBand};
+use crate::security::{SecurityConfig, KDF_MEMORY_KIB_RANGE, KDF_PARALLELISM_RANGE, KDF_TIME_COST_RANGE};
+use crate::streaming::{ExportFormat, StreamingConfig};
+
+/// Layout version of the config file written by this build
+///
+/// 1. Original layout, without `schema_version`
+/// 2. Adds `schema_version`; missing sections and fields are filled from defaults
+/// 3. `security.kdf_iterations` is the Argon2id time cost; PBKDF2-style
+///    counts are reset to the default
+pub const CONFIG_SCHEMA_VERSION: u32 = 3;
+
+/// Prefix of environment variables that override configuration values
+pub const ENV_PREFIX: &str = "GLOWBARN_";
+
+/// Environment variable holding the storage passphrase, for runs without a terminal
+pub const STORAGE_PASSPHRASE_ENV: &str = "GLOWBARN_STORAGE_PASSPHRASE";
+
+/// Environment variable naming a file whose first line is the storage passphrase
+pub const STORAGE_PASSPHRASE_FILE_ENV: &str = "GLOWBARN_STORAGE_PASSPHRASE_FILE";
+
+/// A configuration value that violates a constraint
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("{field}: {constraint}")]
+pub struct ConfigError {
+    /// Dotted path of the offending field, e.g. `sensors.sample_rate`
+    pub field: &'static str,
+    /// What the value must satisfy
+    pub constraint: String,
+}
+
+/// Collects constraint violations during `Config::validate`
+struct Validator {
+    errors: Vec<ConfigError>,
+}
+
+impl Validator {
+    fn check(&mut self, ok: bool, field: &'static str, constraint: impl Into<String>) {
+        if !ok {
+            self.errors.push(ConfigError { field, constraint: constraint.into() });
+        }
+    }
+    
+    fn positive(&mut self, value: f64, field: &'static str) {
+        self.check(value.is_finite() && value > 0.0, field, format!("must be a positive number (got {})", value));
+    }
+    
+    fn unit(&mut self, value: f64, field: &'static str) {
+        self.check((0.0..=1.0).contains(&value), field, format!("must be between 0 and 1 (got {})", value));
+    }
+    
+    fn nonzero(&mut self, value: u64, field: &'static str) {
+        self.check(value > 0, field, "must be greater than 0");
+    }
+}
 
 /// Main application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +76,10 @@ pub struct Config {
     /// Application version
     pub version: String,
     
+    /// Layout version of this file, see `CONFIG_SCHEMA_VERSION`
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
+    
     /// Data directory
     pub data_dir: PathBuf,
     
@@ -50,6 +109,27 @@ pub struct Config {
     
     /// Database configuration
     pub database: DatabaseConfig,
+    
+    /// Number of recent readings the event bus keeps for late subscribers
+    #[serde(default = "default_event_replay_capacity")]
+    pub event_replay_capacity: usize,
+    
+    /// Number of recent detections the event bus keeps for late subscribers
+    #[serde(default = "default_detection_replay_capacity")]
+    pub detection_replay_capacity: usize,
+}
+
+fn default_event_replay_capacity() -> usize {
+    crate::core::DEFAULT_REPLAY_CAPACITY
+}
+
+fn default_detection_replay_capacity() -> usize {
+    crate::core::DEFAULT_DETECTION_REPLAY_CAPACITY
+}
+
+/// Files written before versioning have no `schema_version`
+fn legacy_schema_version() -> u32 {
+    1
 }
 
 impl Default for Config {
@@ -57,6 +137,7 @@ impl Default for Config {
         Self {
             app_name: "GlowBarn".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version: CONFIG_SCHEMA_VERSION,
             data_dir: PathBuf::from("./data"),
             log_level: "info".to_string(),
             demo_mode: true,
@@ -67,19 +148,76 @@ impl Default for Config {
             streaming: StreamingConfig::default(),
             gui: GuiConfig::default(),
             database: DatabaseConfig::default(),
+            event_replay_capacity: default_event_replay_capacity(),
+            detection_replay_capacity: default_detection_replay_capacity(),
         }
     }
 }
 
 impl Config {
-    /// Load configuration from file
+    /// Load configuration from file, upgrading and rewriting older layouts
     pub fn load(path: &Path) -> Result<Self> {
         let content = std::fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
+        let raw: toml::Value = toml::from_str(&content)?;
+        let file_version = schema_version_of(&raw);
+        
+        let config = Self::migrate(raw)?;
+        if file_version < CONFIG_SCHEMA_VERSION {
+            config.save(path)?;
+            info!("Rewrote {:?} with schema version {}", path, CONFIG_SCHEMA_VERSION);
+        }
+        
         info!("Loaded configuration from {:?}", path);
         Ok(config)
     }
     
+    /// Upgrade a parsed config file of any older schema version to the current layout
+    pub fn migrate(mut raw: toml::Value) -> Result<Self> {
+        let from = schema_version_of(&raw);
+        if from > CONFIG_SCHEMA_VERSION {
+            warn!("Config schema version {} is newer than supported version {}; unknown fields are ignored",
+                from, CONFIG_SCHEMA_VERSION);
+        }
+        
+        let table = raw.as_table_mut()
+            .ok_or_else(|| anyhow!("Configuration must be a TOML table"))?;
+        
+        for version in from..CONFIG_SCHEMA_VERSION {
+            match version {
+                1 => {
+                    let defaults = toml::Value::try_from(Config::default())?;
+                    let mut added = Vec::new();
+                    if let Some(defaults) = defaults.as_table() {
+                        fill_missing(table, defaults, "", &mut added);
+                    }
+                    if added.is_empty() {
+                        info!("Config migration v1 -> v2: no missing fields");
+                    } else {
+                        info!("Config migration v1 -> v2: added defaults for {}", added.join(", "));
+                    }
+                }
+                2 => {
+                    let default_cost = SecurityConfig::default().kdf_iterations;
+                    let iterations = table.get_mut("security")
+                        .and_then(|security| security.get_mut("kdf_iterations"));
+                    if let Some(iterations) = iterations {
+                        let legacy = iterations.as_integer()
+                            .is_some_and(|n| n > *crate::security::KDF_TIME_COST_RANGE.end() as i64);
+                        if legacy {
+                            info!("Config migration v2 -> v3: security.kdf_iterations {} reset to Argon2id time cost {}",
+                                iterations, default_cost);
+                            *iterations = toml::Value::Integer(default_cost as i64);
+                        }
+                    }
+                }
+                _ => return Err(anyhow!("Unknown config schema version {}", version)),
+            }
+            table.insert("schema_version".to_string(), toml::Value::Integer(version as i64 + 1));
+        }
+        
+        Ok(raw.try_into()?)
+    }
+    
     /// Save configuration to file
     pub fn save(&self, path: &Path) -> Result<()> {
         let content = toml::to_string_pretty(self)?;
@@ -88,10 +226,171 @@ impl Config {
         Ok(())
     }
     
+    /// Save the fields changed since `base` into the file at `path`
+    ///
+    /// `base` is the configuration the edits started from, with whatever
+    /// environment and command line overrides it had. Fields that weren't
+    /// edited keep their value from the file (or the default if there is
+    /// no file), so overrides don't end up written to it.
+    pub fn save_edits(&self, base: &Config, path: &Path) -> Result<()> {
+        let on_disk = if path.exists() { Self::load(path)? } else { Self::default() };
+        let mut merged = serde_json::to_value(&on_disk)?;
+        merge_edits(&mut merged, &serde_json::to_value(base)?, &serde_json::to_value(self)?);
+        
+        let config: Config = serde_json::from_value(merged)?;
+        config.save(path)
+    }
+    
+    /// Check ranges and invariants that deserialization can't express
+    pub fn validate(&self) -> std::result::Result<(), Vec<ConfigError>> {
+        let mut v = Validator { errors: Vec::new() };
+        
+        let sensors = &self.sensors;
+        v.positive(sensors.sample_rate, "sensors.sample_rate");
+        v.nonzero(sensors.buffer_size as u64, "sensors.buffer_size");
+        v.nonzero(sensors.serial_baud_rate as u64, "sensors.serial_baud_rate");
+        
+        let analysis = &self.analysis;
+        v.nonzero(analysis.entropy_window as u64, "analysis.entropy_window");
+        v.unit(analysis.anomaly_threshold, "analysis.anomaly_threshold");
+        v.positive(analysis.zscore_sigma_threshold, "analysis.zscore_sigma_threshold");
+        v.check(analysis.fft_size >= 2 && analysis.fft_size.is_power_of_two(), "analysis.fft_size",
+            format!("must be a power of two of at least 2 (got {})", analysis.fft_size));
+        v.nonzero(analysis.worker_threads as u64, "analysis.worker_threads");
+        if analysis.multiscale_entropy {
+            v.nonzero(analysis.entropy_scales as u64, "analysis.entropy_scales");
+        }
+        let bocpd = &analysis.bocpd;
+        v.positive(bocpd.hazard_lambda, "analysis.bocpd.hazard_lambda");
+        v.unit(bocpd.threshold, "analysis.bocpd.threshold");
+        v.nonzero(bocpd.recent as u64, "analysis.bocpd.recent");
+        v.nonzero(bocpd.max_run_length as u64, "analysis.bocpd.max_run_length");
+        
+        let detection = &self.detection;
+        v.unit(detection.min_confidence, "detection.min_confidence");
+        v.nonzero(detection.correlation_window_ms, "detection.correlation_window_ms");
+        v.check(detection.min_correlated_sensors >= 2, "detection.min_correlated_sensors",
+            format!("must be at least 2 (got {})", detection.min_correlated_sensors));
+        v.positive(detection.cluster_radius_m, "detection.cluster_radius_m");
+        v.unit(detection.spatial_weight, "detection.spatial_weight");
+        for (name, &weight) in &detection.sensor_weights {
+            v.check(name.parse::<crate::sensors::SensorType>().is_ok(), "detection.sensor_weights",
+                format!("unknown sensor type '{}'", name));
+            v.unit(weight, "detection.sensor_weights");
+        }
+        for &min in detection.type_min_confidence.values() {
+            v.unit(min, "detection.type_min_confidence");
+        }
+        
+        let security = &self.security;
+        v.check(KDF_TIME_COST_RANGE.contains(&security.kdf_iterations), "security.kdf_iterations",
+            format!("must be in {:?} (got {})", KDF_TIME_COST_RANGE, security.kdf_iterations));
+        v.check(KDF_MEMORY_KIB_RANGE.contains(&security.kdf_memory_kib), "security.kdf_memory_kib",
+            format!("must be in {:?} (got {})", KDF_MEMORY_KIB_RANGE, security.kdf_memory_kib));
+        v.check(KDF_PARALLELISM_RANGE.contains(&security.kdf_parallelism), "security.kdf_parallelism",
+            format!("must be in {:?} (got {})", KDF_PARALLELISM_RANGE, security.kdf_parallelism));
+        v.nonzero(self.security.min_password_length as u64, "security.min_password_length");
+        
+        let streaming = &self.streaming;
+        v.check(streaming.mqtt_qos <= 2, "streaming.mqtt_qos",
+            format!("must be 0, 1 or 2 (got {})", streaming.mqtt_qos));
+        v.check(streaming.export_format != ExportFormat::GeoJson, "streaming.export_format",
+            "GeoJSON is only available for batch exports of detections");
+        if streaming.websocket_enabled {
+            v.nonzero(streaming.websocket_max_clients as u64, "streaming.websocket_max_clients");
+        }
+        if streaming.influx_enabled {
+            v.check(!streaming.influx_bucket.is_empty(), "streaming.influx_bucket", "must not be empty");
+            v.nonzero(streaming.influx_batch_size as u64, "streaming.influx_batch_size");
+            v.check(streaming.influx_max_pending >= streaming.influx_batch_size, "streaming.influx_max_pending",
+                format!("must be at least influx_batch_size ({})", streaming.influx_batch_size));
+        }
+        
+        let gui = &self.gui;
+        v.nonzero(gui.width as u64, "gui.width");
+        v.nonzero(gui.height as u64, "gui.height");
+        v.positive(gui.font_size as f64, "gui.font_size");
+        v.nonzero(gui.waveform_history as u64, "gui.waveform_history");
+        v.nonzero(gui.max_fps as u64, "gui.max_fps");
+        v.check(gui.spectrogram_min_db < gui.spectrogram_max_db, "gui.spectrogram_min_db",
+            format!("must be below gui.spectrogram_max_db ({} >= {})", gui.spectrogram_min_db, gui.spectrogram_max_db));
+        
+        if self.database.enabled {
+            v.nonzero(self.database.max_size_mb, "database.max_size_mb");
+            v.nonzero(self.database.flush_interval_secs, "database.flush_interval_secs");
+        }
+        
+        if v.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(v.errors)
+        }
+    }
+    
+    /// Load configuration from file and reject it if `validate` fails
+    pub fn load_validated(path: &Path) -> Result<Self> {
+        let config = Self::load(path)?;
+        if let Err(errors) = config.validate() {
+            let details: Vec<String> = errors.iter().map(|e| format!("  {}", e)).collect();
+            return Err(anyhow!("Invalid configuration in {:?}:\n{}", path, details.join("\n")));
+        }
+        Ok(config)
+    }
+    
+    /// Watch the file at `path` and send every valid new version over `tx`.
+    ///
+    /// Environment overrides are re-applied to each reload. Edits that fail
+    /// to parse or validate are logged and skipped. Watching stops when the
+    /// returned watcher is dropped.
+    pub fn watch(path: &Path, tx: tokio::sync::mpsc::UnboundedSender<Config>) -> Result<notify::RecommendedWatcher> {
+        use notify::{EventKind, RecursiveMode, Watcher};
+        
+        let file_name = path.file_name()
+            .ok_or_else(|| anyhow!("Not a config file: {:?}", path))?
+            .to_owned();
+        // Watch the directory: editors often replace the file rather than write to it
+        let dir = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        
+        let target = path.to_path_buf();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let event = match event {
+                Ok(e) => e,
+                Err(e) => {
+                    warn!("Config watch error: {}", e);
+                    return;
+                }
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                return;
+            }
+            if !event.paths.iter().any(|p| p.file_name() == Some(file_name.as_os_str())) {
+                return;
+            }
+            
+            let reloaded = Self::load_validated(&target).and_then(|mut config| {
+                config.apply_env_overrides()?;
+                Ok(config)
+            });
+            match reloaded {
+                Ok(config) => {
+                    let _ = tx.send(config);
+                }
+                Err(e) => warn!("Ignoring configuration change: {}", e),
+            }
+        })?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        
+        info!("Watching {:?} for changes", path);
+        Ok(watcher)
+    }
+    
     /// Load or create default configuration
     pub fn load_or_create(path: &Path) -> Result<Self> {
         if path.exists() {
-            Self::load(path)
+            Self::load_validated(path)
         } else {
             let config = Self::default();
             
@@ -105,6 +404,53 @@ impl Config {
         }
     }
     
+    /// Override values from `GLOWBARN_*` environment variables.
+    ///
+    /// Nested fields are separated by a double underscore, so
+    /// `GLOWBARN_STREAMING__MQTT_BROKER` sets `streaming.mqtt_broker`.
+    pub fn apply_env_overrides(&mut self) -> Result<()> {
+        // The passphrase variables share the prefix but are not settings
+        let vars = std::env::vars().filter(|(key, _)| {
+            key.starts_with(ENV_PREFIX) && key != STORAGE_PASSPHRASE_ENV && key != STORAGE_PASSPHRASE_FILE_ENV
+        });
+        self.apply_overrides(vars)
+    }
+    
+    /// Apply `(variable, value)` overrides; values are parsed as the type of the field they replace
+    fn apply_overrides(&mut self, vars: impl IntoIterator<Item = (String, String)>) -> Result<()> {
+        let mut tree = serde_json::to_value(&*self)?;
+        let mut applied = Vec::new();
+        
+        for (key, raw) in vars {
+            let Some(path) = key.strip_prefix(ENV_PREFIX) else { continue };
+            let path: Vec<String> = path.split("__").map(|p| p.to_lowercase()).collect();
+            
+            let slot = path.iter().try_fold(&mut tree, |node, part| node.get_mut(part.as_str()));
+            let Some(slot) = slot else {
+                warn!("Ignoring {}: no such configuration field", key);
+                continue;
+            };
+            
+            *slot = parse_env_value(&raw, slot)
+                .map_err(|e| anyhow!("Invalid value for {}: {}", key, e))?;
+            applied.push(path.join("."));
+        }
+        
+        if applied.is_empty() {
+            return Ok(());
+        }
+        
+        let config: Config = serde_json::from_value(tree)?;
+        if let Err(errors) = config.validate() {
+            let details: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            return Err(anyhow!("Invalid environment overrides: {}", details.join("; ")));
+        }
+        
+        *self = config;
+        info!("Configuration overridden from environment: {}", applied.join(", "));
+        Ok(())
+    }
+    
     /// Get configuration directory
     pub fn config_dir() -> PathBuf {
         dirs::config_dir()
@@ -118,6 +464,74 @@ impl Config {
     }
 }
 
+/// Copy into `target` every value that differs between `before` and `after`
+fn merge_edits(target: &mut serde_json::Value, before: &serde_json::Value, after: &serde_json::Value) {
+    use serde_json::Value;
+    
+    match (target, before, after) {
+        (Value::Object(target), Value::Object(before), Value::Object(after)) => {
+            for (key, value) in after {
+                match (target.get_mut(key), before.get(key)) {
+                    (Some(slot), Some(old)) => merge_edits(slot, old, value),
+                    _ => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, before, after) => {
+            if before != after {
+                *target = after.clone();
+            }
+        }
+    }
+}
+
+/// Schema version recorded in a raw config file
+fn schema_version_of(raw: &toml::Value) -> u32 {
+    raw.get("schema_version")
+        .and_then(|v| v.as_integer())
+        .map(|v| v as u32)
+        .unwrap_or_else(legacy_schema_version)
+}
+
+/// Copy keys present in `defaults` but missing from `table`, recording their dotted paths
+fn fill_missing(table: &mut toml::Table, defaults: &toml::Table, prefix: &str, added: &mut Vec<String>) {
+    for (key, default) in defaults {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match (table.get_mut(key), default) {
+            (None, _) => {
+                table.insert(key.clone(), default.clone());
+                added.push(path);
+            }
+            (Some(toml::Value::Table(existing)), toml::Value::Table(default)) => {
+                fill_missing(existing, default, &path, added);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parse an environment value as the JSON type of the value it replaces
+fn parse_env_value(raw: &str, current: &serde_json::Value) -> Result<serde_json::Value> {
+    use serde_json::Value;
+    
+    Ok(match current {
+        Value::Bool(_) => Value::Bool(match raw.to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" => false,
+            _ => return Err(anyhow!("expected a boolean, got '{}'", raw)),
+        }),
+        Value::Number(n) if n.is_f64() => serde_json::json!(raw.parse::<f64>()?),
+        Value::Number(n) if n.is_i64() && n.as_i64().unwrap_or(0) < 0 => serde_json::json!(raw.parse::<i64>()?),
+        Value::Number(_) => serde_json::json!(raw.parse::<u64>()?),
+        // Unset optional field: take the most specific reading of the text
+        Value::Null => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+        Value::Array(_) | Value::Object(_) => serde_json::from_str(raw)?,
+        Value::String(_) => Value::String(raw.to_string()),
+    })
+}
+
 /// Sensor configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SensorConfig {
@@ -136,11 +550,51 @@ pub struct SensorConfig {
     /// Serial port for hardware sensors
     pub serial_port: Option<String>,
     
+    /// Baud rate for serial sensors
+    #[serde(default = "default_serial_baud_rate")]
+    pub serial_baud_rate: u32,
+    
     /// I2C bus number
     pub i2c_bus: Option<u8>,
     
     /// SPI device
     pub spi_device: Option<String>,
+    
+    /// Scenario script driving the demo simulators
+    #[serde(default)]
+    pub scenario_file: Option<PathBuf>,
+    
+    /// `SensorRegistry` keys of sensors to build at startup; when empty and
+    /// `auto_discover` is set, every registered sensor is built
+    #[serde(default)]
+    pub drivers: Vec<String>,
+    
+    /// What a sensor does when a durable reading queue is full
+    #[serde(default)]
+    pub backpressure: BackpressurePolicy,
+    
+    /// Longest a read waits for room under `BackpressurePolicy::Block`
+    #[serde(default = "default_backpressure_block_ms")]
+    pub backpressure_block_ms: u64,
+}
+
+/// Default line speed for serial sensors
+pub(crate) fn default_serial_baud_rate() -> u32 {
+    115_200
+}
+
+fn default_backpressure_block_ms() -> u64 {
+    20
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Drop the reading and count it
+    #[default]
+    Skip,
+    /// Hold the sensor's read loop until there is room, dropping the reading
+    /// if none frees up within `backpressure_block_ms`
+    Block,
 }
 
 impl Default for SensorConfig {
@@ -151,8 +605,13 @@ impl Default for SensorConfig {
             calibration_interval_secs: 3600,
             auto_discover: true,
             serial_port: None,
+            serial_baud_rate: default_serial_baud_rate(),
             i2c_bus: Some(1),
             spi_device: None,
+            scenario_file: None,
+            drivers: Vec::new(),
+            backpressure: BackpressurePolicy::default(),
+            backpressure_block_ms: default_backpressure_block_ms(),
         }
     }
 }
@@ -163,9 +622,13 @@ pub struct AnalysisConfig {
     /// Window size for entropy analysis
     pub entropy_window: usize,
     
-    /// Anomaly detection threshold
+    /// Confidence (0-1) an anomaly needs before it is reported
     pub anomaly_threshold: f64,
     
+    /// Standard deviations from the mean before the Z-score detector flags a point
+    #[serde(default = "default_zscore_sigma_threshold")]
+    pub zscore_sigma_threshold: f64,
+    
     /// FFT size
     pub fft_size: usize,
     
@@ -180,6 +643,39 @@ pub struct AnalysisConfig {
     
     /// Number of entropy scales
     pub entropy_scales: usize,
+    
+    /// How multiscale entropy coarse-grains the signal
+    #[serde(default)]
+    pub multiscale_method: MultiscaleMethod,
+    
+    /// Power spectrum spectral entropy is computed from
+    #[serde(default)]
+    pub spectral_entropy_method: SpectralEntropyMethod,
+    
+    /// Taper of the windowed and Welch spectral entropy methods
+    #[serde(default)]
+    pub spectral_window: WindowFunction,
+    
+    /// Isolation forest size and anomaly score threshold
+    #[serde(default)]
+    pub isolation_forest: IsolationForestConfig,
+    
+    /// Neighbours each point is compared with by the local outlier factor
+    #[serde(default = "default_lof_neighbors")]
+    pub lof_neighbors: usize,
+    
+    /// Anomaly detection methods to run; `Percentile` is the robust choice
+    /// for skewed sensors
+    #[serde(default = "default_anomaly_methods")]
+    pub anomaly_methods: Vec<AnomalyMethod>,
+    
+    /// Band outside which the `Percentile` method flags a point
+    #[serde(default)]
+    pub percentile_band: PercentileBandConfig,
+    
+    /// Hazard and threshold of the `Bocpd` change-point method
+    #[serde(default)]
+    pub bocpd: BocpdConfig,
 }
 
 impl Default for AnalysisConfig {
@@ -187,21 +683,46 @@ impl Default for AnalysisConfig {
         Self {
             entropy_window: 1000,
             anomaly_threshold: 0.7,
+            zscore_sigma_threshold: default_zscore_sigma_threshold(),
             fft_size: 2048,
             gpu_enabled: false,
             worker_threads: 4,
             multiscale_entropy: true,
             entropy_scales: 10,
+            multiscale_method: MultiscaleMethod::default(),
+            spectral_entropy_method: SpectralEntropyMethod::default(),
+            spectral_window: WindowFunction::default(),
+            isolation_forest: IsolationForestConfig::default(),
+            lof_neighbors: default_lof_neighbors(),
+            anomaly_methods: default_anomaly_methods(),
+            percentile_band: PercentileBandConfig::default(),
+            bocpd: BocpdConfig::default(),
         }
     }
 }
 
+fn default_zscore_sigma_threshold() -> f64 {
+    3.0
+}
+
+fn default_lof_neighbors() -> usize {
+    5
+}
+
+fn default_anomaly_methods() -> Vec<AnomalyMethod> {
+    DEFAULT_ANOMALY_METHODS.to_vec()
+}
+
 /// Detection configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DetectionConfig {
     /// Minimum confidence for detection
     pub min_confidence: f64,
     
+    /// Per detection type minimum confidence, overriding `min_confidence`
+    #[serde(default)]
+    pub type_min_confidence: std::collections::HashMap<DetectionType, f64>,
+    
     /// Enable multi-sensor fusion
     pub fusion_enabled: bool,
     
@@ -214,11 +735,40 @@ pub struct DetectionConfig {
     /// Minimum sensors for correlated event
     pub min_correlated_sensors: usize,
     
+    /// Per sensor type reliability weights for fusion, keyed by type name (e.g. `EMFProbe`)
+    #[serde(default)]
+    pub sensor_weights: std::collections::HashMap<String, f64>,
+    
+    /// Radius in meters within which correlated sensors count as clustered
+    #[serde(default = "default_cluster_radius_m")]
+    pub cluster_radius_m: f64,
+    
+    /// Confidence boost for spatially clustered correlated events
+    #[serde(default = "default_spatial_weight")]
+    pub spatial_weight: f64,
+    
+    /// Transmitters expected on site; RF peaks inside them aren't anomalies
+    #[serde(default = "default_known_bands")]
+    pub known_rf_bands: Vec<KnownBand>,
+    
     /// Enable classification
     pub classification_enabled: bool,
     
     /// Alert severity threshold
     pub alert_threshold: Severity,
+    
+    /// Let detections annotated as false positives lower, and confirmed
+    /// ones raise, the fusion weights of their sensor types
+    #[serde(default)]
+    pub annotation_feedback: bool,
+}
+
+fn default_cluster_radius_m() -> f64 {
+    5.0
+}
+
+fn default_spatial_weight() -> f64 {
+    0.2
 }
 
 impl Default for DetectionConfig {
@@ -229,12 +779,41 @@ impl Default for DetectionConfig {
             fusion_method: FusionMethod::DempsterShafer,
             correlation_window_ms: 2000,
             min_correlated_sensors: 2,
+            sensor_weights: std::collections::HashMap::new(),
+            type_min_confidence: std::collections::HashMap::new(),
+            cluster_radius_m: default_cluster_radius_m(),
+            spatial_weight: default_spatial_weight(),
+            known_rf_bands: default_known_bands(),
             classification_enabled: true,
             alert_threshold: Severity::Medium,
+            annotation_feedback: false,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MultiscaleMethod {
+    /// One coarse-graining per scale (Costa et al.)
+    #[default]
+    Standard,
+    /// Refined composite: match counts pooled over every coarse-graining
+    /// offset, much steadier on short windows
+    RefinedComposite,
+}
+
+/// Power spectrum spectral entropy is computed from
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SpectralEntropyMethod {
+    /// Periodogram of the whole, untapered window
+    #[default]
+    Periodogram,
+    /// Periodogram of the whole window after `spectral_window`
+    Windowed,
+    /// Welch average of half-overlapping segments, each tapered with
+    /// `spectral_window`; steadier, at coarser resolution
+    Welch,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum FusionMethod {
     Bayesian,
@@ -271,14 +850,46 @@ pub struct GuiConfig {
     /// Show FPS counter
     pub show_fps: bool,
     
+    /// Upper bound on repaints per second; the console repaints less
+    /// often when data arrives slower or nothing is running
+    #[serde(default = "default_max_fps")]
+    pub max_fps: u32,
+    
+    /// Milliseconds of work per frame above which the console draws fewer
+    /// plot points, redraws the waterfall less often and coarsens the
+    /// thermal image; 0 always draws at full quality
+    #[serde(default = "default_frame_budget_ms")]
+    pub frame_budget_ms: f64,
+    
     /// Waveform history length
     pub waveform_history: usize,
     
     /// Thermal colormap
     pub thermal_colormap: Colormap,
     
+    /// Spectrogram (waterfall) colormap
+    #[serde(default = "default_spectrogram_colormap")]
+    pub spectrogram_colormap: Colormap,
+    
+    /// Power mapped to the bottom of the spectrogram colormap, in dB
+    #[serde(default = "default_spectrogram_min_db")]
+    pub spectrogram_min_db: f32,
+    
+    /// Power mapped to the top of the spectrogram colormap, in dB
+    #[serde(default = "default_spectrogram_max_db")]
+    pub spectrogram_max_db: f32,
+    
+    /// Age in seconds at which a detection is drawn at half strength in the
+    /// detection panel; older ones keep fading
+    #[serde(default = "default_detection_half_life_secs")]
+    pub detection_half_life_secs: f64,
+    
     /// Alert sound enabled
     pub alert_sound: bool,
+    
+    /// Keyboard shortcuts
+    #[serde(default)]
+    pub shortcuts: Shortcuts,
 }
 
 impl Default for GuiConfig {
@@ -290,9 +901,79 @@ impl Default for GuiConfig {
             theme: Theme::Dark,
             font_size: 14.0,
             show_fps: false,
+            max_fps: default_max_fps(),
+            frame_budget_ms: default_frame_budget_ms(),
             waveform_history: 500,
             thermal_colormap: Colormap::Inferno,
+            spectrogram_colormap: default_spectrogram_colormap(),
+            spectrogram_min_db: default_spectrogram_min_db(),
+            spectrogram_max_db: default_spectrogram_max_db(),
+            detection_half_life_secs: default_detection_half_life_secs(),
             alert_sound: true,
+            shortcuts: Shortcuts::default(),
+        }
+    }
+}
+
+fn default_max_fps() -> u32 {
+    60
+}
+
+fn default_frame_budget_ms() -> f64 {
+    12.0
+}
+
+fn default_spectrogram_colormap() -> Colormap {
+    Colormap::Viridis
+}
+
+fn default_spectrogram_min_db() -> f32 {
+    -40.0
+}
+
+fn default_spectrogram_max_db() -> f32 {
+    60.0
+}
+
+fn default_detection_half_life_secs() -> f64 {
+    60.0
+}
+
+/// Console keyboard shortcuts, written like `Ctrl+P`, `Shift+E` or `Space`.
+///
+/// Key names follow egui (`Escape`, `F1`, `A`, ...); `Ctrl` maps to Cmd on
+/// macOS. An empty string disables the shortcut.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Shortcuts {
+    /// Start/stop recording
+    pub record: String,
+    
+    /// Toggle the settings window
+    pub settings: String,
+    
+    /// Open the export dialog
+    pub export: String,
+    
+    /// Close open dialogs
+    pub close: String,
+    
+    /// Open the command palette
+    pub command_palette: String,
+    
+    /// Number keys 1-9 select the nth sensor in the sensor list
+    pub number_keys_focus_sensors: bool,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self {
+            record: "Space".to_string(),
+            settings: "S".to_string(),
+            export: "E".to_string(),
+            close: "Escape".to_string(),
+            command_palette: "Ctrl+P".to_string(),
+            number_keys_focus_sensors: true,
         }
     }
 }
@@ -314,6 +995,63 @@ pub enum Colormap {
     Grayscale,
 }
 
+/// Nine evenly spaced samples of each matplotlib colormap, interpolated linearly
+const INFERNO: [[u8; 3]; 9] = [
+    [0, 0, 4], [31, 12, 72], [85, 15, 109], [136, 34, 106], [186, 54, 85],
+    [227, 89, 51], [249, 140, 10], [249, 201, 50], [252, 255, 164],
+];
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84], [72, 40, 120], [62, 74, 137], [49, 104, 142], [38, 130, 142],
+    [31, 158, 137], [53, 183, 121], [109, 205, 89], [253, 231, 37],
+];
+const PLASMA: [[u8; 3]; 9] = [
+    [13, 8, 135], [76, 2, 161], [126, 3, 168], [169, 35, 149], [204, 71, 120],
+    [229, 107, 93], [248, 148, 65], [253, 195, 40], [240, 249, 33],
+];
+const MAGMA: [[u8; 3]; 9] = [
+    [0, 0, 4], [28, 16, 68], [79, 18, 123], [129, 37, 129], [181, 54, 122],
+    [229, 80, 100], [251, 135, 97], [254, 194, 135], [252, 253, 191],
+];
+const TURBO: [[u8; 3]; 9] = [
+    [48, 18, 59], [70, 98, 215], [54, 170, 249], [26, 228, 182], [114, 254, 94],
+    [200, 239, 52], [250, 186, 57], [246, 107, 25], [122, 4, 3],
+];
+
+impl Colormap {
+    /// RGB color for `t` in 0..=1 (clamped; NaN maps to the low end)
+    pub fn to_rgb(&self, t: f32) -> [u8; 3] {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let stops = match self {
+            Colormap::Inferno => &INFERNO,
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Plasma => &PLASMA,
+            Colormap::Magma => &MAGMA,
+            Colormap::Turbo => &TURBO,
+            Colormap::Grayscale => {
+                let v = (255.0 * t).round() as u8;
+                return [v, v, v];
+            }
+        };
+        
+        let pos = t * (stops.len() - 1) as f32;
+        let i = (pos.floor() as usize).min(stops.len() - 2);
+        let frac = pos - i as f32;
+        let (lo, hi) = (stops[i], stops[i + 1]);
+        
+        let mut rgb = [0u8; 3];
+        for c in 0..3 {
+            rgb[c] = (lo[c] as f32 + (hi[c] as f32 - lo[c] as f32) * frac).round() as u8;
+        }
+        rgb
+    }
+    
+    #[cfg(feature = "gui")]
+    pub fn to_color(&self, t: f32) -> eframe::egui::Color32 {
+        let [r, g, b] = self.to_rgb(t);
+        eframe::egui::Color32::from_rgb(r, g, b)
+    }
+}
+
 /// Database configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseConfig {
@@ -332,6 +1070,11 @@ pub struct DatabaseConfig {
     /// Flush interval in seconds
     pub flush_interval_secs: u64,
     
+    /// Seconds between checkpoints of the learned engine state, 0 to only
+    /// checkpoint at shutdown
+    #[serde(default = "default_checkpoint_interval_secs")]
+    pub checkpoint_interval_secs: u64,
+    
     /// Enable compression
     pub compression: bool,
 }
@@ -344,7 +1087,168 @@ impl Default for DatabaseConfig {
             max_size_mb: 1024,
             retention_days: 30,
             flush_interval_secs: 10,
+            checkpoint_interval_secs: default_checkpoint_interval_secs(),
             compression: true,
         }
     }
 }
+
+fn default_checkpoint_interval_secs() -> u64 {
+    60
+}
+
+/// Held by tests that set or read `GLOWBARN_*` variables, which are shared by the whole process
+#[cfg(test)]
+pub(crate) static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Sets environment variables for one test and puts the previous values back when dropped
+    struct EnvGuard {
+        previous: Vec<(&'static str, Option<std::ffi::OsString>)>,
+    }
+    
+    impl EnvGuard {
+        fn set(vars: &[(&'static str, &str)]) -> Self {
+            let previous = vars.iter()
+                .map(|(key, value)| {
+                    let old = std::env::var_os(key);
+                    std::env::set_var(key, value);
+                    (*key, old)
+                })
+                .collect();
+            Self { previous }
+        }
+    }
+    
+    impl Drop for EnvGuard {
+        fn drop(&mut self) {
+            for (key, old) in self.previous.drain(..).rev() {
+                match old {
+                    Some(value) => std::env::set_var(key, value),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn test_validate_reports_each_violation() {
+        assert!(Config::default().validate().is_ok());
+        
+        let mut config = Config::default();
+        config.sensors.sample_rate = -5.0;
+        config.analysis.entropy_window = 0;
+        config.analysis.fft_size = 1000;
+        config.analysis.bocpd.threshold = 1.5;
+        config.analysis.bocpd.recent = 0;
+        config.detection.min_confidence = 2.0;
+        
+        let errors = config.validate().unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec![
+            "sensors.sample_rate",
+            "analysis.entropy_window",
+            "analysis.fft_size",
+            "analysis.bocpd.threshold",
+            "analysis.bocpd.recent",
+            "detection.min_confidence",
+        ]);
+        assert_eq!(errors[2].to_string(), "analysis.fft_size: must be a power of two of at least 2 (got 1000)");
+    }
+    
+    #[test]
+    fn test_v1_config_is_migrated_and_rewritten() {
+        let mut raw = toml::Value::try_from(Config::default()).unwrap();
+        let table = raw.as_table_mut().unwrap();
+        table.remove("schema_version");
+        table.remove("streaming");
+        table.get_mut("security").unwrap().as_table_mut().unwrap()
+            .insert("kdf_iterations".to_string(), toml::Value::Integer(100_000));
+        
+        let path = std::env::temp_dir().join(format!("glowbarn-v1-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, toml::to_string(&raw).unwrap()).unwrap();
+        
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.schema_version, CONFIG_SCHEMA_VERSION);
+        assert_eq!(config.streaming.websocket_port, StreamingConfig::default().websocket_port);
+        assert_eq!(config.security.kdf_iterations, SecurityConfig::default().kdf_iterations);
+        assert!(config.validate().is_ok());
+        
+        let rewritten: toml::Value = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(schema_version_of(&rewritten), CONFIG_SCHEMA_VERSION);
+        assert!(rewritten.get("streaming").is_some());
+        
+        let _ = std::fs::remove_file(&path);
+    }
+    
+    #[test]
+    fn test_colormap_samples() {
+        let maps = [
+            (Colormap::Inferno, [0, 0, 4], [186, 54, 85], [252, 255, 164]),
+            (Colormap::Viridis, [68, 1, 84], [38, 130, 142], [253, 231, 37]),
+            (Colormap::Plasma, [13, 8, 135], [204, 71, 120], [240, 249, 33]),
+            (Colormap::Magma, [0, 0, 4], [181, 54, 122], [252, 253, 191]),
+            (Colormap::Turbo, [48, 18, 59], [114, 254, 94], [122, 4, 3]),
+            (Colormap::Grayscale, [0, 0, 0], [128, 128, 128], [255, 255, 255]),
+        ];
+        
+        for (map, low, mid, high) in maps {
+            assert_eq!(map.to_rgb(0.0), low, "{:?} at 0", map);
+            assert_eq!(map.to_rgb(0.5), mid, "{:?} at 0.5", map);
+            assert_eq!(map.to_rgb(1.0), high, "{:?} at 1", map);
+        }
+        assert_eq!(Colormap::Magma.to_rgb(f32::NAN), [0, 0, 4]);
+    }
+    
+    #[test]
+    fn test_env_overrides() {
+        let _lock = ENV_LOCK.blocking_lock();
+        let _env = EnvGuard::set(&[
+            ("GLOWBARN_DEMO_MODE", "false"),
+            ("GLOWBARN_STREAMING__MQTT_BROKER", "broker.internal"),
+            ("GLOWBARN_ANALYSIS__ANOMALY_THRESHOLD", "0.25"),
+            ("GLOWBARN_DATABASE__PATH", "/var/lib/glowbarn/db.sqlite"),
+        ]);
+        
+        let mut config = Config::default();
+        config.apply_env_overrides().unwrap();
+        
+        assert!(!config.demo_mode);
+        assert_eq!(config.streaming.mqtt_broker, "broker.internal");
+        assert_eq!(config.analysis.anomaly_threshold, 0.25);
+        assert_eq!(config.database.path, PathBuf::from("/var/lib/glowbarn/db.sqlite"));
+        
+        let mut config = Config::default();
+        let bad = [("GLOWBARN_SENSORS__BUFFER_SIZE".to_string(), "lots".to_string())];
+        assert!(config.apply_overrides(bad).is_err());
+    }
+    
+    #[test]
+    fn test_save_edits_keeps_overrides_out_of_the_file() {
+        let path = std::env::temp_dir().join(format!("glowbarn-edits-{}.toml", uuid::Uuid::new_v4()));
+        let mut on_disk = Config::default();
+        on_disk.streaming.mqtt_broker = "broker.lan".to_string();
+        on_disk.database.retention_days = 30;
+        on_disk.save(&path).unwrap();
+        
+        // Loaded, then overridden from the environment and the command line
+        let mut base = Config::load(&path).unwrap();
+        let overrides = [("GLOWBARN_STREAMING__MQTT_BROKER".to_string(), "broker.internal".to_string())];
+        base.apply_overrides(overrides).unwrap();
+        base.demo_mode = false;
+        
+        let mut edited = base.clone();
+        edited.database.retention_days = 7;
+        edited.save_edits(&base, &path).unwrap();
+        
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(saved.database.retention_days, 7);
+        assert_eq!(saved.streaming.mqtt_broker, "broker.lan");
+        assert!(saved.demo_mode);
+        
+        let _ = std::fs::remove_file(&path);
+    }
+}
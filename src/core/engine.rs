@@ -2,49 +2,426 @@
 // Licensed under the MIT License. See LICENSE file in the project root.
 // https://github.com/bad-antics/glowbarn-rs
 
-//! Main detection engine - simplified for initial compilation
+//! Main detection engine - wires sensors, analysis and detection together
 
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
+use tokio::task::{JoinError, JoinSet};
 use anyhow::Result;
-use tracing::info;
+use tracing::{debug, info, warn, error};
 
 use crate::config::Config;
-use super::SystemState;
+use crate::db::Database;
+use crate::sensors::{HealthStatus, ReplaySensor, Sensor, SensorManager, SensorReading, SensorRegistry};
+use crate::analysis::AnalysisEngine;
+use crate::detection::{Detection, DetectionEngine};
+use crate::streaming::StreamingManager;
+use super::{EventBus, Recorder, SystemMonitor, SystemState};
 
-/// Main GlowBarn engine - simplified for initial build
+/// Event bus channel capacity
+const EVENT_BUS_CAPACITY: usize = 10_000;
+
+/// Readings queued for each durable consumer (persistence, streaming)
+/// before new ones are dropped and counted
+const DURABLE_QUEUE_CAPACITY: usize = 4096;
+
+/// How long `shutdown` waits for pipeline tasks before aborting them
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Readings buffered before a batch is written to the database
+const PERSIST_BATCH_SIZE: usize = 500;
+
+/// Recorded readings read at a time by `Engine::reprocess`
+const REPROCESS_PAGE_SIZE: usize = 1000;
+
+/// How often sensor health is mirrored to the database
+const SENSOR_STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+type TaskResult = (&'static str, Result<()>);
+
+/// Outcome of `Engine::reprocess`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReprocessStats {
+    /// Recorded readings run through the pipeline
+    pub readings: usize,
+    /// Detections recorded live during the session
+    pub original_detections: usize,
+    /// Detections the current settings produce
+    pub reprocessed_detections: usize,
+    /// Reprocessed detections with no original of the same type nearby
+    pub added: usize,
+    /// Originals the current settings no longer produce
+    pub removed: usize,
+}
+
+impl ReprocessStats {
+    /// Detections that differ between the original run and the reprocessing
+    pub fn changed(&self) -> usize {
+        self.added + self.removed
+    }
+}
+
+/// Main GlowBarn engine
 pub struct Engine {
     pub config: Arc<Config>,
     state: Arc<RwLock<SystemState>>,
-    start_time: Option<Instant>,
+    start_time: parking_lot::Mutex<Option<Instant>>,
+    event_bus: Arc<EventBus>,
+    sensor_manager: Arc<SensorManager>,
+    analysis: Arc<AnalysisEngine>,
+    detection: Arc<DetectionEngine>,
+    database: Option<Arc<Database>>,
+    streaming: Option<Arc<StreamingManager>>,
+    recorder: Option<Arc<Recorder>>,
+    shutdown_tx: broadcast::Sender<()>,
+    tasks: JoinSet<TaskResult>,
 }
 
 impl Engine {
     pub async fn new(config: Config) -> Result<Self> {
         let config = Arc::new(config);
+        let event_bus = Arc::new(
+            EventBus::with_replay(
+                EVENT_BUS_CAPACITY,
+                config.event_replay_capacity,
+                config.detection_replay_capacity,
+            )
+            .with_backpressure(
+                config.sensors.backpressure,
+                std::time::Duration::from_millis(config.sensors.backpressure_block_ms),
+            ),
+        );
+        
+        let sensor_manager = Arc::new(
+            SensorManager::new(config.clone(), event_bus.clone(), config.demo_mode).await?
+        );
+        let analysis = Arc::new(AnalysisEngine::new(config.clone(), event_bus.clone()).await?);
+        sensor_manager.attach_analysis(analysis.clone());
+        let detection = Arc::new(DetectionEngine::new(config.clone(), event_bus.clone()).await?);
         
         Ok(Self {
             config,
             state: Arc::new(RwLock::new(SystemState::default())),
-            start_time: None,
+            start_time: parking_lot::Mutex::new(None),
+            event_bus,
+            sensor_manager,
+            analysis,
+            detection,
+            database: None,
+            streaming: None,
+            recorder: None,
+            shutdown_tx: broadcast::channel(1).0,
+            tasks: JoinSet::new(),
         })
     }
     
+    /// Persist readings, detections, calibrations and noise profiles to
+    /// `database` while running
+    pub fn with_database(mut self, database: Arc<Database>) -> Self {
+        self.sensor_manager.attach_database(database.clone());
+        match self.analysis.load_baselines(&database) {
+            Ok(0) => {}
+            Ok(n) => info!("Restored entropy baselines for {} sensors", n),
+            Err(e) => warn!("Failed to restore entropy baselines: {}", e),
+        }
+        self.database = Some(database);
+        self
+    }
+    
+    /// Add sensors built by third-party factories in `registry`; see
+    /// `SensorManager::add_from_registry` for which are built
+    pub async fn with_sensor_registry(self, registry: &SensorRegistry) -> Result<Self> {
+        let added = self.sensor_manager.add_from_registry(registry).await?;
+        info!("Added {} sensors from the registry", added);
+        Ok(self)
+    }
+    
+    /// Replace the configured sensors with replay sources
+    pub async fn with_replay(self, sensors: Vec<ReplaySensor>) -> Result<Self> {
+        info!("Replay mode: {} recorded sensors", sensors.len());
+        let sensors = sensors.into_iter().map(|s| Box::new(s) as Box<dyn Sensor>).collect();
+        self.sensor_manager.replace_sensors(sensors).await?;
+        Ok(self)
+    }
+    
+    /// Forward readings and detections to `streaming` while running
+    pub fn with_streaming(mut self, streaming: Arc<StreamingManager>) -> Self {
+        self.streaming = Some(streaming);
+        self
+    }
+    
+    /// Only persist and export while a recording session is open.
+    ///
+    /// Call after `with_database` and `with_streaming`; recording starts
+    /// stopped and is toggled through `recorder()`.
+    pub fn with_recording(mut self) -> Result<Self> {
+        let Some(ref database) = self.database else {
+            anyhow::bail!("Recording needs a database");
+        };
+        if let Some(ref streaming) = self.streaming {
+            streaming.set_export_enabled(false)?;
+        }
+        self.recorder = Some(Arc::new(Recorder::new(database.clone(), self.streaming.clone())));
+        Ok(self)
+    }
+    
+    /// Recording session control, if built `with_recording`
+    pub fn recorder(&self) -> Option<Arc<Recorder>> {
+        self.recorder.clone()
+    }
+    
+    /// Receiver that fires when `shutdown` is called
+    pub fn shutdown_signal(&self) -> broadcast::Receiver<()> {
+        self.shutdown_tx.subscribe()
+    }
+    
+    /// Start the pipeline in the background; stop it with `shutdown`
     pub async fn start(&mut self) -> Result<()> {
+        self.mark_started().await;
+        
+        let shutdown = self.shutdown_tx.subscribe();
+        let mut tasks = std::mem::take(&mut self.tasks);
+        self.spawn_pipeline(&mut tasks, &shutdown);
+        self.spawn_stats(shutdown);
+        self.tasks = tasks;
+        
+        Ok(())
+    }
+    
+    /// Watch the config file at `path` and apply edits to the running engine.
+    ///
+    /// Detection settings (thresholds, fusion method, sensor weights, alert
+    /// severity, correlation) and analysis settings (anomaly thresholds,
+    /// windows, methods) are applied live; changes to any other section are
+    /// logged and ignored until restart.
+    pub fn watch_config(&mut self, path: &Path) -> Result<()> {
+        let mut current = Config::load_validated(path)?;
+        current.apply_env_overrides()?;
+        
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let watcher = Config::watch(path, tx)?;
+        let detection = self.detection.clone();
+        let analysis = self.analysis.clone();
+        let mut shutdown = self.shutdown_tx.subscribe();
+        
+        self.tasks.spawn(async move {
+            // Dropping the watcher stops the notifications
+            let _watcher = watcher;
+            loop {
+                tokio::select! {
+                    Some(config) = rx.recv() => {
+                        apply_reload(&detection, &analysis, &current, &config);
+                        current = config;
+                    }
+                    _ = shutdown.recv() => break,
+                }
+            }
+            ("config-watch", Ok(()))
+        });
+        
+        Ok(())
+    }
+    
+    /// Stop all subsystems and make sure buffered data reaches disk
+    ///
+    /// Signals shutdown, waits up to `SHUTDOWN_TIMEOUT` for the pipeline
+    /// tasks to drain, then closes the exporter and flushes the database.
+    pub async fn shutdown(mut self) -> Result<()> {
+        info!("Shutting down GlowBarn engine...");
+        let _ = self.shutdown_tx.send(());
+        
+        let tasks = &mut self.tasks;
+        let drained = tokio::time::timeout(SHUTDOWN_TIMEOUT, async {
+            while let Some(result) = tasks.join_next().await {
+                log_task_exit(result);
+            }
+        }).await;
+        
+        if drained.is_err() {
+            warn!("{} tasks did not stop within {:?}, aborting", self.tasks.len(), SHUTDOWN_TIMEOUT);
+            self.tasks.abort_all();
+            while self.tasks.join_next().await.is_some() {}
+        }
+        
+        let mut result = Ok(());
+        
+        if let Some(ref recorder) = self.recorder {
+            if let Err(e) = recorder.stop().await {
+                error!("Failed to end recording session: {}", e);
+                result = Err(e);
+            }
+        }
+        
+        if let Some(ref streaming) = self.streaming {
+            if let Err(e) = streaming.close().await {
+                error!("Failed to close streaming: {}", e);
+                result = Err(e);
+            }
+        }
+        
+        if let Some(ref db) = self.database {
+            if let Err(e) = checkpoint(db, &self.analysis, &self.detection).await {
+                error!("Failed to checkpoint engine state: {}", e);
+                result = Err(e);
+            }
+            if let Err(e) = db.flush() {
+                error!("Failed to flush database: {}", e);
+                result = Err(e);
+            }
+        }
+        
+        self.stop_inner().await;
+        result
+    }
+    
+    async fn mark_started(&self) {
         info!("Starting GlowBarn engine...");
-        self.start_time = Some(Instant::now());
+        *self.start_time.lock() = Some(Instant::now());
         
         {
             let mut state = self.state.write().await;
             state.running = true;
         }
         
+        // RF detection needs the frequencies each sensor's spectra cover
+        for (sensor_id, (start_hz, stop_hz)) in self.sensor_manager.spectrum_spans().await {
+            self.detection.set_spectrum_span(&sensor_id, start_hz, stop_hz);
+        }
+        
+        self.spawn_monitor();
+        
         info!("GlowBarn engine started");
+    }
+    
+    /// Run the full pipeline until `shutdown` fires
+    ///
+    /// Sensors are sampled at their own rates and published to the event
+    /// bus; the analysis and detection engines consume from it.
+    pub async fn run(&self, shutdown: broadcast::Receiver<()>) -> Result<()> {
+        self.mark_started().await;
+        
+        let mut tasks = JoinSet::new();
+        self.spawn_pipeline(&mut tasks, &shutdown);
+        self.spawn_stats(shutdown.resubscribe());
+        
+        while let Some(result) = tasks.join_next().await {
+            log_task_exit(result);
+        }
+        
+        self.stop_inner().await;
         Ok(())
     }
     
+    fn spawn_pipeline(&self, tasks: &mut JoinSet<TaskResult>, shutdown: &broadcast::Receiver<()>) {
+        let sensors = self.sensor_manager.clone();
+        let rx = shutdown.resubscribe();
+        tasks.spawn(async move { ("sensors", sensors.run(rx).await) });
+        
+        let analysis = self.analysis.clone();
+        let rx = shutdown.resubscribe();
+        tasks.spawn(async move { ("analysis", analysis.run(rx).await) });
+        
+        let detection = self.detection.clone();
+        let rx = shutdown.resubscribe();
+        tasks.spawn(async move { ("detection", detection.run(rx).await) });
+        
+        // Subscribe before spawning so nothing published from here on is missed
+        if let Some(ref db) = self.database {
+            let db = db.clone();
+            let readings = self.event_bus.subscribe_readings_durable(DURABLE_QUEUE_CAPACITY);
+            let detections = self.event_bus.subscribe_detections();
+            let flush_every = Duration::from_secs(self.config.database.flush_interval_secs.max(1));
+            let recorder = self.recorder.clone();
+            let rx = shutdown.resubscribe();
+            tasks.spawn(async move {
+                ("persistence", persist(db, recorder, readings, detections, flush_every, rx).await)
+            });
+        }
+        
+        let checkpoint_every = self.config.database.checkpoint_interval_secs;
+        if let Some(db) = self.database.as_ref().filter(|_| checkpoint_every > 0) {
+            let db = db.clone();
+            let analysis = self.analysis.clone();
+            let detection = self.detection.clone();
+            let every = Duration::from_secs(checkpoint_every);
+            let rx = shutdown.resubscribe();
+            tasks.spawn(async move {
+                ("checkpoint", checkpoint_periodically(db, analysis, detection, every, rx).await)
+            });
+        }
+        
+        if let Some(ref db) = self.database {
+            let db = db.clone();
+            let sensors = self.sensor_manager.clone();
+            let rx = shutdown.resubscribe();
+            tasks.spawn(async move { ("sensor-status", track_sensor_status(db, sensors, rx).await) });
+        }
+        
+        if let Some(ref streaming) = self.streaming {
+            let streaming = streaming.clone();
+            let readings = self.event_bus.subscribe_readings_durable(DURABLE_QUEUE_CAPACITY);
+            let detections = self.event_bus.subscribe_detections();
+            let rx = shutdown.resubscribe();
+            tasks.spawn(async move {
+                ("streaming", forward(streaming, readings, detections, rx).await)
+            });
+        }
+    }
+    
+    /// Keep reading/detection counters in the system state up to date
+    fn spawn_stats(&self, mut shutdown: broadcast::Receiver<()>) {
+        let state = self.state.clone();
+        let sensors = self.sensor_manager.clone();
+        let event_bus = self.event_bus.clone();
+        let mut readings = self.event_bus.subscribe_readings();
+        let mut detections = self.event_bus.subscribe_detections();
+        
+        tokio::spawn(async move {
+            let mut sensor_poll = tokio::time::interval(std::time::Duration::from_secs(1));
+            
+            loop {
+                tokio::select! {
+                    reading = readings.recv() => {
+                        match reading {
+                            Ok(_) => state.write().await.total_readings += 1,
+                            Err(broadcast::error::RecvError::Lagged(n)) => state.write().await.total_readings += n,
+                            Err(broadcast::error::RecvError::Closed) => break,
+                        }
+                    }
+                    _ = sensor_poll.tick() => {
+                        let active = sensors.active_count().await;
+                        let mut state = state.write().await;
+                        state.sensors_active = active;
+                        state.dropped_readings = event_bus.dropped_readings();
+                        state.lagged_readings = event_bus.lagged_readings();
+                        state.publish_stats = event_bus.publish_stats();
+                    }
+                    detection = detections.recv() => {
+                        match detection {
+                            Ok(d) => {
+                                let mut state = state.write().await;
+                                state.total_detections += 1;
+                                state.last_detection = Some(d.timestamp);
+                            }
+                            Err(broadcast::error::RecvError::Closed) => break,
+                            Err(_) => {}
+                        }
+                    }
+                    _ = shutdown.recv() => break,
+                }
+            }
+        });
+    }
+    
     pub async fn stop(&mut self) -> Result<()> {
+        self.stop_inner().await;
+        Ok(())
+    }
+    
+    async fn stop_inner(&self) {
         info!("Stopping GlowBarn engine...");
         
         {
@@ -53,14 +430,699 @@ impl Engine {
         }
         
         info!("GlowBarn engine stopped");
-        Ok(())
+    }
+    
+    /// Periodically copy real process metrics into the system state
+    fn spawn_monitor(&self) {
+        let mut monitor = match SystemMonitor::new() {
+            Ok(m) => m,
+            Err(e) => {
+                warn!("System metrics unavailable: {}", e);
+                return;
+            }
+        };
+        
+        let state = self.state.clone();
+        let start = (*self.start_time.lock()).unwrap_or_else(Instant::now);
+        
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(super::monitor::MIN_SAMPLE_INTERVAL);
+            
+            loop {
+                interval.tick().await;
+                let metrics = monitor.sample();
+                
+                let mut state = state.write().await;
+                if !state.running {
+                    break;
+                }
+                state.cpu_usage = metrics.cpu_percent;
+                state.memory_usage = metrics.memory_mb() as f32;
+                state.thread_count = metrics.thread_count;
+                state.uptime_seconds = start.elapsed().as_secs();
+            }
+        });
     }
     
     pub async fn state(&self) -> SystemState {
         self.state.read().await.clone()
     }
     
+    pub fn event_bus(&self) -> Arc<EventBus> {
+        self.event_bus.clone()
+    }
+    
+    pub fn sensor_manager(&self) -> Arc<SensorManager> {
+        self.sensor_manager.clone()
+    }
+    
+    pub fn detection_engine(&self) -> Arc<DetectionEngine> {
+        self.detection.clone()
+    }
+    
     pub fn uptime(&self) -> u64 {
-        self.start_time.map(|t| t.elapsed().as_secs()).unwrap_or(0)
+        let start = *self.start_time.lock();
+        start.map(|t| t.elapsed().as_secs()).unwrap_or(0)
+    }
+    
+    /// Save the learned analysis and detection state (entropy baselines,
+    /// partly filled sensor windows, fusion weights, EVP noise floors and
+    /// recent detections) to the database, so `restore` can resume from it
+    /// after a crash. Also runs every `database.checkpoint_interval_secs`
+    /// and at shutdown.
+    pub async fn checkpoint(&self) -> Result<()> {
+        let Some(ref db) = self.database else {
+            anyhow::bail!("Checkpointing needs a database");
+        };
+        checkpoint(db, &self.analysis, &self.detection).await
+    }
+    
+    /// Resume from the last checkpoint; returns whether there was one
+    pub async fn restore(&self) -> Result<bool> {
+        let Some(ref db) = self.database else {
+            anyhow::bail!("Restoring needs a database");
+        };
+        let analysis = self.analysis.load_checkpoint(db)?;
+        let detection = self.detection.load_checkpoint(db).await?;
+        if analysis || detection {
+            info!("Restored engine state from the last checkpoint");
+        }
+        Ok(analysis || detection)
+    }
+    
+    /// Re-run detection over a recorded session with the current settings.
+    ///
+    /// The new detections replace those from any earlier reprocessing of the
+    /// session and are stored tagged with it, so the originals stay as they
+    /// were recorded.
+    pub fn reprocess(&self, session_id: &str) -> Result<ReprocessStats> {
+        let Some(ref db) = self.database else {
+            anyhow::bail!("Reprocessing needs a database");
+        };
+        let (start, end) = db.session_range(session_id)?
+            .ok_or_else(|| anyhow::anyhow!("Unknown session {}", session_id))?;
+        
+        let mut run = self.detection.start_reprocessing();
+        let mut readings = 0;
+        let mut cursor = None;
+        loop {
+            let page = db.query_readings_page(start, end, None, cursor.as_ref(), REPROCESS_PAGE_SIZE)?;
+            for stored in &page.readings {
+                match stored.to_reading() {
+                    Ok(reading) => {
+                        self.detection.reprocess_reading(&mut run, &reading);
+                        readings += 1;
+                    }
+                    Err(e) => warn!("Skipping undecodable reading {} of session {}: {}", stored.id, session_id, e),
+                }
+            }
+            match page.next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        let reprocessed = run.finish();
+        
+        let originals = db.query_detections_between(start, end)?
+            .iter()
+            .map(|d| d.to_detection())
+            .collect::<Result<Vec<_>>>()?;
+        db.store_reprocessed_detections(session_id, &reprocessed)?;
+        
+        let tolerance_ms = self.detection.correlation_window_ms() as i64;
+        let matched = match_detections(&originals, &reprocessed, tolerance_ms);
+        let stats = ReprocessStats {
+            readings,
+            original_detections: originals.len(),
+            reprocessed_detections: reprocessed.len(),
+            added: reprocessed.len() - matched,
+            removed: originals.len() - matched,
+        };
+        
+        info!(
+            "Reprocessed session {}: {} readings, {} -> {} detections ({} changed)",
+            session_id, stats.readings, stats.original_detections, stats.reprocessed_detections, stats.changed()
+        );
+        Ok(stats)
+    }
+}
+
+/// Pairs of detections of the same type within `tolerance_ms` of each other,
+/// each detection used at most once
+fn match_detections(originals: &[Detection], reprocessed: &[Detection], tolerance_ms: i64) -> usize {
+    let mut used = vec![false; reprocessed.len()];
+    originals.iter()
+        .filter(|original| {
+            let candidate = reprocessed.iter().zip(used.iter_mut()).find(|(d, used)| {
+                !**used
+                    && d.detection_type == original.detection_type
+                    && (d.timestamp - original.timestamp).num_milliseconds().abs() <= tolerance_ms
+            });
+            match candidate {
+                Some((_, used)) => {
+                    *used = true;
+                    true
+                }
+                None => false,
+            }
+        })
+        .count()
+}
+
+fn log_task_exit(result: Result<TaskResult, JoinError>) {
+    match result {
+        Ok((name, Ok(()))) => debug!("{} task stopped", name),
+        Ok((name, Err(e))) => error!("{} task failed: {}", name, e),
+        Err(e) => error!("Pipeline task panicked: {}", e),
+    }
+}
+
+/// Apply the hot-reloadable part of a config change and warn about the rest
+fn apply_reload(detection: &DetectionEngine, analysis: &AnalysisEngine, old: &Config, new: &Config) {
+    let (Ok(serde_json::Value::Object(old_values)), Ok(serde_json::Value::Object(new_values))) =
+        (serde_json::to_value(old), serde_json::to_value(new)) else {
+        return;
+    };
+    
+    for (section, value) in &new_values {
+        if old_values.get(section) == Some(value) {
+            continue;
+        }
+        match section.as_str() {
+            "detection" => detection.apply_config(&new.detection),
+            "analysis" => analysis.apply_config(&new.analysis),
+            _ => warn!("Change to '{}' requires a restart, ignoring it", section),
+        }
+    }
+}
+
+/// Write readings (batched) and detections to the database until shutdown,
+/// then drain anything already published.
+///
+/// With a `recorder`, only what arrives while it is recording is stored.
+async fn persist(
+    db: Arc<Database>,
+    recorder: Option<Arc<Recorder>>,
+    mut readings: mpsc::Receiver<SensorReading>,
+    mut detections: broadcast::Receiver<Detection>,
+    flush_every: Duration,
+    mut shutdown: broadcast::Receiver<()>,
+) -> Result<()> {
+    let recording = || match recorder {
+        Some(ref r) => r.is_recording(),
+        None => true,
+    };
+    let store_detection = |d: &Detection| match db.store_detection(d) {
+        Ok(_) => {
+            if let Some(ref r) = recorder {
+                r.add_detection();
+            }
+        }
+        Err(e) => error!("Failed to store detection: {}", e),
+    };
+    let mut flush_requests = recorder.as_ref().and_then(|r| r.take_flush_requests());
+    let mut pending = Vec::with_capacity(PERSIST_BATCH_SIZE);
+    let mut flush = tokio::time::interval(flush_every);
+    
+    loop {
+        tokio::select! {
+            reading = readings.recv() => {
+                match reading {
+                    Some(r) if recording() => {
+                        pending.push(r);
+                        if pending.len() >= PERSIST_BATCH_SIZE {
+                            store_pending(&db, recorder.as_deref(), &mut pending);
+                        }
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+            detection = detections.recv() => {
+                match detection {
+                    Ok(d) if recording() => store_detection(&d),
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(n)) => warn!("Persistence dropped {} detections", n),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            Some(ack) = next_flush_request(&mut flush_requests) => {
+                store_pending(&db, recorder.as_deref(), &mut pending);
+                let _ = ack.send(());
+            }
+            _ = flush.tick() => store_pending(&db, recorder.as_deref(), &mut pending),
+            _ = shutdown.recv() => break,
+        }
+    }
+    
+    if recording() {
+        while let Ok(r) = readings.try_recv() {
+            pending.push(r);
+        }
+        drain(&mut detections, |d| store_detection(&d));
+    }
+    store_pending(&db, recorder.as_deref(), &mut pending);
+    
+    Ok(())
+}
+
+/// Persist the learned state of `analysis` and `detection` to `db`
+async fn checkpoint(db: &Database, analysis: &AnalysisEngine, detection: &DetectionEngine) -> Result<()> {
+    analysis.save_checkpoint(db)?;
+    detection.save_checkpoint(db).await
+}
+
+/// Checkpoint every `every` until shutdown; the final checkpoint is taken
+/// by `Engine::shutdown`
+async fn checkpoint_periodically(
+    db: Arc<Database>,
+    analysis: Arc<AnalysisEngine>,
+    detection: Arc<DetectionEngine>,
+    every: Duration,
+    mut shutdown: broadcast::Receiver<()>,
+) -> Result<()> {
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + every, every);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                match checkpoint(&db, &analysis, &detection).await {
+                    Ok(()) => debug!("Checkpointed engine state"),
+                    Err(e) => warn!("Failed to checkpoint engine state: {}", e),
+                }
+            }
+            _ = shutdown.recv() => return Ok(()),
+        }
+    }
+}
+
+/// Next request to flush buffered readings; never resolves without a recorder
+async fn next_flush_request(
+    requests: &mut Option<mpsc::UnboundedReceiver<oneshot::Sender<()>>>,
+) -> Option<oneshot::Sender<()>> {
+    match requests {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn store_pending(db: &Database, recorder: Option<&Recorder>, pending: &mut Vec<SensorReading>) {
+    if pending.is_empty() {
+        return;
+    }
+    match db.store_readings_batch(pending) {
+        Ok(_) => {
+            if let Some(r) = recorder {
+                r.add_readings(pending.len());
+            }
+        }
+        Err(e) => error!("Failed to store {} readings: {}", pending.len(), e),
+    }
+    pending.clear();
+}
+
+/// Mirror sensor health changes into the database's `sensors` table
+async fn track_sensor_status(
+    db: Arc<Database>,
+    sensors: Arc<SensorManager>,
+    mut shutdown: broadcast::Receiver<()>,
+) -> Result<()> {
+    let mut last: HashMap<String, HealthStatus> = HashMap::new();
+    let mut poll = tokio::time::interval(SENSOR_STATUS_INTERVAL);
+    
+    loop {
+        tokio::select! {
+            _ = poll.tick() => {
+                for health in sensors.get_all_health().await {
+                    if last.get(&health.sensor_id) == Some(&health.health) {
+                        continue;
+                    }
+                    match db.update_sensor_status(&health) {
+                        Ok(()) => {
+                            debug!("Sensor {} is now {:?}", health.sensor_id, health.health);
+                            last.insert(health.sensor_id.clone(), health.health);
+                        }
+                        Err(e) => warn!("Failed to update status for {}: {}", health.sensor_id, e),
+                    }
+                }
+            }
+            _ = shutdown.recv() => break,
+        }
+    }
+    
+    Ok(())
+}
+
+/// Forward readings and detections to the streaming outputs until shutdown
+async fn forward(
+    streaming: Arc<StreamingManager>,
+    mut readings: mpsc::Receiver<SensorReading>,
+    mut detections: broadcast::Receiver<Detection>,
+    mut shutdown: broadcast::Receiver<()>,
+) -> Result<()> {
+    loop {
+        tokio::select! {
+            Some(reading) = readings.recv() => {
+                if let Err(e) = streaming.publish_reading(&reading).await {
+                    debug!("Failed to stream reading: {}", e);
+                }
+            }
+            Ok(detection) = detections.recv() => {
+                if let Err(e) = streaming.publish_detection(&detection).await {
+                    warn!("Failed to stream detection: {}", e);
+                }
+            }
+            _ = shutdown.recv() => break,
+        }
+    }
+    
+    let mut remaining_readings = Vec::new();
+    let mut remaining_detections = Vec::new();
+    while let Ok(reading) = readings.try_recv() {
+        remaining_readings.push(reading);
+    }
+    drain(&mut detections, |d| remaining_detections.push(d));
+    
+    for reading in remaining_readings {
+        if let Err(e) = streaming.publish_reading(&reading).await {
+            debug!("Failed to stream reading: {}", e);
+        }
+    }
+    for detection in remaining_detections {
+        if let Err(e) = streaming.publish_detection(&detection).await {
+            warn!("Failed to stream detection: {}", e);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Consume everything still buffered in a broadcast receiver
+fn drain<T: Clone>(rx: &mut broadcast::Receiver<T>, mut f: impl FnMut(T)) {
+    loop {
+        match rx.try_recv() {
+            Ok(item) => f(item),
+            Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+            Err(_) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+    use crate::detection::{BeamBreak, DetectionPayload, DetectionType};
+    use crate::sensors::SensorType;
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_demo_engine_produces_readings() {
+        let config = Config { demo_mode: true, ..Default::default() };
+        let engine = Arc::new(Engine::new(config).await.unwrap());
+        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        
+        let runner = engine.clone();
+        let handle = tokio::spawn(async move { runner.run(shutdown_rx).await });
+        
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let state = engine.state().await;
+        assert!(state.running);
+        assert!(state.total_readings > 0, "no readings flowed through the event bus");
+        
+        shutdown_tx.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(10), handle).await.unwrap().unwrap().unwrap();
+        assert!(!engine.state().await.running);
+    }
+    
+    #[tokio::test]
+    async fn test_reload_applies_analysis_and_detection_settings() {
+        let mut old = Config::default();
+        old.detection.sensor_weights.insert("EMFProbe".to_string(), 0.5);
+        let event_bus = Arc::new(EventBus::new(16));
+        let detection = DetectionEngine::new(Arc::new(old.clone()), event_bus.clone()).await.unwrap();
+        let analysis = AnalysisEngine::new(Arc::new(old.clone()), event_bus).await.unwrap();
+        assert_eq!(detection.sensor_weight(SensorType::EMFProbe), 0.5);
+        
+        let mut new = old.clone();
+        new.detection.sensor_weights.clear();
+        new.analysis.entropy_window = 40;
+        apply_reload(&detection, &analysis, &old, &new);
+        
+        // A weight dropped from the config goes back to its default
+        assert_eq!(detection.sensor_weight(SensorType::EMFProbe), 0.70);
+        
+        // The new window applies from the next reading
+        let emf = |value| SensorReading::new("emf", SensorType::EMFProbe, vec![value]);
+        for value in 0..39 {
+            assert!(analysis.accumulate(emf(value as f64)).is_none());
+        }
+        assert_eq!(analysis.accumulate(emf(39.0)).unwrap().data.len(), 40);
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_reading_before_shutdown_is_persisted() {
+        let db_config = DatabaseConfig {
+            path: std::env::temp_dir().join(format!("glowbarn-shutdown-{}.db", uuid::Uuid::new_v4())),
+            ..Default::default()
+        };
+        let db = Arc::new(Database::open(&db_config, None).unwrap());
+        
+        let config = Config { demo_mode: false, ..Default::default() };
+        let mut engine = Engine::new(config).await.unwrap().with_database(db.clone());
+        engine.start().await.unwrap();
+        
+        engine.event_bus().publish_reading(SensorReading::new("last-words", SensorType::EMFProbe, vec![4.2]));
+        engine.shutdown().await.unwrap();
+        
+        let stored = db.query_readings(
+            chrono::Utc::now() - chrono::Duration::minutes(1),
+            chrono::Utc::now() + chrono::Duration::minutes(1),
+            Some("last-words"),
+            None,
+        ).unwrap();
+        assert_eq!(stored.len(), 1);
+        
+        let _ = std::fs::remove_file(&db_config.path);
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_only_recorded_readings_are_persisted() {
+        let db_config = DatabaseConfig {
+            path: std::env::temp_dir().join(format!("glowbarn-recording-{}.db", uuid::Uuid::new_v4())),
+            ..Default::default()
+        };
+        let db = Arc::new(Database::open(&db_config, None).unwrap());
+        
+        let config = Config { demo_mode: false, ..Default::default() };
+        let mut engine = Engine::new(config).await.unwrap().with_database(db.clone()).with_recording().unwrap();
+        engine.start().await.unwrap();
+        let recorder = engine.recorder().unwrap();
+        let bus = engine.event_bus();
+        
+        bus.publish_reading(SensorReading::new("before", SensorType::EMFProbe, vec![1.0]));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        
+        let session = recorder.start().unwrap();
+        bus.publish_reading(SensorReading::new("during", SensorType::EMFProbe, vec![2.0]));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        recorder.stop().await.unwrap();
+        
+        bus.publish_reading(SensorReading::new("after", SensorType::EMFProbe, vec![3.0]));
+        engine.shutdown().await.unwrap();
+        
+        let stored = db.query_readings(
+            chrono::Utc::now() - chrono::Duration::minutes(1),
+            chrono::Utc::now() + chrono::Duration::minutes(1),
+            None,
+            None,
+        ).unwrap();
+        let ids: Vec<_> = stored.iter().map(|r| r.sensor_id.as_str()).collect();
+        assert_eq!(ids, vec!["during"]);
+        assert_eq!(recorder.counts(), (1, 0));
+        assert!(db.session_range(&session).unwrap().is_some());
+        
+        let _ = std::fs::remove_file(&db_config.path);
+    }
+    
+    #[tokio::test]
+    async fn test_reprocess_with_lower_threshold_finds_more() {
+        let db_config = DatabaseConfig {
+            path: std::env::temp_dir().join(format!("glowbarn-reprocess-{}.db", uuid::Uuid::new_v4())),
+            ..Default::default()
+        };
+        let db = Arc::new(Database::open(&db_config, None).unwrap());
+        
+        // Three sensors spiking together, recorded with a strict threshold
+        let session = db.start_session(None).unwrap();
+        let mut spike = vec![0.0; 9];
+        spike.push(10.0);
+        let mut readings: Vec<_> = (0..5)
+            .flat_map(|_| ["emf-1", "geophone-1", "ir-1"])
+            .map(|id| SensorReading::new(id, SensorType::EMFProbe, spike.clone()))
+            .collect();
+        // and a beam broken and restored
+        readings.push(SensorReading::new("grid-1", SensorType::LaserGrid, vec![0.98, 0.0]));
+        readings.push(SensorReading::new("grid-1", SensorType::LaserGrid, vec![0.98, 0.98]));
+        db.store_readings_batch(&readings).unwrap();
+        
+        let mut config = Config { demo_mode: false, ..Default::default() };
+        config.detection.min_confidence = 0.95;
+        let engine = Engine::new(config.clone()).await.unwrap().with_database(db.clone());
+        
+        // Detections recorded by the live path
+        for reading in &readings {
+            engine.detection.process_reading(reading).await;
+        }
+        let original = engine.detection.get_recent_detections(usize::MAX).await;
+        let beam = original.iter().find(|d| d.detection_type == DetectionType::LaserInterruption).unwrap();
+        assert!(matches!(beam.payload, Some(DetectionPayload::BeamBreak(BeamBreak { dwell_ms: Some(_), .. }))));
+        for detection in &original {
+            db.store_detection(detection).unwrap();
+        }
+        db.end_session(&session, readings.len() as u64, original.len() as u64).unwrap();
+        
+        // Same settings, same detections
+        let strict = engine.reprocess(&session).unwrap();
+        assert_eq!(strict.readings, readings.len());
+        assert_eq!(strict.original_detections, original.len());
+        assert_eq!(strict.changed(), 0);
+        
+        config.detection.min_confidence = 0.3;
+        engine.detection.apply_config(&config.detection);
+        let relaxed = engine.reprocess(&session).unwrap();
+        assert!(relaxed.reprocessed_detections > strict.reprocessed_detections);
+        assert!(relaxed.reprocessed_detections > relaxed.original_detections);
+        assert!(relaxed.changed() > 0);
+        
+        // Reprocessed detections replace each other and never mix with the originals
+        let (start, end) = db.session_range(&session).unwrap().unwrap();
+        assert_eq!(db.query_detections(start, end, None, None).unwrap().len(), original.len());
+        assert_eq!(db.query_reprocessed_detections(&session).unwrap().len(), relaxed.reprocessed_detections);
+        
+        let _ = std::fs::remove_file(&db_config.path);
+    }
+    
+    #[tokio::test]
+    async fn test_live_correlated_detection_carries_fusion_uncertainty() {
+        let mut config = Config { demo_mode: false, ..Default::default() };
+        // Keep every correlation, whatever its fused confidence
+        config.detection.min_confidence = 0.0;
+        let engine = Engine::new(config).await.unwrap();
+        let mut published = engine.event_bus.subscribe_detections();
+        
+        let mut spike = vec![0.0; 9];
+        spike.push(10.0);
+        for _ in 0..5 {
+            for sensor_id in ["emf-1", "geophone-1", "ir-1"] {
+                engine.detection.process_reading(&SensorReading::new(sensor_id, SensorType::EMFProbe, spike.clone())).await;
+            }
+        }
+        
+        let correlated: Vec<Detection> = engine.detection.get_recent_detections(usize::MAX).await
+            .into_iter()
+            .filter(|d| d.detection_type == DetectionType::CorrelatedAnomaly)
+            .collect();
+        assert!(!correlated.is_empty(), "coincident spikes correlate");
+        // Dempster-Shafer is the default method, so the mass it leaves
+        // uncommitted comes through on the live path
+        for detection in &correlated {
+            assert!(detection.uncertainty > 0.0 && detection.uncertainty < 1.0, "uncertainty {}", detection.uncertainty);
+        }
+        
+        // and reaches subscribers unchanged
+        let mut seen = 0;
+        while let Ok(detection) = published.try_recv() {
+            if let Some(recorded) = correlated.iter().find(|d| d.id == detection.id) {
+                assert_eq!(detection.uncertainty, recorded.uncertainty);
+                seen += 1;
+            }
+        }
+        assert_eq!(seen, correlated.len());
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_config_edit_updates_running_detection() {
+        // The watcher applies GLOWBARN_* overrides on every reload
+        let _env = crate::config::ENV_LOCK.lock().await;
+        let path = std::env::temp_dir().join(format!("glowbarn-config-{}.toml", uuid::Uuid::new_v4()));
+        let config = Config { demo_mode: false, ..Default::default() };
+        config.save(&path).unwrap();
+        
+        let mut engine = Engine::new(config.clone()).await.unwrap();
+        engine.start().await.unwrap();
+        engine.watch_config(&path).unwrap();
+        
+        let mut edited = config;
+        edited.detection.min_confidence = 0.8;
+        edited.save(&path).unwrap();
+        
+        let detection = engine.detection_engine();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while detection.min_confidence() != 0.8 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        }).await.expect("threshold change was not applied");
+        
+        engine.shutdown().await.unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+    
+    #[tokio::test]
+    async fn test_restored_checkpoint_scores_like_before_the_crash() {
+        use crate::detection::{AnnotationStatus, FusionEngine};
+        
+        let db_config = DatabaseConfig {
+            path: std::env::temp_dir().join(format!("glowbarn-checkpoint-{}.db", uuid::Uuid::new_v4())),
+            ..Default::default()
+        };
+        let db = Arc::new(Database::open(&db_config, None).unwrap());
+        let mut config = Config { demo_mode: false, ..Default::default() };
+        config.detection.annotation_feedback = true;
+        
+        let window = |i: usize, gain: f64| {
+            let data = (0..256)
+                .map(|t| gain * ((t * (i % 5 + 3)) as f64 * 0.05).sin() + ((t * 7919 + i) % 17) as f64 * 0.01)
+                .collect();
+            SensorReading::new("emf", SensorType::EMFProbe, data)
+        };
+        let score = |engine: &Engine, reading: &SensorReading| {
+            let mut result = engine.analysis.analyze_reading(reading).unwrap();
+            engine.analysis.apply_baseline(&mut result);
+            result.baseline
+        };
+        
+        // Warm up a baseline and mark a detection as a false positive
+        let engine = Engine::new(config.clone()).await.unwrap().with_database(db.clone());
+        for i in 0..30 {
+            score(&engine, &window(i, 1.0));
+        }
+        let fused = FusionEngine::new().weighted_fusion(&[window(0, 1.0)]);
+        let detection = engine.detection.detection_from_fusion(fused);
+        assert!(engine.detection.apply_annotation(&detection, None, AnnotationStatus::FalsePositive));
+        engine.checkpoint().await.unwrap();
+        
+        let probe = window(99, 3.0);
+        let before = score(&engine, &probe).expect("baseline should be warm");
+        let feedback = engine.detection.checkpoint().await.feedback;
+        assert_eq!(feedback, vec![(SensorType::EMFProbe, -1)]);
+        drop(engine);
+        
+        // A fresh engine has to re-warm; a restored one picks up where it left off
+        let cold = Engine::new(config.clone()).await.unwrap();
+        assert!(score(&cold, &probe).is_none());
+        
+        // The configured weight was edited meanwhile; the feedback applies on top
+        let mut config = config;
+        config.detection.sensor_weights.insert("EMFProbe".to_string(), 0.5);
+        let restored = Engine::new(config).await.unwrap().with_database(db);
+        assert!(restored.restore().await.unwrap());
+        let after = score(&restored, &probe).expect("baseline should be restored");
+        assert!((after.spectral_z - before.spectral_z).abs() < 1e-9, "{:?} vs {:?}", after, before);
+        assert!((after.shannon_z - before.shannon_z).abs() < 1e-9, "{:?} vs {:?}", after, before);
+        assert_eq!(after.is_anomalous, before.is_anomalous);
+        assert_eq!(restored.detection.checkpoint().await.feedback, feedback);
+        let weight = restored.detection.sensor_weight(SensorType::EMFProbe);
+        assert!((weight - 0.45).abs() < 1e-12, "weight {}", weight);
+        
+        let _ = std::fs::remove_file(&db_config.path);
     }
 }
@@ -4,14 +4,27 @@
 
 //! Event bus for inter-component communication
 
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use parking_lot::Mutex;
 use tokio::sync::{broadcast, mpsc};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
+use crate::analysis::AnalysisResult;
+use crate::config::BackpressurePolicy;
 use crate::sensors::SensorReading;
 use crate::detection::Detection;
 
+/// Default number of readings retained for replay
+pub const DEFAULT_REPLAY_CAPACITY: usize = 1000;
+
+/// Default number of detections retained for replay, kept apart from the
+/// readings so a burst of them can't push detections out
+pub const DEFAULT_DETECTION_REPLAY_CAPACITY: usize = 200;
+
 /// Event types in the system
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EventType {
@@ -40,30 +53,160 @@ pub enum EventPayload {
     Error { code: u32, message: String },
 }
 
+/// Readings one sensor published, and those a full durable queue refused
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishStats {
+    pub sensor_id: String,
+    pub emitted: u64,
+    pub dropped: u64,
+}
+
 /// Central event bus for pub/sub communication
 pub struct EventBus {
     reading_tx: broadcast::Sender<SensorReading>,
     detection_tx: broadcast::Sender<Detection>,
+    analysis_tx: broadcast::Sender<AnalysisResult>,
     event_tx: broadcast::Sender<Event>,
+    /// Bounded queues feeding durable consumers (database, exporters)
+    durable_readings: Mutex<Vec<mpsc::Sender<SensorReading>>>,
+    /// Readings a full durable queue had to refuse
+    dropped_readings: AtomicU64,
+    /// Readings broadcast subscribers reported skipping after falling behind
+    lagged_readings: AtomicU64,
     event_counter: std::sync::atomic::AtomicU64,
+    /// Most recent readings, oldest first
+    recent_readings: Mutex<VecDeque<Event>>,
+    reading_replay_capacity: usize,
+    /// Most recent detections, oldest first
+    recent_detections: Mutex<VecDeque<Event>>,
+    detection_replay_capacity: usize,
+    backpressure: BackpressurePolicy,
+    backpressure_block: Duration,
+    /// Per-sensor counts for every published reading
+    publish_stats: Arc<Mutex<HashMap<String, PublishStats>>>,
 }
 
 impl EventBus {
     pub fn new(capacity: usize) -> Self {
+        Self::with_replay(capacity, DEFAULT_REPLAY_CAPACITY, DEFAULT_DETECTION_REPLAY_CAPACITY)
+    }
+    
+    /// Create a bus that retains the last `reading_replay_capacity` readings
+    /// and, separately, the last `detection_replay_capacity` detections
+    pub fn with_replay(capacity: usize, reading_replay_capacity: usize, detection_replay_capacity: usize) -> Self {
         let (reading_tx, _) = broadcast::channel(capacity);
         let (detection_tx, _) = broadcast::channel(capacity);
+        let (analysis_tx, _) = broadcast::channel(capacity);
         let (event_tx, _) = broadcast::channel(capacity);
         
         Self {
             reading_tx,
             detection_tx,
+            analysis_tx,
             event_tx,
+            durable_readings: Mutex::new(Vec::new()),
+            dropped_readings: AtomicU64::new(0),
+            lagged_readings: AtomicU64::new(0),
             event_counter: std::sync::atomic::AtomicU64::new(0),
+            recent_readings: Mutex::new(VecDeque::with_capacity(reading_replay_capacity)),
+            reading_replay_capacity,
+            recent_detections: Mutex::new(VecDeque::with_capacity(detection_replay_capacity)),
+            detection_replay_capacity,
+            backpressure: BackpressurePolicy::default(),
+            backpressure_block: Duration::ZERO,
+            publish_stats: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+    
+    /// How sensor publications behave when a durable queue is full; `block`
+    /// bounds the wait under `BackpressurePolicy::Block`
+    pub fn with_backpressure(mut self, policy: BackpressurePolicy, block: Duration) -> Self {
+        self.backpressure = policy;
+        self.backpressure_block = block;
+        self
+    }
+    
+    /// Publish a reading from a sensor task, counting it per sensor.
+    ///
+    /// Broadcast subscribers are never waited on: each one that falls behind
+    /// loses its own oldest readings and sees `Lagged`. Durable queues are
+    /// checked one by one, so a full queue only costs its own consumer the
+    /// reading - skipped or, under `BackpressurePolicy::Block`, delivered
+    /// once room frees up within the block time. A reading any queue refused
+    /// counts as dropped. Returns whether every queue took it.
+    ///
+    /// May wait under `Block`; don't hold locks other tasks need across it.
+    pub async fn publish_sensor_reading(&self, reading: SensorReading) -> bool {
+        let _ = self.reading_tx.send(reading.clone());
+        
+        let mut delivered = true;
+        for (tx, reading) in self.offer_durable(&reading) {
+            let refused = match self.backpressure {
+                BackpressurePolicy::Skip => true,
+                BackpressurePolicy::Block => matches!(
+                    tx.send_timeout(reading, self.backpressure_block).await,
+                    Err(mpsc::error::SendTimeoutError::Timeout(_))
+                ),
+            };
+            if refused {
+                self.dropped_readings.fetch_add(1, Ordering::Relaxed);
+                delivered = false;
+            }
         }
+        
+        self.count_publish(&reading.sensor_id, delivered);
+        self.publish_event(EventType::SensorReading, EventPayload::Reading(reading));
+        delivered
+    }
+    
+    /// Try every durable queue without waiting, forgetting closed ones, and
+    /// hand back the full queues along with the reading they refused
+    fn offer_durable(&self, reading: &SensorReading) -> Vec<(mpsc::Sender<SensorReading>, SensorReading)> {
+        let mut full = Vec::new();
+        self.durable_readings.lock().retain(|tx| match tx.try_send(reading.clone()) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(reading)) => {
+                full.push((tx.clone(), reading));
+                true
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        });
+        full
+    }
+    
+    fn count_publish(&self, sensor_id: &str, emitted: bool) {
+        let mut stats = self.publish_stats.lock();
+        let entry = stats.entry(sensor_id.to_string()).or_insert_with(|| PublishStats {
+            sensor_id: sensor_id.to_string(),
+            ..Default::default()
+        });
+        if emitted {
+            entry.emitted += 1;
+        } else {
+            entry.dropped += 1;
+        }
+    }
+    
+    /// Emitted/dropped counts for every sensor that has published, by id
+    pub fn publish_stats(&self) -> Vec<PublishStats> {
+        sorted_publish_stats(&self.publish_stats.lock())
     }
     
+    /// Shared handle to the per-sensor publication counters
+    pub fn publish_stats_handle(&self) -> Arc<Mutex<HashMap<String, PublishStats>>> {
+        self.publish_stats.clone()
+    }
+    
+    /// Publish a reading without ever waiting, whatever the backpressure
+    /// policy: a full durable queue refuses it and the loss is counted, per
+    /// sensor too, instead of going unnoticed
     pub fn publish_reading(&self, reading: SensorReading) {
         let _ = self.reading_tx.send(reading.clone());
+        
+        let refused = self.offer_durable(&reading).len() as u64;
+        self.dropped_readings.fetch_add(refused, Ordering::Relaxed);
+        
+        self.count_publish(&reading.sensor_id, refused == 0);
         self.publish_event(EventType::SensorReading, EventPayload::Reading(reading));
     }
     
@@ -72,6 +215,11 @@ impl EventBus {
         self.publish_event(EventType::Detection, EventPayload::Detection(detection));
     }
     
+    /// Per-reading analysis features; not retained for replay
+    pub fn publish_analysis(&self, result: AnalysisResult) {
+        let _ = self.analysis_tx.send(result);
+    }
+    
     pub fn publish_alert(&self, level: &str, message: &str) {
         self.publish_event(
             EventType::Alert,
@@ -100,18 +248,268 @@ impl EventBus {
             timestamp: Utc::now(),
             payload,
         };
+        
+        let retained = match event.event_type {
+            EventType::SensorReading => Some((&self.recent_readings, self.reading_replay_capacity)),
+            EventType::Detection => Some((&self.recent_detections, self.detection_replay_capacity)),
+            _ => None,
+        };
+        if let Some((recent, capacity)) = retained.filter(|&(_, capacity)| capacity > 0) {
+            let mut recent = recent.lock();
+            if recent.len() >= capacity {
+                recent.pop_front();
+            }
+            recent.push_back(event.clone());
+        }
+        
         let _ = self.event_tx.send(event);
     }
     
+    /// Up to `n` of the most recent readings and detections, oldest first.
+    ///
+    /// New subscribers can use this to backfill before consuming live events.
+    pub fn replay_recent(&self, n: usize) -> Vec<Event> {
+        let mut events: Vec<Event> = self.recent_readings.lock().iter()
+            .chain(self.recent_detections.lock().iter())
+            .cloned()
+            .collect();
+        events.sort_by_key(|e| e.id);
+        events.split_off(events.len().saturating_sub(n))
+    }
+    
+    /// Up to `n` of the most recent detections, oldest first, however many
+    /// readings were published since
+    pub fn replay_detections(&self, n: usize) -> Vec<Event> {
+        let recent = self.recent_detections.lock();
+        let skip = recent.len().saturating_sub(n);
+        recent.iter().skip(skip).cloned().collect()
+    }
+    
     pub fn subscribe_readings(&self) -> broadcast::Receiver<SensorReading> {
         self.reading_tx.subscribe()
     }
     
+    /// Every reading through a bounded queue of `capacity`, for consumers that
+    /// must not lose data silently.
+    ///
+    /// Unlike `subscribe_readings`, a slow consumer doesn't lag past old
+    /// readings: new ones are refused once the queue is full and counted in
+    /// `dropped_readings`.
+    pub fn subscribe_readings_durable(&self, capacity: usize) -> mpsc::Receiver<SensorReading> {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        self.durable_readings.lock().push(tx);
+        rx
+    }
+    
+    /// Total readings refused by full durable queues
+    pub fn dropped_readings(&self) -> u64 {
+        self.dropped_readings.load(Ordering::Relaxed)
+    }
+    
+    /// Count readings a `subscribe_readings` receiver skipped, as reported by
+    /// its `Lagged` error; the bus can't see which sensors they came from
+    pub fn record_lagged(&self, skipped: u64) {
+        self.lagged_readings.fetch_add(skipped, Ordering::Relaxed);
+    }
+    
+    /// Total readings broadcast subscribers reported skipping, summed over
+    /// subscribers
+    pub fn lagged_readings(&self) -> u64 {
+        self.lagged_readings.load(Ordering::Relaxed)
+    }
+    
     pub fn subscribe_detections(&self) -> broadcast::Receiver<Detection> {
         self.detection_tx.subscribe()
     }
     
+    pub fn subscribe_analysis(&self) -> broadcast::Receiver<AnalysisResult> {
+        self.analysis_tx.subscribe()
+    }
+    
     pub fn subscribe_events(&self) -> broadcast::Receiver<Event> {
         self.event_tx.subscribe()
     }
 }
+
+/// Publication counters ordered by sensor id
+pub fn sorted_publish_stats(stats: &HashMap<String, PublishStats>) -> Vec<PublishStats> {
+    let mut stats: Vec<PublishStats> = stats.values().cloned().collect();
+    stats.sort_by(|a, b| a.sensor_id.cmp(&b.sensor_id));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::SensorType;
+    
+    fn reading(value: f64) -> SensorReading {
+        SensorReading::new("emf_1", SensorType::EMFProbe, vec![value])
+    }
+    
+    #[test]
+    fn test_late_subscriber_replays_recent() {
+        let bus = EventBus::with_replay(16, 3, 3);
+        for i in 0..5 {
+            bus.publish_reading(reading(i as f64));
+        }
+        bus.publish_alert("info", "not replayed");
+        
+        let _late = bus.subscribe_events();
+        let replayed = bus.replay_recent(10);
+        assert_eq!(replayed.len(), 3);
+        
+        let values: Vec<f64> = replayed.iter().map(|e| match &e.payload {
+            EventPayload::Reading(r) => r.data[0],
+            other => panic!("unexpected payload {:?}", other),
+        }).collect();
+        assert_eq!(values, vec![2.0, 3.0, 4.0]);
+        
+        assert_eq!(bus.replay_recent(1).len(), 1);
+    }
+    
+    #[test]
+    fn test_detections_outlive_a_flood_of_readings() {
+        let bus = EventBus::with_replay(16, 3, 2);
+        bus.publish_detection(Detection {
+            id: "d1".to_string(),
+            timestamp: Utc::now(),
+            detection_type: crate::detection::DetectionType::EMFSpike,
+            confidence: 0.8,
+            uncertainty: 0.0,
+            severity: crate::detection::Severity::High,
+            sensors: Vec::new(),
+            entropy_deviation: 0.0,
+            anomaly_count: 0,
+            correlation_score: 0.0,
+            classification: None,
+            location: None,
+            payload: None,
+            data_window_start: Utc::now(),
+            data_window_end: Utc::now(),
+        });
+        for i in 0..10 {
+            bus.publish_reading(reading(i as f64));
+        }
+        
+        let detections = bus.replay_detections(10);
+        assert_eq!(detections.len(), 1);
+        assert!(matches!(detections[0].payload, EventPayload::Detection(_)));
+        
+        // Merged in publication order: the detection came first
+        let replayed = bus.replay_recent(10);
+        assert_eq!(replayed.len(), 4);
+        assert!(matches!(replayed[0].payload, EventPayload::Detection(_)));
+        assert_eq!(bus.replay_recent(3).len(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_slow_durable_consumer_counts_drops() {
+        let bus = Arc::new(EventBus::new(16));
+        let mut durable = bus.subscribe_readings_durable(8);
+        
+        // Publish far faster than the consumer keeps up
+        let publisher = bus.clone();
+        let producer = tokio::spawn(async move {
+            for i in 0..200 {
+                publisher.publish_reading(reading(i as f64));
+                tokio::task::yield_now().await;
+            }
+        });
+        let mut received = 0;
+        while !producer.is_finished() {
+            if durable.try_recv().is_ok() {
+                received += 1;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        producer.await.unwrap();
+        while durable.try_recv().is_ok() {
+            received += 1;
+        }
+        
+        assert!(bus.dropped_readings() > 0);
+        assert_eq!(received + bus.dropped_readings(), 200);
+        
+        drop(durable);
+        bus.publish_reading(reading(0.0));
+        assert!(bus.durable_readings.lock().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_full_durable_queue_counts_sensor_drops() {
+        // Skip: a queue nobody drains takes 8 readings and refuses the rest
+        let bus = EventBus::new(8);
+        let mut stalled = bus.subscribe_readings_durable(8);
+        for i in 0..100 {
+            bus.publish_sensor_reading(reading(i as f64)).await;
+        }
+        let mut received = 0u64;
+        while stalled.try_recv().is_ok() {
+            received += 1;
+        }
+        
+        let stats = bus.publish_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].sensor_id, "emf_1");
+        assert_eq!(stats[0].emitted, received);
+        assert_eq!(stats[0].emitted, 8);
+        assert_eq!(stats[0].dropped, 92);
+        assert_eq!(bus.dropped_readings(), 92);
+        
+        // A stalled broadcast subscriber only loses its own readings
+        let bus = EventBus::new(8);
+        let _stalled = bus.subscribe_readings();
+        let mut durable = bus.subscribe_readings_durable(100);
+        for i in 0..100 {
+            assert!(bus.publish_sensor_reading(reading(i as f64)).await);
+        }
+        let mut received = 0;
+        while durable.try_recv().is_ok() {
+            received += 1;
+        }
+        assert_eq!(received, 100);
+        assert_eq!(bus.publish_stats()[0].dropped, 0);
+        
+        // Block: the producer waits for a slow consumer, which sees everything
+        let bus = Arc::new(EventBus::new(8).with_backpressure(BackpressurePolicy::Block, Duration::from_millis(500)));
+        let mut slow = bus.subscribe_readings_durable(8);
+        let consumer = tokio::spawn(async move {
+            for _ in 0..50 {
+                slow.recv().await.expect("durable queue closed");
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        });
+        for i in 0..50 {
+            assert!(bus.publish_sensor_reading(reading(i as f64)).await);
+        }
+        consumer.await.unwrap();
+        
+        let stats = bus.publish_stats();
+        assert_eq!((stats[0].emitted, stats[0].dropped), (50, 0));
+    }
+    
+    #[tokio::test]
+    async fn test_plain_publish_counts_drops_and_lag() {
+        // publish_reading never waits, and counts what it couldn't deliver
+        let bus = EventBus::new(4).with_backpressure(BackpressurePolicy::Block, Duration::from_secs(5));
+        let mut lagging = bus.subscribe_readings();
+        let _stalled = bus.subscribe_readings_durable(8);
+        for i in 0..20 {
+            bus.publish_reading(reading(i as f64));
+        }
+        
+        let stats = bus.publish_stats();
+        assert_eq!((stats[0].emitted, stats[0].dropped), (8, 12));
+        assert_eq!(bus.dropped_readings(), 12);
+        
+        // Lag is only known to the subscriber, which reports it back
+        let skipped = match lagging.recv().await {
+            Err(broadcast::error::RecvError::Lagged(n)) => n,
+            _ => panic!("the broadcast subscriber should have lagged"),
+        };
+        assert_eq!(skipped, 16);
+        bus.record_lagged(skipped);
+        assert_eq!(bus.lagged_readings(), 16);
+    }
+}
@@ -7,10 +7,17 @@
 mod engine;
 mod scheduler;
 mod event_bus;
+mod monitor;
+mod recorder;
 
-pub use engine::Engine;
-pub use scheduler::Scheduler;
-pub use event_bus::{EventBus, Event, EventType};
+pub use engine::{Engine, ReprocessStats};
+pub use scheduler::{Scheduler, Priority, SamplingStats};
+pub use event_bus::{
+    EventBus, Event, EventType, EventPayload, PublishStats, sorted_publish_stats, DEFAULT_REPLAY_CAPACITY,
+    DEFAULT_DETECTION_REPLAY_CAPACITY,
+};
+pub use monitor::{SystemMonitor, SystemMetrics};
+pub use recorder::Recorder;
 
 use crate::sensors::SensorReading;
 use crate::detection::Detection;
@@ -24,9 +31,20 @@ pub struct SystemState {
     pub sensors_active: usize,
     pub total_readings: u64,
     pub total_detections: u64,
+    /// Readings durable consumers (database, exporters) couldn't keep up with
+    #[serde(default)]
+    pub dropped_readings: u64,
+    /// Readings the analysis and detection engines skipped after falling
+    /// behind the broadcast channel
+    #[serde(default)]
+    pub lagged_readings: u64,
+    /// Per-sensor readings published vs dropped on a backed-up reading channel
+    #[serde(default)]
+    pub publish_stats: Vec<PublishStats>,
     pub uptime_seconds: u64,
     pub cpu_usage: f32,
     pub memory_usage: f32,
+    pub thread_count: usize,
     pub last_detection: Option<DateTime<Utc>>,
 }
 
@@ -37,9 +55,13 @@ impl Default for SystemState {
             sensors_active: 0,
             total_readings: 0,
             total_detections: 0,
+            dropped_readings: 0,
+            lagged_readings: 0,
+            publish_stats: Vec::new(),
             uptime_seconds: 0,
             cpu_usage: 0.0,
             memory_usage: 0.0,
+            thread_count: 0,
             last_detection: None,
         }
     }
@@ -0,0 +1,115 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Process resource monitoring
+
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use sysinfo::{Pid, System};
+
+/// Minimum time between samples; sysinfo needs a gap between refreshes to
+/// compute CPU usage, and sampling more often only burns CPU itself
+pub const MIN_SAMPLE_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Snapshot of this process's resource usage
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemMetrics {
+    /// CPU usage as a percentage of total machine capacity (0-100)
+    pub cpu_percent: f32,
+    /// Resident memory in bytes
+    pub memory_bytes: u64,
+    /// Number of OS threads (0 where the platform doesn't report it)
+    pub thread_count: usize,
+}
+
+impl SystemMetrics {
+    pub fn memory_mb(&self) -> f64 {
+        self.memory_bytes as f64 / (1024.0 * 1024.0)
+    }
+}
+
+/// Samples real CPU, memory and thread counts for the current process
+pub struct SystemMonitor {
+    system: System,
+    pid: Pid,
+    num_cpus: f32,
+    min_interval: Duration,
+    last_sample: Option<Instant>,
+    latest: SystemMetrics,
+}
+
+impl SystemMonitor {
+    pub fn new() -> Result<Self> {
+        let pid = sysinfo::get_current_pid().map_err(|e| anyhow!("Cannot determine process id: {}", e))?;
+        let num_cpus = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1) as f32;
+        
+        let mut monitor = Self {
+            system: System::new(),
+            pid,
+            num_cpus,
+            min_interval: MIN_SAMPLE_INTERVAL,
+            last_sample: None,
+            latest: SystemMetrics::default(),
+        };
+        monitor.refresh();
+        
+        Ok(monitor)
+    }
+    
+    /// Override the sampling guard (never below `MIN_SAMPLE_INTERVAL`)
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = interval.max(MIN_SAMPLE_INTERVAL);
+        self
+    }
+    
+    /// Return current metrics, refreshing only if the guard interval has elapsed
+    pub fn sample(&mut self) -> SystemMetrics {
+        let due = self.last_sample
+            .map(|t| t.elapsed() >= self.min_interval)
+            .unwrap_or(true);
+        
+        if due {
+            self.refresh();
+        }
+        
+        self.latest
+    }
+    
+    /// Most recent metrics without refreshing
+    pub fn latest(&self) -> SystemMetrics {
+        self.latest
+    }
+    
+    fn refresh(&mut self) {
+        self.last_sample = Some(Instant::now());
+        
+        if !self.system.refresh_process(self.pid) {
+            return;
+        }
+        
+        if let Some(process) = self.system.process(self.pid) {
+            self.latest = SystemMetrics {
+                cpu_percent: (process.cpu_usage() / self.num_cpus).clamp(0.0, 100.0),
+                memory_bytes: process.memory(),
+                thread_count: process.tasks().map(|t| t.len()).unwrap_or(0),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_reports_nonzero_memory() {
+        let mut monitor = SystemMonitor::new().unwrap();
+        let metrics = monitor.sample();
+        assert!(metrics.memory_bytes > 0);
+        assert!(metrics.memory_mb() > 0.0);
+        assert!((0.0..=100.0).contains(&metrics.cpu_percent));
+    }
+}
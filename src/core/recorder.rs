@@ -0,0 +1,124 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Recording sessions - gate persistence and export behind a start/stop toggle
+//!
+//! An engine built with a `Recorder` still streams every reading to the
+//! event bus, but only writes to the database and export files while a
+//! session is open. Each session is a row in the database's `sessions`
+//! table, so it can be replayed later with `ReplaySensor::from_session`.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use anyhow::{bail, Result};
+use parking_lot::Mutex;
+use tokio::sync::{mpsc, oneshot};
+use tracing::info;
+
+use crate::db::Database;
+use crate::streaming::StreamingManager;
+
+/// Starts and stops recording sessions for a running engine
+pub struct Recorder {
+    database: Arc<Database>,
+    streaming: Option<Arc<StreamingManager>>,
+    active: AtomicBool,
+    session: Mutex<Option<String>>,
+    readings: AtomicU64,
+    detections: AtomicU64,
+    /// Asks the persistence task to write out its buffered readings
+    flush_tx: mpsc::UnboundedSender<oneshot::Sender<()>>,
+    flush_rx: Mutex<Option<mpsc::UnboundedReceiver<oneshot::Sender<()>>>>,
+}
+
+impl Recorder {
+    pub fn new(database: Arc<Database>, streaming: Option<Arc<StreamingManager>>) -> Self {
+        let (flush_tx, flush_rx) = mpsc::unbounded_channel();
+        Self {
+            database,
+            streaming,
+            active: AtomicBool::new(false),
+            session: Mutex::new(None),
+            readings: AtomicU64::new(0),
+            detections: AtomicU64::new(0),
+            flush_tx,
+            flush_rx: Mutex::new(Some(flush_rx)),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Id of the open session, if recording
+    pub fn session_id(&self) -> Option<String> {
+        self.session.lock().clone()
+    }
+
+    /// Readings and detections persisted in the current (or last) session
+    pub fn counts(&self) -> (u64, u64) {
+        (self.readings.load(Ordering::Relaxed), self.detections.load(Ordering::Relaxed))
+    }
+
+    /// Open a session and start persisting and exporting
+    pub fn start(&self) -> Result<String> {
+        let mut session = self.session.lock();
+        if let Some(ref id) = *session {
+            bail!("Already recording session {}", id);
+        }
+
+        let id = self.database.start_session(None)?;
+        self.readings.store(0, Ordering::Relaxed);
+        self.detections.store(0, Ordering::Relaxed);
+        if let Some(ref streaming) = self.streaming {
+            streaming.set_export_enabled(true)?;
+        }
+        self.active.store(true, Ordering::Relaxed);
+
+        info!("Recording session {} started", id);
+        *session = Some(id.clone());
+        Ok(id)
+    }
+
+    /// Stop persisting, close the session and flush the database and exports.
+    ///
+    /// Waits for the persistence task to write readings it buffered while
+    /// the session was open, so the session's counts are complete.
+    pub async fn stop(&self) -> Result<()> {
+        let Some(id) = self.session.lock().take() else {
+            return Ok(());
+        };
+
+        self.active.store(false, Ordering::Relaxed);
+        // Only a running persistence task (which took the receiver) buffers
+        let (ack_tx, ack_rx) = oneshot::channel();
+        if self.flush_rx.lock().is_none() && self.flush_tx.send(ack_tx).is_ok() {
+            let _ = ack_rx.await;
+        }
+
+        if let Some(ref streaming) = self.streaming {
+            streaming.set_export_enabled(false)?;
+        }
+
+        let (readings, detections) = self.counts();
+        self.database.end_session(&id, readings, detections)?;
+        self.database.flush()?;
+
+        info!("Recording session {} stopped: {} readings, {} detections", id, readings, detections);
+        Ok(())
+    }
+
+    /// Flush requests, taken once by the persistence task
+    pub(crate) fn take_flush_requests(&self) -> Option<mpsc::UnboundedReceiver<oneshot::Sender<()>>> {
+        self.flush_rx.lock().take()
+    }
+
+    pub(crate) fn add_readings(&self, n: usize) {
+        self.readings.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_detection(&self) {
+        self.detections.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -2,32 +2,78 @@
 // Licensed under the MIT License. See LICENSE file in the project root.
 // https://github.com/bad-antics/glowbarn-rs
 
-//! Task scheduler for timed operations
+//! Task scheduler for timed operations and per-sensor sampling
 
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
-use tokio::time::Interval;
-use tracing::{debug, warn};
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::sensors::poll_period;
 
 type TaskFn = Box<dyn Fn() + Send + Sync + 'static>;
 
+/// Share of a poll tick that may be spent reading before low-priority reads are shed
+const TICK_BUDGET: Duration = Duration::from_millis(8);
+
 struct ScheduledTask {
     name: String,
     interval: Duration,
     task: TaskFn,
     enabled: bool,
+    last_run: Option<Instant>,
+}
+
+/// Sampling priority; under overload lower priorities are dropped first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Critical,
+}
+
+/// Per-sensor sampling statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingStats {
+    pub sensor_id: String,
+    pub priority: Priority,
+    pub target_hz: f64,
+    pub actual_hz: f64,
+    pub reads: u64,
+    pub skipped: u64,
+    pub errors: u64,
 }
 
+struct ScheduledSensor {
+    next_due: Option<Instant>,
+    first_read: Option<Instant>,
+    stats: SamplingStats,
+}
+
+/// Decides when each sensor is read and runs timed tasks.
+///
+/// The scheduler doesn't own sensors: the sensor manager's poll loop calls
+/// `begin_tick` on every tick, asks `take_due` about each sensor in
+/// `poll_order`, and reports the outcome with `record_read`. Each sensor is
+/// paced at its own `sample_rate`, and once a tick has spent its budget the
+/// reads still due below `Priority::High` are shed.
 pub struct Scheduler {
     tasks: Arc<RwLock<HashMap<String, ScheduledTask>>>,
+    sensors: HashMap<String, ScheduledSensor>,
+    tick_start: Instant,
 }
 
 impl Scheduler {
     pub fn new() -> Self {
         Self {
             tasks: Arc::new(RwLock::new(HashMap::new())),
+            sensors: HashMap::new(),
+            tick_start: Instant::now(),
         }
     }
     
@@ -43,6 +89,7 @@ impl Scheduler {
                 interval,
                 task: Box::new(task),
                 enabled: true,
+                last_run: None,
             },
         );
         debug!("Scheduled task '{}' with interval {:?}", name, interval);
@@ -59,6 +106,126 @@ impl Scheduler {
             task.enabled = enabled;
         }
     }
+    
+    /// Sample `sensor_id` with `priority`; sensors never registered are `Normal`
+    pub fn register(&mut self, sensor_id: &str, priority: Priority) {
+        debug!("Scheduling sensor {} ({:?})", sensor_id, priority);
+        self.entry(sensor_id).stats.priority = priority;
+    }
+    
+    /// Forget a removed sensor's schedule and statistics
+    pub fn unregister(&mut self, sensor_id: &str) {
+        self.sensors.remove(sensor_id);
+    }
+    
+    /// Read `sensor_id` on the next tick, e.g. after it was restarted
+    pub fn reschedule(&mut self, sensor_id: &str) {
+        if let Some(entry) = self.sensors.get_mut(sensor_id) {
+            entry.next_due = None;
+        }
+    }
+    
+    /// Sensor ids highest priority first, so overload shedding hits the low end
+    pub fn poll_order<'a>(&self, sensor_ids: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+        let mut ids: Vec<String> = sensor_ids.into_iter().cloned().collect();
+        ids.sort_by_key(|id| std::cmp::Reverse(self.priority(id)));
+        ids
+    }
+    
+    pub fn priority(&self, sensor_id: &str) -> Priority {
+        self.sensors.get(sensor_id).map(|s| s.stats.priority).unwrap_or_default()
+    }
+    
+    /// Start a poll tick; `take_due` measures the tick budget from here
+    pub fn begin_tick(&mut self) {
+        self.tick_start = Instant::now();
+    }
+    
+    /// Whether `sensor_id` should be read now.
+    ///
+    /// Self-paced sensors (replays) pass their own `due_at`; the rest are
+    /// read every `poll_period(sample_rate)`, without a catch-up burst after
+    /// falling behind. A due read below `Priority::High` is shed, and
+    /// counted as skipped, once the tick has spent `TICK_BUDGET`.
+    pub fn take_due(&mut self, sensor_id: &str, sample_rate: f64, due_at: Option<Instant>, now: Instant) -> bool {
+        let over_budget = self.tick_start.elapsed() > TICK_BUDGET;
+        let period = poll_period(sample_rate);
+        let entry = self.entry(sensor_id);
+        entry.stats.target_hz = 1.0 / period.as_secs_f64();
+        
+        if let Some(at) = due_at {
+            if at > now {
+                return false;
+            }
+        } else {
+            let due = *entry.next_due.get_or_insert(now);
+            if due > now {
+                return false;
+            }
+            entry.next_due = Some(if due + period < now { now + period } else { due + period });
+        }
+        
+        if over_budget && entry.stats.priority < Priority::High {
+            entry.stats.skipped += 1;
+            return false;
+        }
+        true
+    }
+    
+    /// Count a read of `sensor_id` made after `take_due` allowed it
+    pub fn record_read(&mut self, sensor_id: &str, ok: bool, now: Instant) {
+        let entry = self.entry(sensor_id);
+        if !ok {
+            entry.stats.errors += 1;
+            return;
+        }
+        entry.stats.reads += 1;
+        let first = *entry.first_read.get_or_insert(now);
+        let elapsed = (now - first).as_secs_f64();
+        if elapsed > 0.0 {
+            // Reads after the first over the time since it
+            entry.stats.actual_hz = (entry.stats.reads - 1) as f64 / elapsed;
+        }
+    }
+    
+    /// Actual-vs-target rate of every scheduled sensor
+    pub fn sampling_stats(&self) -> Vec<SamplingStats> {
+        self.sensors.values().map(|s| s.stats.clone()).collect()
+    }
+    
+    /// Run the timed tasks whose interval has elapsed
+    pub async fn run_due_tasks(&self) {
+        let mut tasks = self.tasks.write().await;
+        let now = Instant::now();
+        
+        for task in tasks.values_mut() {
+            if !task.enabled {
+                continue;
+            }
+            let due = task.last_run.map(|t| now - t >= task.interval).unwrap_or(true);
+            if due {
+                debug!("Running task '{}'", task.name);
+                (task.task)();
+                task.last_run = Some(now);
+            }
+        }
+    }
+    
+    fn entry(&mut self, sensor_id: &str) -> &mut ScheduledSensor {
+        self.sensors.entry(sensor_id.to_string()).or_insert_with(|| ScheduledSensor {
+            next_due: None,
+            first_read: None,
+            stats: SamplingStats {
+                sensor_id: sensor_id.to_string(),
+                priority: Priority::Normal,
+                target_hz: 0.0,
+                actual_hz: 0.0,
+                reads: 0,
+                skipped: 0,
+                errors: 0,
+            },
+        })
+    }
 }
 
 impl Default for Scheduler {
@@ -66,3 +233,27 @@ impl Default for Scheduler {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_overload_sheds_low_priority_first() {
+        let mut scheduler = Scheduler::new();
+        scheduler.register("critical", Priority::Critical);
+        scheduler.register("low", Priority::Low);
+        let ids = ["low".to_string(), "critical".to_string()];
+        assert_eq!(scheduler.poll_order(&ids), vec!["critical".to_string(), "low".to_string()]);
+        
+        // A tick whose reads have already used up its budget
+        scheduler.begin_tick();
+        tokio::time::advance(TICK_BUDGET * 2).await;
+        let now = Instant::now();
+        assert!(scheduler.take_due("critical", 10.0, None, now));
+        assert!(!scheduler.take_due("low", 10.0, None, now));
+        
+        let low = scheduler.sampling_stats().into_iter().find(|s| s.sensor_id == "low").unwrap();
+        assert_eq!(low.skipped, 1);
+    }
+}
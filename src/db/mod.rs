@@ -3,28 +3,53 @@
 // https://github.com/bad-antics/glowbarn-rs
 
 //! Database module for persistent storage
+//!
+//! When a [`SecurityManager`] with `encrypt_storage` enabled is supplied to
+//! [`Database::open`], the `data` BLOB of readings and detections is sealed
+//! with AES-256-GCM (the cipher prepends a random per-row nonce). Each row
+//! carries an `encrypted` flag so databases written before encryption was
+//! enabled keep working: plaintext rows (`encrypted = 0`) are returned as-is
+//! and only flagged rows are decrypted. Opening an older database adds the
+//! column with a default of 0, so no data rewrite is needed.
 
 use anyhow::{anyhow, Result};
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use chrono::{DateTime, Utc};
 use tracing::{info, warn, debug};
 
-use crate::sensors::SensorReading;
-use crate::detection::Detection;
+use crate::sensors::{CalibrationData, SensorHealth, SensorReading, SensorType};
+use crate::detection::{AnnotationStatus, Detection, DetectionAnnotation, DetectionType, Severity};
 use crate::config::DatabaseConfig;
+use crate::security::{AuditEvent, AuditEventType, SecurityManager};
+
+/// Time column of each table that has one, for `TableStats::oldest`/`newest`
+const TIME_COLUMNS: [(&str, &str); 5] = [
+    ("readings", "timestamp"),
+    ("detections", "timestamp"),
+    ("detection_annotations", "updated_at"),
+    ("sessions", "start_time"),
+    ("audit", "timestamp"),
+];
 
 /// Database manager
 pub struct Database {
     conn: Arc<Mutex<Connection>>,
     config: DatabaseConfig,
+    security: Option<Arc<SecurityManager>>,
+    /// Reused buffer for encoding reading payloads
+    scratch: Mutex<Vec<u8>>,
 }
 
 impl Database {
     /// Open or create database
-    pub fn open(config: &DatabaseConfig) -> Result<Self> {
+    ///
+    /// Pass a security manager to encrypt stored BLOBs at rest; it is only
+    /// used when its `encrypt_storage` setting is enabled.
+    pub fn open(config: &DatabaseConfig, security: Option<Arc<SecurityManager>>) -> Result<Self> {
         // Create parent directories
         if let Some(parent) = config.path.parent() {
             std::fs::create_dir_all(parent)?;
@@ -44,14 +69,27 @@ impl Database {
         let db = Self {
             conn: Arc::new(Mutex::new(conn)),
             config: config.clone(),
+            security: security.filter(|s| s.config().encrypt_storage),
+            scratch: Mutex::new(Vec::new()),
         };
         
         db.create_tables()?;
+        db.migrate()?;
         
         info!("Database opened at {:?}", config.path);
         Ok(db)
     }
     
+    /// Open the database behind an `Arc`, as the application shares it,
+    /// with `security`'s audit events persisted to it
+    pub fn open_shared(config: &DatabaseConfig, security: Option<Arc<SecurityManager>>) -> Result<Arc<Self>> {
+        let db = Arc::new(Self::open(config, security.clone())?);
+        if let Some(security) = security {
+            security.attach_audit_store(&db);
+        }
+        Ok(db)
+    }
+    
     /// Create database tables
     fn create_tables(&self) -> Result<()> {
         let conn = self.conn.lock().unwrap();
@@ -65,6 +103,10 @@ impl Database {
                 sensor_type TEXT NOT NULL,
                 quality REAL NOT NULL,
                 data BLOB NOT NULL,
+                encrypted INTEGER NOT NULL DEFAULT 0,
+                payload_format INTEGER NOT NULL DEFAULT 0,
+                mean_value REAL,
+                max_value REAL,
                 created_at TEXT DEFAULT CURRENT_TIMESTAMP
             );
             
@@ -83,12 +125,22 @@ impl Database {
                 correlation_score REAL,
                 classification TEXT,
                 data BLOB NOT NULL,
+                encrypted INTEGER NOT NULL DEFAULT 0,
+                reprocessed_session TEXT,
                 created_at TEXT DEFAULT CURRENT_TIMESTAMP
             );
             
             CREATE INDEX IF NOT EXISTS idx_detections_timestamp ON detections(timestamp);
             CREATE INDEX IF NOT EXISTS idx_detections_type ON detections(detection_type);
             
+            -- Investigator reviews of detections, one per detection
+            CREATE TABLE IF NOT EXISTS detection_annotations (
+                detection_id TEXT PRIMARY KEY,
+                status TEXT NOT NULL,
+                note TEXT,
+                updated_at TEXT NOT NULL
+            );
+            
             -- Sessions table
             CREATE TABLE IF NOT EXISTS sessions (
                 id TEXT PRIMARY KEY,
@@ -110,6 +162,18 @@ impl Database {
                 status TEXT DEFAULT 'unknown'
             );
             
+            -- Security audit trail
+            CREATE TABLE IF NOT EXISTS audit (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                event_type TEXT NOT NULL,
+                success INTEGER NOT NULL,
+                event TEXT NOT NULL
+            );
+            
+            CREATE INDEX IF NOT EXISTS idx_audit_timestamp ON audit(timestamp);
+            CREATE INDEX IF NOT EXISTS idx_audit_type ON audit(event_type);
+            
             -- Settings table
             CREATE TABLE IF NOT EXISTS settings (
                 key TEXT PRIMARY KEY,
@@ -121,58 +185,259 @@ impl Database {
         Ok(())
     }
     
-    /// Store a sensor reading
-    pub fn store_reading(&self, reading: &SensorReading) -> Result<()> {
+    /// Bring databases created by older versions up to the current schema
+    fn migrate(&self) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         
-        let data = bincode::serialize(&reading.data)?;
+        for table in ["readings", "detections"] {
+            if !has_column(&conn, table, "encrypted")? {
+                conn.execute_batch(&format!(
+                    "ALTER TABLE {} ADD COLUMN encrypted INTEGER NOT NULL DEFAULT 0",
+                    table
+                ))?;
+                info!("Added encrypted flag column to {}", table);
+            }
+        }
         
-        conn.execute(
-            "INSERT INTO readings (timestamp, sensor_id, sensor_type, quality, data) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![
-                reading.timestamp.to_rfc3339(),
-                reading.sensor_id,
-                format!("{:?}", reading.sensor_type),
-                reading.quality,
-                data
-            ],
+        if !has_column(&conn, "detections", "reprocessed_session")? {
+            conn.execute_batch("ALTER TABLE detections ADD COLUMN reprocessed_session TEXT")?;
+            info!("Added reprocessed_session column to detections");
+        }
+        
+        if !has_column(&conn, "readings", "payload_format")? {
+            conn.execute_batch("ALTER TABLE readings ADD COLUMN payload_format INTEGER NOT NULL DEFAULT 0")?;
+            info!("Added payload_format column to readings");
+        }
+        
+        if !has_column(&conn, "readings", "mean_value")? {
+            conn.execute_batch(
+                "ALTER TABLE readings ADD COLUMN mean_value REAL;
+                 ALTER TABLE readings ADD COLUMN max_value REAL;"
+            )?;
+            info!("Added mean_value/max_value columns to readings");
+        }
+        // Also catches rows a previous open couldn't decrypt
+        let filled = self.backfill_summaries(&conn)?;
+        if filled > 0 {
+            info!("Filled mean_value/max_value of {} readings", filled);
+        }
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_readings_sensor_mean ON readings(sensor_id, mean_value);"
         )?;
         
         Ok(())
     }
     
+    /// Compute the summary columns of rows that have none: rows stored
+    /// before the columns existed, or that an earlier open couldn't decrypt.
+    ///
+    /// Rows that can't be decrypted here keep NULL summaries for a later
+    /// open; rows whose payload doesn't decode are skipped with a warning.
+    fn backfill_summaries(&self, conn: &Connection) -> Result<usize> {
+        let rows: Vec<(i64, Vec<u8>, bool, u8)> = {
+            let mut stmt = conn.prepare("SELECT rowid, data, encrypted, payload_format FROM readings WHERE mean_value IS NULL")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        
+        let tx = conn.unchecked_transaction()?;
+        let mut filled = 0;
+        for (rowid, data, encrypted, format) in rows {
+            let Ok(data) = self.unseal(data, encrypted) else { continue };
+            let values = match decode_payload(&data, format) {
+                Ok(values) if !values.is_empty() => values,
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!("Skipping summary of corrupt reading {}: {}", rowid, e);
+                    continue;
+                }
+            };
+            let (mean, max) = payload_summary(&values);
+            tx.execute(
+                "UPDATE readings SET mean_value = ?1, max_value = ?2 WHERE rowid = ?3",
+                params![mean, max, rowid],
+            )?;
+            filled += 1;
+        }
+        tx.commit()?;
+        Ok(filled)
+    }
+    
+    /// Location of the database file
+    pub fn path(&self) -> &Path {
+        &self.config.path
+    }
+    
+    /// Whether new rows are written encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.security.is_some()
+    }
+    
+    /// Encrypt a BLOB for storage if storage encryption is enabled
+    fn seal(&self, data: Vec<u8>) -> Result<(Vec<u8>, bool)> {
+        match self.security {
+            Some(ref security) => Ok((security.encrypt(&data)?, true)),
+            None => Ok((data, false)),
+        }
+    }
+    
+    /// Decrypt a stored BLOB according to its row flag
+    fn unseal(&self, data: Vec<u8>, encrypted: bool) -> Result<Vec<u8>> {
+        if !encrypted {
+            return Ok(data);
+        }
+        
+        match self.security {
+            Some(ref security) => security.decrypt(&data),
+            None => Err(anyhow!("Row is encrypted but no security manager was provided")),
+        }
+    }
+    
+    /// Re-encrypt rows sealed under retired keys with the active key
+    ///
+    /// Runs in small batches so writers are not blocked for long; callers
+    /// that want it in the background can run it via `spawn_blocking`.
+    /// Rows no key in the ring opens are left as they are and counted in a
+    /// warning. Returns the number of rows re-encrypted.
+    pub fn re_encrypt_all(&self) -> Result<usize> {
+        const BATCH_SIZE: usize = 500;
+        
+        let security = match self.security {
+            Some(ref s) => s.clone(),
+            None => return Ok(0),
+        };
+        let active = security.active_key_id();
+        let mut total = 0;
+        let mut unreadable = 0;
+        
+        for table in ["readings", "detections"] {
+            let mut last_rowid: i64 = 0;
+            
+            loop {
+                let conn = self.conn.lock().unwrap();
+                
+                let batch: Vec<(i64, Vec<u8>)> = {
+                    let mut stmt = conn.prepare(&format!(
+                        "SELECT rowid, data FROM {} WHERE encrypted = 1 AND rowid > ?1 ORDER BY rowid LIMIT {}",
+                        table, BATCH_SIZE
+                    ))?;
+                    let rows = stmt.query_map(params![last_rowid], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                    rows.collect::<Result<_, _>>()?
+                };
+                
+                if batch.is_empty() {
+                    break;
+                }
+                last_rowid = batch.last().map(|(id, _)| *id).unwrap_or(last_rowid);
+                
+                let tx = conn.unchecked_transaction()?;
+                for (rowid, data) in batch {
+                    if crate::security::KeyRing::key_id_of(&data) == Some(active) {
+                        continue;
+                    }
+                    
+                    let plaintext = match security.decrypt(&data) {
+                        Ok(plaintext) => plaintext,
+                        Err(e) => {
+                            debug!("Leaving {} row {} as it is: {}", table, rowid, e);
+                            unreadable += 1;
+                            continue;
+                        }
+                    };
+                    let resealed = security.encrypt(&plaintext)?;
+                    tx.execute(
+                        &format!("UPDATE {} SET data = ?1 WHERE rowid = ?2", table),
+                        params![resealed, rowid],
+                    )?;
+                    total += 1;
+                }
+                tx.commit()?;
+            }
+        }
+        
+        if unreadable > 0 {
+            warn!("{} encrypted rows could not be opened with any known key and were not re-encrypted", unreadable);
+        }
+        info!("Re-encrypted {} rows under key version {}", total, active);
+        Ok(total)
+    }
+    
+    /// Store a sensor reading
+    pub fn store_reading(&self, reading: &SensorReading) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let mut scratch = self.scratch.lock().unwrap();
+        
+        self.insert_reading(&conn, reading, &mut scratch)
+    }
+    
     /// Store multiple readings in batch
     pub fn store_readings_batch(&self, readings: &[SensorReading]) -> Result<usize> {
         let conn = self.conn.lock().unwrap();
+        let mut scratch = self.scratch.lock().unwrap();
         
         let tx = conn.unchecked_transaction()?;
-        let mut count = 0;
-        
         for reading in readings {
-            let data = bincode::serialize(&reading.data)?;
-            
-            tx.execute(
-                "INSERT INTO readings (timestamp, sensor_id, sensor_type, quality, data) VALUES (?1, ?2, ?3, ?4, ?5)",
-                params![
-                    reading.timestamp.to_rfc3339(),
-                    reading.sensor_id,
-                    format!("{:?}", reading.sensor_type),
-                    reading.quality,
-                    data
-                ],
-            )?;
-            count += 1;
+            self.insert_reading(&tx, reading, &mut scratch)?;
         }
         
         tx.commit()?;
-        Ok(count)
+        Ok(readings.len())
+    }
+    
+    /// Insert one reading, encoding its payload through `scratch` so the
+    /// unencrypted path doesn't allocate per row
+    fn insert_reading(&self, conn: &Connection, reading: &SensorReading, scratch: &mut Vec<u8>) -> Result<()> {
+        encode_payload(&reading.data, scratch);
+        let sealed = match self.security {
+            Some(ref security) => Some(security.encrypt(scratch)?),
+            None => None,
+        };
+        let data = sealed.as_deref().unwrap_or(scratch.as_slice());
+        let (mean, max) = payload_summary(&reading.data);
+        
+        conn.prepare_cached(
+            "INSERT INTO readings (timestamp, sensor_id, sensor_type, quality, data, encrypted, payload_format, mean_value, max_value)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+        )?.execute(params![
+            reading.timestamp.to_rfc3339(),
+            reading.sensor_id,
+            format!("{:?}", reading.sensor_type),
+            reading.quality,
+            data,
+            sealed.is_some(),
+            PAYLOAD_RAW_F64,
+            mean,
+            max
+        ])?;
+        
+        Ok(())
     }
     
     /// Store a detection
     pub fn store_detection(&self, detection: &Detection) -> Result<()> {
         let conn = self.conn.lock().unwrap();
+        self.insert_detection(&conn, detection, None)
+    }
+    
+    /// Replace the detections reprocessed from `session_id` with `detections`.
+    ///
+    /// They are tagged with the session so they never mix with the ones
+    /// recorded live; `query_detections` leaves them out.
+    pub fn store_reprocessed_detections(&self, session_id: &str, detections: &[Detection]) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
         
-        let data = bincode::serialize(detection)?;
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM detections WHERE reprocessed_session = ?1", params![session_id])?;
+        for detection in detections {
+            self.insert_detection(&tx, detection, Some(session_id))?;
+        }
+        
+        tx.commit()?;
+        Ok(detections.len())
+    }
+    
+    fn insert_detection(&self, conn: &Connection, detection: &Detection, reprocessed_session: Option<&str>) -> Result<()> {
+        let (data, encrypted) = self.seal(bincode::serialize(detection)?)?;
         let classification = detection.classification.as_ref()
             .map(|c| serde_json::to_string(c).ok())
             .flatten();
@@ -180,8 +445,8 @@ impl Database {
         conn.execute(
             r#"INSERT INTO detections 
                (id, timestamp, detection_type, confidence, severity, sensor_count, 
-                entropy_deviation, correlation_score, classification, data)
-               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"#,
+                entropy_deviation, correlation_score, classification, data, encrypted, reprocessed_session)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"#,
             params![
                 detection.id,
                 detection.timestamp.to_rfc3339(),
@@ -192,13 +457,108 @@ impl Database {
                 detection.entropy_deviation,
                 detection.correlation_score,
                 classification,
-                data
+                data,
+                encrypted,
+                reprocessed_session
             ],
         )?;
         
         Ok(())
     }
     
+    /// Record an investigator's verdict on a detection, replacing any
+    /// earlier one
+    pub fn annotate_detection(&self, id: &str, status: AnnotationStatus, note: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT OR REPLACE INTO detection_annotations (detection_id, status, note, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![id, status.as_str(), note, Utc::now().to_rfc3339()],
+        )?;
+        
+        Ok(())
+    }
+    
+    /// Annotation of one detection, if it has been reviewed
+    pub fn detection_annotation(&self, id: &str) -> Result<Option<DetectionAnnotation>> {
+        Ok(self.select_annotations("WHERE detection_id = ?1", params![id])?.pop())
+    }
+    
+    /// Every stored annotation, most recent first
+    pub fn detection_annotations(&self) -> Result<Vec<DetectionAnnotation>> {
+        self.select_annotations("ORDER BY updated_at DESC", [])
+    }
+    
+    fn select_annotations(&self, filter: &str, args: impl rusqlite::Params) -> Result<Vec<DetectionAnnotation>> {
+        let conn = self.conn.lock().unwrap();
+        
+        let mut stmt = conn.prepare(&format!(
+            "SELECT detection_id, status, note, updated_at FROM detection_annotations {}",
+            filter
+        ))?;
+        let rows = stmt.query_map(args, |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get(2)?, row.get::<_, String>(3)?))
+        })?;
+        
+        let mut annotations = Vec::new();
+        for row in rows {
+            let (detection_id, status, note, updated_at) = row?;
+            annotations.push(DetectionAnnotation {
+                detection_id,
+                status: status.parse()?,
+                note,
+                timestamp: DateTime::parse_from_rfc3339(&updated_at)?.with_timezone(&Utc),
+            });
+        }
+        Ok(annotations)
+    }
+    
+    /// Store a security audit event
+    pub fn store_audit_event(&self, event: &AuditEvent) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT INTO audit (timestamp, event_type, success, event) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                event.timestamp.to_rfc3339(),
+                format!("{:?}", event.event_type),
+                event.success,
+                serde_json::to_string(event)?
+            ],
+        )?;
+        
+        Ok(())
+    }
+    
+    /// Query audit events by time range, optionally filtered by type
+    pub fn query_audit(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        event_type: Option<AuditEventType>,
+    ) -> Result<Vec<AuditEvent>> {
+        let conn = self.conn.lock().unwrap();
+        
+        let mut stmt = conn.prepare(
+            "SELECT event FROM audit 
+             WHERE timestamp >= ?1 AND timestamp <= ?2 AND (?3 IS NULL OR event_type = ?3)
+             ORDER BY timestamp ASC"
+        )?;
+        
+        let type_filter = event_type.map(|t| format!("{:?}", t));
+        let rows = stmt.query_map(
+            params![start.to_rfc3339(), end.to_rfc3339(), type_filter],
+            |row| row.get::<_, String>(0),
+        )?;
+        
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(serde_json::from_str(&row?)?);
+        }
+        
+        Ok(results)
+    }
+    
     /// Query readings by time range
     pub fn query_readings(
         &self,
@@ -211,14 +571,14 @@ impl Database {
         
         let sql = if let Some(sid) = sensor_id {
             format!(
-                "SELECT id, timestamp, sensor_id, sensor_type, quality, data FROM readings 
+                "SELECT id, timestamp, sensor_id, sensor_type, quality, data, encrypted, mean_value, max_value, payload_format FROM readings 
                  WHERE timestamp >= ?1 AND timestamp <= ?2 AND sensor_id = ?3
                  ORDER BY timestamp DESC LIMIT {}",
                 limit.unwrap_or(1000)
             )
         } else {
             format!(
-                "SELECT id, timestamp, sensor_id, sensor_type, quality, data FROM readings 
+                "SELECT id, timestamp, sensor_id, sensor_type, quality, data, encrypted, mean_value, max_value, payload_format FROM readings 
                  WHERE timestamp >= ?1 AND timestamp <= ?2
                  ORDER BY timestamp DESC LIMIT {}",
                 limit.unwrap_or(1000)
@@ -232,32 +592,155 @@ impl Database {
         if let Some(sid) = sensor_id {
             let mut rows = stmt.query(params![start.to_rfc3339(), end.to_rfc3339(), sid])?;
             while let Some(row) = rows.next()? {
-                results.push(StoredReading {
-                    id: row.get(0)?,
-                    timestamp: row.get(1)?,
-                    sensor_id: row.get(2)?,
-                    sensor_type: row.get(3)?,
-                    quality: row.get(4)?,
-                    data: row.get(5)?,
-                });
+                results.extend(self.readable_reading(row));
             }
         } else {
             let mut rows = stmt.query(params![start.to_rfc3339(), end.to_rfc3339()])?;
             while let Some(row) = rows.next()? {
-                results.push(StoredReading {
-                    id: row.get(0)?,
-                    timestamp: row.get(1)?,
-                    sensor_id: row.get(2)?,
-                    sensor_type: row.get(3)?,
-                    quality: row.get(4)?,
-                    data: row.get(5)?,
-                });
+                results.extend(self.readable_reading(row));
             }
         }
         
         Ok(results)
     }
     
+    /// Readings between `start` and `end`, oldest first, `limit` at a time
+    ///
+    /// Pass the previous page's `next` as `after` to continue. Pages are
+    /// keyed on (timestamp, id), so each one is an index seek and a long
+    /// range never has to sit in memory at once.
+    pub fn query_readings_page(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        sensor_id: Option<&str>,
+        after: Option<&ReadingCursor>,
+        limit: usize,
+    ) -> Result<ReadingPage> {
+        let conn = self.conn.lock().unwrap();
+        let (after_timestamp, after_id) = match after {
+            Some(cursor) => (cursor.timestamp.clone(), cursor.id),
+            None => (start.to_rfc3339(), i64::MIN),
+        };
+        
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, timestamp, sensor_id, sensor_type, quality, data, encrypted, mean_value, max_value, payload_format FROM readings
+             WHERE (timestamp, id) > (?1, ?2) AND timestamp <= ?3 AND (?4 IS NULL OR sensor_id = ?4)
+             ORDER BY timestamp ASC, id ASC LIMIT ?5"
+        )?;
+        let mut rows = stmt.query(params![after_timestamp, after_id, end.to_rfc3339(), sensor_id, limit as i64])?;
+        
+        let mut readings = Vec::new();
+        let mut fetched = 0;
+        let mut last = None;
+        while let Some(row) = rows.next()? {
+            fetched += 1;
+            last = Some(ReadingCursor { timestamp: row.get(1)?, id: row.get(0)? });
+            readings.extend(self.readable_reading(row));
+        }
+        
+        Ok(ReadingPage {
+            readings,
+            next: if fetched == limit { last } else { None },
+        })
+    }
+    
+    /// Each sensor's reading count and first/last timestamp between `start`
+    /// and `end`, by sensor id
+    pub fn query_reading_spans(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<ReadingSpan>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT sensor_id, MIN(sensor_type), COUNT(*), MIN(timestamp), MAX(timestamp) FROM readings
+             WHERE timestamp >= ?1 AND timestamp <= ?2
+             GROUP BY sensor_id ORDER BY sensor_id"
+        )?;
+        let rows = stmt.query_map(params![start.to_rfc3339(), end.to_rfc3339()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, String>(3)?, row.get::<_, String>(4)?))
+        })?;
+        
+        let mut spans = Vec::new();
+        for row in rows {
+            let (sensor_id, sensor_type, count, first, last) = row?;
+            spans.push(ReadingSpan {
+                sensor_id,
+                sensor_type: sensor_type.parse()?,
+                count: count as usize,
+                first: DateTime::parse_from_rfc3339(&first)?.with_timezone(&Utc),
+                last: DateTime::parse_from_rfc3339(&last)?.with_timezone(&Utc),
+            });
+        }
+        Ok(spans)
+    }
+    
+    /// Decode a reading row, or log and skip it if it won't decrypt (e.g.
+    /// written under a key that is no longer available) so one bad row
+    /// doesn't fail the whole query
+    fn readable_reading(&self, row: &rusqlite::Row) -> Option<StoredReading> {
+        match self.reading_from_row(row) {
+            Ok(reading) => Some(reading),
+            Err(e) => {
+                warn!("Skipping unreadable reading {}: {}", row.get::<_, i64>(0).unwrap_or_default(), e);
+                None
+            }
+        }
+    }
+    
+    fn reading_from_row(&self, row: &rusqlite::Row) -> Result<StoredReading> {
+        Ok(StoredReading {
+            id: row.get(0)?,
+            timestamp: row.get(1)?,
+            sensor_id: row.get(2)?,
+            sensor_type: row.get(3)?,
+            quality: row.get(4)?,
+            data: self.unseal(row.get(5)?, row.get(6)?)?,
+            mean_value: row.get(7)?,
+            max_value: row.get(8)?,
+            payload_format: row.get(9)?,
+        })
+    }
+    
+    /// Readings between `start` and `end`, oldest first, with their stored
+    /// summaries instead of the payload - nothing is decrypted or decoded
+    pub fn query_reading_summaries(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<ReadingSummary>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, sensor_id, sensor_type, quality, mean_value, max_value FROM readings
+             WHERE timestamp >= ?1 AND timestamp <= ?2
+             ORDER BY timestamp ASC"
+        )?;
+        let rows = stmt.query_map(params![start.to_rfc3339(), end.to_rfc3339()], |row| {
+            Ok(ReadingSummary {
+                timestamp: row.get(0)?,
+                sensor_id: row.get(1)?,
+                sensor_type: row.get(2)?,
+                quality: row.get(3)?,
+                mean_value: row.get(4)?,
+                max_value: row.get(5)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+    
+    /// Per-sensor reading count, average of the reading means and overall
+    /// maximum between `start` and `end`, from the stored summary columns
+    pub fn aggregate_readings(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<SensorAggregate>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT sensor_id, COUNT(*), AVG(mean_value), MAX(max_value) FROM readings
+             WHERE timestamp >= ?1 AND timestamp <= ?2
+             GROUP BY sensor_id ORDER BY sensor_id"
+        )?;
+        let rows = stmt.query_map(params![start.to_rfc3339(), end.to_rfc3339()], |row| {
+            Ok(SensorAggregate {
+                sensor_id: row.get(0)?,
+                count: row.get::<_, i64>(1)? as usize,
+                mean_value: row.get(2)?,
+                max_value: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+    
     /// Query detections by time range
     pub fn query_detections(
         &self,
@@ -266,66 +749,277 @@ impl Database {
         min_confidence: Option<f64>,
         limit: Option<usize>,
     ) -> Result<Vec<StoredDetection>> {
-        let conn = self.conn.lock().unwrap();
-        
         let min_conf = min_confidence.unwrap_or(0.0);
+        self.select_detections(
+            &format!(
+                "WHERE timestamp >= ?1 AND timestamp <= ?2 AND confidence >= ?3 AND reprocessed_session IS NULL
+                 ORDER BY timestamp DESC LIMIT {}",
+                limit.unwrap_or(100)
+            ),
+            params![start.to_rfc3339(), end.to_rfc3339(), min_conf],
+        )
+    }
+    
+    /// Every live detection between `start` and `end`, oldest first
+    pub fn query_detections_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<StoredDetection>> {
+        self.select_detections(
+            "WHERE timestamp >= ?1 AND timestamp <= ?2 AND reprocessed_session IS NULL ORDER BY timestamp ASC",
+            params![start.to_rfc3339(), end.to_rfc3339()],
+        )
+    }
+    
+    /// Live detections between `start` and `end` counted by UTC hour, type
+    /// and severity, without decoding any payload
+    pub fn count_detections_by_hour(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<DetectionHourCount>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT substr(timestamp, 1, 13), detection_type, severity, COUNT(*) FROM detections
+             WHERE timestamp >= ?1 AND timestamp <= ?2 AND reprocessed_session IS NULL
+             GROUP BY 1, 2, 3"
+        )?;
+        let rows = stmt.query_map(params![start.to_rfc3339(), end.to_rfc3339()], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
         
-        let sql = format!(
-            "SELECT id, timestamp, detection_type, confidence, severity, sensor_count, data 
-             FROM detections 
-             WHERE timestamp >= ?1 AND timestamp <= ?2 AND confidence >= ?3
-             ORDER BY timestamp DESC LIMIT {}",
-            limit.unwrap_or(100)
-        );
+        let mut counts = Vec::new();
+        for row in rows {
+            let (hour, detection_type, severity, count) = row?;
+            let parsed = DateTime::parse_from_rfc3339(&format!("{}:00:00+00:00", hour))
+                .map_err(anyhow::Error::from)
+                .and_then(|hour| Ok((hour, variant(&detection_type)?, variant(&severity)?)));
+            match parsed {
+                Ok((hour, detection_type, severity)) => counts.push(DetectionHourCount {
+                    hour: hour.with_timezone(&Utc),
+                    detection_type,
+                    severity,
+                    count: count as usize,
+                }),
+                Err(e) => warn!("Skipping {} detections at {} that can't be counted: {}", count, hour, e),
+            }
+        }
+        Ok(counts)
+    }
+    
+    /// Detections written by the last reprocessing of `session_id`, oldest first
+    pub fn query_reprocessed_detections(&self, session_id: &str) -> Result<Vec<StoredDetection>> {
+        self.select_detections(
+            "WHERE reprocessed_session = ?1 ORDER BY timestamp ASC",
+            params![session_id],
+        )
+    }
+    
+    fn select_detections(&self, filter: &str, args: impl rusqlite::Params) -> Result<Vec<StoredDetection>> {
+        let conn = self.conn.lock().unwrap();
         
-        let mut stmt = conn.prepare(&sql)?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, timestamp, detection_type, confidence, severity, sensor_count, data, encrypted 
+             FROM detections {}",
+            filter
+        ))?;
         
-        let rows = stmt.query_map(params![start.to_rfc3339(), end.to_rfc3339(), min_conf], |row| {
-            Ok(StoredDetection {
-                id: row.get(0)?,
-                timestamp: row.get(1)?,
-                detection_type: row.get(2)?,
-                confidence: row.get(3)?,
-                severity: row.get(4)?,
-                sensor_count: row.get(5)?,
-                data: row.get(6)?,
-            })
+        let rows = stmt.query_map(args, |row| {
+            Ok((
+                StoredDetection {
+                    id: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    detection_type: row.get(2)?,
+                    confidence: row.get(3)?,
+                    severity: row.get(4)?,
+                    sensor_count: row.get(5)?,
+                    data: row.get(6)?,
+                },
+                row.get::<_, bool>(7)?,
+            ))
         })?;
         
         let mut results = Vec::new();
         for row in rows {
-            results.push(row?);
+            let (mut detection, encrypted) = row?;
+            match self.unseal(detection.data, encrypted) {
+                Ok(data) => {
+                    detection.data = data;
+                    results.push(detection);
+                }
+                Err(e) => warn!("Skipping unreadable detection {}: {}", detection.id, e),
+            }
         }
         
         Ok(results)
     }
     
-    /// Get database statistics
-    pub fn get_stats(&self) -> Result<DatabaseStats> {
+    /// Start and end of a recorded session; an open session ends now
+    pub fn session_range(&self, session_id: &str) -> Result<Option<(DateTime<Utc>, DateTime<Utc>)>> {
         let conn = self.conn.lock().unwrap();
         
-        let reading_count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM readings",
-            [],
-            |row| row.get(0),
+        let result: Result<(String, Option<String>), _> = conn.query_row(
+            "SELECT start_time, end_time FROM sessions WHERE id = ?1",
+            params![session_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        );
+        
+        let (start, end) = match result {
+            Ok(times) => times,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        
+        let start = DateTime::parse_from_rfc3339(&start)?.with_timezone(&Utc);
+        let end = match end {
+            Some(end) => DateTime::parse_from_rfc3339(&end)?.with_timezone(&Utc),
+            None => Utc::now(),
+        };
+        Ok(Some((start, end)))
+    }
+
+    /// Open a new recording session starting now, returning its id
+    pub fn start_session(&self, notes: Option<&str>) -> Result<String> {
+        let conn = self.conn.lock().unwrap();
+        let id = uuid::Uuid::new_v4().to_string();
+
+        conn.execute(
+            "INSERT INTO sessions (id, start_time, notes) VALUES (?1, ?2, ?3)",
+            params![id, Utc::now().to_rfc3339(), notes],
+        )?;
+
+        Ok(id)
+    }
+
+    /// Close a session, recording how much was captured during it
+    pub fn end_session(&self, session_id: &str, reading_count: u64, detection_count: u64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+
+        let updated = conn.execute(
+            "UPDATE sessions SET end_time = ?1, reading_count = ?2, detection_count = ?3 WHERE id = ?4",
+            params![Utc::now().to_rfc3339(), reading_count as i64, detection_count as i64, session_id],
         )?;
+        if updated == 0 {
+            return Err(anyhow!("Unknown session {}", session_id));
+        }
+
+        Ok(())
+    }
+
+    /// Record a sensor's current health status in the `sensors` table
+    pub fn update_sensor_status(&self, health: &SensorHealth) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
         
-        let detection_count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM detections",
-            [],
-            |row| row.get(0),
+        conn.execute(
+            "INSERT INTO sensors (id, name, sensor_type, last_seen, status)
+             VALUES (?1, ?1, ?2, ?3, ?4)
+             ON CONFLICT(id) DO UPDATE SET
+                status = excluded.status,
+                last_seen = COALESCE(excluded.last_seen, sensors.last_seen)",
+            params![
+                health.sensor_id,
+                format!("{:?}", health.sensor_type),
+                health.last_reading.map(|t| t.to_rfc3339()),
+                health.health.as_str(),
+            ],
         )?;
         
-        let size_bytes: i64 = conn.query_row(
-            "SELECT page_count * page_size as size FROM pragma_page_count(), pragma_page_size()",
-            [],
+        Ok(())
+    }
+    
+    /// Store a sensor's calibration (bincode) in the `sensors` table
+    pub fn store_calibration(&self, sensor_id: &str, sensor_type: SensorType, calibration: &CalibrationData) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let blob = bincode::serialize(calibration)?;
+        
+        conn.execute(
+            "INSERT INTO sensors (id, name, sensor_type, calibration_data)
+             VALUES (?1, ?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET calibration_data = excluded.calibration_data",
+            params![sensor_id, format!("{:?}", sensor_type), blob],
+        )?;
+        
+        Ok(())
+    }
+    
+    /// Load a sensor's stored calibration, if any
+    pub fn load_calibration(&self, sensor_id: &str) -> Result<Option<CalibrationData>> {
+        let conn = self.conn.lock().unwrap();
+        
+        let result: Result<Option<Vec<u8>>, _> = conn.query_row(
+            "SELECT calibration_data FROM sensors WHERE id = ?1",
+            params![sensor_id],
+            |row| row.get(0),
+        );
+        
+        match result {
+            Ok(Some(blob)) => Ok(Some(bincode::deserialize(&blob)?)),
+            Ok(None) | Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Stored health status for a sensor
+    pub fn sensor_status(&self, sensor_id: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        
+        let result = conn.query_row(
+            "SELECT status FROM sensors WHERE id = ?1",
+            params![sensor_id],
             |row| row.get(0),
-        ).unwrap_or(0);
+        );
         
+        match result {
+            Ok(status) => Ok(Some(status)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Checkpoint the WAL so every committed write is in the main database file
+    pub fn flush(&self) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+    
+    /// Row counts and sizes of the whole database and of each table
+    pub fn get_stats(&self) -> Result<DatabaseStats> {
+        let conn = self.conn.lock().unwrap();
+        
+        let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        let table_sizes = table_sizes(&conn);
+        
+        let names: Vec<String> = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        
+        let mut tables = Vec::with_capacity(names.len());
+        for name in names {
+            let row_count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", name), [], |row| row.get(0))?;
+            let (oldest, newest) = match TIME_COLUMNS.iter().find(|(table, _)| *table == name) {
+                Some((_, column)) => conn.query_row(
+                    &format!("SELECT MIN({0}), MAX({0}) FROM \"{1}\"", column, name),
+                    [],
+                    |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, Option<String>>(1)?)),
+                )?,
+                None => (None, None),
+            };
+            tables.push(TableStats {
+                size_bytes: table_sizes.as_ref().map(|sizes| sizes.get(&name).copied().unwrap_or(0)),
+                row_count: row_count as usize,
+                oldest: oldest.as_deref().and_then(parse_timestamp),
+                newest: newest.as_deref().and_then(parse_timestamp),
+                name,
+            });
+        }
+        
+        let count = |name: &str| tables.iter().find(|t| t.name == name).map_or(0, |t| t.row_count);
         Ok(DatabaseStats {
-            reading_count: reading_count as usize,
-            detection_count: detection_count as usize,
-            size_bytes: size_bytes as u64,
+            reading_count: count("readings"),
+            detection_count: count("detections"),
+            size_bytes: (page_count * page_size) as u64,
+            tables,
         })
     }
     
@@ -344,6 +1038,10 @@ impl Database {
             "DELETE FROM detections WHERE timestamp < ?1",
             params![cutoff.to_rfc3339()],
         )?;
+        conn.execute(
+            "DELETE FROM detection_annotations WHERE detection_id NOT IN (SELECT id FROM detections)",
+            [],
+        )?;
         
         // Vacuum to reclaim space
         conn.execute("VACUUM", [])?;
@@ -384,6 +1082,91 @@ impl Database {
     }
 }
 
+/// Reading payload stored as a bincode `Vec<f64>` (rows written before
+/// `payload_format` existed)
+pub const PAYLOAD_BINCODE: u8 = 0;
+
+/// Reading payload stored as raw little-endian f64s
+pub const PAYLOAD_RAW_F64: u8 = 1;
+
+/// Write `values` into `buf` as raw little-endian f64s, reusing its allocation
+fn encode_payload(values: &[f64], buf: &mut Vec<u8>) {
+    buf.clear();
+    buf.reserve(values.len() * 8);
+    for value in values {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// Decode a reading payload written in `format`
+fn decode_payload(data: &[u8], format: u8) -> Result<Vec<f64>> {
+    match format {
+        PAYLOAD_BINCODE => Ok(bincode::deserialize(data)?),
+        PAYLOAD_RAW_F64 => {
+            if data.len() % 8 != 0 {
+                return Err(anyhow!("Raw payload length {} is not a multiple of 8", data.len()));
+            }
+            Ok(data.chunks_exact(8)
+                .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+                .collect())
+        }
+        other => Err(anyhow!("Unknown reading payload format {}", other)),
+    }
+}
+
+/// Mean and maximum of a payload, NULL for an empty one
+fn payload_summary(data: &[f64]) -> (Option<f64>, Option<f64>) {
+    if data.is_empty() {
+        return (None, None);
+    }
+    let mean = data.iter().sum::<f64>() / data.len() as f64;
+    let max = data.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    (Some(mean), Some(max))
+}
+
+/// Bytes of each table's pages plus its indexes', from the `dbstat` virtual
+/// table; `None` when SQLite was built without it
+fn table_sizes(conn: &Connection) -> Option<HashMap<String, u64>> {
+    let query = "SELECT m.tbl_name, SUM(s.pgsize) FROM dbstat s
+                 JOIN sqlite_master m ON m.name = s.name
+                 GROUP BY m.tbl_name";
+    let sizes = conn.prepare(query).and_then(|mut stmt| {
+        let sizes = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64)))?
+            .collect::<rusqlite::Result<HashMap<_, _>>>();
+        sizes
+    });
+    match sizes {
+        Ok(sizes) => Some(sizes),
+        Err(e) => {
+            debug!("Per-table sizes unavailable: {}", e);
+            None
+        }
+    }
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp).ok().map(|t| t.with_timezone(&Utc))
+}
+
+/// Enum variant named by a `{:?}`-formatted column, as detection types
+/// and severities are stored
+fn variant<T: serde::de::DeserializeOwned>(name: &str) -> Result<T> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|_| anyhow!("Unknown variant {:?}", name))
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(1)?;
+        if name == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 #[derive(Debug, Clone)]
 pub struct StoredReading {
     pub id: i64,
@@ -392,6 +1175,83 @@ pub struct StoredReading {
     pub sensor_type: String,
     pub quality: f32,
     pub data: Vec<u8>,
+    /// Mean of the payload, computed at ingest
+    pub mean_value: Option<f64>,
+    /// Largest payload value, computed at ingest
+    pub max_value: Option<f64>,
+    /// How `data` is encoded, `PAYLOAD_BINCODE` or `PAYLOAD_RAW_F64`
+    pub payload_format: u8,
+}
+
+/// Where the next page of `query_readings_page` starts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadingCursor {
+    timestamp: String,
+    id: i64,
+}
+
+/// One page of readings, and the cursor for the next unless this was the last
+#[derive(Debug, Clone)]
+pub struct ReadingPage {
+    pub readings: Vec<StoredReading>,
+    pub next: Option<ReadingCursor>,
+}
+
+/// How many readings one sensor stored over a time range, and when
+#[derive(Debug, Clone)]
+pub struct ReadingSpan {
+    pub sensor_id: String,
+    pub sensor_type: SensorType,
+    pub count: usize,
+    pub first: DateTime<Utc>,
+    pub last: DateTime<Utc>,
+}
+
+/// A stored reading's metadata and summary, without its payload
+#[derive(Debug, Clone)]
+pub struct ReadingSummary {
+    pub timestamp: String,
+    pub sensor_id: String,
+    pub sensor_type: String,
+    pub quality: f32,
+    pub mean_value: Option<f64>,
+    pub max_value: Option<f64>,
+}
+
+/// Summary statistics of one sensor's stored readings
+#[derive(Debug, Clone)]
+pub struct SensorAggregate {
+    pub sensor_id: String,
+    pub count: usize,
+    /// Average of the per-reading means
+    pub mean_value: Option<f64>,
+    pub max_value: Option<f64>,
+}
+
+impl StoredReading {
+    /// Decode the payload samples, whichever format they were stored in
+    pub fn values(&self) -> Result<Vec<f64>> {
+        decode_payload(&self.data, self.payload_format)
+    }
+    
+    /// Decode back into a `SensorReading`
+    pub fn to_reading(&self) -> Result<SensorReading> {
+        let sensor_type: SensorType = self.sensor_type.parse()?;
+        let mut reading = SensorReading::new(&self.sensor_id, sensor_type, self.values()?);
+        reading.timestamp = DateTime::parse_from_rfc3339(&self.timestamp)?.with_timezone(&Utc);
+        reading.quality = self.quality;
+        Ok(reading)
+    }
+}
+
+/// Number of stored detections of one type and severity in one hour
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectionHourCount {
+    /// Start of the hour, in UTC
+    pub hour: DateTime<Utc>,
+    pub detection_type: DetectionType,
+    pub severity: Severity,
+    pub count: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -405,9 +1265,424 @@ pub struct StoredDetection {
     pub data: Vec<u8>,
 }
 
+impl StoredDetection {
+    /// Decode back into a `Detection`
+    pub fn to_detection(&self) -> Result<Detection> {
+        Ok(bincode::deserialize(&self.data)?)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DatabaseStats {
     pub reading_count: usize,
     pub detection_count: usize,
+    /// Size of the database file, WAL aside
     pub size_bytes: u64,
+    /// Every table, by name
+    pub tables: Vec<TableStats>,
+}
+
+impl DatabaseStats {
+    pub fn table(&self, name: &str) -> Option<&TableStats> {
+        self.tables.iter().find(|t| t.name == name)
+    }
+}
+
+/// Rows, size and time span of one table
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableStats {
+    pub name: String,
+    pub row_count: usize,
+    /// Bytes used by the table and its indexes, `None` when SQLite lacks
+    /// the `dbstat` table
+    pub size_bytes: Option<u64>,
+    /// Earliest and latest row, for tables with a time column
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::SecurityConfig;
+    use crate::sensors::SensorType;
+    
+    fn temp_config(name: &str) -> DatabaseConfig {
+        DatabaseConfig {
+            path: std::env::temp_dir().join(format!("glowbarn-{}-{}.db", name, uuid::Uuid::new_v4())),
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn test_encrypted_reading_round_trip() {
+        let config = temp_config("encrypted");
+        let security = Arc::new(SecurityManager::new(SecurityConfig::default()).unwrap());
+        let db = Database::open(&config, Some(security.clone())).unwrap();
+        assert!(db.is_encrypted());
+        
+        let reading = SensorReading::new("emf-probe-1", SensorType::EMFProbe, vec![1.5, -2.25, 3.0]);
+        db.store_reading(&reading).unwrap();
+        
+        // Raw BLOB on disk must not be the plaintext encoding
+        let mut plaintext = Vec::new();
+        encode_payload(&reading.data, &mut plaintext);
+        let (raw, flag): (Vec<u8>, bool) = db.conn.lock().unwrap()
+            .query_row("SELECT data, encrypted FROM readings", [], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        assert!(flag);
+        assert_ne!(raw, plaintext);
+        
+        let start = reading.timestamp - chrono::Duration::seconds(1);
+        let end = reading.timestamp + chrono::Duration::seconds(1);
+        let stored = db.query_readings(start, end, None, None).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].values().unwrap(), reading.data);
+        
+        drop(db);
+        let _ = std::fs::remove_file(&config.path);
+    }
+    
+    #[test]
+    fn test_stored_mean_matches_payload() {
+        let config = temp_config("summary");
+        let db = Database::open(&config, None).unwrap();
+        
+        let single = SensorReading::new("emf-1", SensorType::EMFProbe, vec![1.0, 2.0, 3.0, 10.0]);
+        let batch = vec![
+            SensorReading::new("emf-1", SensorType::EMFProbe, vec![-4.0, 0.5, 0.5]),
+            SensorReading::new("emf-2", SensorType::EMFProbe, vec![]),
+        ];
+        db.store_reading(&single).unwrap();
+        db.store_readings_batch(&batch).unwrap();
+        
+        let start = single.timestamp - chrono::Duration::seconds(1);
+        let end = Utc::now() + chrono::Duration::seconds(1);
+        for stored in db.query_readings(start, end, None, None).unwrap() {
+            let (mean, max) = payload_summary(&stored.values().unwrap());
+            assert_eq!(stored.mean_value, mean);
+            assert_eq!(stored.max_value, max);
+        }
+        
+        let summaries = db.query_reading_summaries(start, end).unwrap();
+        assert_eq!(summaries.len(), 3);
+        assert_eq!(summaries[0].mean_value, Some(4.0));
+        assert_eq!(summaries[0].max_value, Some(10.0));
+        
+        let aggregates = db.aggregate_readings(start, end).unwrap();
+        assert_eq!(aggregates[0].sensor_id, "emf-1");
+        assert_eq!(aggregates[0].count, 2);
+        assert_eq!(aggregates[0].mean_value, Some((4.0 - 1.0) / 2.0));
+        assert_eq!(aggregates[0].max_value, Some(10.0));
+        assert_eq!(aggregates[1].mean_value, None);
+        
+        drop(db);
+        let _ = std::fs::remove_file(&config.path);
+    }
+    
+    #[test]
+    fn test_reopen_fills_missing_summaries_and_skips_corrupt_rows() {
+        let config = temp_config("backfill");
+        let db = Database::open(&config, None).unwrap();
+        let reading = SensorReading::new("emf-1", SensorType::EMFProbe, vec![1.0, 5.0]);
+        db.store_reading(&reading).unwrap();
+        
+        // A summary left NULL (as by an open without the key), and a
+        // payload that doesn't decode
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute("UPDATE readings SET mean_value = NULL, max_value = NULL", []).unwrap();
+            conn.execute(
+                "INSERT INTO readings (timestamp, sensor_id, sensor_type, quality, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![reading.timestamp.to_rfc3339(), "emf-2", "EMFProbe", 1.0, vec![0xffu8; 3]],
+            ).unwrap();
+        }
+        drop(db);
+        
+        let db = Database::open(&config, None).unwrap();
+        let start = reading.timestamp - chrono::Duration::seconds(1);
+        let end = reading.timestamp + chrono::Duration::seconds(1);
+        let summaries = db.query_reading_summaries(start, end).unwrap();
+        let summary = |id: &str| summaries.iter().find(|s| s.sensor_id == id).unwrap().clone();
+        assert_eq!((summary("emf-1").mean_value, summary("emf-1").max_value), (Some(3.0), Some(5.0)));
+        assert_eq!(summary("emf-2").mean_value, None);
+        
+        drop(db);
+        let _ = std::fs::remove_file(&config.path);
+    }
+    
+    #[test]
+    fn test_query_skips_rows_that_wont_decrypt() {
+        let config = temp_config("foreign-key");
+        let reading = SensorReading::new("emf-probe-1", SensorType::EMFProbe, vec![1.0]);
+        
+        let other = Arc::new(SecurityManager::new(SecurityConfig::default()).unwrap());
+        Database::open(&config, Some(other)).unwrap().store_reading(&reading).unwrap();
+        
+        let security = Arc::new(SecurityManager::new(SecurityConfig::default()).unwrap());
+        let db = Database::open(&config, Some(security)).unwrap();
+        let mut readable = reading.clone();
+        readable.data = vec![2.0];
+        db.store_reading(&readable).unwrap();
+        
+        let start = reading.timestamp - chrono::Duration::seconds(1);
+        let end = reading.timestamp + chrono::Duration::seconds(1);
+        let stored = db.query_readings(start, end, None, None).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].values().unwrap(), vec![2.0]);
+        
+        drop(db);
+        let _ = std::fs::remove_file(&config.path);
+    }
+    
+    #[test]
+    fn test_re_encrypt_after_key_rotation() {
+        let config = temp_config("rotate");
+        let reading = SensorReading::new("qrng-1", SensorType::QRNG, vec![0.25, 0.75]);
+        
+        // A row sealed under a key this manager never had
+        let other = Arc::new(SecurityManager::new(SecurityConfig::default()).unwrap());
+        Database::open(&config, Some(other)).unwrap().store_reading(&reading).unwrap();
+        
+        // Rotated while the database shares the manager, as the CLI does
+        let security = Arc::new(SecurityManager::new(SecurityConfig::default()).unwrap());
+        let db = Database::open_shared(&config, Some(security.clone())).unwrap();
+        db.store_reading(&reading).unwrap();
+        assert_eq!(security.rotate_data_key().unwrap(), 1);
+        
+        assert_eq!(db.re_encrypt_all().unwrap(), 1);
+        assert_eq!(db.re_encrypt_all().unwrap(), 0);
+        
+        let start = reading.timestamp - chrono::Duration::seconds(1);
+        let end = reading.timestamp + chrono::Duration::seconds(1);
+        let stored = db.query_readings(start, end, None, None).unwrap();
+        assert_eq!(stored[0].values().unwrap(), reading.data);
+        
+        drop(db);
+        let _ = std::fs::remove_file(&config.path);
+    }
+    
+    #[test]
+    fn test_audit_events_survive_reopen() {
+        let config = temp_config("audit");
+        let mut security_config = SecurityConfig::default();
+        security_config.encrypt_storage = false;
+        let security = Arc::new(SecurityManager::new(security_config).unwrap());
+        
+        let start = Utc::now() - chrono::Duration::seconds(1);
+        {
+            let _db = Database::open_shared(&config, Some(security.clone())).unwrap();
+            
+            let hash = security.hash_password("SecurePassword123!").unwrap();
+            assert!(security.authenticate("field-tech", "wrong", &hash).is_err());
+            assert!(security.authenticate("field-tech", "SecurePassword123!", &hash).is_ok());
+        }
+        let end = Utc::now() + chrono::Duration::seconds(1);
+        
+        let db = Database::open(&config, None).unwrap();
+        let all = db.query_audit(start, end, None).unwrap();
+        assert_eq!(all.len(), 2);
+        
+        let failures = db.query_audit(start, end, Some(AuditEventType::AuthFailure)).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert!(!failures[0].success);
+        assert_eq!(failures[0].user.as_deref(), Some("field-tech"));
+        
+        drop(db);
+        let _ = std::fs::remove_file(&config.path);
+    }
+    
+    #[test]
+    fn test_plaintext_rows_readable_without_security() {
+        let config = temp_config("plain");
+        let db = Database::open(&config, None).unwrap();
+        assert!(!db.is_encrypted());
+        
+        let reading = SensorReading::new("geiger-1", SensorType::GeigerCounter, vec![42.0]);
+        db.store_reading(&reading).unwrap();
+        
+        let start = reading.timestamp - chrono::Duration::seconds(1);
+        let end = reading.timestamp + chrono::Duration::seconds(1);
+        let stored = db.query_readings(start, end, Some("geiger-1"), None).unwrap();
+        assert_eq!(stored[0].values().unwrap(), vec![42.0]);
+        
+        drop(db);
+        let _ = std::fs::remove_file(&config.path);
+    }
+    
+    #[test]
+    fn test_detection_annotation_round_trip() {
+        let config = temp_config("annotations");
+        let db = Database::open(&config, None).unwrap();
+        assert!(db.detection_annotation("det-1").unwrap().is_none());
+        
+        db.annotate_detection("det-1", AnnotationStatus::Acknowledged, None).unwrap();
+        db.annotate_detection("det-2", AnnotationStatus::Confirmed, Some("Recorded on both EMF probes")).unwrap();
+        db.annotate_detection("det-1", AnnotationStatus::FalsePositive, Some("Fridge compressor")).unwrap();
+        
+        let annotation = db.detection_annotation("det-1").unwrap().unwrap();
+        assert_eq!(annotation.status, AnnotationStatus::FalsePositive);
+        assert_eq!(annotation.note.as_deref(), Some("Fridge compressor"));
+        assert_eq!(db.detection_annotations().unwrap().len(), 2);
+        
+        drop(db);
+        let db = Database::open(&config, None).unwrap();
+        let annotation = db.detection_annotation("det-2").unwrap().unwrap();
+        assert_eq!(annotation.status, AnnotationStatus::Confirmed);
+        assert_eq!(annotation.note.as_deref(), Some("Recorded on both EMF probes"));
+        
+        drop(db);
+        let _ = std::fs::remove_file(&config.path);
+    }
+    
+    #[test]
+    fn test_detections_counted_by_hour() {
+        use chrono::TimeZone;
+        use crate::detection::SensorContribution;
+        
+        let config = temp_config("hourly");
+        let db = Database::open(&config, None).unwrap();
+        let at = |h, m| Utc.with_ymd_and_hms(2026, 3, 2, h, m, 0).unwrap();
+        let stored = [
+            (at(3, 10), DetectionType::EMFSpike, Severity::Low),
+            (at(3, 50), DetectionType::EMFSpike, Severity::Low),
+            (at(3, 55), DetectionType::EMFSpike, Severity::High),
+            (at(4, 5), DetectionType::ColdSpot, Severity::Medium),
+            (at(9, 0), DetectionType::ColdSpot, Severity::Medium),
+        ];
+        for (i, (timestamp, detection_type, severity)) in stored.into_iter().enumerate() {
+            db.store_detection(&Detection {
+                id: format!("det-{}", i),
+                timestamp,
+                detection_type,
+                confidence: 0.8,
+                uncertainty: 0.0,
+                severity,
+                sensors: Vec::<SensorContribution>::new(),
+                entropy_deviation: 0.0,
+                anomaly_count: 1,
+                correlation_score: 0.0,
+                classification: None,
+                location: None,
+                payload: None,
+                data_window_start: timestamp,
+                data_window_end: timestamp,
+            }).unwrap();
+        }
+        
+        let mut counts = db.count_detections_by_hour(at(0, 0), at(8, 0)).unwrap();
+        counts.sort_by_key(|c| (c.hour, c.severity));
+        let count = |hour, detection_type, severity, count| DetectionHourCount { hour, detection_type, severity, count };
+        assert_eq!(counts, vec![
+            count(at(3, 0), DetectionType::EMFSpike, Severity::Low, 2),
+            count(at(3, 0), DetectionType::EMFSpike, Severity::High, 1),
+            count(at(4, 0), DetectionType::ColdSpot, Severity::Medium, 1),
+        ]);
+        
+        drop(db);
+        let _ = std::fs::remove_file(&config.path);
+    }
+    
+    #[test]
+    fn test_stats_count_rows_and_size_each_table() {
+        use crate::detection::{DetectionType, SensorContribution, Severity};
+        
+        let config = temp_config("stats");
+        let db = Database::open(&config, None).unwrap();
+        let empty = db.get_stats().unwrap();
+        assert_eq!((empty.reading_count, empty.detection_count), (0, 0));
+        assert!(empty.size_bytes > 0);
+        assert_eq!(empty.table("readings").unwrap().oldest, None);
+        
+        let start = Utc::now() - chrono::Duration::minutes(5);
+        let readings: Vec<SensorReading> = (0..40)
+            .map(|i| {
+                let mut reading = SensorReading::new("emf-1", SensorType::EMFProbe, vec![i as f64; 128]);
+                reading.timestamp = start + chrono::Duration::seconds(i);
+                reading
+            })
+            .collect();
+        db.store_readings_batch(&readings).unwrap();
+        for i in 0..3 {
+            db.store_detection(&Detection {
+                id: format!("det-{}", i),
+                timestamp: start + chrono::Duration::seconds(i),
+                detection_type: DetectionType::EMFSpike,
+                confidence: 0.8,
+                uncertainty: 0.0,
+                severity: Severity::Medium,
+                sensors: vec![SensorContribution {
+                    sensor_id: "emf-1".to_string(),
+                    sensor_type: SensorType::EMFProbe,
+                    weight: 0.7,
+                    reading_value: 1.0,
+                    anomaly_score: 0.9,
+                }],
+                entropy_deviation: 0.0,
+                anomaly_count: 1,
+                correlation_score: 0.0,
+                classification: None,
+                location: None,
+                payload: None,
+                data_window_start: start,
+                data_window_end: start,
+            }).unwrap();
+        }
+        
+        let stats = db.get_stats().unwrap();
+        assert_eq!(stats.reading_count, 40);
+        assert_eq!(stats.detection_count, 3);
+        assert!(stats.size_bytes > empty.size_bytes);
+        
+        let readings = stats.table("readings").unwrap();
+        assert_eq!(readings.row_count, 40);
+        assert_eq!(readings.oldest.unwrap().timestamp(), start.timestamp());
+        assert_eq!(readings.newest.unwrap().timestamp(), start.timestamp() + 39);
+        assert_eq!(stats.table("detections").unwrap().row_count, 3);
+        assert_eq!(stats.table("settings").unwrap().oldest, None);
+        
+        // 40 readings of 128 samples don't fit in a few pages
+        if let Some(size) = readings.size_bytes {
+            assert!(size > 40 * 128 * 4, "readings take {} bytes", size);
+            assert!(size <= stats.size_bytes);
+            assert!(stats.table("detections").unwrap().size_bytes.unwrap() > 0);
+        }
+        
+        drop(db);
+        let _ = std::fs::remove_file(&config.path);
+    }
+    
+    #[test]
+    fn test_legacy_bincode_payloads_still_decode() {
+        let config = temp_config("formats");
+        let db = Database::open(&config, None).unwrap();
+        
+        let reading = SensorReading::new("emf-1", SensorType::EMFProbe, vec![0.5, -1.25, 8.0]);
+        db.store_reading(&reading).unwrap();
+        
+        // A row as written before payload_format existed
+        db.conn.lock().unwrap().execute(
+            "INSERT INTO readings (timestamp, sensor_id, sensor_type, quality, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                reading.timestamp.to_rfc3339(),
+                "emf-2",
+                "EMFProbe",
+                1.0,
+                bincode::serialize(&reading.data).unwrap()
+            ],
+        ).unwrap();
+        
+        let start = reading.timestamp - chrono::Duration::seconds(1);
+        let end = reading.timestamp + chrono::Duration::seconds(1);
+        let stored = db.query_readings(start, end, None, None).unwrap();
+        assert_eq!(stored.len(), 2);
+        for row in &stored {
+            let expected = if row.sensor_id == "emf-1" { PAYLOAD_RAW_F64 } else { PAYLOAD_BINCODE };
+            assert_eq!(row.payload_format, expected);
+            assert_eq!(row.to_reading().unwrap().data, reading.data);
+        }
+        
+        drop(db);
+        let _ = std::fs::remove_file(&config.path);
+    }
 }
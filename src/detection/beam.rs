@@ -0,0 +1,159 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Beam-break tracking for laser grids
+//!
+//! Beam intensities are nominally 1. A beam breaks when it drops below
+//! `BEAM_BREAK_THRESHOLD` and is restored once it climbs back above
+//! `BEAM_RESTORE_THRESHOLD`; the gap between the two keeps a beam hovering
+//! near the threshold from chattering. Every beam of every grid is tracked
+//! on its own, so simultaneous breaks are reported separately.
+
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::sensors::{SensorReading, SensorType};
+
+/// Beam intensity below which a beam counts as broken
+pub const BEAM_BREAK_THRESHOLD: f64 = 0.5;
+
+/// Beam intensity a broken beam must recover to before it counts as restored
+pub const BEAM_RESTORE_THRESHOLD: f64 = 0.6;
+
+/// Which beam of a grid broke and, once it's restored, for how long
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BeamBreak {
+    pub beam: usize,
+    pub dwell_ms: Option<i64>,
+}
+
+/// Edge of one beam's break
+#[derive(Debug, Clone, PartialEq)]
+pub enum BeamEvent {
+    Interrupted {
+        sensor_id: String,
+        beam: usize,
+        at: DateTime<Utc>,
+        intensity: f64,
+    },
+    Restored {
+        sensor_id: String,
+        beam: usize,
+        at: DateTime<Utc>,
+        dwell: Duration,
+    },
+}
+
+/// Confidence that a beam at `intensity` is broken: 0.5 at
+/// `BEAM_BREAK_THRESHOLD`, rising to 1 for a fully blocked beam
+pub fn beam_break_confidence(intensity: f64) -> f64 {
+    let intensity = intensity.clamp(0.0, 1.0);
+    if intensity < BEAM_BREAK_THRESHOLD {
+        0.5 + 0.5 * (BEAM_BREAK_THRESHOLD - intensity) / BEAM_BREAK_THRESHOLD
+    } else {
+        0.5 * (1.0 - intensity) / (1.0 - BEAM_BREAK_THRESHOLD)
+    }
+}
+
+/// Tracks which beams of each laser grid are broken and since when
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BeamBreakTracker {
+    // Per grid, per beam: when the current break started
+    broken_since: HashMap<String, Vec<Option<DateTime<Utc>>>>,
+}
+
+impl BeamBreakTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update beam states from a laser grid reading, returning the edges it
+    /// crossed in beam order. Other sensor types are ignored.
+    pub fn update(&mut self, reading: &SensorReading) -> Vec<BeamEvent> {
+        if reading.sensor_type != SensorType::LaserGrid {
+            return Vec::new();
+        }
+
+        let beams = self.broken_since.entry(reading.sensor_id.clone()).or_default();
+        if beams.len() < reading.data.len() {
+            beams.resize(reading.data.len(), None);
+        }
+
+        let mut events = Vec::new();
+        for (beam, (&intensity, since)) in reading.data.iter().zip(beams.iter_mut()).enumerate() {
+            match *since {
+                None if intensity < BEAM_BREAK_THRESHOLD => {
+                    *since = Some(reading.timestamp);
+                    events.push(BeamEvent::Interrupted {
+                        sensor_id: reading.sensor_id.clone(),
+                        beam,
+                        at: reading.timestamp,
+                        intensity,
+                    });
+                }
+                Some(start) if intensity >= BEAM_RESTORE_THRESHOLD => {
+                    *since = None;
+                    events.push(BeamEvent::Restored {
+                        sensor_id: reading.sensor_id.clone(),
+                        beam,
+                        at: reading.timestamp,
+                        dwell: reading.timestamp - start,
+                    });
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+
+    /// Beams of `sensor_id` that are currently broken
+    pub fn broken_beams(&self, sensor_id: &str) -> Vec<usize> {
+        self.broken_since.get(sensor_id)
+            .map(|beams| beams.iter().enumerate().filter(|(_, s)| s.is_some()).map(|(i, _)| i).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_beam_dwell_is_measured_between_edges() {
+        let mut tracker = BeamBreakTracker::new();
+        let t0 = Utc::now();
+        let frame = |ms: i64, broken: &[(usize, f64)]| {
+            let mut beams = vec![0.98; 16];
+            for &(beam, intensity) in broken {
+                beams[beam] = intensity;
+            }
+            let mut reading = SensorReading::new("laser-1", SensorType::LaserGrid, beams);
+            reading.timestamp = t0 + Duration::milliseconds(ms);
+            reading
+        };
+
+        assert!(tracker.update(&frame(0, &[])).is_empty());
+
+        // Beams 7 and 12 break together; 7 flickers under the restore level
+        let events = tracker.update(&frame(100, &[(7, 0.1), (12, 0.2)]));
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], BeamEvent::Interrupted { beam: 7, .. }));
+        assert!(matches!(events[1], BeamEvent::Interrupted { beam: 12, .. }));
+        assert!(tracker.update(&frame(200, &[(7, 0.55), (12, 0.2)])).is_empty());
+        assert_eq!(tracker.broken_beams("laser-1"), vec![7, 12]);
+
+        let events = tracker.update(&frame(440, &[(12, 0.2)]));
+        assert_eq!(events, vec![BeamEvent::Restored {
+            sensor_id: "laser-1".to_string(),
+            beam: 7,
+            at: t0 + Duration::milliseconds(440),
+            dwell: Duration::milliseconds(340),
+        }]);
+
+        let events = tracker.update(&frame(600, &[]));
+        assert!(matches!(events[..], [BeamEvent::Restored { beam: 12, dwell, .. }] if dwell.num_milliseconds() == 500));
+        assert!(tracker.broken_beams("laser-1").is_empty());
+    }
+}
@@ -87,7 +87,7 @@ impl AnomalyClassifier {
         let scores = self.score_categories(&features);
         
         let (best_category, best_score) = scores.iter()
-            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .max_by(|a, b| a.1.total_cmp(b.1))
             .map(|(c, s)| (c.clone(), *s))
             .unwrap_or(("Unknown".to_string(), 0.0));
         
@@ -115,7 +115,8 @@ impl AnomalyClassifier {
         
         features.insert("is_acoustic".to_string(),
             if matches!(detection.detection_type,
-                DetectionType::InfrasoundEvent | DetectionType::UltrasonicEvent | DetectionType::UnexplainedSound)
+                DetectionType::InfrasoundEvent | DetectionType::UltrasonicEvent | DetectionType::EVP
+                    | DetectionType::UnexplainedSound)
             { 1.0 } else { 0.0 });
         
         features.insert("is_seismic".to_string(),
@@ -18,17 +18,25 @@ pub struct CorrelationEvent {
     pub sensors: Vec<SensorContribution>,
     pub confidence: f64,
     pub lag_ms: i64,
+    /// Fraction of positioned sensors inside the cluster radius (0 if fewer than two)
+    pub spatial_score: f64,
+    /// Mean position of the anomalous sensors that report one
+    pub centroid: Option<[f64; 3]>,
 }
 
 /// Sensor correlator
 pub struct SensorCorrelator {
-    // Buffer of recent readings per sensor
+    // Buffer of recent readings per sensor, trimmed to the correlation window
     buffers: HashMap<String, VecDeque<TimestampedReading>>,
-    buffer_duration_ms: i64,
     min_correlation: f64,
     
     // Correlation windows
     correlation_window_ms: i64,
+    min_correlated_sensors: usize,
+    
+    // Spatial clustering
+    cluster_radius_m: f64,
+    spatial_weight: f64,
 }
 
 #[derive(Debug, Clone)]
@@ -37,20 +45,63 @@ struct TimestampedReading {
     value: f64,
     sensor_type: SensorType,
     anomaly_score: f64,
+    position: Option<[f64; 3]>,
 }
 
 impl SensorCorrelator {
     pub fn new() -> Self {
+        Self::with_config(2000, 2)
+    }
+    
+    /// Correlator using the window and sensor count from `DetectionConfig`
+    pub fn with_config(correlation_window_ms: u64, min_correlated_sensors: usize) -> Self {
         Self {
             buffers: HashMap::new(),
-            buffer_duration_ms: 10000,  // 10 seconds
             min_correlation: 0.5,
-            correlation_window_ms: 2000,  // 2 second window
+            correlation_window_ms: correlation_window_ms as i64,
+            min_correlated_sensors,
+            cluster_radius_m: 5.0,
+            spatial_weight: 0.2,
         }
     }
     
+    /// Boost confidence by up to `weight` when anomalous sensors sit within `radius_m` of each other
+    pub fn with_spatial(mut self, radius_m: f64, weight: f64) -> Self {
+        self.cluster_radius_m = radius_m;
+        self.spatial_weight = weight;
+        self
+    }
+    
+    pub fn set_spatial(&mut self, radius_m: f64, weight: f64) {
+        self.cluster_radius_m = radius_m;
+        self.spatial_weight = weight;
+    }
+    
+    pub fn correlation_window_ms(&self) -> u64 {
+        self.correlation_window_ms as u64
+    }
+    
+    pub fn set_correlation_window_ms(&mut self, window_ms: u64) {
+        self.correlation_window_ms = window_ms as i64;
+    }
+    
+    pub fn min_correlated_sensors(&self) -> usize {
+        self.min_correlated_sensors
+    }
+    
+    /// Number of distinct sensors that must fire together (never fewer than 2)
+    pub fn set_min_correlated_sensors(&mut self, count: usize) {
+        self.min_correlated_sensors = count;
+    }
+    
     /// Add a reading to correlation tracking
     pub fn add_reading(&mut self, reading: SensorReading) {
+        self.add_reading_at(reading, Utc::now());
+    }
+    
+    /// Add a reading, expiring entries relative to `now` instead of the wall
+    /// clock (for recorded data)
+    pub fn add_reading_at(&mut self, reading: SensorReading, now: DateTime<Utc>) {
         let value = if reading.data.is_empty() {
             0.0
         } else {
@@ -64,24 +115,31 @@ impl SensorCorrelator {
             value,
             sensor_type: reading.sensor_type,
             anomaly_score,
+            position: reading.position,
         };
         
-        let buffer = self.buffers
+        self.buffers
             .entry(reading.sensor_id)
-            .or_insert_with(VecDeque::new);
-        
-        buffer.push_back(entry);
-        
-        // Remove old entries
-        let cutoff = Utc::now() - Duration::milliseconds(self.buffer_duration_ms);
-        while buffer.front().map(|r| r.timestamp < cutoff).unwrap_or(false) {
-            buffer.pop_front();
-        }
+            .or_insert_with(VecDeque::new)
+            .push_back(entry);
+        
+        // Expire entries that fell out of the window, including from quiet sensors
+        let cutoff = now - Duration::milliseconds(self.correlation_window_ms);
+        self.buffers.retain(|_, buffer| {
+            while buffer.front().map(|r| r.timestamp < cutoff).unwrap_or(false) {
+                buffer.pop_front();
+            }
+            !buffer.is_empty()
+        });
     }
     
     /// Check for correlated events across sensors
     pub fn check_correlation(&self) -> Option<CorrelationEvent> {
-        let now = Utc::now();
+        self.check_correlation_at(Utc::now())
+    }
+    
+    /// Check for correlated events in the window ending at `now`
+    pub fn check_correlation_at(&self, now: DateTime<Utc>) -> Option<CorrelationEvent> {
         let window_start = now - Duration::milliseconds(self.correlation_window_ms);
         
         // Collect recent anomalous readings from different sensors
@@ -99,12 +157,12 @@ impl SensorCorrelator {
             }
         }
         
-        // Need at least 2 different sensors with anomalies
+        // Need enough different sensors with anomalies
         let unique_sensors: std::collections::HashSet<_> = anomalous_readings.iter()
             .map(|(id, _)| *id)
             .collect();
         
-        if unique_sensors.len() < 2 {
+        if unique_sensors.len() < self.min_correlated_sensors.max(2) {
             return None;
         }
         
@@ -128,7 +186,17 @@ impl SensorCorrelator {
         
         let sensor_diversity = unique_sensors.len() as f64 / 5.0;  // Normalize
         
-        let confidence = (avg_anomaly * 0.6 + sensor_diversity * 0.4).min(1.0);
+        // Sensors firing close together are more convincing than scattered ones
+        let mut positions: HashMap<&String, [f64; 3]> = HashMap::new();
+        for (sensor_id, reading) in &anomalous_readings {
+            if let Some(pos) = reading.position {
+                positions.entry(*sensor_id).or_insert(pos);
+            }
+        }
+        let (spatial_score, centroid) = self.spatial_cluster(&positions.into_values().collect::<Vec<_>>());
+        
+        let confidence = (avg_anomaly * 0.6 + sensor_diversity * 0.4 
+            + spatial_score * self.spatial_weight).min(1.0);
         
         if confidence > self.min_correlation {
             // Calculate time lag between first and last anomaly
@@ -144,12 +212,39 @@ impl SensorCorrelator {
                 sensors: sensor_contributions,
                 confidence,
                 lag_ms,
+                spatial_score,
+                centroid,
             })
         } else {
             None
         }
     }
     
+    /// Centroid of the given positions and the fraction lying within the cluster radius of it
+    fn spatial_cluster(&self, positions: &[[f64; 3]]) -> (f64, Option<[f64; 3]>) {
+        if positions.is_empty() {
+            return (0.0, None);
+        }
+        
+        let n = positions.len() as f64;
+        let mut centroid = [0.0; 3];
+        for pos in positions {
+            for (c, p) in centroid.iter_mut().zip(pos) {
+                *c += p / n;
+            }
+        }
+        
+        let clustered = positions.iter()
+            .filter(|pos| {
+                let dist_sq: f64 = pos.iter().zip(&centroid).map(|(p, c)| (p - c).powi(2)).sum();
+                dist_sq.sqrt() <= self.cluster_radius_m
+            })
+            .count();
+        
+        let score = if clustered >= 2 { clustered as f64 / n } else { 0.0 };
+        (score, Some(centroid))
+    }
+    
     /// Calculate cross-correlation between two sensor buffers
     pub fn cross_correlate(&self, sensor1: &str, sensor2: &str, max_lag_ms: i64) -> Option<(f64, i64)> {
         let buffer1 = self.buffers.get(sensor1)?;
@@ -265,3 +360,48 @@ impl SensorCorrelator {
         matrix
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn spike_at(id: &str, position: [f64; 3]) -> SensorReading {
+        let mut data = vec![0.0; 20];
+        data[19] = 50.0;
+        let mut reading = SensorReading::new(id, SensorType::EMFProbe, data);
+        reading.position = Some(position);
+        reading
+    }
+    
+    fn correlate(positions: &[[f64; 3]]) -> CorrelationEvent {
+        let mut correlator = SensorCorrelator::new();
+        for (i, &pos) in positions.iter().enumerate() {
+            correlator.add_reading(spike_at(&format!("emf-{}", i), pos));
+        }
+        correlator.check_correlation().expect("coincident spikes correlate")
+    }
+    
+    #[test]
+    fn test_clustered_sensors_are_stronger() {
+        let clustered = correlate(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+        let dispersed = correlate(&[[0.0, 0.0, 0.0], [40.0, 0.0, 0.0], [0.0, 40.0, 0.0]]);
+        
+        assert!(clustered.confidence > dispersed.confidence);
+        assert_eq!(clustered.spatial_score, 1.0);
+        assert_eq!(dispersed.spatial_score, 0.0);
+        
+        let [x, y, z] = clustered.centroid.unwrap();
+        assert!((x - 1.0 / 3.0).abs() < 1e-9 && (y - 1.0 / 3.0).abs() < 1e-9 && z == 0.0);
+    }
+    
+    #[test]
+    fn test_min_correlated_sensors() {
+        let mut correlator = SensorCorrelator::with_config(2000, 2);
+        correlator.add_reading(spike_at("emf-1", [0.0; 3]));
+        correlator.add_reading(spike_at("emf-2", [0.0; 3]));
+        assert!(correlator.check_correlation().is_some());
+        
+        correlator.set_min_correlated_sensors(3);
+        assert!(correlator.check_correlation().is_none());
+    }
+}
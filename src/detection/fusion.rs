@@ -5,24 +5,94 @@
 //! Sensor fusion engine - Bayesian, Dempster-Shafer, and neural fusion
 
 use std::collections::HashMap;
+use std::time::Duration;
 use nalgebra::{DMatrix, DVector};
 use serde::{Deserialize, Serialize};
 
+use crate::config::FusionMethod;
+use crate::analysis::{bits_from_unit_samples, nist_battery, normal_isf, poisson_upper_tail, RunningStats};
 use crate::sensors::{SensorReading, SensorType};
-use super::{SensorContribution, DetectionType};
+use super::{beam_break_confidence, AnnotationStatus, SensorContribution, DetectionType, QRNG_BITS_PER_SAMPLE};
+
+/// Conflict K above which Dempster's normalization by 1 - K isn't trusted
+const HIGH_CONFLICT: f64 = 0.9;
+
+/// Buffered readings needed before a sensor's history sets its baseline
+const MIN_HISTORY: usize = 5;
+
+/// Share of a sensor type's weight a false positive takes away, and of its
+/// distance to 1 a confirmation adds
+const FEEDBACK_RATE: f64 = 0.1;
+
+/// Lowest weight false-positive feedback can push a sensor type to
+const MIN_FEEDBACK_WEIGHT: f64 = 0.05;
+
+/// Prior probability of an anomaly that Bayesian fusion starts from
+const ANOMALY_PRIOR: f64 = 0.1;
 
 /// Fusion result
 #[derive(Debug, Clone)]
 pub struct FusionResult {
     pub confidence: f64,
+    /// Bel(anomaly): evidence committed to an anomaly
+    pub belief: f64,
+    /// Pl(anomaly) = 1 - Bel(normal): evidence not against an anomaly
+    pub plausibility: f64,
+    /// Width of [belief, plausibility], evidence committed to neither
+    pub uncertainty: f64,
+    /// Largest conflict K met while combining evidence
+    pub conflict: f64,
+    /// False when sources conflicted too much for Dempster's rule
+    pub reliable: bool,
     pub detection_type: DetectionType,
     pub sensors: Vec<SensorContribution>,
     pub belief_mass: HashMap<String, f64>,
 }
 
+impl FusionResult {
+    /// Result of a method that yields a single probability, no interval
+    fn precise(confidence: f64, detection_type: DetectionType, sensors: Vec<SensorContribution>) -> Self {
+        Self {
+            confidence,
+            belief: confidence,
+            plausibility: confidence,
+            uncertainty: 0.0,
+            conflict: 0.0,
+            reliable: true,
+            detection_type,
+            sensors,
+            belief_mass: HashMap::new(),
+        }
+    }
+}
+
+/// Default sensor reliability weights; other types count as 0.5
+const DEFAULT_WEIGHTS: &[(SensorType, f64)] = &[
+    (SensorType::ThermalImager, 0.85),
+    (SensorType::ThermalArray, 0.80),
+    (SensorType::Accelerometer, 0.75),
+    (SensorType::Geophone, 0.80),
+    (SensorType::EMFProbe, 0.70),
+    (SensorType::FluxGate, 0.85),
+    (SensorType::Infrasound, 0.75),
+    (SensorType::Ultrasonic, 0.75),
+    (SensorType::GeigerCounter, 0.90),
+    (SensorType::QRNG, 0.80),
+    (SensorType::SDRReceiver, 0.70),
+    (SensorType::LaserGrid, 0.95),
+];
+
 /// Sensor fusion engine
+#[derive(Clone)]
 pub struct FusionEngine {
-    // Sensor reliability weights
+    // Sensor reliability weights, as configured
+    base_weights: HashMap<SensorType, f64>,
+    
+    // Net confirmations (positive) or false positives (negative) reported
+    // for each sensor type by annotation feedback
+    feedback: HashMap<SensorType, i32>,
+    
+    // Weights in use: the base weights adjusted by feedback
     sensor_weights: HashMap<SensorType, f64>,
     
     // Belief masses for Dempster-Shafer
@@ -43,23 +113,11 @@ pub struct BeliefMass {
 
 impl FusionEngine {
     pub fn new() -> Self {
-        let mut sensor_weights = HashMap::new();
-        
-        // Default sensor reliability weights
-        sensor_weights.insert(SensorType::ThermalImager, 0.85);
-        sensor_weights.insert(SensorType::ThermalArray, 0.80);
-        sensor_weights.insert(SensorType::Accelerometer, 0.75);
-        sensor_weights.insert(SensorType::Geophone, 0.80);
-        sensor_weights.insert(SensorType::EMFProbe, 0.70);
-        sensor_weights.insert(SensorType::FluxGate, 0.85);
-        sensor_weights.insert(SensorType::Infrasound, 0.75);
-        sensor_weights.insert(SensorType::Ultrasonic, 0.75);
-        sensor_weights.insert(SensorType::GeigerCounter, 0.90);
-        sensor_weights.insert(SensorType::QRNG, 0.80);
-        sensor_weights.insert(SensorType::SDRReceiver, 0.70);
-        sensor_weights.insert(SensorType::LaserGrid, 0.95);
+        let sensor_weights: HashMap<SensorType, f64> = DEFAULT_WEIGHTS.iter().copied().collect();
         
         Self {
+            base_weights: sensor_weights.clone(),
+            feedback: HashMap::new(),
             sensor_weights,
             belief_masses: HashMap::new(),
             reading_buffer: HashMap::new(),
@@ -67,6 +125,16 @@ impl FusionEngine {
         }
     }
     
+    /// Buffered readings of each sensor, e.g. for a checkpoint
+    pub fn reading_history(&self) -> &HashMap<String, Vec<SensorReading>> {
+        &self.reading_buffer
+    }
+    
+    /// Replace the buffered readings, e.g. from a checkpoint
+    pub fn set_reading_history(&mut self, history: HashMap<String, Vec<SensorReading>>) {
+        self.reading_buffer = history;
+    }
+    
     /// Add reading to fusion buffer
     pub fn add_reading(&mut self, reading: SensorReading) {
         let buffer = self.reading_buffer
@@ -80,15 +148,27 @@ impl FusionEngine {
         }
     }
     
+    /// Fuse the newest buffered reading of each of `sensor_ids`, once per
+    /// sensor, with `method`
+    pub fn fuse_latest(&self, sensor_ids: &[&str], method: FusionMethod) -> FusionResult {
+        let mut readings: Vec<SensorReading> = Vec::new();
+        for &id in sensor_ids {
+            if readings.iter().any(|r| r.sensor_id == id) {
+                continue;
+            }
+            readings.extend(self.reading_buffer.get(id).and_then(|buffer| buffer.last()).cloned());
+        }
+        match method {
+            FusionMethod::Bayesian => self.bayesian_fusion(&readings, ANOMALY_PRIOR),
+            FusionMethod::DempsterShafer => self.dempster_shafer_fusion(&readings),
+            FusionMethod::WeightedAverage => self.weighted_fusion(&readings),
+        }
+    }
+    
     /// Bayesian fusion of multiple sensor readings
     pub fn bayesian_fusion(&self, readings: &[SensorReading], prior_anomaly: f64) -> FusionResult {
         if readings.is_empty() {
-            return FusionResult {
-                confidence: 0.0,
-                detection_type: DetectionType::Unknown,
-                sensors: vec![],
-                belief_mass: HashMap::new(),
-            };
+            return FusionResult::precise(0.0, DetectionType::Unknown, vec![]);
         }
         
         // Start with prior probability
@@ -128,23 +208,13 @@ impl FusionEngine {
         
         let detection_type = self.classify_from_sensors(&sensors);
         
-        FusionResult {
-            confidence: posterior,
-            detection_type,
-            sensors,
-            belief_mass: HashMap::new(),
-        }
+        FusionResult::precise(posterior, detection_type, sensors)
     }
     
     /// Dempster-Shafer fusion for handling uncertainty
     pub fn dempster_shafer_fusion(&self, readings: &[SensorReading]) -> FusionResult {
         if readings.is_empty() {
-            return FusionResult {
-                confidence: 0.0,
-                detection_type: DetectionType::Unknown,
-                sensors: vec![],
-                belief_mass: HashMap::new(),
-            };
+            return FusionResult::precise(0.0, DetectionType::Unknown, vec![]);
         }
         
         // Initialize with complete uncertainty
@@ -155,6 +225,7 @@ impl FusionEngine {
         };
         
         let mut sensors = Vec::new();
+        let mut conflict: f64 = 0.0;
         
         for reading in readings {
             let anomaly_score = self.calculate_anomaly_score(reading);
@@ -171,7 +242,9 @@ impl FusionEngine {
             };
             
             // Dempster's rule of combination
-            combined = self.combine_belief_masses(&combined, &mass);
+            let (next, k) = self.combine_belief_masses(&combined, &mass);
+            combined = next;
+            conflict = conflict.max(k);
             
             sensors.push(SensorContribution {
                 sensor_id: reading.sensor_id.clone(),
@@ -200,47 +273,55 @@ impl FusionEngine {
         
         FusionResult {
             confidence,
+            belief: combined.anomaly,
+            plausibility: combined.anomaly + combined.uncertainty,
+            uncertainty: combined.uncertainty,
+            conflict,
+            reliable: conflict <= HIGH_CONFLICT,
             detection_type,
             sensors,
             belief_mass,
         }
     }
     
-    /// Combine two belief masses using Dempster's rule
-    fn combine_belief_masses(&self, m1: &BeliefMass, m2: &BeliefMass) -> BeliefMass {
+    /// Combine two belief masses using Dempster's rule, returning the
+    /// combined mass and the conflict K between them
+    fn combine_belief_masses(&self, m1: &BeliefMass, m2: &BeliefMass) -> (BeliefMass, f64) {
         // Calculate combined masses
         // m12(A) = Σ(m1(B) * m2(C)) for B∩C=A, divided by (1-K)
         // K is the conflict
         
         let k = m1.anomaly * m2.normal + m1.normal * m2.anomaly;  // Conflict
-        let normalizer = (1.0 - k).max(1e-10);
         
-        let anomaly = (
-            m1.anomaly * m2.anomaly +
+        let anomaly = m1.anomaly * m2.anomaly +
             m1.anomaly * m2.uncertainty +
-            m1.uncertainty * m2.anomaly
-        ) / normalizer;
+            m1.uncertainty * m2.anomaly;
         
-        let normal = (
-            m1.normal * m2.normal +
+        let normal = m1.normal * m2.normal +
             m1.normal * m2.uncertainty +
-            m1.uncertainty * m2.normal
-        ) / normalizer;
+            m1.uncertainty * m2.normal;
         
-        let uncertainty = (m1.uncertainty * m2.uncertainty) / normalizer;
+        let uncertainty = m1.uncertainty * m2.uncertainty;
+        
+        if k > HIGH_CONFLICT {
+            // Dividing by a tiny 1 - K would turn a flat disagreement into
+            // near-certainty; keep the conflicting mass as ignorance instead
+            // (Yager's rule)
+            return (BeliefMass { anomaly, normal, uncertainty: uncertainty + k }, k);
+        }
         
-        BeliefMass { anomaly, normal, uncertainty }
+        let normalizer = 1.0 - k;
+        (BeliefMass {
+            anomaly: anomaly / normalizer,
+            normal: normal / normalizer,
+            uncertainty: uncertainty / normalizer,
+        }, k)
     }
     
     /// Weighted average fusion (simple but effective)
     pub fn weighted_fusion(&self, readings: &[SensorReading]) -> FusionResult {
         if readings.is_empty() {
-            return FusionResult {
-                confidence: 0.0,
-                detection_type: DetectionType::Unknown,
-                sensors: vec![],
-                belief_mass: HashMap::new(),
-            };
+            return FusionResult::precise(0.0, DetectionType::Unknown, vec![]);
         }
         
         let mut weighted_sum = 0.0;
@@ -275,43 +356,211 @@ impl FusionEngine {
         
         let detection_type = self.classify_from_sensors(&sensors);
         
-        FusionResult {
-            confidence,
-            detection_type,
-            sensors,
-            belief_mass: HashMap::new(),
+        FusionResult::precise(confidence, detection_type, sensors)
+    }
+    
+    /// Fuse one sensor's buffered readings from the last `window`.
+    ///
+    /// Each reading's mean is scored against a baseline taken from the
+    /// buffered readings before the window. The confidence averages the
+    /// mean score (level) with the share of readings that are elevated
+    /// (persistence), so a sustained elevation outweighs a one-sample blip.
+    /// The window ends at the newest buffered reading, which keeps replayed
+    /// recordings working.
+    pub fn temporal_fusion(&self, sensor_id: &str, window: Duration) -> FusionResult {
+        let empty = || FusionResult::precise(0.0, DetectionType::Unknown, vec![]);
+        let Some(buffer) = self.reading_buffer.get(sensor_id) else {
+            return empty();
+        };
+        let Some(newest) = buffer.last() else {
+            return empty();
+        };
+        // A window too long to represent covers the whole buffer
+        let start = chrono::Duration::from_std(window).ok()
+            .and_then(|w| newest.timestamp.checked_sub_signed(w));
+        
+        let mean_of = |r: &SensorReading| r.data.iter().sum::<f64>() / r.data.len().max(1) as f64;
+        let (before, recent): (Vec<&SensorReading>, Vec<&SensorReading>) =
+            buffer.iter().partition(|r| matches!(start, Some(start) if r.timestamp < start));
+        if before.len() < 2 || recent.is_empty() {
+            return empty();
         }
+        
+        let baseline = RunningStats::from_slice(&before.iter().map(|&r| mean_of(r)).collect::<Vec<_>>());
+        let spread = baseline.std_dev().max(1e-10);
+        let scores: Vec<f64> = recent.iter()
+            .map(|&r| ((mean_of(r) - baseline.mean()).abs() / spread, r.quality as f64))
+            .map(|(z, quality)| z_to_score(z) * quality)
+            .collect();
+        
+        let level = scores.iter().sum::<f64>() / scores.len() as f64;
+        let elevated = scores.iter().filter(|&&s| s > 0.5).count();
+        let persistence = elevated as f64 / scores.len() as f64;
+        
+        let weight = self.sensor_weights
+            .get(&newest.sensor_type)
+            .copied()
+            .unwrap_or(0.5);
+        
+        let sensors = vec![SensorContribution {
+            sensor_id: sensor_id.to_string(),
+            sensor_type: newest.sensor_type,
+            weight,
+            reading_value: recent.iter().map(|&r| mean_of(r)).sum::<f64>() / recent.len() as f64,
+            anomaly_score: level,
+        }];
+        let detection_type = self.classify_from_sensors(&sensors);
+        
+        FusionResult::precise(weight * (level + persistence) / 2.0, detection_type, sensors)
     }
     
-    /// Calculate anomaly score for a reading
+    /// Calculate anomaly score for a reading, in the terms that make sense
+    /// for its sensor type
     fn calculate_anomaly_score(&self, reading: &SensorReading) -> f64 {
         if reading.data.is_empty() {
             return 0.0;
         }
         
-        // Simple statistical anomaly score
-        let mean = reading.data.iter().sum::<f64>() / reading.data.len() as f64;
-        let variance = reading.data.iter()
-            .map(|&x| (x - mean).powi(2))
-            .sum::<f64>() / reading.data.len() as f64;
-        let std_dev = variance.sqrt();
+        let score = match reading.sensor_type {
+            SensorType::GeigerCounter | SensorType::Scintillator => self.poisson_score(reading),
+            SensorType::QRNG => self.qrng_score(reading),
+            SensorType::SDRReceiver | SensorType::SpectrumAnalyzer => self.spectral_peak_score(reading),
+            SensorType::LaserGrid => self.beam_break_score(reading),
+            _ => self.deviation_score(reading),
+        };
         
-        // Check for values far from mean
-        let max_deviation = reading.data.iter()
-            .map(|&x| (x - mean).abs())
-            .fold(0.0_f64, f64::max);
+        // Adjust based on reading quality
+        score * reading.quality as f64
+    }
+    
+    /// Generic score: largest deviation from the sensor's usual level, in
+    /// units of its usual spread. Values are compared in their canonical
+    /// unit, so history recorded in another unit still lines up. Without
+    /// enough (or with perfectly flat) history the reading is compared with
+    /// its own mean.
+    fn deviation_score(&self, reading: &SensorReading) -> f64 {
+        let data = reading.to_canonical();
+        let history: Vec<f64> = self.history(reading)
+            .flat_map(|r| r.to_canonical())
+            .collect();
+        
+        let level_and_spread = |values: &[f64]| {
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let std_dev = (values.iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+            (std_dev > 1e-10).then_some((mean, std_dev))
+        };
+        let baseline = if history.len() >= MIN_HISTORY { level_and_spread(&history) } else { None }
+            .or_else(|| level_and_spread(&data));
+        
+        let z_score = baseline.map_or(0.0, |(mean, std_dev)| {
+            data.iter().map(|&x| (x - mean).abs()).fold(0.0_f64, f64::max) / std_dev
+        });
         
-        let z_score = if std_dev > 1e-10 {
-            max_deviation / std_dev
+        z_to_score(z_score)
+    }
+    
+    /// Counting sensors: the Poisson upper-tail probability of the observed
+    /// counts at the expected rate, as the equivalent normal z. The rate
+    /// comes from the sensor's buffered history, or from the reading itself
+    /// if there isn't enough.
+    fn poisson_score(&self, reading: &SensorReading) -> f64 {
+        let history: Vec<f64> = self.history(reading)
+            .flat_map(|r| r.data.iter().copied())
+            .collect();
+        
+        let (expected, observed) = if history.len() >= MIN_HISTORY {
+            let rate = history.iter().sum::<f64>() / history.len() as f64;
+            (rate * reading.data.len() as f64, reading.data.iter().sum::<f64>())
         } else {
-            0.0
+            let rate = reading.data.iter().sum::<f64>() / reading.data.len() as f64;
+            (rate, reading.data.iter().copied().fold(f64::MIN, f64::max))
         };
         
-        // Sigmoid transform to [0, 1]
-        let score = 1.0 / (1.0 + (-0.5 * (z_score - 2.0)).exp());
+        let z = normal_isf(poisson_upper_tail(observed, expected));
+        z_to_score(z.max(0.0))
+    }
+    
+    /// Random number generators: the worse of the value uniformity check
+    /// and the NIST SP 800-22 battery over the bits of the sensor's recent
+    /// output, which catches bias and correlation a histogram misses
+    fn qrng_score(&self, reading: &SensorReading) -> f64 {
+        let samples: Vec<f64> = self.history(reading)
+            .chain(std::iter::once(reading))
+            .flat_map(|r| r.data.iter().copied())
+            .collect();
+        let battery = nist_battery(&bits_from_unit_samples(&samples, QRNG_BITS_PER_SAMPLE));
+        self.uniformity_score(reading).max(battery.anomaly_score())
+    }
+    
+    /// Random number sources: chi-square test of the samples against a
+    /// uniform distribution on [0, 1]
+    fn uniformity_score(&self, reading: &SensorReading) -> f64 {
+        let n = reading.data.len();
+        let bins = (n / 5).min(10);
+        if bins < 2 {
+            return self.deviation_score(reading);
+        }
         
-        // Adjust based on reading quality
-        score * reading.quality as f64
+        let mut counts = vec![0usize; bins];
+        for &x in &reading.data {
+            counts[((x.clamp(0.0, 1.0) * bins as f64) as usize).min(bins - 1)] += 1;
+        }
+        let expected = n as f64 / bins as f64;
+        let chi_square: f64 = counts.iter()
+            .map(|&c| (c as f64 - expected).powi(2) / expected)
+            .sum();
+        
+        // Wilson-Hilferty: chi-square with k degrees of freedom to a z-score
+        let k = (bins - 1) as f64;
+        let spread = 2.0 / (9.0 * k);
+        let z = ((chi_square / k).cbrt() - (1.0 - spread)) / spread.sqrt();
+        z_to_score(z.max(0.0))
+    }
+    
+    /// Spectra: the bin rising furthest above its usual level, in units of
+    /// the noise floor's spread. Bins are compared with their buffered
+    /// average so known carriers don't count, or with the floor if there
+    /// is no history.
+    fn spectral_peak_score(&self, reading: &SensorReading) -> f64 {
+        let mut sorted = reading.data.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let floor = sorted[sorted.len() / 2];
+        let mut deviations: Vec<f64> = sorted.iter().map(|x| (x - floor).abs()).collect();
+        deviations.sort_by(|a, b| a.total_cmp(b));
+        let spread = (1.4826 * deviations[deviations.len() / 2]).max(1e-10);
+        
+        let history: Vec<&SensorReading> = self.history(reading)
+            .filter(|r| r.data.len() == reading.data.len())
+            .collect();
+        let reference = |bin: usize| {
+            if history.len() < MIN_HISTORY {
+                floor
+            } else {
+                history.iter().map(|r| r.data[bin]).sum::<f64>() / history.len() as f64
+            }
+        };
+        
+        let z = reading.data.iter()
+            .enumerate()
+            .map(|(bin, &x)| (x - reference(bin)) / spread)
+            .fold(0.0_f64, f64::max);
+        z_to_score(z)
+    }
+    
+    /// Beam sensors: intensities are nominally 1 and a beam counts as
+    /// broken below `BEAM_BREAK_THRESHOLD`, where the score crosses 0.5
+    fn beam_break_score(&self, reading: &SensorReading) -> f64 {
+        beam_break_confidence(reading.data.iter().copied().fold(f64::MAX, f64::min))
+    }
+    
+    /// Buffered readings of the same sensor taken before `reading`
+    fn history<'a>(&'a self, reading: &'a SensorReading) -> impl Iterator<Item = &'a SensorReading> + 'a {
+        self.reading_buffer
+            .get(&reading.sensor_id)
+            .into_iter()
+            .flatten()
+            .filter(move |r| r.timestamp < reading.timestamp)
     }
     
     /// Classify detection type from contributing sensors
@@ -322,7 +571,7 @@ impl FusionEngine {
         
         // Find dominant sensor type
         let max_sensor = sensors.iter()
-            .max_by(|a, b| a.anomaly_score.partial_cmp(&b.anomaly_score).unwrap());
+            .max_by(|a, b| a.anomaly_score.total_cmp(&b.anomaly_score));
         
         match max_sensor.map(|s| s.sensor_type) {
             Some(SensorType::ThermalArray) | Some(SensorType::ThermalImager) => {
@@ -355,13 +604,303 @@ impl FusionEngine {
         }
     }
     
-    /// Set sensor weight
+    /// Set the configured weight of a sensor type; feedback learned for it
+    /// still applies on top
     pub fn set_sensor_weight(&mut self, sensor_type: SensorType, weight: f64) {
-        self.sensor_weights.insert(sensor_type, weight.clamp(0.0, 1.0));
+        self.base_weights.insert(sensor_type, weight.clamp(0.0, 1.0));
+        self.update_weight(sensor_type);
+    }
+    
+    /// Go back to the default weight of a sensor type, e.g. when its
+    /// configured weight is removed; feedback learned for it still applies
+    pub fn reset_sensor_weight(&mut self, sensor_type: SensorType) {
+        match DEFAULT_WEIGHTS.iter().find(|(t, _)| *t == sensor_type) {
+            Some(&(_, weight)) => self.base_weights.insert(sensor_type, weight),
+            None => self.base_weights.remove(&sensor_type),
+        };
+        self.update_weight(sensor_type);
+    }
+    
+    /// Weight of a sensor type, 0.5 for one without a weight
+    pub fn sensor_weight(&self, sensor_type: SensorType) -> f64 {
+        self.sensor_weights.get(&sensor_type).copied().unwrap_or(0.5)
     }
     
     /// Get current sensor weights
     pub fn get_sensor_weights(&self) -> &HashMap<SensorType, f64> {
         &self.sensor_weights
     }
+    
+    /// Net verdicts learned from annotation feedback, by sensor type
+    pub fn feedback(&self) -> &HashMap<SensorType, i32> {
+        &self.feedback
+    }
+    
+    /// Replace the learned feedback, e.g. from a checkpoint
+    pub fn set_feedback(&mut self, feedback: HashMap<SensorType, i32>) {
+        let touched: Vec<SensorType> = self.feedback.keys().chain(feedback.keys()).copied().collect();
+        self.feedback = feedback;
+        for sensor_type in touched {
+            self.update_weight(sensor_type);
+        }
+    }
+    
+    /// Learn from an investigator's verdict on a detection the given sensor
+    /// types contributed to: a false positive lowers each type's weight, a
+    /// confirmation raises it, an acknowledgment leaves it alone.
+    ///
+    /// `previous` is the verdict the detection had before, which is undone
+    /// first, so re-saving a review changes nothing and revising one counts
+    /// only the new verdict. Each type is adjusted once however many of its
+    /// sensors took part. Returns whether any weight changed.
+    pub fn apply_feedback(
+        &mut self,
+        sensor_types: &[SensorType],
+        previous: Option<AnnotationStatus>,
+        status: AnnotationStatus,
+    ) -> bool {
+        let vote = |status| match status {
+            AnnotationStatus::Acknowledged => 0,
+            AnnotationStatus::FalsePositive => -1,
+            AnnotationStatus::Confirmed => 1,
+        };
+        let step = vote(status) - previous.map_or(0, vote);
+        if step == 0 {
+            return false;
+        }
+        
+        let mut changed = false;
+        let mut seen = Vec::new();
+        for &sensor_type in sensor_types {
+            if seen.contains(&sensor_type) {
+                continue;
+            }
+            seen.push(sensor_type);
+            
+            let before = self.sensor_weights.get(&sensor_type).copied();
+            *self.feedback.entry(sensor_type).or_default() += step;
+            self.update_weight(sensor_type);
+            changed |= self.sensor_weights.get(&sensor_type).copied() != before;
+        }
+        changed
+    }
+    
+    /// Recompute a type's weight from its base weight and net feedback.
+    ///
+    /// Each false positive takes `FEEDBACK_RATE` of the weight away (down to
+    /// `MIN_FEEDBACK_WEIGHT`) and each confirmation adds `FEEDBACK_RATE` of
+    /// its distance to 1, so only the net count matters, not the order.
+    fn update_weight(&mut self, sensor_type: SensorType) {
+        let net = self.feedback.get(&sensor_type).copied().unwrap_or(0);
+        // Feedback for a type without a weight starts from the neutral 0.5
+        let base = match self.base_weights.get(&sensor_type) {
+            Some(&base) => base,
+            None if net != 0 => 0.5,
+            None => {
+                self.sensor_weights.remove(&sensor_type);
+                return;
+            }
+        };
+        
+        let keep = (1.0 - FEEDBACK_RATE).powi(net.abs());
+        let weight = match net {
+            n if n < 0 => (base * keep).max(MIN_FEEDBACK_WEIGHT.min(base)),
+            n if n > 0 => 1.0 - (1.0 - base) * keep,
+            _ => base,
+        };
+        self.sensor_weights.insert(sensor_type, weight);
+    }
+}
+
+/// Map a z-score onto [0, 1], crossing 0.5 at two standard deviations
+fn z_to_score(z: f64) -> f64 {
+    1.0 / (1.0 + (-0.5 * (z - 2.0)).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    
+    #[test]
+    fn test_high_conflict_pair_is_not_normalized_away() {
+        let engine = FusionEngine::new();
+        
+        // Two confident sensors that flatly disagree
+        let says_anomaly = BeliefMass { anomaly: 0.95, normal: 0.0, uncertainty: 0.05 };
+        let says_normal = BeliefMass { anomaly: 0.0, normal: 0.97, uncertainty: 0.03 };
+        let (combined, k) = engine.combine_belief_masses(&says_anomaly, &says_normal);
+        
+        assert!(k > HIGH_CONFLICT, "conflict {}", k);
+        let total = combined.anomaly + combined.normal + combined.uncertainty;
+        assert!((total - 1.0).abs() < 1e-12);
+        // Dempster's rule would give m(anomaly) ≈ 0.38 from 0.0475 of agreement
+        assert!(combined.anomaly < 0.05);
+        assert!(combined.uncertainty > k);
+        
+        // Agreeing sensors still combine normally
+        let (agreed, k) = engine.combine_belief_masses(&says_anomaly, &says_anomaly);
+        assert_eq!(k, 0.0);
+        assert!(agreed.anomaly > 0.99);
+    }
+    
+    #[test]
+    fn test_dempster_shafer_reports_interval() {
+        let mut engine = FusionEngine::new();
+        let mut spike = vec![0.0; 100];
+        spike[50] = 20.0;
+        
+        let readings = [
+            SensorReading::new("emf-1", SensorType::EMFProbe, spike.clone()),
+            SensorReading::new("geiger-1", SensorType::GeigerCounter, spike.clone()),
+        ];
+        let result = engine.dempster_shafer_fusion(&readings);
+        assert!(result.reliable);
+        assert!(result.belief <= result.confidence && result.confidence <= result.plausibility);
+        assert!((result.plausibility - result.belief - result.uncertainty).abs() < 1e-12);
+        assert!(result.uncertainty > 0.0 && result.uncertainty < 0.1);
+        
+        // Two fully trusted sensors, one spiking and one reporting nothing
+        engine.set_sensor_weight(SensorType::EMFProbe, 1.0);
+        let mut silent = SensorReading::new("emf-2", SensorType::EMFProbe, vec![0.0; 100]);
+        silent.quality = 0.0;
+        let readings = [SensorReading::new("emf-1", SensorType::EMFProbe, spike), silent];
+        let result = engine.dempster_shafer_fusion(&readings);
+        assert!(result.conflict > HIGH_CONFLICT);
+        assert!(!result.reliable);
+        assert!(result.uncertainty > 0.5);
+    }
+    
+    #[test]
+    fn test_sustained_elevation_outweighs_blip() {
+        let mut engine = FusionEngine::new();
+        let t0 = Utc::now();
+        
+        // 20 quiet readings, then either 10 elevated ones or a single spike
+        for (id, elevated) in [("emf-sustained", 20..30), ("emf-blip", 29..30)] {
+            for i in 0..30 {
+                let value = if elevated.contains(&i) { 5.0 } else { 1.0 + 0.1 * (i as f64).sin() };
+                let mut reading = SensorReading::new(id, SensorType::EMFProbe, vec![value; 10]);
+                reading.timestamp = t0 + chrono::Duration::milliseconds(100 * i as i64);
+                engine.add_reading(reading);
+            }
+        }
+        
+        let window = Duration::from_millis(950);
+        let sustained = engine.temporal_fusion("emf-sustained", window);
+        let blip = engine.temporal_fusion("emf-blip", window);
+        
+        assert_eq!(sustained.detection_type, DetectionType::EMFSpike);
+        assert!(sustained.confidence > 0.6, "sustained {}", sustained.confidence);
+        assert!(blip.confidence < 0.3, "blip {}", blip.confidence);
+        assert_eq!(engine.temporal_fusion("missing", window).confidence, 0.0);
+    }
+    
+    #[test]
+    fn test_geiger_counts_are_scored_as_poisson() {
+        let mut engine = FusionEngine::new();
+        let t0 = Utc::now();
+        
+        // Background of about half a count per reading
+        for (i, count) in [0, 1, 0, 0, 1, 2, 0, 1, 0, 0, 1, 0].into_iter().enumerate() {
+            let mut reading = SensorReading::new("geiger-1", SensorType::GeigerCounter, vec![count as f64]);
+            reading.timestamp = t0 + chrono::Duration::seconds(i as i64);
+            engine.add_reading(reading);
+        }
+        
+        let score = |count: f64| {
+            let mut reading = SensorReading::new("geiger-1", SensorType::GeigerCounter, vec![count]);
+            reading.timestamp = t0 + chrono::Duration::seconds(60);
+            engine.calculate_anomaly_score(&reading)
+        };
+        
+        // A single count has no spread of its own, so only the rate can tell
+        assert!(score(1.0) < 0.5, "background scored {}", score(1.0));
+        assert!(score(12.0) > 0.9, "burst scored {}", score(12.0));
+    }
+    
+    #[test]
+    fn test_history_in_another_unit_is_compared_after_conversion() {
+        let mut engine = FusionEngine::new();
+        let t0 = Utc::now();
+        
+        // The probe used to report in mG...
+        for i in 0..10 {
+            let mut reading = SensorReading::new("emf-1", SensorType::EMFProbe, vec![500.0, 504.0, 496.0, 502.0, 498.0]);
+            reading.unit = "mG".to_string();
+            reading.timestamp = t0 + chrono::Duration::seconds(i);
+            engine.add_reading(reading);
+        }
+        
+        // ...and now reports the same field in µT
+        let score = |data: Vec<f64>| {
+            let mut reading = SensorReading::new("emf-1", SensorType::EMFProbe, data);
+            reading.unit = "µT".to_string();
+            reading.timestamp = t0 + chrono::Duration::seconds(60);
+            engine.calculate_anomaly_score(&reading)
+        };
+        
+        let quiet = score(vec![50.0, 50.4, 49.6, 50.2, 49.8]);
+        assert!(quiet < 0.5, "same field scored {}", quiet);
+        let spike = score(vec![50.0, 50.4, 62.0, 50.2, 49.8]);
+        assert!(spike > 0.9, "spike scored {}", spike);
+    }
+    
+    #[test]
+    fn test_laser_grid_scores_broken_beams() {
+        let engine = FusionEngine::new();
+        let score = |beams: Vec<f64>| {
+            engine.calculate_anomaly_score(&SensorReading::new("laser-1", SensorType::LaserGrid, beams))
+        };
+        
+        let intact = vec![0.98; 16];
+        assert!(score(intact.clone()) < 0.1);
+        
+        let mut dimmed = intact.clone();
+        dimmed[3] = 0.6;
+        assert!(score(dimmed) < 0.5);
+        
+        let mut broken = intact;
+        broken[3] = 0.1;
+        assert!(score(broken) > 0.85);
+    }
+    
+    #[test]
+    fn test_feedback_applies_on_top_of_configured_weight() {
+        let mut engine = FusionEngine::new();
+        let emf = [SensorType::EMFProbe];
+        assert!(engine.apply_feedback(&emf, None, AnnotationStatus::Confirmed));
+        assert!(engine.apply_feedback(&emf, None, AnnotationStatus::FalsePositive));
+        assert!(engine.apply_feedback(&emf, None, AnnotationStatus::FalsePositive));
+        assert_eq!(engine.feedback()[&SensorType::EMFProbe], -1);
+        assert!((engine.get_sensor_weights()[&SensorType::EMFProbe] - 0.63).abs() < 1e-12);
+        
+        // A new configured weight keeps the learned feedback
+        engine.set_sensor_weight(SensorType::EMFProbe, 0.5);
+        assert!((engine.get_sensor_weights()[&SensorType::EMFProbe] - 0.45).abs() < 1e-12);
+        engine.set_feedback(HashMap::new());
+        assert_eq!(engine.get_sensor_weights()[&SensorType::EMFProbe], 0.5);
+    }
+    
+    #[test]
+    fn test_revised_verdict_replaces_the_previous_one() {
+        let mut engine = FusionEngine::new();
+        let emf = [SensorType::EMFProbe];
+        let weight = |engine: &FusionEngine| engine.get_sensor_weights()[&SensorType::EMFProbe];
+        
+        assert!(engine.apply_feedback(&emf, None, AnnotationStatus::FalsePositive));
+        let lowered = weight(&engine);
+        // Saving the same verdict again is not another vote
+        assert!(!engine.apply_feedback(&emf, Some(AnnotationStatus::FalsePositive), AnnotationStatus::FalsePositive));
+        assert_eq!(weight(&engine), lowered);
+        
+        // Revised to confirmed: the false positive is undone, not outvoted
+        assert!(engine.apply_feedback(&emf, Some(AnnotationStatus::FalsePositive), AnnotationStatus::Confirmed));
+        assert_eq!(engine.feedback()[&SensorType::EMFProbe], 1);
+        assert!((weight(&engine) - 0.73).abs() < 1e-12);
+        
+        // Back to a plain acknowledgment leaves no vote at all
+        assert!(engine.apply_feedback(&emf, Some(AnnotationStatus::Confirmed), AnnotationStatus::Acknowledged));
+        assert!((weight(&engine) - 0.70).abs() < 1e-12);
+    }
 }
@@ -7,11 +7,14 @@
 mod fusion;
 mod classification;
 mod correlation;
+mod beam;
 
 pub use fusion::*;
 pub use classification::*;
 pub use correlation::*;
+pub use beam::*;
 
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
 use chrono::{DateTime, Utc};
@@ -19,10 +22,45 @@ use serde::{Deserialize, Serialize};
 use anyhow::Result;
 use tracing::{info, warn, debug};
 
-use crate::sensors::{SensorReading, SensorType};
-use crate::analysis::{EntropyResult, Anomaly, AnomalyType};
-use crate::config::Config;
+use crate::sensors::{
+    classify_spectral_peaks, detect_spectral_peaks, PoissonSpikeDetector, SensorReading, SensorType, SpectralPeak,
+    POISSON_SIGNIFICANCE, SPECTRUM_START_HZ, SPECTRUM_STOP_HZ,
+};
+use crate::analysis::{
+    bits_from_unit_samples, detect_thermal_blobs, EntropyResult, Anomaly, AnomalyType, EvpDetector,
+    RandomnessMonitor, RollingBaseline, SchumannState, SchumannTracker, ThermalBlob, ThermalBlobKind, VoiceSegment,
+    SCHUMANN_EXCURSION_DB,
+};
+use crate::config::{Config, DetectionConfig};
 use crate::core::EventBus;
+use crate::db::Database;
+
+/// Settings key the detection checkpoint is persisted under
+const CHECKPOINT_SETTING: &str = "detection.checkpoint";
+
+/// Standard deviations from the frame mean that make a thermal cell a spot
+const THERMAL_SIGMA: f32 = 3.5;
+
+/// Smallest thermal spot reported, so single noisy pixels don't count
+const THERMAL_MIN_AREA: usize = 2;
+
+/// How far, in cells, a spot can move between frames and still be the same
+const THERMAL_TRACK_CELLS: f32 = 2.0;
+
+/// Noise deviations above the floor that make a spectrum bin a peak
+const RF_PEAK_SIGMA: f64 = 6.0;
+
+/// Lowest audio sample rate that resolves the formants an EVP is told by
+const EVP_MIN_SAMPLE_RATE: f64 = 8000.0;
+
+/// Bits taken from each QRNG sample (values in [0, 1]) for randomness tests
+const QRNG_BITS_PER_SAMPLE: u32 = 8;
+
+/// Bits of a QRNG sensor's output each randomness battery runs over
+const QRNG_BLOCK_BITS: usize = 1 << 15;
+
+/// Readings a Geiger counter's rolling background rate is taken over
+const RADIATION_BACKGROUND_READINGS: usize = 120;
 
 /// Detection event
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +69,10 @@ pub struct Detection {
     pub timestamp: DateTime<Utc>,
     pub detection_type: DetectionType,
     pub confidence: f64,
+    /// Evidence committed to neither anomaly nor normal (Dempster-Shafer
+    /// m(Θ)); 0 when the fusion method doesn't model it
+    #[serde(default)]
+    pub uncertainty: f64,
     pub severity: Severity,
     
     // Contributing sensors
@@ -47,11 +89,33 @@ pub struct Detection {
     // Location estimate (if available)
     pub location: Option<[f64; 3]>,
     
+    /// What the detector found in the reading, for the detection types
+    /// that describe more than a value
+    #[serde(default)]
+    pub payload: Option<DetectionPayload>,
+    
     // Raw data reference
     pub data_window_start: DateTime<Utc>,
     pub data_window_end: DateTime<Utc>,
 }
 
+/// Detail of a single-sensor detection, by the detector that raised it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DetectionPayload {
+    /// Broken beam of a laser grid interruption
+    BeamBreak(BeamBreak),
+    /// Segmented spot of a hot or cold spot detection; `location` is the
+    /// thermal sensor's own
+    ThermalBlob(ThermalBlob),
+    /// Strongest unexpected peak of an RF anomaly
+    RfPeak(SpectralPeak),
+    /// Speech-like segment of an EVP detection; the data window spans it
+    EvpSegment(VoiceSegment),
+    /// Schumann mode estimates of an infrasound sensor when one of them
+    /// left its baseline
+    Schumann(SchumannState),
+}
+
 /// Sensor contribution to detection
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SensorContribution {
@@ -63,7 +127,7 @@ pub struct SensorContribution {
 }
 
 /// Detection type
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum DetectionType {
     // Environmental
     ThermalAnomaly,
@@ -122,6 +186,61 @@ pub enum Severity {
     Critical,
 }
 
+impl From<crate::config::Severity> for Severity {
+    fn from(severity: crate::config::Severity) -> Self {
+        match severity {
+            crate::config::Severity::Low => Severity::Low,
+            crate::config::Severity::Medium => Severity::Medium,
+            crate::config::Severity::High => Severity::High,
+            crate::config::Severity::Critical => Severity::Critical,
+        }
+    }
+}
+
+/// Investigator's verdict on a detection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AnnotationStatus {
+    /// Seen, no verdict yet
+    Acknowledged,
+    /// Explained by something mundane
+    FalsePositive,
+    /// Checked and judged genuine
+    Confirmed,
+}
+
+impl AnnotationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnnotationStatus::Acknowledged => "acknowledged",
+            AnnotationStatus::FalsePositive => "false_positive",
+            AnnotationStatus::Confirmed => "confirmed",
+        }
+    }
+}
+
+impl std::str::FromStr for AnnotationStatus {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "acknowledged" => Ok(AnnotationStatus::Acknowledged),
+            "false_positive" => Ok(AnnotationStatus::FalsePositive),
+            "confirmed" => Ok(AnnotationStatus::Confirmed),
+            other => Err(anyhow::anyhow!("Unknown annotation status '{}'", other)),
+        }
+    }
+}
+
+/// Review of one detection, kept in the `detection_annotations` table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectionAnnotation {
+    pub detection_id: String,
+    pub status: AnnotationStatus,
+    pub note: Option<String>,
+    /// When the detection was last annotated
+    pub timestamp: DateTime<Utc>,
+}
+
 /// Classification result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Classification {
@@ -131,12 +250,104 @@ pub struct Classification {
     pub model_version: String,
 }
 
+/// Detection state learned while running, saved by `DetectionEngine::save_checkpoint`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DetectionCheckpoint {
+    /// Net annotation feedback of each sensor type. The configured weights
+    /// it adjusts are not saved, so edits to them still take effect.
+    #[serde(default)]
+    pub feedback: Vec<(SensorType, i32)>,
+    /// Readings each sensor's fusion scores are measured against
+    #[serde(default)]
+    pub fusion_history: HashMap<String, Vec<SensorReading>>,
+    /// Noise floor of each audio sensor's EVP detector
+    pub noise_floors: HashMap<String, RollingBaseline>,
+    /// Spots in each thermal sensor's last frame, so they aren't reported again
+    #[serde(default)]
+    pub thermal_spots: HashMap<String, Vec<ThermalBlob>>,
+    /// Which beams of each laser grid are broken
+    #[serde(default)]
+    pub beams: BeamBreakTracker,
+    /// Held-back detections of beams still broken
+    #[serde(default)]
+    pub open_beams: Vec<Detection>,
+    /// Unexpected peak bins in each RF sensor's last spectrum, so they
+    /// aren't reported again
+    #[serde(default)]
+    pub rf_peaks: HashMap<String, Vec<usize>>,
+    /// Randomness battery of each QRNG sensor, so a source already failing
+    /// isn't reported again
+    #[serde(default)]
+    pub randomness: HashMap<String, RandomnessMonitor>,
+    /// Background rate of each Geiger counter
+    #[serde(default)]
+    pub radiation: HashMap<String, PoissonSpikeDetector>,
+    pub recent_detections: Vec<Detection>,
+    pub detection_count: usize,
+}
+
+/// What the detectors remember between readings: one for live data, and
+/// a fresh one for each reprocessing
+struct DetectorState {
+    correlator: SensorCorrelator,
+    // Sensor weights and the reading history fusion scores against
+    fusion: FusionEngine,
+    beam_tracker: BeamBreakTracker,
+    // Spots in each thermal sensor's previous frame
+    thermal_spots: HashMap<String, Vec<ThermalBlob>>,
+    // EVP detector, with its noise floor, of each audio sensor
+    evp_detectors: HashMap<String, EvpDetector>,
+    // Detection held back for each beam still broken, by sensor and beam
+    open_beams: HashMap<(String, usize), Detection>,
+    // Bins of the unexpected peaks in each RF sensor's previous spectrum
+    rf_peaks: HashMap<String, Vec<usize>>,
+    // Randomness battery over each QRNG sensor's output
+    randomness: HashMap<String, RandomnessMonitor>,
+    // Poisson spike check against each Geiger counter's background
+    radiation: HashMap<String, PoissonSpikeDetector>,
+    // Schumann mode tracker of each infrasound sensor
+    schumann: HashMap<String, SchumannTracker>,
+}
+
+impl DetectorState {
+    fn new(correlator: SensorCorrelator, fusion: FusionEngine) -> Self {
+        Self {
+            correlator,
+            fusion,
+            beam_tracker: BeamBreakTracker::new(),
+            thermal_spots: HashMap::new(),
+            evp_detectors: HashMap::new(),
+            open_beams: HashMap::new(),
+            rf_peaks: HashMap::new(),
+            randomness: HashMap::new(),
+            radiation: HashMap::new(),
+            schumann: HashMap::new(),
+        }
+    }
+}
+
+/// A reprocessing run in progress, see `DetectionEngine::start_reprocessing`
+pub struct Reprocessing {
+    state: DetectorState,
+    detections: Vec<Detection>,
+}
+
+impl Reprocessing {
+    /// Detections found so far, in the order they were raised
+    pub fn finish(self) -> Vec<Detection> {
+        self.detections
+    }
+}
+
 /// Main detection engine
 pub struct DetectionEngine {
     config: Arc<Config>,
-    fusion_engine: FusionEngine,
     classifier: AnomalyClassifier,
-    correlator: parking_lot::Mutex<SensorCorrelator>,
+    detectors: parking_lot::Mutex<DetectorState>,
+    // Frequency span of each RF sensor's spectra, see `set_spectrum_span`
+    spectrum_spans: parking_lot::RwLock<HashMap<String, (f64, f64)>>,
+    // Live copy of the detection settings, replaced by `apply_config`
+    tuning: parking_lot::RwLock<DetectionConfig>,
     event_bus: Arc<EventBus>,
     
     // Detection state
@@ -146,15 +357,24 @@ pub struct DetectionEngine {
 
 impl DetectionEngine {
     pub async fn new(config: Arc<Config>, event_bus: Arc<EventBus>) -> Result<Self> {
-        Ok(Self {
+        let correlator = SensorCorrelator::with_config(
+            config.detection.correlation_window_ms,
+            config.detection.min_correlated_sensors,
+        )
+            .with_spatial(config.detection.cluster_radius_m, config.detection.spatial_weight);
+        
+        let engine = Self {
+            tuning: parking_lot::RwLock::new(config.detection.clone()),
             config,
-            fusion_engine: FusionEngine::new(),
             classifier: AnomalyClassifier::new(),
-            correlator: parking_lot::Mutex::new(SensorCorrelator::new()),
+            detectors: parking_lot::Mutex::new(DetectorState::new(correlator, FusionEngine::new())),
+            spectrum_spans: parking_lot::RwLock::new(HashMap::new()),
             event_bus,
             recent_detections: RwLock::new(Vec::new()),
             detection_count: RwLock::new(0),
-        })
+        };
+        engine.apply_sensor_weights(&engine.config.detection);
+        Ok(engine)
     }
     
     pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) -> Result<()> {
@@ -165,11 +385,14 @@ impl DetectionEngine {
         
         loop {
             tokio::select! {
-                Ok(reading) = reading_rx.recv() => {
-                    if let Some(detection) = self.process_reading(&reading).await {
-                        self.record_detection(detection).await;
+                reading = reading_rx.recv() => match reading {
+                    Ok(reading) => self.process_reading(&reading).await,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("Detection fell behind, skipped {} readings", n);
+                        self.event_bus.record_lagged(n);
                     }
-                }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
                 _ = shutdown.recv() => {
                     info!("Detection engine shutting down...");
                     break;
@@ -180,21 +403,392 @@ impl DetectionEngine {
         Ok(())
     }
     
-    async fn process_reading(&self, reading: &SensorReading) -> Option<Detection> {
-        // Add to correlator for cross-sensor analysis
-        self.correlator.lock().add_reading(reading.clone());
+    /// Run one live reading through the detectors and record what they find
+    pub async fn process_reading(&self, reading: &SensorReading) {
+        let detections = {
+            let mut state = self.detectors.lock();
+            self.detect_reading(&mut state, reading)
+        };
+        for detection in detections {
+            if let (Some(DetectionPayload::BeamBreak(BeamBreak { beam, dwell_ms: Some(dwell_ms) })), Some(sensor)) =
+                (&detection.payload, detection.sensors.first())
+            {
+                self.event_bus.publish_alert(
+                    "info",
+                    &format!("{} beam {} interrupted for {} ms", sensor.sensor_id, beam, dwell_ms),
+                );
+            }
+            self.record_detection(detection).await;
+        }
+    }
+    
+    /// Every detector's verdict on `reading`, the same for live and recorded
+    /// data: windows and expiry follow the reading's timestamp, not the clock
+    fn detect_reading(&self, state: &mut DetectorState, reading: &SensorReading) -> Vec<Detection> {
+        let mut detections = self.track_beams(state, reading);
+        detections.extend(self.detect_thermal_spots(state, reading));
+        detections.extend(self.detect_rf_peaks(state, reading));
+        detections.extend(self.detect_evp(state, reading));
+        detections.extend(self.detect_qrng_bias(state, reading));
+        detections.extend(self.detect_radiation_spike(state, reading));
+        detections.extend(self.detect_schumann_excursion(state, reading));
+        
+        // Add to correlator for cross-sensor analysis, and to the history
+        // fusion scores each sensor against
+        state.correlator.add_reading_at(reading.clone(), reading.timestamp);
+        state.fusion.add_reading(reading.clone());
         
         // Check for correlated events
-        if let Some(correlated) = self.correlator.lock().check_correlation() {
-            let detection = self.create_detection(
-                DetectionType::CorrelatedAnomaly,
-                correlated.confidence,
-                correlated.sensors,
-            );
-            return Some(detection);
+        if let Some(correlated) = state.correlator.check_correlation_at(reading.timestamp) {
+            let window_ms = state.correlator.correlation_window_ms() as i64;
+            let (fusion_enabled, fusion_method) = {
+                let tuning = self.tuning.read();
+                (tuning.fusion_enabled, tuning.fusion_method)
+            };
+            let mut detection = if fusion_enabled {
+                // Score each sensor's latest reading in the terms of its type
+                let sensor_ids: Vec<&str> = correlated.sensors.iter().map(|s| s.sensor_id.as_str()).collect();
+                let fused = state.fusion.fuse_latest(&sensor_ids, fusion_method);
+                let mut detection = self.detection_from_fusion(fused);
+                detection.detection_type = DetectionType::CorrelatedAnomaly;
+                detection
+            } else {
+                self.create_detection(DetectionType::CorrelatedAnomaly, correlated.confidence, correlated.sensors)
+            };
+            detection.correlation_score = correlated.confidence;
+            detection.location = correlated.centroid;
+            detection.timestamp = reading.timestamp;
+            detection.data_window_start = reading.timestamp - chrono::Duration::milliseconds(window_ms);
+            detection.data_window_end = reading.timestamp;
+            detections.push(detection);
+        }
+        
+        detections
+    }
+    
+    /// A `LaserInterruption` detection for each beam that was just restored.
+    ///
+    /// The detection is raised when the beam breaks but held back until it
+    /// is restored, so it is published once, with its dwell filled in. It
+    /// is stamped with the time the beam broke.
+    fn track_beams(&self, state: &mut DetectorState, reading: &SensorReading) -> Vec<Detection> {
+        let events = state.beam_tracker.update(reading);
+        let weight = state.fusion.sensor_weight(SensorType::LaserGrid);
+        
+        let mut detections = Vec::new();
+        for event in events {
+            match event {
+                BeamEvent::Interrupted { sensor_id, beam, at, intensity } => {
+                    let confidence = beam_break_confidence(intensity);
+                    let mut detection = self.create_detection(
+                        DetectionType::LaserInterruption,
+                        confidence,
+                        vec![SensorContribution {
+                            sensor_id: sensor_id.clone(),
+                            sensor_type: SensorType::LaserGrid,
+                            weight,
+                            reading_value: intensity,
+                            anomaly_score: confidence,
+                        }],
+                    );
+                    detection.timestamp = at;
+                    detection.data_window_start = at;
+                    detection.data_window_end = at;
+                    detection.payload = Some(DetectionPayload::BeamBreak(BeamBreak { beam, dwell_ms: None }));
+                    state.open_beams.insert((sensor_id, beam), detection);
+                }
+                BeamEvent::Restored { sensor_id, beam, at, dwell } => {
+                    if let Some(mut detection) = state.open_beams.remove(&(sensor_id, beam)) {
+                        detection.payload = Some(DetectionPayload::BeamBreak(BeamBreak {
+                            beam,
+                            dwell_ms: Some(dwell.num_milliseconds()),
+                        }));
+                        detection.data_window_end = at;
+                        detections.push(detection);
+                    }
+                }
+            }
+        }
+        detections
+    }
+    
+    /// Start re-running recorded readings through the detectors with the
+    /// current settings.
+    ///
+    /// The run has detector state of its own, so live state is left alone,
+    /// and nothing is published. Feed it readings oldest first with
+    /// `reprocess_reading`.
+    pub fn start_reprocessing(&self) -> Reprocessing {
+        let tuning = self.tuning.read().clone();
+        let (correlator, mut fusion) = {
+            let live = self.detectors.lock();
+            let correlator = SensorCorrelator::with_config(
+                live.correlator.correlation_window_ms(),
+                live.correlator.min_correlated_sensors(),
+            )
+                .with_spatial(tuning.cluster_radius_m, tuning.spatial_weight);
+            (correlator, live.fusion.clone())
+        };
+        // Same weights as live, but scores only against the recording
+        fusion.set_reading_history(HashMap::new());
+        Reprocessing {
+            state: DetectorState::new(correlator, fusion),
+            detections: Vec::new(),
+        }
+    }
+    
+    /// Run one recorded reading through `run`, keeping the detections that
+    /// would have been recorded live
+    pub fn reprocess_reading(&self, run: &mut Reprocessing, reading: &SensorReading) {
+        for detection in self.detect_reading(&mut run.state, reading) {
+            if detection.confidence >= self.min_confidence_for(detection.detection_type) {
+                run.detections.push(detection);
+            }
+        }
+    }
+    
+    /// Run recorded readings, oldest first, through fresh detectors with the
+    /// current settings; see `start_reprocessing`
+    pub fn reprocess<'a>(&self, readings: impl IntoIterator<Item = &'a SensorReading>) -> Vec<Detection> {
+        let mut run = self.start_reprocessing();
+        for reading in readings {
+            self.reprocess_reading(&mut run, reading);
         }
+        run.finish()
+    }
+    
+    /// A `HotSpot` or `ColdSpot` detection for each spot that wasn't in the
+    /// sensor's previous frame, so a lingering spot is reported once
+    fn detect_thermal_spots(&self, state: &mut DetectorState, reading: &SensorReading) -> Vec<Detection> {
+        if !matches!(reading.sensor_type, SensorType::ThermalArray | SensorType::ThermalImager) {
+            return Vec::new();
+        }
+        let Some((width, height)) = grid_shape(reading) else {
+            return Vec::new();
+        };
         
-        None
+        let frame: Vec<f32> = reading.data.iter().map(|&t| t as f32).collect();
+        let spots: Vec<ThermalBlob> = detect_thermal_blobs(&frame, width, height, THERMAL_SIGMA)
+            .into_iter()
+            .filter(|b| b.area >= THERMAL_MIN_AREA)
+            .collect();
+        let previous = state.thermal_spots
+            .insert(reading.sensor_id.clone(), spots.clone())
+            .unwrap_or_default();
+        
+        spots.into_iter()
+            .filter(|spot| !previous.iter().any(|p| {
+                p.kind == spot.kind
+                    && (p.centroid[0] - spot.centroid[0]).hypot(p.centroid[1] - spot.centroid[1]) <= THERMAL_TRACK_CELLS
+            }))
+            .map(|spot| {
+                let detection_type = match spot.kind {
+                    ThermalBlobKind::Hot => DetectionType::HotSpot,
+                    ThermalBlobKind::Cold => DetectionType::ColdSpot,
+                };
+                let confidence = 1.0 - 0.5 * (-(spot.sigma - THERMAL_SIGMA) as f64).exp();
+                self.sensor_detection(
+                    state,
+                    reading,
+                    detection_type,
+                    confidence,
+                    spot.peak as f64,
+                    Some(DetectionPayload::ThermalBlob(spot)),
+                )
+            })
+            .collect()
+    }
+    
+    /// An `RFAnomaly` detection when a spectrum has peaks outside the known
+    /// bands, for the strongest of them
+    fn detect_rf_peaks(&self, state: &mut DetectorState, reading: &SensorReading) -> Option<Detection> {
+        if !matches!(reading.sensor_type, SensorType::SDRReceiver | SensorType::SpectrumAnalyzer) {
+            return None;
+        }
+        
+        let (start_hz, stop_hz) = self.spectrum_span(&reading.sensor_id);
+        let mut peaks = detect_spectral_peaks(&reading.data, RF_PEAK_SIGMA);
+        classify_spectral_peaks(
+            &mut peaks,
+            reading.data.len(),
+            start_hz,
+            stop_hz,
+            &self.tuning.read().known_rf_bands,
+        );
+        peaks.retain(|p| !p.is_known());
+        
+        // A carrier that was already there last frame has been reported;
+        // let it wander a bin without counting as new
+        let previous = state.rf_peaks
+            .insert(reading.sensor_id.clone(), peaks.iter().map(|p| p.bin).collect())
+            .unwrap_or_default();
+        // Peaks come strongest first
+        let peak = peaks.into_iter().find(|p| !previous.iter().any(|&bin| bin.abs_diff(p.bin) <= 1))?;
+        
+        // 0.5 right at the threshold, approaching 1 for strong carriers
+        let excess = (peak.snr_db / RF_PEAK_SIGMA).max(1.0);
+        let confidence = 1.0 - 0.5 / excess;
+        
+        Some(self.sensor_detection(
+            state,
+            reading,
+            DetectionType::RFAnomaly,
+            confidence,
+            peak.power,
+            Some(DetectionPayload::RfPeak(peak)),
+        ))
+    }
+    
+    /// An `EVP` detection for each speech-like segment of an audio reading
+    /// that stands out from the sensor's noise floor
+    fn detect_evp(&self, state: &mut DetectorState, reading: &SensorReading) -> Vec<Detection> {
+        if !matches!(reading.sensor_type, SensorType::FullSpectrum | SensorType::ParabolicMic | SensorType::ContactMic)
+            || reading.sample_rate < EVP_MIN_SAMPLE_RATE
+        {
+            return Vec::new();
+        }
+        
+        let segments = state.evp_detectors
+            .entry(reading.sensor_id.clone())
+            .or_default()
+            .detect(&reading.data, reading.sample_rate);
+        if segments.is_empty() {
+            return Vec::new();
+        }
+        
+        let offset = |secs: f64| reading.timestamp + chrono::Duration::microseconds((secs * 1e6) as i64);
+        
+        segments.into_iter()
+            .map(|segment| {
+                let mut detection = self.sensor_detection(
+                    state,
+                    reading,
+                    DetectionType::EVP,
+                    segment.confidence,
+                    segment.snr_db,
+                    Some(DetectionPayload::EvpSegment(segment)),
+                );
+                detection.data_window_start = offset(segment.start_secs);
+                detection.data_window_end = offset(segment.end_secs);
+                detection
+            })
+            .collect()
+    }
+    
+    /// A `QRNGDeviation` detection when a random source's output starts
+    /// failing the NIST SP 800-22 battery
+    fn detect_qrng_bias(&self, state: &mut DetectorState, reading: &SensorReading) -> Option<Detection> {
+        if reading.sensor_type != SensorType::QRNG {
+            return None;
+        }
+        
+        let report = state.randomness
+            .entry(reading.sensor_id.clone())
+            .or_insert_with(|| RandomnessMonitor::new(QRNG_BLOCK_BITS))
+            .push(&bits_from_unit_samples(&reading.data, QRNG_BITS_PER_SAMPLE))?;
+        
+        let confidence = report.anomaly_score();
+        let failures = report.failures().count() as f64;
+        // The failing block ends with this reading
+        let block_secs = if reading.sample_rate > 0.0 {
+            QRNG_BLOCK_BITS as f64 / QRNG_BITS_PER_SAMPLE as f64 / reading.sample_rate
+        } else {
+            0.0
+        };
+        
+        let mut detection = self.sensor_detection(state, reading, DetectionType::QRNGDeviation, confidence, failures, None);
+        detection.data_window_start = reading.timestamp - chrono::Duration::microseconds((block_secs * 1e6) as i64);
+        Some(detection)
+    }
+    
+    /// A `RadiationSpike` detection when a Geiger count is too high to be
+    /// shot noise at the background rate, once per burst
+    fn detect_radiation_spike(&self, state: &mut DetectorState, reading: &SensorReading) -> Option<Detection> {
+        if reading.sensor_type != SensorType::GeigerCounter || reading.data.is_empty() {
+            return None;
+        }
+        
+        let count = reading.data.iter().sum::<f64>();
+        let check = state.radiation
+            .entry(reading.sensor_id.clone())
+            .or_insert_with(|| PoissonSpikeDetector::new(RADIATION_BACKGROUND_READINGS))
+            .check(count)
+            .filter(|c| c.onset)?;
+        
+        // 0.5 right at the significance level, approaching 1 as the tail
+        // probability shrinks
+        let evidence = -check.p_value.max(1e-300).log10();
+        let confidence = evidence / (evidence - POISSON_SIGNIFICANCE.log10());
+        
+        Some(self.sensor_detection(state, reading, DetectionType::RadiationSpike, confidence, count, None))
+    }
+    
+    /// An `InfrasoundEvent` detection when a Schumann mode of an infrasound
+    /// stream moves off its baseline, once per excursion
+    fn detect_schumann_excursion(&self, state: &mut DetectorState, reading: &SensorReading) -> Option<Detection> {
+        if reading.sensor_type != SensorType::Infrasound || reading.sample_rate <= 0.0 {
+            return None;
+        }
+        
+        let tracker = state.schumann
+            .entry(reading.sensor_id.clone())
+            .or_insert_with(|| SchumannTracker::new(reading.sample_rate));
+        if tracker.sample_rate() != reading.sample_rate {
+            *tracker = SchumannTracker::new(reading.sample_rate);
+        }
+        let before = tracker.state().map(|s| s.excursions.clone()).unwrap_or_default();
+        let modes = tracker.push(&reading.data)?.clone();
+        
+        // The mode furthest off its baseline among those that just left it
+        let (amplitude, excursion_db) = (0..modes.excursions.len())
+            .filter(|&m| modes.excursions[m] && !before.get(m).copied().unwrap_or(false))
+            .map(|m| {
+                let (amplitude, baseline) = (modes.mode_amplitudes[m], modes.baseline_amplitudes[m]);
+                (amplitude, 20.0 * (amplitude.max(1e-300) / baseline.max(1e-300)).log10().abs())
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        // 0.5 right at the excursion threshold, approaching 1 beyond it
+        let confidence = 1.0 - 0.5 * SCHUMANN_EXCURSION_DB / excursion_db.max(SCHUMANN_EXCURSION_DB);
+        
+        Some(self.sensor_detection(
+            state,
+            reading,
+            DetectionType::InfrasoundEvent,
+            confidence,
+            amplitude,
+            Some(DetectionPayload::Schumann(modes)),
+        ))
+    }
+    
+    /// Detection of one sensor's `reading`, weighted by the sensor's type,
+    /// stamped with the reading's time and placed at the sensor.
+    /// `value` is the reading value the detector judged.
+    fn sensor_detection(
+        &self,
+        state: &DetectorState,
+        reading: &SensorReading,
+        detection_type: DetectionType,
+        confidence: f64,
+        value: f64,
+        payload: Option<DetectionPayload>,
+    ) -> Detection {
+        let mut detection = self.create_detection(
+            detection_type,
+            confidence,
+            vec![SensorContribution {
+                sensor_id: reading.sensor_id.clone(),
+                sensor_type: reading.sensor_type,
+                weight: state.fusion.sensor_weight(reading.sensor_type),
+                reading_value: value,
+                anomaly_score: confidence,
+            }],
+        );
+        detection.timestamp = reading.timestamp;
+        detection.data_window_start = reading.timestamp;
+        detection.data_window_end = reading.timestamp;
+        detection.location = reading.position;
+        detection.payload = payload;
+        detection
     }
     
     fn create_detection(
@@ -215,6 +809,7 @@ impl DetectionEngine {
             timestamp: Utc::now(),
             detection_type,
             confidence,
+            uncertainty: 0.0,
             severity,
             sensors,
             entropy_deviation: 0.0,
@@ -222,12 +817,27 @@ impl DetectionEngine {
             correlation_score: 0.0,
             classification: None,
             location: None,
+            payload: None,
             data_window_start: Utc::now(),
             data_window_end: Utc::now(),
         }
     }
     
+    /// Detection for a fusion result, keeping its uncertainty
+    pub fn detection_from_fusion(&self, fusion: FusionResult) -> Detection {
+        let mut detection = self.create_detection(fusion.detection_type, fusion.confidence, fusion.sensors);
+        detection.uncertainty = fusion.uncertainty;
+        detection
+    }
+    
     async fn record_detection(&self, detection: Detection) {
+        let min_confidence = self.min_confidence_for(detection.detection_type);
+        if detection.confidence < min_confidence {
+            debug!("Dropping {:?} detection: confidence {:.2} below {:.2}",
+                detection.detection_type, detection.confidence, min_confidence);
+            return;
+        }
+        
         // Increment count
         {
             let mut count = self.detection_count.write().await;
@@ -245,10 +855,121 @@ impl DetectionEngine {
             }
         }
         
+        if detection.severity >= Severity::from(self.alert_threshold()) {
+            let sensors: Vec<&str> = detection.sensors.iter().map(|s| s.sensor_id.as_str()).collect();
+            self.event_bus.publish_alert(
+                &format!("{:?}", detection.severity).to_lowercase(),
+                &format!("{:?} on {} (confidence {:.2})",
+                    detection.detection_type, sensors.join(", "), detection.confidence),
+            );
+        }
+        
         // Publish event
         self.event_bus.publish_detection(detection);
     }
     
+    /// Apply new detection settings without restarting the engine
+    pub fn apply_config(&self, detection: &DetectionConfig) {
+        {
+            let mut detectors = self.detectors.lock();
+            let correlator = &mut detectors.correlator;
+            correlator.set_correlation_window_ms(detection.correlation_window_ms);
+            correlator.set_min_correlated_sensors(detection.min_correlated_sensors);
+            correlator.set_spatial(detection.cluster_radius_m, detection.spatial_weight);
+        }
+        self.apply_sensor_weights(detection);
+        *self.tuning.write() = detection.clone();
+        
+        info!("Detection settings updated: min confidence {:.2}, {:?} fusion, alerts from {:?}",
+            detection.min_confidence, detection.fusion_method, detection.alert_threshold);
+    }
+    
+    /// Set the configured weights and reset those dropped from the config
+    fn apply_sensor_weights(&self, detection: &DetectionConfig) {
+        let mut detectors = self.detectors.lock();
+        let fusion = &mut detectors.fusion;
+        let removed: Vec<SensorType> = self.tuning.read().sensor_weights.keys()
+            .filter(|name| !detection.sensor_weights.contains_key(*name))
+            .filter_map(|name| name.parse().ok())
+            .collect();
+        for sensor_type in removed {
+            fusion.reset_sensor_weight(sensor_type);
+        }
+        for (name, &weight) in &detection.sensor_weights {
+            match name.parse::<SensorType>() {
+                Ok(sensor_type) => fusion.set_sensor_weight(sensor_type, weight),
+                Err(_) => warn!("Ignoring weight for unknown sensor type '{}'", name),
+            }
+        }
+    }
+    
+    /// Feed an investigator's verdict back into the fusion weights of the
+    /// detection's sensor types, when `annotation_feedback` is on, replacing
+    /// the `previous` verdict it had. Returns whether any weight changed.
+    pub fn apply_annotation(
+        &self,
+        detection: &Detection,
+        previous: Option<AnnotationStatus>,
+        status: AnnotationStatus,
+    ) -> bool {
+        if !self.tuning.read().annotation_feedback {
+            return false;
+        }
+        let sensor_types: Vec<SensorType> = detection.sensors.iter().map(|s| s.sensor_type).collect();
+        self.detectors.lock().fusion.apply_feedback(&sensor_types, previous, status)
+    }
+    
+    /// Fusion weight of a sensor type, 0.5 for one without a weight
+    pub fn sensor_weight(&self, sensor_type: SensorType) -> f64 {
+        self.detectors.lock().fusion.sensor_weight(sensor_type)
+    }
+    
+    /// Detections below this confidence are discarded
+    pub fn min_confidence(&self) -> f64 {
+        self.tuning.read().min_confidence
+    }
+    
+    /// Minimum confidence for one detection type, falling back to `min_confidence`
+    pub fn min_confidence_for(&self, detection_type: DetectionType) -> f64 {
+        let tuning = self.tuning.read();
+        tuning.type_min_confidence.get(&detection_type)
+            .copied()
+            .unwrap_or(tuning.min_confidence)
+    }
+    
+    /// Set the frequency span of `sensor_id`'s spectra, e.g. from
+    /// `spectrum_span` of its settings
+    pub fn set_spectrum_span(&self, sensor_id: &str, start_hz: f64, stop_hz: f64) {
+        self.spectrum_spans.write().insert(sensor_id.to_string(), (start_hz, stop_hz));
+    }
+    
+    /// Frequency span of `sensor_id`'s spectra; a sensor without one set is
+    /// taken to sweep the survey span
+    pub fn spectrum_span(&self, sensor_id: &str) -> (f64, f64) {
+        self.spectrum_spans.read()
+            .get(sensor_id)
+            .copied()
+            .unwrap_or((SPECTRUM_START_HZ, SPECTRUM_STOP_HZ))
+    }
+    
+    /// How far apart readings can be and still correlate
+    pub fn correlation_window_ms(&self) -> u64 {
+        self.detectors.lock().correlator.correlation_window_ms()
+    }
+    
+    /// Lowest severity that raises an alert
+    pub fn alert_threshold(&self) -> crate::config::Severity {
+        self.tuning.read().alert_threshold
+    }
+    
+    /// Retune the correlator without restarting the engine
+    pub fn set_correlation(&self, correlation_window_ms: u64, min_correlated_sensors: usize) {
+        let mut detectors = self.detectors.lock();
+        let correlator = &mut detectors.correlator;
+        correlator.set_correlation_window_ms(correlation_window_ms);
+        correlator.set_min_correlated_sensors(min_correlated_sensors);
+    }
+    
     pub async fn get_detection_count(&self) -> usize {
         *self.detection_count.read().await
     }
@@ -257,4 +978,380 @@ impl DetectionEngine {
         let recent = self.recent_detections.read().await;
         recent.iter().rev().take(limit).cloned().collect()
     }
+    
+    /// Snapshot of the state a restart would otherwise lose
+    pub async fn checkpoint(&self) -> DetectionCheckpoint {
+        let (feedback, fusion_history, noise_floors, thermal_spots, beams, open_beams, rf_peaks, randomness, radiation) = {
+            let state = self.detectors.lock();
+            (
+                state.fusion.feedback().iter().map(|(&t, &net)| (t, net)).collect(),
+                state.fusion.reading_history().clone(),
+                state.evp_detectors
+                    .iter()
+                    .map(|(id, detector)| (id.clone(), *detector.noise_floor()))
+                    .collect(),
+                state.thermal_spots.clone(),
+                state.beam_tracker.clone(),
+                state.open_beams.values().cloned().collect(),
+                state.rf_peaks.clone(),
+                state.randomness.clone(),
+                state.radiation.clone(),
+            )
+        };
+        
+        DetectionCheckpoint {
+            feedback,
+            fusion_history,
+            noise_floors,
+            thermal_spots,
+            beams,
+            open_beams,
+            rf_peaks,
+            randomness,
+            radiation,
+            recent_detections: self.recent_detections.read().await.clone(),
+            detection_count: *self.detection_count.read().await,
+        }
+    }
+    
+    /// Resume from `checkpoint`, replacing the current state
+    pub async fn restore(&self, checkpoint: DetectionCheckpoint) {
+        {
+            let mut state = self.detectors.lock();
+            state.fusion.set_feedback(checkpoint.feedback.into_iter().collect());
+            state.fusion.set_reading_history(checkpoint.fusion_history);
+            state.evp_detectors = checkpoint.noise_floors
+                .into_iter()
+                .map(|(id, floor)| (id, EvpDetector::with_noise_floor(floor)))
+                .collect();
+            state.thermal_spots = checkpoint.thermal_spots;
+            state.beam_tracker = checkpoint.beams;
+            state.open_beams = checkpoint.open_beams
+                .into_iter()
+                .filter_map(|d| {
+                    let Some(DetectionPayload::BeamBreak(BeamBreak { beam, .. })) = d.payload else {
+                        return None;
+                    };
+                    let key = (d.sensors.first()?.sensor_id.clone(), beam);
+                    Some((key, d))
+                })
+                .collect();
+            state.rf_peaks = checkpoint.rf_peaks;
+            state.randomness = checkpoint.randomness;
+            state.radiation = checkpoint.radiation;
+        }
+        *self.recent_detections.write().await = checkpoint.recent_detections;
+        *self.detection_count.write().await = checkpoint.detection_count;
+    }
+    
+    /// Persist `checkpoint()` to the settings table
+    pub async fn save_checkpoint(&self, db: &Database) -> Result<()> {
+        let json = serde_json::to_string(&self.checkpoint().await)?;
+        db.set_setting(CHECKPOINT_SETTING, &json)
+    }
+    
+    /// Restore a checkpoint saved by `save_checkpoint`; returns whether
+    /// there was one
+    pub async fn load_checkpoint(&self, db: &Database) -> Result<bool> {
+        let Some(json) = db.get_setting(CHECKPOINT_SETTING)? else {
+            return Ok(false);
+        };
+        self.restore(serde_json::from_str(&json)?).await;
+        Ok(true)
+    }
+}
+
+/// Width and height of a grid reading: its `dimensions` as rows and columns
+/// when they match the data, otherwise the side of a square grid
+fn grid_shape(reading: &SensorReading) -> Option<(usize, usize)> {
+    if let [rows, cols] = reading.dimensions[..] {
+        if rows * cols == reading.data.len() {
+            return Some((cols, rows));
+        }
+    }
+    let side = (reading.data.len() as f64).sqrt().round() as usize;
+    (side > 0 && side * side == reading.data.len()).then_some((side, side))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_type_specific_min_confidence() {
+        let mut config = Config::default();
+        config.detection.min_confidence = 0.5;
+        config.detection.type_min_confidence.insert(DetectionType::EMFFluctuation, 0.8);
+        config.detection.type_min_confidence.insert(DetectionType::RadiationSpike, 0.4);
+        
+        let toml = toml::to_string(&config).unwrap();
+        let parsed: Config = toml::from_str(&toml).unwrap();
+        assert_eq!(parsed.detection.type_min_confidence, config.detection.type_min_confidence);
+        
+        let engine = DetectionEngine::new(Arc::new(config), Arc::new(EventBus::new(16))).await.unwrap();
+        assert_eq!(engine.min_confidence_for(DetectionType::ColdSpot), 0.5);
+        
+        for detection_type in [DetectionType::EMFFluctuation, DetectionType::RadiationSpike] {
+            let detection = engine.create_detection(detection_type, 0.6, vec![]);
+            engine.record_detection(detection).await;
+        }
+        
+        let recorded = engine.get_recent_detections(10).await;
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].detection_type, DetectionType::RadiationSpike);
+        assert_eq!(engine.get_detection_count().await, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_detections_at_alert_threshold_raise_alerts() {
+        let mut config = Config::default();
+        config.detection.alert_threshold = crate::config::Severity::High;
+        let event_bus = Arc::new(EventBus::new(16));
+        let engine = DetectionEngine::new(Arc::new(config), event_bus.clone()).await.unwrap();
+        let mut events = event_bus.subscribe_events();
+        
+        for confidence in [0.6, 0.95] {
+            let detection = engine.create_detection(DetectionType::EMFFluctuation, confidence, vec![]);
+            engine.record_detection(detection).await;
+        }
+        
+        let alerts: Vec<String> = std::iter::from_fn(|| events.try_recv().ok())
+            .filter_map(|event| match event.payload {
+                crate::core::EventPayload::Alert { level, .. } => Some(level),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(alerts, vec!["critical".to_string()]);
+    }
+    
+    #[tokio::test]
+    async fn test_rf_peak_uses_sensor_span_and_is_reported_once() {
+        let engine = DetectionEngine::new(Arc::new(Config::default()), Arc::new(EventBus::new(64))).await.unwrap();
+        let spectrum = |sensor_id: &str, carrier: bool| {
+            let mut data: Vec<f64> = (0..256).map(|i| -90.0 + ((i * 7919) % 13) as f64 * 0.3).collect();
+            if carrier {
+                data[60] = -30.0;
+            }
+            SensorReading::new(sensor_id, SensorType::SDRReceiver, data)
+        };
+        async fn rf_detections(engine: &DetectionEngine) -> usize {
+            engine.get_recent_detections(100).await
+                .into_iter()
+                .filter(|d| d.detection_type == DetectionType::RFAnomaly)
+                .count()
+        }
+        
+        // Over the survey span the carrier is at 1.4 GHz, outside any known band
+        engine.process_reading(&spectrum("survey", true)).await;
+        assert_eq!(rf_detections(&engine).await, 1);
+        let Some(DetectionPayload::RfPeak(peak)) = engine.get_recent_detections(1).await[0].payload.clone() else {
+            panic!("RF anomaly without its peak");
+        };
+        assert!((1.40e9..1.43e9).contains(&peak.frequency_hz.unwrap()));
+        
+        // Still there next frame: the same carrier, not a new one
+        engine.process_reading(&spectrum("survey", true)).await;
+        assert_eq!(rf_detections(&engine).await, 1);
+        
+        // Gone and back again
+        engine.process_reading(&spectrum("survey", false)).await;
+        engine.process_reading(&spectrum("survey", true)).await;
+        assert_eq!(rf_detections(&engine).await, 2);
+        
+        // An SDR tuned to 100 MHz sees the same bin inside FM broadcast
+        engine.set_spectrum_span("tuned", 99e6, 101e6);
+        engine.process_reading(&spectrum("tuned", true)).await;
+        assert_eq!(rf_detections(&engine).await, 2);
+        
+        let checkpoint = engine.checkpoint().await;
+        assert_eq!(checkpoint.rf_peaks["survey"], vec![60]);
+    }
+    
+    #[tokio::test]
+    async fn test_correlated_sensors_are_scored_by_type() {
+        let engine = DetectionEngine::new(Arc::new(Config::default()), Arc::new(EventBus::new(16))).await.unwrap();
+        let at = Utc::now();
+        let reading = |sensor_id: &str, sensor_type, data| {
+            let mut reading = SensorReading::new(sensor_id, sensor_type, data);
+            reading.timestamp = at;
+            reading
+        };
+        let mut emf = vec![50.0; 15];
+        emf.push(80.0);
+        // One count at a tenth of a count per sample: a spike by z-score,
+        // unremarkable for a Poisson source
+        let mut geiger = vec![0.0; 9];
+        geiger.push(1.0);
+        
+        let detections = {
+            let mut state = engine.detectors.lock();
+            engine.detect_reading(&mut state, &reading("emf", SensorType::EMFProbe, emf));
+            engine.detect_reading(&mut state, &reading("geiger", SensorType::GeigerCounter, geiger))
+        };
+        let correlated = detections.iter()
+            .find(|d| d.detection_type == DetectionType::CorrelatedAnomaly)
+            .expect("coincident readings correlate");
+        
+        assert_eq!(correlated.sensors.len(), 2);
+        let geiger = correlated.sensors.iter().find(|s| s.sensor_id == "geiger").unwrap();
+        assert!(geiger.anomaly_score < 0.5, "geiger scored {}", geiger.anomaly_score);
+        // Dempster-Shafer by default, so the evidence left uncommitted shows
+        assert!(correlated.uncertainty > 0.0);
+    }
+    
+    #[tokio::test]
+    async fn test_biased_qrng_is_reported_once() {
+        use rand::{Rng, SeedableRng};
+        
+        let engine = DetectionEngine::new(Arc::new(Config::default()), Arc::new(EventBus::new(64))).await.unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(15);
+        let readings_per_block = QRNG_BLOCK_BITS / (100 * QRNG_BITS_PER_SAMPLE as usize) + 1;
+        
+        // Never above one half: every sample's top bit is zero
+        for _ in 0..3 * readings_per_block {
+            let data = (0..100).map(|_| rng.gen_range(0.0..0.5)).collect();
+            engine.process_reading(&SensorReading::new("qrng", SensorType::QRNG, data)).await;
+        }
+        
+        let detections: Vec<Detection> = engine.get_recent_detections(100).await
+            .into_iter()
+            .filter(|d| d.detection_type == DetectionType::QRNGDeviation)
+            .collect();
+        assert_eq!(detections.len(), 1);
+        assert!(detections[0].confidence > 0.9);
+        assert!(engine.checkpoint().await.randomness["qrng"].is_failing());
+    }
+    
+    #[tokio::test]
+    async fn test_geiger_burst_is_one_radiation_spike() {
+        let engine = DetectionEngine::new(Arc::new(Config::default()), Arc::new(EventBus::new(64))).await.unwrap();
+        let geiger = |count: f64| SensorReading::new("geiger", SensorType::GeigerCounter, vec![count]);
+        async fn spikes(engine: &DetectionEngine) -> Vec<Detection> {
+            engine.get_recent_detections(100).await
+                .into_iter()
+                .filter(|d| d.detection_type == DetectionType::RadiationSpike)
+                .collect()
+        }
+        
+        // About 20 CPM, read each second
+        for i in 0..60 {
+            engine.process_reading(&geiger(if i % 3 == 0 { 1.0 } else { 0.0 })).await;
+        }
+        assert!(spikes(&engine).await.is_empty());
+        
+        // A two-second burst is one spike
+        engine.process_reading(&geiger(12.0)).await;
+        engine.process_reading(&geiger(15.0)).await;
+        let found = spikes(&engine).await;
+        assert_eq!(found.len(), 1);
+        assert!(found[0].confidence > 0.5);
+        assert_eq!(found[0].sensors[0].reading_value, 12.0);
+        
+        // Back to background, then another burst
+        engine.process_reading(&geiger(0.0)).await;
+        engine.process_reading(&geiger(12.0)).await;
+        assert_eq!(spikes(&engine).await.len(), 2);
+        
+        let lambda = engine.checkpoint().await.radiation["geiger"].lambda().unwrap();
+        assert!(lambda < 0.5, "bursts leaked into the background: {}", lambda);
+    }
+    
+    #[tokio::test]
+    async fn test_schumann_excursion_is_one_infrasound_event() {
+        let engine = DetectionEngine::new(Arc::new(Config::default()), Arc::new(EventBus::new(64))).await.unwrap();
+        // One second of infrasound with all three modes, the first at `amplitude`
+        let infrasound = |second: usize, amplitude: f64| {
+            let data = (0..200)
+                .map(|i| {
+                    let t = second as f64 + i as f64 / 200.0;
+                    let tone = |freq: f64| (std::f64::consts::TAU * freq * t).sin();
+                    amplitude * tone(7.83) + 0.0002 * tone(14.3) + 0.0001 * tone(20.8)
+                })
+                .collect();
+            let mut reading = SensorReading::new("infra", SensorType::Infrasound, data);
+            reading.sample_rate = 200.0;
+            reading
+        };
+        
+        // The fundamental quadruples at 30 s and stays there
+        for second in 0..70 {
+            engine.process_reading(&infrasound(second, if second < 30 { 0.0003 } else { 0.0012 })).await;
+        }
+        let events: Vec<Detection> = engine.get_recent_detections(100).await
+            .into_iter()
+            .filter(|d| d.detection_type == DetectionType::InfrasoundEvent)
+            .collect();
+        assert_eq!(events.len(), 1, "{:?}", events);
+        let Some(DetectionPayload::Schumann(modes)) = &events[0].payload else {
+            panic!("infrasound event without its modes");
+        };
+        assert!(modes.excursions[0] && !modes.excursions[1] && !modes.excursions[2], "{:?}", modes);
+    }
+    
+    #[tokio::test]
+    async fn test_false_positive_annotation_lowers_sensor_weight() {
+        let contribution = |sensor_type| SensorContribution {
+            sensor_id: format!("{:?}", sensor_type),
+            sensor_type,
+            weight: 0.7,
+            reading_value: 1.0,
+            anomaly_score: 0.8,
+        };
+        let weight = |engine: &DetectionEngine, sensor_type| {
+            engine.sensor_weight(sensor_type)
+        };
+        
+        let mut config = Config::default();
+        let engine = DetectionEngine::new(Arc::new(config.clone()), Arc::new(EventBus::new(16))).await.unwrap();
+        let detection = engine.create_detection(
+            DetectionType::CorrelatedAnomaly,
+            0.8,
+            vec![contribution(SensorType::EMFProbe), contribution(SensorType::EMFProbe), contribution(SensorType::Geophone)],
+        );
+        
+        // Feedback is opt-in
+        assert!(!engine.apply_annotation(&detection, None, AnnotationStatus::FalsePositive));
+        assert_eq!(weight(&engine, SensorType::EMFProbe), 0.70);
+        
+        config.detection.annotation_feedback = true;
+        engine.apply_config(&config.detection);
+        assert!(engine.apply_annotation(&detection, None, AnnotationStatus::FalsePositive));
+        // Lowered once per sensor type, however many of its sensors contributed
+        assert!((weight(&engine, SensorType::EMFProbe) - 0.63).abs() < 1e-12);
+        assert!((weight(&engine, SensorType::Geophone) - 0.72).abs() < 1e-12);
+        
+        let false_positive = Some(AnnotationStatus::FalsePositive);
+        assert!(!engine.apply_annotation(&detection, false_positive, AnnotationStatus::FalsePositive));
+        assert!((weight(&engine, SensorType::EMFProbe) - 0.63).abs() < 1e-12);
+        assert!(engine.apply_annotation(&detection, false_positive, AnnotationStatus::Confirmed));
+        assert!(weight(&engine, SensorType::Geophone) > 0.80);
+        
+        assert_eq!("false_positive".parse::<AnnotationStatus>().unwrap(), AnnotationStatus::FalsePositive);
+        assert!("dismissed".parse::<AnnotationStatus>().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_beam_break_is_published_once_with_its_dwell() {
+        let event_bus = Arc::new(EventBus::new(16));
+        let engine = DetectionEngine::new(Arc::new(Config::default()), event_bus.clone()).await.unwrap();
+        let mut published = event_bus.subscribe_detections();
+        
+        let t0 = Utc::now();
+        let frame = |ms: i64, intensity: f64| {
+            let mut reading = SensorReading::new("grid-1", SensorType::LaserGrid, vec![0.98, intensity]);
+            reading.timestamp = t0 + chrono::Duration::milliseconds(ms);
+            reading
+        };
+        engine.process_reading(&frame(0, 0.0)).await;
+        engine.process_reading(&frame(100, 0.0)).await;
+        assert!(published.try_recv().is_err(), "published before the beam was restored");
+        
+        engine.process_reading(&frame(250, 0.98)).await;
+        let detection = published.try_recv().unwrap();
+        assert_eq!(detection.detection_type, DetectionType::LaserInterruption);
+        assert_eq!(detection.payload, Some(DetectionPayload::BeamBreak(BeamBreak { beam: 1, dwell_ms: Some(250) })));
+        assert_eq!(detection.timestamp, t0);
+        assert!(published.try_recv().is_err());
+        assert_eq!(engine.get_detection_count().await, 1);
+    }
 }
@@ -45,6 +45,7 @@ pub mod streaming;
 pub mod security;
 pub mod config;
 pub mod db;
+pub mod report;
 
 #[cfg(feature = "gpu")]
 pub mod gpu;
@@ -61,6 +62,7 @@ pub use detection::{DetectionEngine, Detection, DetectionType};
 pub use streaming::StreamingManager;
 pub use security::SecurityManager;
 pub use db::Database;
+pub use report::Report;
 
 /// GlowBarn version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -17,11 +17,12 @@
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use anyhow::Result;
-use clap::Parser;
-use tracing::{info, Level};
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use tracing::{info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 use glowbarn::{Config, VERSION};
 
@@ -31,23 +32,90 @@ use glowbarn::{Config, VERSION};
 #[command(author = "GlowBarn Project")]
 #[command(version = VERSION)]
 #[command(about = "High-performance paranormal detection and anomaly analysis")]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
     /// Configuration file path
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     config: Option<PathBuf>,
 
-    /// Run in headless mode (no GUI)
-    #[arg(long)]
-    headless: bool,
-
     /// Enable debug logging
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     debug: bool,
 
     /// Enable trace-level logging
-    #[arg(long)]
+    #[arg(long, global = true)]
     trace: bool,
 
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Without a subcommand, the options of `run`
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the console, or the engine headless (the default)
+    Run(RunArgs),
+
+    /// Export the readings (or, as GeoJSON, the located detections) of a recorded session
+    Export {
+        /// Session id
+        #[arg(long)]
+        session: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "json")]
+        format: ExportKind,
+
+        /// Output file
+        #[arg(long)]
+        out: PathBuf,
+
+        /// Sensor to export (required for WAV)
+        #[arg(long)]
+        sensor: Option<String>,
+    },
+
+    /// Print database statistics
+    Stats,
+
+    /// Re-run detection over a recorded session with the current settings
+    Reprocess {
+        /// Session id
+        #[arg(long)]
+        session: String,
+    },
+
+    /// Rotate the storage encryption key and re-encrypt stored rows under it
+    RotateKey,
+
+    /// Write an HTML investigation report of a recorded session
+    Report {
+        /// Session id
+        #[arg(long)]
+        session: String,
+    },
+
+    /// Run the entropy and anomaly suite on a WAV or CSV file and print a report
+    Analyze {
+        /// WAV file, or CSV with the samples in the last column
+        #[arg(long)]
+        file: PathBuf,
+
+        /// Sample rate of a CSV file in Hz (WAV files carry their own)
+        #[arg(long)]
+        sample_rate: Option<f64>,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct RunArgs {
+    /// Run in headless mode (no GUI)
+    #[arg(long)]
+    headless: bool,
+
     /// Demo mode with simulated sensors
     #[arg(long)]
     demo: bool,
@@ -63,12 +131,37 @@ struct Args {
     /// Data output directory
     #[arg(long)]
     data_dir: Option<PathBuf>,
+
+    /// Replay a recorded session id or exported JSONL file instead of live sensors (headless)
+    #[arg(long, value_name = "SESSION_ID|FILE")]
+    replay: Option<String>,
+
+    /// Scenario script (TOML or JSON) of timed events for the demo simulators
+    #[arg(long, value_name = "FILE")]
+    scenario: Option<PathBuf>,
+
+    /// Playback speed for --replay (2.0 = twice real time)
+    #[arg(long, default_value = "1.0")]
+    replay_speed: f64,
+}
+
+/// File formats for `export`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ExportKind {
+    Json,
+    Csv,
+    Binary,
+    Influx,
+    Wav,
+    /// Located detections as a GeoJSON FeatureCollection
+    #[value(name = "geojson")]
+    GeoJson,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    // Initialize logging
+    // Initialize logging; on stderr so subcommand output can be piped
     let log_level = if args.trace {
         Level::TRACE
     } else if args.debug {
@@ -84,6 +177,7 @@ fn main() -> Result<()> {
         .with_file(args.debug)
         .with_line_number(args.debug)
         .with_ansi(true)
+        .with_writer(std::io::stderr)
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
 
@@ -93,11 +187,26 @@ fn main() -> Result<()> {
     // Load or create configuration
     let config_path = args.config.unwrap_or_else(Config::default_path);
     let mut config = Config::load_or_create(&config_path)?;
-    
+    config.apply_env_overrides()?;
+    info!("Configuration loaded from {:?}", config_path);
+
+    match args.command.unwrap_or(Command::Run(args.run)) {
+        Command::Run(run) => run_app(config, config_path, run),
+        command => run_command(command, &config, &mut std::io::stdout().lock()),
+    }
+}
+
+/// Run the console or the headless engine until shutdown
+fn run_app(mut config: Config, config_path: PathBuf, args: RunArgs) -> Result<()> {
     // Override with command line args
     if args.demo {
         config.demo_mode = true;
     }
+    if let Some(scenario) = args.scenario {
+        // Scenarios drive the simulators, so they imply demo mode
+        config.demo_mode = true;
+        config.sensors.scenario_file = Some(scenario);
+    }
     if let Some(data_dir) = args.data_dir {
         config.data_dir = data_dir;
     }
@@ -107,20 +216,61 @@ fn main() -> Result<()> {
         config.streaming.mqtt_broker = mqtt;
     }
 
-    info!("Configuration loaded from {:?}", config_path);
     info!("Demo mode: {}", config.demo_mode);
 
-    if args.headless {
+    let replay = args.replay.map(|source| (source, args.replay_speed));
+    
+    if args.headless || replay.is_some() {
         // Run headless mode
         info!("Starting in headless mode...");
         let rt = tokio::runtime::Runtime::new()?;
-        rt.block_on(run_headless(config))?;
+        rt.block_on(run_headless(config, &config_path, replay))?;
     } else {
         // Run GUI application
         #[cfg(feature = "gui")]
         {
             info!("Starting visual console...");
-            glowbarn::ui::run_gui(config)?;
+            let database = if config.database.enabled {
+                // Without a passphrase the console still runs, just without the database
+                match storage_security(&config).and_then(|security| glowbarn::db::Database::open_shared(&config.database, security)) {
+                    Ok(db) => Some(db),
+                    Err(e) => {
+                        warn!("Database unavailable, export disabled: {}", e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            
+            // Run the engine in the background and feed its output to the console
+            let rt = tokio::runtime::Runtime::new()?;
+            let engine = rt.block_on(async {
+                let mut engine = glowbarn::core::Engine::new(config.clone()).await?;
+                if config.streaming.any_enabled() {
+                    let mut streaming = glowbarn::streaming::StreamingManager::new(config.streaming.clone()).await?;
+                    streaming.start(engine.shutdown_signal()).await?;
+                    engine = engine.with_streaming(std::sync::Arc::new(streaming));
+                }
+                // The console only persists while the Record toggle is on
+                if let Some(ref db) = database {
+                    engine = engine.with_database(db.clone()).with_recording()?;
+                    if let Err(e) = engine.restore().await {
+                        warn!("Failed to restore engine state: {}", e);
+                    }
+                }
+                engine.start().await?;
+                Ok::<_, anyhow::Error>(engine)
+            })?;
+            let mut live = glowbarn::ui::LiveFeed::new(&engine.event_bus(), engine.sensor_manager(), rt.handle().clone())
+                .with_detection_engine(engine.detection_engine());
+            if let Some(recorder) = engine.recorder() {
+                live = live.with_recorder(recorder);
+            }
+            
+            let result = glowbarn::ui::run_gui(config, config_path, database, Some(live));
+            rt.block_on(engine.shutdown())?;
+            result?;
         }
         
         #[cfg(not(feature = "gui"))]
@@ -132,51 +282,368 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Run a one-off subcommand, writing its report to `out`
+fn run_command(command: Command, config: &Config, out: &mut impl Write) -> Result<()> {
+    use glowbarn::db::Database;
+    use glowbarn::streaming::{BatchExporter, ExportFormat, ExportSelection};
+    use std::sync::Arc;
+
+    match command {
+        Command::Run(_) => unreachable!("run is handled by run_app"),
+
+        Command::Stats => {
+            // Counts only, so encrypted payloads needn't be unlocked
+            let db = Database::open(&config.database, None)?;
+            let stats = db.get_stats()?;
+            writeln!(out, "database: {}", config.database.path.display())?;
+            writeln!(out, "readings: {}", stats.reading_count)?;
+            writeln!(out, "detections: {}", stats.detection_count)?;
+            writeln!(out, "size_bytes: {}", stats.size_bytes)?;
+            writeln!(out, "tables:")?;
+            for table in &stats.tables {
+                let size = table.size_bytes.map_or("?".to_string(), |b| b.to_string());
+                write!(out, "  - {}: {} rows, {} bytes", table.name, table.row_count, size)?;
+                if let (Some(oldest), Some(newest)) = (table.oldest, table.newest) {
+                    write!(out, ", {} to {}", oldest.to_rfc3339(), newest.to_rfc3339())?;
+                }
+                writeln!(out)?;
+            }
+        }
+
+        Command::Export { session, format, out: path, sensor } => {
+            let db = Database::open_shared(&config.database, storage_security(config)?)?;
+            let (start, end) = db.session_range(&session)?
+                .with_context(|| format!("Unknown session {}", session))?;
+
+            let (format, selection) = match format {
+                ExportKind::Json => (ExportFormat::Json, ExportSelection::Readings),
+                ExportKind::Csv => (ExportFormat::Csv, ExportSelection::Readings),
+                ExportKind::Binary => (ExportFormat::Binary, ExportSelection::Readings),
+                ExportKind::Influx => (ExportFormat::InfluxLineProtocol, ExportSelection::Readings),
+                ExportKind::GeoJson => (ExportFormat::GeoJson, ExportSelection::Detections),
+                ExportKind::Wav => {
+                    let sensor = sensor.context("WAV export needs --sensor")?;
+                    let mut readings = db.query_readings(start, end, Some(&sensor), Some(i64::MAX as usize))?
+                        .iter()
+                        .map(|r| r.to_reading())
+                        .collect::<Result<Vec<_>>>()?;
+                    readings.reverse();
+                    BatchExporter::new(ExportFormat::Json).export_readings_wav(&readings, &path, None)?;
+                    writeln!(out, "exported {} readings to {}", readings.len(), path.display())?;
+                    return Ok(());
+                }
+            };
+            let count = BatchExporter::new(format)
+                .export_from_database(&db, selection, start, end, &path)?;
+            let records = match selection {
+                ExportSelection::Readings => "readings",
+                ExportSelection::Detections => "located detections",
+            };
+            writeln!(out, "exported {} {} to {}", count, records, path.display())?;
+        }
+
+        Command::Reprocess { session } => {
+            let db = Database::open_shared(&config.database, storage_security(config)?)?;
+            let rt = tokio::runtime::Runtime::new()?;
+            let engine = rt.block_on(glowbarn::core::Engine::new(config.clone()))?.with_database(db);
+            let stats = engine.reprocess(&session)?;
+            writeln!(out, "session: {}", session)?;
+            writeln!(out, "readings: {}", stats.readings)?;
+            writeln!(out, "original_detections: {}", stats.original_detections)?;
+            writeln!(out, "reprocessed_detections: {}", stats.reprocessed_detections)?;
+            writeln!(out, "changed: {} (+{} / -{})", stats.changed(), stats.added, stats.removed)?;
+        }
+
+        Command::RotateKey => {
+            let security = storage_security(config)?
+                .context("Storage encryption (or the database) is disabled; there is no key to rotate")?;
+            let db = Database::open_shared(&config.database, Some(security.clone()))?;
+            let key_id = security.rotate_data_key()?;
+            let re_encrypted = db.re_encrypt_all()?;
+            writeln!(out, "active_key: {}", key_id)?;
+            writeln!(out, "re_encrypted: {}", re_encrypted)?;
+        }
+
+        Command::Report { session } => {
+            let db = Database::open_shared(&config.database, storage_security(config)?)?;
+            let path = glowbarn::Report::generate(&db, &session)?;
+            writeln!(out, "report: {}", path.display())?;
+        }
+
+        Command::Analyze { file, sample_rate } => {
+            let (samples, sample_rate) = load_samples(&file, sample_rate)?;
+            let mut reading = glowbarn::SensorReading::new(
+                &file.display().to_string(),
+                glowbarn::SensorType::FullSpectrum,
+                samples,
+            );
+            reading.sample_rate = sample_rate;
+
+            let rt = tokio::runtime::Runtime::new()?;
+            let analysis = rt.block_on(glowbarn::AnalysisEngine::new(
+                Arc::new(config.clone()),
+                Arc::new(glowbarn::EventBus::new(16)),
+            ))?;
+            let result = analysis.analyze_reading(&reading)
+                .with_context(|| format!("No samples in {:?}", file))?;
+            let entropy = &result.entropy;
+
+            writeln!(out, "file: {}", file.display())?;
+            writeln!(out, "samples: {} @ {} Hz", reading.data.len(), sample_rate)?;
+            writeln!(out, "shannon_entropy: {:.4}", entropy.shannon)?;
+            writeln!(out, "sample_entropy: {:.4}", entropy.sample)?;
+            writeln!(out, "permutation_entropy: {:.4}", entropy.permutation)?;
+            writeln!(out, "spectral_entropy: {:.4}", entropy.spectral)?;
+            writeln!(out, "hurst_exponent: {:.4}", entropy.hurst_exponent)?;
+            writeln!(out, "kurtosis: {:.4}", entropy.kurtosis)?;
+            writeln!(out, "skewness: {:.4}", entropy.skewness)?;
+            writeln!(out, "dominant_frequency_hz: {:.2}", result.signal.dominant_frequency)?;
+            writeln!(out, "rms: {:.6}", result.signal.rms)?;
+            writeln!(out, "anomaly_score: {:.4}", entropy.anomaly_score)?;
+            writeln!(out, "anomalous: {}", result.is_anomalous())?;
+            writeln!(out, "anomalies: {}", result.anomalies.len())?;
+            for anomaly in &result.anomalies {
+                writeln!(out, "  - {:?} at {} (value {:.4}, confidence {:.2})",
+                    anomaly.anomaly_type, anomaly.index, anomaly.value, anomaly.confidence)?;
+            }
+            writeln!(out, "patterns: {}", result.patterns.len())?;
+            for pattern in &result.patterns {
+                writeln!(out, "  - {:?} at {}: {}", pattern.pattern_type, pattern.start_index, pattern.description)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Samples and sample rate of a WAV file (first channel), or of a CSV file
+/// whose last column holds the samples; non-numeric rows such as a header
+/// are skipped
+fn load_samples(path: &Path, sample_rate: Option<f64>) -> Result<(Vec<f64>, f64)> {
+    let is_wav = path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("wav"));
+
+    if is_wav {
+        let mut reader = hound::WavReader::open(path)
+            .with_context(|| format!("Failed to open {:?}", path))?;
+        let spec = reader.spec();
+        let interleaved: Vec<f64> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>()
+                .map(|s| s.map(f64::from))
+                .collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let full_scale = (1i64 << (spec.bits_per_sample - 1)) as f64;
+                reader.samples::<i32>()
+                    .map(|s| s.map(|s| s as f64 / full_scale))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+        let samples = interleaved.into_iter().step_by(spec.channels.max(1) as usize).collect();
+        return Ok((samples, spec.sample_rate as f64));
+    }
+
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let samples = text.lines()
+        .filter_map(|line| line.rsplit(',').next()?.trim().parse::<f64>().ok())
+        .collect();
+    Ok((samples, sample_rate.unwrap_or(1.0)))
+}
+
+/// Security manager for database encryption, unlocked with a passphrase
+///
+/// Used when storage encryption and the database are enabled. The
+/// passphrase comes from `GLOWBARN_STORAGE_PASSPHRASE`, from the file named
+/// by `GLOWBARN_STORAGE_PASSPHRASE_FILE`, or from a prompt when stdin is a
+/// terminal. Without one this fails rather than store data unencrypted; set
+/// `security.encrypt_storage = false` to store plaintext.
+fn storage_security(config: &Config) -> Result<Option<std::sync::Arc<glowbarn::SecurityManager>>> {
+    use glowbarn::config::{STORAGE_PASSPHRASE_ENV, STORAGE_PASSPHRASE_FILE_ENV};
+    use std::io::IsTerminal;
+    
+    if !(config.database.enabled && config.security.encrypt_storage) {
+        return Ok(None);
+    }
+    
+    let configured = passphrase_from(
+        std::env::var(STORAGE_PASSPHRASE_ENV).ok(),
+        std::env::var_os(STORAGE_PASSPHRASE_FILE_ENV).map(PathBuf::from).as_deref(),
+    )?;
+    let passphrase = match configured {
+        Some(passphrase) => passphrase,
+        None if std::io::stdin().is_terminal() => rpassword::prompt_password("Storage passphrase: ")
+            .map(zeroize::Zeroizing::new)
+            .context("Failed to read the storage passphrase")?,
+        None => anyhow::bail!(
+            "Storage encryption is enabled but no passphrase was given; set {} or {}, run from a terminal, \
+             or set security.encrypt_storage = false to store data unencrypted",
+            STORAGE_PASSPHRASE_ENV, STORAGE_PASSPHRASE_FILE_ENV
+        ),
+    };
+    if passphrase.is_empty() {
+        anyhow::bail!("Storage encryption is enabled but the passphrase is empty; set security.encrypt_storage = false to store data unencrypted");
+    }
+    
+    let security = glowbarn::SecurityManager::new(config.security.clone())?;
+    security.load_pseudonym_secret(&config.data_dir.join("audit.secret"))?;
+    security.unlock_storage(&passphrase, &config.data_dir.join("storage.salt"))?;
+    info!("Storage encryption unlocked");
+    Ok(Some(std::sync::Arc::new(security)))
+}
+
+/// A passphrase given directly, or else as the first line of `file`
+fn passphrase_from(direct: Option<String>, file: Option<&Path>) -> Result<Option<zeroize::Zeroizing<String>>> {
+    if let Some(passphrase) = direct {
+        return Ok(Some(zeroize::Zeroizing::new(passphrase)));
+    }
+    let Some(path) = file else { return Ok(None) };
+    let contents = zeroize::Zeroizing::new(
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read passphrase file {:?}", path))?,
+    );
+    Ok(Some(zeroize::Zeroizing::new(contents.lines().next().unwrap_or_default().to_string())))
+}
+
 /// Run the application in headless mode (no GUI)
-async fn run_headless(config: Config) -> Result<()> {
+///
+/// With `replay` set to `(session id or file, speed)`, recorded readings
+/// are played back in place of the configured sensors.
+async fn run_headless(config: Config, config_path: &Path, replay: Option<(String, f64)>) -> Result<()> {
     use glowbarn::{
         core::Engine,
+        sensors::ReplaySensor,
         streaming::StreamingManager,
         db::Database,
     };
-    use tokio::sync::broadcast;
+    use std::sync::Arc;
     
     info!("Initializing headless mode...");
     
     // Initialize database
-    let db_path = config.data_dir.join("glowbarn.db");
-    let db = Database::open(&config.database)?;
-    info!("Database opened at {:?}", db_path);
-    
-    // Create event channel for sensor data
-    let (tx, _rx): (broadcast::Sender<String>, broadcast::Receiver<String>) = broadcast::channel(1000);
-    
-    // Initialize streaming if enabled
-    let streaming = if config.streaming.websocket_enabled {
-        let streaming = StreamingManager::new(config.streaming.clone());
-        info!("Streaming manager initialized");
-        Some(streaming)
+    let db = if config.database.enabled {
+        let db = Database::open_shared(&config.database, storage_security(&config)?)?;
+        info!("Database opened at {:?}", config.database.path);
+        Some(db)
     } else {
         None
     };
     
     // Initialize the core engine
-    let engine = Engine::new(config.clone()).await?;
+    let mut engine = Engine::new(config.clone()).await?;
     info!("Core engine initialized");
     
+    if let Some((ref source, speed)) = replay {
+        let path = PathBuf::from(source);
+        let sensors = if path.is_file() {
+            ReplaySensor::from_jsonl(&path, speed)?
+        } else {
+            let db = db.as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Replaying a session requires the database"))?;
+            ReplaySensor::from_session(db, source, speed)?
+        };
+        info!("Replaying {} at {}x", source, speed);
+        engine = engine.with_replay(sensors).await?;
+    }
+    
+    if let Some(db) = db {
+        engine = engine.with_database(db);
+        if let Err(e) = engine.restore().await {
+            warn!("Failed to restore engine state: {}", e);
+        }
+    }
+    
+    // Initialize streaming if enabled
+    if config.streaming.any_enabled() {
+        let mut streaming = StreamingManager::new(config.streaming.clone()).await?;
+        streaming.start(engine.shutdown_signal()).await?;
+        info!("Streaming manager initialized");
+        engine = engine.with_streaming(Arc::new(streaming));
+    }
+    
+    engine.start().await?;
+    
+    if let Err(e) = engine.watch_config(config_path) {
+        warn!("Config hot-reload disabled: {}", e);
+    }
+    
     info!("🚀 GlowBarn running in headless mode");
     info!("   Press Ctrl+C to shutdown");
     
-    // Wait for shutdown signal
-    tokio::signal::ctrl_c().await?;
+    // Report pipeline activity until shutdown
+    let mut status_interval = tokio::time::interval(std::time::Duration::from_secs(10));
+    loop {
+        tokio::select! {
+            _ = status_interval.tick() => {
+                let state = engine.state().await;
+                info!(
+                    "Sensors: {} | Readings: {} | Detections: {} | Dropped: {} | Lagged: {}",
+                    state.sensors_active, state.total_readings, state.total_detections,
+                    state.dropped_readings, state.lagged_readings
+                );
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
     
     info!("Shutdown signal received, cleaning up...");
-    
-    // Cleanup
-    drop(streaming);
-    drop(db);
+    engine.shutdown().await?;
     
     info!("GlowBarn shutdown complete");
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glowbarn::{SensorReading, SensorType};
+
+    #[test]
+    fn test_run_is_the_default_command() {
+        let args = Args::try_parse_from(["glowbarn", "--headless", "--demo"]).unwrap();
+        assert!(args.command.is_none());
+        assert!(args.run.headless && args.run.demo);
+    }
+
+    #[test]
+    fn test_stats_subcommand() {
+        let tag = uuid::Uuid::new_v4();
+        let config_path = std::env::temp_dir().join(format!("glowbarn-cli-{}.toml", tag));
+        let mut config = Config::default();
+        config.database.path = std::env::temp_dir().join(format!("glowbarn-cli-{}.db", tag));
+        config.save(&config_path).unwrap();
+
+        let db = glowbarn::Database::open(&config.database, None).unwrap();
+        db.store_readings_batch(&[
+            SensorReading::new("emf-1", SensorType::EMFProbe, vec![1.0]),
+            SensorReading::new("emf-1", SensorType::EMFProbe, vec![2.0]),
+        ]).unwrap();
+        drop(db);
+
+        let args = Args::try_parse_from(["glowbarn", "--config", config_path.to_str().unwrap(), "stats"]).unwrap();
+        let config = Config::load_or_create(args.config.as_ref().unwrap()).unwrap();
+        let mut out = Vec::new();
+        run_command(args.command.unwrap(), &config, &mut out).unwrap();
+
+        let report = String::from_utf8(out).unwrap();
+        assert!(report.contains("readings: 2\n"), "{}", report);
+        assert!(report.contains("detections: 0\n"), "{}", report);
+
+        let _ = std::fs::remove_file(&config.database.path);
+        let _ = std::fs::remove_file(&config_path);
+    }
+
+    #[test]
+    fn test_passphrase_from_env_or_file() {
+        assert!(passphrase_from(None, None).unwrap().is_none());
+        assert_eq!(passphrase_from(Some("direct".to_string()), None).unwrap().unwrap().as_str(), "direct");
+
+        let path = std::env::temp_dir().join(format!("glowbarn-passphrase-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "from file\r\n").unwrap();
+        assert_eq!(passphrase_from(None, Some(&path)).unwrap().unwrap().as_str(), "from file");
+        // The variable wins over the file
+        assert_eq!(passphrase_from(Some("direct".to_string()), Some(&path)).unwrap().unwrap().as_str(), "direct");
+        let _ = std::fs::remove_file(&path);
+
+        assert!(passphrase_from(None, Some(&path)).is_err());
+    }
+}
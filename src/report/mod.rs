@@ -0,0 +1,539 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Investigation reports for recorded sessions
+//!
+//! A report is a single HTML file with the session's summary, a timeline of
+//! its detections, statistics and entropy/complexity measures of every
+//! sensor, and plots rendered with `plotters`. The plots are embedded as
+//! base64 PNGs, so the file can be shared on its own.
+
+use anyhow::{Context, Result};
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use plotters::coord::types::RangedCoordf64;
+use plotters::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::analysis::{
+    AnalysisConfig, ComplexityAnalyzer, ComplexityResult, EntropyAnalyzer, EntropyResult,
+    RunningStats, StatisticalAnalyzer, StatisticalSummary,
+};
+use crate::db::Database;
+use crate::detection::{AnnotationStatus, Detection, DetectionAnnotation, Severity};
+
+/// Most recent samples of a sensor the entropy measures are computed over
+const ENTROPY_SAMPLES: usize = 4096;
+
+/// Most recent samples of a sensor the complexity measures are computed
+/// over; recurrence analysis is quadratic in this
+const COMPLEXITY_SAMPLES: usize = 1000;
+
+/// Readings fetched from the database at a time
+const READING_PAGE: usize = 10_000;
+
+/// Values of a sensor its median, quartiles, mode and shape are estimated
+/// from; a longer session is represented by a uniform sample of this size
+const SUMMARY_SAMPLES: usize = 100_000;
+
+/// Most points of a sensor's plotted series; a longer session is averaged
+/// down to between this and twice as many
+const SERIES_POINTS: usize = 2000;
+
+const PLOT_WIDTH: u32 = 720;
+const PLOT_HEIGHT: u32 = 160;
+
+/// Statistics of one sensor over a session
+#[derive(Debug, Clone)]
+pub struct SensorSection {
+    pub sensor_id: String,
+    pub sensor_type: String,
+    pub readings: usize,
+    pub summary: StatisticalSummary,
+    pub entropy: EntropyResult,
+    pub complexity: ComplexityResult,
+    /// Seconds since the session start and mean value of each reading
+    pub series: Vec<(f64, f64)>,
+}
+
+/// Everything a session's report shows
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub session_id: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Oldest first
+    pub detections: Vec<Detection>,
+    /// Investigator reviews of the session's detections, by detection id
+    pub annotations: BTreeMap<String, DetectionAnnotation>,
+    /// In sensor id order
+    pub sensors: Vec<SensorSection>,
+}
+
+impl Report {
+    /// Write the report of `session_id` to `reports/session-<id>.html` next
+    /// to the database, returning its path
+    pub fn generate(db: &Database, session_id: &str) -> Result<PathBuf> {
+        let report = Self::build(db, session_id)?;
+        let dir = db.path().parent().unwrap_or_else(|| Path::new(".")).join("reports");
+        std::fs::create_dir_all(&dir)?;
+        
+        let path = dir.join(format!("session-{}.html", session_id));
+        std::fs::write(&path, report.to_html()?)
+            .with_context(|| format!("Failed to write {:?}", path))?;
+        Ok(path)
+    }
+    
+    /// Gather a session's detections and readings and analyze each sensor
+    ///
+    /// Readings are read a page at a time and folded into bounded buffers
+    /// per sensor, so a long session never sits in memory at once. Rows
+    /// that can't be decoded are logged and left out.
+    pub fn build(db: &Database, session_id: &str) -> Result<Self> {
+        let (start, end) = db.session_range(session_id)?
+            .with_context(|| format!("Unknown session {}", session_id))?;
+        
+        let detections: Vec<Detection> = db.query_detections_between(start, end)?
+            .iter()
+            .filter_map(|stored| match stored.to_detection() {
+                Ok(detection) => Some(detection),
+                Err(e) => {
+                    warn!("Leaving undecodable detection {} out of the report: {}", stored.id, e);
+                    None
+                }
+            })
+            .collect();
+        let ids: HashSet<&str> = detections.iter().map(|d| d.id.as_str()).collect();
+        let annotations = db.detection_annotations()?
+            .into_iter()
+            .filter(|a| ids.contains(a.detection_id.as_str()))
+            .map(|a| (a.detection_id.clone(), a))
+            .collect();
+        
+        let mut grouped: BTreeMap<String, SensorAccumulator> = BTreeMap::new();
+        let mut cursor = None;
+        loop {
+            let page = db.query_readings_page(start, end, None, cursor.as_ref(), READING_PAGE)?;
+            for stored in &page.readings {
+                let decoded = stored.values().and_then(|values| {
+                    Ok((DateTime::parse_from_rfc3339(&stored.timestamp)?.with_timezone(&Utc), values))
+                });
+                let (at, values) = match decoded {
+                    Ok(decoded) => decoded,
+                    Err(e) => {
+                        warn!("Leaving undecodable reading {} out of the report: {}", stored.id, e);
+                        continue;
+                    }
+                };
+                if values.is_empty() {
+                    continue;
+                }
+                let mean = stored.mean_value.unwrap_or_else(|| values.iter().sum::<f64>() / values.len() as f64);
+                grouped.entry(stored.sensor_id.clone())
+                    .or_insert_with(|| SensorAccumulator::new(&stored.sensor_type))
+                    .push(seconds_between(start, at), mean, &values);
+            }
+            match page.next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        
+        let sensors = grouped.into_iter()
+            .map(|(sensor_id, accumulator)| accumulator.finish(sensor_id))
+            .collect();
+        
+        Ok(Self { session_id: session_id.to_string(), start, end, detections, annotations, sensors })
+    }
+    
+    /// Number of detections at each severity, lowest first
+    pub fn severity_counts(&self) -> BTreeMap<Severity, usize> {
+        let mut counts = BTreeMap::new();
+        for detection in &self.detections {
+            *counts.entry(detection.severity).or_insert(0) += 1;
+        }
+        counts
+    }
+    
+    /// Render the report as a standalone HTML page
+    pub fn to_html(&self) -> Result<String> {
+        let mut html = String::new();
+        let title = format!("GlowBarn session {}", escape(&self.session_id));
+        writeln!(html, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>", title)?;
+        writeln!(html, "<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>", STYLE, title)?;
+        
+        // Summary
+        let readings: usize = self.sensors.iter().map(|s| s.readings).sum();
+        writeln!(html, "<h2>Summary</h2>\n<table>")?;
+        writeln!(html, "<tr><th>Start</th><td>{}</td></tr>", self.start.to_rfc3339())?;
+        writeln!(html, "<tr><th>End</th><td>{}</td></tr>", self.end.to_rfc3339())?;
+        writeln!(html, "<tr><th>Duration</th><td>{:.1} s</td></tr>", seconds_between(self.start, self.end))?;
+        writeln!(html, "<tr><th>Sensors</th><td>{}</td></tr>", self.sensors.len())?;
+        writeln!(html, "<tr><th>Readings</th><td>{}</td></tr>", readings)?;
+        writeln!(html, "<tr><th>Detections</th><td id=\"detection-count\">{}</td></tr>", self.detections.len())?;
+        for (severity, count) in self.severity_counts().iter().rev() {
+            writeln!(html, "<tr><th>{:?}</th><td>{}</td></tr>", severity, count)?;
+        }
+        let false_positives = self.annotations.values().filter(|a| a.status == AnnotationStatus::FalsePositive).count();
+        writeln!(html, "<tr><th>Reviewed</th><td>{} ({} false positive)</td></tr>", self.annotations.len(), false_positives)?;
+        writeln!(html, "</table>")?;
+        
+        // Detection timeline
+        writeln!(html, "<h2>Detections</h2>")?;
+        if self.detections.is_empty() {
+            writeln!(html, "<p>No detections.</p>")?;
+        } else {
+            let points: Vec<(f64, f64, RGBColor)> = self.detections.iter()
+                .map(|d| (seconds_between(self.start, d.timestamp), d.confidence, severity_color(d.severity)))
+                .collect();
+            let png = plot_png(seconds_between(self.start, self.end), (0.0, 1.0), |chart| {
+                chart.draw_series(points.iter().map(|&(x, y, color)| Circle::new((x, y), 4, color.filled())))?;
+                Ok(())
+            })?;
+            writeln!(html, "{}", image(&png, "Detection confidence over the session"))?;
+            writeln!(html, "<p class=\"caption\">Confidence (0 to 1) against time; colour shows severity.</p>")?;
+            
+            writeln!(html, "<table>\n<tr><th>Time</th><th>+s</th><th>Type</th><th>Severity</th><th>Confidence</th><th>Sensors</th><th>Review</th></tr>")?;
+            for detection in &self.detections {
+                let sensors: Vec<&str> = detection.sensors.iter().map(|s| s.sensor_id.as_str()).collect();
+                let review = match self.annotations.get(&detection.id) {
+                    Some(annotation) => match annotation.note {
+                        Some(ref note) => format!("{}: {}", annotation.status.as_str(), note),
+                        None => annotation.status.as_str().to_string(),
+                    },
+                    None => String::new(),
+                };
+                writeln!(
+                    html,
+                    "<tr class=\"{:?}\"><td>{}</td><td>{:.1}</td><td>{:?}</td><td>{:?}</td><td>{:.2}</td><td>{}</td><td>{}</td></tr>",
+                    detection.severity,
+                    detection.timestamp.format("%H:%M:%S%.3f"),
+                    seconds_between(self.start, detection.timestamp),
+                    detection.detection_type,
+                    detection.severity,
+                    detection.confidence,
+                    escape(&sensors.join(", ")),
+                    escape(&review),
+                )?;
+            }
+            writeln!(html, "</table>")?;
+        }
+        
+        // Entropy and complexity, most anomalous sensors first
+        writeln!(html, "<h2>Entropy and complexity</h2>")?;
+        let mut ranked: Vec<&SensorSection> = self.sensors.iter().collect();
+        ranked.sort_by(|a, b| b.entropy.anomaly_score.total_cmp(&a.entropy.anomaly_score));
+        writeln!(html, "<table>\n<tr><th>Sensor</th><th>Anomaly score</th><th>Shannon</th><th>Spectral</th><th>Permutation</th><th>Sample</th><th>Hurst</th><th>Higuchi FD</th><th>Lyapunov</th><th>Determinism</th></tr>")?;
+        for sensor in ranked {
+            let (e, c) = (&sensor.entropy, &sensor.complexity);
+            writeln!(
+                html,
+                "<tr{}><td>{}</td><td>{:.3}</td><td>{:.3}</td><td>{:.3}</td><td>{:.3}</td><td>{:.3}</td><td>{:.3}</td><td>{:.3}</td><td>{:.3}</td><td>{:.3}</td></tr>",
+                if e.is_anomalous { " class=\"High\"" } else { "" },
+                escape(&sensor.sensor_id),
+                e.anomaly_score, e.shannon, e.spectral, e.permutation, e.sample, e.hurst_exponent,
+                c.higuchi_dimension, c.lyapunov_exponent, c.determinism,
+            )?;
+        }
+        writeln!(html, "</table>")?;
+        
+        // Per-sensor statistics
+        writeln!(html, "<h2>Sensors</h2>")?;
+        for sensor in &self.sensors {
+            let s = &sensor.summary;
+            writeln!(html, "<h3>{} <small>{}</small></h3>", escape(&sensor.sensor_id), escape(&sensor.sensor_type))?;
+            writeln!(html, "<table>\n<tr><th>Readings</th><th>Samples</th><th>Mean</th><th>Std dev</th><th>Min</th><th>Median</th><th>Max</th><th>IQR</th><th>Skewness</th><th>Kurtosis</th></tr>")?;
+            writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{:.4}</td><td>{:.4}</td><td>{:.4}</td><td>{:.4}</td><td>{:.4}</td><td>{:.4}</td><td>{:.3}</td><td>{:.3}</td></tr>\n</table>",
+                sensor.readings, s.count, s.mean, s.std_dev, s.min, s.median, s.max, s.iqr, s.skewness, s.kurtosis,
+            )?;
+            
+            if sensor.series.len() > 1 {
+                let (low, high) = sensor.series.iter()
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &(_, y)| (lo.min(y), hi.max(y)));
+                let png = plot_png(seconds_between(self.start, self.end), (low, high), |chart| {
+                    chart.draw_series(LineSeries::new(sensor.series.iter().copied(), &BLUE))?;
+                    Ok(())
+                })?;
+                writeln!(html, "{}", image(&png, &format!("{} reading means", escape(&sensor.sensor_id))))?;
+                writeln!(html, "<p class=\"caption\">Mean of each reading, {:.4} to {:.4}.</p>", low, high)?;
+            }
+        }
+        
+        writeln!(html, "<footer>Generated {} by GlowBarn v{}</footer>\n</body>\n</html>", Utc::now().to_rfc3339(), crate::VERSION)?;
+        Ok(html)
+    }
+}
+
+/// One sensor's readings, gathered a page at a time in bounded memory
+struct SensorAccumulator {
+    sensor_type: String,
+    readings: usize,
+    moments: RunningStats,
+    min: f64,
+    max: f64,
+    // Uniform sample of every value (reservoir sampling), for the order
+    // statistics and shape
+    sample: Vec<f64>,
+    rng: StdRng,
+    // Latest values, for entropy and complexity
+    recent: VecDeque<f64>,
+    // Each point averages `stride` readings; `pending` sums the readings of
+    // the point being filled
+    series: Vec<(f64, f64)>,
+    stride: usize,
+    pending: (f64, f64, usize),
+}
+
+impl SensorAccumulator {
+    fn new(sensor_type: &str) -> Self {
+        Self {
+            sensor_type: sensor_type.to_string(),
+            readings: 0,
+            moments: RunningStats::new(),
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sample: Vec::new(),
+            rng: StdRng::seed_from_u64(0),
+            recent: VecDeque::new(),
+            series: Vec::new(),
+            stride: 1,
+            pending: (0.0, 0.0, 0),
+        }
+    }
+    
+    /// Add a reading taken `at` seconds into the session
+    fn push(&mut self, at: f64, mean: f64, values: &[f64]) {
+        self.readings += 1;
+        for &x in values {
+            self.moments.push(x);
+            self.min = self.min.min(x);
+            self.max = self.max.max(x);
+            
+            let seen = self.moments.count() as usize;
+            if self.sample.len() < SUMMARY_SAMPLES {
+                self.sample.push(x);
+            } else {
+                let slot = self.rng.gen_range(0..seen);
+                if slot < SUMMARY_SAMPLES {
+                    self.sample[slot] = x;
+                }
+            }
+            
+            if self.recent.len() == ENTROPY_SAMPLES.max(COMPLEXITY_SAMPLES) {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(x);
+        }
+        
+        let (t, y, n) = &mut self.pending;
+        *t += at;
+        *y += mean;
+        *n += 1;
+        if *n == self.stride {
+            self.series.push((*t / *n as f64, *y / *n as f64));
+            self.pending = (0.0, 0.0, 0);
+        }
+        if self.series.len() == 2 * SERIES_POINTS {
+            self.series = self.series.chunks(2)
+                .map(|pair| ((pair[0].0 + pair[1].0) / 2.0, (pair[0].1 + pair[1].1) / 2.0))
+                .collect();
+            self.stride *= 2;
+        }
+    }
+    
+    fn finish(mut self, sensor_id: String) -> SensorSection {
+        let (t, y, n) = self.pending;
+        if n > 0 {
+            self.series.push((t / n as f64, y / n as f64));
+        }
+        
+        // Order statistics and shape come from the sample, the rest is exact
+        let mut summary = StatisticalAnalyzer::new().summarize(&self.sample);
+        summary.count = self.moments.count() as usize;
+        summary.mean = self.moments.mean();
+        summary.variance = self.moments.variance();
+        summary.std_dev = self.moments.std_dev();
+        summary.min = self.min;
+        summary.max = self.max;
+        summary.range = self.max - self.min;
+        summary.coefficient_of_variation = if summary.mean.abs() > 1e-10 {
+            summary.std_dev / summary.mean.abs()
+        } else {
+            0.0
+        };
+        
+        let recent = self.recent.make_contiguous();
+        SensorSection {
+            sensor_id,
+            sensor_type: self.sensor_type,
+            readings: self.readings,
+            summary,
+            entropy: EntropyAnalyzer::new(AnalysisConfig::default())
+                .analyze(&recent[recent.len().saturating_sub(ENTROPY_SAMPLES)..]),
+            complexity: ComplexityAnalyzer::new()
+                .analyze(&recent[recent.len().saturating_sub(COMPLEXITY_SAMPLES)..]),
+            series: self.series,
+        }
+    }
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin:0.5em 0}\
+th,td{border:1px solid #ccc;padding:0.2em 0.6em;text-align:left}\
+tr.High{background:#fde3c8}tr.Critical{background:#f8c4c4}\
+img{display:block;border:1px solid #ccc}\
+.caption,footer{color:#666;font-size:0.9em}";
+
+fn seconds_between(start: DateTime<Utc>, at: DateTime<Utc>) -> f64 {
+    (at - start).num_milliseconds() as f64 / 1000.0
+}
+
+fn severity_color(severity: Severity) -> RGBColor {
+    match severity {
+        Severity::Low => RGBColor(120, 160, 220),
+        Severity::Medium => RGBColor(230, 180, 40),
+        Severity::High => RGBColor(230, 110, 30),
+        Severity::Critical => RGBColor(200, 30, 30),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn image(png: &[u8], alt: &str) -> String {
+    format!(
+        "<img alt=\"{}\" src=\"data:image/png;base64,{}\">",
+        alt,
+        base64::engine::general_purpose::STANDARD.encode(png),
+    )
+}
+
+type Chart<'a, 'b> = ChartContext<'a, BitMapBackend<'b>, Cartesian2d<RangedCoordf64, RangedCoordf64>>;
+
+/// Render a plot spanning `duration` seconds and the `y` range to PNG.
+///
+/// Axes are left unlabelled, since text needs system fonts; the HTML around
+/// the image describes them instead.
+fn plot_png(duration: f64, y: (f64, f64), draw: impl FnOnce(&mut Chart<'_, '_>) -> Result<()>) -> Result<Vec<u8>> {
+    let spread = (y.1 - y.0).abs().max(1e-9);
+    let y_range = (y.0 - 0.05 * spread)..(y.1 + 0.05 * spread);
+    let x_range = 0.0..duration.max(1e-3);
+    
+    let path = std::env::temp_dir().join(format!("glowbarn-plot-{}.png", uuid::Uuid::new_v4()));
+    let rendered = (|| -> Result<()> {
+        let root = BitMapBackend::new(&path, (PLOT_WIDTH, PLOT_HEIGHT)).into_drawing_area();
+        root.fill(&WHITE)?;
+        let mut chart = ChartBuilder::on(&root).margin(6).build_cartesian_2d(x_range, y_range)?;
+        draw(&mut chart)?;
+        root.present()?;
+        Ok(())
+    })();
+    
+    let png = rendered.and_then(|()| Ok(std::fs::read(&path)?));
+    let _ = std::fs::remove_file(&path);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+    use crate::detection::{DetectionType, SensorContribution};
+    use crate::sensors::{SensorReading, SensorType};
+    
+    fn detection(severity: Severity) -> Detection {
+        Detection {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            detection_type: DetectionType::EMFSpike,
+            confidence: 0.7,
+            uncertainty: 0.0,
+            severity,
+            sensors: vec![SensorContribution {
+                sensor_id: "emf-1".to_string(),
+                sensor_type: SensorType::EMFProbe,
+                weight: 1.0,
+                reading_value: 4.0,
+                anomaly_score: 0.9,
+            }],
+            entropy_deviation: 0.0,
+            anomaly_count: 1,
+            correlation_score: 0.0,
+            classification: None,
+            location: None,
+            payload: None,
+            data_window_start: Utc::now(),
+            data_window_end: Utc::now(),
+        }
+    }
+    
+    #[test]
+    fn test_report_for_small_session() {
+        let config = DatabaseConfig {
+            path: std::env::temp_dir()
+                .join(format!("glowbarn-report-{}", uuid::Uuid::new_v4()))
+                .join("glowbarn.db"),
+            ..Default::default()
+        };
+        let db = Database::open(&config, None).unwrap();
+        let session = db.start_session(Some("attic")).unwrap();
+        for i in 0..20 {
+            let data = (0..64).map(|j| ((i * 64 + j) as f64 * 0.3).sin()).collect();
+            db.store_reading(&SensorReading::new("emf-1", SensorType::EMFProbe, data)).unwrap();
+        }
+        for severity in [Severity::Low, Severity::High, Severity::Critical] {
+            let detection = detection(severity);
+            db.store_detection(&detection).unwrap();
+            if severity == Severity::High {
+                db.annotate_detection(&detection.id, AnnotationStatus::FalsePositive, Some("Microwave <on>")).unwrap();
+            }
+        }
+        db.end_session(&session, 20, 3).unwrap();
+        
+        let path = Report::generate(&db, &session).unwrap();
+        assert_eq!(path, config.path.parent().unwrap().join("reports").join(format!("session-{}.html", session)));
+        let html = std::fs::read_to_string(&path).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>") && html.trim_end().ends_with("</html>"));
+        assert!(html.contains("<td id=\"detection-count\">3</td>"), "{}", html);
+        assert!(html.contains("<td>false_positive: Microwave &lt;on&gt;</td>"), "{}", html);
+        assert!(html.contains("<h3>emf-1"));
+        assert!(html.contains("data:image/png;base64,iVBORw0KGgo"));
+        
+        assert!(Report::generate(&db, "no-such-session").is_err());
+        drop(db);
+        let _ = std::fs::remove_dir_all(config.path.parent().unwrap());
+    }
+    
+    #[test]
+    fn test_long_sensor_history_is_gathered_in_bounded_memory() {
+        let mut accumulator = SensorAccumulator::new("EMFProbe");
+        let readings = 5 * SERIES_POINTS;
+        for i in 0..readings {
+            let values: Vec<f64> = (0..64).map(|j| ((i * 64 + j) % 1000) as f64).collect();
+            accumulator.push(i as f64, values.iter().sum::<f64>() / 64.0, &values);
+        }
+        assert_eq!(accumulator.sample.len(), SUMMARY_SAMPLES);
+        assert_eq!(accumulator.recent.len(), ENTROPY_SAMPLES.max(COMPLEXITY_SAMPLES));
+        
+        let section = accumulator.finish("emf-1".to_string());
+        assert_eq!(section.readings, readings);
+        assert!((SERIES_POINTS..2 * SERIES_POINTS).contains(&section.series.len()), "{} points", section.series.len());
+        assert!(section.series.windows(2).all(|w| w[0].0 < w[1].0));
+        
+        // Values cycle evenly through 0..1000
+        let s = &section.summary;
+        assert_eq!(s.count, readings * 64);
+        assert!((s.mean - 499.5).abs() < 1e-6, "mean {}", s.mean);
+        assert_eq!((s.min, s.max), (0.0, 999.0));
+        assert!((s.median - 499.5).abs() < 15.0, "median {}", s.median);
+    }
+}
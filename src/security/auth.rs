@@ -6,13 +6,18 @@
 
 use anyhow::{anyhow, Result};
 use argon2::{
-    Argon2,
+    Algorithm, Argon2, Params, Version,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
+
+use super::totp;
+
+/// Allowed clock drift for TOTP codes, in 30 second steps
+const TOTP_SKEW: u8 = 1;
 
 /// Authentication manager
 pub struct AuthManager {
@@ -30,6 +35,15 @@ pub struct AuthManager {
     
     /// Minimum password length
     min_password_length: usize,
+    
+    /// Idle timeout applied to new and refreshed sessions
+    session_timeout: Duration,
+    
+    /// Base32 TOTP secrets for users enrolled in 2FA
+    totp_secrets: HashMap<String, Zeroizing<String>>,
+    
+    /// Argon2id cost parameters for new password hashes
+    argon2_params: Params,
 }
 
 /// User session
@@ -52,6 +66,19 @@ pub struct PasswordStrength {
     pub acceptable: bool,
 }
 
+/// Verify a password against an Argon2 PHC string
+///
+/// Needs no `AuthManager` state, so callers can run it outside any lock.
+pub fn verify_password_hash(password: &str, hash: &str) -> Result<bool> {
+    let parsed_hash = PasswordHash::new(hash)
+        .map_err(|e| anyhow!("Invalid hash format: {}", e))?;
+    
+    // Verification takes the algorithm and parameters from the hash itself
+    let argon2 = Argon2::default();
+    
+    Ok(argon2.verify_password(password.as_bytes(), &parsed_hash).is_ok())
+}
+
 impl AuthManager {
     pub fn new(min_password_length: usize) -> Self {
         Self {
@@ -60,14 +87,32 @@ impl AuthManager {
             lockout_threshold: 5,
             lockout_duration: Duration::minutes(15),
             min_password_length,
+            session_timeout: Duration::hours(1),
+            totp_secrets: HashMap::new(),
+            argon2_params: Params::default(),
         }
     }
     
+    /// Set the session timeout used by `start_session` and `refresh_session`
+    pub fn with_session_timeout(mut self, timeout_secs: u64) -> Self {
+        self.session_timeout = Duration::seconds(timeout_secs as i64);
+        self
+    }
+    
+    /// Set the Argon2id cost parameters used by `hash_password`
+    pub fn with_argon2_params(mut self, params: Params) -> Self {
+        self.argon2_params = params;
+        self
+    }
+    
     /// Hash password using Argon2id
+    ///
+    /// The PHC string records the cost parameters, so hashes made under
+    /// earlier settings still verify after they change.
     pub fn hash_password(&self, password: &str) -> Result<String> {
         let salt = SaltString::generate(&mut OsRng);
         
-        let argon2 = Argon2::default();
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, self.argon2_params.clone());
         
         let hash = argon2.hash_password(password.as_bytes(), &salt)
             .map_err(|e| anyhow!("Password hashing failed: {}", e))?;
@@ -77,12 +122,7 @@ impl AuthManager {
     
     /// Verify password against hash
     pub fn verify_password(&self, password: &str, hash: &str) -> Result<bool> {
-        let parsed_hash = PasswordHash::new(hash)
-            .map_err(|e| anyhow!("Invalid hash format: {}", e))?;
-        
-        let argon2 = Argon2::default();
-        
-        Ok(argon2.verify_password(password.as_bytes(), &parsed_hash).is_ok())
+        verify_password_hash(password, hash)
     }
     
     /// Check password strength
@@ -182,12 +222,18 @@ impl AuthManager {
     
     /// Record failed login attempt
     pub fn record_failed_attempt(&mut self, identifier: &str) {
+        let now = Utc::now();
         let entry = self.failed_attempts
             .entry(identifier.to_string())
-            .or_insert((0, Utc::now()));
+            .or_insert((0, now));
+        
+        // Start counting afresh once a previous lockout window has passed
+        if now - entry.1 >= self.lockout_duration {
+            entry.0 = 0;
+        }
         
         entry.0 += 1;
-        entry.1 = Utc::now();
+        entry.1 = now;
     }
     
     /// Clear failed attempts on successful login
@@ -195,6 +241,86 @@ impl AuthManager {
         self.failed_attempts.remove(identifier);
     }
     
+    /// Verify a password and open a session, enforcing lockout
+    ///
+    /// Failed attempts are counted per user; once `lockout_threshold` is
+    /// reached the user is refused (even with the right password) until
+    /// `lockout_duration` has passed.
+    pub fn authenticate(&mut self, user_id: &str, password: &str, hash: &str) -> Result<Session> {
+        self.authenticate_with_totp(user_id, password, hash, None)
+    }
+    
+    /// Like `authenticate`, additionally requiring a valid TOTP code for
+    /// users enrolled in 2FA. A missing or wrong code counts as a failed attempt.
+    pub fn authenticate_with_totp(
+        &mut self,
+        user_id: &str,
+        password: &str,
+        hash: &str,
+        totp_code: Option<&str>,
+    ) -> Result<Session> {
+        if self.is_locked_out(user_id) {
+            return Err(anyhow!("Account {} is locked out", user_id));
+        }
+        
+        let password_ok = self.verify_password(password, hash)?;
+        self.finish_authentication(user_id, password_ok, totp_code)
+    }
+    
+    /// Second half of `authenticate_with_totp`, for callers that verified the
+    /// password themselves (e.g. without holding a lock around Argon2).
+    /// Lockout is checked again since other attempts may have landed meanwhile.
+    pub fn finish_authentication(
+        &mut self,
+        user_id: &str,
+        password_ok: bool,
+        totp_code: Option<&str>,
+    ) -> Result<Session> {
+        if self.is_locked_out(user_id) {
+            return Err(anyhow!("Account {} is locked out", user_id));
+        }
+        
+        if !password_ok {
+            self.record_failed_attempt(user_id);
+            return Err(anyhow!("Invalid credentials for {}", user_id));
+        }
+        
+        if let Some(secret) = self.totp_secrets.get(user_id) {
+            let valid = totp_code
+                .map(|code| totp::verify_totp(secret, code, TOTP_SKEW))
+                .unwrap_or(false);
+            
+            if !valid {
+                self.record_failed_attempt(user_id);
+                return Err(anyhow!("Invalid or missing TOTP code for {}", user_id));
+            }
+        }
+        
+        self.clear_failed_attempts(user_id);
+        Ok(self.start_session(user_id))
+    }
+    
+    /// Require a TOTP code for this user on future logins
+    pub fn enable_totp(&mut self, user_id: &str, secret: &str) {
+        self.totp_secrets.insert(user_id.to_string(), Zeroizing::new(secret.to_string()));
+    }
+    
+    /// Stop requiring a TOTP code for this user
+    pub fn disable_totp(&mut self, user_id: &str) {
+        self.totp_secrets.remove(user_id);
+    }
+    
+    /// Whether the user is enrolled in TOTP 2FA
+    pub fn requires_totp(&self, user_id: &str) -> bool {
+        self.totp_secrets.contains_key(user_id)
+    }
+    
+    /// Create a session using the configured session timeout
+    pub fn start_session(&mut self, user_id: &str) -> Session {
+        let timeout = self.session_timeout.num_seconds().max(0) as u64;
+        self.create_session(user_id, timeout, None, None)
+    }
+    
     /// Create new session
     pub fn create_session(
         &mut self, 
@@ -231,6 +357,26 @@ impl AuthManager {
         })
     }
     
+    /// Whether the session exists, is active and has not expired
+    pub fn is_session_valid(&self, session_id: &str) -> bool {
+        self.validate_session(session_id).is_some()
+    }
+    
+    /// Extend a valid session by the session timeout (sliding expiry)
+    pub fn refresh_session(&mut self, session_id: &str) -> Option<Session> {
+        let now = Utc::now();
+        let timeout = self.session_timeout;
+        
+        self.sessions.get_mut(session_id).and_then(|session| {
+            if session.is_active && session.expires_at > now {
+                session.expires_at = now + timeout;
+                Some(session.clone())
+            } else {
+                None
+            }
+        })
+    }
+    
     /// Invalidate session
     pub fn invalidate_session(&mut self, session_id: &str) -> bool {
         if let Some(session) = self.sessions.get_mut(session_id) {
@@ -252,10 +398,17 @@ impl AuthManager {
     
     /// Cleanup expired sessions
     pub fn cleanup_sessions(&mut self) {
+        self.reap_expired();
+    }
+    
+    /// Remove expired and invalidated sessions, returning how many were dropped
+    pub fn reap_expired(&mut self) -> usize {
         let now = Utc::now();
+        let before = self.sessions.len();
         self.sessions.retain(|_, session| {
-            session.expires_at > now
+            session.is_active && session.expires_at > now
         });
+        before - self.sessions.len()
     }
     
     /// Get active sessions for user
@@ -297,6 +450,21 @@ mod tests {
         assert!(!auth.verify_password("WrongPassword", &hash).unwrap());
     }
     
+    #[test]
+    fn test_custom_argon2_params_are_recorded() {
+        let params = Params::new(8 * 1024, 3, 2, None).unwrap();
+        let auth = AuthManager::new(12).with_argon2_params(params);
+        let password = "SecurePassword123!";
+        
+        let hash = auth.hash_password(password).unwrap();
+        assert!(hash.starts_with("$argon2id$v=19$m=8192,t=3,p=2$"), "{}", hash);
+        
+        // A manager configured differently still verifies from the stored parameters
+        let other = AuthManager::new(12);
+        assert!(other.verify_password(password, &hash).unwrap());
+        assert!(!other.verify_password("WrongPassword", &hash).unwrap());
+    }
+    
     #[test]
     fn test_password_strength() {
         let auth = AuthManager::new(12);
@@ -319,4 +487,56 @@ mod tests {
         auth.invalidate_session(&session.id);
         assert!(auth.validate_session(&session.id).is_none());
     }
+    
+    #[test]
+    fn test_session_expiry() {
+        let mut auth = AuthManager::new(12).with_session_timeout(0);
+        
+        let session = auth.start_session("user1");
+        assert!(!auth.is_session_valid(&session.id));
+        assert!(auth.refresh_session(&session.id).is_none());
+        assert_eq!(auth.reap_expired(), 1);
+        
+        let mut auth = AuthManager::new(12).with_session_timeout(60);
+        let session = auth.start_session("user1");
+        let refreshed = auth.refresh_session(&session.id).unwrap();
+        assert!(refreshed.expires_at >= session.expires_at);
+        assert_eq!(auth.reap_expired(), 0);
+    }
+    
+    #[test]
+    fn test_totp_required_when_enrolled() {
+        let mut auth = AuthManager::new(12);
+        let hash = auth.hash_password("SecurePassword123!").unwrap();
+        let secret = totp::generate_secret();
+        auth.enable_totp("user1", &secret);
+        
+        assert!(auth.authenticate("user1", "SecurePassword123!", &hash).is_err());
+        assert!(auth.authenticate_with_totp("user1", "SecurePassword123!", &hash, Some("000000x")).is_err());
+        
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let key = totp::base32_decode(&secret).unwrap();
+        let code = totp::generate_code(&key, now, totp::TOTP_DIGITS);
+        assert!(auth.authenticate_with_totp("user1", "SecurePassword123!", &hash, Some(&code)).is_ok());
+    }
+    
+    #[test]
+    fn test_lockout_after_failed_attempts() {
+        let mut auth = AuthManager::new(12);
+        let hash = auth.hash_password("SecurePassword123!").unwrap();
+        
+        for _ in 0..5 {
+            assert!(auth.authenticate("user1", "wrong", &hash).is_err());
+        }
+        
+        assert!(auth.is_locked_out("user1"));
+        assert!(auth.authenticate("user1", "SecurePassword123!", &hash).is_err());
+        
+        // Other users are unaffected
+        let session = auth.authenticate("user2", "SecurePassword123!", &hash).unwrap();
+        assert!(auth.is_session_valid(&session.id));
+    }
 }
@@ -7,10 +7,24 @@
 use aes_gcm::{
     Aes256Gcm,
     Key, Nonce,
-    aead::{Aead, KeyInit, OsRng, rand_core::RngCore},
+    aead::{Aead, KeyInit, OsRng, Payload, rand_core::RngCore},
 };
-use anyhow::{anyhow, Result};
-use zeroize::Zeroizing;
+use anyhow::{anyhow, bail, Result};
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Bytes of the key id a `KeyRing` ciphertext starts with
+pub const KEY_ID_LEN: usize = 4;
+
+/// Plaintext bytes per chunk of an encrypted stream
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Random nonce prefix written at the start of an encrypted stream
+const STREAM_PREFIX_LEN: usize = 8;
+
+/// Chunk flag marking the last chunk, so truncation is detected
+const STREAM_FINAL: u8 = 1;
 
 /// AES-256-GCM cipher
 pub struct AesGcmCipher {
@@ -30,6 +44,33 @@ impl AesGcmCipher {
         Self { key: Zeroizing::new(key) }
     }
     
+    /// Derive the key from a passphrase with Argon2id
+    ///
+    /// The same passphrase, salt and time cost always yield the same key, so
+    /// data encrypted in one run can be decrypted in the next. Only the salt
+    /// needs to be kept (see `KeyStore::passphrase_salt`). Memory cost and
+    /// lanes are Argon2's defaults; use `from_passphrase_with_params` to
+    /// set them.
+    pub fn from_passphrase(passphrase: &str, salt: &[u8], kdf_iterations: u32) -> Result<Self> {
+        if !super::KDF_TIME_COST_RANGE.contains(&kdf_iterations) {
+            bail!("KDF iterations must be in {:?} (got {})", super::KDF_TIME_COST_RANGE, kdf_iterations);
+        }
+        let params = argon2::Params::new(
+            argon2::Params::DEFAULT_M_COST,
+            kdf_iterations,
+            argon2::Params::DEFAULT_P_COST,
+            None,
+        ).map_err(|e| anyhow!("Argon2 params error: {}", e))?;
+        Self::from_passphrase_with_params(passphrase, salt, &params)
+    }
+    
+    /// Derive the key from a passphrase with explicit Argon2id parameters,
+    /// e.g. `SecurityConfig::argon2_params`
+    pub fn from_passphrase_with_params(passphrase: &str, salt: &[u8], params: &argon2::Params) -> Result<Self> {
+        let key = super::derive_key(passphrase, salt, params)?;
+        Ok(Self { key })
+    }
+    
     /// Encrypt plaintext
     /// Returns: nonce (12 bytes) || ciphertext || tag (16 bytes)
     pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
@@ -72,12 +113,281 @@ impl AesGcmCipher {
         Ok(plaintext)
     }
     
+    /// Encrypt everything `reader` yields into `writer`, one chunk at a time
+    ///
+    /// Format: nonce prefix (8 bytes), then per chunk a flag byte, the
+    /// sealed length (u32 LE) and the sealed chunk. Each chunk's nonce is
+    /// the prefix followed by the chunk index, and the index and flag are
+    /// authenticated as associated data, so chunks can't be reordered,
+    /// dropped or truncated. Memory use is bounded by `STREAM_CHUNK_SIZE`.
+    /// Returns the number of plaintext bytes encrypted.
+    pub fn encrypt_stream<R: Read, W: Write>(&self, mut reader: R, mut writer: W) -> Result<u64> {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&*self.key));
+        
+        let mut prefix = [0u8; STREAM_PREFIX_LEN];
+        OsRng.fill_bytes(&mut prefix);
+        writer.write_all(&prefix)?;
+        
+        let mut chunk = Zeroizing::new(vec![0u8; STREAM_CHUNK_SIZE]);
+        let mut total = 0u64;
+        for index in 0..=u32::MAX {
+            let n = read_full(&mut reader, &mut chunk)?;
+            // A short read means the input is exhausted
+            let flag = if n < STREAM_CHUNK_SIZE { STREAM_FINAL } else { 0 };
+            
+            let aad = stream_aad(index, flag);
+            let sealed = cipher.encrypt(&stream_nonce(&prefix, index), Payload { msg: &chunk[..n], aad: &aad })
+                .map_err(|e| anyhow!("Encryption failed: {}", e))?;
+            
+            writer.write_all(&[flag])?;
+            writer.write_all(&(sealed.len() as u32).to_le_bytes())?;
+            writer.write_all(&sealed)?;
+            total += n as u64;
+            
+            if flag == STREAM_FINAL {
+                writer.flush()?;
+                return Ok(total);
+            }
+        }
+        
+        bail!("Stream too long to encrypt")
+    }
+    
+    /// Decrypt a stream written by `encrypt_stream` into `writer`
+    ///
+    /// Fails on any tampered, reordered or missing chunk; plaintext of the
+    /// chunks before the failure may already have been written.
+    /// Returns the number of plaintext bytes written.
+    pub fn decrypt_stream<R: Read, W: Write>(&self, mut reader: R, mut writer: W) -> Result<u64> {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&*self.key));
+        
+        let mut prefix = [0u8; STREAM_PREFIX_LEN];
+        reader.read_exact(&mut prefix)
+            .map_err(|e| anyhow!("Encrypted stream header missing: {}", e))?;
+        
+        let mut total = 0u64;
+        for index in 0..=u32::MAX {
+            let mut header = [0u8; 5];
+            if read_full(&mut reader, &mut header)? < header.len() {
+                bail!("Encrypted stream truncated after {} chunks", index);
+            }
+            let flag = header[0];
+            let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+            if len > STREAM_CHUNK_SIZE + 16 {
+                bail!("Encrypted chunk {} has invalid length {}", index, len);
+            }
+            
+            let mut sealed = vec![0u8; len];
+            reader.read_exact(&mut sealed)
+                .map_err(|e| anyhow!("Encrypted chunk {} truncated: {}", index, e))?;
+            
+            let aad = stream_aad(index, flag);
+            let plaintext = Zeroizing::new(
+                cipher.decrypt(&stream_nonce(&prefix, index), Payload { msg: &sealed, aad: &aad })
+                    .map_err(|e| anyhow!("Decryption of chunk {} failed: {}", index, e))?,
+            );
+            writer.write_all(&plaintext)?;
+            total += plaintext.len() as u64;
+            
+            if flag == STREAM_FINAL {
+                writer.flush()?;
+                return Ok(total);
+            }
+        }
+        
+        bail!("Encrypted stream has no final chunk")
+    }
+    
     /// Get key (for secure storage)
     pub fn get_key(&self) -> &[u8; 32] {
         &self.key
     }
 }
 
+/// Versioned set of AES-256-GCM keys
+///
+/// Ciphertexts are tagged with the id of the key that produced them:
+/// key id (u32 BE) || nonce (12 bytes) || ciphertext || tag (16 bytes).
+/// Rotation adds a new active key; retired keys stay in the ring so
+/// whatever they sealed stays readable. Use `seal`/`unseal` to keep the
+/// ring across restarts.
+pub struct KeyRing {
+    /// Oldest first, active key last
+    keys: VecDeque<(u32, AesGcmCipher)>,
+}
+
+impl KeyRing {
+    /// Create ring with a single random key
+    pub fn new() -> Result<Self> {
+        let mut keys = VecDeque::new();
+        keys.push_back((0, AesGcmCipher::new()?));
+        Ok(Self { keys })
+    }
+    
+    /// Create ring with a single provided key
+    pub fn with_key(key: [u8; 32]) -> Self {
+        Self::with_cipher(AesGcmCipher::with_key(key))
+    }
+    
+    /// Create ring around an existing cipher, e.g. one derived from a passphrase
+    pub fn with_cipher(cipher: AesGcmCipher) -> Self {
+        let mut keys = VecDeque::new();
+        keys.push_back((0, cipher));
+        Self { keys }
+    }
+    
+    /// Id of the key used for new encryptions
+    pub fn active_id(&self) -> u32 {
+        self.keys.back().map(|(id, _)| *id).unwrap_or(0)
+    }
+    
+    /// Ids of all keys available for decryption
+    pub fn versions(&self) -> Vec<u32> {
+        self.keys.iter().map(|(id, _)| *id).collect()
+    }
+    
+    /// Generate a new random key and make it active
+    pub fn rotate(&mut self) -> Result<u32> {
+        let cipher = AesGcmCipher::new()?;
+        self.push(cipher)
+    }
+    
+    /// Make the provided key active
+    pub fn rotate_to(&mut self, key: [u8; 32]) -> Result<u32> {
+        self.push(AesGcmCipher::with_key(key))
+    }
+    
+    /// Drop the active key, making the one before it active again; used to
+    /// back out a rotation that couldn't be persisted
+    pub fn undo_rotate(&mut self) -> Result<()> {
+        if self.keys.len() < 2 {
+            bail!("No earlier key to fall back to");
+        }
+        self.keys.pop_back();
+        Ok(())
+    }
+    
+    fn push(&mut self, cipher: AesGcmCipher) -> Result<u32> {
+        let id = self.active_id().checked_add(1)
+            .ok_or_else(|| anyhow!("Key ids exhausted"))?;
+        self.keys.push_back((id, cipher));
+        Ok(id)
+    }
+    
+    fn cipher(&self, id: u32) -> Result<&AesGcmCipher> {
+        self.keys.iter()
+            .find(|(k, _)| *k == id)
+            .map(|(_, cipher)| cipher)
+            .ok_or_else(|| anyhow!("Unknown key version {}", id))
+    }
+    
+    /// Encrypt with the active key
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let (id, cipher) = self.keys.back()
+            .ok_or_else(|| anyhow!("Key ring is empty"))?;
+        
+        let sealed = cipher.encrypt(plaintext)?;
+        let mut result = Vec::with_capacity(KEY_ID_LEN + sealed.len());
+        result.extend_from_slice(&id.to_be_bytes());
+        result.extend_from_slice(&sealed);
+        Ok(result)
+    }
+    
+    /// Decrypt with the key named by the ciphertext's id
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let id = Self::key_id_of(data)
+            .ok_or_else(|| anyhow!("Ciphertext too short"))?;
+        
+        self.cipher(id)?.decrypt(&data[KEY_ID_LEN..])
+    }
+    
+    /// Key id embedded in a ciphertext
+    pub fn key_id_of(data: &[u8]) -> Option<u32> {
+        let id = data.get(..KEY_ID_LEN)?;
+        Some(u32::from_be_bytes(id.try_into().ok()?))
+    }
+    
+    /// Stream-encrypt with the active key: key id (u32 BE), then the
+    /// `AesGcmCipher::encrypt_stream` format
+    pub fn encrypt_stream<R: Read, W: Write>(&self, reader: R, mut writer: W) -> Result<u64> {
+        let (id, cipher) = self.keys.back()
+            .ok_or_else(|| anyhow!("Key ring is empty"))?;
+        
+        writer.write_all(&id.to_be_bytes())?;
+        cipher.encrypt_stream(reader, writer)
+    }
+    
+    /// Stream-decrypt with the key named by the stream's id
+    pub fn decrypt_stream<R: Read, W: Write>(&self, mut reader: R, writer: W) -> Result<u64> {
+        let mut id = [0u8; KEY_ID_LEN];
+        reader.read_exact(&mut id)
+            .map_err(|e| anyhow!("Encrypted stream header missing: {}", e))?;
+        
+        self.cipher(u32::from_be_bytes(id))?.decrypt_stream(reader, writer)
+    }
+    
+    /// Every key, retired ones included, encrypted under `wrapping`
+    ///
+    /// The plaintext is each key's id (u32 BE) followed by its 32 bytes,
+    /// oldest first.
+    pub fn seal(&self, wrapping: &AesGcmCipher) -> Result<Vec<u8>> {
+        let mut plaintext = Zeroizing::new(Vec::with_capacity(self.keys.len() * (KEY_ID_LEN + 32)));
+        for (id, cipher) in &self.keys {
+            plaintext.extend_from_slice(&id.to_be_bytes());
+            plaintext.extend_from_slice(cipher.get_key());
+        }
+        wrapping.encrypt(&plaintext)
+    }
+    
+    /// Ring written by `seal`; fails if `wrapping` isn't the key it was sealed under
+    pub fn unseal(sealed: &[u8], wrapping: &AesGcmCipher) -> Result<Self> {
+        let plaintext = Zeroizing::new(wrapping.decrypt(sealed)?);
+        if plaintext.is_empty() || plaintext.len() % (KEY_ID_LEN + 32) != 0 {
+            bail!("Sealed key ring is malformed");
+        }
+        
+        let keys = plaintext.chunks_exact(KEY_ID_LEN + 32)
+            .map(|entry| {
+                let (id, key) = entry.split_at(KEY_ID_LEN);
+                let mut bytes = [0u8; 32];
+                bytes.copy_from_slice(key);
+                let cipher = AesGcmCipher::with_key(bytes);
+                bytes.zeroize();
+                (u32::from_be_bytes(id.try_into().unwrap()), cipher)
+            })
+            .collect();
+        Ok(Self { keys })
+    }
+}
+
+/// Nonce for chunk `index` of a stream: prefix || index (u32 BE)
+fn stream_nonce(prefix: &[u8; STREAM_PREFIX_LEN], index: u32) -> Nonce<aes_gcm::aead::consts::U12> {
+    let mut nonce = [0u8; 12];
+    nonce[..STREAM_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[STREAM_PREFIX_LEN..].copy_from_slice(&index.to_be_bytes());
+    nonce.into()
+}
+
+/// Associated data binding a chunk to its position and final flag
+fn stream_aad(index: u32, flag: u8) -> [u8; 5] {
+    let [a, b, c, d] = index.to_be_bytes();
+    [a, b, c, d, flag]
+}
+
+/// Read until `buf` is full or the reader is exhausted
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
+
 /// ChaCha20-Poly1305 cipher (alternative)
 pub struct ChaCha20Cipher {
     key: Zeroizing<[u8; 32]>,
@@ -165,6 +475,82 @@ mod tests {
         assert_eq!(&decrypted, plaintext);
     }
     
+    #[test]
+    fn test_passphrase_ciphers_share_a_key() {
+        let salt = [7u8; 32];
+        let writer = AesGcmCipher::from_passphrase("correct horse battery staple", &salt, 1).unwrap();
+        let ciphertext = writer.encrypt(b"stored reading").unwrap();
+        
+        // A separately derived cipher, as after a restart
+        let reader = AesGcmCipher::from_passphrase("correct horse battery staple", &salt, 1).unwrap();
+        assert_eq!(reader.decrypt(&ciphertext).unwrap(), b"stored reading");
+        
+        let wrong = AesGcmCipher::from_passphrase("wrong passphrase", &salt, 1).unwrap();
+        assert!(wrong.decrypt(&ciphertext).is_err());
+    }
+    
+    #[test]
+    fn test_key_rotation_keeps_old_ciphertexts_readable() {
+        let mut ring = KeyRing::new().unwrap();
+        let old = ring.encrypt(b"before rotation").unwrap();
+        assert_eq!(KeyRing::key_id_of(&old), Some(0));
+        
+        let new_id = ring.rotate().unwrap();
+        assert_eq!(new_id, 1);
+        let new = ring.encrypt(b"after rotation").unwrap();
+        assert_eq!(KeyRing::key_id_of(&new), Some(1));
+        
+        assert_eq!(ring.decrypt(&old).unwrap(), b"before rotation");
+        assert_eq!(ring.decrypt(&new).unwrap(), b"after rotation");
+        
+        // Retired keys are kept however often the key rotates, and past
+        // the 256 ids a single byte could name
+        for _ in 0..300 {
+            ring.rotate().unwrap();
+        }
+        assert_eq!(ring.active_id(), 301);
+        assert_eq!(ring.decrypt(&old).unwrap(), b"before rotation");
+        
+        // Sealed and restored, e.g. across a restart
+        let wrapping = AesGcmCipher::new().unwrap();
+        let restored = KeyRing::unseal(&ring.seal(&wrapping).unwrap(), &wrapping).unwrap();
+        assert_eq!(restored.versions(), ring.versions());
+        assert_eq!(restored.decrypt(&new).unwrap(), b"after rotation");
+        assert!(KeyRing::unseal(&ring.seal(&wrapping).unwrap(), &AesGcmCipher::new().unwrap()).is_err());
+    }
+    
+    #[test]
+    fn test_stream_round_trip() {
+        use rand::{Rng, SeedableRng};
+        
+        let ring = KeyRing::new().unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        // Several chunks plus a partial one
+        let mut original = vec![0u8; 3 * 1024 * 1024 + 123];
+        rng.fill(&mut original[..]);
+        
+        let mut sealed = Vec::new();
+        assert_eq!(ring.encrypt_stream(&original[..], &mut sealed).unwrap(), original.len() as u64);
+        
+        let mut restored = Vec::new();
+        assert_eq!(ring.decrypt_stream(&sealed[..], &mut restored).unwrap(), original.len() as u64);
+        assert_eq!(restored, original);
+        
+        // Dropping the final chunk is detected
+        let truncated = &sealed[..sealed.len() - 200];
+        assert!(ring.decrypt_stream(truncated, &mut Vec::new()).is_err());
+        
+        // So is swapping two chunks
+        let record = 5 + STREAM_CHUNK_SIZE + 16;
+        let body = KEY_ID_LEN + STREAM_PREFIX_LEN;
+        let mut swapped = sealed.clone();
+        swapped[body..body + record].copy_from_slice(&sealed[body + record..body + 2 * record]);
+        swapped[body + record..body + 2 * record].copy_from_slice(&sealed[body..body + record]);
+        // Records are aligned, so the first one parses and fails authentication
+        let error = format!("{:#}", ring.decrypt_stream(&swapped[..], &mut Vec::new()).unwrap_err());
+        assert!(error.contains("Decryption of chunk 0 failed"), "{}", error);
+    }
+    
     #[test]
     fn test_chacha20_encrypt_decrypt() {
         let cipher = ChaCha20Cipher::new().unwrap();
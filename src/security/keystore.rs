@@ -4,14 +4,21 @@
 
 //! Secure key storage
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use zeroize::{Zeroize, Zeroizing};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use super::encryption::AesGcmCipher;
+use super::encryption::{AesGcmCipher, KeyRing};
+
+/// Length of key derivation salts
+pub const SALT_LEN: usize = 32;
+
+/// Known plaintext sealed under the storage key to verify a passphrase
+const VERIFIER_PLAINTEXT: &[u8] = b"glowbarn storage key";
 
 /// Key store for managing encryption keys
 pub struct KeyStore {
@@ -23,6 +30,13 @@ pub struct KeyStore {
     
     /// Storage path
     path: Option<PathBuf>,
+    
+    /// Versioned data encryption keys (active key plus retired ones)
+    data_keys: RwLock<KeyRing>,
+    
+    /// Where `data_keys` is kept sealed, and the key sealing it; set by
+    /// `unlock_data_key`
+    keyring_file: RwLock<Option<(AesGcmCipher, PathBuf)>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,20 +72,167 @@ impl KeyStore {
             keys: HashMap::new(),
             master_key: None,
             path: None,
+            data_keys: RwLock::new(KeyRing::new()?),
+            keyring_file: RwLock::new(None),
         })
     }
     
+    /// Encrypt data with the active data key
+    pub fn encrypt_data(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        self.data_keys.read().encrypt(plaintext)
+    }
+    
+    /// Decrypt data with whichever retained key produced it
+    pub fn decrypt_data(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        self.data_keys.read().decrypt(ciphertext)
+    }
+    
+    /// Stream-encrypt with the active data key
+    pub fn encrypt_data_stream<R: std::io::Read, W: std::io::Write>(&self, reader: R, writer: W) -> Result<u64> {
+        self.data_keys.read().encrypt_stream(reader, writer)
+    }
+    
+    /// Stream-decrypt with whichever retained key produced the stream
+    pub fn decrypt_data_stream<R: std::io::Read, W: std::io::Write>(&self, reader: R, writer: W) -> Result<u64> {
+        self.data_keys.read().decrypt_stream(reader, writer)
+    }
+    
+    /// Generate a new active data key, retaining old ones for decryption
+    ///
+    /// After `unlock_data_key` the whole ring is rewritten to its keyring
+    /// file first; if that fails the rotation is undone.
+    pub fn rotate_data_key(&self) -> Result<u32> {
+        let mut ring = self.data_keys.write();
+        let id = ring.rotate()?;
+        
+        if let Some((wrapping, path)) = self.keyring_file.read().as_ref() {
+            if let Err(e) = write_sealed_ring(&ring, wrapping, path) {
+                ring.undo_rotate()?;
+                return Err(e);
+            }
+        }
+        Ok(id)
+    }
+    
+    /// Id of the active data key
+    pub fn active_data_key_id(&self) -> u32 {
+        self.data_keys.read().active_id()
+    }
+    
+    /// Replace the data keys with the ones unlocked by a passphrase
+    ///
+    /// Unlike the random key from `new`, the derived key can be recreated
+    /// on the next start, so stored data stays readable across restarts.
+    /// The key is checked against the verifier at `verifier_path` (see
+    /// `check_passphrase`) before it is used for anything. Until the first
+    /// rotation the derived key is the only data key; after it, the ring
+    /// with every rotated key is kept at `keyring_path`, sealed under the
+    /// derived key.
+    pub fn unlock_data_key(
+        &self,
+        passphrase: &str,
+        salt: &[u8],
+        params: &argon2::Params,
+        verifier_path: &Path,
+        keyring_path: &Path,
+    ) -> Result<()> {
+        let cipher = AesGcmCipher::from_passphrase_with_params(passphrase, salt, params)?;
+        Self::check_passphrase(&cipher, verifier_path)?;
+        
+        let ring = if keyring_path.exists() {
+            KeyRing::unseal(&std::fs::read(keyring_path)?, &cipher)
+                .map_err(|e| anyhow!("Keyring {:?} can't be opened: {}", keyring_path, e))?
+        } else {
+            KeyRing::with_cipher(AesGcmCipher::with_key(*cipher.get_key()))
+        };
+        *self.data_keys.write() = ring;
+        *self.keyring_file.write() = Some((cipher, keyring_path.to_owned()));
+        Ok(())
+    }
+    
+    /// Check a passphrase-derived cipher against the verifier at `path`
+    ///
+    /// A wrong passphrase derives a different key without any error, so a
+    /// known value sealed under the right key is kept next to the salt. The
+    /// first call writes it; later calls reject a cipher that can't open it.
+    pub fn check_passphrase(cipher: &AesGcmCipher, path: &Path) -> Result<()> {
+        if path.exists() {
+            let sealed = std::fs::read(path)?;
+            return match cipher.decrypt(&sealed) {
+                Ok(plaintext) if plaintext == VERIFIER_PLAINTEXT => Ok(()),
+                _ => bail!("Wrong storage passphrase (or kdf_* settings changed since it was set)"),
+            };
+        }
+        
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, cipher.encrypt(VERIFIER_PLAINTEXT)?)?;
+        Ok(())
+    }
+    
+    /// Salt for passphrase key derivation, stored at `path`
+    ///
+    /// Only the salt is written - never the key. A new random salt is
+    /// created the first time; later calls return the stored one.
+    pub fn passphrase_salt(path: &Path) -> Result<[u8; SALT_LEN]> {
+        if path.exists() {
+            let data = std::fs::read(path)?;
+            return data.as_slice().try_into()
+                .map_err(|_| anyhow!("Salt file {:?} must be {} bytes, found {}", path, SALT_LEN, data.len()));
+        }
+        
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&super::secure_random_bytes(SALT_LEN));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, salt)?;
+        Ok(salt)
+    }
+    
+    /// Per-install secret kept at `path`, for keyed digests such as audit
+    /// pseudonyms
+    ///
+    /// Created from random bytes the first time, readable only by the
+    /// owner on Unix; later calls return the stored one.
+    pub fn install_secret(path: &Path) -> Result<Zeroizing<[u8; 32]>> {
+        use std::io::Write;
+        
+        if path.exists() {
+            let data = Zeroizing::new(std::fs::read(path)?);
+            let secret: [u8; 32] = data.as_slice().try_into()
+                .map_err(|_| anyhow!("Secret file {:?} must be 32 bytes, found {}", path, data.len()))?;
+            return Ok(Zeroizing::new(secret));
+        }
+        
+        let mut secret = Zeroizing::new([0u8; 32]);
+        secret.copy_from_slice(&super::secure_random_bytes(32));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(path)?.write_all(&*secret)?;
+        Ok(secret)
+    }
+    
     /// Initialize with master password
     pub fn init_with_password(&mut self, password: &str) -> Result<()> {
-        let salt = super::secure_random_bytes(32);
-        let key = derive_key(password, &salt, 100_000)?;
+        let salt = super::secure_random_bytes(SALT_LEN);
+        let key = derive_key(password, &salt, &argon2::Params::default())?;
         self.master_key = Some(key);
         Ok(())
     }
     
     /// Unlock with master password
     pub fn unlock(&mut self, password: &str, salt: &[u8; 32]) -> Result<()> {
-        let key = derive_key(password, salt, 100_000)?;
+        let key = derive_key(password, salt, &argon2::Params::default())?;
         self.master_key = Some(key);
         Ok(())
     }
@@ -162,47 +323,29 @@ impl KeyStore {
     }
 }
 
+/// Write `ring` sealed under `wrapping` to `path`, replacing it atomically
+fn write_sealed_ring(ring: &KeyRing, wrapping: &AesGcmCipher, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, ring.seal(wrapping)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
 /// Derive key from password using Argon2id
-pub fn derive_key(password: &str, salt: &[u8], iterations: u32) -> Result<Zeroizing<[u8; 32]>> {
-    use argon2::{
-        Argon2,
-        password_hash::{PasswordHasher, SaltString},
-        Params,
-    };
-    
-    // Configure Argon2id
-    let params = Params::new(
-        65536,           // memory cost (64 MB)
-        iterations.min(10), // time cost (iterations capped for Argon2)
-        4,               // parallelism
-        Some(32),        // output length
-    ).map_err(|e| anyhow!("Argon2 params error: {}", e))?;
-    
-    let argon2 = Argon2::new(
-        argon2::Algorithm::Argon2id,
-        argon2::Version::V0x13,
-        params,
-    );
-    
-    // Use salt to create a SaltString (needs base64 encoding)
-    let salt_b64 = base64::Engine::encode(
-        &base64::engine::general_purpose::STANDARD_NO_PAD, 
-        &salt[..22]  // SaltString needs 22 bytes
-    );
-    let salt_string = SaltString::from_b64(&salt_b64)
-        .map_err(|e| anyhow!("Salt error: {}", e))?;
-    
-    // Hash password
-    let hash = argon2.hash_password(password.as_bytes(), &salt_string)
-        .map_err(|e| anyhow!("Password hashing failed: {}", e))?;
+///
+/// `params` sets the time and memory cost and lanes, normally from
+/// `SecurityConfig::argon2_params`; `salt` needs at least 8 bytes.
+pub fn derive_key(password: &str, salt: &[u8], params: &argon2::Params) -> Result<Zeroizing<[u8; 32]>> {
+    use argon2::{Algorithm, Argon2, Version};
     
-    // Extract key from hash output
-    let hash_output = hash.hash
-        .ok_or_else(|| anyhow!("No hash output"))?;
-    let bytes = hash_output.as_bytes();
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone());
     
     let mut key = Zeroizing::new([0u8; 32]);
-    key.copy_from_slice(&bytes[..32]);
+    argon2.hash_password_into(password.as_bytes(), salt, &mut *key)
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
     
     Ok(key)
 }
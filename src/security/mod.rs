@@ -8,16 +8,32 @@ mod encryption;
 mod keystore;
 mod auth;
 mod secure_memory;
+pub mod totp;
 
 pub use encryption::*;
 pub use keystore::*;
 pub use auth::*;
 pub use secure_memory::*;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+use parking_lot::RwLock;
+use ring::hmac;
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::{Arc, Weak};
 use tracing::{info, warn};
 
+use crate::db::Database;
+
+/// Accepted Argon2id time cost (passes over memory)
+pub const KDF_TIME_COST_RANGE: std::ops::RangeInclusive<u32> = 1..=64;
+
+/// Accepted Argon2id memory cost in KiB (8 MiB to 4 GiB)
+pub const KDF_MEMORY_KIB_RANGE: std::ops::RangeInclusive<u32> = 8 * 1024..=4 * 1024 * 1024;
+
+/// Accepted Argon2id lane count
+pub const KDF_PARALLELISM_RANGE: std::ops::RangeInclusive<u32> = 1..=64;
+
 /// Security configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityConfig {
@@ -27,9 +43,17 @@ pub struct SecurityConfig {
     /// Enable encryption for network traffic
     pub encrypt_network: bool,
     
-    /// Key derivation iterations (higher = slower but more secure)
+    /// Argon2id time cost - passes over memory (higher = slower but more secure)
     pub kdf_iterations: u32,
     
+    /// Argon2id memory cost in KiB
+    #[serde(default = "default_kdf_memory_kib")]
+    pub kdf_memory_kib: u32,
+    
+    /// Argon2id parallelism (lanes)
+    #[serde(default = "default_kdf_parallelism")]
+    pub kdf_parallelism: u32,
+    
     /// Session timeout in seconds
     pub session_timeout_secs: u64,
     
@@ -38,6 +62,12 @@ pub struct SecurityConfig {
     
     /// Minimum password length
     pub min_password_length: usize,
+    
+    /// Pseudonymize IP addresses and user names in audit events before
+    /// they are logged or persisted (see `AuditEvent::redacted` and
+    /// `SecurityManager::load_pseudonym_secret`)
+    #[serde(default)]
+    pub redact_pii: bool,
 }
 
 impl Default for SecurityConfig {
@@ -45,61 +75,266 @@ impl Default for SecurityConfig {
         Self {
             encrypt_storage: true,
             encrypt_network: true,
-            kdf_iterations: 100_000,
+            kdf_iterations: argon2::Params::DEFAULT_T_COST,
+            kdf_memory_kib: default_kdf_memory_kib(),
+            kdf_parallelism: default_kdf_parallelism(),
             session_timeout_secs: 3600,  // 1 hour
             audit_logging: true,
             min_password_length: 12,
+            redact_pii: false,
+        }
+    }
+}
+
+fn default_kdf_memory_kib() -> u32 {
+    argon2::Params::DEFAULT_M_COST
+}
+
+fn default_kdf_parallelism() -> u32 {
+    argon2::Params::DEFAULT_P_COST
+}
+
+impl SecurityConfig {
+    /// Argon2id parameters for password hashing, rejecting values outside
+    /// the `KDF_*_RANGE` bounds
+    pub fn argon2_params(&self) -> Result<argon2::Params> {
+        if !KDF_TIME_COST_RANGE.contains(&self.kdf_iterations) {
+            bail!("kdf_iterations must be in {:?} (got {})", KDF_TIME_COST_RANGE, self.kdf_iterations);
         }
+        if !KDF_MEMORY_KIB_RANGE.contains(&self.kdf_memory_kib) {
+            bail!("kdf_memory_kib must be in {:?} (got {})", KDF_MEMORY_KIB_RANGE, self.kdf_memory_kib);
+        }
+        if !KDF_PARALLELISM_RANGE.contains(&self.kdf_parallelism) {
+            bail!("kdf_parallelism must be in {:?} (got {})", KDF_PARALLELISM_RANGE, self.kdf_parallelism);
+        }
+        
+        argon2::Params::new(self.kdf_memory_kib, self.kdf_iterations, self.kdf_parallelism, None)
+            .map_err(|e| anyhow!("Argon2 params error: {}", e))
     }
 }
 
 /// Security manager
 pub struct SecurityManager {
     config: SecurityConfig,
-    cipher: AesGcmCipher,
     keystore: KeyStore,
-    auth: AuthManager,
+    auth: RwLock<AuthManager>,
     audit: Option<AuditLog>,
 }
 
 impl SecurityManager {
     pub fn new(config: SecurityConfig) -> Result<Self> {
         let keystore = KeyStore::new()?;
-        let cipher = AesGcmCipher::new()?;
-        let auth = AuthManager::new(config.min_password_length);
+        let auth = RwLock::new(
+            AuthManager::new(config.min_password_length)
+                .with_session_timeout(config.session_timeout_secs)
+                .with_argon2_params(config.argon2_params()?),
+        );
         let audit = if config.audit_logging {
-            Some(AuditLog::new())
+            Some(AuditLog::new().with_redaction(config.redact_pii))
         } else {
             None
         };
         
         Ok(Self {
             config,
-            cipher,
             keystore,
             auth,
             audit,
         })
     }
     
-    /// Encrypt data with AES-256-GCM
+    /// Active security configuration
+    pub fn config(&self) -> &SecurityConfig {
+        &self.config
+    }
+    
+    /// Encrypt data with AES-256-GCM under the active key version
     pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
-        self.cipher.encrypt(plaintext)
+        self.keystore.encrypt_data(plaintext)
     }
     
-    /// Decrypt data
+    /// Decrypt data, selecting the key by the embedded key id
     pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
-        self.cipher.decrypt(ciphertext)
+        self.keystore.decrypt_data(ciphertext)
+    }
+    
+    /// Encrypt a stream chunk by chunk, for payloads too large to buffer
+    /// (exports, thermal BLOBs); returns the plaintext bytes consumed
+    pub fn encrypt_stream<R: std::io::Read, W: std::io::Write>(&self, reader: R, writer: W) -> Result<u64> {
+        self.keystore.encrypt_data_stream(reader, writer)
+    }
+    
+    /// Decrypt a stream written by `encrypt_stream`; returns the plaintext bytes written
+    pub fn decrypt_stream<R: std::io::Read, W: std::io::Write>(&self, reader: R, writer: W) -> Result<u64> {
+        self.keystore.decrypt_data_stream(reader, writer)
+    }
+    
+    /// Rotate the data encryption key
+    ///
+    /// New data is encrypted under a fresh key; previous keys are retained
+    /// so existing ciphertexts stay readable. After `unlock_storage` the
+    /// keys are persisted, so they survive restarts too. Use
+    /// `Database::re_encrypt_all` to migrate stored rows to the new key.
+    pub fn rotate_key(&mut self) -> Result<()> {
+        self.rotate_data_key().map(|_| ())
+    }
+    
+    /// `rotate_key` for a manager shared with the database; the key ring
+    /// is locked for the rotation. Returns the new key id.
+    pub fn rotate_data_key(&self) -> Result<u32> {
+        let result = self.keystore.rotate_data_key();
+        
+        self.log_audit(AuditEvent {
+            timestamp: chrono::Utc::now(),
+            event_type: AuditEventType::EncryptionOperation,
+            description: match result {
+                Ok(id) => format!("Rotated data encryption key to version {}", id),
+                Err(ref e) => format!("Data encryption key rotation failed: {}", e),
+            },
+            user: None,
+            ip_address: None,
+            success: result.is_ok(),
+        });
+        
+        result
+    }
+    
+    /// Derive the storage key from a passphrase so encrypted rows survive restarts
+    ///
+    /// The salt is kept at `salt_path` and a passphrase verifier beside it
+    /// with a `.verify` extension, both created on first use; the derived
+    /// key itself is never written. Keys from `rotate_key` are kept beside
+    /// them with a `.keys` extension, sealed under the derived key. A
+    /// passphrase that doesn't match the verifier is rejected. Argon2id
+    /// costs come from the `kdf_*` settings.
+    pub fn unlock_storage(&self, passphrase: &str, salt_path: &std::path::Path) -> Result<()> {
+        let salt = KeyStore::passphrase_salt(salt_path)?;
+        let params = self.config.argon2_params()?;
+        let result = self.keystore.unlock_data_key(
+            passphrase,
+            &salt,
+            &params,
+            &salt_path.with_extension("verify"),
+            &salt_path.with_extension("keys"),
+        );
+        
+        self.log_audit(AuditEvent {
+            timestamp: chrono::Utc::now(),
+            event_type: AuditEventType::EncryptionOperation,
+            description: "Derived storage key from passphrase".to_string(),
+            user: None,
+            ip_address: None,
+            success: result.is_ok(),
+        });
+        
+        result
+    }
+    
+    /// Id of the key used for new encryptions
+    pub fn active_key_id(&self) -> u32 {
+        self.keystore.active_data_key_id()
     }
     
     /// Hash password using Argon2id
     pub fn hash_password(&self, password: &str) -> Result<String> {
-        self.auth.hash_password(password)
+        self.auth.read().hash_password(password)
     }
     
     /// Verify password against hash
     pub fn verify_password(&self, password: &str, hash: &str) -> Result<bool> {
-        self.auth.verify_password(password, hash)
+        self.auth.read().verify_password(password, hash)
+    }
+    
+    /// Verify credentials and open a session, enforcing lockout
+    pub fn authenticate(&self, user_id: &str, password: &str, hash: &str) -> Result<Session> {
+        self.authenticate_with_totp(user_id, password, hash, None)
+    }
+    
+    /// Verify credentials plus a TOTP code for users enrolled in 2FA
+    pub fn authenticate_with_totp(
+        &self,
+        user_id: &str,
+        password: &str,
+        hash: &str,
+        totp_code: Option<&str>,
+    ) -> Result<Session> {
+        // Argon2 is slow on purpose; verify without holding the lock so other
+        // logins and session checks are not stalled behind it
+        let result = if self.auth.read().is_locked_out(user_id) {
+            Err(anyhow!("Account {} is locked out", user_id))
+        } else {
+            verify_password_hash(password, hash)
+                .and_then(|password_ok| self.auth.write().finish_authentication(user_id, password_ok, totp_code))
+        };
+        
+        self.log_audit(AuditEvent {
+            timestamp: chrono::Utc::now(),
+            event_type: if result.is_ok() { AuditEventType::Login } else { AuditEventType::AuthFailure },
+            description: match result {
+                Ok(_) => format!("User {} logged in", user_id),
+                Err(ref e) => e.to_string(),
+            },
+            user: Some(user_id.to_string()),
+            ip_address: None,
+            success: result.is_ok(),
+        });
+        
+        result
+    }
+    
+    /// Enroll a user in TOTP 2FA, returning the new base32 secret
+    pub fn enable_totp(&self, user_id: &str) -> String {
+        let secret = totp::generate_secret();
+        self.auth.write().enable_totp(user_id, &secret);
+        secret
+    }
+    
+    /// Remove a user's TOTP enrollment
+    pub fn disable_totp(&self, user_id: &str) {
+        self.auth.write().disable_totp(user_id);
+    }
+    
+    /// Create a session for an already-authenticated user
+    pub fn create_session(&self, user_id: &str) -> Session {
+        self.auth.write().start_session(user_id)
+    }
+    
+    /// Check whether a session is active and unexpired
+    pub fn validate_session(&self, session_id: &str) -> bool {
+        self.auth.read().is_session_valid(session_id)
+    }
+    
+    /// Extend a valid session by the configured timeout
+    pub fn refresh_session(&self, session_id: &str) -> Option<Session> {
+        self.auth.write().refresh_session(session_id)
+    }
+    
+    /// End a session
+    pub fn invalidate_session(&self, session_id: &str) -> bool {
+        let invalidated = self.auth.write().invalidate_session(session_id);
+        
+        if invalidated {
+            self.log_audit(AuditEvent {
+                timestamp: chrono::Utc::now(),
+                event_type: AuditEventType::Logout,
+                description: format!("Session {} invalidated", session_id),
+                user: None,
+                ip_address: None,
+                success: true,
+            });
+        }
+        
+        invalidated
+    }
+    
+    /// Drop expired sessions, returning how many were removed
+    pub fn reap_expired_sessions(&self) -> usize {
+        self.auth.write().reap_expired()
+    }
+    
+    /// Whether a user is currently locked out after failed logins
+    pub fn is_locked_out(&self, user_id: &str) -> bool {
+        self.auth.read().is_locked_out(user_id)
     }
     
     /// Generate secure random bytes
@@ -107,6 +342,42 @@ impl SecurityManager {
         secure_random_bytes(len)
     }
     
+    /// Persist audit events to the database in addition to memory
+    pub fn attach_audit_store(&self, db: &Arc<Database>) {
+        if let Some(ref audit) = self.audit {
+            audit.attach_database(db);
+        }
+    }
+    
+    /// Recent audit events held in memory
+    pub fn audit_events(&self, limit: usize) -> Vec<AuditEvent> {
+        self.audit.as_ref()
+            .map(|audit| audit.get_events(limit))
+            .unwrap_or_default()
+    }
+    
+    /// Key audit pseudonyms with the per-install secret at `path`, created
+    /// on first use, so they stay the same across restarts
+    ///
+    /// Until this is called they are keyed with a random secret and only
+    /// correlate within one run.
+    pub fn load_pseudonym_secret(&self, path: &std::path::Path) -> Result<()> {
+        if let Some(ref audit) = self.audit {
+            audit.set_pseudonym_secret(&*KeyStore::install_secret(path)?);
+        }
+        Ok(())
+    }
+    
+    /// `event` with personal data pseudonymized under the audit log's key,
+    /// so it correlates with logged events; without an audit log the data
+    /// is removed instead
+    pub fn redact(&self, event: &AuditEvent) -> AuditEvent {
+        match self.audit {
+            Some(ref audit) => audit.redact(event),
+            None => event.redacted(),
+        }
+    }
+    
     /// Log security audit event
     pub fn log_audit(&self, event: AuditEvent) {
         if let Some(ref audit) = self.audit {
@@ -126,6 +397,135 @@ pub struct AuditEvent {
     pub success: bool,
 }
 
+/// Stands in for personal data that `AuditEvent::redacted` removes outright
+const REDACTED: &str = "[redacted]";
+
+impl AuditEvent {
+    /// Copy with personal data removed
+    ///
+    /// IPv4 addresses are truncated to their /24 and IPv6 to their /48
+    /// network; user names and other host names are replaced with
+    /// `[redacted]`, in the description too. Use `redacted_with` (or
+    /// `SecurityManager::redact`) to keep events of one user correlatable.
+    pub fn redacted(&self) -> AuditEvent {
+        self.redact(None)
+    }
+    
+    /// Copy with personal data pseudonymized
+    ///
+    /// Like `redacted`, except that user and host names become `user-` or
+    /// `ip-` plus a short HMAC-SHA256 under `key`. That is deterministic,
+    /// so events from the same source still correlate, but without the key
+    /// a name can't be confirmed by hashing guesses.
+    pub fn redacted_with(&self, key: &hmac::Key) -> AuditEvent {
+        self.redact(Some(key))
+    }
+    
+    fn redact(&self, key: Option<&hmac::Key>) -> AuditEvent {
+        let user = self.user.as_deref().map(|user| pseudonym(key, "user", user));
+        let mut description = redact_ips_in(&self.description, key);
+        if let (Some(name), Some(replacement)) = (&self.user, &user) {
+            description = replace_word(&description, name, replacement);
+        }
+        
+        AuditEvent {
+            description,
+            user,
+            ip_address: self.ip_address.as_deref().map(|ip| redact_ip(ip, key)),
+            ..self.clone()
+        }
+    }
+}
+
+/// Replace the occurrences of `word` in `text` that aren't part of a
+/// longer word
+fn replace_word(text: &str, word: &str, replacement: &str) -> String {
+    if word.is_empty() {
+        return text.to_string();
+    }
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (at, _) in text.match_indices(word) {
+        let end = at + word.len();
+        let starts_word = !text[..at].chars().next_back().is_some_and(is_word_char);
+        let ends_word = !text[end..].chars().next().is_some_and(is_word_char);
+        if at >= copied && starts_word && ends_word {
+            out.push_str(&text[copied..at]);
+            out.push_str(replacement);
+            copied = end;
+        }
+    }
+    out.push_str(&text[copied..]);
+    out
+}
+
+/// Network prefix of an address, or a pseudonym if it isn't one
+fn redact_ip(ip: &str, key: Option<&hmac::Key>) -> String {
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V4(v4)) => {
+            let [a, b, c, _] = v4.octets();
+            format!("{}.{}.{}.0/24", a, b, c)
+        }
+        Ok(IpAddr::V6(v6)) => {
+            let network = std::net::Ipv6Addr::from(u128::from(v6) & !0u128 << 80);
+            format!("{}/48", network)
+        }
+        Err(_) => pseudonym(key, "ip", ip),
+    }
+}
+
+/// `prefix-` and the first 8 hex digits of the HMAC of `prefix` and the
+/// value, or `[redacted]` without a key
+fn pseudonym(key: Option<&hmac::Key>, prefix: &str, value: &str) -> String {
+    let Some(key) = key else { return REDACTED.to_string() };
+    let mut context = hmac::Context::with_key(key);
+    context.update(prefix.as_bytes());
+    context.update(&[0]);
+    context.update(value.as_bytes());
+    let tag = context.sign();
+    let hex: String = tag.as_ref()[..4].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}", prefix, hex)
+}
+
+/// Replace every IP address (optionally with a port) in free text
+fn redact_ips_in(text: &str, key: Option<&hmac::Key>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut run = String::new();
+    for c in text.chars() {
+        if c.is_ascii_hexdigit() || c == '.' || c == ':' {
+            run.push(c);
+        } else {
+            push_redacted_run(&mut out, &run, key);
+            run.clear();
+            out.push(c);
+        }
+    }
+    push_redacted_run(&mut out, &run, key);
+    out
+}
+
+/// Append a run of address-like characters, redacted if it is an address
+fn push_redacted_run(out: &mut String, run: &str, key: Option<&hmac::Key>) {
+    // Trailing sentence punctuation isn't part of the address
+    let core = run.trim_end_matches(['.', ':']);
+    let rest = &run[core.len()..];
+    
+    if core.parse::<IpAddr>().is_ok() {
+        out.push_str(&redact_ip(core, key));
+    } else if let Some((host, port)) = core.rsplit_once(':')
+        .filter(|(host, port)| host.parse::<std::net::Ipv4Addr>().is_ok() && port.bytes().all(|b| b.is_ascii_digit()))
+    {
+        out.push_str(&redact_ip(host, key));
+        out.push(':');
+        out.push_str(port);
+    } else {
+        out.push_str(core);
+    }
+    out.push_str(rest);
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AuditEventType {
     Login,
@@ -142,18 +542,58 @@ pub enum AuditEventType {
 }
 
 /// Simple audit log
+///
+/// Keeps recent events in memory and, once a database is attached, writes
+/// every event through to its `audit` table. The database is held weakly
+/// because it may itself hold the security manager.
 pub struct AuditLog {
     events: std::sync::RwLock<Vec<AuditEvent>>,
+    store: RwLock<Option<Weak<Database>>>,
+    redact_pii: bool,
+    // Keys the pseudonyms of redacted events
+    pseudonym_key: RwLock<hmac::Key>,
 }
 
 impl AuditLog {
     pub fn new() -> Self {
         Self {
             events: std::sync::RwLock::new(Vec::new()),
+            store: RwLock::new(None),
+            redact_pii: false,
+            pseudonym_key: RwLock::new(hmac::Key::new(hmac::HMAC_SHA256, &secure_random_bytes(32))),
         }
     }
     
+    /// Redact personal data from events before logging or persisting them
+    pub fn with_redaction(mut self, redact_pii: bool) -> Self {
+        self.redact_pii = redact_pii;
+        self
+    }
+    
+    /// Key pseudonyms with `secret` instead of the random per-run key
+    pub fn set_pseudonym_secret(&self, secret: &[u8]) {
+        *self.pseudonym_key.write() = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    }
+    
+    /// `event` pseudonymized under this log's key
+    pub fn redact(&self, event: &AuditEvent) -> AuditEvent {
+        event.redacted_with(&self.pseudonym_key.read())
+    }
+    
+    /// Write events through to the database from now on
+    pub fn attach_database(&self, db: &Arc<Database>) {
+        *self.store.write() = Some(Arc::downgrade(db));
+    }
+    
     pub fn log(&self, event: AuditEvent) {
+        let event = if self.redact_pii { event.redacted_with(&self.pseudonym_key.read()) } else { event };
+        
+        if let Some(db) = self.store.read().as_ref().and_then(|w| w.upgrade()) {
+            if let Err(e) = db.store_audit_event(&event) {
+                warn!("Failed to persist audit event: {}", e);
+            }
+        }
+        
         if let Ok(mut events) = self.events.write() {
             info!(
                 event_type = ?event.event_type,
@@ -186,3 +626,145 @@ pub fn secure_random_bytes(len: usize) -> Vec<u8> {
     rng.fill(&mut bytes).expect("Failed to generate random bytes");
     bytes
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_redacted_audit_log_has_no_raw_ip() {
+        let audit = AuditLog::new().with_redaction(true);
+        let event = |ip: &str| AuditEvent {
+            timestamp: chrono::Utc::now(),
+            event_type: AuditEventType::AuthFailure,
+            description: format!("Login failed for alice from {}:51234.", ip),
+            user: Some("alice".to_string()),
+            ip_address: Some(ip.to_string()),
+            success: false,
+        };
+        audit.log(event("192.168.1.42"));
+        audit.log(event("192.168.1.77"));
+        
+        let events = audit.get_events(2);
+        for logged in &events {
+            assert!(!logged.description.contains("192.168.1.42"), "{}", logged.description);
+            assert!(!logged.description.contains("alice"), "{}", logged.description);
+            assert_eq!(logged.ip_address.as_deref(), Some("192.168.1.0/24"));
+        }
+        // Still correlatable: the same user and network map to the same values
+        assert_eq!(events[0].user, events[1].user);
+        assert_eq!(events[0].description, events[1].description);
+        assert!(events[0].description.ends_with("from 192.168.1.0/24:51234."));
+        
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"install secret");
+        let v6 = AuditEvent { ip_address: Some("2001:db8:abcd:12::1".to_string()), ..event("10.0.0.1") };
+        assert_eq!(v6.redacted_with(&key).ip_address.as_deref(), Some("2001:db8:abcd::/48"));
+    }
+    
+    #[test]
+    fn test_unkeyed_redaction_truncates_and_removes_names() {
+        let event = AuditEvent {
+            timestamp: chrono::Utc::now(),
+            event_type: AuditEventType::AuthFailure,
+            description: "Login failed for alice from 10.1.2.3:443 via gateway.local".to_string(),
+            user: Some("alice".to_string()),
+            ip_address: Some("gateway.local".to_string()),
+            success: false,
+        };
+        
+        let redacted = event.redacted();
+        assert_eq!(redacted.user.as_deref(), Some(REDACTED));
+        assert_eq!(redacted.ip_address.as_deref(), Some(REDACTED));
+        assert_eq!(redacted.description, "Login failed for [redacted] from 10.1.2.0/24:443 via gateway.local");
+        
+        // The manager's form keeps users correlatable
+        let security = SecurityManager::new(SecurityConfig::default()).unwrap();
+        let keyed = security.redact(&event);
+        assert!(keyed.user.as_deref().unwrap().starts_with("user-"));
+        assert_eq!(security.redact(&event).user, keyed.user);
+    }
+    
+    #[test]
+    fn test_pseudonyms_are_keyed_and_replace_whole_names() {
+        let event = AuditEvent {
+            timestamp: chrono::Utc::now(),
+            event_type: AuditEventType::Login,
+            description: "al logged in; alan and sal_al weren't, al.".to_string(),
+            user: Some("al".to_string()),
+            ip_address: Some("sensor-hub.local".to_string()),
+            success: true,
+        };
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"install secret");
+        let redacted = event.redacted_with(&key);
+        let user = redacted.user.clone().unwrap();
+        assert!(user.starts_with("user-"));
+        assert_eq!(redacted.description, format!("{} logged in; alan and sal_al weren't, {}.", user, user));
+        assert!(redacted.ip_address.unwrap().starts_with("ip-"));
+        
+        // Same key, same pseudonym; another install's key gives another
+        assert_eq!(event.redacted_with(&key).user, redacted.user);
+        let other = hmac::Key::new(hmac::HMAC_SHA256, b"other install");
+        assert_ne!(event.redacted_with(&other).user, redacted.user);
+    }
+    
+    #[test]
+    fn test_authenticate_through_manager_enforces_lockout() {
+        let config = SecurityConfig { kdf_iterations: 1, kdf_memory_kib: 8 * 1024, kdf_parallelism: 1, ..Default::default() };
+        let security = SecurityManager::new(config).unwrap();
+        let hash = security.hash_password("SecurePassword123!").unwrap();
+        
+        let session = security.authenticate("user1", "SecurePassword123!", &hash).unwrap();
+        assert!(security.validate_session(&session.id));
+        
+        for _ in 0..5 {
+            assert!(security.authenticate("user2", "wrong", &hash).is_err());
+        }
+        assert!(security.is_locked_out("user2"));
+        assert!(security.authenticate("user2", "SecurePassword123!", &hash).is_err());
+        assert!(security.authenticate("user1", "SecurePassword123!", &hash).is_ok());
+    }
+    
+    #[test]
+    fn test_unlock_storage_rejects_wrong_passphrase() {
+        let config = SecurityConfig { kdf_iterations: 1, kdf_memory_kib: 8 * 1024, kdf_parallelism: 1, ..Default::default() };
+        let salt_path = std::env::temp_dir().join(format!("glowbarn-{}.salt", uuid::Uuid::new_v4()));
+        
+        let first = SecurityManager::new(config.clone()).unwrap();
+        first.unlock_storage("correct horse", &salt_path).unwrap();
+        let sealed = first.encrypt(b"reading").unwrap();
+        
+        let again = SecurityManager::new(config.clone()).unwrap();
+        again.unlock_storage("correct horse", &salt_path).unwrap();
+        assert_eq!(again.decrypt(&sealed).unwrap(), b"reading");
+        
+        let wrong = SecurityManager::new(config).unwrap();
+        assert!(wrong.unlock_storage("wrong horse", &salt_path).is_err());
+        
+        let _ = std::fs::remove_file(&salt_path);
+        let _ = std::fs::remove_file(salt_path.with_extension("verify"));
+    }
+    
+    #[test]
+    fn test_rotated_keys_survive_restart() {
+        let config = SecurityConfig { kdf_iterations: 1, kdf_memory_kib: 8 * 1024, kdf_parallelism: 1, ..Default::default() };
+        let salt_path = std::env::temp_dir().join(format!("glowbarn-{}.salt", uuid::Uuid::new_v4()));
+        
+        let mut first = SecurityManager::new(config.clone()).unwrap();
+        first.unlock_storage("correct horse", &salt_path).unwrap();
+        let derived = first.encrypt(b"before rotation").unwrap();
+        first.rotate_key().unwrap();
+        first.rotate_key().unwrap();
+        let rotated = first.encrypt(b"after rotation").unwrap();
+        assert_eq!(first.active_key_id(), 2);
+        
+        let again = SecurityManager::new(config).unwrap();
+        again.unlock_storage("correct horse", &salt_path).unwrap();
+        assert_eq!(again.active_key_id(), 2);
+        assert_eq!(again.decrypt(&derived).unwrap(), b"before rotation");
+        assert_eq!(again.decrypt(&rotated).unwrap(), b"after rotation");
+        
+        for ext in ["salt", "verify", "keys"] {
+            let _ = std::fs::remove_file(salt_path.with_extension(ext));
+        }
+    }
+}
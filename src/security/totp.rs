@@ -0,0 +1,185 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! TOTP two-factor authentication (RFC 6238)
+
+use ring::hmac;
+
+use super::secure_memory::constant_time_compare;
+
+/// Time step in seconds
+pub const TOTP_STEP_SECS: u64 = 30;
+
+/// Digits in generated codes
+pub const TOTP_DIGITS: u32 = 6;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Generate a random 160-bit shared secret, base32 encoded
+pub fn generate_secret() -> String {
+    base32_encode(&super::secure_random_bytes(20))
+}
+
+/// Build an `otpauth://` URI suitable for authenticator app QR codes
+pub fn provisioning_uri(secret: &str, account: &str, issuer: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(issuer),
+        percent_encode(account),
+        secret,
+        percent_encode(issuer),
+        TOTP_DIGITS,
+        TOTP_STEP_SECS,
+    )
+}
+
+/// Verify a code against the current time, accepting `skew` steps either side
+pub fn verify_totp(secret: &str, code: &str, skew: u8) -> bool {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    
+    verify_totp_at(secret, code, skew, now)
+}
+
+/// Verify a code against a given Unix time
+pub fn verify_totp_at(secret: &str, code: &str, skew: u8, unix_time: u64) -> bool {
+    let key = match base32_decode(secret) {
+        Some(k) if !k.is_empty() => k,
+        _ => return false,
+    };
+    
+    let digits = code.len() as u32;
+    if !(6..=8).contains(&digits) || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    
+    let step = unix_time / TOTP_STEP_SECS;
+    let mut valid = false;
+    
+    // Check every window without early exit so timing does not reveal which matched
+    for offset in -(skew as i64)..=(skew as i64) {
+        let counter = step as i64 + offset;
+        if counter < 0 {
+            continue;
+        }
+        let expected = hotp(&key, counter as u64, digits);
+        valid |= constant_time_compare(expected.as_bytes(), code.as_bytes());
+    }
+    
+    valid
+}
+
+/// Generate the TOTP code for a raw key at a given Unix time
+pub fn generate_code(key: &[u8], unix_time: u64, digits: u32) -> String {
+    hotp(key, unix_time / TOTP_STEP_SECS, digits)
+}
+
+/// HOTP (RFC 4226) with HMAC-SHA1 and dynamic truncation
+fn hotp(key: &[u8], counter: u64, digits: u32) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, key);
+    let tag = hmac::sign(&key, &counter.to_be_bytes());
+    let mac = tag.as_ref();
+    
+    let offset = (mac[mac.len() - 1] & 0x0f) as usize;
+    let binary = ((mac[offset] as u32 & 0x7f) << 24)
+        | ((mac[offset + 1] as u32) << 16)
+        | ((mac[offset + 2] as u32) << 8)
+        | (mac[offset + 3] as u32);
+    
+    let code = binary % 10u32.pow(digits);
+    format!("{:0width$}", code, width = digits as usize)
+}
+
+/// RFC 4648 base32 without padding
+fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() * 8 + 4) / 5);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            let idx = (buffer >> (bits - 5)) & 0x1f;
+            out.push(BASE32_ALPHABET[idx as usize] as char);
+            bits -= 5;
+        }
+    }
+    
+    if bits > 0 {
+        let idx = (buffer << (5 - bits)) & 0x1f;
+        out.push(BASE32_ALPHABET[idx as usize] as char);
+    }
+    
+    out
+}
+
+/// Decode base32, ignoring case, spaces and padding
+pub(crate) fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    
+    for c in input.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let upper = c.to_ascii_uppercase() as u8;
+        let value = BASE32_ALPHABET.iter().position(|&a| a == upper)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            out.push((buffer >> (bits - 8)) as u8);
+            bits -= 8;
+        }
+    }
+    
+    Some(out)
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_rfc6238_sha1_vectors() {
+        let key = b"12345678901234567890";
+        let vectors = [
+            (59, "94287082"),
+            (1111111109, "07081804"),
+            (1111111111, "14050471"),
+            (1234567890, "89005924"),
+            (2000000000, "69279037"),
+            (20000000000, "65353130"),
+        ];
+        
+        let secret = base32_encode(key);
+        for (time, expected) in vectors {
+            assert_eq!(generate_code(key, time, 8), expected);
+            assert!(verify_totp_at(&secret, expected, 0, time));
+        }
+    }
+    
+    #[test]
+    fn test_skew_window() {
+        let secret = generate_secret();
+        let key = base32_decode(&secret).unwrap();
+        let code = generate_code(&key, 1_000_000, TOTP_DIGITS);
+        
+        assert!(verify_totp_at(&secret, &code, 1, 1_000_000 + TOTP_STEP_SECS));
+        assert!(!verify_totp_at(&secret, &code, 0, 1_000_000 + TOTP_STEP_SECS));
+        assert!(!verify_totp_at(&secret, "abcdef", 1, 1_000_000));
+    }
+}
@@ -4,23 +4,266 @@
 
 //! Sensor manager - coordinates all sensors
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
+use chrono::Utc;
 use tokio::sync::{broadcast, RwLock};
-use tokio::time::{interval, Duration};
+use tokio::time::{interval, Duration, Instant};
 use anyhow::Result;
 use tracing::{info, warn, error, debug};
 
-use super::{Sensor, SensorReading, SensorType, SensorStatus, SensorHealth};
-use super::simulator::SensorSimulator;
-use crate::config::Config;
-use crate::core::EventBus;
+use super::{Sensor, SensorReading, SensorType, SensorStatus, SensorHealth, HealthStatus, CalibrationData, NoiseProfile};
+use super::simulator::{SensorSimulator, ScenarioPlayer};
+use crate::analysis::AnalysisEngine;
+use crate::config::{Config, SensorConfig};
+use crate::core::{EventBus, Priority, SamplingStats, Scheduler};
+use crate::db::Database;
+
+/// Settings key prefix a sensor's noise profile is persisted under
+const NOISE_PROFILE_SETTING: &str = "noise_profile.";
+
+/// Upper bound on how often a single sensor is polled; high-rate sensors
+/// return a block of samples per read instead
+pub const MAX_POLL_HZ: f64 = 100.0;
+
+/// Interval between reads for a sensor with the given sample rate
+pub fn poll_period(sample_rate: f64) -> Duration {
+    let hz = if sample_rate.is_finite() && sample_rate > 0.0 {
+        sample_rate.min(MAX_POLL_HZ)
+    } else {
+        1.0
+    };
+    Duration::from_secs_f64(1.0 / hz)
+}
+
+/// Number of recent reads used for the error rate and quality trend
+const HEALTH_WINDOW: usize = 50;
+
+/// Error rate above which a sensor is Degraded
+const DEGRADED_ERROR_RATE: f64 = 0.1;
+
+/// Error rate above which a sensor is Failed
+const FAILED_ERROR_RATE: f64 = 0.9;
+
+/// Mean quality below which a sensor is Degraded
+const DEGRADED_QUALITY: f32 = 0.5;
+
+/// Quality drop (newer vs older half of the window) that counts as Degraded
+const DEGRADED_QUALITY_TREND: f32 = -0.2;
+
+/// Shortest silence that marks a sensor Stale, so scheduler jitter on
+/// fast sensors isn't mistaken for an outage
+const MIN_STALE_AFTER: Duration = Duration::from_secs(1);
+
+/// A sensor is Stale once it misses two read periods (and at least `MIN_STALE_AFTER`)
+pub fn stale_after(sample_rate: f64) -> Duration {
+    (poll_period(sample_rate) * 2).max(MIN_STALE_AFTER)
+}
+
+/// Rolling health bookkeeping for one sensor
+struct HealthTracker {
+    health: SensorHealth,
+    added: Instant,
+    /// Set by `SensorManager::disable`; the scheduler skips disabled sensors
+    disabled: bool,
+    last_success: Option<Instant>,
+    outcomes: VecDeque<bool>,
+    qualities: VecDeque<f32>,
+}
+
+impl HealthTracker {
+    fn new(id: &str, sensor_type: SensorType) -> Self {
+        Self {
+            health: SensorHealth::new(id, sensor_type),
+            added: Instant::now(),
+            disabled: false,
+            last_success: None,
+            outcomes: VecDeque::with_capacity(HEALTH_WINDOW),
+            qualities: VecDeque::with_capacity(HEALTH_WINDOW),
+        }
+    }
+    
+    fn push_outcome(&mut self, ok: bool) {
+        if self.outcomes.len() == HEALTH_WINDOW {
+            self.outcomes.pop_front();
+        }
+        self.outcomes.push_back(ok);
+    }
+    
+    fn record_success(&mut self, quality: f32) {
+        self.push_outcome(true);
+        if self.qualities.len() == HEALTH_WINDOW {
+            self.qualities.pop_front();
+        }
+        self.qualities.push_back(quality);
+        
+        self.last_success = Some(Instant::now());
+        self.health.readings_count += 1;
+        self.health.signal_quality = quality;
+        self.health.last_reading = Some(Utc::now());
+    }
+    
+    fn record_error(&mut self, error: String) {
+        self.push_outcome(false);
+        self.health.error_count += 1;
+        self.health.last_error = Some(error);
+    }
+    
+    fn error_rate(&self) -> f64 {
+        if self.outcomes.is_empty() {
+            return 0.0;
+        }
+        self.outcomes.iter().filter(|ok| !**ok).count() as f64 / self.outcomes.len() as f64
+    }
+    
+    fn quality_trend(&self) -> f32 {
+        let n = self.qualities.len();
+        if n < 4 {
+            return 0.0;
+        }
+        let half = n / 2;
+        let older = self.qualities.iter().take(half).sum::<f32>() / half as f32;
+        let newer = self.qualities.iter().skip(half).sum::<f32>() / (n - half) as f32;
+        newer - older
+    }
+    
+    fn mean_quality(&self) -> Option<f32> {
+        if self.qualities.is_empty() {
+            None
+        } else {
+            Some(self.qualities.iter().sum::<f32>() / self.qualities.len() as f32)
+        }
+    }
+    
+    fn assess(&self, status: SensorStatus, sample_rate: f64, now: Instant) -> HealthStatus {
+        if self.disabled {
+            return HealthStatus::Offline;
+        }
+        
+        let error_rate = self.error_rate();
+        
+        if status == SensorStatus::Error
+            || (self.outcomes.len() >= 10 && error_rate >= FAILED_ERROR_RATE)
+        {
+            return HealthStatus::Failed;
+        }
+        
+        let since = self.last_success.unwrap_or(self.added);
+        if now.duration_since(since) > stale_after(sample_rate) {
+            return HealthStatus::Stale;
+        }
+        
+        if error_rate > DEGRADED_ERROR_RATE
+            || matches!(self.mean_quality(), Some(q) if q < DEGRADED_QUALITY)
+            || self.quality_trend() < DEGRADED_QUALITY_TREND
+        {
+            return HealthStatus::Degraded;
+        }
+        
+        HealthStatus::Healthy
+    }
+    
+    /// Current health snapshot with time-dependent fields filled in
+    fn snapshot(&self, status: SensorStatus, sample_rate: f64) -> SensorHealth {
+        let now = Instant::now();
+        let mut health = self.health.clone();
+        health.status = status;
+        health.health = self.assess(status, sample_rate, now);
+        health.uptime_seconds = now.duration_since(self.added).as_secs();
+        health.last_read_age_secs = self.last_success.map(|t| now.duration_since(t).as_secs_f64());
+        health.error_rate = self.error_rate();
+        health.quality_trend = self.quality_trend();
+        health
+    }
+}
+
+/// Apply a calibration to raw values as `(value - offset) * scale`.
+///
+/// Offset/scale vectors are per channel when their length matches the
+/// data; otherwise their first element applies to every channel.
+pub fn apply_calibration(calibration: &CalibrationData, data: &mut [f64]) {
+    let offset_per_channel = calibration.offset.len() == data.len();
+    let scale_per_channel = calibration.scale.len() == data.len();
+    let offset0 = calibration.offset.first().copied().unwrap_or(0.0);
+    let scale0 = calibration.scale.first().copied().unwrap_or(1.0);
+    
+    for (i, value) in data.iter_mut().enumerate() {
+        let offset = if offset_per_channel { calibration.offset[i] } else { offset0 };
+        let scale = if scale_per_channel { calibration.scale[i] } else { scale0 };
+        *value = (*value - offset) * scale;
+    }
+}
+
+/// Builds a sensor from the `[sensors]` configuration
+pub type SensorFactory = Box<dyn Fn(&SensorConfig) -> Box<dyn Sensor> + Send + Sync>;
+
+/// Named sensor factories, so sensors defined outside this crate can be
+/// built by the manager without forking it
+///
+/// Register a factory under a key, then either list the key in
+/// `sensors.drivers` or leave that empty with `auto_discover` on, and pass
+/// the registry to `SensorManager::add_from_registry` (or
+/// `Engine::with_sensor_registry`). Factories must return sensors that honour
+/// the `Sensor` contract.
+#[derive(Default)]
+pub struct SensorRegistry {
+    factories: HashMap<String, SensorFactory>,
+}
+
+impl SensorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Register `factory` under `key`, replacing any factory already there
+    pub fn register<F>(&mut self, key: &str, factory: F)
+    where
+        F: Fn(&SensorConfig) -> Box<dyn Sensor> + Send + Sync + 'static,
+    {
+        if self.factories.insert(key.to_string(), Box::new(factory)).is_some() {
+            warn!("Sensor factory '{}' replaced", key);
+        }
+    }
+    
+    /// Register `factory` keyed by the sensor type's name, e.g. `"EMFProbe"`
+    pub fn register_type<F>(&mut self, sensor_type: SensorType, factory: F)
+    where
+        F: Fn(&SensorConfig) -> Box<dyn Sensor> + Send + Sync + 'static,
+    {
+        self.register(&format!("{:?}", sensor_type), factory);
+    }
+    
+    pub fn contains(&self, key: &str) -> bool {
+        self.factories.contains_key(key)
+    }
+    
+    /// Registered keys, sorted
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.factories.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        keys
+    }
+    
+    /// Build the sensor registered under `key`
+    pub fn build(&self, key: &str, config: &SensorConfig) -> Result<Box<dyn Sensor>> {
+        let factory = self.factories.get(key)
+            .ok_or_else(|| anyhow::anyhow!("No sensor registered as '{}'", key))?;
+        Ok(factory(config))
+    }
+}
 
 /// Manages all sensors in the system
 pub struct SensorManager {
     config: Arc<Config>,
     sensors: RwLock<HashMap<String, Box<dyn Sensor>>>,
-    health: RwLock<HashMap<String, SensorHealth>>,
+    health: RwLock<HashMap<String, HealthTracker>>,
+    calibrations: RwLock<HashMap<String, CalibrationData>>,
+    noise_profiles: RwLock<HashMap<String, NoiseProfile>>,
+    // When each sensor is read next, its priority and its sampling rates
+    scheduler: tokio::sync::Mutex<Scheduler>,
+    database: parking_lot::RwLock<Option<Arc<Database>>>,
+    // Told each sensor's characterized noise σ
+    analysis: parking_lot::RwLock<Option<Arc<AnalysisEngine>>>,
     event_bus: Arc<EventBus>,
     demo_mode: bool,
 }
@@ -31,12 +274,20 @@ impl SensorManager {
             config,
             sensors: RwLock::new(HashMap::new()),
             health: RwLock::new(HashMap::new()),
+            calibrations: RwLock::new(HashMap::new()),
+            noise_profiles: RwLock::new(HashMap::new()),
+            scheduler: tokio::sync::Mutex::new(Scheduler::new()),
+            database: parking_lot::RwLock::new(None),
+            analysis: parking_lot::RwLock::new(None),
             event_bus,
             demo_mode,
         };
         
         if demo_mode {
             manager.add_demo_sensors().await?;
+        } else {
+            #[cfg(feature = "serial")]
+            manager.add_serial_sensors().await?;
         }
         
         Ok(manager)
@@ -62,14 +313,71 @@ impl SensorManager {
             ("laser-grid-1", SensorType::LaserGrid, 60.0),
         ];
         
+        let player = match self.config.sensors.scenario_file {
+            Some(ref path) => {
+                let player = ScenarioPlayer::load(path)?;
+                info!("Loaded scenario {:?} with {} events", path, player.scenario().events.len());
+                Some(player)
+            }
+            None => None,
+        };
+        
         for (id, sensor_type, sample_rate) in demo_configs {
-            let simulator = SensorSimulator::new(id, sensor_type, sample_rate);
+            let mut simulator = SensorSimulator::new(id, sensor_type, sample_rate);
+            if let Some(ref player) = player {
+                player.attach(&mut simulator);
+            }
             self.add_sensor(Box::new(simulator)).await?;
         }
         
         Ok(())
     }
     
+    /// Add the configured serial port, or every discovered USB port when
+    /// `auto_discover` is set
+    #[cfg(feature = "serial")]
+    async fn add_serial_sensors(&self) -> Result<()> {
+        let sensor_config = &self.config.sensors;
+        
+        let ports = match sensor_config.serial_port {
+            Some(ref port) => vec![port.clone()],
+            None if sensor_config.auto_discover => super::discover_ports().unwrap_or_else(|e| {
+                warn!("Serial port discovery failed: {}", e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        
+        for (i, port) in ports.iter().enumerate() {
+            info!("Using serial port {}", port);
+            let sensor = super::SerialSensor::new(&format!("serial-{}", i + 1), port, sensor_config.serial_baud_rate);
+            self.add_sensor(Box::new(sensor)).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Build and add sensors from `registry`: the keys in `sensors.drivers`,
+    /// or every registered key when that list is empty, `auto_discover` is
+    /// on and the manager isn't in demo mode. Returns how many were added.
+    pub async fn add_from_registry(&self, registry: &SensorRegistry) -> Result<usize> {
+        let sensor_config = &self.config.sensors;
+        let keys: Vec<&str> = if !sensor_config.drivers.is_empty() {
+            sensor_config.drivers.iter().map(String::as_str).collect()
+        } else if sensor_config.auto_discover && !self.demo_mode {
+            registry.keys()
+        } else {
+            Vec::new()
+        };
+        
+        for key in &keys {
+            let sensor = registry.build(key, sensor_config)?;
+            info!("Built sensor {} from driver '{}'", sensor.id(), key);
+            self.add_sensor(sensor).await?;
+        }
+        Ok(keys.len())
+    }
+    
     pub async fn add_sensor(&self, sensor: Box<dyn Sensor>) -> Result<()> {
         let id = sensor.id().to_string();
         let sensor_type = sensor.sensor_type();
@@ -78,23 +386,24 @@ impl SensorManager {
         sensors.insert(id.clone(), sensor);
         
         let mut health = self.health.write().await;
-        health.insert(id.clone(), SensorHealth {
-            sensor_id: id.clone(),
-            status: SensorStatus::Disconnected,
-            uptime_seconds: 0,
-            readings_count: 0,
-            error_count: 0,
-            last_error: None,
-            signal_quality: 0.0,
-            noise_level: 0.0,
-            temperature: None,
-            battery_level: None,
-        });
+        health.insert(id.clone(), HealthTracker::new(&id, sensor_type));
         
         info!("Added sensor: {} ({:?})", id, sensor_type);
         Ok(())
     }
     
+    /// Disconnect and drop every sensor, then install `replacement`
+    pub async fn replace_sensors(&self, replacement: Vec<Box<dyn Sensor>>) -> Result<()> {
+        let ids: Vec<String> = self.sensors.read().await.keys().cloned().collect();
+        for id in ids {
+            self.remove_sensor(&id).await?;
+        }
+        for sensor in replacement {
+            self.add_sensor(sensor).await?;
+        }
+        Ok(())
+    }
+    
     pub async fn remove_sensor(&self, id: &str) -> Result<()> {
         let mut sensors = self.sensors.write().await;
         if let Some(mut sensor) = sensors.remove(id) {
@@ -103,54 +412,83 @@ impl SensorManager {
         
         let mut health = self.health.write().await;
         health.remove(id);
+        self.scheduler.lock().await.unregister(id);
         
         info!("Removed sensor: {}", id);
         Ok(())
     }
     
+    /// Frequency span of each RF sensor that reports one in its settings
+    pub async fn spectrum_spans(&self) -> Vec<(String, (f64, f64))> {
+        let sensors = self.sensors.read().await;
+        sensors.iter()
+            .filter(|(_, s)| matches!(s.sensor_type(), SensorType::SDRReceiver | SensorType::SpectrumAnalyzer))
+            .filter_map(|(id, s)| Some((id.clone(), super::spectrum_span(&s.config())?)))
+            .collect()
+    }
+    
+    /// Sampling priority of a sensor; under overload lower priorities are
+    /// read less often first
+    pub async fn set_priority(&self, id: &str, priority: Priority) -> Result<()> {
+        if !self.sensors.read().await.contains_key(id) {
+            anyhow::bail!("Unknown sensor {}", id);
+        }
+        self.scheduler.lock().await.register(id, priority);
+        Ok(())
+    }
+    
+    /// Actual-vs-target read rate of each sensor the poll loop has seen
+    pub async fn sampling_stats(&self) -> Vec<SamplingStats> {
+        self.scheduler.lock().await.sampling_stats()
+    }
+    
     pub async fn active_count(&self) -> usize {
         let sensors = self.sensors.read().await;
         sensors.values().filter(|s| s.status() == SensorStatus::Active).count()
     }
     
-    pub async fn get_health(&self, id: &str) -> Option<SensorHealth> {
+    /// Health of one sensor: time since last read, rolling error rate,
+    /// quality trend and the overall Healthy/Degraded/Stale/Failed status
+    pub async fn health(&self, sensor_id: &str) -> Option<SensorHealth> {
+        let sensors = self.sensors.read().await;
         let health = self.health.read().await;
-        health.get(id).cloned()
+        let sensor = sensors.get(sensor_id)?;
+        health.get(sensor_id).map(|h| h.snapshot(sensor.status(), sensor.sample_rate()))
+    }
+    
+    pub async fn get_health(&self, id: &str) -> Option<SensorHealth> {
+        self.health(id).await
     }
     
     pub async fn get_all_health(&self) -> Vec<SensorHealth> {
+        let sensors = self.sensors.read().await;
         let health = self.health.read().await;
-        health.values().cloned().collect()
+        sensors.iter()
+            .filter_map(|(id, s)| health.get(id).map(|h| h.snapshot(s.status(), s.sample_rate())))
+            .collect()
     }
     
     pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) -> Result<()> {
         info!("Starting sensor manager...");
         
-        // Connect all sensors
-        {
-            let mut sensors = self.sensors.write().await;
-            for (id, sensor) in sensors.iter_mut() {
-                match sensor.connect().await {
-                    Ok(_) => {
-                        info!("Connected sensor: {}", id);
-                        if let Err(e) = sensor.calibrate().await {
-                            warn!("Calibration failed for {}: {}", id, e);
-                        }
-                    }
-                    Err(e) => {
-                        error!("Failed to connect sensor {}: {}", id, e);
-                    }
-                }
-            }
-        }
+        self.connect_all().await;
         
-        // Main reading loop
-        let mut read_interval = interval(Duration::from_millis(10));  // 100Hz base rate
+        // Main reading loop; the scheduler reads each sensor when its own
+        // period elapses
+        let mut read_interval = interval(poll_period(MAX_POLL_HZ));
+        
+        let recalibrate_every = self.config.sensors.calibration_interval_secs;
+        let mut recalibrate = interval(Duration::from_secs(recalibrate_every.max(1)));
+        recalibrate.reset();
         
         loop {
             tokio::select! {
                 _ = read_interval.tick() => {
-                    self.read_all_sensors().await;
+                    self.read_due_sensors().await;
+                    self.scheduler.lock().await.run_due_tasks().await;
+                }
+                _ = recalibrate.tick(), if recalibrate_every > 0 => {
+                    self.recalibrate_all().await;
                 }
                 _ = shutdown.recv() => {
                     info!("Sensor manager shutting down...");
@@ -172,34 +510,715 @@ impl SensorManager {
         Ok(())
     }
     
-    async fn read_all_sensors(&self) {
+    /// Persist calibrations and noise profiles to `database` and restore
+    /// them on connect
+    pub fn attach_database(&self, database: Arc<Database>) {
+        *self.database.write() = Some(database);
+    }
+    
+    /// Hand each sensor's characterized noise σ to `analysis` as its noise
+    /// floor
+    pub fn attach_analysis(&self, analysis: Arc<AnalysisEngine>) {
+        *self.analysis.write() = Some(analysis);
+    }
+    
+    /// Connect every sensor and bring it online
+    ///
+    /// `calibrate()` is what activates a sensor, so it always runs; a stored
+    /// calibration that is younger than `calibration_interval_secs` takes
+    /// precedence over the fresh result so offsets survive restarts. A
+    /// stored noise profile is restored along with it.
+    async fn connect_all(&self) {
+        let disabled = self.disabled_ids().await;
         let mut sensors = self.sensors.write().await;
-        let mut health = self.health.write().await;
-        
         for (id, sensor) in sensors.iter_mut() {
-            if sensor.status() != SensorStatus::Active {
+            if disabled.contains(id) {
+                continue;
+            }
+            if let Err(e) = sensor.connect().await {
+                error!("Failed to connect sensor {}: {}", id, e);
                 continue;
             }
+            info!("Connected sensor: {}", id);
+            
+            let fresh = match sensor.calibrate().await {
+                Ok(c) => Some(c),
+                Err(e) => {
+                    warn!("Calibration failed for {}: {}", id, e);
+                    None
+                }
+            };
             
-            match sensor.read().await {
-                Ok(reading) => {
-                    // Update health
-                    if let Some(h) = health.get_mut(id) {
-                        h.readings_count += 1;
-                        h.signal_quality = reading.quality;
+            match self.load_calibration(id).filter(|c| self.is_current(c)) {
+                Some(stored) => {
+                    debug!("Restored calibration for {} from {}", id, stored.timestamp);
+                    self.calibrations.write().await.insert(id.clone(), stored);
+                }
+                None => {
+                    if let Some(calibration) = fresh {
+                        self.save_calibration(id, sensor.sensor_type(), calibration).await;
                     }
-                    
-                    // Publish reading
-                    self.event_bus.publish_reading(reading);
                 }
-                Err(e) => {
-                    if let Some(h) = health.get_mut(id) {
-                        h.error_count += 1;
-                        h.last_error = Some(e.to_string());
+            }
+            
+            if let Some(profile) = self.load_noise_profile(id) {
+                debug!("Restored noise profile for {} from {}", id, profile.timestamp);
+                self.adopt_noise_profile(profile).await;
+            }
+        }
+    }
+    
+    fn is_current(&self, calibration: &CalibrationData) -> bool {
+        let max_age = self.config.sensors.calibration_interval_secs;
+        max_age == 0 || (Utc::now() - calibration.timestamp).num_seconds() < max_age as i64
+    }
+    
+    /// Calibrate a sensor now and persist the result
+    pub async fn calibrate_sensor(&self, id: &str) -> Result<CalibrationData> {
+        let mut sensors = self.sensors.write().await;
+        let sensor = sensors.get_mut(id).ok_or_else(|| anyhow::anyhow!("Unknown sensor {}", id))?;
+        
+        let calibration = sensor.calibrate().await?;
+        self.save_calibration(id, sensor.sensor_type(), calibration.clone()).await;
+        info!("Calibrated sensor {}", id);
+        
+        Ok(calibration)
+    }
+    
+    /// Calibration currently applied to a sensor's readings
+    pub async fn calibration(&self, id: &str) -> Option<CalibrationData> {
+        self.calibrations.read().await.get(id).cloned()
+    }
+    
+    /// Change how often a sensor is read
+    pub async fn set_sample_rate(&self, id: &str, rate: f64) -> Result<()> {
+        if !(rate.is_finite() && rate > 0.0) {
+            anyhow::bail!("Sample rate must be positive, got {}", rate);
+        }
+        let mut sensors = self.sensors.write().await;
+        let sensor = sensors.get_mut(id).ok_or_else(|| anyhow::anyhow!("Unknown sensor {}", id))?;
+        sensor.set_sample_rate(rate)?;
+        info!("Sample rate of {} set to {} Hz", id, rate);
+        Ok(())
+    }
+    
+    /// Connect a sensor and bring it online (`calibrate` activates it)
+    pub async fn start_sensor(&self, id: &str) -> Result<()> {
+        if !self.is_enabled(id).await {
+            anyhow::bail!("Sensor {} is disabled", id);
+        }
+        {
+            let mut sensors = self.sensors.write().await;
+            let sensor = sensors.get_mut(id).ok_or_else(|| anyhow::anyhow!("Unknown sensor {}", id))?;
+            sensor.connect().await?;
+        }
+        self.scheduler.lock().await.reschedule(id);
+        self.calibrate_sensor(id).await?;
+        info!("Started sensor {}", id);
+        Ok(())
+    }
+    
+    /// Disconnect a sensor; it stays registered and can be started again
+    pub async fn stop_sensor(&self, id: &str) -> Result<()> {
+        let mut sensors = self.sensors.write().await;
+        let sensor = sensors.get_mut(id).ok_or_else(|| anyhow::anyhow!("Unknown sensor {}", id))?;
+        sensor.disconnect().await?;
+        info!("Stopped sensor {}", id);
+        Ok(())
+    }
+    
+    /// Start every enabled sensor, returning the first failure after trying all
+    pub async fn start_all(&self) -> Result<()> {
+        let disabled = self.disabled_ids().await;
+        let mut result = Ok(());
+        for id in self.sensor_ids().await {
+            if disabled.contains(&id) {
+                continue;
+            }
+            if let Err(e) = self.start_sensor(&id).await {
+                warn!("Failed to start {}: {}", id, e);
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+    
+    /// Stop every sensor; they stay enabled and `start_all` brings them back
+    pub async fn stop_all(&self) -> Result<()> {
+        let mut result = Ok(());
+        for id in self.sensor_ids().await {
+            if let Err(e) = self.stop_sensor(&id).await {
+                warn!("Failed to stop {}: {}", id, e);
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+    
+    /// Clear a sensor's disabled flag and start it
+    pub async fn enable(&self, id: &str) -> Result<()> {
+        {
+            let mut health = self.health.write().await;
+            let tracker = health.get_mut(id).ok_or_else(|| anyhow::anyhow!("Unknown sensor {}", id))?;
+            tracker.disabled = false;
+        }
+        self.start_sensor(id).await?;
+        info!("Enabled sensor {}", id);
+        Ok(())
+    }
+    
+    /// Take a sensor out of the read schedule, disconnect it and record it
+    /// as offline in the database
+    pub async fn disable(&self, id: &str) -> Result<()> {
+        {
+            let mut health = self.health.write().await;
+            let tracker = health.get_mut(id).ok_or_else(|| anyhow::anyhow!("Unknown sensor {}", id))?;
+            tracker.disabled = true;
+        }
+        self.stop_sensor(id).await?;
+        
+        let db = self.database.read().clone();
+        if let (Some(db), Some(health)) = (db, self.health(id).await) {
+            db.update_sensor_status(&health)?;
+        }
+        info!("Disabled sensor {}", id);
+        Ok(())
+    }
+    
+    /// Whether a sensor is registered and not disabled
+    pub async fn is_enabled(&self, id: &str) -> bool {
+        self.health.read().await.get(id).is_some_and(|h| !h.disabled)
+    }
+    
+    async fn sensor_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.sensors.read().await.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+    
+    async fn disabled_ids(&self) -> std::collections::HashSet<String> {
+        self.health.read().await.iter()
+            .filter(|(_, h)| h.disabled)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+    
+    /// Sample a sensor for `duration` at its own rate and profile its noise
+    ///
+    /// Run it while the sensor sees nothing of interest: readings taken here
+    /// are not published. The profile becomes the sensor's noise baseline:
+    /// it is persisted next to the calibration, which it leaves alone, and
+    /// its σ is the least spread the anomaly detector scores the sensor with.
+    pub async fn characterize(&self, id: &str, duration: Duration) -> Result<NoiseProfile> {
+        let sensor_type = self.sensors.read().await.get(id)
+            .map(|s| s.sensor_type())
+            .ok_or_else(|| anyhow::anyhow!("Unknown sensor {}", id))?;
+        let started = Instant::now();
+        let mut samples = Vec::new();
+        
+        loop {
+            let period = {
+                let mut sensors = self.sensors.write().await;
+                let sensor = sensors.get_mut(id).ok_or_else(|| anyhow::anyhow!("Unknown sensor {}", id))?;
+                if sensor.status() != SensorStatus::Active {
+                    anyhow::bail!("Sensor {} is not active", id);
+                }
+                let mut reading = sensor.read().await?;
+                if let Some(calibration) = self.calibrations.read().await.get(id) {
+                    apply_calibration(calibration, &mut reading.data);
+                }
+                samples.extend(reading.data.into_iter().filter(|x| x.is_finite()));
+                poll_period(sensor.sample_rate())
+            };
+            
+            if started.elapsed() + period > duration {
+                break;
+            }
+            tokio::time::sleep(period).await;
+        }
+        
+        if samples.len() < 2 {
+            anyhow::bail!("Too few samples from {} to characterize", id);
+        }
+        let profile = NoiseProfile::from_samples(id, sensor_type, &samples, started.elapsed().as_secs_f64());
+        self.save_noise_profile(profile.clone()).await;
+        
+        info!("Characterized {}: σ {:.4} from {} samples", id, profile.noise_floor, samples.len());
+        Ok(profile)
+    }
+    
+    /// Noise profile from the last `characterize` of a sensor
+    pub async fn noise_profile(&self, id: &str) -> Option<NoiseProfile> {
+        self.noise_profiles.read().await.get(id).cloned()
+    }
+    
+    /// Stored noise profile for a sensor, from the database when attached
+    fn load_noise_profile(&self, id: &str) -> Option<NoiseProfile> {
+        let db = self.database.read().clone()?;
+        let load = || -> Result<Option<NoiseProfile>> {
+            let Some(json) = db.get_setting(&format!("{}{}", NOISE_PROFILE_SETTING, id))? else {
+                return Ok(None);
+            };
+            Ok(Some(serde_json::from_str(&json)?))
+        };
+        match load() {
+            Ok(profile) => profile,
+            Err(e) => {
+                warn!("Failed to load noise profile for {}: {}", id, e);
+                None
+            }
+        }
+    }
+    
+    async fn save_noise_profile(&self, profile: NoiseProfile) {
+        let db = self.database.read().clone();
+        if let Some(db) = db {
+            let store = || -> Result<()> {
+                let json = serde_json::to_string(&profile)?;
+                db.set_setting(&format!("{}{}", NOISE_PROFILE_SETTING, profile.sensor_id), &json)
+            };
+            if let Err(e) = store() {
+                warn!("Failed to store noise profile for {}: {}", profile.sensor_id, e);
+            }
+        }
+        self.adopt_noise_profile(profile).await;
+    }
+    
+    async fn adopt_noise_profile(&self, profile: NoiseProfile) {
+        let analysis = self.analysis.read().clone();
+        if let Some(analysis) = analysis {
+            analysis.set_noise_floor(&profile.sensor_id, profile.noise_floor);
+        }
+        self.noise_profiles.write().await.insert(profile.sensor_id.clone(), profile);
+    }
+    
+    /// Stored calibration for a sensor, from the database when attached
+    pub fn load_calibration(&self, id: &str) -> Option<CalibrationData> {
+        let db = self.database.read().clone()?;
+        match db.load_calibration(id) {
+            Ok(calibration) => calibration,
+            Err(e) => {
+                warn!("Failed to load calibration for {}: {}", id, e);
+                None
+            }
+        }
+    }
+    
+    async fn save_calibration(&self, id: &str, sensor_type: SensorType, calibration: CalibrationData) {
+        let db = self.database.read().clone();
+        if let Some(db) = db {
+            if let Err(e) = db.store_calibration(id, sensor_type, &calibration) {
+                warn!("Failed to store calibration for {}: {}", id, e);
+            }
+        }
+        self.calibrations.write().await.insert(id.to_string(), calibration);
+    }
+    
+    async fn recalibrate_all(&self) {
+        let ids: Vec<String> = {
+            let sensors = self.sensors.read().await;
+            sensors.iter()
+                .filter(|(_, s)| s.status() == SensorStatus::Active)
+                .map(|(id, _)| id.clone())
+                .collect()
+        };
+        
+        for id in ids {
+            if let Err(e) = self.calibrate_sensor(&id).await {
+                warn!("Recalibration failed for {}: {}", id, e);
+            }
+        }
+    }
+    
+    async fn read_due_sensors(&self) {
+        let mut readings = Vec::new();
+        {
+            let mut sensors = self.sensors.write().await;
+            let mut health = self.health.write().await;
+            let calibrations = self.calibrations.read().await;
+            let mut scheduler = self.scheduler.lock().await;
+            scheduler.begin_tick();
+            let now = Instant::now();
+            
+            for id in scheduler.poll_order(sensors.keys()) {
+                let Some(sensor) = sensors.get_mut(&id) else {
+                    continue;
+                };
+                let polled = matches!(sensor.status(), SensorStatus::Active | SensorStatus::Reconnecting);
+                if !polled || health.get(&id).is_some_and(|h| h.disabled) {
+                    continue;
+                }
+                if !scheduler.take_due(&id, sensor.sample_rate(), sensor.due_at(), now) {
+                    continue;
+                }
+                
+                match sensor.read().await {
+                    Ok(mut reading) => {
+                        if let Some(calibration) = calibrations.get(&id) {
+                            apply_calibration(calibration, &mut reading.data);
+                        }
+                        
+                        // Update health
+                        if let Some(h) = health.get_mut(&id) {
+                            h.record_success(reading.quality);
+                        }
+                        scheduler.record_read(&id, true, now);
+                        
+                        readings.push(reading);
+                    }
+                    Err(e) => {
+                        if let Some(h) = health.get_mut(&id) {
+                            h.record_error(e.to_string());
+                        }
+                        scheduler.record_read(&id, false, now);
+                        debug!("Read error for {}: {}", id, e);
                     }
-                    debug!("Read error for {}: {}", id, e);
                 }
             }
         }
+        
+        // Publish outside the locks: under BackpressurePolicy::Block this may
+        // wait on a full consumer queue, and other tasks need the sensors
+        for reading in readings {
+            self.event_bus.publish_sensor_reading(reading).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use async_trait::async_trait;
+    
+    /// Sensor that stops producing readings while `paused` is set
+    struct PausableSensor {
+        paused: Arc<AtomicBool>,
+    }
+    
+    #[async_trait]
+    impl Sensor for PausableSensor {
+        fn id(&self) -> &str { "pausable" }
+        fn sensor_type(&self) -> SensorType { SensorType::EMFProbe }
+        fn status(&self) -> SensorStatus {
+            if self.paused.load(Ordering::SeqCst) { SensorStatus::Maintenance } else { SensorStatus::Active }
+        }
+        async fn connect(&mut self) -> Result<()> { Ok(()) }
+        async fn disconnect(&mut self) -> Result<()> { Ok(()) }
+        async fn calibrate(&mut self) -> Result<CalibrationData> { anyhow::bail!("not supported") }
+        async fn read(&mut self) -> Result<SensorReading> {
+            Ok(SensorReading::new("pausable", SensorType::EMFProbe, vec![1.0]))
+        }
+        fn sample_rate(&self) -> f64 { 20.0 }
+        fn set_sample_rate(&mut self, _rate: f64) -> Result<()> { Ok(()) }
+        fn config(&self) -> serde_json::Value { serde_json::Value::Null }
+        fn set_config(&mut self, _config: serde_json::Value) -> Result<()> { Ok(()) }
+    }
+    
+    #[test]
+    fn test_stale_after_has_a_floor() {
+        assert_eq!(stale_after(MAX_POLL_HZ), MIN_STALE_AFTER);
+        assert_eq!(stale_after(20.0), MIN_STALE_AFTER);
+        assert_eq!(stale_after(0.5), Duration::from_secs(4));
+    }
+    
+    #[tokio::test]
+    async fn test_paused_sensor_goes_stale() {
+        let config = Arc::new(Config::default());
+        let manager = SensorManager::new(config, Arc::new(EventBus::new(16)), false).await.unwrap();
+        let paused = Arc::new(AtomicBool::new(false));
+        manager.add_sensor(Box::new(PausableSensor { paused: paused.clone() })).await.unwrap();
+        
+        manager.read_due_sensors().await;
+        let health = manager.health("pausable").await.unwrap();
+        assert_eq!(health.health, HealthStatus::Healthy);
+        assert_eq!(health.readings_count, 1);
+        
+        paused.store(true, Ordering::SeqCst);
+        tokio::time::sleep(stale_after(20.0) + Duration::from_millis(50)).await;
+        manager.read_due_sensors().await;
+        assert_eq!(manager.health("pausable").await.unwrap().health, HealthStatus::Stale);
+        
+        paused.store(false, Ordering::SeqCst);
+        manager.read_due_sensors().await;
+        assert_eq!(manager.health("pausable").await.unwrap().health, HealthStatus::Healthy);
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_loop_reads_each_sensor_at_its_rate() {
+        let manager = SensorManager::new(Arc::new(Config::default()), Arc::new(EventBus::new(16)), false).await.unwrap();
+        manager.add_sensor(Box::new(SensorSimulator::with_seed("fast", SensorType::EMFProbe, 10.0, 1))).await.unwrap();
+        manager.add_sensor(Box::new(SensorSimulator::with_seed("slow", SensorType::GeigerCounter, 1.0, 2))).await.unwrap();
+        manager.set_priority("slow", Priority::Low).await.unwrap();
+        assert!(manager.set_priority("missing", Priority::High).await.is_err());
+        manager.start_all().await.unwrap();
+        
+        // Two seconds of poll ticks
+        for _ in 0..200 {
+            manager.read_due_sensors().await;
+            tokio::time::advance(poll_period(MAX_POLL_HZ)).await;
+        }
+        
+        let stats: HashMap<String, SamplingStats> = manager.sampling_stats().await
+            .into_iter()
+            .map(|s| (s.sensor_id.clone(), s))
+            .collect();
+        assert_eq!(stats["fast"].reads, 20);
+        assert_eq!(stats["slow"].reads, 2);
+        assert_eq!(stats["slow"].priority, Priority::Low);
+        assert!((stats["fast"].target_hz - 10.0).abs() < 1e-6);
+        assert!((stats["fast"].actual_hz - 10.0).abs() < 1e-6);
+    }
+    
+    /// Sensor that comes back on its first read after losing its device
+    struct UnpluggedSensor {
+        status: SensorStatus,
+    }
+    
+    #[async_trait]
+    impl Sensor for UnpluggedSensor {
+        fn id(&self) -> &str { "unplugged" }
+        fn sensor_type(&self) -> SensorType { SensorType::EMFProbe }
+        fn status(&self) -> SensorStatus { self.status }
+        async fn connect(&mut self) -> Result<()> { Ok(()) }
+        async fn disconnect(&mut self) -> Result<()> { Ok(()) }
+        async fn calibrate(&mut self) -> Result<CalibrationData> { anyhow::bail!("not supported") }
+        async fn read(&mut self) -> Result<SensorReading> {
+            self.status = SensorStatus::Active;
+            Ok(SensorReading::new("unplugged", SensorType::EMFProbe, vec![1.0]))
+        }
+        fn sample_rate(&self) -> f64 { 10.0 }
+        fn set_sample_rate(&mut self, _rate: f64) -> Result<()> { Ok(()) }
+        fn config(&self) -> serde_json::Value { serde_json::Value::Null }
+        fn set_config(&mut self, _config: serde_json::Value) -> Result<()> { Ok(()) }
+    }
+    
+    #[tokio::test]
+    async fn test_reconnecting_sensor_is_still_polled() {
+        let event_bus = Arc::new(EventBus::new(16));
+        let manager = SensorManager::new(Arc::new(Config::default()), event_bus.clone(), false).await.unwrap();
+        manager.add_sensor(Box::new(UnpluggedSensor { status: SensorStatus::Reconnecting })).await.unwrap();
+        let mut readings = event_bus.subscribe_readings();
+        
+        manager.read_due_sensors().await;
+        assert_eq!(readings.try_recv().unwrap().sensor_id, "unplugged");
+        assert_eq!(manager.active_count().await, 1);
+    }
+    
+    /// Sensor reporting a constant 10.0 with a configurable calibration offset
+    struct OffsetSensor {
+        offset: f64,
+        status: SensorStatus,
+    }
+    
+    #[async_trait]
+    impl Sensor for OffsetSensor {
+        fn id(&self) -> &str { "offset" }
+        fn sensor_type(&self) -> SensorType { SensorType::Barometer }
+        fn status(&self) -> SensorStatus { self.status }
+        async fn connect(&mut self) -> Result<()> { Ok(()) }
+        async fn disconnect(&mut self) -> Result<()> { Ok(()) }
+        async fn calibrate(&mut self) -> Result<CalibrationData> {
+            self.status = SensorStatus::Active;
+            Ok(CalibrationData {
+                offset: vec![self.offset],
+                scale: vec![1.0],
+                noise_floor: 0.0,
+                timestamp: Utc::now(),
+                temperature: None,
+                notes: String::new(),
+                signature: vec![],
+            })
+        }
+        async fn read(&mut self) -> Result<SensorReading> {
+            Ok(SensorReading::new("offset", SensorType::Barometer, vec![10.0]))
+        }
+        fn sample_rate(&self) -> f64 { 1.0 }
+        fn set_sample_rate(&mut self, _rate: f64) -> Result<()> { Ok(()) }
+        fn config(&self) -> serde_json::Value { serde_json::Value::Null }
+        fn set_config(&mut self, _config: serde_json::Value) -> Result<()> { Ok(()) }
+    }
+    
+    #[tokio::test]
+    async fn test_calibration_survives_reopen() {
+        let db_config = crate::config::DatabaseConfig {
+            path: std::env::temp_dir().join(format!("glowbarn-calibration-{}.db", uuid::Uuid::new_v4())),
+            ..Default::default()
+        };
+        let config = Arc::new(Config::default());
+        
+        {
+            let db = Arc::new(Database::open(&db_config, None).unwrap());
+            let manager = SensorManager::new(config.clone(), Arc::new(EventBus::new(16)), false).await.unwrap();
+            manager.attach_database(db);
+            manager.add_sensor(Box::new(OffsetSensor { offset: 3.0, status: SensorStatus::Disconnected })).await.unwrap();
+            manager.calibrate_sensor("offset").await.unwrap();
+        }
+        
+        // After a restart the sensor reports a different offset, but the stored one wins
+        let db = Arc::new(Database::open(&db_config, None).unwrap());
+        let event_bus = Arc::new(EventBus::new(16));
+        let manager = SensorManager::new(config, event_bus.clone(), false).await.unwrap();
+        manager.attach_database(db);
+        manager.add_sensor(Box::new(OffsetSensor { offset: 0.0, status: SensorStatus::Disconnected })).await.unwrap();
+        
+        assert_eq!(manager.load_calibration("offset").unwrap().offset, vec![3.0]);
+        
+        let mut readings = event_bus.subscribe_readings();
+        manager.connect_all().await;
+        manager.read_due_sensors().await;
+        assert_eq!(readings.try_recv().unwrap().data, vec![7.0]);
+        
+        let _ = std::fs::remove_file(&db_config.path);
+    }
+    
+    /// Sensor that is Active between `connect()` and `disconnect()`
+    struct SwitchSensor {
+        status: SensorStatus,
+    }
+    
+    #[async_trait]
+    impl Sensor for SwitchSensor {
+        fn id(&self) -> &str { "switch" }
+        fn sensor_type(&self) -> SensorType { SensorType::Geophone }
+        fn status(&self) -> SensorStatus { self.status }
+        async fn connect(&mut self) -> Result<()> {
+            self.status = SensorStatus::Active;
+            Ok(())
+        }
+        async fn disconnect(&mut self) -> Result<()> {
+            self.status = SensorStatus::Disconnected;
+            Ok(())
+        }
+        async fn calibrate(&mut self) -> Result<CalibrationData> {
+            Ok(CalibrationData {
+                offset: vec![0.0],
+                scale: vec![1.0],
+                noise_floor: 0.0,
+                timestamp: Utc::now(),
+                temperature: None,
+                notes: String::new(),
+                signature: vec![],
+            })
+        }
+        async fn read(&mut self) -> Result<SensorReading> {
+            Ok(SensorReading::new("switch", SensorType::Geophone, vec![0.5]))
+        }
+        fn sample_rate(&self) -> f64 { 10.0 }
+        fn set_sample_rate(&mut self, _rate: f64) -> Result<()> { Ok(()) }
+        fn config(&self) -> serde_json::Value { serde_json::Value::Null }
+        fn set_config(&mut self, _config: serde_json::Value) -> Result<()> { Ok(()) }
+    }
+    
+    #[tokio::test]
+    async fn test_disable_and_enable_sensor() {
+        let db_config = crate::config::DatabaseConfig {
+            path: std::env::temp_dir().join(format!("glowbarn-disable-{}.db", uuid::Uuid::new_v4())),
+            ..Default::default()
+        };
+        let db = Arc::new(Database::open(&db_config, None).unwrap());
+        let event_bus = Arc::new(EventBus::new(16));
+        let manager = SensorManager::new(Arc::new(Config::default()), event_bus.clone(), false).await.unwrap();
+        manager.attach_database(db.clone());
+        manager.add_sensor(Box::new(SwitchSensor { status: SensorStatus::Disconnected })).await.unwrap();
+        let mut readings = event_bus.subscribe_readings();
+        
+        manager.start_all().await.unwrap();
+        manager.read_due_sensors().await;
+        assert!(readings.try_recv().is_ok());
+        
+        manager.disable("switch").await.unwrap();
+        assert!(!manager.is_enabled("switch").await);
+        assert_eq!(manager.health("switch").await.unwrap().health, HealthStatus::Offline);
+        assert_eq!(db.sensor_status("switch").unwrap().as_deref(), Some("offline"));
+        
+        // Disabled sensors are skipped by the scheduler and by start_all
+        manager.start_all().await.unwrap();
+        manager.read_due_sensors().await;
+        assert!(readings.try_recv().is_err());
+        assert_eq!(manager.active_count().await, 0);
+        assert!(manager.start_sensor("switch").await.is_err());
+        
+        manager.enable("switch").await.unwrap();
+        assert!(manager.is_enabled("switch").await);
+        manager.read_due_sensors().await;
+        assert!(readings.try_recv().is_ok());
+        
+        manager.stop_all().await.unwrap();
+        assert!(manager.is_enabled("switch").await);
+        assert_eq!(manager.active_count().await, 0);
+        
+        assert!(manager.disable("missing").await.is_err());
+        
+        let _ = std::fs::remove_file(&db_config.path);
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_characterize_recovers_simulator_noise() {
+        let db_config = crate::config::DatabaseConfig {
+            path: std::env::temp_dir().join(format!("glowbarn-noise-{}.db", uuid::Uuid::new_v4())),
+            ..Default::default()
+        };
+        let db = Arc::new(Database::open(&db_config, None).unwrap());
+        let manager = SensorManager::new(Arc::new(Config::default()), Arc::new(EventBus::new(16)), false).await.unwrap();
+        manager.attach_database(db.clone());
+        // Static meter reads N(100, 20) V/m; no discharges while characterizing
+        let mut sim = SensorSimulator::with_seed("static", SensorType::StaticMeter, 100.0, 7);
+        sim.set_config(serde_json::json!({ "anomaly_probability": 0.0 })).unwrap();
+        manager.add_sensor(Box::new(sim)).await.unwrap();
+        
+        assert!(manager.characterize("static", Duration::from_millis(50)).await.is_err());
+        manager.start_sensor("static").await.unwrap();
+        let calibration = manager.calibration("static").await.map(|c| c.noise_floor);
+        
+        let profile = manager.characterize("static", Duration::from_secs(2)).await.unwrap();
+        assert!(profile.summary.count >= 100, "only {} samples", profile.summary.count);
+        assert!((profile.summary.mean - 100.0).abs() < 5.0, "mean {}", profile.summary.mean);
+        assert!((profile.summary.std_dev - 20.0).abs() < 4.0, "std {}", profile.summary.std_dev);
+        assert!((profile.noise_floor - 20.0).abs() < 5.0, "noise floor {}", profile.noise_floor);
+        assert_eq!(profile.histogram.iter().sum::<usize>(), profile.summary.count);
+        
+        let (p5, p50, p95) = (profile.quantile(5.0).unwrap(), profile.quantile(50.0).unwrap(), profile.quantile(95.0).unwrap());
+        assert!(p5 < p50 && p50 < p95);
+        
+        // Kept as the sensor's noise baseline, leaving its calibration alone
+        assert_eq!(manager.calibration("static").await.map(|c| c.noise_floor), calibration);
+        assert!(manager.noise_profile("static").await.is_some());
+        
+        // and restored by the next manager on the same database
+        let restarted = SensorManager::new(Arc::new(Config::default()), Arc::new(EventBus::new(16)), false).await.unwrap();
+        restarted.attach_database(db);
+        assert_eq!(restarted.load_noise_profile("static").map(|p| p.noise_floor), Some(profile.noise_floor));
+        
+        let _ = std::fs::remove_file(&db_config.path);
+    }
+    
+    #[tokio::test]
+    async fn test_registered_sensor_is_built_and_read() {
+        let mut registry = SensorRegistry::new();
+        registry.register("switch-driver", |_: &SensorConfig| -> Box<dyn Sensor> {
+            Box::new(SwitchSensor { status: SensorStatus::Disconnected })
+        });
+        assert_eq!(registry.keys(), vec!["switch-driver"]);
+        assert!(registry.build("missing", &SensorConfig::default()).is_err());
+        
+        let mut config = Config::default();
+        config.sensors.drivers = vec!["switch-driver".to_string()];
+        let event_bus = Arc::new(EventBus::new(16));
+        let manager = SensorManager::new(Arc::new(config), event_bus.clone(), false).await.unwrap();
+        assert_eq!(manager.add_from_registry(&registry).await.unwrap(), 1);
+        
+        let mut readings = event_bus.subscribe_readings();
+        manager.connect_all().await;
+        manager.read_due_sensors().await;
+        let reading = readings.try_recv().unwrap();
+        assert_eq!(reading.sensor_id, "switch");
+        assert_eq!(reading.data, vec![0.5]);
+        
+        // Naming a driver nobody registered is a configuration error
+        let mut config = Config::default();
+        config.sensors.drivers = vec!["nope".to_string()];
+        let manager = SensorManager::new(Arc::new(config), Arc::new(EventBus::new(16)), false).await.unwrap();
+        assert!(manager.add_from_registry(&registry).await.is_err());
     }
 }
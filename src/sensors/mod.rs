@@ -19,9 +19,13 @@ mod magnetic;
 mod ionization;
 mod quantum;
 mod simulator;
+mod replay;
+mod noise;
+#[cfg(feature = "serial")]
+mod serial;
 
-pub use manager::SensorManager;
-pub use traits::{Sensor, SensorReading, SensorType, SensorStatus, CalibrationData, SensorHealth};
+pub use manager::{SensorManager, SensorRegistry, SensorFactory, poll_period, MAX_POLL_HZ};
+pub use traits::{Sensor, SensorReading, SensorType, SensorStatus, CalibrationData, SensorHealth, HealthStatus, DownsampleMethod, downsample, Quantity, convert_unit, unit_quantity};
 pub use thermal::*;
 pub use seismic::*;
 pub use emf::*;
@@ -34,4 +38,8 @@ pub use capacitive::*;
 pub use magnetic::*;
 pub use ionization::*;
 pub use quantum::*;
-pub use simulator::SensorSimulator;
+pub use simulator::{SensorSimulator, Scenario, ScenarioEvent, ScenarioPlayer};
+pub use replay::ReplaySensor;
+pub use noise::{NoiseProfile, NOISE_HISTOGRAM_BINS, NOISE_QUANTILES};
+#[cfg(feature = "serial")]
+pub use serial::{SerialSensor, discover_ports, parse_line};
@@ -0,0 +1,66 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Noise characterization of a sensor sampled while nothing is happening
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{histogram, StatisticalAnalyzer, StatisticalSummary};
+use super::SensorType;
+
+/// Bins in a noise profile's histogram
+pub const NOISE_HISTOGRAM_BINS: usize = 32;
+
+/// Percentiles reported in a noise profile
+pub const NOISE_QUANTILES: [f64; 7] = [1.0, 5.0, 25.0, 50.0, 75.0, 95.0, 99.0];
+
+/// Interquartile range of a unit normal distribution
+const NORMAL_IQR: f64 = 1.349;
+
+/// Distribution of a sensor's quiet output, used as its noise baseline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoiseProfile {
+    pub sensor_id: String,
+    pub sensor_type: SensorType,
+    pub timestamp: DateTime<Utc>,
+    /// How long the sensor was sampled
+    pub duration_secs: f64,
+    pub summary: StatisticalSummary,
+    /// Counts in `NOISE_HISTOGRAM_BINS` equal-width bins over `summary.min..=summary.max`
+    pub histogram: Vec<usize>,
+    /// `(percentile, value)` for each of `NOISE_QUANTILES`
+    pub quantiles: Vec<(f64, f64)>,
+    /// Noise σ estimated from the interquartile range, so the odd spike
+    /// during characterization doesn't inflate it
+    pub noise_floor: f64,
+}
+
+impl NoiseProfile {
+    /// Profile `samples`, every value read from the sensor over `duration_secs`
+    pub fn from_samples(sensor_id: &str, sensor_type: SensorType, samples: &[f64], duration_secs: f64) -> Self {
+        let stats = StatisticalAnalyzer::new();
+        let summary = stats.summarize(samples);
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let quantiles = NOISE_QUANTILES.iter().map(|&p| (p, stats.percentile(&sorted, p))).collect();
+
+        Self {
+            sensor_id: sensor_id.to_string(),
+            sensor_type,
+            timestamp: Utc::now(),
+            duration_secs,
+            histogram: histogram(samples, NOISE_HISTOGRAM_BINS),
+            quantiles,
+            noise_floor: summary.iqr / NORMAL_IQR,
+            summary,
+        }
+    }
+
+    /// Value at percentile `p`, if it is one of `NOISE_QUANTILES`
+    pub fn quantile(&self, p: f64) -> Option<f64> {
+        self.quantiles.iter().find(|(q, _)| *q == p).map(|(_, v)| *v)
+    }
+}
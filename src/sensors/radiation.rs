@@ -4,11 +4,20 @@
 
 //! Radiation sensors - Geiger counters, scintillators, neutron detectors
 
+use std::collections::VecDeque;
 use async_trait::async_trait;
 use anyhow::{Result, bail};
 use chrono::Utc;
+use serde::{Deserialize, Serialize};
 
 use super::{Sensor, SensorReading, SensorType, SensorStatus, CalibrationData};
+use crate::analysis::poisson_upper_tail;
+
+/// Default tail probability below which a count is a spike, not shot noise
+pub const POISSON_SIGNIFICANCE: f64 = 1e-3;
+
+/// Readings needed before the rolling background rate is trusted
+const MIN_BACKGROUND_READINGS: usize = 5;
 
 /// Geiger-Müller counter
 pub struct GeigerSensor {
@@ -247,3 +256,129 @@ impl Sensor for DosimeterArraySensor {
     fn config(&self) -> serde_json::Value { serde_json::json!({"num_dosimeters": self.num_dosimeters}) }
     fn set_config(&mut self, _config: serde_json::Value) -> Result<()> { Ok(()) }
 }
+
+/// Outcome of checking one count against the background rate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoissonCheck {
+    pub count: f64,
+    /// Background counts expected per reading
+    pub lambda: f64,
+    /// P(X >= count) for X ~ Poisson(lambda)
+    pub p_value: f64,
+    pub spike: bool,
+    /// A spike after a count that wasn't one, i.e. the start of a burst
+    pub onset: bool,
+}
+
+/// Flags radiation counts too high to be statistical fluctuation.
+///
+/// Counts are Poisson, so their spread grows with the rate and a z-score
+/// misjudges both quiet and busy tubes. Each count is instead checked
+/// against the Poisson upper tail at the background rate λ, which is either
+/// fixed with `with_background` or the mean of the last `window` counts.
+/// Spikes are kept out of the rolling rate so a burst can't mask itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoissonSpikeDetector {
+    significance: f64,
+    window: usize,
+    recent: VecDeque<f64>,
+    background: Option<f64>,
+    // Whether the last count checked was a spike
+    #[serde(default)]
+    spiking: bool,
+}
+
+impl PoissonSpikeDetector {
+    pub fn new(window: usize) -> Self {
+        Self {
+            significance: POISSON_SIGNIFICANCE,
+            window: window.max(1),
+            recent: VecDeque::new(),
+            background: None,
+            spiking: false,
+        }
+    }
+    
+    pub fn with_significance(mut self, significance: f64) -> Self {
+        self.significance = significance;
+        self
+    }
+    
+    /// Use a known background of `lambda` counts per reading
+    pub fn with_background(mut self, lambda: f64) -> Self {
+        self.background = Some(lambda);
+        self
+    }
+    
+    /// Background counts per reading, once known
+    pub fn lambda(&self) -> Option<f64> {
+        if let Some(lambda) = self.background {
+            return Some(lambda);
+        }
+        if self.recent.len() < MIN_BACKGROUND_READINGS {
+            return None;
+        }
+        let n = self.recent.len() as f64;
+        // At least half a count per window, so a quiet spell of zeros
+        // doesn't make every later count impossible
+        Some((self.recent.iter().sum::<f64>() / n).max(0.5 / n))
+    }
+    
+    /// Check `count`, then fold it into the rolling rate unless it's a
+    /// spike. `None` while the rolling rate is still being learned.
+    pub fn check(&mut self, count: f64) -> Option<PoissonCheck> {
+        let result = self.lambda().map(|lambda| {
+            let p_value = poisson_upper_tail(count, lambda);
+            let spike = p_value < self.significance;
+            PoissonCheck { count, lambda, p_value, spike, onset: spike && !self.spiking }
+        });
+        self.spiking = result.is_some_and(|r| r.spike);
+        
+        if !result.is_some_and(|r| r.spike) {
+            self.recent.push_back(count);
+            if self.recent.len() > self.window {
+                self.recent.pop_front();
+            }
+        }
+        result
+    }
+    
+    /// Forget the rolling rate, e.g. after moving to a new location
+    pub fn reset(&mut self) {
+        self.recent.clear();
+        self.spiking = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poisson_detector_flags_real_spikes_only() {
+        let mut detector = PoissonSpikeDetector::new(30);
+        for i in 0..120 {
+            let check = detector.check(20.0);
+            assert_eq!(check.is_none(), i < MIN_BACKGROUND_READINGS);
+            assert!(!check.is_some_and(|c| c.spike), "20 CPM flagged: {:?}", check);
+        }
+        assert_eq!(detector.lambda(), Some(20.0));
+
+        let jump = detector.check(80.0).unwrap();
+        assert!(jump.spike && jump.onset);
+        assert!((jump.p_value / 4.6171994563209054e-24 - 1.0).abs() < 1e-6, "{:?}", jump);
+        // The spike stays out of the background
+        assert_eq!(detector.lambda(), Some(20.0));
+        // A burst starts once
+        let ongoing = detector.check(80.0).unwrap();
+        assert!(ongoing.spike && !ongoing.onset);
+
+        // Ordinary fluctuations at a known rate pass, a large excess doesn't
+        let mut known = PoissonSpikeDetector::new(30).with_background(20.0);
+        let typical = known.check(25.0).unwrap();
+        assert!((typical.p_value - 0.15677262182623773).abs() < 1e-9);
+        assert!(!typical.spike);
+        assert!((known.check(35.0).unwrap().p_value - 0.0014890338613391590).abs() < 1e-9);
+        assert!(known.check(40.0).unwrap().spike);
+    }
+}
@@ -0,0 +1,416 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Replay sensors - re-emit recorded readings with their original timing
+//!
+//! A recording (database time range or exported JSONL file) is split into
+//! one `ReplaySensor` per original sensor id. All sensors of a recording
+//! share a clock so cross-sensor timing is preserved. Emitted readings are
+//! re-stamped with the current time because downstream correlation windows
+//! are relative to now.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+use async_trait::async_trait;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use tokio::time::Instant;
+use tracing::warn;
+
+use super::{Sensor, SensorReading, SensorType, SensorStatus, CalibrationData};
+use crate::db::{Database, ReadingCursor};
+
+/// Readings fetched per database query while replaying a stored range
+pub const REPLAY_PAGE_SIZE: usize = 1000;
+
+/// Where a database-backed replay continues reading
+struct PagedSource {
+    db: Arc<Database>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    cursor: Option<ReadingCursor>,
+}
+
+/// Sensor that plays back a recorded sequence of readings
+///
+/// The sensor never waits inside `read`: `due_at` tells the manager when
+/// the next recorded reading is due, so a replay paces itself without
+/// holding up other sensors.
+pub struct ReplaySensor {
+    id: String,
+    sensor_type: SensorType,
+    status: SensorStatus,
+    sample_rate: f64,
+    time_scale: f64,
+    /// Readings ready to play, topped up from `pages` as they run out
+    readings: VecDeque<SensorReading>,
+    pages: Option<PagedSource>,
+    remaining: usize,
+    /// Recording time that maps to the start of playback
+    origin: DateTime<Utc>,
+    /// Playback start, shared by all sensors of one recording
+    clock: Arc<Mutex<Option<Instant>>>,
+}
+
+impl ReplaySensor {
+    /// Split a recording into one replay sensor per sensor id.
+    ///
+    /// `time_scale` > 1 plays back faster than real time.
+    pub fn from_readings(mut readings: Vec<SensorReading>, time_scale: f64) -> Result<Vec<Self>> {
+        check_time_scale(time_scale)?;
+        if readings.is_empty() {
+            bail!("Recording contains no readings");
+        }
+        
+        readings.sort_by_key(|r| r.timestamp);
+        let origin = readings[0].timestamp;
+        let clock = Arc::new(Mutex::new(None));
+        
+        let mut by_sensor: BTreeMap<String, VecDeque<SensorReading>> = BTreeMap::new();
+        for reading in readings {
+            by_sensor.entry(reading.sensor_id.clone()).or_default().push_back(reading);
+        }
+        
+        Ok(by_sensor
+            .into_iter()
+            .map(|(id, readings)| {
+                let (first, last) = (readings[0].timestamp, readings[readings.len() - 1].timestamp);
+                Self {
+                    sensor_type: readings[0].sensor_type,
+                    sample_rate: estimate_rate(readings.len(), first, last) * time_scale,
+                    id,
+                    status: SensorStatus::Disconnected,
+                    time_scale,
+                    remaining: readings.len(),
+                    readings,
+                    pages: None,
+                    origin,
+                    clock: clock.clone(),
+                }
+            })
+            .collect())
+    }
+    
+    /// Load a recording exported as JSON lines (one `SensorReading` per line)
+    pub fn from_jsonl(path: &Path, time_scale: f64) -> Result<Vec<Self>> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open recording {:?}", path))?;
+        
+        let mut readings = Vec::new();
+        for (n, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reading: SensorReading = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid reading on line {}", n + 1))?;
+            readings.push(reading);
+        }
+        
+        Self::from_readings(readings, time_scale)
+    }
+    
+    /// Replay the readings stored between `start` and `end`
+    ///
+    /// Each sensor pages through its own readings `REPLAY_PAGE_SIZE` at a
+    /// time, so a long session is never loaded whole.
+    pub fn from_database(db: &Arc<Database>, start: DateTime<Utc>, end: DateTime<Utc>, time_scale: f64) -> Result<Vec<Self>> {
+        check_time_scale(time_scale)?;
+        let spans = db.query_reading_spans(start, end)?;
+        let Some(origin) = spans.iter().map(|s| s.first).min() else {
+            bail!("Recording contains no readings");
+        };
+        let clock = Arc::new(Mutex::new(None));
+        
+        let mut sensors = Vec::with_capacity(spans.len());
+        for span in spans {
+            let mut sensor = Self {
+                sample_rate: estimate_rate(span.count, span.first, span.last) * time_scale,
+                id: span.sensor_id,
+                sensor_type: span.sensor_type,
+                status: SensorStatus::Disconnected,
+                time_scale,
+                readings: VecDeque::new(),
+                pages: Some(PagedSource { db: db.clone(), start, end, cursor: None }),
+                remaining: span.count,
+                origin,
+                clock: clock.clone(),
+            };
+            sensor.top_up()?;
+            sensors.push(sensor);
+        }
+        Ok(sensors)
+    }
+    
+    /// Load the readings recorded during a session
+    pub fn from_session(db: &Arc<Database>, session_id: &str, time_scale: f64) -> Result<Vec<Self>> {
+        let (start, end) = db.session_range(session_id)?
+            .with_context(|| format!("Unknown session {}", session_id))?;
+        Self::from_database(db, start, end, time_scale)
+    }
+    
+    /// Readings left to play
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+    
+    pub fn is_finished(&self) -> bool {
+        self.readings.is_empty()
+    }
+    
+    /// Fetch the next page of a database replay once the buffer runs dry
+    fn top_up(&mut self) -> Result<()> {
+        if !self.readings.is_empty() {
+            return Ok(());
+        }
+        let Some(ref mut source) = self.pages else {
+            return Ok(());
+        };
+        
+        let page = source.db.query_readings_page(
+            source.start,
+            source.end,
+            Some(self.id.as_str()),
+            source.cursor.as_ref(),
+            REPLAY_PAGE_SIZE,
+        )?;
+        for stored in &page.readings {
+            match stored.to_reading() {
+                Ok(reading) => self.readings.push_back(reading),
+                Err(e) => {
+                    warn!("Skipping undecodable reading {} of {}: {}", stored.id, self.id, e);
+                    self.remaining = self.remaining.saturating_sub(1);
+                }
+            }
+        }
+        match page.next {
+            Some(cursor) => source.cursor = Some(cursor),
+            None => self.pages = None,
+        }
+        Ok(())
+    }
+}
+
+fn check_time_scale(time_scale: f64) -> Result<()> {
+    if !(time_scale.is_finite() && time_scale > 0.0) {
+        bail!("Replay time scale must be positive, got {}", time_scale);
+    }
+    Ok(())
+}
+
+/// Mean read rate of `count` readings from `first` to `last`, 1 Hz if it
+/// can't be told
+fn estimate_rate(count: usize, first: DateTime<Utc>, last: DateTime<Utc>) -> f64 {
+    let span = (last - first).num_microseconds().unwrap_or(0) as f64 / 1e6;
+    if count < 2 || span <= 0.0 {
+        return 1.0;
+    }
+    (count - 1) as f64 / span
+}
+
+#[async_trait]
+impl Sensor for ReplaySensor {
+    fn id(&self) -> &str { &self.id }
+    fn sensor_type(&self) -> SensorType { self.sensor_type }
+    fn status(&self) -> SensorStatus { self.status }
+    
+    async fn connect(&mut self) -> Result<()> {
+        self.status = SensorStatus::Connected;
+        Ok(())
+    }
+    
+    async fn disconnect(&mut self) -> Result<()> {
+        self.status = SensorStatus::Disconnected;
+        Ok(())
+    }
+    
+    async fn calibrate(&mut self) -> Result<CalibrationData> {
+        // Recorded values are already calibrated
+        self.status = SensorStatus::Active;
+        Ok(CalibrationData {
+            offset: vec![0.0],
+            scale: vec![1.0],
+            noise_floor: 0.0,
+            timestamp: Utc::now(),
+            temperature: None,
+            notes: "Replay".to_string(),
+            signature: vec![],
+        })
+    }
+    
+    async fn read(&mut self) -> Result<SensorReading> {
+        let Some(due) = self.due_at() else {
+            self.status = SensorStatus::Disconnected;
+            bail!("Replay of {} finished", self.id);
+        };
+        if Instant::now() < due {
+            bail!("Next reading of replay {} is not due yet", self.id);
+        }
+        
+        let mut reading = self.readings.pop_front().expect("due_at saw a reading");
+        reading.timestamp = Utc::now();
+        self.remaining = self.remaining.saturating_sub(1);
+        
+        if let Err(e) = self.top_up() {
+            warn!("Replay of {} stopped early: {}", self.id, e);
+            self.pages = None;
+        }
+        if self.readings.is_empty() {
+            self.status = SensorStatus::Disconnected;
+        }
+        
+        Ok(reading)
+    }
+    
+    /// When the next recorded reading is due; starts the shared playback
+    /// clock on first use
+    fn due_at(&self) -> Option<Instant> {
+        let next = self.readings.front()?;
+        let start = *self.clock.lock().get_or_insert_with(Instant::now);
+        let offset = (next.timestamp - self.origin).to_std().unwrap_or_default();
+        Some(start + offset.div_f64(self.time_scale))
+    }
+    
+    fn sample_rate(&self) -> f64 { self.sample_rate }
+    fn set_sample_rate(&mut self, rate: f64) -> Result<()> { self.sample_rate = rate; Ok(()) }
+    fn config(&self) -> serde_json::Value {
+        serde_json::json!({"time_scale": self.time_scale, "remaining": self.remaining})
+    }
+    fn set_config(&mut self, _config: serde_json::Value) -> Result<()> { Ok(()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::time::Duration;
+    use crate::config::Config;
+    use crate::core::EventBus;
+    use crate::detection::{Detection, DetectionEngine};
+    use crate::sensors::SensorManager;
+    
+    /// Two sensors spiking at the same moment
+    fn recorded_spike() -> Vec<SensorReading> {
+        let t0 = Utc::now() - chrono::Duration::minutes(5);
+        let mut spike = vec![0.0; 20];
+        spike[19] = 50.0;
+        
+        [("emf-1", SensorType::EMFProbe, 0), ("geiger-1", SensorType::GeigerCounter, 20)]
+            .into_iter()
+            .map(|(id, sensor_type, ms)| {
+                let mut r = SensorReading::new(id, sensor_type, spike.clone());
+                r.timestamp = t0 + chrono::Duration::milliseconds(ms);
+                r
+            })
+            .collect()
+    }
+    
+    async fn first_detection(event_bus: Arc<EventBus>, feed: impl std::future::Future<Output = ()>) -> Detection {
+        let detection = Arc::new(DetectionEngine::new(Arc::new(Config::default()), event_bus.clone()).await.unwrap());
+        let mut detections = event_bus.subscribe_detections();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        
+        let runner = detection.clone();
+        tokio::spawn(async move { runner.run(shutdown_rx).await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        
+        feed.await;
+        let found = tokio::time::timeout(Duration::from_secs(5), detections.recv()).await.unwrap().unwrap();
+        let _ = shutdown_tx.send(());
+        found
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_replayed_spike_matches_live_detection() {
+        let recording = recorded_spike();
+        
+        // Live: publish the readings as they arrive
+        let live_bus = Arc::new(EventBus::new(64));
+        let live = first_detection(live_bus.clone(), {
+            let bus = live_bus.clone();
+            let recording = recording.clone();
+            async move {
+                for mut r in recording {
+                    r.timestamp = Utc::now();
+                    bus.publish_reading(r);
+                }
+            }
+        }).await;
+        
+        // Replay: the same readings from an exported file through the sensor manager
+        let path = std::env::temp_dir().join(format!("glowbarn-replay-{}.jsonl", uuid::Uuid::new_v4()));
+        {
+            let mut file = std::fs::File::create(&path).unwrap();
+            for r in &recording {
+                writeln!(file, "{}", serde_json::to_string(r).unwrap()).unwrap();
+            }
+        }
+        
+        let replay_bus = Arc::new(EventBus::new(64));
+        let manager = Arc::new(SensorManager::new(Arc::new(Config::default()), replay_bus.clone(), false).await.unwrap());
+        for sensor in ReplaySensor::from_jsonl(&path, 1.0).unwrap() {
+            manager.add_sensor(Box::new(sensor)).await.unwrap();
+        }
+        let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        let replayed = first_detection(replay_bus, {
+            let manager = manager.clone();
+            async move {
+                tokio::spawn(async move { manager.run(shutdown_rx).await });
+            }
+        }).await;
+        let _ = shutdown_tx.send(());
+        let _ = std::fs::remove_file(&path);
+        
+        assert_eq!(replayed.detection_type, live.detection_type);
+        assert!((replayed.confidence - live.confidence).abs() < 1e-9);
+        
+        let sensors = |d: &Detection| {
+            let mut ids: Vec<_> = d.sensors.iter().map(|s| s.sensor_id.clone()).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(sensors(&replayed), sensors(&live));
+    }
+    
+    #[tokio::test]
+    async fn test_database_replay_pages_through_session() {
+        let db_config = crate::config::DatabaseConfig {
+            path: std::env::temp_dir().join(format!("glowbarn-replay-{}.db", uuid::Uuid::new_v4())),
+            ..Default::default()
+        };
+        let db = Arc::new(Database::open(&db_config, None).unwrap());
+        let t0 = Utc::now() - chrono::Duration::minutes(5);
+        let total = REPLAY_PAGE_SIZE * 2 + 500;
+        let recording: Vec<SensorReading> = (0..total)
+            .map(|i| {
+                let mut r = SensorReading::new("emf-1", SensorType::EMFProbe, vec![i as f64]);
+                r.timestamp = t0 + chrono::Duration::milliseconds(i as i64);
+                r
+            })
+            .collect();
+        db.store_readings_batch(&recording).unwrap();
+        
+        let mut sensors = ReplaySensor::from_database(&db, t0, Utc::now(), 1e6).unwrap();
+        assert_eq!(sensors.len(), 1);
+        let sensor = &mut sensors[0];
+        assert_eq!(sensor.remaining(), total);
+        sensor.calibrate().await.unwrap();
+        
+        let mut values = Vec::new();
+        while !sensor.is_finished() {
+            assert!(sensor.readings.len() <= REPLAY_PAGE_SIZE);
+            let due = sensor.due_at().unwrap();
+            tokio::time::sleep_until(due).await;
+            values.push(sensor.read().await.unwrap().data[0]);
+        }
+        assert_eq!(values, (0..total).map(|i| i as f64).collect::<Vec<_>>());
+        assert_eq!(sensor.remaining(), 0);
+        assert_eq!(sensor.status(), SensorStatus::Disconnected);
+        
+        let _ = std::fs::remove_file(&db_config.path);
+    }
+}
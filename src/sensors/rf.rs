@@ -7,9 +7,16 @@
 use async_trait::async_trait;
 use anyhow::{Result, bail};
 use chrono::Utc;
+use serde::{Deserialize, Serialize};
 
 use super::{Sensor, SensorReading, SensorType, SensorStatus, CalibrationData};
 
+/// Lower edge of a survey spectrum's first bin, Hz
+pub const SPECTRUM_START_HZ: f64 = 1_000_000.0;
+
+/// Upper edge of a survey spectrum's last bin, Hz
+pub const SPECTRUM_STOP_HZ: f64 = 6_000_000_000.0;
+
 /// Software-Defined Radio receiver
 pub struct SDRSensor {
     id: String,
@@ -275,3 +282,160 @@ impl Sensor for EMIDetectorSensor {
     fn config(&self) -> serde_json::Value { serde_json::json!({}) }
     fn set_config(&mut self, _config: serde_json::Value) -> Result<()> { Ok(()) }
 }
+
+/// Frequency band of a transmitter expected on site
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnownBand {
+    pub name: String,
+    pub start_hz: f64,
+    pub stop_hz: f64,
+}
+
+impl KnownBand {
+    pub fn new(name: &str, start_hz: f64, stop_hz: f64) -> Self {
+        Self { name: name.to_string(), start_hz, stop_hz }
+    }
+}
+
+/// FM broadcast and the 2.4 and 5 GHz WiFi bands
+pub fn default_known_bands() -> Vec<KnownBand> {
+    vec![
+        KnownBand::new("FM", 87.5e6, 108e6),
+        KnownBand::new("WiFi 2.4 GHz", 2.4e9, 2.4835e9),
+        KnownBand::new("WiFi 5 GHz", 5.15e9, 5.85e9),
+    ]
+}
+
+/// Local maximum of a power spectrum standing out of the noise floor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpectralPeak {
+    pub bin: usize,
+    /// Centre of the bin, once the spectrum's span is known
+    pub frequency_hz: Option<f64>,
+    /// Power in the spectrum's units (dBm for the SDR)
+    pub power: f64,
+    /// Height above the noise floor in dB
+    pub snr_db: f64,
+    /// Name of the known band the bin overlaps
+    pub known_band: Option<String>,
+}
+
+impl SpectralPeak {
+    pub fn is_known(&self) -> bool {
+        self.known_band.is_some()
+    }
+}
+
+/// Peaks of a dB power spectrum more than `noise_floor_sigma` noise
+/// deviations above the floor, strongest first.
+///
+/// The floor is the median bin and its spread the scaled median absolute
+/// deviation, so the peaks themselves barely move either. Frequencies and
+/// bands are filled in by `classify_spectral_peaks`.
+pub fn detect_spectral_peaks(spectrum: &[f64], noise_floor_sigma: f64) -> Vec<SpectralPeak> {
+    if spectrum.len() < 3 {
+        return Vec::new();
+    }
+    
+    let median = |mut values: Vec<f64>| {
+        values.sort_by(|a, b| a.total_cmp(b));
+        values[values.len() / 2]
+    };
+    let floor = median(spectrum.to_vec());
+    let spread = (1.4826 * median(spectrum.iter().map(|x| (x - floor).abs()).collect())).max(1e-10);
+    let threshold = floor + noise_floor_sigma * spread;
+    
+    let mut peaks: Vec<SpectralPeak> = (0..spectrum.len())
+        .filter(|&bin| {
+            let power = spectrum[bin];
+            let left = bin.checked_sub(1).map_or(f64::MIN, |i| spectrum[i]);
+            let right = spectrum.get(bin + 1).copied().unwrap_or(f64::MIN);
+            // Ties go to the lower bin, so a flat-topped peak counts once
+            power > threshold && power > left && power >= right
+        })
+        .map(|bin| SpectralPeak {
+            bin,
+            frequency_hz: None,
+            power: spectrum[bin],
+            snr_db: spectrum[bin] - floor,
+            known_band: None,
+        })
+        .collect();
+    peaks.sort_by(|a, b| b.power.total_cmp(&a.power));
+    peaks
+}
+
+/// Give peaks of a `bins`-bin spectrum spanning `start_hz..stop_hz` their
+/// frequency and the first known band their bin overlaps
+pub fn classify_spectral_peaks(peaks: &mut [SpectralPeak], bins: usize, start_hz: f64, stop_hz: f64, bands: &[KnownBand]) {
+    let width = (stop_hz - start_hz) / bins.max(1) as f64;
+    for peak in peaks {
+        let low = start_hz + peak.bin as f64 * width;
+        let high = low + width;
+        peak.frequency_hz = Some(low + width / 2.0);
+        peak.known_band = bands.iter()
+            .find(|band| band.start_hz < high && band.stop_hz > low)
+            .map(|band| band.name.clone());
+    }
+}
+
+/// Frequency span of a sensor's spectra, from its `Sensor::config`: a
+/// sweep's `start_frequency`..`stop_frequency`, or `bandwidth` around the
+/// tuned `center_frequency`
+pub fn spectrum_span(config: &serde_json::Value) -> Option<(f64, f64)> {
+    let get = |key| config.get(key).and_then(|v| v.as_f64());
+    if let (Some(start), Some(stop)) = (get("start_frequency"), get("stop_frequency")) {
+        return (stop > start).then_some((start, stop));
+    }
+    let (center, bandwidth) = (get("center_frequency")?, get("bandwidth")?);
+    (bandwidth > 0.0).then(|| (center - bandwidth / 2.0, center + bandwidth / 2.0))
+}
+
+/// Bin of a `bins`-bin survey spectrum holding `frequency_hz`
+pub fn spectrum_bin(frequency_hz: f64, bins: usize) -> usize {
+    let fraction = (frequency_hz - SPECTRUM_START_HZ) / (SPECTRUM_STOP_HZ - SPECTRUM_START_HZ);
+    ((fraction * bins as f64) as usize).min(bins.saturating_sub(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_distr::{Distribution, Normal};
+
+    #[test]
+    fn test_known_signals_are_recognized_and_novel_peak_flagged() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(256);
+        let noise = Normal::new(-90.0, 3.0).unwrap();
+        let mut spectrum: Vec<f64> = (0..256).map(|_| noise.sample(&mut rng)).collect();
+        let fm = spectrum_bin(98e6, 256);
+        let wifi = spectrum_bin(2.437e9, 256);
+        for (bin, power) in [(fm, -50.0), (wifi, -60.0)] {
+            spectrum[bin] = power;
+            spectrum[bin - 1] = power - 10.0;
+            spectrum[bin + 1] = power - 10.0;
+        }
+        spectrum[60] = -30.0;
+
+        let mut peaks = detect_spectral_peaks(&spectrum, 6.0);
+        classify_spectral_peaks(&mut peaks, spectrum.len(), SPECTRUM_START_HZ, SPECTRUM_STOP_HZ, &default_known_bands());
+        assert_eq!(peaks.iter().map(|p| p.bin).collect::<Vec<_>>(), vec![60, fm, wifi]);
+
+        assert!(!peaks[0].is_known());
+        assert!((peaks[0].snr_db - 60.0).abs() < 3.0, "{:?}", peaks[0]);
+        let novel_hz = peaks[0].frequency_hz.unwrap();
+        assert!((1.40e9..1.43e9).contains(&novel_hz), "{}", novel_hz);
+        assert_eq!(peaks[1].known_band.as_deref(), Some("FM"));
+        assert_eq!(peaks[2].known_band.as_deref(), Some("WiFi 2.4 GHz"));
+    }
+    
+    #[test]
+    fn test_span_comes_from_sensor_settings() {
+        assert_eq!(spectrum_span(&SDRSensor::new("sdr").config()), Some((99e6, 101e6)));
+        assert_eq!(
+            spectrum_span(&SpectrumAnalyzerSensor::new("sa").config()),
+            Some((SPECTRUM_START_HZ, SPECTRUM_STOP_HZ)),
+        );
+        assert_eq!(spectrum_span(&serde_json::json!({"gain": 30.0})), None);
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Serial-port sensor backend
+//!
+//! Devices stream one reading per line:
+//!
+//! ```text
+//! <sensor_type>,<value>,<value>...\n
+//! ```
+//!
+//! where `<sensor_type>` is a `SensorType` variant name (e.g. `EMFProbe`)
+//! or a number for `Custom(n)`. A port that disappears leaves the sensor
+//! `Reconnecting`; the manager keeps polling it and each read retries the
+//! port with exponential backoff.
+
+use std::time::Duration;
+use async_trait::async_trait;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Utc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::time::Instant;
+use tokio_serial::{SerialPortBuilderExt, SerialPortType, SerialStream};
+use tracing::{info, warn};
+
+use super::{Sensor, SensorReading, SensorType, SensorStatus, CalibrationData};
+use crate::config::default_serial_baud_rate;
+
+/// How long a read waits for a complete line
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// List USB serial ports that may have sensors attached
+pub fn discover_ports() -> Result<Vec<String>> {
+    let ports = tokio_serial::available_ports()?;
+    Ok(ports
+        .into_iter()
+        .filter(|p| matches!(p.port_type, SerialPortType::UsbPort(_)))
+        .map(|p| p.port_name)
+        .collect())
+}
+
+/// Parse one protocol line into its sensor type and values
+pub fn parse_line(line: &str) -> Result<(SensorType, Vec<f64>)> {
+    let mut fields = line.trim().split(',').map(str::trim);
+    
+    let type_field = fields.next().filter(|f| !f.is_empty())
+        .ok_or_else(|| anyhow!("Empty line"))?;
+    let sensor_type: SensorType = type_field.parse()?;
+    
+    let data = fields
+        .map(|f| f.parse::<f64>().with_context(|| format!("Invalid value '{}'", f)))
+        .collect::<Result<Vec<_>>>()?;
+    if data.is_empty() {
+        bail!("No values for {:?}", sensor_type);
+    }
+    
+    Ok((sensor_type, data))
+}
+
+/// Sensor attached to a serial port speaking the line protocol
+pub struct SerialSensor {
+    id: String,
+    port_name: String,
+    baud_rate: u32,
+    sensor_type: SensorType,
+    status: SensorStatus,
+    sample_rate: f64,
+    sequence: u64,
+    reader: Option<BufReader<SerialStream>>,
+    line: String,
+    reconnect_delay: Duration,
+    next_reconnect: Option<Instant>,
+}
+
+impl SerialSensor {
+    pub fn new(id: &str, port_name: &str, baud_rate: u32) -> Self {
+        Self {
+            id: id.to_string(),
+            port_name: port_name.to_string(),
+            baud_rate,
+            sensor_type: SensorType::Custom(0),
+            status: SensorStatus::Disconnected,
+            sample_rate: 10.0,
+            sequence: 0,
+            reader: None,
+            line: String::new(),
+            reconnect_delay: MIN_RECONNECT_DELAY,
+            next_reconnect: None,
+        }
+    }
+    
+    /// Wrap an already-open stream (e.g. one end of a pty pair)
+    pub fn from_stream(id: &str, stream: SerialStream) -> Self {
+        let mut sensor = Self::new(id, "", default_serial_baud_rate());
+        sensor.reader = Some(BufReader::new(stream));
+        sensor.status = SensorStatus::Connected;
+        sensor
+    }
+    
+    pub fn port_name(&self) -> &str {
+        &self.port_name
+    }
+    
+    fn open(&mut self) -> Result<()> {
+        let stream = tokio_serial::new(&self.port_name, self.baud_rate)
+            .timeout(READ_TIMEOUT)
+            .open_native_async()
+            .with_context(|| format!("Failed to open {}", self.port_name))?;
+        self.reader = Some(BufReader::new(stream));
+        self.reconnect_delay = MIN_RECONNECT_DELAY;
+        self.next_reconnect = None;
+        Ok(())
+    }
+    
+    /// Reopen the port if it was lost, respecting the backoff delay
+    fn ensure_open(&mut self) -> Result<()> {
+        if self.reader.is_some() {
+            return Ok(());
+        }
+        if self.port_name.is_empty() {
+            bail!("Serial stream for {} closed", self.id);
+        }
+        if let Some(at) = self.next_reconnect {
+            if Instant::now() < at {
+                bail!("Waiting to reconnect {}", self.port_name);
+            }
+        }
+        
+        match self.open() {
+            Ok(()) => {
+                info!("Reconnected serial sensor {} on {}", self.id, self.port_name);
+                self.status = SensorStatus::Active;
+                Ok(())
+            }
+            Err(e) => {
+                self.next_reconnect = Some(Instant::now() + self.reconnect_delay);
+                self.reconnect_delay = (self.reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+                Err(e)
+            }
+        }
+    }
+    
+    fn mark_unplugged(&mut self, reason: &str) {
+        warn!("Serial sensor {} lost ({}), will reconnect", self.id, reason);
+        self.reader = None;
+        self.status = SensorStatus::Reconnecting;
+        self.next_reconnect = Some(Instant::now() + self.reconnect_delay);
+    }
+}
+
+#[async_trait]
+impl Sensor for SerialSensor {
+    fn id(&self) -> &str { &self.id }
+    fn sensor_type(&self) -> SensorType { self.sensor_type }
+    fn status(&self) -> SensorStatus { self.status }
+    
+    async fn connect(&mut self) -> Result<()> {
+        self.status = SensorStatus::Connecting;
+        if self.reader.is_none() {
+            if let Err(e) = self.open() {
+                self.status = SensorStatus::Error;
+                return Err(e);
+            }
+        }
+        self.status = SensorStatus::Connected;
+        Ok(())
+    }
+    
+    async fn disconnect(&mut self) -> Result<()> {
+        self.reader = None;
+        self.status = SensorStatus::Disconnected;
+        Ok(())
+    }
+    
+    async fn calibrate(&mut self) -> Result<CalibrationData> {
+        self.status = SensorStatus::Active;
+        Ok(CalibrationData {
+            offset: vec![0.0],
+            scale: vec![1.0],
+            noise_floor: 0.0,
+            timestamp: Utc::now(),
+            temperature: None,
+            notes: format!("Serial sensor on {}", self.port_name),
+            signature: vec![],
+        })
+    }
+    
+    async fn read(&mut self) -> Result<SensorReading> {
+        self.ensure_open()?;
+        let reader = self.reader.as_mut().expect("port opened above");
+        
+        self.line.clear();
+        let result = tokio::time::timeout(READ_TIMEOUT, reader.read_line(&mut self.line)).await;
+        match result {
+            Err(_) => bail!("Timed out waiting for data from {}", self.id),
+            Ok(Ok(0)) => {
+                self.mark_unplugged("end of stream");
+                bail!("Serial sensor {} disconnected", self.id);
+            }
+            Ok(Err(e)) => {
+                self.mark_unplugged(&e.to_string());
+                bail!("Serial sensor {} disconnected: {}", self.id, e);
+            }
+            Ok(Ok(_)) => {}
+        }
+        
+        let (sensor_type, data) = parse_line(&self.line)?;
+        self.sensor_type = sensor_type;
+        self.sequence += 1;
+        
+        let mut reading = SensorReading::new(&self.id, sensor_type, data);
+        reading.sequence = self.sequence;
+        reading.sample_rate = self.sample_rate;
+        Ok(reading)
+    }
+    
+    fn sample_rate(&self) -> f64 { self.sample_rate }
+    fn set_sample_rate(&mut self, rate: f64) -> Result<()> { self.sample_rate = rate; Ok(()) }
+    fn config(&self) -> serde_json::Value {
+        serde_json::json!({"port": self.port_name, "baud_rate": self.baud_rate})
+    }
+    fn set_config(&mut self, config: serde_json::Value) -> Result<()> {
+        if let Some(b) = config.get("baud_rate").and_then(|v| v.as_u64()) {
+            self.baud_rate = b as u32;
+        }
+        if let Some(p) = config.get("port").and_then(|v| v.as_str()) {
+            self.port_name = p.to_string();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    
+    #[test]
+    fn test_parse_line() {
+        let (t, data) = parse_line("EMFProbe,1.5,-2\r\n").unwrap();
+        assert_eq!(t, SensorType::EMFProbe);
+        assert_eq!(data, vec![1.5, -2.0]);
+        
+        assert_eq!(parse_line("7,0.1").unwrap().0, SensorType::Custom(7));
+        assert!(parse_line("Bogus,1").is_err());
+        assert!(parse_line("EMFProbe,abc").is_err());
+        assert!(parse_line("EMFProbe").is_err());
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_reads_lines_over_pty() {
+        let (device, host) = SerialStream::pair().unwrap();
+        let mut sensor = SerialSensor::from_stream("serial-1", host);
+        let mut device = device;
+        
+        device.write_all(b"GeigerCounter,12\nIonCounter,1.0,2.0\n").await.unwrap();
+        
+        let first = sensor.read().await.unwrap();
+        assert_eq!(first.sensor_type, SensorType::GeigerCounter);
+        assert_eq!(first.data, vec![12.0]);
+        
+        let second = sensor.read().await.unwrap();
+        assert_eq!(second.sensor_type, SensorType::IonCounter);
+        assert_eq!(second.data, vec![1.0, 2.0]);
+        assert_eq!(second.sequence, 2);
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unplugged_port_keeps_reconnecting() {
+        let (device, host) = SerialStream::pair().unwrap();
+        let mut sensor = SerialSensor::from_stream("serial-1", host);
+        drop(device);
+        
+        assert!(sensor.read().await.is_err());
+        assert_eq!(sensor.status(), SensorStatus::Reconnecting);
+    }
+}
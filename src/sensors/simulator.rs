@@ -9,9 +9,111 @@ use anyhow::Result;
 use rand::prelude::*;
 use rand_distr::{Normal, Uniform};
 use std::f64::consts::PI;
+use std::path::Path;
 use chrono::Utc;
+use serde::{Deserialize, Serialize};
 
-use super::{Sensor, SensorReading, SensorType, SensorStatus, CalibrationData};
+use super::{spectrum_bin, Sensor, SensorReading, SensorType, SensorStatus, CalibrationData};
+
+/// One timed event in a scenario script
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioEvent {
+    /// Start time in seconds of simulated sensor time
+    pub at_secs: f64,
+    
+    /// How long the event lasts
+    pub duration_secs: f64,
+    
+    /// Simulator ids affected; several ids make a correlated event
+    pub sensors: Vec<String>,
+    
+    /// Value added to every sample while the event runs
+    pub amplitude: f64,
+    
+    /// Optional description for logs
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl ScenarioEvent {
+    /// First sample index (inclusive) and last (exclusive) at `sample_rate`
+    pub fn sample_range(&self, sample_rate: f64) -> std::ops::Range<u64> {
+        let start = (self.at_secs * sample_rate).round().max(0.0) as u64;
+        let end = ((self.at_secs + self.duration_secs) * sample_rate).round().max(0.0) as u64;
+        start..end
+    }
+    
+    fn is_active(&self, index: u64, sample_rate: f64) -> bool {
+        self.sample_range(sample_rate).contains(&index)
+    }
+}
+
+/// Scenario script: a list of timed events
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: String,
+    pub events: Vec<ScenarioEvent>,
+}
+
+/// Drives simulators from a scenario script
+///
+/// ```toml
+/// name = "demo"
+///
+/// [[events]]
+/// at_secs = 30.0
+/// duration_secs = 2.0
+/// sensors = ["emf-probe-1"]
+/// amplitude = 40.0
+///
+/// [[events]]
+/// at_secs = 45.0
+/// duration_secs = 3.0
+/// sensors = ["thermal-array-1", "infrasound-1"]
+/// amplitude = 5.0
+/// label = "correlated thermal + infrasound"
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScenarioPlayer {
+    scenario: Scenario,
+}
+
+impl ScenarioPlayer {
+    pub fn new(scenario: Scenario) -> Self {
+        Self { scenario }
+    }
+    
+    /// Load a scenario from TOML, or JSON when the extension is `.json`
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let scenario: Scenario = if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            serde_json::from_str(&content)?
+        } else {
+            toml::from_str(&content)?
+        };
+        
+        for event in &scenario.events {
+            if !(event.at_secs >= 0.0 && event.duration_secs > 0.0) {
+                anyhow::bail!("Scenario event at {}s must have a non-negative start and positive duration", event.at_secs);
+            }
+        }
+        
+        Ok(Self::new(scenario))
+    }
+    
+    pub fn scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+    
+    /// Give a simulator the events that target it
+    pub fn attach(&self, simulator: &mut SensorSimulator) {
+        simulator.script = self.scenario.events.iter()
+            .filter(|e| e.sensors.iter().any(|id| id == &simulator.id))
+            .cloned()
+            .collect();
+    }
+}
 
 /// Simulates realistic sensor data for testing
 pub struct SensorSimulator {
@@ -21,6 +123,11 @@ pub struct SensorSimulator {
     status: SensorStatus,
     sequence: u64,
     rng: rand::rngs::StdRng,
+    seed: Option<u64>,
+    
+    // Scripted events for this sensor and samples generated so far
+    script: Vec<ScenarioEvent>,
+    samples: u64,
     
     // Simulation state
     time: f64,
@@ -38,6 +145,9 @@ impl SensorSimulator {
             status: SensorStatus::Disconnected,
             sequence: 0,
             rng: rand::rngs::StdRng::from_entropy(),
+            seed: None,
+            script: Vec::new(),
+            samples: 0,
             time: 0.0,
             anomaly_probability: 0.02,
             noise_level: 0.1,
@@ -45,7 +155,56 @@ impl SensorSimulator {
         }
     }
     
+    /// Simulator whose data, including injected anomalies, is fully
+    /// determined by `seed`
+    pub fn with_seed(id: &str, sensor_type: SensorType, sample_rate: f64, seed: u64) -> Self {
+        let mut sim = Self::new(id, sensor_type, sample_rate);
+        sim.set_seed(seed);
+        sim
+    }
+    
+    /// Reseed the random stream; the simulated clock and drift carry on
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = rand::rngs::StdRng::seed_from_u64(seed);
+        self.seed = Some(seed);
+    }
+    
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+    
+    /// Scripted events this simulator will play
+    pub fn script(&self) -> &[ScenarioEvent] {
+        &self.script
+    }
+    
     fn generate_data(&mut self) -> Vec<f64> {
+        let index = self.samples;
+        self.samples += 1;
+        
+        let scripted: f64 = self.script.iter()
+            .filter(|e| e.is_active(index, self.sample_rate))
+            .map(|e| e.amplitude)
+            .sum();
+        let scripted_active = self.script.iter().any(|e| e.is_active(index, self.sample_rate));
+        
+        // Scripted events replace random anomaly injection while they run
+        let anomaly_probability = self.anomaly_probability;
+        if scripted_active {
+            self.anomaly_probability = 0.0;
+        }
+        
+        let mut data = self.generate_raw();
+        
+        self.anomaly_probability = anomaly_probability;
+        if scripted_active {
+            data.iter_mut().for_each(|v| *v += scripted);
+        }
+        
+        data
+    }
+    
+    fn generate_raw(&mut self) -> Vec<f64> {
         self.time += 1.0 / self.sample_rate;
         self.drift += self.rng.gen_range(-0.001..0.001);
         
@@ -295,7 +454,7 @@ impl SensorSimulator {
     }
     
     fn generate_rf_spectrum(&mut self) -> Vec<f64> {
-        // 256 frequency bins from 1MHz to 1GHz
+        // 256 frequency bins from 1 MHz to 6 GHz
         let bins = 256;
         let mut data = vec![0.0; bins];
         
@@ -306,12 +465,13 @@ impl SensorSimulator {
         
         // Known signals (FM radio, WiFi, etc.)
         let signals = [
-            (88, -50.0),  // FM radio
-            (150, -60.0), // WiFi 2.4GHz
-            (200, -55.0), // WiFi 5GHz
+            (98e6, -50.0),   // FM radio
+            (2.437e9, -60.0), // WiFi 2.4GHz
+            (5.5e9, -55.0),  // WiFi 5GHz
         ];
         
-        for (bin, strength) in signals {
+        for (frequency, strength) in signals {
+            let bin = spectrum_bin(frequency, bins);
             if bin < bins {
                 data[bin] = strength + self.rng.gen_range(-5.0..5.0);
                 if bin > 0 { data[bin-1] = strength - 10.0; }
@@ -476,6 +636,12 @@ impl Sensor for SensorSimulator {
             SensorType::SDRReceiver => "dBm",
             _ => "",
         };
+        // Rows, columns of the simulated grids
+        let dimensions = match self.sensor_type {
+            SensorType::ThermalArray => vec![8, 8],
+            SensorType::ThermalImager => vec![60, 80],
+            _ => vec![],
+        };
         
         Ok(SensorReading {
             sensor_id: self.id.clone(),
@@ -483,7 +649,7 @@ impl Sensor for SensorSimulator {
             timestamp: Utc::now(),
             sequence: self.sequence,
             data,
-            dimensions: vec![],
+            dimensions,
             unit: unit.to_string(),
             sample_rate: self.sample_rate,
             quality: 1.0 - self.noise_level as f32 * 0.5,
@@ -505,6 +671,7 @@ impl Sensor for SensorSimulator {
         serde_json::json!({
             "anomaly_probability": self.anomaly_probability,
             "noise_level": self.noise_level,
+            "seed": self.seed,
         })
     }
     
@@ -515,6 +682,73 @@ impl Sensor for SensorSimulator {
         if let Some(nl) = config.get("noise_level").and_then(|v| v.as_f64()) {
             self.noise_level = nl;
         }
+        if let Some(seed) = config.get("seed").and_then(|v| v.as_u64()) {
+            self.set_seed(seed);
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    async fn sequence(sim: &mut SensorSimulator, n: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for _ in 0..n {
+            let reading = sim.read().await.unwrap();
+            bytes.extend(bincode::serialize(&(reading.sequence, &reading.data)).unwrap());
+        }
+        bytes
+    }
+    
+    #[tokio::test]
+    async fn test_scenario_events_fire_at_sample_indices() {
+        let scenario: Scenario = toml::from_str(r#"
+            [[events]]
+            at_secs = 1.0
+            duration_secs = 0.5
+            sensors = ["emf"]
+            amplitude = 1000.0
+            
+            [[events]]
+            at_secs = 2.0
+            duration_secs = 0.2
+            sensors = ["emf", "geiger"]
+            amplitude = 5000.0
+        "#).unwrap();
+        let player = ScenarioPlayer::new(scenario);
+        
+        let mut emf = SensorSimulator::with_seed("emf", SensorType::EMFProbe, 10.0, 7);
+        let mut baseline = SensorSimulator::with_seed("emf", SensorType::EMFProbe, 10.0, 7);
+        player.attach(&mut emf);
+        assert_eq!(emf.script().len(), 2);
+        
+        let mut geiger = SensorSimulator::new("geiger", SensorType::GeigerCounter, 10.0);
+        player.attach(&mut geiger);
+        assert_eq!(geiger.script().len(), 1);
+        
+        let mut fired = Vec::new();
+        for index in 0..30 {
+            let scripted = emf.read().await.unwrap().data[0];
+            let plain = baseline.read().await.unwrap().data[0];
+            if scripted - plain > 500.0 {
+                fired.push(index);
+            }
+        }
+        assert_eq!(fired, vec![10, 11, 12, 13, 14, 20, 21]);
+    }
+    
+    #[tokio::test]
+    async fn test_same_seed_same_readings() {
+        for sensor_type in [SensorType::EMFProbe, SensorType::ThermalArray, SensorType::GeigerCounter] {
+            let mut a = SensorSimulator::with_seed("a", sensor_type, 10.0, 42);
+            let mut b = SensorSimulator::with_seed("b", sensor_type, 10.0, 42);
+            assert_eq!(sequence(&mut a, 200).await, sequence(&mut b, 200).await);
+        }
+        
+        let mut a = SensorSimulator::with_seed("a", SensorType::EMFProbe, 10.0, 1);
+        let mut c = SensorSimulator::with_seed("c", SensorType::EMFProbe, 10.0, 2);
+        assert_ne!(sequence(&mut a, 50).await, sequence(&mut c, 50).await);
+    }
+}
@@ -9,7 +9,7 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use nalgebra::DVector;
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 
 /// Sensor types supported by GlowBarn
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -91,6 +91,23 @@ pub enum SensorType {
     Custom(u32),        // User-defined
 }
 
+impl std::str::FromStr for SensorType {
+    type Err = anyhow::Error;
+    
+    /// Parse a variant name (`EMFProbe`), `Custom(n)` or a bare number for `Custom(n)`
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(n) = s.strip_prefix("Custom(").and_then(|r| r.strip_suffix(')')) {
+            return Ok(SensorType::Custom(n.trim().parse()?));
+        }
+        if let Ok(n) = s.parse::<u32>() {
+            return Ok(SensorType::Custom(n));
+        }
+        serde_json::from_value(serde_json::Value::String(s.to_string()))
+            .map_err(|_| anyhow::anyhow!("Unknown sensor type '{}'", s))
+    }
+}
+
 /// Sensor operational status
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SensorStatus {
@@ -99,6 +116,8 @@ pub enum SensorStatus {
     Connected,
     Calibrating,
     Active,
+    /// Lost its device and is retrying; still polled so reads can reconnect
+    Reconnecting,
     Error,
     Maintenance,
 }
@@ -137,6 +156,186 @@ pub struct SensorReading {
     pub orientation: Option<[f64; 3]>,  // roll, pitch, yaw in radians
 }
 
+/// How `downsample` reduces a block of samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DownsampleMethod {
+    /// Keep each block's min and max in time order so spikes survive
+    MinMax,
+    /// Replace each block with its mean
+    Mean,
+}
+
+/// Reduce `data` to at most `target_len` points.
+///
+/// Data that already fits is returned unchanged. `MinMax` emits two points
+/// per block, so it uses `target_len / 2` blocks (falling back to `Mean`
+/// when `target_len < 2`).
+pub fn downsample(data: &[f64], target_len: usize, method: DownsampleMethod) -> Vec<f64> {
+    if target_len >= data.len() {
+        return data.to_vec();
+    }
+    if target_len == 0 {
+        return Vec::new();
+    }
+    
+    let method = if target_len < 2 { DownsampleMethod::Mean } else { method };
+    let blocks = match method {
+        DownsampleMethod::MinMax => target_len / 2,
+        DownsampleMethod::Mean => target_len,
+    };
+    
+    let n = data.len();
+    let mut out = Vec::with_capacity(target_len);
+    
+    for b in 0..blocks {
+        let block = &data[b * n / blocks..(b + 1) * n / blocks];
+        match method {
+            DownsampleMethod::Mean => {
+                out.push(block.iter().sum::<f64>() / block.len() as f64);
+            }
+            DownsampleMethod::MinMax => {
+                let (mut min_i, mut max_i) = (0, 0);
+                for (i, &v) in block.iter().enumerate() {
+                    if v < block[min_i] {
+                        min_i = i;
+                    }
+                    if v > block[max_i] {
+                        max_i = i;
+                    }
+                }
+                let (first, second) = if min_i <= max_i { (min_i, max_i) } else { (max_i, min_i) };
+                out.push(block[first]);
+                out.push(block[second]);
+            }
+        }
+    }
+    
+    out
+}
+
+/// Physical quantity a reading's unit measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Quantity {
+    MagneticField,
+    ElectricField,
+    Acceleration,
+    Velocity,
+    Temperature,
+    Pressure,
+    Power,
+    CountRate,
+}
+
+impl Quantity {
+    /// Unit readings of this quantity are compared in. Power stays on a
+    /// decibel scale since it spans many orders of magnitude.
+    pub fn canonical_unit(self) -> &'static str {
+        match self {
+            Quantity::MagneticField => "µT",
+            Quantity::ElectricField => "V/m",
+            Quantity::Acceleration => "m/s²",
+            Quantity::Velocity => "m/s",
+            Quantity::Temperature => "°C",
+            Quantity::Pressure => "hPa",
+            Quantity::Power => "dBm",
+            Quantity::CountRate => "CPS",
+        }
+    }
+}
+
+/// How a unit's values map onto its quantity's SI unit
+#[derive(Debug, Clone, Copy)]
+enum UnitScale {
+    /// `si = value * scale + offset`
+    Linear { scale: f64, offset: f64 },
+    /// `si = reference * 10^(value / 10)`
+    Decibel { reference: f64 },
+}
+
+impl UnitScale {
+    const fn linear(scale: f64) -> Self {
+        UnitScale::Linear { scale, offset: 0.0 }
+    }
+    
+    fn to_si(self, value: f64) -> f64 {
+        match self {
+            UnitScale::Linear { scale, offset } => value * scale + offset,
+            UnitScale::Decibel { reference } => reference * 10f64.powf(value / 10.0),
+        }
+    }
+    
+    fn si_to_unit(self, si: f64) -> f64 {
+        match self {
+            UnitScale::Linear { scale, offset } => (si - offset) / scale,
+            UnitScale::Decibel { reference } => 10.0 * (si / reference).log10(),
+        }
+    }
+}
+
+/// Units sensors report in, with the alternative spellings of micro
+const UNITS: &[(&str, Quantity, UnitScale)] = &[
+    ("T", Quantity::MagneticField, UnitScale::linear(1.0)),
+    ("mT", Quantity::MagneticField, UnitScale::linear(1e-3)),
+    ("µT", Quantity::MagneticField, UnitScale::linear(1e-6)),
+    ("μT", Quantity::MagneticField, UnitScale::linear(1e-6)),
+    ("uT", Quantity::MagneticField, UnitScale::linear(1e-6)),
+    ("nT", Quantity::MagneticField, UnitScale::linear(1e-9)),
+    ("G", Quantity::MagneticField, UnitScale::linear(1e-4)),
+    ("mG", Quantity::MagneticField, UnitScale::linear(1e-7)),
+    ("V/m", Quantity::ElectricField, UnitScale::linear(1.0)),
+    ("kV/m", Quantity::ElectricField, UnitScale::linear(1e3)),
+    ("m/s²", Quantity::Acceleration, UnitScale::linear(1.0)),
+    ("m/s^2", Quantity::Acceleration, UnitScale::linear(1.0)),
+    ("g", Quantity::Acceleration, UnitScale::linear(9.80665)),
+    ("m/s", Quantity::Velocity, UnitScale::linear(1.0)),
+    ("mm/s", Quantity::Velocity, UnitScale::linear(1e-3)),
+    ("µm/s", Quantity::Velocity, UnitScale::linear(1e-6)),
+    ("μm/s", Quantity::Velocity, UnitScale::linear(1e-6)),
+    ("um/s", Quantity::Velocity, UnitScale::linear(1e-6)),
+    ("K", Quantity::Temperature, UnitScale::linear(1.0)),
+    ("°C", Quantity::Temperature, UnitScale::Linear { scale: 1.0, offset: 273.15 }),
+    ("°F", Quantity::Temperature, UnitScale::Linear { scale: 5.0 / 9.0, offset: 273.15 - 32.0 * 5.0 / 9.0 }),
+    ("Pa", Quantity::Pressure, UnitScale::linear(1.0)),
+    ("hPa", Quantity::Pressure, UnitScale::linear(100.0)),
+    ("mbar", Quantity::Pressure, UnitScale::linear(100.0)),
+    ("kPa", Quantity::Pressure, UnitScale::linear(1e3)),
+    ("W", Quantity::Power, UnitScale::linear(1.0)),
+    ("mW", Quantity::Power, UnitScale::linear(1e-3)),
+    ("dBW", Quantity::Power, UnitScale::Decibel { reference: 1.0 }),
+    ("dBm", Quantity::Power, UnitScale::Decibel { reference: 1e-3 }),
+    ("CPS", Quantity::CountRate, UnitScale::linear(1.0)),
+    ("CPM", Quantity::CountRate, UnitScale::linear(1.0 / 60.0)),
+];
+
+fn lookup_unit(unit: &str) -> Option<(Quantity, UnitScale)> {
+    let unit = unit.trim();
+    UNITS.iter()
+        .find(|(symbol, _, _)| *symbol == unit)
+        .map(|&(_, quantity, scale)| (quantity, scale))
+}
+
+/// Scales of `from` and `to`, which must measure the same quantity
+fn conversion(from: &str, to: &str) -> Result<(UnitScale, UnitScale)> {
+    let (from_quantity, from_scale) = lookup_unit(from).ok_or_else(|| anyhow!("Unknown unit '{}'", from))?;
+    let (to_quantity, to_scale) = lookup_unit(to).ok_or_else(|| anyhow!("Unknown unit '{}'", to))?;
+    if from_quantity != to_quantity {
+        bail!("Cannot convert {:?} in '{}' to {:?} in '{}'", from_quantity, from, to_quantity, to);
+    }
+    Ok((from_scale, to_scale))
+}
+
+/// Quantity `unit` measures, `None` for units this module doesn't know
+pub fn unit_quantity(unit: &str) -> Option<Quantity> {
+    lookup_unit(unit).map(|(quantity, _)| quantity)
+}
+
+/// Convert `value` from one unit to another of the same quantity, e.g.
+/// `convert_unit(10.0, "mG", "µT")` is 1 µT
+pub fn convert_unit(value: f64, from: &str, to: &str) -> Result<f64> {
+    let (from_scale, to_scale) = conversion(from, to)?;
+    Ok(to_scale.si_to_unit(from_scale.to_si(value)))
+}
+
 impl SensorReading {
     pub fn new(sensor_id: &str, sensor_type: SensorType, data: Vec<f64>) -> Self {
         Self {
@@ -154,6 +353,49 @@ impl SensorReading {
         }
     }
     
+    /// The data converted to `target`, which must measure the same quantity
+    /// as the reading's `unit`
+    pub fn to_unit(&self, target: &str) -> Result<Vec<f64>> {
+        // Units are looked up once per reading, not per sample
+        let (from_scale, to_scale) = conversion(&self.unit, target)?;
+        Ok(self.data.iter()
+            .map(|&x| to_scale.si_to_unit(from_scale.to_si(x)))
+            .collect())
+    }
+    
+    /// The data in its quantity's canonical unit, or as recorded if the
+    /// unit is unknown
+    pub fn to_canonical(&self) -> Vec<f64> {
+        match unit_quantity(&self.unit) {
+            Some(quantity) => self.to_unit(quantity.canonical_unit()).unwrap_or_else(|_| self.data.clone()),
+            None => self.data.clone(),
+        }
+    }
+    
+    /// The data in its canonical unit, standardized to zero mean and unit
+    /// variance so that readings of different sensors and units share one
+    /// dimensionless scale. A flat reading normalizes to zeros.
+    pub fn normalize(&self) -> Vec<f64> {
+        let data = self.to_canonical();
+        if data.is_empty() {
+            return data;
+        }
+        
+        let n = data.len() as f64;
+        let mean = data.iter().sum::<f64>() / n;
+        let std_dev = (data.iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+        if std_dev > 1e-10 {
+            data.iter().map(|&x| (x - mean) / std_dev).collect()
+        } else {
+            vec![0.0; data.len()]
+        }
+    }
+    
+    /// Decimate the data to at most `target_len` points
+    pub fn downsample(&self, target_len: usize, method: DownsampleMethod) -> Vec<f64> {
+        downsample(&self.data, target_len, method)
+    }
+    
     pub fn as_vector(&self) -> DVector<f64> {
         DVector::from_vec(self.data.clone())
     }
@@ -168,6 +410,23 @@ impl SensorReading {
 }
 
 /// Trait for all sensors
+///
+/// Built-in and third-party sensors (see `SensorRegistry`) are driven the
+/// same way by the `SensorManager`:
+///
+/// - `id()` is unique among the manager's sensors and never changes.
+/// - `connect()` opens the hardware; `calibrate()` runs next and is what
+///   puts the sensor in `SensorStatus::Active`. Only active sensors are read,
+///   plus `Reconnecting` ones, whose reads retry the lost device.
+/// - `read()` is called every `poll_period(sample_rate())`, or at `due_at()`
+///   for sensors that report one, and returns one block of samples;
+///   sensors faster than `MAX_POLL_HZ` batch several samples per reading.
+///   Errors count against the sensor's health and the
+///   read is retried next period rather than aborting the loop.
+/// - `disconnect()` may be called on a sensor that never connected and
+///   must leave it restartable with `connect()`.
+/// - Calls on one sensor never overlap, but they may come from different
+///   tasks, hence `Send + Sync`.
 #[async_trait]
 pub trait Sensor: Send + Sync {
     /// Get sensor unique identifier
@@ -191,6 +450,12 @@ pub trait Sensor: Send + Sync {
     /// Read raw data from sensor
     async fn read(&mut self) -> Result<SensorReading>;
     
+    /// When the next sample is ready, for sensors that pace themselves
+    /// (e.g. replays); `None` means read every `poll_period`
+    fn due_at(&self) -> Option<tokio::time::Instant> {
+        None
+    }
+    
     /// Get sample rate in Hz
     fn sample_rate(&self) -> f64;
     
@@ -204,17 +469,128 @@ pub trait Sensor: Send + Sync {
     fn set_config(&mut self, config: serde_json::Value) -> Result<()>;
 }
 
+/// Overall health derived from read timing, error rate and signal quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,
+    Stale,
+    Failed,
+    /// Disabled by the operator; not read until enabled again
+    Offline,
+}
+
+impl HealthStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Degraded => "degraded",
+            HealthStatus::Stale => "stale",
+            HealthStatus::Failed => "failed",
+            HealthStatus::Offline => "offline",
+        }
+    }
+}
+
 /// Sensor health metrics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SensorHealth {
     pub sensor_id: String,
+    pub sensor_type: SensorType,
     pub status: SensorStatus,
+    pub health: HealthStatus,
     pub uptime_seconds: u64,
     pub readings_count: u64,
     pub error_count: u64,
     pub last_error: Option<String>,
+    pub last_reading: Option<DateTime<Utc>>,
+    /// Seconds since the last successful read (None if never read)
+    pub last_read_age_secs: Option<f64>,
+    /// Fraction of recent reads that failed
+    pub error_rate: f64,
+    /// Change in mean quality between the older and newer half of recent reads
+    pub quality_trend: f32,
     pub signal_quality: f32,
     pub noise_level: f64,
     pub temperature: Option<f64>,
     pub battery_level: Option<f32>,
 }
+
+impl SensorHealth {
+    pub fn new(sensor_id: &str, sensor_type: SensorType) -> Self {
+        Self {
+            sensor_id: sensor_id.to_string(),
+            sensor_type,
+            status: SensorStatus::Disconnected,
+            health: HealthStatus::Stale,
+            uptime_seconds: 0,
+            readings_count: 0,
+            error_count: 0,
+            last_error: None,
+            last_reading: None,
+            last_read_age_secs: None,
+            error_rate: 0.0,
+            quality_trend: 0.0,
+            signal_quality: 0.0,
+            noise_level: 0.0,
+            temperature: None,
+            battery_level: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_minmax_keeps_spike() {
+        let mut data = vec![1.0; 1000];
+        data[437] = 99.0;
+        data[712] = -50.0;
+        let reading = SensorReading::new("rf-1", SensorType::SDRReceiver, data);
+        
+        let minmax = reading.downsample(20, DownsampleMethod::MinMax);
+        assert_eq!(minmax.len(), 20);
+        assert!(minmax.contains(&99.0));
+        assert!(minmax.contains(&-50.0));
+        
+        let mean = reading.downsample(20, DownsampleMethod::Mean);
+        assert_eq!(mean.len(), 20);
+        assert!(mean.iter().all(|&v| v < 99.0));
+        
+        assert_eq!(reading.downsample(5000, DownsampleMethod::MinMax).len(), 1000);
+    }
+    
+    #[test]
+    fn test_unit_conversion_and_normalization() {
+        assert!((convert_unit(10.0, "mG", "µT").unwrap() - 1.0).abs() < 1e-12);
+        assert!((convert_unit(2.5, "uT", "mG").unwrap() - 25.0).abs() < 1e-12);
+        assert!((convert_unit(212.0, "°F", "°C").unwrap() - 100.0).abs() < 1e-9);
+        assert!((convert_unit(30.0, "dBm", "W").unwrap() - 1.0).abs() < 1e-12);
+        assert!((convert_unit(120.0, "CPM", "CPS").unwrap() - 2.0).abs() < 1e-12);
+        assert!(convert_unit(1.0, "mG", "CPM").is_err());
+        assert!(convert_unit(1.0, "furlongs", "µT").is_err());
+        
+        // The same disturbance seen by an EMF probe in mG and a fluxgate in µT
+        let pattern = [0.0, 0.1, -0.2, 0.05, 3.0, -0.1, 0.2, 0.0];
+        let mut emf = SensorReading::new("emf", SensorType::EMFProbe, pattern.iter().map(|p| 500.0 + 40.0 * p).collect());
+        emf.unit = "mG".to_string();
+        let mut flux = SensorReading::new("flux", SensorType::FluxGate, pattern.iter().map(|p| 48.0 + 4.0 * p).collect());
+        flux.unit = "µT".to_string();
+        
+        assert!((emf.data[4] - flux.data[4]).abs() > 400.0);
+        let in_ut = emf.to_unit("µT").unwrap();
+        assert!((in_ut[4] - 62.0).abs() < 1e-9);
+        assert!(emf.to_unit("CPM").is_err());
+        
+        let (a, b) = (emf.normalize(), flux.normalize());
+        for (x, y) in a.iter().zip(&b) {
+            assert!((x - y).abs() < 1e-9, "{:?} vs {:?}", a, b);
+        }
+        assert!(a[4] > 2.0);
+        
+        let flat = SensorReading::new("flat", SensorType::Barometer, vec![1013.0; 4]);
+        assert_eq!(flat.normalize(), vec![0.0; 4]);
+    }
+}
@@ -6,17 +6,52 @@
 
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fs::{File, OpenOptions};
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tracing::{info, warn};
 
-use crate::sensors::SensorReading;
+use crate::analysis::{AnalysisConfig, SignalProcessor};
+use crate::sensors::{DownsampleMethod, SensorReading};
 use crate::detection::Detection;
+use crate::db::Database;
 use super::ExportFormat;
 
+/// InfluxDB line-protocol point for a reading (mean of its samples)
+pub fn influx_reading_line(reading: &SensorReading) -> String {
+    let mean = reading.downsample(1, DownsampleMethod::Mean)
+        .first()
+        .copied()
+        .unwrap_or(0.0);
+    format!(
+        "sensor,id={},type={:?} value={},quality={} {}",
+        reading.sensor_id,
+        reading.sensor_type,
+        mean,
+        reading.quality as i32,
+        reading.timestamp.timestamp_nanos_opt().unwrap_or(0)
+    )
+}
+
+/// InfluxDB line-protocol point for a detection
+pub fn influx_detection_line(detection: &Detection) -> String {
+    format!(
+        "detection,type={:?},severity={:?} confidence={},sensor_count={}i {}",
+        detection.detection_type,
+        detection.severity,
+        detection.confidence,
+        detection.sensors.len(),
+        detection.timestamp.timestamp_nanos_opt().unwrap_or(0)
+    )
+}
+
+/// CSV field for a reading mean; empty when the reading has none
+fn csv_mean(mean: Option<f64>) -> String {
+    mean.map(|mean| format!("{:.6}", mean)).unwrap_or_default()
+}
+
 /// Data exporter
 pub struct DataExporter {
     path: PathBuf,
@@ -29,6 +64,10 @@ pub struct DataExporter {
 
 impl DataExporter {
     pub fn new(path: &str, format: ExportFormat) -> Result<Self> {
+        if format == ExportFormat::GeoJson {
+            return Err(anyhow!("GeoJSON is a single document and cannot be streamed; use a batch export"));
+        }
+        
         let path = PathBuf::from(path);
         
         // Create directory if it doesn't exist
@@ -88,9 +127,9 @@ impl DataExporter {
                     writer.write_all(&bytes)?;
                 }
                 ExportFormat::InfluxLineProtocol => {
-                    let line = self.to_influx_line(reading);
-                    writeln!(writer, "{}", line)?;
+                    writeln!(writer, "{}", influx_reading_line(reading))?;
                 }
+                ExportFormat::GeoJson => unreachable!("rejected by DataExporter::new"),
             }
             
             writer.flush()?;
@@ -148,16 +187,9 @@ impl DataExporter {
                     writer.write_all(&bytes)?;
                 }
                 ExportFormat::InfluxLineProtocol => {
-                    let line = format!(
-                        "detection,type={:?},severity={:?} confidence={},sensor_count={}i {}",
-                        detection.detection_type,
-                        detection.severity,
-                        detection.confidence,
-                        detection.sensors.len(),
-                        detection.timestamp.timestamp_nanos_opt().unwrap_or(0)
-                    );
-                    writeln!(writer, "{}", line)?;
+                    writeln!(writer, "{}", influx_detection_line(detection))?;
                 }
+                ExportFormat::GeoJson => unreachable!("rejected by DataExporter::new"),
             }
             
             writer.flush()?;
@@ -176,6 +208,7 @@ impl DataExporter {
             ExportFormat::Csv => "csv",
             ExportFormat::Binary => "bin",
             ExportFormat::InfluxLineProtocol => "lp",
+            ExportFormat::GeoJson => "geojson",
         };
         self.path.join(format!("readings_{}.{}", timestamp, ext))
     }
@@ -187,6 +220,7 @@ impl DataExporter {
             ExportFormat::Csv => "csv",
             ExportFormat::Binary => "bin",
             ExportFormat::InfluxLineProtocol => "lp",
+            ExportFormat::GeoJson => "geojson",
         };
         self.path.join(format!("detections_{}.{}", timestamp, ext))
     }
@@ -220,23 +254,6 @@ impl DataExporter {
         Ok(())
     }
     
-    fn to_influx_line(&self, reading: &SensorReading) -> String {
-        let mean = if reading.data.is_empty() {
-            0.0
-        } else {
-            reading.data.iter().sum::<f64>() / reading.data.len() as f64
-        };
-        
-        format!(
-            "sensor,id={},type={:?} value={},quality={} {}",
-            reading.sensor_id,
-            reading.sensor_type,
-            mean,
-            reading.quality as i32,
-            reading.timestamp.timestamp_nanos_opt().unwrap_or(0)
-        )
-    }
-    
     /// Get export statistics
     pub fn get_stats(&self) -> (usize, usize) {
         let readings = *self.readings_count.lock().unwrap();
@@ -256,14 +273,104 @@ impl DataExporter {
     }
 }
 
+/// Which stored records an export covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportSelection {
+    Readings,
+    Detections,
+}
+
 /// Batch exporter for large datasets
 pub struct BatchExporter {
     format: ExportFormat,
+    /// Local oscillator for shifting WAV exports down, if enabled
+    wav_heterodyne_hz: Option<f64>,
+    /// Where local detection locations are anchored in GeoJSON exports
+    geo_origin: Option<GeoOrigin>,
 }
 
 impl BatchExporter {
     pub fn new(format: ExportFormat) -> Self {
-        Self { format }
+        Self { format, wav_heterodyne_hz: None, geo_origin: None }
+    }
+    
+    /// Shift WAV exports down by `shift_hz` (heterodyne), so ultrasonic
+    /// content lands in the audible range
+    pub fn with_heterodyne(mut self, shift_hz: f64) -> Self {
+        self.wav_heterodyne_hz = Some(shift_hz);
+        self
+    }
+    
+    /// Convert detection locations from local metres to longitude/latitude
+    /// around `origin` in GeoJSON exports
+    pub fn with_geo_origin(mut self, origin: GeoOrigin) -> Self {
+        self.geo_origin = Some(origin);
+        self
+    }
+    
+    /// Write stored records between `start` and `end` to `path`, oldest first.
+    ///
+    /// Returns the number of records written.
+    pub fn export_from_database(
+        &self,
+        db: &Database,
+        selection: ExportSelection,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        path: &Path,
+    ) -> Result<usize> {
+        if self.format == ExportFormat::GeoJson && selection == ExportSelection::Readings {
+            return Err(anyhow!("GeoJSON export covers located detections, not readings"));
+        }
+        
+        let mut writer = BufWriter::new(File::create(path)
+            .map_err(|e| anyhow!("Failed to create {:?}: {}", path, e))?);
+        
+        let count = match selection {
+            ExportSelection::Readings if self.format == ExportFormat::Csv => {
+                // The CSV only needs each reading's mean, which is stored
+                // alongside it, so skip decrypting and decoding payloads
+                let summaries = db.query_reading_summaries(start, end)?;
+                writeln!(writer, "timestamp,sensor_id,sensor_type,quality,mean_value")?;
+                for summary in &summaries {
+                    writeln!(writer, "{},{},{},{},{}",
+                        summary.timestamp,
+                        summary.sensor_id,
+                        summary.sensor_type,
+                        summary.quality,
+                        csv_mean(summary.mean_value)
+                    )?;
+                }
+                writer.flush()?;
+                summaries.len()
+            }
+            ExportSelection::Readings => {
+                let mut readings = db.query_readings(start, end, None, Some(i64::MAX as usize))?
+                    .iter()
+                    .map(|r| r.to_reading())
+                    .collect::<Result<Vec<_>>>()?;
+                readings.reverse();
+                self.export_readings(&readings, &mut writer)?;
+                readings.len()
+            }
+            ExportSelection::Detections => {
+                let mut detections = db.query_detections(start, end, None, Some(i64::MAX as usize))?
+                    .iter()
+                    .map(|d| d.to_detection())
+                    .collect::<Result<Vec<_>>>()?;
+                detections.reverse();
+                if self.format == ExportFormat::GeoJson {
+                    // Only located detections become features
+                    self.export_detections_geojson(&detections, self.geo_origin, &mut writer)?
+                } else {
+                    self.export_detections(&detections, &mut writer)?;
+                    detections.len()
+                }
+            }
+        };
+        
+        info!("Exported {} {:?} to {:?}", count, selection, path);
+        Ok(count)
     }
     
     /// Export readings to file
@@ -278,17 +385,13 @@ impl BatchExporter {
             ExportFormat::Csv => {
                 writeln!(writer, "timestamp,sensor_id,sensor_type,quality,mean_value")?;
                 for reading in readings {
-                    let mean = if reading.data.is_empty() {
-                        0.0
-                    } else {
-                        reading.data.iter().sum::<f64>() / reading.data.len() as f64
-                    };
-                    writeln!(writer, "{},{},{:?},{},{:.6}", 
+                    let mean = reading.downsample(1, DownsampleMethod::Mean).first().copied();
+                    writeln!(writer, "{},{},{:?},{},{}", 
                         reading.timestamp.to_rfc3339(),
                         reading.sensor_id,
                         reading.sensor_type,
                         reading.quality,
-                        mean
+                        csv_mean(mean)
                     )?;
                 }
             }
@@ -302,21 +405,12 @@ impl BatchExporter {
             }
             ExportFormat::InfluxLineProtocol => {
                 for reading in readings {
-                    let mean = if reading.data.is_empty() {
-                        0.0
-                    } else {
-                        reading.data.iter().sum::<f64>() / reading.data.len() as f64
-                    };
-                    writeln!(writer,
-                        "sensor,id={},type={:?} value={},quality={} {}",
-                        reading.sensor_id,
-                        reading.sensor_type,
-                        mean,
-                        reading.quality as i32,
-                        reading.timestamp.timestamp_nanos_opt().unwrap_or(0)
-                    )?;
+                    writeln!(writer, "{}", influx_reading_line(reading))?;
                 }
             }
+            ExportFormat::GeoJson => {
+                return Err(anyhow!("GeoJSON export covers located detections, not readings"));
+            }
         }
         
         writer.flush()?;
@@ -346,6 +440,9 @@ impl BatchExporter {
                     )?;
                 }
             }
+            ExportFormat::GeoJson => {
+                self.export_detections_geojson(detections, self.geo_origin, writer)?;
+            }
             _ => {
                 // Use JSON for other formats
                 for detection in detections {
@@ -358,4 +455,289 @@ impl BatchExporter {
         writer.flush()?;
         Ok(())
     }
+    
+    /// Write one sensor's readings, concatenated in order, as a mono 32-bit
+    /// float WAV.
+    ///
+    /// Samples keep their sensor units. `sample_rate` defaults to the first
+    /// reading's own rate.
+    pub fn export_readings_wav(&self, readings: &[SensorReading], path: &Path, sample_rate: Option<f64>) -> Result<()> {
+        let first = readings.first().ok_or_else(|| anyhow!("No readings to export"))?;
+        if let Some(other) = readings.iter().find(|r| r.sensor_id != first.sensor_id) {
+            return Err(anyhow!(
+                "WAV export takes one sensor's readings, got {} and {}",
+                first.sensor_id, other.sensor_id
+            ));
+        }
+        
+        let sample_rate = sample_rate.unwrap_or(first.sample_rate);
+        if !(1.0..=u32::MAX as f64).contains(&sample_rate.round()) {
+            return Err(anyhow!("Invalid WAV sample rate {}", sample_rate));
+        }
+        
+        let mut samples: Vec<f64> = readings.iter().flat_map(|r| r.data.iter().copied()).collect();
+        if let Some(shift_hz) = self.wav_heterodyne_hz {
+            samples = SignalProcessor::new(AnalysisConfig::default()).heterodyne(&samples, sample_rate, shift_hz);
+        }
+        
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: sample_rate.round() as u32,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(path, spec)
+            .map_err(|e| anyhow!("Failed to create {:?}: {}", path, e))?;
+        for sample in samples {
+            writer.write_sample(sample as f32)?;
+        }
+        writer.finalize()?;
+        
+        info!("Exported {} readings from {} to {:?}", readings.len(), first.sensor_id, path);
+        Ok(())
+    }
+}
+
+/// WGS84 equatorial radius, for turning local metres into degrees
+const EARTH_RADIUS_M: f64 = 6_378_137.0;
+
+/// Geographic position of the local sensor coordinate origin.
+///
+/// Sensor positions (and so detection locations) are metres east, north and
+/// up from this point.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoOrigin {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+}
+
+impl GeoOrigin {
+    /// GeoJSON `[longitude, latitude, altitude]` of a local `[x, y, z]`.
+    ///
+    /// Uses a flat-earth approximation, fine over the extent of a site.
+    pub fn to_geojson_position(&self, local: [f64; 3]) -> [f64; 3] {
+        let [east, north, up] = local;
+        let latitude = self.latitude + (north / EARTH_RADIUS_M).to_degrees();
+        let longitude = self.longitude
+            + (east / (EARTH_RADIUS_M * self.latitude.to_radians().cos())).to_degrees();
+        [longitude, latitude, self.altitude + up]
+    }
+}
+
+impl BatchExporter {
+    /// Write located detections as a GeoJSON `FeatureCollection` of points,
+    /// returning how many were written. Detections without a location are
+    /// skipped.
+    ///
+    /// With `origin`, locations are local metres and are converted to
+    /// longitude/latitude; without it they are written as-is, for setups
+    /// whose sensor positions are already `[longitude, latitude, altitude]`.
+    pub fn export_detections_geojson<W: Write>(
+        &self,
+        detections: &[Detection],
+        origin: Option<GeoOrigin>,
+        writer: &mut W,
+    ) -> Result<usize> {
+        let features: Vec<serde_json::Value> = detections.iter()
+            .filter_map(|d| d.location.map(|location| (d, location)))
+            .map(|(detection, location)| {
+                let coordinates = match origin {
+                    Some(origin) => origin.to_geojson_position(location),
+                    None => location,
+                };
+                serde_json::json!({
+                    "type": "Feature",
+                    "id": detection.id,
+                    "geometry": {
+                        "type": "Point",
+                        "coordinates": coordinates,
+                    },
+                    "properties": {
+                        "detection_type": format!("{:?}", detection.detection_type),
+                        "severity": format!("{:?}", detection.severity),
+                        "confidence": detection.confidence,
+                        "timestamp": detection.timestamp.to_rfc3339(),
+                        "sensor_count": detection.sensors.len(),
+                    },
+                })
+            })
+            .collect();
+        
+        let count = features.len();
+        let collection = serde_json::json!({
+            "type": "FeatureCollection",
+            "features": features,
+        });
+        serde_json::to_writer(&mut *writer, &collection)?;
+        writeln!(writer)?;
+        writer.flush()?;
+        
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+    use crate::detection::{DetectionType, Severity};
+    use crate::sensors::SensorType;
+    
+    #[test]
+    fn test_export_readings_from_database() {
+        let db_config = DatabaseConfig {
+            path: std::env::temp_dir().join(format!("glowbarn-export-{}.db", uuid::Uuid::new_v4())),
+            ..Default::default()
+        };
+        let db = Database::open(&db_config, None).unwrap();
+        for value in [1.0, 2.0, 3.0] {
+            db.store_reading(&SensorReading::new("emf-1", SensorType::EMFProbe, vec![value])).unwrap();
+        }
+        // No samples, so no mean
+        db.store_reading(&SensorReading::new("emf-2", SensorType::EMFProbe, Vec::new())).unwrap();
+        
+        let out = std::env::temp_dir().join(format!("glowbarn-export-{}.csv", uuid::Uuid::new_v4()));
+        let exporter = BatchExporter::new(ExportFormat::Csv);
+        let count = exporter.export_from_database(
+            &db,
+            ExportSelection::Readings,
+            Utc::now() - chrono::Duration::minutes(1),
+            Utc::now() + chrono::Duration::minutes(1),
+            &out,
+        ).unwrap();
+        
+        let contents = std::fs::read_to_string(&out).unwrap();
+        let lines: Vec<_> = contents.lines().collect();
+        assert_eq!(count, 4);
+        assert_eq!(lines[0], "timestamp,sensor_id,sensor_type,quality,mean_value");
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].contains("emf-1,EMFProbe"));
+        let empty = lines.iter().find(|line| line.contains("emf-2")).unwrap();
+        assert!(empty.ends_with(','), "{}", empty);
+        
+        let _ = std::fs::remove_file(&out);
+        let _ = std::fs::remove_file(&db_config.path);
+    }
+    
+    #[test]
+    fn test_wav_tone_round_trip() {
+        let tone = |freq: f64, sample_rate: f64, offset: usize| -> SensorReading {
+            let data = (offset..offset + 4000)
+                .map(|i| 0.5 * (2.0 * std::f64::consts::PI * freq * i as f64 / sample_rate).sin())
+                .collect();
+            let mut reading = SensorReading::new("mic-1", SensorType::FullSpectrum, data);
+            reading.sample_rate = sample_rate;
+            reading
+        };
+        let read_back = |path: &Path| -> (u32, Vec<f64>) {
+            let mut reader = hound::WavReader::open(path).unwrap();
+            let rate = reader.spec().sample_rate;
+            (rate, reader.samples::<f32>().map(|s| s.unwrap() as f64).collect())
+        };
+        let signal = SignalProcessor::new(AnalysisConfig::default());
+        let out = std::env::temp_dir().join(format!("glowbarn-tone-{}.wav", uuid::Uuid::new_v4()));
+        
+        let readings = vec![tone(440.0, 8000.0, 0), tone(440.0, 8000.0, 4000)];
+        BatchExporter::new(ExportFormat::Json).export_readings_wav(&readings, &out, None).unwrap();
+        let (rate, samples) = read_back(&out);
+        assert_eq!(rate, 8000);
+        assert_eq!(samples.len(), 8000);
+        assert!((samples[4321] - readings[1].data[321]).abs() < 1e-6);
+        assert!(signal.goertzel(&samples, 8000.0, 440.0) > 100.0 * signal.goertzel(&samples, 8000.0, 880.0));
+        
+        // 40 kHz is inaudible; shifted by 38 kHz it plays at 2 kHz
+        let ultrasonic = vec![tone(40_000.0, 192_000.0, 0)];
+        BatchExporter::new(ExportFormat::Json)
+            .with_heterodyne(38_000.0)
+            .export_readings_wav(&ultrasonic, &out, None)
+            .unwrap();
+        let (_, shifted) = read_back(&out);
+        let settled = &shifted[1000..];
+        let at = |freq| signal.goertzel(settled, 192_000.0, freq);
+        assert!(at(2000.0) > 100.0 * at(40_000.0));
+        assert!(at(2000.0) > 100.0 * at(78_000.0));
+        
+        let _ = std::fs::remove_file(&out);
+    }
+    
+    fn located(detection_type: DetectionType, location: Option<[f64; 3]>) -> Detection {
+        Detection {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            detection_type,
+            confidence: 0.8,
+            uncertainty: 0.0,
+            severity: Severity::High,
+            sensors: vec![],
+            entropy_deviation: 0.0,
+            anomaly_count: 0,
+            correlation_score: 0.0,
+            classification: None,
+            location,
+            payload: None,
+            data_window_start: Utc::now(),
+            data_window_end: Utc::now(),
+        }
+    }
+    
+    #[test]
+    fn test_geojson_round_trip() {
+        let detections = vec![
+            located(DetectionType::EMFSpike, Some([0.0, 0.0, 0.0])),
+            located(DetectionType::ThermalAnomaly, None),
+            located(DetectionType::CorrelatedAnomaly, Some([100.0, 50.0, 2.0])),
+        ];
+        let origin = GeoOrigin { latitude: 45.0, longitude: -122.0, altitude: 10.0 };
+        
+        let mut out = Vec::new();
+        let count = BatchExporter::new(ExportFormat::Json)
+            .export_detections_geojson(&detections, Some(origin), &mut out)
+            .unwrap();
+        assert_eq!(count, 2);
+        
+        let parsed: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(parsed["type"], "FeatureCollection");
+        let features = parsed["features"].as_array().unwrap();
+        assert_eq!(features.len(), 2);
+        
+        assert_eq!(features[0]["geometry"]["type"], "Point");
+        assert_eq!(features[0]["geometry"]["coordinates"], serde_json::json!([-122.0, 45.0, 10.0]));
+        assert_eq!(features[0]["properties"]["detection_type"], "EMFSpike");
+        assert_eq!(features[0]["properties"]["severity"], "High");
+        
+        // 100 m east and 50 m north of the origin
+        let coords: Vec<f64> = serde_json::from_value(features[1]["geometry"]["coordinates"].clone()).unwrap();
+        assert!(coords[0] > -122.0 && coords[0] < -121.998);
+        assert!(coords[1] > 45.0 && coords[1] < 45.001);
+        assert_eq!(coords[2], 12.0);
+    }
+    
+    #[test]
+    fn test_geojson_export_from_database() {
+        let db_config = DatabaseConfig {
+            path: std::env::temp_dir().join(format!("glowbarn-export-{}.db", uuid::Uuid::new_v4())),
+            ..Default::default()
+        };
+        let db = Database::open(&db_config, None).unwrap();
+        db.store_detection(&located(DetectionType::EMFSpike, Some([10.0, 0.0, 0.0]))).unwrap();
+        db.store_detection(&located(DetectionType::ThermalAnomaly, None)).unwrap();
+        
+        let (start, end) = (Utc::now() - chrono::Duration::minutes(1), Utc::now() + chrono::Duration::minutes(1));
+        let out = std::env::temp_dir().join(format!("glowbarn-export-{}.geojson", uuid::Uuid::new_v4()));
+        let exporter = BatchExporter::new(ExportFormat::GeoJson)
+            .with_geo_origin(GeoOrigin { latitude: 45.0, longitude: -122.0, altitude: 0.0 });
+        
+        let count = exporter.export_from_database(&db, ExportSelection::Detections, start, end, &out).unwrap();
+        assert_eq!(count, 1);
+        let parsed: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert_eq!(parsed["features"].as_array().unwrap().len(), 1);
+        assert!(parsed["features"][0]["geometry"]["coordinates"][0].as_f64().unwrap() > -122.0);
+        
+        assert!(exporter.export_from_database(&db, ExportSelection::Readings, start, end, &out).is_err());
+        assert!(DataExporter::new(&std::env::temp_dir().to_string_lossy(), ExportFormat::GeoJson).is_err());
+        
+        let _ = std::fs::remove_file(&out);
+        let _ = std::fs::remove_file(&db_config.path);
+    }
 }
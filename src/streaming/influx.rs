@@ -0,0 +1,340 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! InfluxDB v2 line-protocol push
+//!
+//! Points are queued as line-protocol strings and written in batches to
+//! `/api/v2/write`, either when a batch fills up or every
+//! `influx_flush_interval_ms`. Failed writes are retried with exponential
+//! backoff; server (5xx) and rate-limit (429) errors put the batch back at
+//! the front of the queue, while other client errors drop it since
+//! resending the same points can't succeed. The queue is bounded by
+//! `influx_max_pending` and drops its oldest points when full, so a
+//! stalled server never grows memory without limit.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use anyhow::{anyhow, bail, Result};
+use flate2::{write::GzEncoder, Compression};
+use parking_lot::Mutex;
+use tokio::sync::{broadcast, Notify};
+use tracing::{debug, info, warn};
+
+use super::StreamingConfig;
+
+/// Attempts per batch before it is requeued
+const MAX_ATTEMPTS: u32 = 4;
+
+/// First retry delay, doubled after each failed attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+
+/// Batched InfluxDB writer
+#[derive(Clone)]
+pub struct InfluxClient {
+    http: reqwest::Client,
+    write_url: String,
+    token: Option<String>,
+    gzip: bool,
+    batch_size: usize,
+    max_pending: usize,
+    flush_interval: Duration,
+    pending: Arc<Mutex<VecDeque<String>>>,
+    batch_ready: Arc<Notify>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl InfluxClient {
+    pub fn new(config: &StreamingConfig) -> Result<Self> {
+        if config.influx_bucket.is_empty() {
+            bail!("InfluxDB bucket must be set");
+        }
+        
+        let mut url = reqwest::Url::parse(&config.influx_url)
+            .map_err(|e| anyhow!("Invalid InfluxDB URL {}: {}", config.influx_url, e))?
+            .join("api/v2/write")?;
+        url.query_pairs_mut()
+            .append_pair("org", &config.influx_org)
+            .append_pair("bucket", &config.influx_bucket)
+            .append_pair("precision", "ns");
+        
+        Ok(Self {
+            http: reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?,
+            write_url: url.to_string(),
+            token: config.influx_token.clone(),
+            gzip: config.influx_gzip,
+            batch_size: config.influx_batch_size.max(1),
+            max_pending: config.influx_max_pending.max(config.influx_batch_size.max(1)),
+            flush_interval: Duration::from_millis(config.influx_flush_interval_ms.max(1)),
+            pending: Arc::new(Mutex::new(VecDeque::new())),
+            batch_ready: Arc::new(Notify::new()),
+            dropped: Arc::new(AtomicU64::new(0)),
+        })
+    }
+    
+    /// Queue a line-protocol point
+    pub fn push(&self, line: String) {
+        let len = {
+            let mut pending = self.pending.lock();
+            if pending.len() >= self.max_pending {
+                pending.pop_front();
+                if self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                    warn!("InfluxDB queue full, dropping oldest points");
+                }
+            }
+            pending.push_back(line);
+            pending.len()
+        };
+        
+        if len >= self.batch_size {
+            self.batch_ready.notify_one();
+        }
+    }
+    
+    /// Points waiting to be written
+    pub fn pending(&self) -> usize {
+        self.pending.lock().len()
+    }
+    
+    /// Points dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+    
+    /// Write queued points in the background until shutdown
+    pub fn start(&self, mut shutdown: broadcast::Receiver<()>) {
+        let client = self.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(client.flush_interval);
+            loop {
+                tokio::select! {
+                    _ = tick.tick() => {}
+                    _ = client.batch_ready.notified() => {}
+                    _ = shutdown.recv() => break,
+                }
+                
+                // Drain full batches now; a partial one waits for the next tick
+                loop {
+                    match client.flush().await {
+                        Ok(n) if n == client.batch_size => continue,
+                        Ok(_) => break,
+                        Err(e) => {
+                            warn!("InfluxDB write failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+            debug!("InfluxDB writer stopped");
+        });
+        info!("Pushing line protocol to {}", self.write_url);
+    }
+    
+    /// Write one batch, returning how many points it held
+    pub async fn flush(&self) -> Result<usize> {
+        let batch: Vec<String> = {
+            let mut pending = self.pending.lock();
+            let n = pending.len().min(self.batch_size);
+            pending.drain(..n).collect()
+        };
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        
+        match self.write(&batch).await {
+            Ok(()) => Ok(batch.len()),
+            Err(WriteError::Rejected(e)) => Err(anyhow!("InfluxDB rejected {} points: {}", batch.len(), e)),
+            Err(WriteError::Unavailable(e)) => {
+                self.requeue(batch);
+                Err(anyhow!("InfluxDB unavailable: {}", e))
+            }
+        }
+    }
+    
+    /// Write everything queued, stopping at the first failure
+    pub async fn flush_all(&self) -> Result<()> {
+        while self.flush().await? > 0 {}
+        Ok(())
+    }
+    
+    /// Put a failed batch back in front of newer points, within the bound
+    fn requeue(&self, batch: Vec<String>) {
+        let mut pending = self.pending.lock();
+        let room = self.max_pending.saturating_sub(pending.len());
+        let skip = batch.len().saturating_sub(room);
+        if skip > 0 {
+            self.dropped.fetch_add(skip as u64, Ordering::Relaxed);
+        }
+        for line in batch.into_iter().skip(skip).rev() {
+            pending.push_front(line);
+        }
+    }
+    
+    async fn write(&self, batch: &[String]) -> Result<(), WriteError> {
+        let body = self.encode(batch).map_err(|e| WriteError::Rejected(e.to_string()))?;
+        let mut last_error = String::new();
+        
+        for attempt in 0..MAX_ATTEMPTS {
+            if attempt > 0 {
+                tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+            }
+            
+            let mut request = self.http.post(&self.write_url)
+                .header(reqwest::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+                .body(body.clone());
+            if self.gzip {
+                request = request.header(reqwest::header::CONTENT_ENCODING, "gzip");
+            }
+            if let Some(ref token) = self.token {
+                request = request.header(reqwest::header::AUTHORIZATION, format!("Token {}", token));
+            }
+            
+            match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let text = response.text().await.unwrap_or_default();
+                    last_error = format!("{}: {}", status, text);
+                    let retryable = status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                    if !retryable {
+                        return Err(WriteError::Rejected(last_error));
+                    }
+                }
+                Err(e) => last_error = e.to_string(),
+            }
+            debug!("InfluxDB write attempt {} failed: {}", attempt + 1, last_error);
+        }
+        
+        Err(WriteError::Unavailable(last_error))
+    }
+    
+    fn encode(&self, batch: &[String]) -> std::io::Result<Vec<u8>> {
+        let body = batch.join("\n");
+        if !self.gzip {
+            return Ok(body.into_bytes());
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(body.as_bytes())?;
+        encoder.finish()
+    }
+}
+
+enum WriteError {
+    /// The server refused the points; retrying won't help
+    Rejected(String),
+    /// The server couldn't be reached or failed; the points can be resent
+    Unavailable(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    
+    /// Minimal HTTP server answering with `statuses` in turn (then 204),
+    /// recording each request's headers and decompressed body
+    async fn mock_influx(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<(String, String)>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            let mut statuses = statuses.into_iter();
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = Vec::new();
+                let header_end = loop {
+                    let mut chunk = [0u8; 4096];
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break pos + 4;
+                    }
+                };
+                let headers = String::from_utf8_lossy(&buf[..header_end]).to_lowercase();
+                let length: usize = headers.lines()
+                    .find_map(|l| l.strip_prefix("content-length:"))
+                    .map(|v| v.trim().parse().unwrap())
+                    .unwrap_or(0);
+                while buf.len() < header_end + length {
+                    let mut chunk = [0u8; 4096];
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                
+                let mut body = String::new();
+                flate2::read::GzDecoder::new(&buf[header_end..header_end + length])
+                    .read_to_string(&mut body)
+                    .unwrap();
+                recorded.lock().push((headers, body));
+                
+                let status = statuses.next().unwrap_or(204);
+                let response = format!("HTTP/1.1 {} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        
+        (url, requests)
+    }
+    
+    #[tokio::test]
+    async fn test_batches_are_written_and_retried() {
+        let (url, requests) = mock_influx(vec![503]).await;
+        let config = StreamingConfig {
+            influx_url: url,
+            influx_bucket: "field".to_string(),
+            influx_token: Some("secret".to_string()),
+            influx_batch_size: 2,
+            ..Default::default()
+        };
+        let client = InfluxClient::new(&config).unwrap();
+        
+        for i in 0..5 {
+            client.push(format!("sensor,id=emf-1 value={} {}", i, i));
+        }
+        assert_eq!(client.flush().await.unwrap(), 2);
+        client.flush_all().await.unwrap();
+        assert_eq!(client.pending(), 0);
+        
+        let requests = requests.lock();
+        // The first batch was refused once with 503 and sent again
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0].1, requests[1].1);
+        
+        let bodies: Vec<&str> = requests[1..].iter().map(|(_, body)| body.as_str()).collect();
+        assert_eq!(bodies, vec![
+            "sensor,id=emf-1 value=0 0\nsensor,id=emf-1 value=1 1",
+            "sensor,id=emf-1 value=2 2\nsensor,id=emf-1 value=3 3",
+            "sensor,id=emf-1 value=4 4",
+        ]);
+        
+        let headers = &requests[0].0;
+        assert!(headers.starts_with("post /api/v2/write?org=glowbarn&bucket=field&precision=ns"));
+        assert!(headers.contains("authorization: token secret"));
+        assert!(headers.contains("content-encoding: gzip"));
+    }
+    
+    #[tokio::test]
+    async fn test_full_queue_drops_oldest() {
+        let config = StreamingConfig {
+            influx_enabled: true,
+            influx_batch_size: 2,
+            influx_max_pending: 3,
+            ..Default::default()
+        };
+        let client = InfluxClient::new(&config).unwrap();
+        
+        for i in 0..5 {
+            client.push(format!("p{}", i));
+        }
+        assert_eq!(client.pending(), 3);
+        assert_eq!(client.dropped(), 2);
+        assert_eq!(client.pending.lock().front().map(String::as_str), Some("p2"));
+    }
+}
@@ -3,24 +3,38 @@
 // https://github.com/bad-antics/glowbarn-rs
 
 //! Streaming module - MQTT, WebSocket, and data export
+//!
+//! MQTT topic layout:
+//!
+//! | Topic                       | Payload          | QoS              | Retain                     |
+//! |-----------------------------|------------------|------------------|----------------------------|
+//! | `glowbarn/sensors/<id>`     | `SensorReading`  | 0                | no                         |
+//! | `glowbarn/detections`       | `Detection`      | `mqtt_qos`       | `mqtt_retain_detections`   |
 
 mod mqtt;
 mod websocket;
 mod export;
+mod influx;
 
 pub use mqtt::*;
 pub use websocket::*;
 pub use export::*;
+pub use influx::InfluxClient;
 
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::broadcast;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 
 use crate::config::Config;
 
+/// MQTT topic for detections
+pub const DETECTIONS_TOPIC: &str = "glowbarn/detections";
+
 /// Streaming configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StreamingConfig {
     /// Enable MQTT
     pub mqtt_enabled: bool,
@@ -30,6 +44,10 @@ pub struct StreamingConfig {
     pub mqtt_username: Option<String>,
     pub mqtt_password: Option<String>,
     pub mqtt_use_tls: bool,
+    /// QoS level (0-2) for detection messages; readings always use 0
+    pub mqtt_qos: u8,
+    /// Retain the latest detection so late subscribers receive it
+    pub mqtt_retain_detections: bool,
     
     /// Enable WebSocket server
     pub websocket_enabled: bool,
@@ -40,6 +58,22 @@ pub struct StreamingConfig {
     pub export_enabled: bool,
     pub export_format: ExportFormat,
     pub export_path: String,
+    
+    /// Push line protocol to an InfluxDB v2 `/api/v2/write` endpoint
+    pub influx_enabled: bool,
+    /// Server base URL, e.g. `http://localhost:8086`
+    pub influx_url: String,
+    pub influx_org: String,
+    pub influx_bucket: String,
+    pub influx_token: Option<String>,
+    /// Points per write request
+    pub influx_batch_size: usize,
+    /// Longest a point waits before its batch is sent
+    pub influx_flush_interval_ms: u64,
+    /// Points held while the server is slow or down; the oldest are dropped beyond this
+    pub influx_max_pending: usize,
+    /// Gzip request bodies
+    pub influx_gzip: bool,
 }
 
 impl Default for StreamingConfig {
@@ -52,6 +86,8 @@ impl Default for StreamingConfig {
             mqtt_username: None,
             mqtt_password: None,
             mqtt_use_tls: false,
+            mqtt_qos: 1,
+            mqtt_retain_detections: false,
             
             websocket_enabled: false,
             websocket_port: 8765,
@@ -60,10 +96,27 @@ impl Default for StreamingConfig {
             export_enabled: true,
             export_format: ExportFormat::Json,
             export_path: "./data".to_string(),
+            
+            influx_enabled: false,
+            influx_url: "http://localhost:8086".to_string(),
+            influx_org: "glowbarn".to_string(),
+            influx_bucket: "glowbarn".to_string(),
+            influx_token: None,
+            influx_batch_size: 5000,
+            influx_flush_interval_ms: 1000,
+            influx_max_pending: 100_000,
+            influx_gzip: true,
         }
     }
 }
 
+impl StreamingConfig {
+    /// Whether any output (MQTT, WebSocket, InfluxDB or file export) is on
+    pub fn any_enabled(&self) -> bool {
+        self.mqtt_enabled || self.websocket_enabled || self.influx_enabled || self.export_enabled
+    }
+}
+
 /// Export format
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ExportFormat {
@@ -71,6 +124,8 @@ pub enum ExportFormat {
     Csv,
     Binary,
     InfluxLineProtocol,
+    /// One `FeatureCollection` of located detections; batch exports only
+    GeoJson,
 }
 
 /// Streaming manager
@@ -78,11 +133,18 @@ pub struct StreamingManager {
     config: StreamingConfig,
     mqtt_client: Option<MqttClient>,
     websocket_server: Option<WebSocketServer>,
+    influx: Option<InfluxClient>,
     exporter: DataExporter,
+    /// Starts as `config.export_enabled`; toggled by recording sessions
+    export_active: AtomicBool,
 }
 
 impl StreamingManager {
     pub async fn new(config: StreamingConfig) -> Result<Self> {
+        if config.mqtt_qos > 2 {
+            bail!("Invalid MQTT QoS {} (expected 0, 1 or 2)", config.mqtt_qos);
+        }
+        
         let mqtt_client = if config.mqtt_enabled {
             Some(MqttClient::new(&config).await?)
         } else {
@@ -95,12 +157,20 @@ impl StreamingManager {
             None
         };
         
+        let influx = if config.influx_enabled {
+            Some(InfluxClient::new(&config)?)
+        } else {
+            None
+        };
+        
         let exporter = DataExporter::new(&config.export_path, config.export_format)?;
         
         Ok(Self {
+            export_active: AtomicBool::new(config.export_enabled),
             config,
             mqtt_client,
             websocket_server,
+            influx,
             exporter,
         })
     }
@@ -110,6 +180,10 @@ impl StreamingManager {
             mqtt.connect().await?;
         }
         
+        if let Some(ref influx) = self.influx {
+            influx.start(shutdown.resubscribe());
+        }
+        
         if let Some(ref mut ws) = self.websocket_server {
             ws.start(shutdown).await?;
         }
@@ -120,8 +194,7 @@ impl StreamingManager {
     pub async fn publish_reading(&self, reading: &crate::sensors::SensorReading) -> Result<()> {
         // MQTT
         if let Some(ref mqtt) = self.mqtt_client {
-            let topic = format!("glowbarn/sensors/{}", reading.sensor_id);
-            mqtt.publish(&topic, reading).await?;
+            mqtt.publish_message(reading_message(reading)?).await?;
         }
         
         // WebSocket
@@ -129,8 +202,13 @@ impl StreamingManager {
             ws.broadcast(reading).await?;
         }
         
+        // InfluxDB
+        if let Some(ref influx) = self.influx {
+            influx.push(influx_reading_line(reading));
+        }
+        
         // Export
-        if self.config.export_enabled {
+        if self.export_enabled() {
             self.exporter.export_reading(reading)?;
         }
         
@@ -140,7 +218,7 @@ impl StreamingManager {
     pub async fn publish_detection(&self, detection: &crate::detection::Detection) -> Result<()> {
         // MQTT
         if let Some(ref mqtt) = self.mqtt_client {
-            mqtt.publish("glowbarn/detections", detection).await?;
+            mqtt.publish_message(detection_message(&self.config, detection)?).await?;
         }
         
         // WebSocket
@@ -148,11 +226,105 @@ impl StreamingManager {
             ws.broadcast_detection(detection).await?;
         }
         
+        // InfluxDB
+        if let Some(ref influx) = self.influx {
+            influx.push(influx_detection_line(detection));
+        }
+        
         // Export
-        if self.config.export_enabled {
+        if self.export_enabled() {
             self.exporter.export_detection(detection)?;
         }
         
         Ok(())
     }
+    
+    /// Whether readings and detections are currently written to export files
+    pub fn export_enabled(&self) -> bool {
+        self.export_active.load(Ordering::Relaxed)
+    }
+    
+    /// Start or stop writing export files; stopping flushes and closes the
+    /// current files so the next start opens fresh ones
+    pub fn set_export_enabled(&self, enabled: bool) -> Result<()> {
+        let was_enabled = self.export_active.swap(enabled, Ordering::Relaxed);
+        if was_enabled && !enabled {
+            self.exporter.close()?;
+        }
+        Ok(())
+    }
+    
+    /// Flush export files and pending InfluxDB points, and disconnect from the MQTT broker
+    pub async fn close(&self) -> Result<()> {
+        self.exporter.close()?;
+        
+        if let Some(ref influx) = self.influx {
+            influx.flush_all().await?;
+        }
+        
+        if let Some(ref mqtt) = self.mqtt_client {
+            mqtt.disconnect().await?;
+        }
+        
+        Ok(())
+    }
+}
+
+/// Build the MQTT message for a sensor reading (QoS 0, not retained)
+fn reading_message(reading: &crate::sensors::SensorReading) -> Result<MqttMessage> {
+    Ok(MqttMessage {
+        topic: format!("glowbarn/sensors/{}", reading.sensor_id),
+        payload: serde_json::to_vec(reading)?,
+        qos: 0,
+        retain: false,
+    })
+}
+
+/// Build the MQTT message for a detection using the configured QoS/retain
+fn detection_message<T: Serialize>(config: &StreamingConfig, detection: &T) -> Result<MqttMessage> {
+    Ok(MqttMessage {
+        topic: DETECTIONS_TOPIC.to_string(),
+        payload: serde_json::to_vec(detection)?,
+        qos: config.mqtt_qos,
+        retain: config.mqtt_retain_detections,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::{SensorReading, SensorType};
+    
+    #[test]
+    fn test_detection_message_uses_configured_qos_and_retain() {
+        let config = StreamingConfig {
+            mqtt_qos: 2,
+            mqtt_retain_detections: true,
+            ..Default::default()
+        };
+        
+        let msg = detection_message(&config, &serde_json::json!({"confidence": 0.9})).unwrap();
+        assert_eq!(msg.topic, DETECTIONS_TOPIC);
+        assert_eq!(msg.qos, 2);
+        assert!(msg.retain);
+    }
+    
+    #[test]
+    fn test_reading_message_is_fire_and_forget() {
+        let reading = SensorReading::new("emf-probe-1", SensorType::EMFProbe, vec![1.0]);
+        let msg = reading_message(&reading).unwrap();
+        assert_eq!(msg.topic, "glowbarn/sensors/emf-probe-1");
+        assert_eq!(msg.qos, 0);
+        assert!(!msg.retain);
+    }
+    
+    #[tokio::test]
+    async fn test_invalid_qos_rejected() {
+        let config = StreamingConfig {
+            mqtt_qos: 3,
+            export_enabled: false,
+            ..Default::default()
+        };
+        assert!(StreamingManager::new(config).await.is_err());
+    }
 }
@@ -5,19 +5,34 @@
 //! MQTT client for streaming data
 
 use anyhow::{anyhow, Result};
-use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS, Transport};
+use parking_lot::Mutex;
+use rand::Rng;
+use rumqttc::{AsyncClient, ConnectReturnCode, Event, MqttOptions, Packet, QoS, Transport};
 use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::RwLock;
 use tracing::{info, warn, error, debug};
 
 use super::StreamingConfig;
 
+/// Maximum number of messages held while the broker is unreachable
+const MAX_PENDING_MESSAGES: usize = 1000;
+
 /// MQTT client wrapper
+///
+/// The rumqttc event loop runs in a background task that reconnects with
+/// exponential backoff. Messages published while disconnected are queued
+/// (bounded, oldest dropped first) and flushed once the broker acks the
+/// new session; subscriptions are re-issued on every reconnect.
 pub struct MqttClient {
     client: AsyncClient,
     config: MqttConfig,
-    connected: RwLock<bool>,
+    connected: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
+    pending: Arc<Mutex<VecDeque<MqttMessage>>>,
+    subscriptions: Arc<Mutex<Vec<String>>>,
 }
 
 #[derive(Clone)]
@@ -29,7 +44,10 @@ pub struct MqttConfig {
     pub password: Option<String>,
     pub use_tls: bool,
     pub keep_alive_secs: u64,
+    /// Initial reconnect delay, doubled after each failed attempt
     pub reconnect_interval_ms: u64,
+    /// Upper bound for the reconnect delay
+    pub max_reconnect_interval_ms: u64,
 }
 
 impl MqttClient {
@@ -42,7 +60,8 @@ impl MqttClient {
             password: config.mqtt_password.clone(),
             use_tls: config.mqtt_use_tls,
             keep_alive_secs: 30,
-            reconnect_interval_ms: 5000,
+            reconnect_interval_ms: 1000,
+            max_reconnect_interval_ms: 60_000,
         };
         
         let mut options = MqttOptions::new(
@@ -65,51 +84,115 @@ impl MqttClient {
         
         let (client, mut eventloop) = AsyncClient::new(options, 100);
         
+        let connected = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let pending = Arc::new(Mutex::new(VecDeque::new()));
+        let subscriptions = Arc::new(Mutex::new(Vec::<String>::new()));
+        
         // Spawn eventloop handler
-        tokio::spawn(async move {
-            loop {
-                match eventloop.poll().await {
-                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
-                        info!("MQTT connected");
-                    }
-                    Ok(Event::Incoming(Packet::Publish(msg))) => {
-                        debug!("MQTT received: {:?}", msg.topic);
-                    }
-                    Ok(_) => {}
-                    Err(e) => {
-                        warn!("MQTT error: {:?}", e);
-                        tokio::time::sleep(Duration::from_secs(5)).await;
+        {
+            let client = client.clone();
+            let connected = connected.clone();
+            let stopped = stopped.clone();
+            let pending = pending.clone();
+            let subscriptions = subscriptions.clone();
+            let base_ms = mqtt_config.reconnect_interval_ms;
+            let max_ms = mqtt_config.max_reconnect_interval_ms;
+            
+            tokio::spawn(async move {
+                let mut attempt: u32 = 0;
+                
+                loop {
+                    match eventloop.poll().await {
+                        Ok(Event::Incoming(Packet::ConnAck(ack))) if ack.code != ConnectReturnCode::Success => {
+                            // Credentials or authorization; the session is unusable
+                            connected.store(false, Ordering::SeqCst);
+                            
+                            let delay = backoff_delay(attempt, base_ms, max_ms, &mut rand::thread_rng());
+                            attempt = attempt.saturating_add(1);
+                            warn!(
+                                "MQTT broker refused the connection: {:?}; retry #{} in {} ms ({} messages queued)",
+                                ack.code, attempt, delay.as_millis(), pending.lock().len()
+                            );
+                            tokio::time::sleep(delay).await;
+                        }
+                        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                            info!("MQTT connected");
+                            attempt = 0;
+                            connected.store(true, Ordering::SeqCst);
+                            
+                            for topic in subscriptions.lock().iter() {
+                                if let Err(e) = client.try_subscribe(topic.as_str(), QoS::AtLeastOnce) {
+                                    warn!("MQTT resubscribe to {} failed: {}", topic, e);
+                                }
+                            }
+                            
+                            flush_pending(&client, &pending);
+                        }
+                        Ok(Event::Incoming(Packet::Publish(msg))) => {
+                            debug!("MQTT received: {:?}", msg.topic);
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            connected.store(false, Ordering::SeqCst);
+                            
+                            if stopped.load(Ordering::SeqCst) {
+                                info!("MQTT event loop stopped");
+                                break;
+                            }
+                            
+                            let delay = backoff_delay(attempt, base_ms, max_ms, &mut rand::thread_rng());
+                            attempt = attempt.saturating_add(1);
+                            warn!(
+                                "MQTT connection error: {:?}; retry #{} in {} ms ({} messages queued)",
+                                e, attempt, delay.as_millis(), pending.lock().len()
+                            );
+                            tokio::time::sleep(delay).await;
+                        }
                     }
                 }
-            }
-        });
+            });
+        }
         
         Ok(Self {
             client,
             config: mqtt_config,
-            connected: RwLock::new(false),
+            connected,
+            stopped,
+            pending,
+            subscriptions,
         })
     }
     
     pub async fn connect(&self) -> Result<()> {
-        // Connection is handled by eventloop
-        *self.connected.write().await = true;
+        // Connection and reconnection are handled by the eventloop task
         info!("MQTT client initialized for {}:{}", self.config.broker, self.config.port);
         Ok(())
     }
     
     pub async fn publish<T: Serialize>(&self, topic: &str, payload: &T) -> Result<()> {
         let json = serde_json::to_vec(payload)?;
-        
-        self.client.publish(topic, QoS::AtLeastOnce, false, json)
-            .await
-            .map_err(|e| anyhow!("MQTT publish failed: {}", e))?;
-        
-        Ok(())
+        self.publish_raw(topic, &json).await
     }
     
     pub async fn publish_raw(&self, topic: &str, payload: &[u8]) -> Result<()> {
-        self.client.publish(topic, QoS::AtLeastOnce, false, payload.to_vec())
+        self.publish_message(MqttMessage {
+            topic: topic.to_string(),
+            payload: payload.to_vec(),
+            qos: 1,
+            retain: false,
+        }).await
+    }
+    
+    /// Publish a message with explicit QoS/retain, queueing it while the
+    /// broker is unreachable
+    pub async fn publish_message(&self, msg: MqttMessage) -> Result<()> {
+        if !self.is_connected() {
+            enqueue(&self.pending, msg);
+            return Ok(());
+        }
+        
+        self.client.publish(msg.topic.as_str(), qos_from_u8(msg.qos), msg.retain, msg.payload)
             .await
             .map_err(|e| anyhow!("MQTT publish failed: {}", e))?;
         
@@ -117,28 +200,88 @@ impl MqttClient {
     }
     
     pub async fn subscribe(&self, topic: &str) -> Result<()> {
-        self.client.subscribe(topic, QoS::AtLeastOnce)
-            .await
-            .map_err(|e| anyhow!("MQTT subscribe failed: {}", e))?;
+        {
+            let mut subs = self.subscriptions.lock();
+            if !subs.iter().any(|t| t == topic) {
+                subs.push(topic.to_string());
+            }
+        }
+        
+        // When disconnected the subscription is issued on the next ConnAck
+        if self.is_connected() {
+            self.client.subscribe(topic, QoS::AtLeastOnce)
+                .await
+                .map_err(|e| anyhow!("MQTT subscribe failed: {}", e))?;
+        }
         
         info!("Subscribed to MQTT topic: {}", topic);
         Ok(())
     }
     
     pub async fn disconnect(&self) -> Result<()> {
+        self.stopped.store(true, Ordering::SeqCst);
+        
         self.client.disconnect()
             .await
             .map_err(|e| anyhow!("MQTT disconnect failed: {}", e))?;
         
-        *self.connected.write().await = false;
+        self.connected.store(false, Ordering::SeqCst);
         Ok(())
     }
     
-    pub async fn is_connected(&self) -> bool {
-        *self.connected.read().await
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+    
+    /// Number of messages waiting for the broker to come back
+    pub fn pending_count(&self) -> usize {
+        self.pending.lock().len()
+    }
+}
+
+fn qos_from_u8(qos: u8) -> QoS {
+    match qos {
+        0 => QoS::AtMostOnce,
+        1 => QoS::AtLeastOnce,
+        _ => QoS::ExactlyOnce,
     }
 }
 
+fn enqueue(pending: &Mutex<VecDeque<MqttMessage>>, msg: MqttMessage) {
+    let mut queue = pending.lock();
+    if queue.len() >= MAX_PENDING_MESSAGES {
+        queue.pop_front();
+    }
+    queue.push_back(msg);
+}
+
+/// Hand queued messages to the client without blocking the event loop
+fn flush_pending(client: &AsyncClient, pending: &Mutex<VecDeque<MqttMessage>>) {
+    let mut queue = pending.lock();
+    let total = queue.len();
+    
+    while let Some(msg) = queue.pop_front() {
+        if let Err(e) = client.try_publish(msg.topic.as_str(), qos_from_u8(msg.qos), msg.retain, msg.payload.clone()) {
+            // Request channel is full; keep the rest for the next reconnect
+            error!("MQTT flush stalled: {}", e);
+            queue.push_front(msg);
+            break;
+        }
+    }
+    
+    if total > 0 {
+        info!("Flushed {} queued MQTT messages", total - queue.len());
+    }
+}
+
+/// Exponential backoff with up to 25% random jitter
+fn backoff_delay<R: Rng>(attempt: u32, base_ms: u64, max_ms: u64, rng: &mut R) -> Duration {
+    let exp = base_ms.saturating_mul(1u64 << attempt.min(16));
+    let capped = exp.min(max_ms);
+    let jitter = rng.gen_range(0..=capped / 4);
+    Duration::from_millis(capped + jitter)
+}
+
 /// MQTT message
 #[derive(Debug, Clone)]
 pub struct MqttMessage {
@@ -147,3 +290,161 @@ pub struct MqttMessage {
     pub qos: u8,
     pub retain: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc;
+    
+    async fn read_packet(socket: &mut TcpStream) -> std::io::Result<(u8, Vec<u8>)> {
+        let header = socket.read_u8().await?;
+        let mut len = 0usize;
+        for shift in (0..28).step_by(7) {
+            let byte = socket.read_u8().await?;
+            len |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let mut body = vec![0; len];
+        socket.read_exact(&mut body).await?;
+        Ok((header, body))
+    }
+    
+    /// Just enough of an MQTT 3.1.1 broker: answers CONNECT with
+    /// `return_code` (reporting it as `CONNECT`) and PINGREQ, and reports
+    /// the topic of every PUBLISH
+    async fn fake_broker(listener: TcpListener, return_code: u8, packets: mpsc::UnboundedSender<String>) {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            while let Ok((header, body)) = read_packet(&mut socket).await {
+                let connack = [0x20, 0x02, 0x00, return_code];
+                let reply: &[u8] = match header >> 4 {
+                    1 => {
+                        let _ = packets.send("CONNECT".to_string());
+                        &connack
+                    }
+                    3 => {
+                        let len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                        let _ = packets.send(String::from_utf8_lossy(&body[2..2 + len]).into_owned());
+                        &[]
+                    }
+                    12 => &[0xd0, 0x00],
+                    _ => &[],
+                };
+                if socket.write_all(reply).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    
+    async fn wait_until(condition: impl Fn() -> bool) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !condition() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        }).await.expect("condition was not reached");
+    }
+    
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let mut rng = StdRng::seed_from_u64(7);
+        
+        let first = backoff_delay(0, 1000, 60_000, &mut rng).as_millis();
+        assert!((1000..=1250).contains(&first));
+        
+        let third = backoff_delay(2, 1000, 60_000, &mut rng).as_millis();
+        assert!((4000..=5000).contains(&third));
+        
+        let late = backoff_delay(30, 1000, 60_000, &mut rng).as_millis();
+        assert!((60_000..=75_000).contains(&late));
+    }
+    
+    #[test]
+    fn test_pending_queue_is_bounded() {
+        let pending = Mutex::new(VecDeque::new());
+        for i in 0..MAX_PENDING_MESSAGES + 5 {
+            enqueue(&pending, MqttMessage {
+                topic: format!("glowbarn/test/{}", i),
+                payload: vec![],
+                qos: 1,
+                retain: false,
+            });
+        }
+        
+        let queue = pending.lock();
+        assert_eq!(queue.len(), MAX_PENDING_MESSAGES);
+        assert_eq!(queue.front().unwrap().topic, "glowbarn/test/5");
+    }
+    
+    #[tokio::test]
+    async fn test_reconnects_and_flushes_after_broker_restart() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (packets_tx, mut packets) = mpsc::unbounded_channel();
+        let broker = tokio::spawn(fake_broker(listener, 0, packets_tx.clone()));
+        
+        let config = StreamingConfig {
+            mqtt_broker: "127.0.0.1".to_string(),
+            mqtt_port: port,
+            ..Default::default()
+        };
+        let client = MqttClient::new(&config).await.unwrap();
+        wait_until(|| client.is_connected()).await;
+        
+        // Kill the broker; the client notices and queues what is published meanwhile
+        broker.abort();
+        let _ = broker.await;
+        wait_until(|| !client.is_connected()).await;
+        
+        client.publish_message(MqttMessage {
+            topic: "glowbarn/test/offline".to_string(),
+            payload: b"{}".to_vec(),
+            qos: 0,
+            retain: false,
+        }).await.unwrap();
+        assert_eq!(client.pending_count(), 1);
+        
+        // Restart it on the same port; the queued message follows the reconnect
+        let listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+        let broker = tokio::spawn(fake_broker(listener, 0, packets_tx));
+        wait_until(|| client.is_connected()).await;
+        
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while packets.recv().await.unwrap() != "glowbarn/test/offline" {}
+        }).await.expect("queued message was not delivered");
+        assert_eq!(client.pending_count(), 0);
+        
+        broker.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_refused_connection_keeps_messages_queued() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (packets_tx, mut packets) = mpsc::unbounded_channel();
+        // 5: not authorized
+        let broker = tokio::spawn(fake_broker(listener, 5, packets_tx));
+        
+        let config = StreamingConfig {
+            mqtt_broker: "127.0.0.1".to_string(),
+            mqtt_port: port,
+            ..Default::default()
+        };
+        let client = MqttClient::new(&config).await.unwrap();
+        let packet = tokio::time::timeout(Duration::from_secs(5), packets.recv()).await.unwrap().unwrap();
+        assert_eq!(packet, "CONNECT");
+        
+        // Give the refusal time to arrive
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!client.is_connected());
+        client.publish_raw("glowbarn/test/refused", b"{}").await.unwrap();
+        assert_eq!(client.pending_count(), 1);
+        
+        broker.abort();
+    }
+}
@@ -6,11 +6,11 @@
 
 use anyhow::{anyhow, Result};
 use futures_util::{SinkExt, StreamExt};
-use serde::Serialize;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::{broadcast, RwLock};
 use tokio_tungstenite::{accept_async, tungstenite::Message};
 use tracing::{info, warn, error, debug};
@@ -26,18 +26,117 @@ pub struct WebSocketServer {
     broadcast_tx: broadcast::Sender<WebSocketMessage>,
 }
 
+/// Number of lag events after which a slow client is disconnected
+const MAX_LAG_EVENTS: u32 = 10;
+
 struct ClientHandle {
     addr: SocketAddr,
     subscriptions: Vec<String>,
+    lag_events: u32,
+    skipped_messages: u64,
+}
+
+/// Tracks how often a client fell behind the broadcast channel
+#[derive(Debug, Default)]
+struct LagTracker {
+    events: u32,
+    skipped: u64,
+}
+
+impl LagTracker {
+    /// Record a lag event; returns true once the client should be dropped
+    fn record(&mut self, skipped: u64) -> bool {
+        self.events += 1;
+        self.skipped += skipped;
+        self.events > MAX_LAG_EVENTS
+    }
+    
+    fn notice(skipped: u64) -> serde_json::Value {
+        serde_json::json!({
+            "type": "lagged",
+            "skipped": skipped,
+        })
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum WebSocketMessage {
-    SensorReading(String),  // JSON
+    SensorReading { sensor_id: String, json: String },
     Detection(String),      // JSON
     System(String),         // System message
 }
 
+impl WebSocketMessage {
+    /// Topic this message is published under (`sensors/<id>`, `detections`, `system`)
+    pub fn topic(&self) -> String {
+        match self {
+            WebSocketMessage::SensorReading { sensor_id, .. } => format!("sensors/{}", sensor_id),
+            WebSocketMessage::Detection(_) => "detections".to_string(),
+            WebSocketMessage::System(_) => "system".to_string(),
+        }
+    }
+}
+
+/// Per-client topic subscriptions
+///
+/// Patterns are either exact topics, `*` for everything, or a prefix ending
+/// in `/*` (e.g. `sensors/*`). New clients receive everything until they
+/// subscribe to something explicitly; system messages reach every client.
+#[derive(Debug, Clone)]
+pub struct Subscriptions {
+    patterns: Vec<String>,
+    implicit_all: bool,
+}
+
+impl Default for Subscriptions {
+    fn default() -> Self {
+        Self {
+            patterns: vec!["*".to_string()],
+            implicit_all: true,
+        }
+    }
+}
+
+impl Subscriptions {
+    pub fn subscribe(&mut self, topic: &str) {
+        if self.implicit_all {
+            self.patterns.clear();
+            self.implicit_all = false;
+        }
+        if !self.patterns.iter().any(|p| p == topic) {
+            self.patterns.push(topic.to_string());
+        }
+    }
+    
+    pub fn unsubscribe(&mut self, topic: &str) {
+        self.implicit_all = false;
+        self.patterns.retain(|p| p != topic);
+    }
+    
+    pub fn matches(&self, topic: &str) -> bool {
+        self.patterns.iter().any(|p| topic_matches(p, topic))
+    }
+    
+    /// Whether a broadcast goes to this client; system messages always do
+    pub fn wants(&self, msg: &WebSocketMessage) -> bool {
+        matches!(msg, WebSocketMessage::System(_)) || self.matches(&msg.topic())
+    }
+    
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+}
+
+fn topic_matches(pattern: &str, topic: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    match pattern.strip_suffix('*') {
+        Some(prefix) => topic.starts_with(prefix),
+        None => pattern == topic,
+    }
+}
+
 impl WebSocketServer {
     pub fn new(port: u16, max_clients: usize) -> Self {
         let (broadcast_tx, _) = broadcast::channel(1000);
@@ -93,9 +192,12 @@ impl WebSocketServer {
         Ok(())
     }
     
-    pub async fn broadcast<T: Serialize>(&self, data: &T) -> Result<()> {
-        let json = serde_json::to_string(data)?;
-        let _ = self.broadcast_tx.send(WebSocketMessage::SensorReading(json));
+    pub async fn broadcast(&self, reading: &SensorReading) -> Result<()> {
+        let json = serde_json::to_string(reading)?;
+        let _ = self.broadcast_tx.send(WebSocketMessage::SensorReading {
+            sensor_id: reading.sensor_id.clone(),
+            json,
+        });
         Ok(())
     }
     
@@ -114,6 +216,22 @@ impl WebSocketServer {
         self.clients.read().await.len()
     }
     
+    /// Total messages skipped across connected clients because they lagged
+    pub async fn get_skipped_messages(&self) -> u64 {
+        self.clients.read().await
+            .values()
+            .map(|c| c.skipped_messages)
+            .sum()
+    }
+    
+    /// Total times connected clients fell behind the broadcast channel
+    pub async fn get_lag_events(&self) -> u32 {
+        self.clients.read().await
+            .values()
+            .map(|c| c.lag_events)
+            .sum()
+    }
+    
     pub async fn get_client_addrs(&self) -> Vec<SocketAddr> {
         self.clients.read().await
             .values()
@@ -140,15 +258,23 @@ async fn handle_connection(
     
     info!("New WebSocket connection from {} (id: {})", addr, client_id);
     
+    // Subscribe to all by default; the loop below owns the live set and
+    // mirrors it into the shared client table for introspection
+    let mut subscriptions = Subscriptions::default();
+    
     // Register client
     {
         let mut clients = clients.write().await;
         clients.insert(client_id.clone(), ClientHandle {
             addr,
-            subscriptions: vec!["*".to_string()],  // Subscribe to all by default
+            subscriptions: subscriptions.patterns().to_vec(),
+            lag_events: 0,
+            skipped_messages: 0,
         });
     }
     
+    let mut lag = LagTracker::default();
+    
     let (mut ws_sender, mut ws_receiver) = ws_stream.split();
     
     // Send welcome message
@@ -180,20 +306,24 @@ async fn handle_connection(
                                         let pong = serde_json::json!({"type": "pong"});
                                         let _ = ws_sender.send(Message::Text(pong.to_string().into())).await;
                                     }
-                                    "subscribe" => {
+                                    "subscribe" | "unsubscribe" => {
                                         if let Some(topic) = cmd.get("topic").and_then(|v| v.as_str()) {
-                                            let mut clients = clients.write().await;
-                                            if let Some(client) = clients.get_mut(&client_id) {
-                                                client.subscriptions.push(topic.to_string());
+                                            if cmd_type == "subscribe" {
+                                                subscriptions.subscribe(topic);
+                                            } else {
+                                                subscriptions.unsubscribe(topic);
                                             }
-                                        }
-                                    }
-                                    "unsubscribe" => {
-                                        if let Some(topic) = cmd.get("topic").and_then(|v| v.as_str()) {
+                                            
                                             let mut clients = clients.write().await;
                                             if let Some(client) = clients.get_mut(&client_id) {
-                                                client.subscriptions.retain(|s| s != topic);
+                                                client.subscriptions = subscriptions.patterns().to_vec();
                                             }
+                                            
+                                            let ack = serde_json::json!({
+                                                "type": "subscriptions",
+                                                "topics": subscriptions.patterns(),
+                                            });
+                                            let _ = ws_sender.send(Message::Text(ack.to_string().into())).await;
                                         }
                                     }
                                     _ => {}
@@ -219,8 +349,14 @@ async fn handle_connection(
             
             // Outgoing broadcasts
             msg = broadcast_rx.recv() => {
+                if let Ok(ref m) = msg {
+                    if !subscriptions.wants(m) {
+                        continue;
+                    }
+                }
+                
                 match msg {
-                    Ok(WebSocketMessage::SensorReading(json)) => {
+                    Ok(WebSocketMessage::SensorReading { json, .. }) => {
                         let wrapper = serde_json::json!({
                             "type": "reading",
                             "data": serde_json::from_str::<serde_json::Value>(&json).unwrap_or_default()
@@ -247,7 +383,30 @@ async fn handle_connection(
                         });
                         let _ = ws_sender.send(Message::Text(wrapper.to_string().into())).await;
                     }
-                    Err(_) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        let disconnect = lag.record(skipped);
+                        warn!("WebSocket client {} lagged, skipped {} messages", addr, skipped);
+                        
+                        {
+                            let mut clients = clients.write().await;
+                            if let Some(client) = clients.get_mut(&client_id) {
+                                client.lag_events = lag.events;
+                                client.skipped_messages = lag.skipped;
+                            }
+                        }
+                        
+                        let notice = LagTracker::notice(skipped);
+                        if ws_sender.send(Message::Text(notice.to_string().into())).await.is_err() {
+                            break;
+                        }
+                        
+                        if disconnect {
+                            warn!("Disconnecting {}: lagged {} times ({} messages skipped)", addr, lag.events, lag.skipped);
+                            let _ = ws_sender.send(Message::Close(None)).await;
+                            break;
+                        }
+                    }
+                    Err(RecvError::Closed) => break,
                 }
             }
         }
@@ -261,3 +420,95 @@ async fn handle_connection(
     
     info!("WebSocket client {} disconnected", addr);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn reading_msg(sensor_id: &str) -> WebSocketMessage {
+        WebSocketMessage::SensorReading {
+            sensor_id: sensor_id.to_string(),
+            json: "{}".to_string(),
+        }
+    }
+    
+    #[test]
+    fn test_default_subscription_receives_everything() {
+        let subs = Subscriptions::default();
+        assert!(subs.matches(&reading_msg("emf-probe-1").topic()));
+        assert!(subs.matches(&WebSocketMessage::Detection("{}".into()).topic()));
+    }
+    
+    #[test]
+    fn test_clients_filtered_by_sensor_id() {
+        let mut client_a = Subscriptions::default();
+        let mut client_b = Subscriptions::default();
+        client_a.subscribe("sensors/emf-probe-1");
+        client_b.subscribe("sensors/geiger-1");
+        
+        let emf = reading_msg("emf-probe-1").topic();
+        let geiger = reading_msg("geiger-1").topic();
+        
+        assert!(client_a.matches(&emf));
+        assert!(!client_a.matches(&geiger));
+        assert!(client_b.matches(&geiger));
+        assert!(!client_b.matches(&emf));
+        assert!(!client_a.matches("detections"));
+    }
+    
+    #[test]
+    fn test_system_messages_reach_every_client() {
+        let mut subs = Subscriptions::default();
+        subs.subscribe("sensors/emf-probe-1");
+        assert!(subs.wants(&WebSocketMessage::System("shutting down".into())));
+        assert!(!subs.wants(&WebSocketMessage::Detection("{}".into())));
+        
+        subs.unsubscribe("sensors/emf-probe-1");
+        assert!(subs.wants(&WebSocketMessage::System("shutting down".into())));
+        assert!(!subs.wants(&reading_msg("emf-probe-1")));
+    }
+    
+    #[tokio::test]
+    async fn test_flooded_client_is_notified() {
+        let (tx, mut rx) = broadcast::channel(4);
+        for i in 0..10 {
+            tx.send(reading_msg(&format!("sensor-{}", i))).unwrap();
+        }
+        
+        let mut lag = LagTracker::default();
+        match rx.recv().await {
+            Err(RecvError::Lagged(skipped)) => {
+                assert_eq!(skipped, 6);
+                assert!(!lag.record(skipped));
+                let notice = LagTracker::notice(skipped);
+                assert_eq!(notice["type"], "lagged");
+                assert_eq!(notice["skipped"], 6);
+            }
+            other => panic!("expected lag, got {:?}", other),
+        }
+        
+        // Remaining buffered messages are still delivered after the notice
+        assert!(rx.recv().await.is_ok());
+    }
+    
+    #[test]
+    fn test_repeated_lag_disconnects() {
+        let mut lag = LagTracker::default();
+        for _ in 0..MAX_LAG_EVENTS {
+            assert!(!lag.record(1));
+        }
+        assert!(lag.record(1));
+        assert_eq!(lag.skipped, (MAX_LAG_EVENTS + 1) as u64);
+    }
+    
+    #[test]
+    fn test_prefix_wildcard_and_unsubscribe() {
+        let mut subs = Subscriptions::default();
+        subs.subscribe("sensors/*");
+        assert!(subs.matches("sensors/qrng-1"));
+        assert!(!subs.matches("detections"));
+        
+        subs.unsubscribe("sensors/*");
+        assert!(!subs.matches("sensors/qrng-1"));
+    }
+}
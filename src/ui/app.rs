@@ -9,62 +9,731 @@ use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
 use chrono::Utc;
 
-use crate::config::Config;
-use crate::sensors::{SensorManager, SensorReading, SensorType};
-use crate::detection::{Detection, DetectionType, Severity};
-use super::{GuiState, SystemStats, ThermalData, SpectrumData};
+use crate::config::{Colormap, Config, FusionMethod, GuiConfig, Shortcuts, Theme};
+use crate::db::Database;
+use crate::streaming::{BatchExporter, ExportFormat, ExportSelection};
+use crate::core::SystemMonitor;
+use crate::sensors::{HealthStatus, SensorHealth, SensorManager, SensorReading, SensorType};
+use crate::detection::{Detection, DetectionAnnotation, DetectionType, Severity};
+use super::{FrameBudget, GuiState, LiveFeed, RingBuffer, ThermalData, SpectrumData};
 use super::panels::*;
 use super::widgets::*;
 use super::theme::*;
 
+/// How long a toast notification stays on screen
+const TOAST_DURATION: std::time::Duration = std::time::Duration::from_secs(4);
+
+/// How often the OS dark-mode preference is checked under `Theme::System`
+const SYSTEM_THEME_POLL: std::time::Duration = std::time::Duration::from_secs(5);
+
+type ExportOutcome = Result<(usize, std::path::PathBuf), String>;
+
+/// Something the user can trigger from a shortcut or the command palette
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    ToggleRecording,
+    Settings,
+    Export,
+    CloseDialogs,
+    CommandPalette,
+    ShowShortcuts,
+    About,
+    FocusSensor(String),
+}
+
+impl Command {
+    fn label(&self) -> String {
+        match self {
+            Command::ToggleRecording => "Start/stop recording".to_string(),
+            Command::Settings => "Settings".to_string(),
+            Command::Export => "Export data...".to_string(),
+            Command::CloseDialogs => "Close dialogs".to_string(),
+            Command::CommandPalette => "Command palette".to_string(),
+            Command::ShowShortcuts => "Keyboard shortcuts".to_string(),
+            Command::About => "About GlowBarn".to_string(),
+            Command::FocusSensor(id) => format!("Focus sensor {}", id),
+        }
+    }
+}
+
+/// Number keys that select sensors by list position
+const SENSOR_KEYS: [egui::Key; 9] = [
+    egui::Key::Num1, egui::Key::Num2, egui::Key::Num3,
+    egui::Key::Num4, egui::Key::Num5, egui::Key::Num6,
+    egui::Key::Num7, egui::Key::Num8, egui::Key::Num9,
+];
+
+/// Ctrl+P command palette
+#[derive(Default)]
+struct CommandPalette {
+    query: String,
+    selected: usize,
+}
+
+/// State of the File → Export Data dialog
+struct ExportDialog {
+    open: bool,
+    selection: ExportSelection,
+    format: ExportFormat,
+    hours: u32,
+    // Result of the export running in the background
+    pending: Option<std::sync::mpsc::Receiver<ExportOutcome>>,
+}
+
+impl Default for ExportDialog {
+    fn default() -> Self {
+        Self {
+            open: false,
+            selection: ExportSelection::Detections,
+            format: ExportFormat::Csv,
+            hours: 24,
+            pending: None,
+        }
+    }
+}
+
 /// Main GlowBarn application
 pub struct GlowBarnApp {
     config: Config,
+    // `config` as last loaded or saved, with its environment and command
+    // line overrides; saving writes only what changed since
+    config_base: Config,
+    config_path: std::path::PathBuf,
+    database: Option<Arc<Database>>,
+    // Data from a running engine; demo data is synthesized only without one
+    live: Option<LiveFeed>,
     state: GuiState,
     
     // Panels
     sensor_panel: SensorPanel,
+    sensor_detail_panel: SensorDetailPanel,
     waveform_panel: WaveformPanel,
     thermal_panel: ThermalPanel,
     spectrum_panel: SpectrumPanel,
+    spectrogram_panel: SpectrogramPanel,
+    show_waterfall: bool,
     detection_panel: DetectionPanel,
+    heatmap_panel: DetectionHeatmapPanel,
+    show_heatmap: bool,
     stats_panel: StatsPanel,
     
     // Demo data generation
     demo_mode: bool,
     frame_count: u64,
     
-    // Frame timing
+    // Frame timing: when the last frame started, how long it spent in
+    // `update` and the wait it asked for before the next
     last_update: std::time::Instant,
+    build_time: std::time::Duration,
+    repaint_wait: std::time::Duration,
+    frame_budget: FrameBudget,
+    start_time: std::time::Instant,
+    system_theme: SystemThemeWatcher,
+    // OS dark/light preference, once the watcher has reported it
+    system_mode: Option<dark_light::Mode>,
+    
+    // Process resource usage
+    monitor: Option<SystemMonitor>,
+    
+    // Outcome of the last settings save
+    settings_status: Option<Result<(), String>>,
+    
+    export: ExportDialog,
+    
+    // Transient notification: message, success, shown at
+    toast: Option<(String, bool, std::time::Instant)>,
+    
+    // Parsed from `config.gui.shortcuts`
+    shortcuts: Vec<(Command, egui::KeyboardShortcut)>,
+    palette: Option<CommandPalette>,
+    show_shortcuts: bool,
 }
 
 impl GlowBarnApp {
-    pub fn new(cc: &eframe::CreationContext<'_>, config: Config) -> Self {
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        config: Config,
+        config_path: std::path::PathBuf,
+        database: Option<Arc<Database>>,
+        live: Option<LiveFeed>,
+    ) -> Self {
         let demo_mode = config.demo_mode;
+        let shortcuts = bind_shortcuts(&config.gui.shortcuts);
+        let budget = FrameBudget::new(frame_budget(&config.gui));
+        
+        let mut state = GuiState::default();
+        if let Some(ref db) = database {
+            match db.detection_annotations() {
+                Ok(annotations) => {
+                    state.annotations = annotations.into_iter().map(|a| (a.detection_id.clone(), a)).collect();
+                }
+                Err(e) => tracing::warn!("Failed to load detection annotations: {}", e),
+            }
+        }
         
         Self {
+            config_base: config.clone(),
             config,
-            state: GuiState::default(),
+            config_path,
+            database,
+            live,
+            state,
             sensor_panel: SensorPanel::new(),
+            sensor_detail_panel: SensorDetailPanel::new(),
             waveform_panel: WaveformPanel::new(),
             thermal_panel: ThermalPanel::new(),
             spectrum_panel: SpectrumPanel::new(),
+            spectrogram_panel: SpectrogramPanel::new(),
+            show_waterfall: false,
             detection_panel: DetectionPanel::new(),
+            heatmap_panel: DetectionHeatmapPanel::new(),
+            show_heatmap: false,
             stats_panel: StatsPanel::new(),
             demo_mode,
             frame_count: 0,
             last_update: std::time::Instant::now(),
+            build_time: std::time::Duration::ZERO,
+            repaint_wait: std::time::Duration::ZERO,
+            frame_budget: budget,
+            start_time: std::time::Instant::now(),
+            system_theme: SystemThemeWatcher::spawn(cc.egui_ctx.clone(), SYSTEM_THEME_POLL),
+            system_mode: None,
+            monitor: SystemMonitor::new().ok(),
+            settings_status: None,
+            export: ExportDialog::default(),
+            toast: None,
+            shortcuts,
+            palette: None,
+            show_shortcuts: false,
+        }
+    }
+    
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::ToggleRecording => self.toggle_recording(),
+            Command::Settings => self.state.show_settings = !self.state.show_settings,
+            Command::Export => self.export.open = true,
+            Command::CloseDialogs => {
+                self.state.show_settings = false;
+                self.state.show_about = false;
+                self.export.open = false;
+                self.palette = None;
+                self.show_shortcuts = false;
+            }
+            Command::CommandPalette => self.palette = Some(CommandPalette::default()),
+            Command::ShowShortcuts => self.show_shortcuts = true,
+            Command::About => self.state.show_about = true,
+            Command::FocusSensor(id) => self.state.selected_sensor = Some(id),
+        }
+    }
+    
+    /// Shortcut text for a command, e.g. "Ctrl+P"
+    fn shortcut_text(&self, ctx: &egui::Context, command: &Command) -> Option<String> {
+        self.shortcuts.iter()
+            .find(|(c, _)| c == command)
+            .map(|(_, shortcut)| ctx.format_shortcut(shortcut))
+    }
+    
+    /// Run commands whose shortcuts were pressed this frame
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        // Plain keys would fire while typing; only chorded keys and Esc pass then
+        let typing = ctx.wants_keyboard_input();
+        let mut triggered = Vec::new();
+        
+        ctx.input_mut(|input| {
+            for (command, shortcut) in &self.shortcuts {
+                let chorded = shortcut.modifiers.any() || shortcut.logical_key == egui::Key::Escape;
+                if (!typing || chorded) && input.consume_shortcut(shortcut) {
+                    triggered.push(command.clone());
+                }
+            }
+            
+            if !typing && self.config.gui.shortcuts.number_keys_focus_sensors {
+                for (n, key) in SENSOR_KEYS.into_iter().enumerate() {
+                    if input.consume_key(egui::Modifiers::NONE, key) {
+                        if let Some(health) = self.state.sensor_health.get(n) {
+                            triggered.push(Command::FocusSensor(health.sensor_id.clone()));
+                        }
+                    }
+                }
+            }
+        });
+        
+        for command in triggered {
+            self.run_command(command);
+        }
+    }
+    
+    /// Searchable list of every command
+    fn command_palette(&mut self, ctx: &egui::Context) {
+        let Some(palette) = self.palette.as_mut() else { return };
+        
+        let mut commands = vec![
+            Command::ToggleRecording,
+            Command::Settings,
+            Command::Export,
+            Command::ShowShortcuts,
+            Command::About,
+            Command::CloseDialogs,
+        ];
+        commands.extend(self.state.sensor_health.iter().map(|h| Command::FocusSensor(h.sensor_id.clone())));
+        
+        let query = palette.query.to_lowercase();
+        let matches: Vec<Command> = commands.into_iter()
+            .filter(|c| c.label().to_lowercase().contains(&query))
+            .collect();
+        
+        let (up, down, enter) = ctx.input(|i| {
+            (i.key_pressed(egui::Key::ArrowUp), i.key_pressed(egui::Key::ArrowDown), i.key_pressed(egui::Key::Enter))
+        });
+        if down {
+            palette.selected += 1;
+        }
+        if up {
+            palette.selected = palette.selected.saturating_sub(1);
+        }
+        palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+        
+        let mut chosen = enter.then(|| matches.get(palette.selected).cloned()).flatten();
+        let labels: Vec<(String, Option<String>)> = matches.iter()
+            .map(|c| (c.label(), self.shortcut_text(ctx, c)))
+            .collect();
+        
+        let palette = self.palette.as_mut().expect("checked above");
+        egui::Window::new("Command Palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 80.0))
+            .show(ctx, |ui| {
+                let search = ui.add(egui::TextEdit::singleline(&mut palette.query)
+                    .hint_text("Type a command...")
+                    .desired_width(320.0));
+                search.request_focus();
+                ui.separator();
+                
+                for (i, (label, shortcut)) in labels.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.selectable_label(i == palette.selected, label).clicked() {
+                            chosen = matches.get(i).cloned();
+                        }
+                        if let Some(shortcut) = shortcut {
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                ui.weak(shortcut);
+                            });
+                        }
+                    });
+                }
+                if labels.is_empty() {
+                    ui.weak("No matching commands");
+                }
+            });
+        
+        if let Some(command) = chosen {
+            self.palette = None;
+            self.run_command(command);
+        }
+    }
+    
+    fn shortcuts_window(&mut self, ctx: &egui::Context) {
+        let mut rows: Vec<(String, String)> = self.shortcuts.iter()
+            .map(|(command, shortcut)| (ctx.format_shortcut(shortcut), command.label()))
+            .collect();
+        if self.config.gui.shortcuts.number_keys_focus_sensors {
+            rows.push(("1-9".to_string(), "Focus nth sensor in the list".to_string()));
+        }
+        
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut self.show_shortcuts)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcuts_grid").striped(true).show(ui, |ui| {
+                    for (keys, action) in &rows {
+                        ui.monospace(keys);
+                        ui.label(action);
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+                ui.weak("Change these under [gui.shortcuts] in the config file.");
+            });
+    }
+    
+    /// Inspector window for the selected sensor
+    fn sensor_detail_window(&mut self, ctx: &egui::Context) {
+        let Some(id) = self.state.selected_sensor.clone() else { return };
+        let mut open = true;
+        let mut action = None;
+        
+        egui::Window::new(format!("🔎 {}", id))
+            .id(egui::Id::new("sensor_detail"))
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                action = self.sensor_detail_panel.show(ui, &self.state, self.live.is_some());
+            });
+        
+        if !open {
+            self.state.selected_sensor = None;
+        }
+        
+        if let (Some(action), Some(ref mut live)) = (action, &mut self.live) {
+            live.control_sensor(&id, action);
         }
     }
     
+    /// Run an enable/disable or Start/Stop All from the sensor list
+    fn apply_sensor_list_action(&mut self, action: SensorListAction) {
+        let Some(ref mut live) = self.live else { return };
+        match action {
+            SensorListAction::Sensor(id, action) => live.control_sensor(&id, action),
+            SensorListAction::StartAll => live.set_all_running(true),
+            SensorListAction::StopAll => live.set_all_running(false),
+        }
+    }
+    
+    /// Toast the outcome of control actions the live engine finished since
+    /// the last frame
+    fn collect_finished_actions(&mut self) {
+        let Some(ref mut live) = self.live else { return };
+        for (label, result) in live.finished_actions() {
+            let (message, ok) = match result {
+                Ok(()) => (format!("{} done", label), true),
+                Err(e) => (format!("{} failed: {}", label, e), false),
+            };
+            self.toast = Some((message, ok, std::time::Instant::now()));
+        }
+    }
+    
+    /// Save a review from the detection panel and let the live engine learn
+    /// from it
+    fn apply_detection_review(&mut self, review: DetectionReview) {
+        let DetectionReview { detection, status, note } = review;
+        if let Some(ref db) = self.database {
+            if let Err(e) = db.annotate_detection(&detection.id, status, note.as_deref()) {
+                self.toast = Some((format!("Saving review failed: {}", e), false, std::time::Instant::now()));
+                return;
+            }
+        }
+        
+        // Only a changed verdict teaches the engine anything new
+        let previous = self.state.annotations.get(&detection.id).map(|a| a.status);
+        let reweighted = self.live.as_ref().is_some_and(|live| live.apply_annotation(&detection, previous, status));
+        let message = if reweighted {
+            format!("{:?} marked {:?}; sensor weights updated", detection.detection_type, status)
+        } else {
+            format!("{:?} marked {:?}", detection.detection_type, status)
+        };
+        self.toast = Some((message, true, std::time::Instant::now()));
+        
+        self.state.annotations.insert(detection.id.clone(), DetectionAnnotation {
+            detection_id: detection.id,
+            status,
+            note,
+            timestamp: Utc::now(),
+        });
+    }
+    
+    /// Start or stop a recording session on the live engine
+    fn toggle_recording(&mut self) {
+        let Some(ref mut live) = self.live else { return };
+        let on = !self.state.recording;
+        match live.set_recording(on) {
+            Ok(true) => {
+                self.state.recording = on;
+                // Saving reports through `collect_finished_actions`
+                if on {
+                    self.toast = Some(("Recording started".to_string(), true, std::time::Instant::now()));
+                }
+            }
+            Ok(false) => {}
+            Err(e) => {
+                self.toast = Some((format!("Recording failed: {}", e), false, std::time::Instant::now()));
+            }
+        }
+    }
+    
+    /// Pick a destination and export stored records on a background thread
+    fn export_window(&mut self, ctx: &egui::Context) {
+        // Collect a finished export
+        if let Some(rx) = &self.export.pending {
+            if let Ok(outcome) = rx.try_recv() {
+                self.export.pending = None;
+                let (message, ok) = match outcome {
+                    Ok((count, path)) => (format!("Exported {} records to {}", count, path.display()), true),
+                    Err(e) => (format!("Export failed: {}", e), false),
+                };
+                self.toast = Some((message, ok, std::time::Instant::now()));
+            }
+        }
+        
+        let mut open = self.export.open;
+        egui::Window::new("Export Data")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let export = &mut self.export;
+                
+                // GeoJSON holds located detections only
+                let geojson = export.format == ExportFormat::GeoJson;
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut export.selection, ExportSelection::Detections, "Detections");
+                    if ui.add_enabled(!geojson, egui::RadioButton::new(export.selection == ExportSelection::Readings, "Readings")).clicked() {
+                        export.selection = ExportSelection::Readings;
+                    }
+                });
+                egui::ComboBox::from_label("Format")
+                    .selected_text(format!("{:?}", export.format))
+                    .show_ui(ui, |ui| {
+                        for format in [ExportFormat::Csv, ExportFormat::Json, ExportFormat::InfluxLineProtocol, ExportFormat::GeoJson] {
+                            ui.selectable_value(&mut export.format, format, format!("{:?}", format));
+                        }
+                    });
+                if export.format == ExportFormat::GeoJson {
+                    export.selection = ExportSelection::Detections;
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Last");
+                    ui.add(egui::DragValue::new(&mut export.hours).clamp_range(1..=24 * 365));
+                    ui.label("hours");
+                });
+                
+                ui.separator();
+                let Some(db) = self.database.clone() else {
+                    ui.colored_label(GlowBarnColors::WARNING, "Database is disabled; nothing to export");
+                    return;
+                };
+                
+                if export.pending.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Exporting...");
+                    });
+                    return;
+                }
+                
+                if ui.button("Export...").clicked() {
+                    let extension = match export.format {
+                        ExportFormat::Csv => "csv",
+                        ExportFormat::InfluxLineProtocol => "lp",
+                        ExportFormat::GeoJson => "geojson",
+                        _ => "jsonl",
+                    };
+                    let default_name = format!("glowbarn-{:?}.{}", export.selection, extension).to_lowercase();
+                    
+                    if let Some(path) = rfd::FileDialog::new().set_file_name(default_name).save_file() {
+                        let (tx, rx) = std::sync::mpsc::channel();
+                        let (selection, format) = (export.selection, export.format);
+                        let end = Utc::now();
+                        let start = end - chrono::Duration::hours(export.hours as i64);
+                        
+                        std::thread::spawn(move || {
+                            let outcome = BatchExporter::new(format)
+                                .export_from_database(&db, selection, start, end, &path)
+                                .map(|count| (count, path))
+                                .map_err(|e| e.to_string());
+                            let _ = tx.send(outcome);
+                        });
+                        export.pending = Some(rx);
+                    }
+                }
+            });
+        self.export.open = open;
+    }
+    
+    fn show_toast(&mut self, ctx: &egui::Context) {
+        let Some((message, ok, shown_at)) = &self.toast else { return };
+        if shown_at.elapsed() > TOAST_DURATION {
+            self.toast = None;
+            return;
+        }
+        
+        let color = if *ok { GlowBarnColors::SUCCESS } else { GlowBarnColors::DANGER };
+        egui::Area::new("toast")
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-16.0, -40.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.colored_label(color, message);
+                });
+            });
+    }
+    
+    /// Edit the live `Config`; "Save" writes it to the active config path
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.state.show_settings;
+        
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let config = &mut self.config;
+                
+                ui.heading("General");
+                if ui.checkbox(&mut self.demo_mode, "Demo Mode").changed() {
+                    config.demo_mode = self.demo_mode;
+                }
+                ui.checkbox(&mut self.state.alerts_enabled, "Enable Alerts");
+                
+                ui.separator();
+                ui.heading("Display");
+                let theme_before = config.gui.theme;
+                egui::ComboBox::from_label("Theme")
+                    .selected_text(format!("{:?}", config.gui.theme))
+                    .show_ui(ui, |ui| {
+                        for theme in [Theme::Dark, Theme::Light, Theme::System] {
+                            ui.selectable_value(&mut config.gui.theme, theme, format!("{:?}", theme));
+                        }
+                    });
+                if config.gui.theme != theme_before {
+                    apply_theme(ctx, config.gui.theme, self.system_mode);
+                    apply_font_size(ctx, config.gui.font_size);
+                }
+                if ui.add(egui::Slider::new(&mut config.gui.font_size, 8.0..=24.0).text("Font size")).changed() {
+                    apply_font_size(ctx, config.gui.font_size);
+                }
+                ui.add(egui::Slider::new(&mut config.gui.max_fps, 1..=144).text("Max FPS"));
+                ui.add(egui::Slider::new(&mut config.gui.frame_budget_ms, 0.0..=50.0).text("Frame budget (ms, 0 = off)"));
+                ui.checkbox(&mut config.gui.show_fps, "Show FPS");
+                egui::ComboBox::from_label("Thermal colormap")
+                    .selected_text(format!("{:?}", config.gui.thermal_colormap))
+                    .show_ui(ui, |ui| {
+                        for map in [Colormap::Inferno, Colormap::Viridis, Colormap::Plasma,
+                                    Colormap::Magma, Colormap::Turbo, Colormap::Grayscale] {
+                            ui.selectable_value(&mut config.gui.thermal_colormap, map, format!("{:?}", map));
+                        }
+                    });
+                egui::ComboBox::from_label("Waterfall colormap")
+                    .selected_text(format!("{:?}", config.gui.spectrogram_colormap))
+                    .show_ui(ui, |ui| {
+                        for map in [Colormap::Inferno, Colormap::Viridis, Colormap::Plasma,
+                                    Colormap::Magma, Colormap::Turbo, Colormap::Grayscale] {
+                            ui.selectable_value(&mut config.gui.spectrogram_colormap, map, format!("{:?}", map));
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    ui.label("Waterfall range");
+                    ui.add(egui::DragValue::new(&mut config.gui.spectrogram_min_db).suffix(" dB"));
+                    ui.label("to");
+                    ui.add(egui::DragValue::new(&mut config.gui.spectrogram_max_db).suffix(" dB"));
+                });
+                
+                ui.separator();
+                ui.heading("Analysis");
+                ui.add(egui::Slider::new(&mut config.analysis.anomaly_threshold, 0.0..=1.0)
+                    .text("Anomaly confidence"));
+                ui.add(egui::Slider::new(&mut config.analysis.zscore_sigma_threshold, 1.0..=10.0)
+                    .text("Z-score threshold (σ)"));
+                
+                ui.separator();
+                ui.heading("Detection");
+                egui::ComboBox::from_label("Fusion method")
+                    .selected_text(format!("{:?}", config.detection.fusion_method))
+                    .show_ui(ui, |ui| {
+                        for method in [FusionMethod::Bayesian, FusionMethod::DempsterShafer, FusionMethod::WeightedAverage] {
+                            ui.selectable_value(&mut config.detection.fusion_method, method, format!("{:?}", method));
+                        }
+                    });
+                let window_changed = ui.add(egui::Slider::new(&mut config.detection.correlation_window_ms, 100..=10_000)
+                    .text("Correlation window (ms)")).changed();
+                let min_changed = ui.add(egui::Slider::new(&mut config.detection.min_correlated_sensors, 2..=8)
+                    .text("Min correlated sensors")).changed();
+                if window_changed || min_changed {
+                    if let Some(ref live) = self.live {
+                        live.set_correlation(
+                            config.detection.correlation_window_ms,
+                            config.detection.min_correlated_sensors,
+                        );
+                    }
+                }
+                
+                ui.separator();
+                ui.heading("Storage");
+                ui.horizontal(|ui| {
+                    ui.label("Retention (days)");
+                    ui.add(egui::DragValue::new(&mut config.database.retention_days).clamp_range(1..=3650));
+                });
+                
+                ui.separator();
+                let errors = config.validate().err().unwrap_or_default();
+                for error in &errors {
+                    ui.colored_label(GlowBarnColors::DANGER, error.to_string());
+                }
+                
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(errors.is_empty(), egui::Button::new("Save")).clicked() {
+                        let saved = config.save_edits(&self.config_base, &self.config_path);
+                        if saved.is_ok() {
+                            self.config_base = config.clone();
+                        }
+                        self.settings_status = Some(saved.map_err(|e| e.to_string()));
+                    }
+                    match &self.settings_status {
+                        Some(Ok(())) => {
+                            ui.colored_label(GlowBarnColors::SUCCESS, format!("Saved to {}", self.config_path.display()));
+                        }
+                        Some(Err(e)) => {
+                            ui.colored_label(GlowBarnColors::DANGER, format!("Save failed: {}", e));
+                        }
+                        None => {}
+                    }
+                });
+            });
+        
+        self.state.show_settings = open;
+    }
+    
+    /// Switch theme now and save the choice to the config file
+    fn set_theme(&mut self, ctx: &egui::Context, theme: Theme) {
+        if self.config.gui.theme == theme {
+            return;
+        }
+        self.config.gui.theme = theme;
+        apply_theme(ctx, theme, self.system_mode);
+        apply_font_size(ctx, self.config.gui.font_size);
+        
+        // Only the theme: other unsaved settings and overrides stay out
+        let mut edit = self.config_base.clone();
+        edit.gui.theme = theme;
+        match edit.save_edits(&self.config_base, &self.config_path) {
+            Ok(()) => self.config_base.gui.theme = theme,
+            Err(e) => self.toast = Some((format!("Theme not saved: {}", e), false, std::time::Instant::now())),
+        }
+    }
+    
+    /// Track OS dark/light changes reported by the watcher while the theme
+    /// is `System`
+    fn follow_system_theme(&mut self, ctx: &egui::Context) {
+        let Some(mode) = self.system_theme.changed() else { return };
+        self.system_mode = Some(mode);
+        if self.config.gui.theme == Theme::System && follow_system_theme(ctx, mode) {
+            apply_font_size(ctx, self.config.gui.font_size);
+        }
+    }
+    
+    /// Refresh CPU/memory/uptime from the real process (rate-limited by the monitor)
+    fn update_system_metrics(&mut self) {
+        if let Some(ref mut monitor) = self.monitor {
+            let metrics = monitor.sample();
+            self.state.stats.cpu_usage = metrics.cpu_percent;
+            self.state.stats.memory_mb = metrics.memory_mb();
+            self.state.stats.thread_count = metrics.thread_count;
+        }
+        self.state.stats.uptime_secs = self.start_time.elapsed().as_secs();
+    }
+    
     fn update_demo_data(&mut self) {
         let t = self.frame_count as f64 * 0.05;
         
+        if self.state.sensor_health.is_empty() {
+            self.state.sensor_health = demo_sensor_health();
+        }
+        
         // Generate demo waveform data
+        let history = self.config.gui.waveform_history;
         for sensor_id in ["EMF-001", "Thermal-001", "Audio-001", "Seismic-001"] {
             let waveform = self.state.waveforms
                 .entry(sensor_id.to_string())
-                .or_insert_with(Vec::new);
+                .or_insert_with(|| RingBuffer::new(history));
+            waveform.set_capacity(history);
             
             // Generate different patterns for each sensor
             let value = match sensor_id {
@@ -76,11 +745,6 @@ impl GlowBarnApp {
             };
             
             waveform.push(value);
-            
-            // Keep last 500 samples
-            if waveform.len() > 500 {
-                waveform.drain(0..waveform.len() - 500);
-            }
         }
         
         // Generate demo thermal data
@@ -104,9 +768,9 @@ impl GlowBarnApp {
             self.state.thermal_data = Some(ThermalData {
                 width: 32,
                 height: 24,
-                data: thermal.clone(),
                 min_temp: thermal.iter().fold(f32::MAX, |a, &b| a.min(b)),
                 max_temp: thermal.iter().fold(f32::MIN, |a, &b| a.max(b)),
+                data: thermal,
                 timestamp: Utc::now(),
             });
         }
@@ -136,6 +800,9 @@ impl GlowBarnApp {
                 }
             }
             
+            let column = magnitudes.iter().map(|&m| 20.0 * (m as f64 + 1e-5).log10()).collect();
+            self.state.spectrogram.push("demo", vec![column], 100.0, Utc::now(), chrono::Duration::zero());
+            
             self.state.spectrum_data = Some(SpectrumData {
                 frequencies,
                 magnitudes,
@@ -157,6 +824,7 @@ impl GlowBarnApp {
                     _ => DetectionType::EntropyAnomaly,
                 },
                 confidence: 0.5 + rand_f64() * 0.5,
+                uncertainty: rand_f64() * 0.3,
                 severity: match (rand_f64() * 4.0) as u32 {
                     0 => Severity::Low,
                     1 => Severity::Medium,
@@ -169,6 +837,7 @@ impl GlowBarnApp {
                 correlation_score: rand_f64() * 0.8,
                 classification: None,
                 location: None,
+                payload: None,
                 data_window_start: Utc::now(),
                 data_window_end: Utc::now(),
             };
@@ -182,26 +851,80 @@ impl GlowBarnApp {
         }
         
         // Update stats
-        let elapsed = self.last_update.elapsed().as_secs_f64();
-        self.state.stats = SystemStats {
-            readings_per_sec: 100.0,
-            detections_total: self.state.detections.len(),
-            cpu_usage: 15.0 + rand_f64() as f32 * 10.0,
-            memory_mb: 128.0 + rand_f64() * 50.0,
-            uptime_secs: (self.frame_count / 60) as u64,
-            active_sensors: 14,
-        };
+        self.state.stats.readings_per_sec = 100.0;
+        self.state.stats.detections_total = self.state.detections.len();
+        self.state.stats.active_sensors = 14;
     }
 }
 
+/// Sensor list shown in demo mode
+fn demo_sensor_health() -> Vec<SensorHealth> {
+    [
+        ("EMF-001", SensorType::EMFProbe, HealthStatus::Healthy),
+        ("Thermal-001", SensorType::ThermalArray, HealthStatus::Healthy),
+        ("Audio-001", SensorType::FullSpectrum, HealthStatus::Healthy),
+        ("Seismic-001", SensorType::Geophone, HealthStatus::Healthy),
+        ("Geiger-001", SensorType::GeigerCounter, HealthStatus::Healthy),
+        ("RF-001", SensorType::SDRReceiver, HealthStatus::Degraded),
+        ("QRNG-001", SensorType::QRNG, HealthStatus::Healthy),
+        ("Laser-001", SensorType::LaserGrid, HealthStatus::Healthy),
+        ("FluxGate-001", SensorType::FluxGate, HealthStatus::Healthy),
+        ("Ion-001", SensorType::IonCounter, HealthStatus::Failed),
+        ("UV-001", SensorType::UVSensor, HealthStatus::Stale),
+        ("Infra-001", SensorType::Infrasound, HealthStatus::Healthy),
+        ("Ultra-001", SensorType::Ultrasonic, HealthStatus::Healthy),
+        ("Static-001", SensorType::StaticMeter, HealthStatus::Healthy),
+    ]
+    .into_iter()
+    .map(|(id, sensor_type, status)| {
+        let mut health = SensorHealth::new(id, sensor_type);
+        health.health = status;
+        health
+    })
+    .collect()
+}
+
+/// Repaint interval when nothing is running
+const IDLE_REPAINT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// How long to wait before the next frame
+///
+/// Frames are capped at `max_fps`. While data keeps arriving the console
+/// polls at that cap; between readings it waits about one reading
+/// interval, and with no active sensors it idles at `IDLE_REPAINT`. Input
+/// events repaint immediately regardless.
+fn repaint_delay(max_fps: u32, new_data: bool, active_sensors: usize, readings_per_sec: f64) -> std::time::Duration {
+    let min_frame = std::time::Duration::from_secs_f64(1.0 / max_fps.max(1) as f64);
+    if new_data {
+        return min_frame;
+    }
+    if active_sensors == 0 || readings_per_sec <= 0.0 {
+        return IDLE_REPAINT.max(min_frame);
+    }
+    std::time::Duration::from_secs_f64(1.0 / readings_per_sec).clamp(min_frame, IDLE_REPAINT.max(min_frame))
+}
+
 impl eframe::App for GlowBarnApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.frame_count += 1;
+        let frame_start = std::time::Instant::now();
+        let frame_interval = frame_start - self.last_update;
+        let quality = self.frame_budget.quality();
         
         // Update demo data
-        if self.demo_mode {
+        let new_data = if let Some(ref mut live) = self.live {
+            live.drain_into(&mut self.state, self.config.gui.waveform_history)
+        } else if self.demo_mode {
             self.update_demo_data();
-        }
+            true
+        } else {
+            false
+        };
+        
+        self.collect_finished_actions();
+        self.update_system_metrics();
+        self.follow_system_theme(ctx);
+        self.handle_shortcuts(ctx);
         
         // Top menu bar
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
@@ -211,6 +934,7 @@ impl eframe::App for GlowBarnApp {
                         ui.close_menu();
                     }
                     if ui.button("Export Data...").clicked() {
+                        self.export.open = true;
                         ui.close_menu();
                     }
                     ui.separator();
@@ -221,9 +945,27 @@ impl eframe::App for GlowBarnApp {
                 
                 ui.menu_button("View", |ui| {
                     ui.checkbox(&mut self.state.show_settings, "Settings");
+                    ui.checkbox(&mut self.show_heatmap, "Detection Heatmap");
+                    ui.separator();
+                    ui.menu_button("Theme", |ui| {
+                        for theme in [Theme::Dark, Theme::Light, Theme::System] {
+                            if ui.radio(self.config.gui.theme == theme, format!("{:?}", theme)).clicked() {
+                                self.set_theme(ctx, theme);
+                                ui.close_menu();
+                            }
+                        }
+                    });
                 });
                 
                 ui.menu_button("Help", |ui| {
+                    if ui.button("Keyboard Shortcuts").clicked() {
+                        self.show_shortcuts = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Command Palette").clicked() {
+                        self.palette = Some(CommandPalette::default());
+                        ui.close_menu();
+                    }
                     if ui.button("About").clicked() {
                         self.state.show_about = true;
                         ui.close_menu();
@@ -231,6 +973,14 @@ impl eframe::App for GlowBarnApp {
                 });
                 
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    // Record toggle, only when a database can take the session
+                    if matches!(self.live, Some(ref live) if live.recorder().is_some()) {
+                        let label = if self.state.recording { "⏹ Stop" } else { "⏺ Record" };
+                        if ui.selectable_label(self.state.recording, label).clicked() {
+                            self.toggle_recording();
+                        }
+                    }
+                    
                     // Recording indicator
                     if self.state.recording {
                         ui.colored_label(egui::Color32::RED, "⏺ RECORDING");
@@ -243,7 +993,7 @@ impl eframe::App for GlowBarnApp {
                     
                     // FPS
                     if self.config.gui.show_fps {
-                        ui.label(format!("{:.0} FPS", 1.0 / ctx.input(|i| i.predicted_dt)));
+                        ui.label(format!("{:.0} FPS", self.state.stats.fps));
                     }
                 });
             });
@@ -264,6 +1014,12 @@ impl eframe::App for GlowBarnApp {
                 ui.separator();
                 ui.label(format!("Mem: {:.0} MB", self.state.stats.memory_mb));
                 
+                if let Some(recorder) = self.live.as_ref().and_then(|l| l.recorder()) {
+                    let (readings, detections) = recorder.counts();
+                    ui.separator();
+                    ui.label(format!("Recorded: {} readings, {} detections", readings, detections));
+                }
+                
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     ui.label(chrono::Local::now().format("%H:%M:%S").to_string());
                 });
@@ -271,20 +1027,28 @@ impl eframe::App for GlowBarnApp {
         });
         
         // Left panel - Sensor list
+        let mut sensor_action = None;
         egui::SidePanel::left("sensor_panel")
             .resizable(true)
             .default_width(250.0)
             .show(ctx, |ui| {
-                self.sensor_panel.show(ui, &mut self.state);
+                sensor_action = self.sensor_panel.show(ui, &mut self.state, self.live.is_some());
             });
+        if let Some(action) = sensor_action {
+            self.apply_sensor_list_action(action);
+        }
         
         // Right panel - Detections
+        let mut review = None;
         egui::SidePanel::right("detection_panel")
             .resizable(true)
             .default_width(300.0)
             .show(ctx, |ui| {
-                self.detection_panel.show(ui, &mut self.state);
+                review = self.detection_panel.show(ui, &mut self.state, &self.config.gui);
             });
+        if let Some(review) = review {
+            self.apply_detection_review(review);
+        }
         
         // Central panel with visualizations
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -300,12 +1064,12 @@ impl eframe::App for GlowBarnApp {
                         // Waveforms
                         ui.group(|ui| {
                             ui.set_min_width(ui.available_width() * 0.6);
-                            self.waveform_panel.show(ui, &self.state);
+                            self.waveform_panel.show(ui, &self.state, quality);
                         });
                         
                         // Thermal
                         ui.group(|ui| {
-                            self.thermal_panel.show(ui, &self.state);
+                            self.thermal_panel.show(ui, &self.state, &self.config.gui, quality);
                         });
                     });
                 });
@@ -315,7 +1079,18 @@ impl eframe::App for GlowBarnApp {
                 // Spectrum
                 ui.group(|ui| {
                     ui.set_min_width(ui.available_width() * 0.7);
-                    self.spectrum_panel.show(ui, &self.state);
+                    ui.vertical(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.selectable_value(&mut self.show_waterfall, false, "Spectrum");
+                            ui.selectable_value(&mut self.show_waterfall, true, "Waterfall");
+                        });
+                        if self.show_waterfall {
+                            let redraw = self.frame_count % quality.spectrogram_every() == 0;
+                            self.spectrogram_panel.show(ui, &self.state, &self.config.gui, redraw);
+                        } else {
+                            self.spectrum_panel.show(ui, &self.state, quality);
+                        }
+                    });
                 });
                 
                 // Stats
@@ -327,17 +1102,34 @@ impl eframe::App for GlowBarnApp {
         
         // Settings window
         if self.state.show_settings {
-            egui::Window::new("Settings")
-                .open(&mut self.state.show_settings)
+            self.settings_window(ctx);
+        }
+        
+        if self.export.open || self.export.pending.is_some() {
+            self.export_window(ctx);
+        }
+        self.show_toast(ctx);
+        
+        self.sensor_detail_window(ctx);
+        
+        if self.show_heatmap {
+            let mut heatmap_action = None;
+            egui::Window::new("📅 Detection Activity")
+                .open(&mut self.show_heatmap)
+                .default_width(640.0)
                 .show(ctx, |ui| {
-                    ui.heading("General");
-                    ui.checkbox(&mut self.demo_mode, "Demo Mode");
-                    ui.checkbox(&mut self.state.alerts_enabled, "Enable Alerts");
-                    
-                    ui.separator();
-                    ui.heading("Display");
-                    // Theme selection, etc.
+                    heatmap_action = self.heatmap_panel.show(ui, self.database.as_ref(), &self.config.gui);
                 });
+            if let Some(action) = heatmap_action {
+                self.detection_panel.apply_heatmap(action);
+            }
+        }
+        
+        if self.palette.is_some() {
+            self.command_palette(ctx);
+        }
+        if self.show_shortcuts {
+            self.shortcuts_window(ctx);
         }
         
         // About window
@@ -358,13 +1150,55 @@ impl eframe::App for GlowBarnApp {
                 });
         }
         
-        // Request continuous repainting for real-time updates
-        ctx.request_repaint();
+        let repaint_wait = repaint_delay(
+            self.config.gui.max_fps,
+            new_data,
+            self.state.stats.active_sensors,
+            self.state.stats.readings_per_sec,
+        );
+        ctx.request_repaint_after(repaint_wait);
         
-        self.last_update = std::time::Instant::now();
+        // The interval since the last frame started covers all of its work,
+        // tessellation and painting included, and the wait it asked for
+        self.frame_budget.set_budget(frame_budget(&self.config.gui));
+        self.frame_budget.record(frame_interval, self.repaint_wait, self.build_time);
+        self.state.stats.fps = self.frame_budget.fps();
+        self.state.stats.frame_time_ms = self.frame_budget.frame_time().as_secs_f64() * 1000.0;
+        self.state.stats.frame_budget_ms = self.config.gui.frame_budget_ms.max(0.0);
+        self.state.stats.quality = self.frame_budget.quality();
+        
+        self.last_update = frame_start;
+        self.repaint_wait = repaint_wait;
+        self.build_time = frame_start.elapsed();
     }
 }
 
+/// Work allowed per frame before the console sheds detail
+fn frame_budget(config: &GuiConfig) -> std::time::Duration {
+    std::time::Duration::from_secs_f64(config.frame_budget_ms.max(0.0) / 1000.0)
+}
+
+/// Resolve configured shortcut strings, skipping any that don't parse
+fn bind_shortcuts(config: &Shortcuts) -> Vec<(Command, egui::KeyboardShortcut)> {
+    [
+        (Command::ToggleRecording, &config.record),
+        (Command::Settings, &config.settings),
+        (Command::Export, &config.export),
+        (Command::CloseDialogs, &config.close),
+        (Command::CommandPalette, &config.command_palette),
+    ]
+    .into_iter()
+    .filter(|(_, spec)| !spec.is_empty())
+    .filter_map(|(command, spec)| match parse_shortcut(spec) {
+        Some(shortcut) => Some((command, shortcut)),
+        None => {
+            tracing::warn!("Ignoring invalid shortcut '{}' for {}", spec, command.label());
+            None
+        }
+    })
+    .collect()
+}
+
 // Simple random number generator for demo
 fn rand_f64() -> f64 {
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -374,3 +1208,25 @@ fn rand_f64() -> f64 {
         .subsec_nanos();
     (nanos as f64 / u32::MAX as f64)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    #[test]
+    fn test_repaint_idles_without_data() {
+        let frame = Duration::from_secs_f64(1.0 / 60.0);
+        
+        // Streaming data: poll at the cap
+        assert_eq!(repaint_delay(60, true, 3, 100.0), frame);
+        // Between readings at 10 Hz: wait for the next one
+        assert_eq!(repaint_delay(60, false, 3, 10.0), Duration::from_millis(100));
+        // Faster than the cap still waits a whole frame
+        assert_eq!(repaint_delay(60, false, 3, 1000.0), frame);
+        // Nothing running: slow heartbeat, far below 60 FPS
+        assert_eq!(repaint_delay(60, false, 0, 0.0), IDLE_REPAINT);
+        
+        assert_eq!(repaint_delay(10, true, 1, 50.0), Duration::from_millis(100));
+    }
+}
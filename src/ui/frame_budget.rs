@@ -0,0 +1,264 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Frame-time measurement and adaptive quality
+//!
+//! `FrameBudget` keeps the last `FRAME_WINDOW` frame times and intervals for
+//! a rolling FPS and frame-time readout, and steps the console's `Quality`
+//! down while the average frame takes longer than the budget, and back up
+//! once it has stayed well under it. Both steps wait for a run of frames so
+//! a single slow one (a resize, a texture upload) doesn't make the display
+//! flip between levels.
+//!
+//! A frame's cost is measured from frame start to frame start, less the
+//! wait it asked for before repainting, so tessellation and painting after
+//! `update` count as well as building the UI.
+
+use std::time::Duration;
+
+use super::RingBuffer;
+
+/// Frames averaged for the FPS and frame-time readout
+pub const FRAME_WINDOW: usize = 30;
+
+/// Consecutive over-budget frames before quality drops a level
+const DEGRADE_AFTER: usize = 10;
+
+/// Consecutive frames under `RECOVER_FRACTION` of the budget before quality
+/// rises a level
+const RECOVER_AFTER: usize = 120;
+
+/// Share of the budget the average frame must stay under to recover; well
+/// below 1 so the cheaper level's own savings don't trigger a recovery
+const RECOVER_FRACTION: f64 = 0.5;
+
+/// Level of detail the console draws at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Quality {
+    #[default]
+    Full,
+    Reduced,
+    Minimal,
+}
+
+impl Quality {
+    fn lower(self) -> Self {
+        match self {
+            Quality::Full => Quality::Reduced,
+            Quality::Reduced | Quality::Minimal => Quality::Minimal,
+        }
+    }
+    
+    fn higher(self) -> Self {
+        match self {
+            Quality::Minimal => Quality::Reduced,
+            Quality::Reduced | Quality::Full => Quality::Full,
+        }
+    }
+    
+    /// Most points a waveform or spectrum line is drawn with
+    pub fn max_plot_points(self) -> usize {
+        match self {
+            Quality::Full => usize::MAX,
+            Quality::Reduced => 256,
+            Quality::Minimal => 64,
+        }
+    }
+    
+    /// Frames between waterfall redraws
+    pub fn spectrogram_every(self) -> u64 {
+        match self {
+            Quality::Full => 1,
+            Quality::Reduced => 4,
+            Quality::Minimal => 16,
+        }
+    }
+    
+    /// Thermal cells averaged into one texel along each side
+    pub fn thermal_block(self) -> usize {
+        match self {
+            Quality::Full => 1,
+            Quality::Reduced => 2,
+            Quality::Minimal => 4,
+        }
+    }
+}
+
+/// Rolling frame timing and the quality it allows
+#[derive(Debug, Clone)]
+pub struct FrameBudget {
+    /// Work per frame above which quality drops; zero keeps full quality
+    budget: Duration,
+    /// Seconds each frame cost, idle wait excluded
+    frame_times: RingBuffer<f64>,
+    /// Seconds between the starts of consecutive frames
+    intervals: RingBuffer<f64>,
+    quality: Quality,
+    over_budget: usize,
+    under_budget: usize,
+}
+
+impl FrameBudget {
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            frame_times: RingBuffer::new(FRAME_WINDOW),
+            intervals: RingBuffer::new(FRAME_WINDOW),
+            quality: Quality::Full,
+            over_budget: 0,
+            under_budget: 0,
+        }
+    }
+    
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+    
+    /// Change the budget; zero disables adaptive quality
+    pub fn set_budget(&mut self, budget: Duration) {
+        self.budget = budget;
+    }
+    
+    pub fn quality(&self) -> Quality {
+        self.quality
+    }
+    
+    /// Average frame time over the window
+    pub fn frame_time(&self) -> Duration {
+        Duration::from_secs_f64(mean(self.frame_times.as_slice()))
+    }
+    
+    /// Frames per second over the window
+    pub fn fps(&self) -> f64 {
+        let interval = mean(self.intervals.as_slice());
+        if interval > 0.0 {
+            1.0 / interval
+        } else {
+            0.0
+        }
+    }
+    
+    /// Record a frame that started `interval` after the previous one, and
+    /// return the quality to draw the next at.
+    ///
+    /// The previous frame cost the interval less `idle`, the wait it asked
+    /// for before repainting. Input can cut that wait short, so the cost is
+    /// never taken as less than `build_time`, what the previous frame spent
+    /// in `update`.
+    pub fn record(&mut self, interval: Duration, idle: Duration, build_time: Duration) -> Quality {
+        let frame_time = interval.saturating_sub(idle).max(build_time);
+        self.frame_times.push(frame_time.as_secs_f64());
+        self.intervals.push(interval.as_secs_f64());
+        
+        if self.budget.is_zero() {
+            self.set_quality(Quality::Full);
+            return self.quality;
+        }
+        
+        let average = mean(self.frame_times.as_slice());
+        let budget = self.budget.as_secs_f64();
+        if average > budget {
+            self.over_budget += 1;
+            self.under_budget = 0;
+        } else if average < budget * RECOVER_FRACTION {
+            self.under_budget += 1;
+            self.over_budget = 0;
+        } else {
+            self.over_budget = 0;
+            self.under_budget = 0;
+        }
+        
+        if self.over_budget >= DEGRADE_AFTER {
+            self.set_quality(self.quality.lower());
+        } else if self.under_budget >= RECOVER_AFTER {
+            self.set_quality(self.quality.higher());
+        }
+        self.quality
+    }
+    
+    /// Switch level and judge the new one on its own frames only
+    fn set_quality(&mut self, quality: Quality) {
+        self.over_budget = 0;
+        self.under_budget = 0;
+        if quality != self.quality {
+            self.quality = quality;
+            self.frame_times.clear();
+        }
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Wait each frame asks for before the next, as at 60 FPS
+    const IDLE: Duration = Duration::from_millis(16);
+    
+    /// Frames that cost `frame_time_ms`, half of it spent in `update`
+    fn run(budget: &mut FrameBudget, frames: usize, frame_time_ms: u64) -> Quality {
+        let frame_time = Duration::from_millis(frame_time_ms);
+        for _ in 0..frames {
+            budget.record(IDLE + frame_time, IDLE, frame_time / 2);
+        }
+        budget.quality()
+    }
+    
+    #[test]
+    fn test_budget_degrades_and_recovers_with_hysteresis() {
+        let mut budget = FrameBudget::new(Duration::from_millis(12));
+        
+        // A single slow frame is absorbed by the average
+        assert_eq!(run(&mut budget, FRAME_WINDOW, 5), Quality::Full);
+        assert_eq!(run(&mut budget, 1, 100), Quality::Full);
+        assert_eq!(run(&mut budget, FRAME_WINDOW, 5), Quality::Full);
+        
+        // Sustained overruns drop one level at a time, each judged afresh
+        assert_eq!(run(&mut budget, FRAME_WINDOW, 20), Quality::Reduced);
+        assert_eq!(run(&mut budget, FRAME_WINDOW, 20), Quality::Minimal);
+        assert!((budget.frame_time().as_secs_f64() - 0.020).abs() < 1e-9);
+        assert!((budget.fps() - 1000.0 / 36.0).abs() < 1e-6);
+        
+        // Just under budget holds the level instead of bouncing back
+        assert_eq!(run(&mut budget, 2 * RECOVER_AFTER, 10), Quality::Minimal);
+        
+        // Well under budget for long enough climbs back
+        assert_eq!(run(&mut budget, RECOVER_AFTER + FRAME_WINDOW, 4), Quality::Reduced);
+        assert_eq!(run(&mut budget, RECOVER_AFTER, 4), Quality::Full);
+    }
+    
+    #[test]
+    fn test_zero_budget_keeps_full_quality() {
+        let mut budget = FrameBudget::new(Duration::from_millis(12));
+        assert_eq!(run(&mut budget, DEGRADE_AFTER, 50), Quality::Reduced);
+        
+        budget.set_budget(Duration::ZERO);
+        assert_eq!(run(&mut budget, 1, 50), Quality::Full);
+        assert_eq!(run(&mut budget, 10 * DEGRADE_AFTER, 50), Quality::Full);
+    }
+    
+    #[test]
+    fn test_frame_cost_counts_painting_but_not_the_wait() {
+        let mut budget = FrameBudget::new(Duration::from_millis(12));
+        
+        // 4 ms in `update` and 10 ms more painting is over budget
+        let (interval, build_time) = (Duration::from_millis(30), Duration::from_millis(4));
+        for _ in 1..DEGRADE_AFTER {
+            budget.record(interval, IDLE, build_time);
+        }
+        assert!((budget.frame_time().as_secs_f64() - 0.014).abs() < 1e-9);
+        assert_eq!(budget.record(interval, IDLE, build_time), Quality::Reduced);
+        
+        // Woken early by input, a frame costs at least its `update`
+        budget.record(Duration::from_millis(5), IDLE, Duration::from_millis(3));
+        assert!((budget.frame_time().as_secs_f64() - 0.003).abs() < 1e-9);
+    }
+}
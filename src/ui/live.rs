@@ -0,0 +1,372 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Live engine data feed for the GUI
+//!
+//! The engine runs on a tokio runtime in the background; each frame the
+//! app drains whatever the event bus published since the last frame into
+//! its `GuiState`.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tokio::sync::oneshot;
+use rustfft::num_complex::Complex;
+use chrono::Utc;
+
+use crate::analysis::{AnalysisConfig, SignalProcessor};
+use crate::core::{sorted_publish_stats, EventBus, PublishStats, Recorder};
+use crate::detection::{AnnotationStatus, Detection, DetectionEngine};
+use crate::sensors::{CalibrationData, NoiseProfile, SensorHealth, SensorManager, SensorReading, SensorType};
+use super::{GuiState, RingBuffer, SensorAction, SpectrumData, ThermalData};
+
+/// How often sensor health and counts are refreshed
+const HEALTH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Detections kept for the detection panel
+const MAX_DETECTIONS: usize = 100;
+
+/// Fewest samples worth computing a spectrum for
+const MIN_SPECTRUM_LEN: usize = 64;
+
+/// How long a sensor is sampled when characterized from the console
+pub const CHARACTERIZE_DURATION: Duration = Duration::from_secs(10);
+
+/// Largest STFT window for the waterfall
+const SPECTROGRAM_WINDOW: usize = 128;
+
+/// Receivers for the data a running engine publishes
+pub struct LiveFeed {
+    readings: broadcast::Receiver<SensorReading>,
+    detections: broadcast::Receiver<Detection>,
+    sensors: Arc<SensorManager>,
+    publish_stats: Arc<parking_lot::Mutex<HashMap<String, PublishStats>>>,
+    recorder: Option<Arc<Recorder>>,
+    detection: Option<Arc<DetectionEngine>>,
+    runtime: tokio::runtime::Handle,
+    /// Keeps FFT plans across frames
+    signal: SignalProcessor,
+    last_refresh: Option<Instant>,
+    // Readings since `last_refresh`, for the readings/s stat
+    readings_since_refresh: usize,
+    // Health query running on the engine's runtime, collected by a later frame
+    pending_health: Option<oneshot::Receiver<HealthSnapshot>>,
+    // Control actions running on the engine's runtime, see `finished_actions`
+    pending_actions: Vec<PendingAction>,
+}
+
+/// A control action running on the engine's runtime, and what it's called
+struct PendingAction {
+    label: String,
+    result: oneshot::Receiver<anyhow::Result<()>>,
+}
+
+/// Sensor state queried from the sensor manager off the GUI thread
+struct HealthSnapshot {
+    health: Vec<SensorHealth>,
+    active: usize,
+    // Sensor the calibration and noise profile belong to
+    selected: Option<String>,
+    calibration: Option<CalibrationData>,
+    noise_profile: Option<NoiseProfile>,
+}
+
+impl LiveFeed {
+    /// `runtime` is the runtime the engine runs on; async queries are
+    /// spawned on it and their results collected by later frames, so the
+    /// GUI thread never waits on the engine
+    pub fn new(event_bus: &EventBus, sensors: Arc<SensorManager>, runtime: tokio::runtime::Handle) -> Self {
+        Self {
+            readings: event_bus.subscribe_readings(),
+            detections: event_bus.subscribe_detections(),
+            sensors,
+            publish_stats: event_bus.publish_stats_handle(),
+            recorder: None,
+            detection: None,
+            runtime,
+            signal: SignalProcessor::new(AnalysisConfig::default()),
+            last_refresh: None,
+            readings_since_refresh: 0,
+            pending_health: None,
+            pending_actions: Vec::new(),
+        }
+    }
+
+    /// Let the console start and stop recording sessions
+    pub fn with_recorder(mut self, recorder: Arc<Recorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    pub fn recorder(&self) -> Option<&Arc<Recorder>> {
+        self.recorder.as_ref()
+    }
+
+    /// Let detection reviews feed back into the engine's fusion weights
+    pub fn with_detection_engine(mut self, detection: Arc<DetectionEngine>) -> Self {
+        self.detection = Some(detection);
+        self
+    }
+
+    /// Pass a review, replacing the detection's `previous` one, to the
+    /// detection engine, returning whether it changed any fusion weight
+    pub fn apply_annotation(&self, detection: &Detection, previous: Option<AnnotationStatus>, status: AnnotationStatus) -> bool {
+        self.detection.as_ref().is_some_and(|engine| engine.apply_annotation(detection, previous, status))
+    }
+
+    /// Retune the live correlator from the settings window
+    pub fn set_correlation(&self, correlation_window_ms: u64, min_correlated_sensors: usize) {
+        if let Some(ref engine) = self.detection {
+            engine.set_correlation(correlation_window_ms, min_correlated_sensors);
+        }
+    }
+
+    /// Open or close a recording session; `Ok(false)` if recording isn't
+    /// available. Closing flushes the session in the background and reports
+    /// through `finished_actions`.
+    pub fn set_recording(&mut self, on: bool) -> anyhow::Result<bool> {
+        let Some(recorder) = self.recorder.clone() else {
+            return Ok(false);
+        };
+        if on {
+            recorder.start()?;
+        } else {
+            self.spawn_action("Saving recording".to_string(), async move { recorder.stop().await });
+        }
+        Ok(true)
+    }
+
+    /// Start a sensor control action against the engine's sensor manager;
+    /// its outcome comes from `finished_actions`
+    pub fn control_sensor(&mut self, id: &str, action: SensorAction) {
+        let (sensors, id) = (self.sensors.clone(), id.to_string());
+        self.spawn_action(format!("{}: {:?}", id, action), async move {
+            match action {
+                SensorAction::Start => sensors.start_sensor(&id).await,
+                SensorAction::Stop => sensors.stop_sensor(&id).await,
+                SensorAction::Calibrate => sensors.calibrate_sensor(&id).await.map(|_| ()),
+                SensorAction::SetSampleRate(rate) => sensors.set_sample_rate(&id, rate).await,
+                SensorAction::Enable => sensors.enable(&id).await,
+                SensorAction::Disable => sensors.disable(&id).await,
+                // Takes seconds; the profile shows up with the next health refresh
+                SensorAction::Characterize => sensors.characterize(&id, CHARACTERIZE_DURATION).await.map(|_| ()),
+            }
+        });
+    }
+
+    /// Start every enabled sensor, or stop them all, in the background
+    pub fn set_all_running(&mut self, running: bool) {
+        let sensors = self.sensors.clone();
+        let label = if running { "Start all" } else { "Stop all" };
+        self.spawn_action(label.to_string(), async move {
+            if running {
+                sensors.start_all().await
+            } else {
+                sensors.stop_all().await
+            }
+        });
+    }
+
+    /// Labels and outcomes of the control actions that finished since the
+    /// last call
+    pub fn finished_actions(&mut self) -> Vec<(String, anyhow::Result<()>)> {
+        let mut finished = Vec::new();
+        self.pending_actions.retain_mut(|pending| {
+            let result = match pending.result.try_recv() {
+                Ok(result) => result,
+                Err(oneshot::error::TryRecvError::Empty) => return true,
+                Err(oneshot::error::TryRecvError::Closed) => Err(anyhow::anyhow!("stopped before finishing")),
+            };
+            finished.push((pending.label.clone(), result));
+            false
+        });
+        finished
+    }
+
+    fn spawn_action(&mut self, label: String, action: impl Future<Output = anyhow::Result<()>> + Send + 'static) {
+        let (tx, rx) = oneshot::channel();
+        self.runtime.spawn(async move {
+            let _ = tx.send(action.await);
+        });
+        self.pending_actions.push(PendingAction { label, result: rx });
+    }
+
+    /// Move everything published since the last frame into `state`,
+    /// keeping `history` samples per waveform.
+    ///
+    /// Returns whether any reading, detection or sensor health arrived.
+    pub fn drain_into(&mut self, state: &mut GuiState, history: usize) -> bool {
+        let mut received = false;
+        loop {
+            match self.readings.try_recv() {
+                Ok(reading) => {
+                    received = true;
+                    self.readings_since_refresh += 1;
+                    apply_reading(&self.signal, state, reading, history);
+                }
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+
+        loop {
+            match self.detections.try_recv() {
+                Ok(detection) => {
+                    received = true;
+                    state.stats.detections_total += 1;
+                    state.detections.push(detection);
+                }
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        if state.detections.len() > MAX_DETECTIONS {
+            state.detections.drain(0..state.detections.len() - MAX_DETECTIONS);
+        }
+
+        if let Some(rx) = self.pending_health.as_mut() {
+            match rx.try_recv() {
+                Ok(snapshot) => {
+                    self.pending_health = None;
+                    received = true;
+                    state.sensor_health = snapshot.health;
+                    state.stats.active_sensors = snapshot.active;
+                    // Selection may have moved on while the query ran
+                    if snapshot.selected == state.selected_sensor {
+                        state.selected_calibration = snapshot.calibration;
+                        state.selected_noise_profile = snapshot.noise_profile;
+                    }
+                }
+                Err(oneshot::error::TryRecvError::Empty) => {}
+                Err(oneshot::error::TryRecvError::Closed) => self.pending_health = None,
+            }
+        }
+
+        if self.pending_health.is_none() && !matches!(self.last_refresh, Some(t) if t.elapsed() < HEALTH_INTERVAL) {
+            if let Some(last) = self.last_refresh {
+                state.stats.readings_per_sec = self.readings_since_refresh as f64 / last.elapsed().as_secs_f64();
+            }
+            self.readings_since_refresh = 0;
+            self.last_refresh = Some(Instant::now());
+            state.stats.publish_stats = sorted_publish_stats(&self.publish_stats.lock());
+
+            let sensors = self.sensors.clone();
+            let selected = state.selected_sensor.clone();
+            let (tx, rx) = oneshot::channel();
+            self.runtime.spawn(async move {
+                let (calibration, noise_profile) = match selected {
+                    Some(ref id) => (sensors.calibration(id).await, sensors.noise_profile(id).await),
+                    None => (None, None),
+                };
+                let _ = tx.send(HealthSnapshot {
+                    health: sensors.get_all_health().await,
+                    active: sensors.active_count().await,
+                    selected,
+                    calibration,
+                    noise_profile,
+                });
+            });
+            self.pending_health = Some(rx);
+        }
+        
+        received
+    }
+}
+
+fn apply_reading(signal: &SignalProcessor, state: &mut GuiState, reading: SensorReading, history: usize) {
+    let waveform = state.waveforms.entry(reading.sensor_id.clone())
+        .or_insert_with(|| RingBuffer::new(history));
+    waveform.set_capacity(history);
+    waveform.extend_from_slice(&reading.data);
+
+    match reading.sensor_type {
+        SensorType::ThermalArray | SensorType::ThermalImager => {
+            if let Some(thermal) = thermal_frame(&reading) {
+                state.thermal_data = Some(thermal);
+            }
+        }
+        _ => {
+            let wanted = match state.selected_sensor.as_deref() {
+                Some(id) => id == reading.sensor_id,
+                None => true,
+            };
+            if wanted && reading.data.len() >= MIN_SPECTRUM_LEN {
+                state.spectrum_data = Some(spectrum(signal, &reading.data, reading.sample_rate));
+                push_spectrogram(signal, state, &reading);
+            }
+        }
+    }
+
+    match state.readings.iter_mut().find(|r| r.sensor_id == reading.sensor_id) {
+        Some(latest) => *latest = reading,
+        None => state.readings.push(reading),
+    }
+}
+
+/// Interpret a thermal reading as a row-major grid
+fn thermal_frame(reading: &SensorReading) -> Option<ThermalData> {
+    let len = reading.data.len();
+    let (width, height) = [(8, 8), (32, 24), (80, 60), (160, 120)]
+        .into_iter()
+        .find(|(w, h)| w * h == len)
+        .or_else(|| {
+            let side = (len as f64).sqrt() as usize;
+            (side > 0 && side * side == len).then_some((side, side))
+        })?;
+
+    let data: Vec<f32> = reading.data.iter().map(|&t| t as f32).collect();
+    Some(ThermalData {
+        width,
+        height,
+        min_temp: data.iter().copied().fold(f32::MAX, f32::min),
+        max_temp: data.iter().copied().fold(f32::MIN, f32::max),
+        data,
+        timestamp: reading.timestamp,
+    })
+}
+
+/// Append the reading's short-time spectra to the waterfall
+fn push_spectrogram(signal: &SignalProcessor, state: &mut GuiState, reading: &SensorReading) {
+    let window = SPECTROGRAM_WINDOW.min(prev_power_of_two(reading.data.len()));
+    let hop = window / 2;
+    let frames = signal.spectrogram(&reading.data, reading.sample_rate, window, hop);
+    if frames.is_empty() {
+        return;
+    }
+
+    let bin_hz = (reading.sample_rate / window as f64) as f32;
+    let hop_time = chrono::Duration::microseconds((hop as f64 / reading.sample_rate * 1e6) as i64);
+    state.spectrogram.push(&reading.sensor_id, frames, bin_hz, reading.timestamp, hop_time);
+}
+
+fn prev_power_of_two(n: usize) -> usize {
+    if n == 0 { 0 } else { 1 << (usize::BITS - 1 - n.leading_zeros()) }
+}
+
+/// Single-sided magnitude spectrum of `data`
+fn spectrum(signal: &SignalProcessor, data: &[f64], sample_rate: f64) -> SpectrumData {
+    let n = data.len();
+    let mean = data.iter().sum::<f64>() / n as f64;
+    let mut buffer: Vec<Complex<f64>> = data.iter().map(|&x| Complex::new(x - mean, 0.0)).collect();
+    signal.fft_plans().forward(n).process(&mut buffer);
+
+    let bin_hz = sample_rate / n as f64;
+    let frequencies: Vec<f32> = (0..n / 2).map(|i| (i as f64 * bin_hz) as f32).collect();
+    let magnitudes: Vec<f32> = buffer[..n / 2].iter().map(|c| (c.norm() / n as f64) as f32).collect();
+
+    let peak_freq = magnitudes.iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(i, _)| frequencies[i])
+        .unwrap_or(0.0);
+
+    SpectrumData {
+        frequencies,
+        magnitudes,
+        peak_freq,
+        timestamp: Utc::now(),
+    }
+}
@@ -9,12 +9,18 @@ mod panels;
 mod widgets;
 mod plots;
 mod theme;
+mod live;
+mod ring_buffer;
+mod frame_budget;
 
 pub use app::*;
 pub use panels::*;
 pub use widgets::*;
 pub use plots::*;
 pub use theme::*;
+pub use live::LiveFeed;
+pub use ring_buffer::RingBuffer;
+pub use frame_budget::*;
 
 use anyhow::Result;
 use eframe::egui;
@@ -22,9 +28,11 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::config::Config;
-use crate::sensors::SensorReading;
-use crate::detection::Detection;
+use crate::sensors::{CalibrationData, NoiseProfile, SensorHealth, SensorReading};
+use crate::detection::{Detection, DetectionAnnotation};
+use crate::core::PublishStats;
 use crate::core::EventBus;
+use crate::db::Database;
 
 /// GUI state
 pub struct GuiState {
@@ -34,8 +42,14 @@ pub struct GuiState {
     /// Recent detections
     pub detections: Vec<Detection>,
     
-    /// Waveform history
-    pub waveforms: std::collections::HashMap<String, Vec<f64>>,
+    /// Investigator reviews, by detection id
+    pub annotations: std::collections::HashMap<String, DetectionAnnotation>,
+    
+    /// Per-sensor health for the sensor list
+    pub sensor_health: Vec<SensorHealth>,
+    
+    /// Waveform history, `GuiConfig::waveform_history` samples per sensor
+    pub waveforms: std::collections::HashMap<String, RingBuffer<f64>>,
     
     /// Thermal grid data
     pub thermal_data: Option<ThermalData>,
@@ -43,12 +57,21 @@ pub struct GuiState {
     /// Spectrum data
     pub spectrum_data: Option<SpectrumData>,
     
+    /// Rolling spectrogram for the waterfall
+    pub spectrogram: SpectrogramData,
+    
     /// System stats
     pub stats: SystemStats,
     
     /// Selected sensor
     pub selected_sensor: Option<String>,
     
+    /// Calibration applied to the selected sensor
+    pub selected_calibration: Option<CalibrationData>,
+    
+    /// Noise profile from the last characterization of the selected sensor
+    pub selected_noise_profile: Option<NoiseProfile>,
+    
     /// Show settings
     pub show_settings: bool,
     
@@ -67,11 +90,16 @@ impl Default for GuiState {
         Self {
             readings: Vec::new(),
             detections: Vec::new(),
+            annotations: std::collections::HashMap::new(),
+            sensor_health: Vec::new(),
             waveforms: std::collections::HashMap::new(),
             thermal_data: None,
             spectrum_data: None,
+            spectrogram: SpectrogramData::default(),
             stats: SystemStats::default(),
             selected_sensor: None,
+            selected_calibration: None,
+            selected_noise_profile: None,
             show_settings: false,
             show_about: false,
             recording: false,
@@ -100,6 +128,57 @@ pub struct SpectrumData {
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+/// Columns of a scrolling spectrogram, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct SpectrogramData {
+    /// Power per frequency bin in dB
+    pub columns: std::collections::VecDeque<Vec<f32>>,
+    /// Time each column ends at
+    pub times: std::collections::VecDeque<chrono::DateTime<chrono::Utc>>,
+    /// Width of one frequency bin
+    pub bin_hz: f32,
+    /// Sensor the columns were computed from
+    pub sensor_id: Option<String>,
+}
+
+impl SpectrogramData {
+    /// Columns kept before the oldest scroll off
+    pub const MAX_COLUMNS: usize = 300;
+    
+    /// Append `frames` (oldest first, as from `SignalProcessor::spectrogram`)
+    /// that end at `end` and are `hop` apart. A different sensor or bin
+    /// layout starts a fresh history.
+    pub fn push(
+        &mut self,
+        sensor_id: &str,
+        frames: Vec<Vec<f64>>,
+        bin_hz: f32,
+        end: chrono::DateTime<chrono::Utc>,
+        hop: chrono::Duration,
+    ) {
+        let bins = frames.first().map(Vec::len).unwrap_or(0);
+        let same_layout = self.sensor_id.as_deref() == Some(sensor_id)
+            && self.bin_hz == bin_hz
+            && matches!(self.columns.back(), Some(c) if c.len() == bins);
+        if !same_layout {
+            self.columns.clear();
+            self.times.clear();
+            self.sensor_id = Some(sensor_id.to_string());
+            self.bin_hz = bin_hz;
+        }
+        
+        let count = frames.len() as i32;
+        for (i, frame) in frames.into_iter().enumerate() {
+            self.columns.push_back(frame.into_iter().map(|p| p as f32).collect());
+            self.times.push_back(end - hop * (count - 1 - i as i32));
+        }
+        while self.columns.len() > Self::MAX_COLUMNS {
+            self.columns.pop_front();
+            self.times.pop_front();
+        }
+    }
+}
+
 /// System statistics
 #[derive(Debug, Clone, Default)]
 pub struct SystemStats {
@@ -107,12 +186,31 @@ pub struct SystemStats {
     pub detections_total: usize,
     pub cpu_usage: f32,
     pub memory_mb: f64,
+    pub thread_count: usize,
     pub uptime_secs: u64,
     pub active_sensors: usize,
+    /// Per-sensor readings published vs dropped on a backed-up channel
+    pub publish_stats: Vec<PublishStats>,
+    /// Rolling frames per second
+    pub fps: f64,
+    /// Rolling time spent building a frame, in milliseconds
+    pub frame_time_ms: f64,
+    /// Frame budget in milliseconds, 0 when adaptive quality is off
+    pub frame_budget_ms: f64,
+    /// Level of detail the console currently draws at
+    pub quality: Quality,
 }
 
-/// Launch GUI application
-pub fn run_gui(config: Config) -> Result<()> {
+/// Launch GUI application; settings are saved back to `config_path`
+///
+/// With `live` set the console shows data from a running engine instead of
+/// synthesized demo data.
+pub fn run_gui(
+    config: Config,
+    config_path: std::path::PathBuf,
+    database: Option<Arc<Database>>,
+    live: Option<LiveFeed>,
+) -> Result<()> {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([config.gui.width as f32, config.gui.height as f32])
@@ -130,9 +228,11 @@ pub fn run_gui(config: Config) -> Result<()> {
             setup_fonts(&cc.egui_ctx);
             
             // Apply theme
-            apply_theme(&cc.egui_ctx, config.gui.theme);
+            // `System` starts from egui's defaults until the OS answers
+            apply_theme(&cc.egui_ctx, config.gui.theme, None);
+            apply_font_size(&cc.egui_ctx, config.gui.font_size);
             
-            Box::new(GlowBarnApp::new(cc, config))
+            Box::new(GlowBarnApp::new(cc, config, config_path, database, live))
         }),
     ).map_err(|e| anyhow::anyhow!("Failed to run GUI: {}", e))
 }
@@ -4,12 +4,16 @@
 
 //! UI panels
 
+use std::sync::Arc;
 use eframe::egui;
-use crate::sensors::SensorType;
-use crate::detection::{DetectionType, Severity};
-use super::{GuiState, ThermalData, SpectrumData};
+use crate::config::{Colormap, GuiConfig};
+use crate::db::{Database, DetectionHourCount};
+use crate::sensors::{downsample, DownsampleMethod, HealthStatus};
+use crate::detection::{AnnotationStatus, Detection, DetectionType, Severity};
+use super::{GuiState, Quality, RingBuffer, ThermalData, SpectrumData};
 use super::plots::*;
 use super::widgets::*;
+use super::theme::GlowBarnColors;
 
 /// Sensor list panel
 pub struct SensorPanel {
@@ -23,7 +27,10 @@ impl SensorPanel {
         }
     }
     
-    pub fn show(&mut self, ui: &mut egui::Ui, state: &mut GuiState) {
+    /// Show the sensor list; `controls` enables the per-sensor switches and
+    /// Start/Stop All, which need a live engine. Returns the action chosen.
+    pub fn show(&mut self, ui: &mut egui::Ui, state: &mut GuiState, controls: bool) -> Option<SensorListAction> {
+        let mut action = None;
         ui.heading("🔌 Sensors");
         ui.separator();
         
@@ -36,45 +43,41 @@ impl SensorPanel {
         ui.separator();
         
         egui::ScrollArea::vertical().show(ui, |ui| {
-            // Demo sensors
-            let sensors = [
-                ("EMF-001", SensorType::EMFProbe, true),
-                ("Thermal-001", SensorType::ThermalArray, true),
-                ("Audio-001", SensorType::FullSpectrum, true),
-                ("Seismic-001", SensorType::Geophone, true),
-                ("Geiger-001", SensorType::GeigerCounter, true),
-                ("RF-001", SensorType::SDRReceiver, true),
-                ("QRNG-001", SensorType::QRNG, true),
-                ("Laser-001", SensorType::LaserGrid, true),
-                ("FluxGate-001", SensorType::FluxGate, true),
-                ("Ion-001", SensorType::IonCounter, false),
-                ("UV-001", SensorType::UVSensor, true),
-                ("Infra-001", SensorType::Infrasound, true),
-                ("Ultra-001", SensorType::Ultrasonic, true),
-                ("Static-001", SensorType::StaticMeter, true),
-            ];
+            let filter = self.search_filter.to_lowercase();
+            let mut clicked = None;
             
-            for (id, sensor_type, online) in sensors {
-                if !self.search_filter.is_empty() 
-                    && !id.to_lowercase().contains(&self.search_filter.to_lowercase()) {
+            for health in &state.sensor_health {
+                let id = health.sensor_id.as_str();
+                if !filter.is_empty() && !id.to_lowercase().contains(&filter) {
                     continue;
                 }
                 
                 let selected = state.selected_sensor.as_deref() == Some(id);
                 
                 ui.horizontal(|ui| {
+                    // Enable switch
+                    let mut enabled = health.health != HealthStatus::Offline;
+                    let toggle = ui.add_enabled(controls, egui::Checkbox::without_text(&mut enabled))
+                        .on_hover_text(if enabled { "Disable sensor" } else { "Enable sensor" });
+                    if toggle.changed() {
+                        let change = if enabled { SensorAction::Enable } else { SensorAction::Disable };
+                        action = Some(SensorListAction::Sensor(id.to_string(), change));
+                    }
+                    
                     // Status dot
-                    let color = if online {
-                        egui::Color32::GREEN
-                    } else {
-                        egui::Color32::RED
+                    let color = match health.health {
+                        HealthStatus::Healthy => egui::Color32::GREEN,
+                        HealthStatus::Degraded => egui::Color32::YELLOW,
+                        HealthStatus::Stale => egui::Color32::from_rgb(255, 140, 0),
+                        HealthStatus::Failed => egui::Color32::RED,
+                        HealthStatus::Offline => egui::Color32::GRAY,
                     };
-                    ui.colored_label(color, "●");
+                    ui.colored_label(color, "●").on_hover_text(format!("{:?}", health.health));
                     
                     // Sensor button
-                    let response = ui.selectable_label(selected, format!("{:?}", sensor_type));
+                    let response = ui.selectable_label(selected, format!("{:?}", health.sensor_type));
                     if response.clicked() {
-                        state.selected_sensor = Some(id.to_string());
+                        clicked = Some(id.to_string());
                     }
                     
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -82,19 +85,216 @@ impl SensorPanel {
                     });
                 });
             }
+            
+            if clicked.is_some() {
+                state.selected_sensor = clicked;
+            }
         });
         
         ui.separator();
         
         // Control buttons
         ui.horizontal(|ui| {
-            if ui.button("▶ Start All").clicked() {
-                // Start all sensors
+            if ui.add_enabled(controls, egui::Button::new("▶ Start All")).clicked() {
+                action = Some(SensorListAction::StartAll);
             }
-            if ui.button("⏹ Stop All").clicked() {
-                // Stop all sensors
+            if ui.add_enabled(controls, egui::Button::new("⏹ Stop All")).clicked() {
+                action = Some(SensorListAction::StopAll);
             }
         });
+        
+        action
+    }
+}
+
+/// Action requested from the sensor list
+#[derive(Debug, Clone, PartialEq)]
+pub enum SensorListAction {
+    Sensor(String, SensorAction),
+    StartAll,
+    StopAll,
+}
+
+/// Control action requested from the sensor inspector
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SensorAction {
+    Start,
+    Stop,
+    Calibrate,
+    SetSampleRate(f64),
+    /// Put a disabled sensor back in the read schedule and start it
+    Enable,
+    /// Stop a sensor and keep it out of the read schedule
+    Disable,
+    /// Sample the sensor's quiet output and keep it as its noise baseline
+    Characterize,
+}
+
+/// Details and controls for the selected sensor
+pub struct SensorDetailPanel {
+    // Sample rate being edited, and the sensor it belongs to
+    rate_input: f64,
+    rate_sensor: Option<String>,
+}
+
+impl SensorDetailPanel {
+    pub fn new() -> Self {
+        Self {
+            rate_input: 0.0,
+            rate_sensor: None,
+        }
+    }
+    
+    /// Show the selected sensor; returns the control action the user chose
+    pub fn show(&mut self, ui: &mut egui::Ui, state: &GuiState, controls: bool) -> Option<SensorAction> {
+        let id = state.selected_sensor.as_deref()?;
+        let health = state.sensor_health.iter().find(|h| h.sensor_id == id);
+        let latest = state.readings.iter().find(|r| r.sensor_id == id);
+        let mut action = None;
+        
+        let sample_rate = latest.map(|r| r.sample_rate).unwrap_or(0.0);
+        if self.rate_sensor.as_deref() != Some(id) {
+            self.rate_sensor = Some(id.to_string());
+            self.rate_input = sample_rate;
+        }
+        
+        egui::Grid::new("sensor_detail_grid").num_columns(2).show(ui, |ui| {
+            if let Some(h) = health {
+                ui.label("Type");
+                ui.label(format!("{:?}", h.sensor_type));
+                ui.end_row();
+                
+                ui.label("Status");
+                ui.label(format!("{:?} ({:?})", h.status, h.health));
+                ui.end_row();
+                
+                ui.label("Readings");
+                ui.label(format!("{} ({} errors, {:.0}% recent)", h.readings_count, h.error_count, h.error_rate * 100.0));
+                ui.end_row();
+                
+                if let Some(ref e) = h.last_error {
+                    ui.label("Last error");
+                    ui.colored_label(egui::Color32::LIGHT_RED, e);
+                    ui.end_row();
+                }
+            }
+            
+            ui.label("Sample rate");
+            ui.horizontal(|ui| {
+                ui.add_enabled(controls, egui::DragValue::new(&mut self.rate_input)
+                    .clamp_range(0.01..=1_000_000.0)
+                    .speed(0.1)
+                    .suffix(" Hz"));
+                let changed = (self.rate_input - sample_rate).abs() > f64::EPSILON;
+                if ui.add_enabled(controls && changed, egui::Button::new("Apply")).clicked() {
+                    action = Some(SensorAction::SetSampleRate(self.rate_input));
+                }
+            });
+            ui.end_row();
+            
+            ui.label("Calibration");
+            match state.selected_calibration {
+                Some(ref c) => ui.label(format!(
+                    "{} • noise floor {:.3} • {}",
+                    c.timestamp.format("%Y-%m-%d %H:%M"),
+                    c.noise_floor,
+                    if c.notes.is_empty() { "no notes" } else { c.notes.as_str() },
+                )),
+                None => ui.weak("none"),
+            };
+            ui.end_row();
+            
+            ui.label("Noise profile");
+            match state.selected_noise_profile {
+                Some(ref p) => ui.label(format!(
+                    "σ {:.4} • mean {:.4} • {} samples • {}",
+                    p.noise_floor,
+                    p.summary.mean,
+                    p.summary.count,
+                    p.timestamp.format("%Y-%m-%d %H:%M"),
+                )),
+                None => ui.weak("not characterized"),
+            };
+            ui.end_row();
+        });
+        
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(controls, |ui| {
+                if ui.button("▶ Start").clicked() {
+                    action = Some(SensorAction::Start);
+                }
+                if ui.button("⏹ Stop").clicked() {
+                    action = Some(SensorAction::Stop);
+                }
+                if ui.button("🎯 Calibrate").clicked() {
+                    action = Some(SensorAction::Calibrate);
+                }
+                if ui.button("📈 Characterize").on_hover_text("Sample the sensor while nothing is happening").clicked() {
+                    action = Some(SensorAction::Characterize);
+                }
+            });
+        });
+        
+        if let Some(ref profile) = state.selected_noise_profile {
+            ui.collapsing("Noise distribution", |ui| {
+                let quantiles: Vec<String> = profile.quantiles.iter()
+                    .map(|(p, v)| format!("p{}: {:.4}", p, v))
+                    .collect();
+                ui.small(quantiles.join("  "));
+                
+                let bins = profile.histogram.len().max(1);
+                let width = profile.summary.range / bins as f64;
+                let bars: Vec<egui_plot::Bar> = profile.histogram.iter()
+                    .enumerate()
+                    .map(|(i, &count)| {
+                        egui_plot::Bar::new(profile.summary.min + (i as f64 + 0.5) * width, count as f64).width(width)
+                    })
+                    .collect();
+                egui_plot::Plot::new(format!("noise_histogram_{}", id))
+                    .height(120.0)
+                    .show_grid(false)
+                    .show(ui, |plot_ui| {
+                        plot_ui.bar_chart(egui_plot::BarChart::new(bars).color(get_sensor_color(id)));
+                    });
+            });
+        }
+        
+        ui.separator();
+        
+        let history = state.waveforms.get(id).map(RingBuffer::as_slice).unwrap_or(&[]);
+        let summary = crate::analysis::StatisticalAnalyzer::new().summarize(history);
+        ui.small(format!("Last {} samples", summary.count));
+        let stats = [
+            ("Mean", summary.mean), ("Std dev", summary.std_dev),
+            ("Min", summary.min), ("Max", summary.max),
+            ("Median", summary.median), ("IQR", summary.iqr),
+            ("Skewness", summary.skewness), ("Kurtosis", summary.kurtosis),
+        ];
+        egui::Grid::new("sensor_stats_grid").num_columns(4).show(ui, |ui| {
+            for row in stats.chunks(2) {
+                for (name, value) in row {
+                    ui.label(*name);
+                    ui.monospace(format!("{:.3}", value));
+                }
+                ui.end_row();
+            }
+        });
+        
+        let width = ui.available_width();
+        let decimated = downsample(history, (width as usize).max(2), DownsampleMethod::MinMax);
+        let step = history.len() as f64 / decimated.len().max(1) as f64;
+        egui_plot::Plot::new(format!("sensor_detail_{}", id))
+            .height(200.0)
+            .show_grid(true)
+            .show(ui, |plot_ui| {
+                let points: egui_plot::PlotPoints = decimated.iter()
+                    .enumerate()
+                    .map(|(i, &v)| [i as f64 * step, v])
+                    .collect();
+                plot_ui.line(egui_plot::Line::new(points).color(get_sensor_color(id)).width(1.5));
+            });
+        
+        action
     }
 }
 
@@ -112,7 +312,7 @@ impl WaveformPanel {
         }
     }
     
-    pub fn show(&self, ui: &mut egui::Ui, state: &GuiState) {
+    pub fn show(&self, ui: &mut egui::Ui, state: &GuiState, quality: Quality) {
         ui.heading("📈 Real-time Waveforms");
         
         egui::ScrollArea::vertical().show(ui, |ui| {
@@ -141,10 +341,15 @@ impl WaveformPanel {
                         .allow_drag(false)
                         .include_y(0.0);
                     
+                    // One min/max pair per pixel column is all the plot can show
+                    let points = (width as usize).min(quality.max_plot_points()).max(2);
+                    let decimated = downsample(data.as_slice(), points, DownsampleMethod::MinMax);
+                    let step = data.len() as f64 / decimated.len().max(1) as f64;
+                    
                     plot.show(ui, |plot_ui| {
-                        let points: egui_plot::PlotPoints = data.iter()
+                        let points: egui_plot::PlotPoints = decimated.iter()
                             .enumerate()
-                            .map(|(i, &v)| [i as f64, v])
+                            .map(|(i, &v)| [i as f64 * step, v])
                             .collect();
                         
                         let line = egui_plot::Line::new(points)
@@ -161,19 +366,44 @@ impl WaveformPanel {
 
 /// Thermal imaging panel
 pub struct ThermalPanel {
-    colormap: Colormap,
     show_temps: bool,
+    /// The current frame, one texel per block of grid cells
+    texture: Option<egui::TextureHandle>,
+    /// Frame timestamp, colormap and block size the texture was drawn with
+    texture_key: Option<(chrono::DateTime<chrono::Utc>, Colormap, usize)>,
 }
 
 impl ThermalPanel {
     pub fn new() -> Self {
         Self {
-            colormap: Colormap::Inferno,
             show_temps: true,
+            texture: None,
+            texture_key: None,
+        }
+    }
+    
+    /// Redraw the texture only for a new frame, colormap or block size
+    fn update_texture(&mut self, ctx: &egui::Context, thermal: &ThermalData, colormap: Colormap, block: usize) {
+        let key = (thermal.timestamp, colormap, block);
+        if self.texture.is_some() && self.texture_key == Some(key) {
+            return;
+        }
+        
+        let range = (thermal.max_temp - thermal.min_temp).max(f32::EPSILON);
+        let (temps, size) = coarsen_grid(&thermal.data, thermal.width, thermal.height, block);
+        let pixels = temps.iter()
+            .map(|&temp| colormap.to_color((temp - thermal.min_temp) / range))
+            .collect();
+        let image = egui::ColorImage { size, pixels };
+        
+        match self.texture {
+            Some(ref mut texture) => texture.set(image, egui::TextureOptions::NEAREST),
+            None => self.texture = Some(ctx.load_texture("thermal", image, egui::TextureOptions::NEAREST)),
         }
+        self.texture_key = Some(key);
     }
     
-    pub fn show(&mut self, ui: &mut egui::Ui, state: &GuiState) {
+    pub fn show(&mut self, ui: &mut egui::Ui, state: &GuiState, config: &GuiConfig, quality: Quality) {
         ui.heading("🌡️ Thermal");
         
         if let Some(ref thermal) = state.thermal_data {
@@ -183,7 +413,10 @@ impl ThermalPanel {
                 ui.small(format!("Max: {:.1}°C", thermal.max_temp));
             });
             
-            // Draw thermal grid
+            self.update_texture(ui.ctx(), thermal, config.thermal_colormap, quality.thermal_block());
+            let texture = self.texture.as_ref().expect("set above");
+            
+            // Draw thermal grid as one textured rect
             let available = ui.available_size();
             let cell_w = (available.x / thermal.width as f32).min(12.0);
             let cell_h = (available.y / thermal.height as f32).min(12.0);
@@ -194,21 +427,12 @@ impl ThermalPanel {
             );
             
             let rect = response.rect;
-            
-            for y in 0..thermal.height {
-                for x in 0..thermal.width {
-                    let temp = thermal.data[y * thermal.width + x];
-                    let normalized = (temp - thermal.min_temp) / (thermal.max_temp - thermal.min_temp);
-                    let color = self.colormap.to_color(normalized);
-                    
-                    let cell_rect = egui::Rect::from_min_size(
-                        rect.min + egui::vec2(x as f32 * cell_w, y as f32 * cell_h),
-                        egui::vec2(cell_w, cell_h),
-                    );
-                    
-                    painter.rect_filled(cell_rect, 0.0, color);
-                }
-            }
+            painter.image(
+                texture.id(),
+                rect,
+                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
             
             // Show temperature on hover
             if let Some(pos) = response.hover_pos() {
@@ -232,6 +456,22 @@ impl ThermalPanel {
     }
 }
 
+/// Average `block`×`block` cells of a row-major grid into one, edge blocks
+/// over the cells they cover; returns the cells and the new [width, height]
+fn coarsen_grid(data: &[f32], width: usize, height: usize, block: usize) -> (Vec<f32>, [usize; 2]) {
+    let block = block.max(1);
+    let (w, h) = (width.div_ceil(block), height.div_ceil(block));
+    let mut sums = vec![0.0f32; w * h];
+    let mut counts = vec![0u32; w * h];
+    for (i, &temp) in data.iter().enumerate().take(width * height) {
+        let cell = (i / width / block) * w + (i % width) / block;
+        sums[cell] += temp;
+        counts[cell] += 1;
+    }
+    let cells = sums.iter().zip(&counts).map(|(&sum, &n)| sum / n.max(1) as f32).collect();
+    (cells, [w, h])
+}
+
 /// Spectrum analyzer panel
 pub struct SpectrumPanel {
     log_scale: bool,
@@ -244,7 +484,7 @@ impl SpectrumPanel {
         }
     }
     
-    pub fn show(&self, ui: &mut egui::Ui, state: &GuiState) {
+    pub fn show(&self, ui: &mut egui::Ui, state: &GuiState, quality: Quality) {
         ui.heading("📊 Spectrum Analyzer");
         
         if let Some(ref spectrum) = state.spectrum_data {
@@ -259,10 +499,12 @@ impl SpectrumPanel {
                 .allow_zoom(true)
                 .allow_drag(true);
             
+            // Keep each run of bins' peak when there are more than the quality allows
+            let chunk = spectrum.frequencies.len().div_ceil(quality.max_plot_points()).max(1);
             plot.show(ui, |plot_ui| {
-                let points: egui_plot::PlotPoints = spectrum.frequencies.iter()
-                    .zip(spectrum.magnitudes.iter())
-                    .map(|(&f, &m)| [f as f64, m as f64])
+                let points: egui_plot::PlotPoints = spectrum.frequencies.chunks(chunk)
+                    .zip(spectrum.magnitudes.chunks(chunk))
+                    .map(|(f, m)| [f[0] as f64, m.iter().copied().fold(f32::MIN, f32::max) as f64])
                     .collect();
                 
                 let line = egui_plot::Line::new(points)
@@ -279,21 +521,262 @@ impl SpectrumPanel {
     }
 }
 
+/// Scrolling spectrogram (waterfall), newest column on the right
+pub struct SpectrogramPanel {
+    texture: Option<egui::TextureHandle>,
+}
+
+impl SpectrogramPanel {
+    pub fn new() -> Self {
+        Self {
+            texture: None,
+        }
+    }
+    
+    /// Draw the waterfall; without `redraw` the last texture is reused
+    pub fn show(&mut self, ui: &mut egui::Ui, state: &GuiState, config: &GuiConfig, redraw: bool) {
+        ui.heading("🌊 Waterfall");
+        
+        let spectrogram = &state.spectrogram;
+        let bins = spectrogram.columns.back().map(Vec::len).unwrap_or(0);
+        if bins == 0 {
+            ui.centered_and_justified(|ui| {
+                ui.label("No spectrogram data");
+            });
+            return;
+        }
+        
+        let nyquist = spectrogram.bin_hz * bins as f32;
+        if let Some(ref id) = spectrogram.sensor_id {
+            ui.small(format!("{} • 0-{:.0} Hz • {:.0} to {:.0} dB",
+                id, nyquist, config.spectrogram_min_db, config.spectrogram_max_db));
+        }
+        
+        // One pixel per column and bin, low frequencies at the bottom
+        let width = spectrogram.columns.len();
+        if redraw || self.texture.is_none() {
+            let (min_db, max_db) = (config.spectrogram_min_db, config.spectrogram_max_db);
+            let mut image = egui::ColorImage::new([width, bins], egui::Color32::BLACK);
+            for (x, column) in spectrogram.columns.iter().enumerate() {
+                for (bin, &db) in column.iter().enumerate().take(bins) {
+                    let t = (db - min_db) / (max_db - min_db);
+                    image[(x, bins - 1 - bin)] = config.spectrogram_colormap.to_color(t);
+                }
+            }
+            
+            match self.texture {
+                Some(ref mut texture) => texture.set(image, egui::TextureOptions::NEAREST),
+                None => self.texture = Some(ui.ctx().load_texture("spectrogram", image, egui::TextureOptions::NEAREST)),
+            }
+        }
+        let texture = self.texture.as_ref().expect("set above");
+        
+        const AXIS_WIDTH: f32 = 48.0;
+        let size = egui::vec2(ui.available_width(), ui.available_height().clamp(80.0, 200.0));
+        let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+        let plot = egui::Rect::from_min_max(response.rect.min + egui::vec2(AXIS_WIDTH, 0.0), response.rect.max);
+        painter.image(
+            texture.id(),
+            plot,
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+        
+        // Frequency axis
+        let text_color = ui.visuals().text_color();
+        for i in 0..=4 {
+            let frac = i as f32 / 4.0;
+            let y = plot.bottom() - frac * plot.height();
+            painter.line_segment([egui::pos2(plot.left() - 4.0, y), egui::pos2(plot.left(), y)], (1.0, text_color));
+            painter.text(
+                egui::pos2(plot.left() - 6.0, y),
+                egui::Align2::RIGHT_CENTER,
+                format_hz(frac * nyquist),
+                egui::FontId::proportional(10.0),
+                text_color,
+            );
+        }
+        
+        if let Some(pos) = response.hover_pos().filter(|p| plot.contains(*p)) {
+            let x = (((pos.x - plot.left()) / plot.width()) * width as f32) as usize;
+            let bin = (((plot.bottom() - pos.y) / plot.height()) * bins as f32) as usize;
+            let x = x.min(width - 1);
+            let bin = bin.min(bins - 1);
+            
+            let db = spectrogram.columns[x].get(bin).copied().unwrap_or(f32::NAN);
+            let age = spectrogram.times.back()
+                .zip(spectrogram.times.get(x))
+                .map(|(newest, t)| (*newest - *t).num_milliseconds() as f64 / 1000.0)
+                .unwrap_or(0.0);
+            egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("spectrogram_tooltip"), |ui| {
+                ui.label(format!("t: -{:.2} s", age));
+                ui.label(format!("f: {}", format_hz((bin as f32 + 0.5) * spectrogram.bin_hz)));
+                ui.label(format!("{:.1} dB", db));
+            });
+        }
+    }
+}
+
+fn format_hz(hz: f32) -> String {
+    if hz >= 1000.0 {
+        format!("{:.1} kHz", hz / 1000.0)
+    } else {
+        format!("{:.0} Hz", hz)
+    }
+}
+
+/// Verdict an investigator gave a detection in the detection panel
+#[derive(Debug, Clone)]
+pub struct DetectionReview {
+    pub detection: Detection,
+    pub status: AnnotationStatus,
+    pub note: Option<String>,
+}
+
 /// Detection events panel
-pub struct DetectionPanel;
+pub struct DetectionPanel {
+    // Live alerts hide detections that have faded out; the log shows all
+    live_alerts: bool,
+    min_severity: Severity,
+    // Types to show; empty shows all
+    types: std::collections::HashSet<DetectionType>,
+    min_confidence: f64,
+    max_age: Option<chrono::Duration>,
+    sort_by_confidence: bool,
+    // Review notes being typed, by detection id
+    notes: std::collections::HashMap<String, String>,
+    // Stored hour picked on the heatmap, listed instead of the live detections
+    hour: Option<DetectionHour>,
+}
 
 impl DetectionPanel {
     pub fn new() -> Self {
-        Self
+        Self {
+            live_alerts: false,
+            min_severity: Severity::Low,
+            types: std::collections::HashSet::new(),
+            min_confidence: 0.0,
+            max_age: None,
+            sort_by_confidence: false,
+            notes: std::collections::HashMap::new(),
+            hour: None,
+        }
+    }
+    
+    /// Follow a click on the detection heatmap
+    pub fn apply_heatmap(&mut self, action: HeatmapAction) {
+        self.hour = match action {
+            HeatmapAction::Focus(hour) => Some(hour),
+            HeatmapAction::Clear => None,
+        };
+    }
+    
+    /// Detections passing the current filters, in display order. Without
+    /// a `half_life` nothing fades, so live alerts hide nothing.
+    fn visible<'a>(&self, detections: &'a [Detection], half_life: Option<chrono::Duration>) -> Vec<&'a Detection> {
+        let now = chrono::Utc::now();
+        let cutoff = self.max_age.map(|age| now - age);
+        
+        let mut visible: Vec<&Detection> = detections.iter()
+            .rev()
+            .filter(|d| !self.live_alerts || half_life.map_or(true, |h| decay_alpha(now - d.timestamp, h) >= LIVE_ALERT_MIN_ALPHA))
+            .filter(|d| d.severity >= self.min_severity)
+            .filter(|d| d.confidence >= self.min_confidence)
+            .filter(|d| self.types.is_empty() || self.types.contains(&d.detection_type))
+            .filter(|d| !matches!(cutoff, Some(c) if d.timestamp < c))
+            .collect();
+        
+        if self.sort_by_confidence {
+            visible.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        }
+        visible
+    }
+    
+    fn filter_controls(&mut self, ui: &mut egui::Ui, detections: &[Detection]) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.live_alerts, true, "Live alerts");
+            ui.selectable_value(&mut self.live_alerts, false, "Full log");
+        });
+        
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("detection_severity")
+                .selected_text(format!("≥ {:?}", self.min_severity))
+                .show_ui(ui, |ui| {
+                    for severity in [Severity::Low, Severity::Medium, Severity::High, Severity::Critical] {
+                        ui.selectable_value(&mut self.min_severity, severity, format!("{:?}", severity));
+                    }
+                });
+            
+            let mut present: Vec<DetectionType> = detections.iter().map(|d| d.detection_type).collect();
+            present.sort_by_key(|t| format!("{:?}", t));
+            present.dedup();
+            
+            let label = if self.types.is_empty() {
+                "All types".to_string()
+            } else {
+                format!("{} types", self.types.len())
+            };
+            ui.menu_button(label, |ui| {
+                if ui.button("All").clicked() {
+                    self.types.clear();
+                }
+                for detection_type in present {
+                    let mut selected = self.types.contains(&detection_type);
+                    if ui.checkbox(&mut selected, format!("{:?}", detection_type)).changed() {
+                        if selected {
+                            self.types.insert(detection_type);
+                        } else {
+                            self.types.remove(&detection_type);
+                        }
+                    }
+                }
+            });
+        });
+        
+        ui.horizontal(|ui| {
+            let ages = [
+                ("All time", None),
+                ("Last 5 min", Some(chrono::Duration::minutes(5))),
+                ("Last hour", Some(chrono::Duration::hours(1))),
+                ("Last 24 h", Some(chrono::Duration::hours(24))),
+            ];
+            let current = ages.iter().find(|(_, age)| *age == self.max_age).map_or("All time", |(name, _)| name);
+            egui::ComboBox::from_id_source("detection_age")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for (name, age) in ages {
+                        ui.selectable_value(&mut self.max_age, age, name);
+                    }
+                });
+            
+            ui.toggle_value(&mut self.sort_by_confidence, "Sort by confidence");
+        });
+        
+        ui.add(egui::Slider::new(&mut self.min_confidence, 0.0..=1.0).text("Min confidence"));
     }
     
-    pub fn show(&self, ui: &mut egui::Ui, state: &mut GuiState) {
+    /// Show the detection list, returning a review if one was given
+    pub fn show(&mut self, ui: &mut egui::Ui, state: &mut GuiState, config: &GuiConfig) -> Option<DetectionReview> {
         ui.heading("⚠️ Detections");
         ui.separator();
         
+        // A heatmap hour replaces the live list until it's dismissed
+        let hour = self.hour.take();
+        let mut keep_hour = true;
+        if let Some(ref hour) = hour {
+            ui.horizontal(|ui| {
+                let (start, end) = (hour.start.with_timezone(&chrono::Local), hour.end.with_timezone(&chrono::Local));
+                ui.label(format!("Stored {} {}-{}", start.format("%m-%d"), start.format("%H:%M"), end.format("%H:%M")));
+                if ui.small_button("✖ Live").clicked() {
+                    keep_hour = false;
+                }
+            });
+        }
+        let detections = hour.as_ref().map_or(&state.detections[..], |h| &h.detections[..]);
+        
         // Summary
-        let critical = state.detections.iter().filter(|d| d.severity == Severity::Critical).count();
-        let high = state.detections.iter().filter(|d| d.severity == Severity::High).count();
+        let critical = detections.iter().filter(|d| d.severity == Severity::Critical).count();
+        let high = detections.iter().filter(|d| d.severity == Severity::High).count();
         
         ui.horizontal(|ui| {
             if critical > 0 {
@@ -306,8 +789,22 @@ impl DetectionPanel {
         
         ui.separator();
         
+        self.filter_controls(ui, detections);
+        
+        // Stored detections are history; only the live list fades
+        let half_life = hour.is_none()
+            .then(|| chrono::Duration::milliseconds((config.detection_half_life_secs * 1000.0) as i64));
+        let visible = self.visible(detections, half_life);
+        ui.small(format!("Showing {} of {}", visible.len(), detections.len()));
+        
+        ui.separator();
+        
+        let now = chrono::Utc::now();
+        let text_color = ui.visuals().text_color();
+        let mut review = None;
         egui::ScrollArea::vertical().show(ui, |ui| {
-            for detection in state.detections.iter().rev().take(50) {
+            for detection in visible {
+                let alpha = half_life.map_or(1.0, |h| decay_alpha(now - detection.timestamp, h));
                 ui.group(|ui| {
                     let (icon, color) = match detection.severity {
                         Severity::Critical => ("🔴", egui::Color32::RED),
@@ -315,32 +812,446 @@ impl DetectionPanel {
                         Severity::Medium => ("🟡", egui::Color32::YELLOW),
                         Severity::Low => ("🟢", egui::Color32::GREEN),
                     };
+                    let small = |text: String| egui::RichText::new(text).small().color(text_color.gamma_multiply(alpha));
                     
                     ui.horizontal(|ui| {
-                        ui.label(icon);
-                        ui.colored_label(color, format!("{:?}", detection.detection_type));
+                        ui.label(egui::RichText::new(icon).color(egui::Color32::WHITE.gamma_multiply(alpha)));
+                        ui.colored_label(color.gamma_multiply(alpha), format!("{:?}", detection.detection_type));
                     });
                     
                     ui.horizontal(|ui| {
-                        ui.small(format!("Confidence: {:.0}%", detection.confidence * 100.0));
-                        ui.small(format!("| {}", detection.timestamp.format("%H:%M:%S")));
+                        if detection.uncertainty >= 0.005 {
+                            ui.label(small(format!("Confidence: {:.0}% ±{:.0}% uncertain",
+                                detection.confidence * 100.0, detection.uncertainty * 100.0)));
+                        } else {
+                            ui.label(small(format!("Confidence: {:.0}%", detection.confidence * 100.0)));
+                        }
+                        ui.label(small(format!("| {}", detection.timestamp.format("%H:%M:%S"))));
                     });
                     
                     if detection.entropy_deviation > 0.1 {
-                        ui.small(format!("Entropy dev: {:.2}", detection.entropy_deviation));
+                        ui.label(small(format!("Entropy dev: {:.2}", detection.entropy_deviation)));
+                    }
+                    
+                    if let Some(annotation) = state.annotations.get(&detection.id) {
+                        let verdict = match annotation.status {
+                            AnnotationStatus::Acknowledged => "Acknowledged",
+                            AnnotationStatus::FalsePositive => "False positive",
+                            AnnotationStatus::Confirmed => "Confirmed",
+                        };
+                        match annotation.note {
+                            Some(ref note) => ui.label(small(format!("✎ {}: {}", verdict, note))),
+                            None => ui.label(small(format!("✎ {}", verdict))),
+                        };
                     }
+                    
+                    egui::CollapsingHeader::new("Review")
+                        .id_source(("detection_review", &detection.id))
+                        .show(ui, |ui| {
+                            let note = self.notes.entry(detection.id.clone()).or_default();
+                            ui.add(egui::TextEdit::singleline(note).hint_text("Note"));
+                            ui.horizontal(|ui| {
+                                let verdicts = [
+                                    (AnnotationStatus::Acknowledged, "✔ Ack"),
+                                    (AnnotationStatus::FalsePositive, "✖ False positive"),
+                                    (AnnotationStatus::Confirmed, "★ Confirm"),
+                                ];
+                                for (status, label) in verdicts {
+                                    if ui.button(label).clicked() {
+                                        let note = note.trim();
+                                        review = Some(DetectionReview {
+                                            detection: detection.clone(),
+                                            status,
+                                            note: (!note.is_empty()).then(|| note.to_string()),
+                                        });
+                                    }
+                                }
+                            });
+                        });
                 });
             }
         });
         
+        if let Some(DetectionReview { ref detection, .. }) = review {
+            self.notes.remove(&detection.id);
+        }
+        
         ui.separator();
         
         if ui.button("Clear All").clicked() {
             state.detections.clear();
+            self.notes.clear();
         }
+        if keep_hour {
+            self.hour = hour;
+        }
+        
+        review
     }
 }
 
+/// Detections binned by calendar day and hour of day
+#[derive(Debug, Clone, PartialEq)]
+pub struct HourlyActivity {
+    /// Day of the first row
+    pub start: chrono::NaiveDate,
+    /// Detections in each hour, one row per day
+    pub counts: Vec<[usize; 24]>,
+    /// Highest severity in each hour, one row per day
+    pub peak_severity: Vec<[Option<Severity>; 24]>,
+    /// Detections of each type in the range, most frequent first
+    pub by_type: Vec<(DetectionType, usize)>,
+}
+
+impl HourlyActivity {
+    /// Bin `detections` into `days` days from `start`, in the days and hours
+    /// of `tz`. Detections outside the range are left out.
+    pub fn bin<Tz: chrono::TimeZone>(detections: &[Detection], start: chrono::NaiveDate, days: usize, tz: &Tz) -> Self {
+        let entries = detections.iter().map(|d| (d.timestamp, d.detection_type, d.severity, 1));
+        Self::tally(entries, start, days, tz)
+    }
+    
+    /// Bin hourly counts from `Database::count_detections_by_hour` like
+    /// `bin`. Each count lands in the local hour its UTC hour starts in.
+    pub fn from_counts<Tz: chrono::TimeZone>(counts: &[DetectionHourCount], start: chrono::NaiveDate, days: usize, tz: &Tz) -> Self {
+        let entries = counts.iter().map(|c| (c.hour, c.detection_type, c.severity, c.count));
+        Self::tally(entries, start, days, tz)
+    }
+    
+    fn tally<Tz: chrono::TimeZone>(
+        entries: impl Iterator<Item = (chrono::DateTime<chrono::Utc>, DetectionType, Severity, usize)>,
+        start: chrono::NaiveDate,
+        days: usize,
+        tz: &Tz,
+    ) -> Self {
+        let mut activity = Self {
+            start,
+            counts: vec![[0; 24]; days],
+            peak_severity: vec![[None; 24]; days],
+            by_type: Vec::new(),
+        };
+        
+        let mut by_type = std::collections::HashMap::new();
+        for (timestamp, detection_type, severity, count) in entries {
+            let Some((day, hour)) = activity.cell_of(timestamp, tz) else { continue };
+            activity.counts[day][hour] += count;
+            let peak = &mut activity.peak_severity[day][hour];
+            *peak = (*peak).max(Some(severity));
+            *by_type.entry(detection_type).or_insert(0) += count;
+        }
+        
+        activity.by_type = by_type.into_iter().collect();
+        activity.by_type.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| format!("{:?}", a.0).cmp(&format!("{:?}", b.0))));
+        activity
+    }
+    
+    /// Row and column of the cell `timestamp` falls in
+    pub fn cell_of<Tz: chrono::TimeZone>(&self, timestamp: chrono::DateTime<chrono::Utc>, tz: &Tz) -> Option<(usize, usize)> {
+        use chrono::Timelike;
+        
+        let local = timestamp.with_timezone(tz);
+        let day = (local.date_naive() - self.start).num_days();
+        (0..self.counts.len() as i64).contains(&day).then(|| (day as usize, local.hour() as usize))
+    }
+    
+    pub fn days(&self) -> usize {
+        self.counts.len()
+    }
+    
+    pub fn total(&self) -> usize {
+        self.counts.iter().flatten().sum()
+    }
+    
+    pub fn max_count(&self) -> usize {
+        self.counts.iter().flatten().copied().max().unwrap_or(0)
+    }
+    
+    /// UTC start and end of the local hour at `cell`
+    pub fn hour_range<Tz: chrono::TimeZone>(&self, (day, hour): (usize, usize), tz: &Tz) -> Option<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> {
+        let date = self.start + chrono::Duration::days(day as i64);
+        let start = date.and_hms_opt(hour as u32, 0, 0)?.and_local_timezone(tz.clone()).earliest()?;
+        let start = start.with_timezone(&chrono::Utc);
+        Some((start, start + chrono::Duration::hours(1)))
+    }
+}
+
+/// Most stored detections of one hour handed to the detection panel
+const HOUR_DETECTION_LIMIT: usize = 500;
+
+/// Stored detections of one heatmap hour, shown by `DetectionPanel`
+/// in place of the live list
+#[derive(Debug, Clone)]
+pub struct DetectionHour {
+    pub start: chrono::DateTime<chrono::Utc>,
+    pub end: chrono::DateTime<chrono::Utc>,
+    /// Newest first, at most `HOUR_DETECTION_LIMIT`
+    pub detections: Vec<Detection>,
+}
+
+/// What a click on the heatmap asks of the detection panel
+#[derive(Debug, Clone)]
+pub enum HeatmapAction {
+    /// Show only the detections of this hour
+    Focus(DetectionHour),
+    /// Back to the live list
+    Clear,
+}
+
+/// Day-by-hour heatmap of stored detections, for spotting phenomena that
+/// recur at the same time of day
+pub struct DetectionHeatmapPanel {
+    days: usize,
+    // Shade by peak severity instead of count
+    by_severity: bool,
+    activity: Option<HourlyActivity>,
+    error: Option<String>,
+    selected: Option<(usize, usize)>,
+    // Counts being aggregated on a worker thread
+    pending: Option<std::sync::mpsc::Receiver<Result<HourlyActivity, String>>>,
+    // Detections of the clicked hour being loaded on a worker thread
+    pending_hour: Option<std::sync::mpsc::Receiver<Result<DetectionHour, String>>>,
+}
+
+impl DetectionHeatmapPanel {
+    pub fn new() -> Self {
+        Self {
+            days: 7,
+            by_severity: false,
+            activity: None,
+            error: None,
+            selected: None,
+            pending: None,
+            pending_hour: None,
+        }
+    }
+    
+    /// Count the detections of the last `days` local days on a worker thread
+    fn load(&mut self, db: &Arc<Database>) {
+        use chrono::Local;
+        
+        let today = Local::now().date_naive();
+        let start = today - chrono::Duration::days(self.days as i64 - 1);
+        let from = start.and_hms_opt(0, 0, 0)
+            .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+            .map(|t| t.with_timezone(&chrono::Utc))
+            .unwrap_or_else(|| chrono::Utc::now() - chrono::Duration::days(self.days as i64));
+        
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (db, days) = (db.clone(), self.days);
+        std::thread::spawn(move || {
+            let activity = db.count_detections_by_hour(from, chrono::Utc::now())
+                .map(|counts| HourlyActivity::from_counts(&counts, start, days, &Local))
+                .map_err(|e| e.to_string());
+            let _ = tx.send(activity);
+        });
+        self.pending = Some(rx);
+        self.pending_hour = None;
+        self.selected = None;
+    }
+    
+    /// Load the stored detections of the local hour at `cell` on a worker thread
+    fn load_hour(&mut self, db: &Arc<Database>, cell: (usize, usize)) {
+        let Some((start, end)) = self.activity.as_ref().and_then(|a| a.hour_range(cell, &chrono::Local)) else { return };
+        
+        let (tx, rx) = std::sync::mpsc::channel();
+        let db = db.clone();
+        std::thread::spawn(move || {
+            let hour = db.query_detections(start, end, None, Some(HOUR_DETECTION_LIMIT))
+                .map(|stored| {
+                    let detections = stored.iter()
+                        .filter_map(|d| d.to_detection()
+                            .map_err(|e| tracing::warn!("Skipping undecodable detection {}: {}", d.id, e))
+                            .ok())
+                        .collect();
+                    DetectionHour { start, end, detections }
+                })
+                .map_err(|e| e.to_string());
+            let _ = tx.send(hour);
+        });
+        self.pending_hour = Some(rx);
+    }
+    
+    /// Pick up whatever the worker threads finished
+    fn collect(&mut self) -> Option<HeatmapAction> {
+        if let Some(result) = self.pending.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.pending = None;
+            match result {
+                Ok(activity) => {
+                    self.activity = Some(activity);
+                    self.error = None;
+                }
+                Err(e) => {
+                    self.activity = None;
+                    self.error = Some(e);
+                }
+            }
+        }
+        
+        let result = self.pending_hour.as_ref().and_then(|rx| rx.try_recv().ok())?;
+        self.pending_hour = None;
+        match result {
+            Ok(hour) => Some(HeatmapAction::Focus(hour)),
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+    
+    /// Show the heatmap; a click on an hour asks the detection panel to
+    /// show that hour's detections once they're loaded
+    pub fn show(&mut self, ui: &mut egui::Ui, database: Option<&Arc<Database>>, config: &GuiConfig) -> Option<HeatmapAction> {
+        let Some(db) = database else {
+            ui.label("Database is disabled; no stored detections to chart");
+            return None;
+        };
+        let mut action = self.collect();
+        if self.pending.is_some() || self.pending_hour.is_some() {
+            ui.ctx().request_repaint();
+        }
+        
+        let days_before = self.days;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("heatmap_days")
+                .selected_text(format!("Last {} days", self.days))
+                .show_ui(ui, |ui| {
+                    for days in [7, 14, 30, 90] {
+                        ui.selectable_value(&mut self.days, days, format!("Last {} days", days));
+                    }
+                });
+            ui.selectable_value(&mut self.by_severity, false, "Count");
+            ui.selectable_value(&mut self.by_severity, true, "Severity");
+            let unloaded = self.activity.is_none() && self.error.is_none() && self.pending.is_none();
+            if ui.button("⟳ Refresh").clicked() || unloaded || self.days != days_before {
+                self.load(db);
+            }
+            if self.pending.is_some() || self.pending_hour.is_some() {
+                ui.spinner();
+            }
+        });
+        
+        if let Some(ref error) = self.error {
+            ui.colored_label(egui::Color32::RED, format!("Query failed: {}", error));
+            return action;
+        }
+        let Some(ref activity) = self.activity else { return action };
+        ui.small(format!("{} detections, busiest hour {}", activity.total(), activity.max_count()));
+        
+        // Grid: one row per day, one column per hour
+        const LABEL_WIDTH: f32 = 48.0;
+        const HEADER_HEIGHT: f32 = 14.0;
+        let cell_w = ((ui.available_width() - LABEL_WIDTH) / 24.0).clamp(6.0, 24.0);
+        let cell_h = (240.0 / activity.days() as f32).clamp(3.0, 18.0);
+        let size = egui::vec2(LABEL_WIDTH + 24.0 * cell_w, HEADER_HEIGHT + activity.days() as f32 * cell_h);
+        let (response, painter) = ui.allocate_painter(size, egui::Sense::click());
+        let grid = egui::Rect::from_min_size(
+            response.rect.min + egui::vec2(LABEL_WIDTH, HEADER_HEIGHT),
+            egui::vec2(24.0 * cell_w, activity.days() as f32 * cell_h),
+        );
+        
+        let text_color = ui.visuals().text_color();
+        let max_count = activity.max_count().max(1) as f32;
+        for day in 0..activity.days() {
+            for hour in 0..24 {
+                let count = activity.counts[day][hour];
+                let color = if count == 0 {
+                    ui.visuals().extreme_bg_color
+                } else if self.by_severity {
+                    let level = activity.peak_severity[day][hour].map_or(0, |s| s as usize);
+                    config.spectrogram_colormap.to_color((level + 1) as f32 / 4.0)
+                } else {
+                    config.spectrogram_colormap.to_color(count as f32 / max_count)
+                };
+                let cell = egui::Rect::from_min_size(
+                    grid.min + egui::vec2(hour as f32 * cell_w, day as f32 * cell_h),
+                    egui::vec2(cell_w, cell_h),
+                );
+                painter.rect_filled(cell.shrink(0.5), 0.0, color);
+                if self.selected == Some((day, hour)) {
+                    painter.rect_stroke(cell, 0.0, (1.5, text_color));
+                }
+            }
+            
+            // Label every day when they fit, else every week
+            if cell_h >= 10.0 || day % 7 == 0 {
+                let date = activity.start + chrono::Duration::days(day as i64);
+                painter.text(
+                    egui::pos2(grid.left() - 4.0, grid.top() + (day as f32 + 0.5) * cell_h),
+                    egui::Align2::RIGHT_CENTER,
+                    date.format("%m-%d").to_string(),
+                    egui::FontId::proportional(10.0),
+                    text_color,
+                );
+            }
+        }
+        for hour in (0..24).step_by(6) {
+            painter.text(
+                egui::pos2(grid.left() + hour as f32 * cell_w, response.rect.top()),
+                egui::Align2::LEFT_TOP,
+                format!("{:02}h", hour),
+                egui::FontId::proportional(10.0),
+                text_color,
+            );
+        }
+        
+        let cell_at = |pos: egui::Pos2| {
+            grid.contains(pos).then(|| {
+                let day = (((pos.y - grid.top()) / cell_h) as usize).min(activity.days() - 1);
+                let hour = (((pos.x - grid.left()) / cell_w) as usize).min(23);
+                (day, hour)
+            })
+        };
+        if let Some((day, hour)) = response.hover_pos().and_then(cell_at) {
+            let date = activity.start + chrono::Duration::days(day as i64);
+            egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("heatmap_tooltip"), |ui| {
+                ui.label(format!("{} {:02}:00", date, hour));
+                ui.label(format!("{} detections", activity.counts[day][hour]));
+                if let Some(severity) = activity.peak_severity[day][hour] {
+                    ui.label(format!("Peak severity: {:?}", severity));
+                }
+            });
+        }
+        let clicked = response.clicked().then(|| response.interact_pointer_pos().and_then(cell_at));
+        ui.small("Click an hour to list its detections in the Detections panel; click it again to go back");
+        
+        ui.separator();
+        ui.label("By type");
+        for (detection_type, count) in &activity.by_type {
+            ui.horizontal(|ui| {
+                ui.small(format!("{:?}", detection_type));
+                ui.add(egui::ProgressBar::new(*count as f32 / activity.total().max(1) as f32).text(count.to_string()));
+            });
+        }
+        
+        if let Some(clicked) = clicked {
+            self.selected = if clicked == self.selected { None } else { clicked };
+            match self.selected {
+                Some(cell) => self.load_hour(db, cell),
+                None => {
+                    self.pending_hour = None;
+                    action = Some(HeatmapAction::Clear);
+                }
+            }
+        }
+        action
+    }
+}
+
+/// Strength below which a detection drops out of the live alerts view,
+/// a little over three half-lives
+const LIVE_ALERT_MIN_ALPHA: f32 = 0.1;
+
+/// Strength (1 down toward 0) to draw a detection of `age` at, halving
+/// every `half_life`. Detections from the future and a non-positive
+/// half-life don't fade.
+pub fn decay_alpha(age: chrono::Duration, half_life: chrono::Duration) -> f32 {
+    if half_life <= chrono::Duration::zero() || age <= chrono::Duration::zero() {
+        return 1.0;
+    }
+    let half_lives = age.num_milliseconds() as f64 / half_life.num_milliseconds() as f64;
+    0.5f64.powf(half_lives) as f32
+}
+
 /// Statistics panel
 pub struct StatsPanel;
 
@@ -357,6 +1268,32 @@ impl StatsPanel {
         ui.label(format!("Readings/sec: {:.0}", state.stats.readings_per_sec));
         ui.label(format!("Total Detections: {}", state.stats.detections_total));
         
+        if !state.stats.publish_stats.is_empty() {
+            let dropped: u64 = state.stats.publish_stats.iter().map(|s| s.dropped).sum();
+            let header = format!("Published readings ({} dropped)", dropped);
+            egui::CollapsingHeader::new(header).id_source("publish_stats").show(ui, |ui| {
+                egui::Grid::new("publish_stats_grid").num_columns(3).striped(true).show(ui, |ui| {
+                    ui.strong("Sensor");
+                    ui.strong("Emitted");
+                    ui.strong("Dropped");
+                    ui.end_row();
+                    
+                    for stats in &state.stats.publish_stats {
+                        ui.label(&stats.sensor_id);
+                        ui.label(stats.emitted.to_string());
+                        let total = stats.emitted + stats.dropped;
+                        let text = format!("{} ({:.1}%)", stats.dropped, 100.0 * stats.dropped as f64 / total.max(1) as f64);
+                        if stats.dropped > 0 {
+                            ui.colored_label(GlowBarnColors::WARNING, text);
+                        } else {
+                            ui.label(text);
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        }
+        
         ui.separator();
         
         ui.label(format!("CPU: {:.1}%", state.stats.cpu_usage));
@@ -366,6 +1303,24 @@ impl StatsPanel {
         ui.add(egui::ProgressBar::new(cpu_ratio).show_percentage());
         
         ui.label(format!("Memory: {:.0} MB", state.stats.memory_mb));
+        ui.label(format!("Threads: {}", state.stats.thread_count));
+        
+        ui.separator();
+        
+        ui.label(format!("FPS: {:.0}", state.stats.fps));
+        let frame_time = if state.stats.frame_budget_ms > 0.0 {
+            format!("Frame time: {:.1} ms (budget {:.0} ms)", state.stats.frame_time_ms, state.stats.frame_budget_ms)
+        } else {
+            format!("Frame time: {:.1} ms", state.stats.frame_time_ms)
+        };
+        if state.stats.frame_budget_ms > 0.0 && state.stats.frame_time_ms > state.stats.frame_budget_ms {
+            ui.colored_label(GlowBarnColors::WARNING, frame_time);
+        } else {
+            ui.label(frame_time);
+        }
+        if state.stats.quality != Quality::Full {
+            ui.colored_label(GlowBarnColors::WARNING, format!("Quality: {:?}", state.stats.quality));
+        }
         
         ui.separator();
         
@@ -376,54 +1331,6 @@ impl StatsPanel {
     }
 }
 
-/// Colormap enum
-#[derive(Clone, Copy)]
-pub enum Colormap {
-    Inferno,
-    Viridis,
-    Plasma,
-    Turbo,
-    Grayscale,
-}
-
-impl Colormap {
-    pub fn to_color(&self, t: f32) -> egui::Color32 {
-        let t = t.clamp(0.0, 1.0);
-        
-        match self {
-            Colormap::Inferno => {
-                // Inferno colormap approximation
-                let r = (255.0 * (-4.545 * t.powi(3) + 5.014 * t.powi(2) + 0.491 * t).clamp(0.0, 1.0)) as u8;
-                let g = (255.0 * (2.068 * t.powi(3) - 2.861 * t.powi(2) + 1.338 * t).clamp(0.0, 1.0)) as u8;
-                let b = (255.0 * (-2.213 * t.powi(3) + 3.009 * t.powi(2) + 0.1 * t + 0.163).clamp(0.0, 1.0)) as u8;
-                egui::Color32::from_rgb(r, g, b)
-            }
-            Colormap::Viridis => {
-                let r = (255.0 * (0.267 + 0.329 * t.powi(2)).clamp(0.0, 1.0)) as u8;
-                let g = (255.0 * (0.004 + 0.873 * t - 0.378 * t.powi(2)).clamp(0.0, 1.0)) as u8;
-                let b = (255.0 * (0.329 + 0.311 * t - 0.640 * t.powi(2)).clamp(0.0, 1.0)) as u8;
-                egui::Color32::from_rgb(r, g, b)
-            }
-            Colormap::Turbo => {
-                let r = (255.0 * (0.18995 + 2.31 * t - 1.5 * t.powi(2)).clamp(0.0, 1.0)) as u8;
-                let g = (255.0 * (0.07176 + 2.89 * t - 2.0 * t.powi(2)).clamp(0.0, 1.0)) as u8;
-                let b = (255.0 * (0.23217 + 1.26 * t - 1.5 * t.powi(2)).clamp(0.0, 1.0)) as u8;
-                egui::Color32::from_rgb(r, g, b)
-            }
-            Colormap::Plasma => {
-                let r = (255.0 * (0.05 + 0.91 * t).clamp(0.0, 1.0)) as u8;
-                let g = (255.0 * (0.02 + 0.53 * t - 0.55 * t.powi(2)).clamp(0.0, 1.0)) as u8;
-                let b = (255.0 * (0.53 - 0.03 * t - 0.5 * t.powi(2)).clamp(0.0, 1.0)) as u8;
-                egui::Color32::from_rgb(r, g, b)
-            }
-            Colormap::Grayscale => {
-                let v = (255.0 * t) as u8;
-                egui::Color32::from_rgb(v, v, v)
-            }
-        }
-    }
-}
-
 fn get_sensor_color(sensor_id: &str) -> egui::Color32 {
     match sensor_id {
         s if s.contains("EMF") => egui::Color32::from_rgb(100, 200, 255),
@@ -433,3 +1340,88 @@ fn get_sensor_color(sensor_id: &str) -> egui::Color32 {
         _ => egui::Color32::from_rgb(200, 200, 200),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    
+    #[test]
+    fn test_detections_fade_by_half_life() {
+        let half_life = Duration::seconds(60);
+        assert_eq!(decay_alpha(Duration::zero(), half_life), 1.0);
+        assert!((decay_alpha(Duration::seconds(60), half_life) - 0.5).abs() < 1e-6);
+        assert!((decay_alpha(Duration::seconds(90), half_life) - 0.5f32.powf(1.5)).abs() < 1e-6);
+        assert!((decay_alpha(Duration::seconds(180), half_life) - 0.125).abs() < 1e-6);
+        // Gone from the live view after a little over three half-lives
+        assert!(decay_alpha(Duration::seconds(199), half_life) >= LIVE_ALERT_MIN_ALPHA);
+        assert!(decay_alpha(Duration::seconds(200), half_life) < LIVE_ALERT_MIN_ALPHA);
+        
+        assert_eq!(decay_alpha(Duration::seconds(-5), half_life), 1.0);
+        assert_eq!(decay_alpha(Duration::hours(1), Duration::zero()), 1.0);
+    }
+    
+    #[test]
+    fn test_detections_bin_into_local_hours() {
+        use crate::detection::SensorContribution;
+        use chrono::{FixedOffset, NaiveDate, TimeZone, Utc};
+        
+        let detection = |timestamp: chrono::DateTime<Utc>, detection_type, severity| Detection {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp,
+            detection_type,
+            confidence: 0.8,
+            uncertainty: 0.0,
+            severity,
+            sensors: Vec::<SensorContribution>::new(),
+            entropy_deviation: 0.0,
+            anomaly_count: 0,
+            correlation_score: 0.0,
+            classification: None,
+            location: None,
+            payload: None,
+            data_window_start: timestamp,
+            data_window_end: timestamp,
+        };
+        let at = |d, h, m| Utc.with_ymd_and_hms(2026, 3, d, h, m, 0).unwrap();
+        let detections = vec![
+            detection(at(2, 3, 10), DetectionType::EMFSpike, Severity::Low),
+            detection(at(2, 3, 55), DetectionType::EMFSpike, Severity::High),
+            detection(at(3, 3, 20), DetectionType::ColdSpot, Severity::Medium),
+            detection(at(3, 23, 30), DetectionType::EMFSpike, Severity::Low),
+            // Before and after the range
+            detection(at(1, 12, 0), DetectionType::ColdSpot, Severity::Critical),
+            detection(at(9, 12, 0), DetectionType::ColdSpot, Severity::Critical),
+        ];
+        let start = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        
+        let activity = HourlyActivity::bin(&detections, start, 3, &Utc);
+        assert_eq!(activity.total(), 4);
+        assert_eq!(activity.counts[0][3], 2);
+        assert_eq!(activity.peak_severity[0][3], Some(Severity::High));
+        assert_eq!(activity.counts[1][3], 1);
+        assert_eq!(activity.counts[1][23], 1);
+        assert_eq!(activity.max_count(), 2);
+        assert_eq!(activity.by_type, vec![(DetectionType::EMFSpike, 3), (DetectionType::ColdSpot, 1)]);
+        
+        // Two hours ahead of UTC, the late one moves to the next day's 01:00
+        let plus_two = FixedOffset::east_opt(2 * 3600).unwrap();
+        let shifted = HourlyActivity::bin(&detections, start, 3, &plus_two);
+        assert_eq!(shifted.counts[0][5], 2);
+        assert_eq!(shifted.counts[2][1], 1);
+        assert_eq!(shifted.cell_of(at(3, 23, 30), &plus_two), Some((2, 1)));
+    }
+    
+    #[test]
+    fn test_thermal_grid_coarsens_by_block() {
+        // 3x3 grid into 2x2 blocks: edge blocks average the cells they cover
+        let data = [1.0, 3.0, 5.0,
+                    3.0, 5.0, 7.0,
+                    9.0, 9.0, 2.0];
+        let (cells, size) = coarsen_grid(&data, 3, 3, 2);
+        assert_eq!(size, [2, 2]);
+        assert_eq!(cells, vec![3.0, 6.0, 9.0, 2.0]);
+        
+        assert_eq!(coarsen_grid(&data, 3, 3, 1), (data.to_vec(), [3, 3]));
+    }
+}
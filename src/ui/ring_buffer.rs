@@ -0,0 +1,137 @@
+// Copyright (c) 2026 bad-antics
+// Licensed under the MIT License. See LICENSE file in the project root.
+// https://github.com/bad-antics/glowbarn-rs
+
+//! Fixed-capacity sample history for waveform plots
+//!
+//! Every sample is stored twice, at its slot and at slot + capacity, so the
+//! newest `capacity` samples are always one contiguous slice. Pushing is
+//! O(1) and never allocates, and plots and statistics can keep borrowing
+//! `&[f64]`.
+
+/// Ring buffer keeping the most recent `capacity` values
+#[derive(Debug, Clone)]
+pub struct RingBuffer<T> {
+    /// Two copies of the ring, `2 * capacity` long
+    buf: Vec<T>,
+    capacity: usize,
+    /// Slot of the oldest value
+    start: usize,
+    len: usize,
+}
+
+impl<T: Copy + Default> RingBuffer<T> {
+    /// Empty buffer holding at most `capacity` (at least 1) values
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            buf: vec![T::default(); 2 * capacity],
+            capacity,
+            start: 0,
+            len: 0,
+        }
+    }
+    
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    
+    /// Append a value, dropping the oldest once full
+    pub fn push(&mut self, value: T) {
+        let slot = (self.start + self.len) % self.capacity;
+        self.buf[slot] = value;
+        self.buf[slot + self.capacity] = value;
+        
+        if self.len < self.capacity {
+            self.len += 1;
+        } else {
+            self.start = (self.start + 1) % self.capacity;
+        }
+    }
+    
+    /// Append values in order; only the last `capacity` can survive
+    pub fn extend_from_slice(&mut self, values: &[T]) {
+        let skip = values.len().saturating_sub(self.capacity);
+        for &value in &values[skip..] {
+            self.push(value);
+        }
+    }
+    
+    /// Values oldest first
+    pub fn as_slice(&self) -> &[T] {
+        &self.buf[self.start..self.start + self.len]
+    }
+    
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+    
+    /// Most recent value
+    pub fn last(&self) -> Option<&T> {
+        self.as_slice().last()
+    }
+    
+    /// Change the capacity, keeping the most recent values that fit
+    pub fn set_capacity(&mut self, capacity: usize) {
+        if capacity.max(1) == self.capacity {
+            return;
+        }
+        let mut resized = Self::new(capacity);
+        resized.extend_from_slice(self.as_slice());
+        *self = resized;
+    }
+    
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+    }
+}
+
+impl<'a, T: Copy + Default> IntoIterator for &'a RingBuffer<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+    
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_ring_buffer_caps_and_keeps_order() {
+        let mut ring = RingBuffer::new(5);
+        for i in 0..3 {
+            ring.push(i as f64);
+        }
+        assert_eq!(ring.as_slice(), &[0.0, 1.0, 2.0]);
+        
+        for i in 3..23 {
+            ring.push(i as f64);
+            assert!(ring.len() <= ring.capacity());
+        }
+        assert_eq!(ring.len(), 5);
+        assert_eq!(ring.as_slice(), &[18.0, 19.0, 20.0, 21.0, 22.0]);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), vec![18.0, 19.0, 20.0, 21.0, 22.0]);
+        assert_eq!(ring.last(), Some(&22.0));
+        
+        ring.extend_from_slice(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+        assert_eq!(ring.as_slice(), &[3.0, 4.0, 5.0, 6.0, 7.0]);
+        
+        ring.set_capacity(3);
+        assert_eq!(ring.as_slice(), &[5.0, 6.0, 7.0]);
+        ring.set_capacity(6);
+        ring.push(8.0);
+        assert_eq!(ring.as_slice(), &[5.0, 6.0, 7.0, 8.0]);
+    }
+}
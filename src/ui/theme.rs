@@ -8,15 +8,85 @@ use eframe::egui;
 use crate::config::Theme;
 
 /// Apply theme to egui context
-pub fn apply_theme(ctx: &egui::Context, theme: Theme) {
-    match theme {
-        Theme::Dark => apply_dark_theme(ctx),
+///
+/// `system` is the OS preference last reported by a `SystemThemeWatcher`,
+/// used for `Theme::System`.
+pub fn apply_theme(ctx: &egui::Context, theme: Theme, system: Option<dark_light::Mode>) {
+    match resolve_theme(ctx, theme, system) {
         Theme::Light => apply_light_theme(ctx),
-        Theme::System => {
-            // Could detect system theme here
-            apply_dark_theme(ctx);
+        _ => apply_dark_theme(ctx),
+    }
+}
+
+/// Dark or Light for `theme`, following the OS preference `system` when it
+/// is `System`
+///
+/// When the OS preference isn't known yet or there is none, the context's
+/// current visuals win, so `System` never flips the theme on its own in
+/// that case.
+pub fn resolve_theme(ctx: &egui::Context, theme: Theme, system: Option<dark_light::Mode>) -> Theme {
+    match (theme, system) {
+        (Theme::System, Some(dark_light::Mode::Dark)) => Theme::Dark,
+        (Theme::System, Some(dark_light::Mode::Light)) => Theme::Light,
+        (Theme::System, _) => {
+            if ctx.style().visuals.dark_mode { Theme::Dark } else { Theme::Light }
+        }
+        (theme, _) => theme,
+    }
+}
+
+/// Asks the OS for its dark/light preference on a thread of its own, so
+/// the GUI never waits on `dark_light::detect`
+pub struct SystemThemeWatcher {
+    changes: std::sync::mpsc::Receiver<dark_light::Mode>,
+}
+
+impl SystemThemeWatcher {
+    /// Check every `poll`, repainting `ctx` when the preference changes.
+    /// The thread ends with the first change after the watcher is dropped.
+    pub fn spawn(ctx: egui::Context, poll: std::time::Duration) -> Self {
+        let (tx, changes) = std::sync::mpsc::channel();
+        let spawned = std::thread::Builder::new()
+            .name("system-theme".to_string())
+            .spawn(move || {
+                let mut last = None;
+                loop {
+                    let mode = dark_light::detect();
+                    if last != Some(mode) {
+                        if tx.send(mode).is_err() {
+                            return;
+                        }
+                        ctx.request_repaint();
+                        last = Some(mode);
+                    }
+                    std::thread::sleep(poll);
+                }
+            });
+        if let Err(e) = spawned {
+            tracing::warn!("Not following the system theme: {}", e);
         }
+        Self { changes }
+    }
+    
+    /// The latest preference, if it changed since the last call
+    pub fn changed(&self) -> Option<dark_light::Mode> {
+        self.changes.try_iter().last()
+    }
+}
+
+/// Re-apply the `System` theme if the OS preference `system` differs from
+/// the current visuals; returns whether the visuals changed
+pub fn follow_system_theme(ctx: &egui::Context, system: dark_light::Mode) -> bool {
+    let dark = resolve_theme(ctx, Theme::System, Some(system)) == Theme::Dark;
+    if dark == ctx.style().visuals.dark_mode {
+        return false;
     }
+    if dark {
+        apply_dark_theme(ctx);
+    } else {
+        apply_light_theme(ctx);
+    }
+    true
 }
 
 fn apply_dark_theme(ctx: &egui::Context) {
@@ -91,6 +161,39 @@ fn apply_light_theme(ctx: &egui::Context) {
     ctx.set_style(style);
 }
 
+/// Scale all text styles so body text is `size` points
+pub fn apply_font_size(ctx: &egui::Context, size: f32) {
+    use egui::{FontFamily, FontId, TextStyle};
+    
+    let mut style = (*ctx.style()).clone();
+    style.text_styles = [
+        (TextStyle::Small, FontId::new(size * 0.75, FontFamily::Proportional)),
+        (TextStyle::Body, FontId::new(size, FontFamily::Proportional)),
+        (TextStyle::Button, FontId::new(size, FontFamily::Proportional)),
+        (TextStyle::Monospace, FontId::new(size, FontFamily::Monospace)),
+        (TextStyle::Heading, FontId::new(size * 1.4, FontFamily::Proportional)),
+    ].into();
+    ctx.set_style(style);
+}
+
+/// Parse a shortcut such as `Ctrl+Shift+P`; `None` if empty or not understood
+pub fn parse_shortcut(spec: &str) -> Option<egui::KeyboardShortcut> {
+    let mut parts: Vec<&str> = spec.split('+').map(str::trim).collect();
+    let key = egui::Key::from_name(parts.pop()?)?;
+    
+    let mut modifiers = egui::Modifiers::NONE;
+    for part in parts {
+        match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "cmd" | "command" => modifiers.command = true,
+            "shift" => modifiers.shift = true,
+            "alt" | "option" => modifiers.alt = true,
+            _ => return None,
+        }
+    }
+    
+    Some(egui::KeyboardShortcut::new(modifiers, key))
+}
+
 /// GlowBarn specific color palette
 pub struct GlowBarnColors;
 
@@ -148,3 +251,40 @@ pub fn severity_color(severity: &crate::detection::Severity) -> egui::Color32 {
         crate::detection::Severity::Critical => GlowBarnColors::SEVERITY_CRITICAL,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_shortcut() {
+        let palette = parse_shortcut("Ctrl+P").unwrap();
+        assert!(palette.modifiers.command);
+        assert_eq!(palette.logical_key, egui::Key::P);
+        
+        assert_eq!(parse_shortcut("Space").unwrap().modifiers, egui::Modifiers::NONE);
+        assert!(parse_shortcut("Hyper+P").is_none());
+        assert!(parse_shortcut("").is_none());
+    }
+    
+    #[test]
+    fn test_dark_and_light_visuals_differ() {
+        let ctx = egui::Context::default();
+        
+        apply_theme(&ctx, Theme::Dark, None);
+        let dark = ctx.style().visuals.clone();
+        apply_theme(&ctx, Theme::Light, None);
+        let light = ctx.style().visuals.clone();
+        
+        assert!(dark.dark_mode);
+        assert!(!light.dark_mode);
+        assert_ne!(dark.panel_fill, light.panel_fill);
+        assert_ne!(dark.window_fill, light.window_fill);
+        assert_ne!(dark.widgets.inactive.bg_fill, light.widgets.inactive.bg_fill);
+        
+        // System follows the OS, and keeps the current visuals without it
+        assert_eq!(resolve_theme(&ctx, Theme::System, Some(dark_light::Mode::Dark)), Theme::Dark);
+        assert_eq!(resolve_theme(&ctx, Theme::System, None), Theme::Light);
+        assert_eq!(resolve_theme(&ctx, Theme::Dark, Some(dark_light::Mode::Light)), Theme::Dark);
+    }
+}